@@ -0,0 +1,91 @@
+//! 解析、行处理和 DOCX 生成的基准测试
+//!
+//! 以 `tests/test_data/黑色爱情诗.fountain` 为小剧本，重复 10 次、100 次作为中、大剧本。
+//! 改动解析循环或排版前先保存基线，改动后与基线比较，criterion 会标出变慢的项目：
+//!
+//! ```text
+//! cargo bench --bench export -- --save-baseline before
+//! cargo bench --bench export -- --baseline before
+//! ```
+
+use betterfountain_rust::docx::generate_docx_bytes;
+use betterfountain_rust::docx::line_processor::process_document_lines;
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::FountainParser;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+const FIXTURE: &str = include_str!("../tests/test_data/黑色爱情诗.fountain");
+
+// 小、中、大三种规模的剧本；标题页只保留一份。大剧本单次解析就要数秒，各组只取 10 个样本
+fn fixtures() -> Vec<(&'static str, String)> {
+    let body = FIXTURE.split_once("\n\n").map_or(FIXTURE, |(_, body)| body);
+    let repeat = |times: usize| {
+        let mut script = FIXTURE.to_string();
+        for _ in 1..times {
+            script.push_str("\n\n");
+            script.push_str(body);
+        }
+        script
+    };
+    vec![
+        ("small", repeat(1)),
+        ("medium", repeat(10)),
+        ("huge", repeat(100)),
+    ]
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let config = Conf::default();
+    let parser = FountainParser::new();
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    for (name, script) in fixtures() {
+        group.throughput(Throughput::Bytes(script.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &script, |b, script| {
+            b.iter(|| parser.parse(script, &config, false, None))
+        });
+    }
+    group.finish();
+}
+
+fn bench_lines(c: &mut Criterion) {
+    let config = Conf::default();
+    let parser = FountainParser::new();
+    let mut group = c.benchmark_group("lines");
+    group.sample_size(10);
+    for (name, script) in fixtures() {
+        let parsed = parser.parse(&script, &config, false, None);
+        group.throughput(Throughput::Bytes(script.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_batched(
+                || parsed.clone(),
+                |mut parsed| process_document_lines(&mut parsed, &config),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_docx(c: &mut Criterion) {
+    let config = Conf::default();
+    let parser = FountainParser::new();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("docx");
+    group.sample_size(10);
+    for (name, script) in fixtures() {
+        let parsed = parser.parse(&script, &config, false, None);
+        group.throughput(Throughput::Bytes(script.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(generate_docx_bytes(&config, &parsed))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_lines, bench_docx);
+criterion_main!(benches);
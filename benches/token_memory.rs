@@ -0,0 +1,86 @@
+//! 比较 `ParseOutput` 与 `BorrowedOutput` 解析过程中的峰值和解析后常驻的堆内存
+//!
+//! `parse_borrowed` 逐场景转换 token，两项都应低于 `parse`。
+//!
+//! 运行：`cargo bench --bench token_memory`
+
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::FountainParser;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 统计当前和峰值堆内存的分配器
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// 重复若干次的场景，组成大剧本
+fn script(scenes: usize) -> String {
+    let mut script = String::from("Title: 内存测试\nAuthor: 测试\n\n");
+    for i in 0..scenes {
+        script.push_str(&format!(
+            "INT. 客厅 - 日 #{}#\n\n阿强走进来，把外套挂在门后，环顾四周。\n\n@阿强\n（低声）\n今天怎么这么安静？\n\n@阿珍\n大家都出去了。\n\n> CUT TO:\n\n",
+            i + 1
+        ));
+    }
+    script
+}
+
+// 执行 f 并返回 (结果常驻的字节数, 执行期间的峰值字节数)
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize, usize) {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let value = f();
+    let retained = CURRENT.load(Ordering::Relaxed) - base;
+    let peak = PEAK.load(Ordering::Relaxed) - base;
+    (value, retained, peak)
+}
+
+fn main() {
+    let config = Conf::default();
+    let parser = FountainParser::new();
+    println!(
+        "{:>8} {:>10} {:>16} {:>16} {:>16} {:>16}",
+        "场景数", "原文", "ParseOutput常驻", "ParseOutput峰值", "Borrowed常驻", "Borrowed峰值"
+    );
+    for scenes in [100, 1_000, 5_000] {
+        let script = script(scenes);
+        let (owned, owned_retained, owned_peak) =
+            measure(|| parser.parse(&script, &config, false, Some(false)));
+        drop(owned);
+        let (borrowed, borrowed_retained, borrowed_peak) =
+            measure(|| parser.parse_borrowed(&script, &config));
+        println!(
+            "{:>8} {:>10} {:>16} {:>16} {:>16} {:>16}",
+            scenes,
+            script.len(),
+            owned_retained,
+            owned_peak,
+            borrowed_retained,
+            borrowed_peak
+        );
+        assert!(borrowed_retained < owned_retained);
+        assert!(borrowed_peak < owned_peak);
+        drop(borrowed);
+    }
+}
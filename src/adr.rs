@@ -0,0 +1,109 @@
+//! ADR/配音提示表（cue sheet）
+//!
+//! 每段对白一条提示：编号（即角色行的 take_number，编号方式见 `Conf::dialogue_numbering`）、
+//! 角色、场景编号、按预估时长推算的入点/出点时间码和对白文本，可导出为 CSV。
+//! 开启 `Conf::print_dialogue_numbers` 后，DOCX 导出会把同样的编号打印在左侧页边，便于对照。
+
+use crate::parser::ParseOutput;
+use crate::stats::csv_field;
+use crate::utils::strip_style_chars;
+use serde::{Deserialize, Serialize};
+
+/// 提示表中的一条对白
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdrCue {
+    /// 对白编号
+    pub take: i32,
+    pub character: String,
+    /// 所在场景的编号，第一个场景之前的对白为 None
+    pub scene_number: Option<String>,
+    /// 角色名所在行（从 0 开始）
+    pub line: usize,
+    /// 入点（秒）
+    pub start_sec: f64,
+    /// 出点（秒）
+    pub end_sec: f64,
+    /// 对白文本，不含括号注释，多行以空格连接
+    pub text: String,
+}
+
+/// 按顺序列出所有对白的提示
+pub fn adr_cues(output: &ParseOutput) -> Vec<AdrCue> {
+    let mut cues: Vec<AdrCue> = Vec::new();
+    let mut scene_number: Option<String> = None;
+    // 正在收集对白的提示
+    let mut open = false;
+    for token in &output.tokens {
+        match token.token_type.as_str() {
+            "scene_heading" => {
+                scene_number = token
+                    .number
+                    .as_deref()
+                    .map(|number| number.trim_start_matches('↑').to_string());
+                open = false;
+            }
+            "character" => {
+                open = false;
+                if let (Some(take), Some(character)) = (token.take_number, &token.character) {
+                    cues.push(AdrCue {
+                        take,
+                        character: character.clone(),
+                        scene_number: scene_number.clone(),
+                        line: token.line,
+                        start_sec: token.play_time_sec,
+                        end_sec: token.play_time_sec,
+                        text: String::new(),
+                    });
+                    open = true;
+                }
+            }
+            "parenthetical" => {}
+            "dialogue" if open => {
+                let cue = cues.last_mut().unwrap();
+                let text = strip_style_chars(&token.text);
+                let text = text.trim();
+                if !text.is_empty() {
+                    if !cue.text.is_empty() {
+                        cue.text.push(' ');
+                    }
+                    cue.text.push_str(text);
+                }
+                cue.end_sec = cue.end_sec.max(token.play_time_sec);
+            }
+            _ => open = false,
+        }
+    }
+    cues
+}
+
+/// 秒数换算成时间码 `HH:MM:SS:FF`，`fps` 为每秒帧数
+pub fn timecode(sec: f64, fps: u32) -> String {
+    let fps = fps.max(1) as u64;
+    let frames = (sec.max(0.0) * fps as f64).round() as u64;
+    let (total_sec, frame) = (frames / fps, frames % fps);
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        total_sec / 3600,
+        total_sec / 60 % 60,
+        total_sec % 60,
+        frame
+    )
+}
+
+/// 把提示表输出为 CSV
+pub fn adr_cues_csv(cues: &[AdrCue], fps: u32) -> String {
+    let mut csv = String::from("take,character,scene,line,timecode_in,timecode_out,text\n");
+    for cue in cues {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            cue.take,
+            csv_field(&cue.character),
+            csv_field(cue.scene_number.as_deref().unwrap_or("")),
+            cue.line,
+            timecode(cue.start_sec, fps),
+            timecode(cue.end_sec, fps),
+            csv_field(&cue.text)
+        ));
+    }
+    csv
+}
@@ -3,10 +3,19 @@
 //! 这个模块提供了Flutter应用可以调用的API接口
 
 // 纯Rust API，不依赖flutter_rust_bridge
-use crate::models::Conf;
+use crate::models::{Conf, StructToken};
 use crate::parser::{FountainParser, ParseOutput};
-use crate::docx::{DocxOptions, PrintProfile, generate_docx_document};
+use crate::docx::{
+    generate_docx_bytes_timed, generate_docx_document_timed, DocxGenerateError, DocxOptions,
+    PrintProfile,
+};
+use crate::logging::ExportTimings;
+use crate::utils::strip_style_chars;
 use std::collections::HashMap;
+use std::time::Instant;
+use thiserror::Error;
+
+pub use crate::cache::{CacheKey, CacheStats, CachedEngine};
 
 /// 简化的配置结构，用于Flutter调用
 #[derive(Debug, Clone)]
@@ -94,12 +103,14 @@ pub struct ExportResult {
     pub success: bool,
     pub message: String,
     pub file_path: Option<String>,
+    /// 解析、排版、保存各阶段的耗时，导出失败时只记录解析的耗时
+    pub timings: ExportTimings,
 }
 
 /// 解析Fountain文本
 pub async fn parse_fountain_text(text: String, config: Option<SimpleConf>) -> String {
     let conf = config.unwrap_or_default().into();
-    let mut parser = FountainParser::new();
+    let parser = FountainParser::new();
     let result = parser.parse(&text, &conf, false, None);
 
     // 返回简单的JSON格式结果
@@ -115,20 +126,27 @@ pub async fn export_to_docx(
     let conf = config.unwrap_or_default().into();
 
     // 解析文本
-    let mut parser = FountainParser::new();
+    let parse_start = Instant::now();
+    let parser = FountainParser::new();
     let parsed = parser.parse(&text, &conf, false, None);
+    let parse = parse_start.elapsed();
 
     // 直接使用异步函数
-    match generate_docx_document(&output_path, &conf, &parsed).await {
-        Ok(_) => ExportResult {
+    match generate_docx_document_timed(&output_path, &conf, &parsed).await {
+        Ok((_, timings)) => ExportResult {
             success: true,
             message: "DOCX文档导出成功".to_string(),
             file_path: Some(output_path),
+            timings: ExportTimings { parse, ..timings },
         },
         Err(e) => ExportResult {
             success: false,
             message: format!("导出失败: {}", e),
             file_path: None,
+            timings: ExportTimings {
+                parse,
+                ..Default::default()
+            },
         },
     }
 }
@@ -141,24 +159,722 @@ pub async fn export_to_docx_base64(
     let conf = config.unwrap_or_default().into();
 
     // 解析文本
-    let mut parser = FountainParser::new();
+    let parse_start = Instant::now();
+    let parser = FountainParser::new();
     let parsed = parser.parse(&text, &conf, false, None);
+    let parse = parse_start.elapsed();
 
     // 直接使用异步函数
-    match generate_docx_document("$PREVIEW$", &conf, &parsed).await {
-        Ok(_) => ExportResult {
+    match generate_docx_document_timed("$PREVIEW$", &conf, &parsed).await {
+        Ok((_, timings)) => ExportResult {
             success: true,
             message: "DOCX Base64编码生成成功".to_string(),
             file_path: None,
+            timings: ExportTimings { parse, ..timings },
         },
         Err(e) => ExportResult {
             success: false,
             message: format!("生成失败: {}", e),
             file_path: None,
+            timings: ExportTimings {
+                parse,
+                ..Default::default()
+            },
         },
     }
 }
 
+/// 测量一次完整导出各阶段的耗时：解析、行处理、排版和打包 DOCX，不写文件。
+/// 用于验证解析和排版的性能改动，与 `benches/export.rs` 的基准测试配合使用
+pub async fn profile_export(text: &str, config: &Conf) -> Result<ExportTimings, DocxGenerateError> {
+    let parse_start = Instant::now();
+    let parser = FountainParser::new();
+    let parsed = parser.parse(text, config, false, None);
+    let parse = parse_start.elapsed();
+
+    let (_, timings) = generate_docx_bytes_timed(config, &parsed).await?;
+    Ok(ExportTimings { parse, ..timings })
+}
+
+/// 场景选择方式
+#[derive(Debug, Clone)]
+pub enum SceneSelector {
+    /// 按场景编号选择，如 "3"、"12A"
+    Numbers(Vec<String>),
+    /// 按场景顺序选择，从 1 开始，包含两端
+    Range { from: usize, to: usize },
+    /// 按地点选择，地点名称包含该文本（忽略大小写）的场景
+    Location(String),
+}
+
+/// 抽取出的单个场景
+#[derive(Debug, Clone)]
+pub struct ExtractedScene {
+    /// 场景编号
+    pub number: String,
+    /// 场景标题所在行（从 0 开始）
+    pub line: usize,
+    /// 场景的 Fountain 原文，从场景标题到下一个场景标题或章节之前
+    pub text: String,
+}
+
+/// 从剧本中抽取选中的场景，返回每个场景的 Fountain 原文，可用于按场景拆分文件或导出部分草稿
+///
+/// 场景的划分按 `config` 解析，如 `config.scene_heading_prefixes` 中的前缀也算作场景标题。
+pub fn extract_scenes(
+    script: &str,
+    selector: &SceneSelector,
+    config: &Conf,
+) -> Vec<ExtractedScene> {
+    // 解析器把 \r 和 \n 都当作换行，先统一换行符，保证行号与原文一一对应
+    let script = script.replace("\r\n", "\n");
+    let parser = FountainParser::new();
+    let parsed = parser.parse(&script, config, false, None);
+    let lines: Vec<&str> = script.split(&['\r', '\n'][..]).collect();
+
+    let scenes: Vec<(usize, String)> = parsed
+        .properties
+        .scenes
+        .iter()
+        .filter_map(|scene| {
+            let line = scene.get("line")?.as_u64()? as usize;
+            let number = scene
+                .get("number")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            Some((line, number))
+        })
+        .collect();
+
+    // 场景在下一个场景标题或章节处结束
+    let mut boundaries: Vec<usize> = scenes.iter().map(|(line, _)| *line).collect();
+    boundaries.extend(
+        parsed
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == "section")
+            .map(|t| t.line),
+    );
+    boundaries.sort_unstable();
+
+    let location_lines: Vec<usize> = match selector {
+        SceneSelector::Location(query) => {
+            let query = query.trim().to_uppercase();
+            parsed
+                .properties
+                .locations
+                .iter()
+                .filter(|(name, _)| name.to_uppercase().contains(&query))
+                .flat_map(|(_, references)| references.iter().map(|r| r.line))
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    scenes
+        .iter()
+        .enumerate()
+        .filter(|(idx, (line, number))| match selector {
+            SceneSelector::Numbers(numbers) => numbers.iter().any(|n| n.trim() == number),
+            SceneSelector::Range { from, to } => (*from..=*to).contains(&(idx + 1)),
+            SceneSelector::Location(_) => location_lines.contains(line),
+        })
+        .map(|(_, (line, number))| {
+            let end = boundaries
+                .iter()
+                .copied()
+                .find(|b| b > line)
+                .unwrap_or(lines.len())
+                .min(lines.len());
+            let mut text = lines[*line..end].join("\n").trim_end().to_string();
+            text.push('\n');
+            ExtractedScene {
+                number: number.clone(),
+                line: *line,
+                text,
+            }
+        })
+        .collect()
+}
+
+/// 搜索过滤条件解析错误
+#[derive(Error, Debug)]
+pub enum SearchFilterError {
+    #[error("未知的过滤条件 {0}")]
+    UnknownFilter(String),
+
+    #[error("无效的场景范围 {0}")]
+    InvalidSceneRange(String),
+}
+
+/// 搜索过滤条件，对应 `type:dialogue character:JANE scene:12-20 location:KITCHEN` 写法
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    /// token 类型，满足其一即可
+    pub types: Vec<String>,
+    /// 角色名（忽略大小写）
+    pub character: Option<String>,
+    /// 场景编号范围（按编号的数字部分比较，包含两端）
+    pub scenes: Option<(u32, u32)>,
+    /// 地点名称包含该文本（忽略大小写）
+    pub location: Option<String>,
+}
+
+impl SearchFilters {
+    /// 解析过滤条件，多个条件用空白分隔，`type` 可以用逗号写多个类型
+    pub fn parse(text: &str) -> Result<Self, SearchFilterError> {
+        let mut filters = SearchFilters::default();
+        for item in text.split_whitespace() {
+            let (key, value) = item
+                .split_once(':')
+                .ok_or_else(|| SearchFilterError::UnknownFilter(item.to_string()))?;
+            match key.to_lowercase().as_str() {
+                "type" => filters
+                    .types
+                    .extend(value.split(',').filter(|t| !t.is_empty()).map(|t| t.to_lowercase())),
+                "character" => filters.character = Some(value.to_string()),
+                "location" => filters.location = Some(value.to_string()),
+                "scene" => {
+                    let invalid = || SearchFilterError::InvalidSceneRange(value.to_string());
+                    let (from, to) = value.split_once('-').unwrap_or((value, value));
+                    let from = from.trim().parse().map_err(|_| invalid())?;
+                    let to = to.trim().parse().map_err(|_| invalid())?;
+                    filters.scenes = Some((from, to));
+                }
+                _ => return Err(SearchFilterError::UnknownFilter(item.to_string())),
+            }
+        }
+        Ok(filters)
+    }
+}
+
+/// 一条搜索结果
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// 在 `ParseOutput::tokens` 中的下标
+    pub token_index: usize,
+    pub token_type: String,
+    /// 所在行（从 0 开始）
+    pub line: usize,
+    /// 去掉样式符号后的文本
+    pub text: String,
+    /// 所在场景的编号，第一个场景之前为 None
+    pub scene_number: Option<String>,
+}
+
+/// 在解析结果中搜索，`query` 为空时列出所有满足过滤条件的 token，文本匹配忽略大小写
+pub fn search(output: &ParseOutput, query: &str, filters: &SearchFilters) -> Vec<SearchHit> {
+    let query = query.trim().to_lowercase();
+    let character = filters.character.as_ref().map(|c| c.trim().to_uppercase());
+    let location = filters.location.as_ref().map(|l| l.trim().to_uppercase());
+
+    // 场景标题行 -> 地点名称
+    let locations: HashMap<usize, &str> = output
+        .properties
+        .locations
+        .iter()
+        .flat_map(|(name, references)| references.iter().map(move |r| (r.line, name.as_str())))
+        .collect();
+
+    let mut hits = Vec::new();
+    let mut scene_number: Option<String> = None;
+    let mut scene_location = "";
+    for (index, token) in output.tokens.iter().enumerate() {
+        if token.token_type == "scene_heading" {
+            scene_number = token.number.as_ref().map(|n| n.trim_start_matches('↑').to_string());
+            scene_location = locations.get(&token.line).copied().unwrap_or_default();
+        }
+
+        let text = strip_style_chars(&token.text).trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        if !filters.types.is_empty() && !filters.types.contains(&token.token_type) {
+            continue;
+        }
+        if let Some(character) = &character {
+            let speaker = token.character.as_deref().map(|c| c.trim().to_uppercase());
+            if speaker.as_ref() != Some(character) {
+                continue;
+            }
+        }
+        if let Some((from, to)) = filters.scenes {
+            let number = scene_number.as_deref().and_then(|n| {
+                let digits: String = n
+                    .chars()
+                    .skip_while(|c| !c.is_ascii_digit())
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                digits.parse::<u32>().ok()
+            });
+            if !number.is_some_and(|n| (from..=to).contains(&n)) {
+                continue;
+            }
+        }
+        if let Some(location) = &location {
+            if !scene_location.to_uppercase().contains(location) {
+                continue;
+            }
+        }
+        if !query.is_empty() && !text.to_lowercase().contains(&query) {
+            continue;
+        }
+
+        hits.push(SearchHit {
+            token_index: index,
+            token_type: token.token_type.clone(),
+            line: token.line,
+            text,
+            scene_number: scene_number.clone(),
+        });
+    }
+    hits
+}
+
+/// 光标所在位置的补全类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// 标题页中尚未输入冒号的键
+    TitlePage,
+    /// 场景标题
+    SceneHeading,
+    /// 空行之后的新段落，可能是角色名或场景标题
+    Character,
+    /// 动作、对白等正文
+    Text,
+}
+
+/// 编辑器补全所需的数据，只填充与光标位置相关的列表，其余为空
+#[derive(Debug, Clone)]
+pub struct CompletionContext {
+    pub kind: CompletionKind,
+    /// 光标所在行从行首到光标的文本，前端可据此过滤候选项
+    pub prefix: String,
+    /// 已出现的角色名，按出场次数从多到少排序
+    pub characters: Vec<String>,
+    /// 场景标题前缀，如 "INT."、"EXT."
+    pub scene_heading_prefixes: Vec<String>,
+    /// 已出现的地点，按场景数从多到少排序
+    pub locations: Vec<String>,
+    /// 标题页中尚未使用的键
+    pub title_page_keys: Vec<String>,
+}
+
+const SCENE_HEADING_PREFIXES: [&str; 5] = ["INT.", "EXT.", "EST.", "INT./EXT.", "I/E."];
+
+const TITLE_PAGE_KEYS: [&str; 17] = [
+    "Title",
+    "Credit",
+    "Author",
+    "Authors",
+    "Source",
+    "Notes",
+    "Draft date",
+    "Date",
+    "Contact",
+    "Revision",
+    "Copyright",
+    "Watermark",
+    "Header",
+    "Footer",
+    "Font",
+    "Metadata",
+    "Image",
+];
+
+/// 取得 `cursor_offset`（按字符计，超出末尾时视为在末尾）处的补全数据，供编辑器前端直接使用
+///
+/// `cursor_offset` 按原文计算，原文中的 `\r\n` 算两个字符。光标所在行正在输入的角色名或地点不会作为候选项返回，
+/// 场景标题前缀除内置的以外还包括 `config.scene_heading_prefixes`。
+pub fn completion_context(script: &str, cursor_offset: usize, config: &Conf) -> CompletionContext {
+    // 解析器把 \r 和 \n 都当作换行，先统一换行符，保证行号与原文一一对应；
+    // 光标前的文本在统一换行符之前截取，偏移量不受影响
+    let before: String = script.chars().take(cursor_offset).collect();
+    let before = before.replace("\r\n", "\n");
+    let script = script.replace("\r\n", "\n");
+    let parser = FountainParser::new();
+    let parsed = parser.parse(&script, config, false, None);
+    let lines: Vec<&str> = script.split(&['\r', '\n'][..]).collect();
+
+    let line = before.matches(&['\r', '\n'][..]).count();
+    let prefix = before
+        .rsplit(&['\r', '\n'][..])
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let current = lines.get(line).copied().unwrap_or_default();
+
+    let title_key_like = |l: &str| {
+        l.split_once([':', '：'])
+            .is_some_and(|(key, _)| !key.trim().is_empty() && key.chars().count() <= 30)
+    };
+    let in_title_page = lines[..line].iter().all(|l| !l.trim().is_empty())
+        && (line == 0 || title_key_like(lines[0]));
+    let paragraph_start = line == 0 || lines[line - 1].trim().is_empty();
+    let scene_heading =
+        crate::parser::fountain_parser::scene_heading_regex(&config.scene_heading_prefixes)
+            .is_match(current)
+            && !current.trim_start().starts_with("..");
+
+    let kind = if scene_heading && paragraph_start {
+        CompletionKind::SceneHeading
+    } else if in_title_page && !prefix.contains([':', '：']) && !prefix.starts_with([' ', '\t']) {
+        CompletionKind::TitlePage
+    } else if paragraph_start {
+        CompletionKind::Character
+    } else {
+        CompletionKind::Text
+    };
+
+    let mut context = CompletionContext {
+        kind,
+        prefix,
+        characters: Vec::new(),
+        scene_heading_prefixes: Vec::new(),
+        locations: Vec::new(),
+        title_page_keys: Vec::new(),
+    };
+
+    if kind == CompletionKind::TitlePage {
+        let used = &parsed.properties.title_keys;
+        context.title_page_keys = TITLE_PAGE_KEYS
+            .iter()
+            .filter(|key| !used.contains(&key.to_lowercase().replace(' ', "_")))
+            .map(|key| key.to_string())
+            .collect();
+        return context;
+    }
+
+    if matches!(kind, CompletionKind::SceneHeading | CompletionKind::Character) {
+        context.scene_heading_prefixes = SCENE_HEADING_PREFIXES
+            .iter()
+            .map(|p| p.to_string())
+            .chain(
+                config
+                    .scene_heading_prefixes
+                    .iter()
+                    .map(|p| p.trim().to_string()),
+            )
+            .filter(|p| !p.is_empty())
+            .collect();
+    }
+
+    if kind == CompletionKind::SceneHeading {
+        let mut locations: Vec<(&str, usize)> = parsed
+            .properties
+            .locations
+            .values()
+            .filter_map(|references| {
+                let count = references.iter().filter(|r| r.line != line).count();
+                Some((references.first()?.name.trim(), count))
+            })
+            .filter(|(name, count)| *count > 0 && !name.is_empty())
+            .collect();
+        locations.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        context.locations = locations.into_iter().map(|(name, _)| name.to_string()).collect();
+    } else {
+        // 角色名 -> 出场次数，不计光标所在行
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        if let Some(character_lines) = &parsed.properties.character_lines {
+            for (_, name) in character_lines.iter().filter(|(l, _)| **l != line) {
+                *counts.entry(name.as_str()).or_default() += 1;
+            }
+        }
+        let mut characters: Vec<(&str, usize)> = counts.into_iter().collect();
+        characters.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        context.characters = characters.into_iter().map(|(name, _)| name.to_string()).collect();
+    }
+    context
+}
+
+/// 文档符号和折叠范围的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SymbolKind {
+    Section,
+    Scene,
+    /// 对白块（角色名及其后的对白），需开启 `Conf::dialogue_foldable`
+    Dialogue,
+    Note,
+}
+
+/// 编辑器大纲中的一个符号
+#[derive(Debug, Clone)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 章节层级（# 的个数），其他类型为 0
+    pub level: usize,
+    /// 起始行（从 0 开始）
+    pub start_line: usize,
+    /// 结束行，为范围内最后一个有内容的行
+    pub end_line: usize,
+    /// 范围内的下级符号，按起始行排序
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// 可折叠的行范围
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldingRange {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub kind: SymbolKind,
+}
+
+/// 把 `properties.structure` 转为编辑器大纲：章节包含其下的低级章节和场景，场景包含对白块和注解
+///
+/// 章节到下一个同级或更高级章节之前结束，场景到下一个场景或章节之前结束，对白块到空行之前结束。
+pub fn document_symbols(output: &ParseOutput) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+    collect_symbols(&output.properties.structure, &mut symbols);
+
+    // 有内容的行，用于去掉范围末尾的空行
+    let mut content_lines: Vec<usize> = output
+        .tokens
+        .iter()
+        .filter(|t| {
+            !matches!(
+                t.token_type.as_str(),
+                "separator"
+                    | "dialogue_begin"
+                    | "dialogue_end"
+                    | "dual_dialogue_begin"
+                    | "dual_dialogue_end"
+            )
+        })
+        .map(|t| t.line)
+        .collect();
+    content_lines.sort_unstable();
+    content_lines.dedup();
+
+    let sections: Vec<(usize, usize)> = symbols
+        .iter()
+        .filter(|s| s.kind == SymbolKind::Section)
+        .map(|s| (s.start_line, s.level))
+        .collect();
+    let scenes: Vec<usize> = symbols
+        .iter()
+        .filter(|s| s.kind == SymbolKind::Scene)
+        .map(|s| s.start_line)
+        .collect();
+
+    for symbol in symbols.iter_mut() {
+        let start = symbol.start_line;
+        let next = match symbol.kind {
+            SymbolKind::Section => sections
+                .iter()
+                .filter(|(line, level)| *line > start && *level <= symbol.level)
+                .map(|(line, _)| *line)
+                .min(),
+            SymbolKind::Scene => sections
+                .iter()
+                .map(|(line, _)| *line)
+                .chain(scenes.iter().copied())
+                .filter(|line| *line > start)
+                .min(),
+            SymbolKind::Dialogue => {
+                symbol.end_line = dialogue_end_line(output, start);
+                continue;
+            }
+            SymbolKind::Note => continue,
+        };
+        let last = content_lines.partition_point(|line| next.is_none_or(|next| *line < next));
+        symbol.end_line = content_lines[..last].last().copied().unwrap_or(start).max(start);
+    }
+
+    symbols.sort_by(|a, b| {
+        a.start_line
+            .cmp(&b.start_line)
+            .then(b.end_line.cmp(&a.end_line))
+            .then(a.kind.cmp(&b.kind))
+    });
+
+    // 按范围嵌套：栈顶为当前最内层的符号
+    fn pop(stack: &mut Vec<DocumentSymbol>, roots: &mut Vec<DocumentSymbol>) {
+        if let Some(symbol) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(symbol),
+                None => roots.push(symbol),
+            }
+        }
+    }
+    let mut roots = Vec::new();
+    let mut stack: Vec<DocumentSymbol> = Vec::new();
+    for symbol in symbols {
+        while stack
+            .last()
+            .is_some_and(|top| symbol.end_line > top.end_line)
+        {
+            pop(&mut stack, &mut roots);
+        }
+        stack.push(symbol);
+    }
+    while !stack.is_empty() {
+        pop(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// 编辑器可折叠的范围，按起始行排序，只占一行的符号不折叠
+pub fn folding_ranges(output: &ParseOutput) -> Vec<FoldingRange> {
+    fn collect(symbols: &[DocumentSymbol], ranges: &mut Vec<FoldingRange>) {
+        for symbol in symbols {
+            if symbol.end_line > symbol.start_line {
+                ranges.push(FoldingRange {
+                    start_line: symbol.start_line,
+                    end_line: symbol.end_line,
+                    kind: symbol.kind,
+                });
+            }
+            collect(&symbol.children, ranges);
+        }
+    }
+    let mut ranges = Vec::new();
+    collect(&document_symbols(output), &mut ranges);
+    ranges
+}
+
+// 展开结构树，跳过书签，范围和下级关系之后按行号重新计算
+fn collect_symbols(structure: &[StructToken], symbols: &mut Vec<DocumentSymbol>) {
+    for token in structure {
+        let kind = if token.isnote {
+            Some(SymbolKind::Note)
+        } else if token.section {
+            Some(SymbolKind::Section)
+        } else if token.isscene {
+            Some(SymbolKind::Scene)
+        } else if token.ischartor {
+            Some(SymbolKind::Dialogue)
+        } else {
+            None
+        };
+        if let (Some(kind), Some(range)) = (kind, &token.range) {
+            symbols.push(DocumentSymbol {
+                name: strip_style_chars(&token.text).trim().to_string(),
+                kind,
+                level: if kind == SymbolKind::Section { token.level } else { 0 },
+                start_line: range.start.line,
+                end_line: range.start.line,
+                children: Vec::new(),
+            });
+        }
+        collect_symbols(&token.children, symbols);
+    }
+}
+
+// 角色名所在行开始的对白块的最后一行
+fn dialogue_end_line(output: &ParseOutput, start: usize) -> usize {
+    output
+        .tokens
+        .iter()
+        .skip_while(|t| !(t.line == start && t.token_type == "character"))
+        .skip(1)
+        .take_while(|t| !t.token_type.ends_with("dialogue_end") && t.token_type != "character")
+        .map(|t| t.line)
+        .fold(start, usize::max)
+}
+
+/// 一行在影片中的时间段
+#[derive(Debug, Clone)]
+struct TimedLine {
+    line: usize,
+    start_sec: f64,
+    end_sec: f64,
+    scene_heading: bool,
+}
+
+/// 播放时间与剧本行的对应表，由 token 的 `play_time_sec` 构建，供预览工具让时间轴与剧本位置同步
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackMap {
+    /// 按行号排序，开始时间随行号递增
+    lines: Vec<TimedLine>,
+}
+
+impl PlaybackMap {
+    pub fn new(output: &ParseOutput) -> Self {
+        let mut lines: Vec<TimedLine> = Vec::new();
+        // 行号到 `lines` 中下标的索引，同一行的多个 token 合并为一项
+        let mut index: HashMap<usize, usize> = HashMap::new();
+        let mut previous_end = 0.0;
+        for token in output.tokens.iter().filter(|t| {
+            !matches!(
+                t.token_type.as_str(),
+                "separator"
+                    | "dialogue_begin"
+                    | "dialogue_end"
+                    | "dual_dialogue_begin"
+                    | "dual_dialogue_end"
+            )
+        }) {
+            // play_time_sec 是该行结束时的时间，占时长的行从上一行结束时开始
+            let end_sec = token.play_time_sec;
+            let start_sec = if token.time.is_some() {
+                previous_end
+            } else {
+                end_sec
+            };
+            previous_end = end_sec;
+            match index.get(&token.line) {
+                Some(&i) => {
+                    let line = &mut lines[i];
+                    line.start_sec = line.start_sec.min(start_sec);
+                    line.end_sec = line.end_sec.max(end_sec);
+                }
+                None => {
+                    index.insert(token.line, lines.len());
+                    lines.push(TimedLine {
+                        line: token.line,
+                        start_sec,
+                        end_sec,
+                        scene_heading: token.token_type == "scene_heading",
+                    });
+                }
+            }
+        }
+        lines.sort_by_key(|l| l.line);
+        PlaybackMap { lines }
+    }
+
+    /// 全片时长（秒）
+    pub fn total_sec(&self) -> f64 {
+        self.lines.iter().map(|l| l.end_sec).fold(0.0, f64::max)
+    }
+
+    /// `seconds` 时正在播放的行，正好是某场戏的开始时返回场景标题所在行，超出全片时长时返回最后一行，
+    /// 剧本为空时返回 None
+    pub fn time_to_line(&self, seconds: f64) -> Option<usize> {
+        let mut index = self
+            .lines
+            .iter()
+            .position(|l| l.end_sec > seconds)
+            .unwrap_or(self.lines.len().checked_sub(1)?);
+        let target = &self.lines[index];
+        if seconds <= target.start_sec {
+            // 向前跳过同一时刻开始、不占时长的行，找到场景标题
+            for (k, line) in self.lines[..index].iter().enumerate().rev() {
+                if line.end_sec > line.start_sec || line.end_sec < target.start_sec {
+                    break;
+                }
+                if line.scene_heading {
+                    index = k;
+                    break;
+                }
+            }
+        }
+        Some(self.lines[index].line)
+    }
+
+    /// 行（从 0 开始）开始播放的时间，空行、注释等没有 token 的行取前一行结束的时间
+    pub fn line_to_time(&self, line: usize) -> f64 {
+        let count = self.lines.partition_point(|l| l.line <= line);
+        match count.checked_sub(1).map(|i| &self.lines[i]) {
+            Some(timed) if timed.line == line => timed.start_sec,
+            Some(timed) => timed.end_sec,
+            None => 0.0,
+        }
+    }
+}
+
 /// 测试函数
 pub fn test_connection() -> String {
     "Rust bridge connection successful!".to_string()
@@ -0,0 +1,315 @@
+//! 解析与导出缓存
+//!
+//! 监视模式、CI 中反复导出同一份剧本时，剧本和配置都没有变化，重新解析、排版是浪费。
+//! `CachedEngine` 以剧本内容和配置的哈希为键缓存解析结果和导出的文件内容，
+//! 默认只放在内存中；指定缓存目录后同时写到磁盘，进程重启后仍然有效。
+//! 导出时读取的外部文件（`{include: ...}` 引用的剧本、插图）的内容也计入键中，这些文件修改后不再命中。
+//! 磁盘缓存只是加速：读不到或内容损坏时按未命中处理，写入失败也不影响结果。
+
+use crate::docx::{generate_bytes_with_backend, DocxGenerateError, DocxGenerateResult};
+use crate::logging::debug_log;
+use crate::models::Conf;
+use crate::parser::{expand_includes, FountainParser, IncludeError, ParseOutput};
+use crate::render::backend_for;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 缓存键：剧本内容的哈希、配置的哈希、本库版本和解析器设置的哈希，以及外部文件的哈希
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub script: u64,
+    pub config: u64,
+    /// 本库版本与解析器标识（[`FountainParser::fingerprint`]），升级后或换了时长模型时旧的磁盘缓存不再命中
+    pub engine: u64,
+    /// 外部文件（引用的剧本、插图）的路径和内容，见 [`CacheKey::with_files`]
+    pub files: u64,
+}
+
+impl CacheKey {
+    /// 配置按序列化后的 JSON（键已排序）计算哈希，与 HashMap 的遍历顺序无关；
+    /// 哈希算法固定（FNV-1a），同一输入在不同进程、不同版本的编译器下得到相同的键
+    pub fn new(script: &str, config: &Conf) -> Self {
+        Self::with_parser(script, config, &FountainParser::new())
+    }
+
+    /// 与 [`CacheKey::new`] 相同，解析器的设置（如自定义的对白时长模型）也计入键中
+    pub fn with_parser(script: &str, config: &Conf, parser: &FountainParser) -> Self {
+        let config = serde_json::to_value(config)
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+        let engine = format!("{}\n{}", env!("CARGO_PKG_VERSION"), parser.fingerprint());
+        CacheKey {
+            script: fnv1a(script.as_bytes()),
+            config: fnv1a(config.as_bytes()),
+            engine: fnv1a(engine.as_bytes()),
+            files: fnv1a(&[]),
+        }
+    }
+
+    /// 再计入 `paths` 中各文件的路径和内容（与顺序无关），读不到的文件也会改变键
+    pub fn with_files<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        let mut paths: Vec<PathBuf> = paths
+            .into_iter()
+            .map(|p| p.as_ref().to_path_buf())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        let mut bytes = self.files.to_le_bytes().to_vec();
+        for path in paths {
+            bytes.extend_from_slice(path.to_string_lossy().as_bytes());
+            match fs::read(&path) {
+                Ok(data) => bytes.extend_from_slice(&fnv1a(&data).to_le_bytes()),
+                Err(_) => bytes.push(0),
+            }
+        }
+        self.files = fnv1a(&bytes);
+        self
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:016x}{:016x}{:016x}{:016x}",
+            self.script, self.config, self.engine, self.files
+        )
+    }
+}
+
+// 导出文件的缓存键：(键, 格式)
+type ArtifactKey = (CacheKey, String);
+
+/// 缓存命中情况
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// 带缓存的解析与导出
+#[derive(Default)]
+pub struct CachedEngine {
+    parser: FountainParser,
+    /// 磁盘缓存目录，为空时只缓存在内存中
+    dir: Option<PathBuf>,
+    parsed: Mutex<HashMap<CacheKey, Arc<ParseOutput>>>,
+    artifacts: Mutex<HashMap<ArtifactKey, Arc<Vec<u8>>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl CachedEngine {
+    /// 只缓存在内存中
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 同时缓存到 `dir` 目录，目录不存在时在第一次写入时创建
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        CachedEngine {
+            dir: Some(dir.into()),
+            ..Self::default()
+        }
+    }
+
+    /// 使用指定的解析器（如设置了自定义的对白时长模型）
+    pub fn with_parser(mut self, parser: FountainParser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// 解析剧本（不生成 HTML），剧本和配置没有变化时直接返回上次的结果
+    pub fn parse(&self, script: &str, config: &Conf) -> Arc<ParseOutput> {
+        let key = CacheKey::with_parser(script, config, &self.parser);
+        self.parse_cached(key, || self.parser.parse(script, config, false, None))
+    }
+
+    /// 读取剧本文件并解析（展开 `{include: ...}`，见 [`FountainParser::parse_file`]），
+    /// 剧本、被引用的文件和配置都没有变化时直接返回上次的结果
+    pub fn parse_file(&self, path: &Path, config: &Conf) -> Result<Arc<ParseOutput>, IncludeError> {
+        self.parse_file_keyed(path, config)
+            .map(|(_, parsed)| parsed)
+    }
+
+    /// 导出为 `format`（docx、odt、html）格式的文件内容，剧本、配置和插图都没有变化时直接返回上次的结果
+    pub async fn export(
+        &self,
+        script: &str,
+        config: &Conf,
+        format: &str,
+    ) -> DocxGenerateResult<Arc<Vec<u8>>> {
+        let key = CacheKey::with_parser(script, config, &self.parser);
+        let parsed = self.parse_cached(key, || self.parser.parse(script, config, false, None));
+        self.export_cached(key, &parsed, config, format).await
+    }
+
+    /// 与 [`CachedEngine::export`] 相同，剧本从文件读取并展开引用，被引用的文件修改后重新导出
+    pub async fn export_file(
+        &self,
+        path: &Path,
+        config: &Conf,
+        format: &str,
+    ) -> DocxGenerateResult<Arc<Vec<u8>>> {
+        let (key, parsed) = self.parse_file_keyed(path, config)?;
+        self.export_cached(key, &parsed, config, format).await
+    }
+
+    // 键计入展开引用时读取的各个文件
+    fn parse_file_keyed(
+        &self,
+        path: &Path,
+        config: &Conf,
+    ) -> Result<(CacheKey, Arc<ParseOutput>), IncludeError> {
+        let expanded = expand_includes(path)?;
+        let key = CacheKey::with_parser(&expanded.script, config, &self.parser)
+            .with_files(expanded.origins.iter().map(|origin| &origin.file));
+        let parsed = self.parse_cached(key, || {
+            self.parser.parse_expanded(&expanded, config, false, None)
+        });
+        Ok((key, parsed))
+    }
+
+    fn parse_cached(&self, key: CacheKey, parse: impl FnOnce() -> ParseOutput) -> Arc<ParseOutput> {
+        if let Some(output) = self.parsed.lock().unwrap().get(&key) {
+            self.hit();
+            return output.clone();
+        }
+        if let Some(output) = self
+            .read_disk(&key, "json")
+            .and_then(|data| serde_json::from_slice::<ParseOutput>(&data).ok())
+        {
+            self.hit();
+            return self.remember_parsed(key, output);
+        }
+
+        self.miss();
+        let output = parse();
+        if let Ok(data) = serde_json::to_vec(&output) {
+            self.write_disk(&key, "json", &data);
+        }
+        self.remember_parsed(key, output)
+    }
+
+    // 导出结果的键再计入插图文件，插图修改后重新导出
+    async fn export_cached(
+        &self,
+        key: CacheKey,
+        parsed: &ParseOutput,
+        config: &Conf,
+        format: &str,
+    ) -> DocxGenerateResult<Arc<Vec<u8>>> {
+        let format = format.to_lowercase();
+        let Some(backend) = backend_for(&format) else {
+            return Err(DocxGenerateError::InvalidConfig(format!(
+                "不支持的导出格式: {}",
+                format
+            )));
+        };
+        let key = key.with_files(image_paths(parsed));
+        let artifact_key = (key, format.clone());
+        if let Some(data) = self.artifacts.lock().unwrap().get(&artifact_key) {
+            self.hit();
+            return Ok(data.clone());
+        }
+        if let Some(data) = self.read_disk(&key, &format) {
+            self.hit();
+            return Ok(self.remember_artifact(artifact_key, data));
+        }
+
+        self.miss();
+        let data = generate_bytes_with_backend(config, parsed, backend).await?;
+        self.write_disk(&key, &format, &data);
+        Ok(self.remember_artifact(artifact_key, data))
+    }
+
+    /// 缓存命中与未命中的次数（解析和导出分别计数）
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 清空内存中的缓存，磁盘缓存保留
+    pub fn clear(&self) {
+        self.parsed.lock().unwrap().clear();
+        self.artifacts.lock().unwrap().clear();
+    }
+
+    fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn remember_parsed(&self, key: CacheKey, output: ParseOutput) -> Arc<ParseOutput> {
+        let output = Arc::new(output);
+        self.parsed.lock().unwrap().insert(key, output.clone());
+        output
+    }
+
+    fn remember_artifact(&self, key: ArtifactKey, data: Vec<u8>) -> Arc<Vec<u8>> {
+        let data = Arc::new(data);
+        self.artifacts.lock().unwrap().insert(key, data.clone());
+        data
+    }
+
+    fn disk_path(&self, key: &CacheKey, extension: &str) -> Option<PathBuf> {
+        self.dir
+            .as_deref()
+            .map(|dir| dir.join(format!("{}.{}", key, extension)))
+    }
+
+    fn read_disk(&self, key: &CacheKey, extension: &str) -> Option<Vec<u8>> {
+        fs::read(self.disk_path(key, extension)?).ok()
+    }
+
+    // 先写临时文件再改名，避免并发读到写了一半的文件
+    fn write_disk(&self, key: &CacheKey, extension: &str, data: &[u8]) {
+        let Some(path) = self.disk_path(key, extension) else {
+            return;
+        };
+        let tmp = path.with_extension(format!("{}.tmp", extension));
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, data))
+            .and_then(|_| fs::rename(&tmp, &path));
+        if let Err(e) = result {
+            debug_log!("【cache】写入缓存 {} 失败: {}", path.display(), e);
+        }
+    }
+}
+
+impl fmt::Debug for CachedEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedEngine")
+            .field("dir", &self.dir.as_deref().map(Path::display))
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+// 导出时读取的插图（相对路径按导出时的规则，以当前目录为准）
+fn image_paths(parsed: &ParseOutput) -> impl Iterator<Item = &str> {
+    parsed
+        .tokens
+        .iter()
+        .chain(parsed.title_page.values().flatten())
+        .filter(|token| token.token_type == "image")
+        .filter_map(|token| token.metadata.as_ref()?.get("src").map(String::as_str))
+}
+
+// 64 位 FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
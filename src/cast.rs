@@ -0,0 +1,79 @@
+//! 角色表（CAST OF CHARACTERS）
+//!
+//! 由 `ScreenplayProperties.characters` 生成，角色按首次出场的先后排列，并注明首次出场的场景。
+//! 开启 `Conf::print_cast_list` 后，DOCX 导出会在标题页之后插入一页角色表。
+
+use crate::parser::ParseOutput;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 角色表页的标题
+pub const CAST_TITLE: &str = "CAST OF CHARACTERS";
+
+/// 角色表中的一个角色
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CastMember {
+    pub name: String,
+    /// 首次出场的场景编号，只在第一个场景之前出现时为 None
+    pub first_scene: Option<String>,
+    /// 首次出场的角色名所在行（从 0 开始）
+    pub first_line: usize,
+    /// 出场的场景数
+    pub scene_count: usize,
+}
+
+impl CastMember {
+    /// 角色表中的一行，如 "阿强 (SC. 1)"
+    pub fn display(&self) -> String {
+        match &self.first_scene {
+            Some(scene) => format!("{} (SC. {})", self.name, scene),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// 按首次出场顺序列出所有角色
+pub fn cast_list(output: &ParseOutput) -> Vec<CastMember> {
+    let properties = &output.properties;
+    let mut first_lines: HashMap<&str, usize> = HashMap::new();
+    for (line, name) in properties.character_lines.iter().flatten() {
+        let first = first_lines.entry(name.as_str()).or_insert(*line);
+        *first = (*first).min(*line);
+    }
+
+    let mut members: Vec<CastMember> = properties
+        .characters
+        .iter()
+        .map(|(name, scenes)| {
+            let first_scene = scenes.iter().min().and_then(|index| {
+                let number = properties.scenes.get(*index)?.get("number")?.as_str()?;
+                Some(number.trim_start_matches('↑').to_string())
+            });
+            CastMember {
+                name: name.clone(),
+                first_scene,
+                first_line: first_lines
+                    .get(name.as_str())
+                    .copied()
+                    .unwrap_or(usize::MAX),
+                scene_count: scenes.len(),
+            }
+        })
+        .collect();
+    members.sort_by(|a, b| {
+        a.first_line
+            .cmp(&b.first_line)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    members
+}
+
+/// 角色表页的文本：标题、空行，之后每个角色一行
+pub fn cast_page_text(members: &[CastMember]) -> String {
+    let mut text = format!("{}\n\n", CAST_TITLE);
+    for member in members {
+        text.push_str(&member.display());
+        text.push('\n');
+    }
+    text
+}
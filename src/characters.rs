@@ -0,0 +1,164 @@
+//! 角色信息
+//!
+//! `ScreenplayProperties.characters` 只记录角色出场的场景下标，这里把解析结果整理成带类型的角色信息：
+//! 用过的扩展（V.O.、O.S. 等）、双对白次数、首末次说话的行、别名，
+//! 并按角色建立对白索引，便于查询某个角色的全部对白。
+
+use crate::models::ScriptToken;
+use crate::parser::ParseOutput;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+lazy_static! {
+    /// 角色名后的扩展，如 (V.O.)、（画外音）
+    static ref EXTENSION_REGEX: Regex = Regex::new(r"\(([^)]*)\)|（([^）]*)）").unwrap();
+}
+
+/// 一个角色的信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharacterInfo {
+    /// 规范角色名（别名已合并）
+    pub name: String,
+    /// 角色名后用过的扩展，按首次出现的顺序，如 ["V.O.", "CONT'D"]
+    pub extensions: Vec<String>,
+    /// 参与双对白（含三人同时对白）的次数
+    pub dual_count: usize,
+    /// 第一次说话的角色名所在行（从 0 开始），只在动作中出现的角色为 None
+    pub first_line: Option<usize>,
+    /// 最后一次说话的角色名所在行
+    pub last_line: Option<usize>,
+    /// 说话的次数（角色名出现的次数）
+    pub cue_count: usize,
+    /// 出场的场景下标（对应 `ScreenplayProperties.scenes`），按先后排列
+    pub scenes: Vec<usize>,
+    /// 标题页 metadata 中声明的别名
+    pub aliases: Vec<String>,
+}
+
+/// 角色索引，建立一次后可反复查询角色信息和对白
+pub struct CharacterIndex<'a> {
+    output: &'a ParseOutput,
+    characters: Vec<CharacterInfo>,
+    /// 规范角色名 -> 对白 token 在 `ParseOutput::tokens` 中的下标
+    dialogue: HashMap<String, Vec<usize>>,
+}
+
+impl<'a> CharacterIndex<'a> {
+    pub fn new(output: &'a ParseOutput) -> Self {
+        let properties = &output.properties;
+        let mut characters: Vec<CharacterInfo> = properties
+            .characters
+            .iter()
+            .map(|(name, scenes)| {
+                let mut scenes = scenes.clone();
+                scenes.sort_unstable();
+                let mut aliases: Vec<String> = properties
+                    .character_aliases
+                    .iter()
+                    .filter(|(_, canonical)| *canonical == name)
+                    .map(|(alias, _)| alias.clone())
+                    .collect();
+                aliases.sort();
+                CharacterInfo {
+                    name: name.clone(),
+                    extensions: Vec::new(),
+                    dual_count: 0,
+                    first_line: None,
+                    last_line: None,
+                    cue_count: 0,
+                    scenes,
+                    aliases,
+                }
+            })
+            .collect();
+        let positions: HashMap<String, usize> = characters
+            .iter()
+            .enumerate()
+            .map(|(i, info)| (info.name.clone(), i))
+            .collect();
+
+        // 括号注释不带角色名，归到前面的角色名下
+        let mut dialogue: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut speaker: Option<&String> = None;
+        for (index, token) in output.tokens.iter().enumerate() {
+            if token.is_type(&["dialogue", "parenthetical"]) {
+                if let Some(name) = token.character.as_ref().or(speaker) {
+                    dialogue.entry(name.clone()).or_default().push(index);
+                }
+                continue;
+            }
+            if token.token_type != "character" {
+                speaker = None;
+                continue;
+            }
+            speaker = token.character.as_ref();
+            let Some(info) = speaker
+                .and_then(|name| positions.get(name))
+                .map(|&i| &mut characters[i])
+            else {
+                continue;
+            };
+            info.cue_count += 1;
+            info.first_line.get_or_insert(token.line);
+            info.last_line = Some(token.line);
+            if token.dual.as_deref().is_some_and(|d| !d.is_empty()) {
+                info.dual_count += 1;
+            }
+            for caps in EXTENSION_REGEX.captures_iter(&token.text) {
+                let extension = caps
+                    .get(1)
+                    .or(caps.get(2))
+                    .map_or("", |m| m.as_str())
+                    .trim();
+                if !extension.is_empty() && !info.extensions.iter().any(|e| e == extension) {
+                    info.extensions.push(extension.to_string());
+                }
+            }
+        }
+
+        // 按第一次说话的先后排列，不说话的角色排在最后
+        characters.sort_by(|a, b| {
+            a.first_line
+                .unwrap_or(usize::MAX)
+                .cmp(&b.first_line.unwrap_or(usize::MAX))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        CharacterIndex {
+            output,
+            characters,
+            dialogue,
+        }
+    }
+
+    /// 所有角色，按第一次说话的先后排列
+    pub fn characters(&self) -> &[CharacterInfo] {
+        &self.characters
+    }
+
+    /// 按角色名或别名查找角色，忽略大小写
+    pub fn get(&self, name: &str) -> Option<&CharacterInfo> {
+        let name = self.output.properties.canonical_character(name);
+        self.characters
+            .iter()
+            .find(|info| info.name.to_uppercase() == name.to_uppercase())
+    }
+
+    /// 角色的全部对白 token（对白、括号注释等，不含角色名本身），按先后排列
+    pub fn dialogue(&self, name: &str) -> Vec<&'a ScriptToken> {
+        let Some(info) = self.get(name) else {
+            return Vec::new();
+        };
+        let output = self.output;
+        self.dialogue
+            .get(&info.name)
+            .map(|indexes| indexes.iter().map(|&i| &output.tokens[i]).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// 整理解析结果中的角色信息，按第一次说话的先后排列
+pub fn character_list(output: &ParseOutput) -> Vec<CharacterInfo> {
+    CharacterIndex::new(output).characters
+}
@@ -0,0 +1,405 @@
+//! 两稿剧本对比
+//!
+//! 先按场景标题对齐两稿中的场景（标题改动但编号相同的场景也视为同一场），
+//! 再逐场比较动作、对白等内容行，报告新增、删除和修改的场景及行。
+
+use crate::models::ScriptToken;
+use crate::parser::ParseOutput;
+use crate::utils::strip_style_chars;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// 场景内的一行变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineChange {
+    /// 只会是 Added 或 Removed，修改过的行表现为删除旧行加新增新行
+    pub kind: ChangeKind,
+    pub token_type: String,
+    /// 对白、括号注释所属的角色
+    pub character: Option<String>,
+    pub text: String,
+    /// 所在行（从 0 开始），删除的行为旧稿中的行号，其它为新稿中的行号
+    pub line: usize,
+}
+
+/// 一个场景的变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneChange {
+    pub kind: ChangeKind,
+    /// 场景标题，删除的场景为旧稿中的标题，其它为新稿中的标题；第一个场景之前的内容为空字符串
+    pub heading: String,
+    /// 标题有改动时旧稿中的标题
+    pub old_heading: Option<String>,
+    pub old_number: Option<String>,
+    pub new_number: Option<String>,
+    /// 场景标题在旧稿中的行号
+    pub old_line: Option<usize>,
+    /// 场景标题在新稿中的行号
+    pub new_line: Option<usize>,
+    pub lines: Vec<LineChange>,
+}
+
+/// 两稿的对比结果，只包含有变化的场景，按新稿顺序排列，删除的场景位于其原来的位置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptDiff {
+    pub scenes: Vec<SceneChange>,
+}
+
+impl ScriptDiff {
+    /// 新稿中有变化的行（新增、修改场景的标题及新增的内容行），用于在 DOCX 中标注修订
+    pub fn changed_lines(&self) -> Vec<usize> {
+        let mut lines = Vec::new();
+        for scene in &self.scenes {
+            if scene.kind == ChangeKind::Added || scene.old_heading.is_some() {
+                lines.extend(scene.new_line);
+            }
+            lines.extend(
+                scene
+                    .lines
+                    .iter()
+                    .filter(|l| l.kind == ChangeKind::Added)
+                    .map(|l| l.line),
+            );
+        }
+        lines
+    }
+
+    /// 输出 JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// 修订稿：在新稿中按原位置插回旧稿被删除的内容，用于导出带修订标记的文档
+#[derive(Debug, Clone)]
+pub struct RevisedDraft {
+    /// 新稿的解析结果，插入了旧稿中被删除的 token，这些 token 的行号为新稿最大行号之后的虚拟行号
+    pub output: ParseOutput,
+    /// 有修订的行：Added 为新稿中新增的行，Removed 为插回的旧稿内容
+    pub revisions: HashMap<usize, ChangeKind>,
+}
+
+// 场景中的一行内容
+#[derive(Debug, Clone)]
+struct Item {
+    token_type: String,
+    character: Option<String>,
+    text: String,
+    line: usize,
+    // 在 tokens 中的下标
+    token: usize,
+}
+
+impl Item {
+    fn same(&self, other: &Item) -> bool {
+        self.token_type == other.token_type
+            && self.character == other.character
+            && self.text == other.text
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SceneBlock {
+    heading: String,
+    number: Option<String>,
+    line: Option<usize>,
+    items: Vec<Item>,
+    // 场景标题在 tokens 中的下标
+    token: Option<usize>,
+    // 场景在 tokens 中的范围
+    start: usize,
+    end: usize,
+}
+
+/// 对比两稿
+pub fn compare(old: &ParseOutput, new: &ParseOutput) -> ScriptDiff {
+    let old_scenes = scene_blocks(old);
+    let new_scenes = scene_blocks(new);
+
+    let scenes = align_scenes(&old_scenes, &new_scenes)
+        .into_iter()
+        .filter_map(|pair| match pair {
+            (Some(o), Some(n)) => diff_scene(&old_scenes[o], &new_scenes[n]),
+            (Some(o), None) => Some(whole_scene(&old_scenes[o], ChangeKind::Removed)),
+            (None, Some(n)) => Some(whole_scene(&new_scenes[n], ChangeKind::Added)),
+            (None, None) => None,
+        })
+        .collect();
+    ScriptDiff { scenes }
+}
+
+/// 生成修订稿
+///
+/// 被删除的行插在新稿中原来的位置：场景内删除的行插在其后第一个保留或新增的行之前，
+/// 整场删除的场景插在下一个场景之前。
+pub fn revised_draft(old: &ParseOutput, new: &ParseOutput) -> RevisedDraft {
+    let old_scenes = scene_blocks(old);
+    let new_scenes = scene_blocks(new);
+    let line_offset = new.tokens.iter().map(|t| t.line + 1).max().unwrap_or(0);
+
+    let mut revisions = HashMap::new();
+    // 插入到新稿第 i 个 token 之前的旧稿 token
+    let mut inserted: Vec<Vec<ScriptToken>> = vec![Vec::new(); new.tokens.len() + 1];
+    // 整场删除的场景，等到下一个场景时插入
+    let mut pending: Vec<usize> = Vec::new();
+    let mut remove = |token: usize, anchor: usize, inserted: &mut Vec<Vec<ScriptToken>>| {
+        let mut token = old.tokens[token].clone();
+        token.line += line_offset;
+        revisions.insert(token.line, ChangeKind::Removed);
+        inserted[anchor].push(token);
+    };
+    let mut added = Vec::new();
+
+    for pair in align_scenes(&old_scenes, &new_scenes) {
+        let (o, n) = match pair {
+            (Some(o), None) => {
+                let scene = &old_scenes[o];
+                pending.extend(scene.token);
+                pending.extend(scene.items.iter().map(|i| i.token));
+                continue;
+            }
+            (o, Some(n)) => (o, &new_scenes[n]),
+            (None, None) => continue,
+        };
+        for token in pending.drain(..) {
+            remove(token, n.start, &mut inserted);
+        }
+        let Some(o) = o.map(|o| &old_scenes[o]) else {
+            added.extend(n.token.map(|t| new.tokens[t].line));
+            added.extend(n.items.iter().map(|i| i.line));
+            continue;
+        };
+        if o.heading != n.heading {
+            if let Some(token) = o.token {
+                remove(token, n.start, &mut inserted);
+            }
+            added.extend(n.token.map(|t| new.tokens[t].line));
+        }
+        let pairs = lcs(&o.items, &n.items, Item::same);
+        let (mut oi, mut ni) = (0, 0);
+        for &(po, pn) in pairs
+            .iter()
+            .chain(std::iter::once(&(o.items.len(), n.items.len())))
+        {
+            let anchor = n.items.get(ni).map_or(n.end, |i| i.token);
+            for item in &o.items[oi..po] {
+                remove(item.token, anchor, &mut inserted);
+            }
+            added.extend(n.items[ni..pn].iter().map(|i| i.line));
+            oi = po + 1;
+            ni = pn + 1;
+        }
+    }
+    for token in pending {
+        remove(token, new.tokens.len(), &mut inserted);
+    }
+    for line in added {
+        revisions.insert(line, ChangeKind::Added);
+    }
+
+    let mut output = new.clone();
+    output.tokens.clear();
+    for (before, token) in inserted.iter_mut().zip(&new.tokens) {
+        output.tokens.append(before);
+        output.tokens.push(token.clone());
+    }
+    if let Some(tail) = inserted.last_mut() {
+        output.tokens.append(tail);
+    }
+    RevisedDraft { output, revisions }
+}
+
+// 按标题对齐两稿的场景，返回 (旧稿场景, 新稿场景)，按合并后的顺序排列
+fn align_scenes(
+    old_scenes: &[SceneBlock],
+    new_scenes: &[SceneBlock],
+) -> Vec<(Option<usize>, Option<usize>)> {
+    let pairs = lcs(old_scenes, new_scenes, |a, b| a.heading == b.heading);
+    let mut aligned: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+    let (mut oi, mut ni) = (0, 0);
+    for &(po, pn) in pairs
+        .iter()
+        .chain(std::iter::once(&(old_scenes.len(), new_scenes.len())))
+    {
+        aligned.extend((oi..po).map(|o| (Some(o), None)));
+        aligned.extend((ni..pn).map(|n| (None, Some(n))));
+        if po < old_scenes.len() {
+            aligned.push((Some(po), Some(pn)));
+        }
+        oi = po + 1;
+        ni = pn + 1;
+    }
+
+    // 标题改动但编号相同的场景视为同一场
+    for idx in 0..aligned.len() {
+        let (None, Some(n)) = aligned[idx] else {
+            continue;
+        };
+        let Some(number) = new_scenes[n].number.as_ref() else {
+            continue;
+        };
+        let removed = aligned.iter().position(|&(o, new)| {
+            new.is_none() && o.is_some_and(|o| old_scenes[o].number.as_ref() == Some(number))
+        });
+        if let Some(removed) = removed {
+            aligned[idx].0 = aligned[removed].0;
+            aligned[removed] = (None, None);
+        }
+    }
+    aligned
+}
+
+fn diff_scene(old: &SceneBlock, new: &SceneBlock) -> Option<SceneChange> {
+    let pairs = lcs(&old.items, &new.items, Item::same);
+    let mut lines = Vec::new();
+    let (mut oi, mut ni) = (0, 0);
+    for &(po, pn) in pairs
+        .iter()
+        .chain(std::iter::once(&(old.items.len(), new.items.len())))
+    {
+        lines.extend(
+            old.items[oi..po]
+                .iter()
+                .map(|i| line_change(i, ChangeKind::Removed)),
+        );
+        lines.extend(
+            new.items[ni..pn]
+                .iter()
+                .map(|i| line_change(i, ChangeKind::Added)),
+        );
+        oi = po + 1;
+        ni = pn + 1;
+    }
+
+    let heading_changed = old.heading != new.heading;
+    if lines.is_empty() && !heading_changed {
+        return None;
+    }
+    Some(SceneChange {
+        kind: ChangeKind::Modified,
+        heading: new.heading.clone(),
+        old_heading: heading_changed.then(|| old.heading.clone()),
+        old_number: old.number.clone(),
+        new_number: new.number.clone(),
+        old_line: old.line,
+        new_line: new.line,
+        lines,
+    })
+}
+
+fn whole_scene(scene: &SceneBlock, kind: ChangeKind) -> SceneChange {
+    let removed = kind == ChangeKind::Removed;
+    SceneChange {
+        kind,
+        heading: scene.heading.clone(),
+        old_heading: None,
+        old_number: if removed { scene.number.clone() } else { None },
+        new_number: if removed { None } else { scene.number.clone() },
+        old_line: if removed { scene.line } else { None },
+        new_line: if removed { None } else { scene.line },
+        lines: scene.items.iter().map(|i| line_change(i, kind)).collect(),
+    }
+}
+
+fn line_change(item: &Item, kind: ChangeKind) -> LineChange {
+    LineChange {
+        kind,
+        token_type: item.token_type.clone(),
+        character: item.character.clone(),
+        text: item.text.clone(),
+        line: item.line,
+    }
+}
+
+// 按场景切分 token，第一个场景之前的内容作为标题为空的场景
+fn scene_blocks(output: &ParseOutput) -> Vec<SceneBlock> {
+    let mut scenes = vec![SceneBlock {
+        heading: String::new(),
+        number: None,
+        line: None,
+        items: Vec::new(),
+        token: None,
+        start: 0,
+        end: output.tokens.len(),
+    }];
+    for (index, token) in output.tokens.iter().enumerate() {
+        let text = strip_style_chars(&token.text)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if token.token_type == "scene_heading" {
+            if let Some(scene) = scenes.last_mut() {
+                scene.end = index;
+            }
+            scenes.push(SceneBlock {
+                heading: text,
+                number: token
+                    .number
+                    .as_ref()
+                    .map(|n| n.trim_start_matches('↑').to_string()),
+                line: Some(token.line),
+                items: Vec::new(),
+                token: Some(index),
+                start: index,
+                end: output.tokens.len(),
+            });
+            continue;
+        }
+        if text.is_empty()
+            || token.token_type.ends_with("_begin")
+            || token.token_type.ends_with("_end")
+        {
+            continue;
+        }
+        if let Some(scene) = scenes.last_mut() {
+            scene.items.push(Item {
+                token_type: token.token_type.clone(),
+                character: token.character.clone(),
+                text,
+                line: token.line,
+                token: index,
+            });
+        }
+    }
+    if scenes[0].items.is_empty() {
+        scenes.remove(0);
+    }
+    scenes
+}
+
+// 最长公共子序列，返回配对的下标
+fn lcs<T>(a: &[T], b: &[T], eq: impl Fn(&T, &T) -> bool) -> Vec<(usize, usize)> {
+    let mut table = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = if eq(&a[i], &b[j]) {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if eq(&a[i], &b[j]) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
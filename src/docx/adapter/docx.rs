@@ -3,9 +3,9 @@
 //! 该模块提供了与原始 TypeScript 版本 docxmaker.ts 中 Docx 命名空间兼容的 API
 
 use super::*;
+use crate::logging::debug_log;
 use base64;
 use docx_rs;
-use std::fs::File;
 use std::path::Path;
 
 /// 段落间距
@@ -260,6 +260,8 @@ pub struct ParagraphStyle {
     pub paragraph: Option<Box<ParagraphStyle>>,
     pub spacing: Option<ParagraphSpacing>,
     pub indent: Option<ParagraphIndent>,
+    /// 样式自带的制表位，与段落自己的制表位一起生效
+    pub tabs: Vec<TabStop>,
 }
 
 impl ParagraphStyle {
@@ -274,6 +276,44 @@ impl ParagraphStyle {
             paragraph: None,
             spacing: None,
             indent: None,
+            tabs: Vec::new(),
+        }
+    }
+}
+
+/// 制表位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TabStop {
+    /// 位置（twips），从页面左边距算起，与段落缩进无关
+    pub position: i32,
+    pub alignment: TabAlignment,
+    pub leader: TabLeader,
+}
+
+impl TabStop {
+    /// 没有前导符的制表位
+    pub fn new(position: i32, alignment: TabAlignment) -> Self {
+        Self {
+            position,
+            alignment,
+            leader: TabLeader::None,
+        }
+    }
+
+    /// 设置前导符
+    pub fn leader(mut self, leader: TabLeader) -> Self {
+        self.leader = leader;
+        self
+    }
+
+    /// 转换为 docx-rs 的 Tab
+    pub fn to_docx_tab(&self) -> docx_rs::Tab {
+        let tab = docx_rs::Tab::new()
+            .val(self.alignment.to_docx_tab_value())
+            .pos(self.position.max(0) as usize);
+        match self.leader {
+            TabLeader::None => tab,
+            leader => tab.leader(leader.to_docx_tab_leader()),
         }
     }
 }
@@ -339,6 +379,35 @@ impl Section {
             children: Vec::new(),
         }
     }
+
+    /// 为节中（包括表格中）的批注引用填入批注内容，`comments` 以批注 ID 为键
+    pub fn fill_comments(&mut self, comments: &HashMap<usize, Comment>) {
+        let mut fill = |paragraph: &mut Paragraph| {
+            for run in &mut paragraph.runs {
+                if let RunType::Text(TextRun {
+                    comment: Some(comment),
+                    ..
+                }) = run
+                {
+                    if let Some(content) = comments.get(&comment.id) {
+                        *comment = content.clone();
+                    }
+                }
+            }
+        };
+        for child in &mut self.children {
+            match child {
+                SectionChild::Paragraph(paragraph) => fill(paragraph),
+                SectionChild::Table(table) => table
+                    .rows
+                    .iter_mut()
+                    .flat_map(|row| row.cells.iter_mut())
+                    .flat_map(|cell| cell.children.iter_mut())
+                    .for_each(&mut fill),
+                SectionChild::PageBreak => {}
+            }
+        }
+    }
 }
 
 /// 节子元素
@@ -353,12 +422,20 @@ pub enum SectionChild {
 #[derive(Debug, Clone)]
 pub struct SectionProperties {
     pub page: Option<PageProperties>,
+    /// 竖排（文字方向 tbRl：从上到下，行从右到左）
+    pub vertical: bool,
+    /// 在左侧页边标行号，每隔几行标一次（1 为每行），各节连续编号；None 不标
+    pub line_numbers: Option<u32>,
 }
 
 impl SectionProperties {
     /// 创建新的节属性
     pub fn new() -> Self {
-        Self { page: None }
+        Self {
+            page: None,
+            vertical: false,
+            line_numbers: None,
+        }
     }
 }
 
@@ -423,14 +500,42 @@ impl PageMargin {
 /// 页码
 #[derive(Debug, Clone)]
 pub struct PageNumbers {
-    pub start: i32,
+    /// 起始页码，为空时接续上一节
+    pub start: Option<i32>,
+    pub format: PageNumberFormat,
 }
 
 impl PageNumbers {
     /// 创建新的页码
     pub fn new(start: i32) -> Self {
-        Self { start }
+        Self {
+            start: Some(start),
+            format: PageNumberFormat::Decimal,
+        }
     }
+
+    /// 接续上一节的页码
+    pub fn continued() -> Self {
+        Self {
+            start: None,
+            format: PageNumberFormat::Decimal,
+        }
+    }
+
+    /// 设置页码格式
+    pub fn format(mut self, format: PageNumberFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+/// 页码格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageNumberFormat {
+    /// 阿拉伯数字
+    Decimal,
+    /// 小写罗马数字
+    LowerRoman,
 }
 
 /// 页眉
@@ -513,6 +618,14 @@ pub struct DocumentOptions {
     pub styles: Option<Styles>,
     pub sections: Vec<Section>,
     pub footnotes: HashMap<usize, Footnote>,
+    /// 文档保护（编辑限制）
+    pub protection: Option<DocumentProtection>,
+    /// 确定性输出，打包时固定日期、条目顺序和各种 id，见 `Conf::deterministic`
+    pub deterministic: bool,
+    /// 确定性输出时 docProps/core.xml 中的创建、修改日期和压缩包条目的修改时间
+    pub deterministic_timestamp: String,
+    /// 页面底部脚注的编号和分隔线
+    pub footnote_layout: FootnoteLayout,
 }
 
 impl DocumentOptions {
@@ -525,6 +638,83 @@ impl DocumentOptions {
             styles: None,
             sections: Vec::new(),
             footnotes: HashMap::new(),
+            protection: None,
+            deterministic: false,
+            deterministic_timestamp: DETERMINISTIC_TIMESTAMP.to_string(),
+            footnote_layout: FootnoteLayout::default(),
+        }
+    }
+}
+
+/// 文档保护（编辑限制），不设密码，在 Word 中可以直接停止保护
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentProtection {
+    /// 打开时建议以只读方式打开
+    ReadOnlyRecommended,
+    /// 只读
+    ReadOnly,
+    /// 只能添加批注
+    Comments,
+    /// 所有修改都记为修订
+    TrackedChanges,
+}
+
+impl DocumentProtection {
+    /// 按名称（"read_only_recommended"、"read_only"、"comments"、"tracked_changes"）选择，
+    /// 空名称或不认识的名称返回 None
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "read_only_recommended" => Some(DocumentProtection::ReadOnlyRecommended),
+            "read_only" => Some(DocumentProtection::ReadOnly),
+            "comments" => Some(DocumentProtection::Comments),
+            "tracked_changes" => Some(DocumentProtection::TrackedChanges),
+            _ => None,
+        }
+    }
+}
+
+/// 脚注编号格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FootnoteFormat {
+    /// 阿拉伯数字 1, 2, 3
+    #[default]
+    Decimal,
+    /// 小写字母 a, b, c
+    LowerLetter,
+    /// 小写罗马数字 i, ii, iii
+    LowerRoman,
+    /// 参考标记，每个脚注都用 ※
+    ReferenceMark,
+}
+
+impl FootnoteFormat {
+    /// 按写法（"1"、"a"、"i"、"※"）选择，不认识的写法使用阿拉伯数字
+    pub fn from_name(name: &str) -> Self {
+        match name.trim() {
+            "a" => FootnoteFormat::LowerLetter,
+            "i" => FootnoteFormat::LowerRoman,
+            "※" => FootnoteFormat::ReferenceMark,
+            _ => FootnoteFormat::Decimal,
+        }
+    }
+}
+
+/// 页面底部脚注的编号和分隔线（docx-rs 不支持，打包后写入 XML）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FootnoteLayout {
+    pub format: FootnoteFormat,
+    /// 每页重新编号，否则全文连续编号
+    pub restart_each_page: bool,
+    /// 脚注上方的分隔线
+    pub separator: bool,
+}
+
+impl Default for FootnoteLayout {
+    fn default() -> Self {
+        Self {
+            format: FootnoteFormat::Decimal,
+            restart_each_page: false,
+            separator: true,
         }
     }
 }
@@ -545,6 +735,21 @@ impl Document {
         }
     }
 
+    /// 以已有的 .docx 作为模板创建文档
+    ///
+    /// 模板中的样式、页眉页脚和正文内容（如公司 logo、说明文字）都会保留，
+    /// 之后添加的段落追加在模板正文之后
+    pub fn from_template(path: &Path) -> DocxAdapterResult<Self> {
+        let bytes = std::fs::read(path)?;
+        let docx = docx_rs::read_docx(&bytes).map_err(|e| {
+            DocxAdapterError::InvalidConfig(format!("无法读取模板 {}: {}", path.display(), e))
+        })?;
+        Ok(Self {
+            docx: docx.settings(docx_rs::Settings::new().balance_single_byte_double_byte_width(false)),
+            options: DocumentOptions::new(),
+        })
+    }
+
     /// 添加段落
     pub fn add_paragraph(&mut self, paragraph: Paragraph) -> &mut Self {
         self.docx = self.docx.clone().add_paragraph(
@@ -658,6 +863,10 @@ impl Document {
                     }
                 }
 
+                for tab in &paragraph_style.tabs {
+                    paragraph_property = paragraph_property.add_tab(tab.to_docx_tab());
+                }
+
                 // 直接设置字段而不是调用方法
                 docx_style.paragraph_property = paragraph_property;
 
@@ -916,7 +1125,7 @@ impl Document {
                         ));
                     }
                     section_docx = section_docx.header(docx_header);
-                    println!(
+                    debug_log!(
                         "【create_document】已设置全局页眉，包含 {} 个段落",
                         headers.default.children.len()
                     );
@@ -931,7 +1140,7 @@ impl Document {
                         ));
                     }
                     section_docx = section_docx.footer(docx_footer);
-                    println!(
+                    debug_log!(
                         "【create_document】已设置全局页脚，包含 {} 个段落",
                         footers.default.children.len()
                     );
@@ -949,7 +1158,7 @@ impl Document {
                         docx_rs::PageSize::new()
                             .size(page_size.width as u32, page_size.height as u32),
                     );
-                    println!(
+                    debug_log!(
                         "【create_document】已应用页面大小: {}x{} twip",
                         page_size.width, page_size.height
                     );
@@ -967,12 +1176,16 @@ impl Document {
                             .header(page_margin.header)
                             .footer(page_margin.footer),
                     );
-                    println!("【create_document】已应用页面边距: top={}, right={}, bottom={}, left={}, header={}, footer={}",
+                    debug_log!("【create_document】已应用页面边距: top={}, right={}, bottom={}, left={}, header={}, footer={}",
                         page_margin.top, page_margin.right, page_margin.bottom,
                         page_margin.left, page_margin.header, page_margin.footer);
                 }
 
-                println!("【create_document】页面属性已应用到文档");
+                debug_log!("【create_document】页面属性已应用到文档");
+            }
+
+            if section.properties.vertical {
+                section_docx = section_docx.text_direction("tbRl".to_string());
             }
 
             if let Some(footers) = &section.footers {
@@ -984,8 +1197,19 @@ impl Document {
                         .any(|run| matches!(run, RunType::PageNumber(_)))
                 });
 
-                if has_page_numbers {
-                    let page_num_type = docx_rs::PageNumType::new().start(1);
+                // 指定了起始页码时总是写出（页脚不显示页码的节也要计数，如隐藏页码的正文第一页），
+                // start 为空时接续上一节；没有指定页码设置时显示页码的节从 1 开始
+                let start = match section
+                    .properties
+                    .page
+                    .as_ref()
+                    .and_then(|page| page.page_numbers.as_ref())
+                {
+                    Some(page_numbers) => page_numbers.start,
+                    None => has_page_numbers.then_some(1),
+                };
+                if let Some(start) = start {
+                    let page_num_type = docx_rs::PageNumType::new().start(start.max(0) as u32);
                     section_docx = section_docx.page_num_type(page_num_type);
                 }
             }
@@ -1029,7 +1253,7 @@ impl Document {
             //     );
             // }
 
-            println!(
+            debug_log!(
                 "【create_document】处理 section #{}, 包含 {} 个子元素",
                 section_index,
                 section.children.len()
@@ -1044,7 +1268,7 @@ impl Document {
                     let current_index = end_index - 1;
                     let should_skip = match &section.children[current_index] {
                         SectionChild::PageBreak => {
-                            println!(
+                            debug_log!(
                                 "【create_document】section #{} 的元素 #{} 是分页符，将被跳过",
                                 section_index, current_index
                             );
@@ -1078,7 +1302,7 @@ impl Document {
                                 });
 
                             if is_empty {
-                                println!(
+                                debug_log!(
                                     "【create_document】section #{} 的元素 #{} 是空段落，将被跳过",
                                     section_index, current_index
                                 );
@@ -1086,17 +1310,17 @@ impl Document {
                                 for (run_index, run) in paragraph.runs.iter().enumerate() {
                                     match run {
                                         RunType::Text(text_run) => {
-                                            println!("【create_document】  运行 #{}: 文本='{}', break_before={}, props.break_before={:?}",
+                                            debug_log!("【create_document】  运行 #{}: 文本='{}', break_before={}, props.break_before={:?}",
                                                 run_index, text_run.text.replace('\n', "\\n"), text_run.break_before, text_run.props.break_before);
                                         }
                                         RunType::Break(_) => {
-                                            println!(
+                                            debug_log!(
                                                 "【create_document】  运行 #{}: 分页符",
                                                 run_index
                                             );
                                         }
                                         _ => {
-                                            println!(
+                                            debug_log!(
                                                 "【create_document】  运行 #{}: 其他类型",
                                                 run_index
                                             );
@@ -1121,7 +1345,7 @@ impl Document {
                 &section.children[..]
             };
 
-            println!(
+            debug_log!(
                 "【create_document】section #{} 实际处理 {} 个子元素",
                 section_index,
                 children_to_process.len()
@@ -1154,16 +1378,54 @@ impl Document {
 
         // 添加脚注 - 使用docx-rs的真正脚注功能
         // 注意：脚注引用已经在段落中通过FootnoteReferenceRun添加了，这里不需要再处理引用
-        println!("【create_document】脚注处理完成，文档中包含的脚注引用将自动关联到相应的脚注内容");
+        debug_log!(
+            "【create_document】脚注处理完成，文档中包含的脚注引用将自动关联到相应的脚注内容"
+        );
 
         docx
     }
 
     /// 保存文档
     pub fn save(&self, filepath: &str) -> DocxAdapterResult<()> {
-        let file = File::create(Path::new(filepath))?;
-        match self.docx.clone().build().pack(file) {
-            Ok(_) => Ok(()),
+        std::fs::write(Path::new(filepath), self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// 获取打包后的 .docx 文件内容
+    pub fn to_bytes(&self) -> DocxAdapterResult<Vec<u8>> {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        match self.docx.clone().build().pack(&mut buffer) {
+            Ok(_) => {
+                let patches: Vec<SectionXmlPatch> = self
+                    .options
+                    .sections
+                    .iter()
+                    .map(SectionXmlPatch::of)
+                    .collect();
+                let protection = self.options.protection;
+                let footnotes = self.options.footnote_layout;
+                let bytes = if patches.iter().any(|patch| !patch.is_empty())
+                    || protection.is_some()
+                    || footnotes != FootnoteLayout::default()
+                {
+                    rewrite_parts(buffer.into_inner(), |name, xml| match name {
+                        "word/document.xml" => Some(patch_footnote_references(
+                            &patch_section_properties(xml, &patches),
+                            footnotes,
+                        )),
+                        "word/footnotes.xml" => Some(patch_footnotes(xml, footnotes)),
+                        "word/settings.xml" => protection.map(|p| insert_protection(xml, p)),
+                        _ => None,
+                    })?
+                } else {
+                    buffer.into_inner()
+                };
+                if self.options.deterministic {
+                    normalize_package(bytes, &self.options.deterministic_timestamp)
+                } else {
+                    Ok(bytes)
+                }
+            }
             Err(e) => {
                 if let Some(zip_err) = e
                     .source()
@@ -1182,33 +1444,61 @@ impl Document {
 
     /// 获取 Base64 编码的文档
     pub fn to_base64(&self) -> DocxAdapterResult<String> {
-        // 保存到临时文件
-        let temp_path = std::env::temp_dir().join("temp_docx.docx");
-        let file = File::create(&temp_path)?;
-        match self.docx.clone().build().pack(file) {
-            Ok(_) => {}
-            Err(e) => {
-                if let Some(zip_err) = e
-                    .source()
-                    .and_then(|s| s.downcast_ref::<zip::result::ZipError>())
-                {
-                    return Err(DocxAdapterError::InvalidConfig(format!(
-                        "ZIP error: {:?}",
-                        zip_err
-                    )));
-                } else {
-                    return Err(DocxAdapterError::DocxError(docx_rs::DocxError::ZipError(e)));
-                }
-            }
-        }
+        Ok(base64::encode(&self.to_bytes()?))
+    }
+}
+
+/// 修订类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionKind {
+    Insert,
+    Delete,
+}
 
-        // 读取文件内容
-        let bytes = std::fs::read(&temp_path)?;
+/// 修订标记（Word 的修订模式，对应 w:ins / w:del）
+#[derive(Debug, Clone)]
+pub struct Revision {
+    pub kind: RevisionKind,
+    pub author: String,
+    /// ISO 8601 格式的时间，如 2024-01-01T00:00:00Z
+    pub date: String,
+}
 
-        // 删除临时文件
-        std::fs::remove_file(&temp_path)?;
+/// 批注（Word 的 w:comment）
+#[derive(Debug, Clone)]
+pub struct Comment {
+    pub id: usize,
+    pub author: String,
+    /// ISO 8601 格式的时间
+    pub date: String,
+    pub children: Vec<Paragraph>,
+}
+
+impl Comment {
+    /// 创建新的批注
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            author: String::new(),
+            date: String::new(),
+            children: Vec::new(),
+        }
+    }
 
-        Ok(base64::encode(&bytes))
+    /// 转换为 docx-rs 的 Comment
+    fn to_docx_comment(
+        &self,
+        mstyles: Option<Styles>,
+        footnotes: HashMap<usize, Footnote>,
+    ) -> docx_rs::Comment {
+        let mut comment = docx_rs::Comment::new(self.id)
+            .author(&self.author)
+            .date(&self.date);
+        for paragraph in &self.children {
+            comment =
+                comment.add_paragraph(paragraph.to_docx_paragraph(mstyles.clone(), footnotes.clone()));
+        }
+        comment
     }
 }
 
@@ -1222,6 +1512,18 @@ pub struct Paragraph {
     pub spacing: Option<ParagraphSpacing>,
     pub frame: Option<ParagraphFrame>,
     pub outline_level: Option<usize>, // 添加 outline 层级支持
+    pub change_bar: bool, // 左侧修订线，标注与上一稿相比有改动的段落
+    pub revision: Option<Revision>, // 整段标记为修订插入或删除
+    /// 包住整段的书签，作为目录等文档内超链接的目标
+    pub bookmark: Option<Bookmark>,
+    /// 四周边框的颜色，为空时没有边框
+    pub border: Option<String>,
+    /// 自定义制表位，文本中的制表符（\t）依次跳到下一个制表位
+    pub tabs: Vec<TabStop>,
+    /// 与下一段保持在同一页，避免在本段之后分页
+    pub keep_next: bool,
+    /// 段内不分页，整段放在同一页
+    pub keep_lines: bool,
 }
 
 impl Paragraph {
@@ -1235,6 +1537,13 @@ impl Paragraph {
             spacing: None,
             frame: None,
             outline_level: None,
+            change_bar: false,
+            revision: None,
+            bookmark: None,
+            border: None,
+            tabs: Vec::new(),
+            keep_next: false,
+            keep_lines: false,
         }
     }
     pub fn new_with_spacing(spacing: ParagraphSpacing) -> Self {
@@ -1246,6 +1555,13 @@ impl Paragraph {
             spacing: Some(spacing),
             frame: None,
             outline_level: None,
+            change_bar: false,
+            revision: None,
+            bookmark: None,
+            border: None,
+            tabs: Vec::new(),
+            keep_next: false,
+            keep_lines: false,
         }
     }
 
@@ -1273,6 +1589,12 @@ impl Paragraph {
         self
     }
 
+    /// 添加图片运行
+    pub fn add_image_run(&mut self, run: ImageRun) -> &mut Self {
+        self.runs.push(RunType::Image(run));
+        self
+    }
+
     /// 添加运行（通用方法）
     pub fn add_run(&mut self, run: RunType) -> &mut Self {
         self.runs.push(run);
@@ -1437,6 +1759,48 @@ impl Paragraph {
         self
     }
 
+    /// 设置左侧修订线
+    pub fn change_bar(&mut self, change_bar: bool) -> &mut Self {
+        self.change_bar = change_bar;
+        self
+    }
+
+    /// 把整段标记为修订插入或删除
+    pub fn revision(&mut self, revision: Revision) -> &mut Self {
+        self.revision = Some(revision);
+        self
+    }
+
+    /// 用书签包住整段
+    pub fn bookmark(&mut self, bookmark: Bookmark) -> &mut Self {
+        self.bookmark = Some(bookmark);
+        self
+    }
+
+    /// 四周加颜色为 `color` 的边框
+    pub fn border(&mut self, color: &str) -> &mut Self {
+        self.border = Some(color.to_string());
+        self
+    }
+
+    /// 添加制表位
+    pub fn tab_stop(&mut self, tab: TabStop) -> &mut Self {
+        self.tabs.push(tab);
+        self
+    }
+
+    /// 设置是否与下一段保持在同一页
+    pub fn keep_next(&mut self, keep_next: bool) -> &mut Self {
+        self.keep_next = keep_next;
+        self
+    }
+
+    /// 设置是否段内不分页
+    pub fn keep_lines(&mut self, keep_lines: bool) -> &mut Self {
+        self.keep_lines = keep_lines;
+        self
+    }
+
     /// 转换为 docx-rs 的 Paragraph
     pub fn to_docx_paragraph(
         &self,
@@ -1464,6 +1828,9 @@ impl Paragraph {
                             if let Some(sp) = &paragraph_style.spacing {
                                 paragraph = paragraph.line_spacing(sp.to_docx_line_spacing());
                             }
+                            for tab in &paragraph_style.tabs {
+                                paragraph = paragraph.add_tab(tab.to_docx_tab());
+                            }
                         }
                     }
                 }
@@ -1629,10 +1996,93 @@ impl Paragraph {
             paragraph = paragraph.outline_lvl(level);
         }
 
-        for run in &self.runs {
-            paragraph = paragraph.add_run(run.to_docx_run(mstyles.clone(), footnotes.clone()));
+        for tab in &self.tabs {
+            paragraph = paragraph.add_tab(tab.to_docx_tab());
         }
 
+        if self.keep_next {
+            paragraph = paragraph.keep_next(true);
+        }
+        if self.keep_lines {
+            paragraph = paragraph.keep_lines(true);
+        }
+
+        if self.change_bar {
+            paragraph = paragraph.set_border(
+                docx_rs::ParagraphBorder::new(docx_rs::ParagraphBorderPosition::Left)
+                    .size(12)
+                    .space(12)
+                    .color("000000"),
+            );
+        }
+
+        if let Some(color) = &self.border {
+            let color = color.trim_start_matches('#');
+            for position in [
+                docx_rs::ParagraphBorderPosition::Top,
+                docx_rs::ParagraphBorderPosition::Left,
+                docx_rs::ParagraphBorderPosition::Bottom,
+                docx_rs::ParagraphBorderPosition::Right,
+            ] {
+                paragraph = paragraph.set_border(
+                    docx_rs::ParagraphBorder::new(position)
+                        .size(8)
+                        .space(4)
+                        .color(color),
+                );
+            }
+        }
+
+        if let Some(bookmark) = &self.bookmark {
+            paragraph = paragraph.add_bookmark_start(bookmark.id, &bookmark.name);
+        }
+
+        for run in &self.runs {
+            // 批注引用运行：批注范围只包住注释所在的位置，不扩展到整个段落
+            if let RunType::Text(TextRun {
+                comment: Some(comment),
+                ..
+            }) = run
+            {
+                paragraph = paragraph
+                    .add_comment_start(comment.to_docx_comment(mstyles.clone(), footnotes.clone()))
+                    .add_comment_end(comment.id);
+                continue;
+            }
+            let docx_run = run.to_docx_run(mstyles.clone(), footnotes.clone());
+            let (bookmark, link) = match run {
+                RunType::Text(text_run) => (text_run.bookmark.as_ref(), text_run.hyperlink()),
+                _ => (None, None),
+            };
+            if let Some(bookmark) = bookmark {
+                paragraph = paragraph.add_bookmark_start(bookmark.id, &bookmark.name);
+            }
+            paragraph = match &self.revision {
+                Some(revision) if revision.kind == RevisionKind::Insert => paragraph.add_insert(
+                    docx_rs::Insert::new(docx_run)
+                        .author(&revision.author)
+                        .date(&revision.date),
+                ),
+                Some(revision) => paragraph.add_delete(
+                    docx_rs::Delete::new()
+                        .add_run(to_delete_run(docx_run))
+                        .author(&revision.author)
+                        .date(&revision.date),
+                ),
+                None => match link {
+                    Some((target, link_type)) => paragraph.add_hyperlink(
+                        docx_rs::Hyperlink::new(target, link_type).add_run(docx_run),
+                    ),
+                    None => paragraph.add_run(docx_run),
+                },
+            };
+            if let Some(bookmark) = bookmark {
+                paragraph = paragraph.add_bookmark_end(bookmark.id);
+            }
+        }
+        if let Some(bookmark) = &self.bookmark {
+            paragraph = paragraph.add_bookmark_end(bookmark.id);
+        }
         if let Some(spacing) = &self.spacing {
             // 应用行距和段落间距设置
             paragraph = paragraph.line_spacing(spacing.to_docx_line_spacing());
@@ -1651,6 +2101,21 @@ pub trait RunTrait {
     ) -> docx_rs::Run;
 }
 
+/// 删除修订中的文本要用 w:delText 而不是 w:t
+fn to_delete_run(mut run: docx_rs::Run) -> docx_rs::Run {
+    run.children = run
+        .children
+        .into_iter()
+        .map(|child| match child {
+            docx_rs::RunChild::Text(text) => {
+                docx_rs::RunChild::DeleteText(docx_rs::DeleteText::new(&text.text))
+            }
+            child => child,
+        })
+        .collect();
+    run
+}
+
 /// 运行类型枚举
 #[derive(Debug, Clone)]
 pub enum RunType {
@@ -1658,6 +2123,7 @@ pub enum RunType {
     Break(BreakRun),
     Hyperlink(HyperlinkRun),
     PageNumber(PageNumberRun),
+    Image(ImageRun),
 }
 
 impl RunTrait for RunType {
@@ -1671,6 +2137,7 @@ impl RunTrait for RunType {
             RunType::Break(run) => run.to_docx_run(mstyles, footnotes),
             RunType::Hyperlink(run) => run.to_docx_run(mstyles, footnotes),
             RunType::PageNumber(run) => run.to_docx_run(mstyles, footnotes),
+            RunType::Image(run) => run.to_docx_run(mstyles, footnotes),
         }
     }
 }
@@ -1684,6 +2151,31 @@ pub struct TextRun {
     pub children: Vec<RunType>,
     pub footnote_id: Option<usize>, // 脚注ID，如果是脚注引用则设置此值
     pub footnote_content: Option<Vec<Paragraph>>, // 脚注内容（已格式化的运行）
+    pub comment: Option<Comment>, // 批注锚点，如果是批注引用则设置此值，批注范围为该运行所在的位置
+    /// 包住该运行的书签，作为文档内超链接的目标
+    pub bookmark: Option<Bookmark>,
+    /// 指向的书签名，设置后该运行是文档内超链接
+    pub link: Option<String>,
+    /// 外部链接地址，设置后该运行是指向网址或邮箱的超链接
+    pub url: Option<String>,
+}
+
+/// 书签，作为文档内超链接的目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    /// 书签 id，文档内唯一
+    pub id: usize,
+    /// 书签名，超链接通过名称指向书签
+    pub name: String,
+}
+
+impl Bookmark {
+    pub fn new(id: usize, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+        }
+    }
 }
 
 impl TextRun {
@@ -1696,6 +2188,10 @@ impl TextRun {
             children: Vec::new(),
             footnote_id: None,
             footnote_content: None,
+            comment: None,
+            bookmark: None,
+            link: None,
+            url: None,
         }
     }
 
@@ -1708,6 +2204,10 @@ impl TextRun {
             children: Vec::new(),
             footnote_id: None,
             footnote_content: None,
+            comment: None,
+            bookmark: None,
+            link: None,
+            url: None,
         }
     }
 
@@ -1724,6 +2224,44 @@ impl TextRun {
             children: Vec::new(),
             footnote_id: Some(footnote_id),
             footnote_content: Some(footnote_content),
+            comment: None,
+            bookmark: None,
+            link: None,
+            url: None,
+        }
+    }
+
+    /// 创建批注引用运行，批注内容之后通过 [`Section::fill_comments`] 填入
+    pub fn comment_reference(comment_id: usize) -> Self {
+        let mut run = Self::new("");
+        run.comment = Some(Comment::new(comment_id));
+        run
+    }
+
+    /// 用书签包住该运行
+    pub fn bookmark(mut self, bookmark: Bookmark) -> Self {
+        self.bookmark = Some(bookmark);
+        self
+    }
+
+    /// 链接到名为 `name` 的书签
+    pub fn link_to(mut self, name: &str) -> Self {
+        self.link = Some(name.to_string());
+        self
+    }
+
+    /// 链接到外部地址 `url`
+    pub fn link_to_url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    // 超链接的目标，书签优先于外部地址
+    fn hyperlink(&self) -> Option<(&str, docx_rs::HyperlinkType)> {
+        match (&self.link, &self.url) {
+            (Some(anchor), _) => Some((anchor, docx_rs::HyperlinkType::Anchor)),
+            (None, Some(url)) => Some((url, docx_rs::HyperlinkType::External)),
+            (None, None) => None,
         }
     }
 
@@ -1816,7 +2354,7 @@ impl RunTrait for TextRun {
         if let (Some(footnote_id), Some(footnote_content)) =
             (&self.footnote_id, &self.footnote_content)
         {
-            println!(
+            debug_log!(
                 "【to_docx_run】创建脚注引用，ID: {}, 内容段落数: {}, 收集到脚注：{}",
                 footnote_id,
                 footnote_content.len(),
@@ -1853,7 +2391,7 @@ impl RunTrait for TextRun {
                 // 如果没有内容，添加默认内容（不设置样式，由外层控制）
                 // 从footnotes找到与 footnote_id 匹配的脚注
                 if let Some(fnote) = footnotes.get(footnote_id) {
-                    println!(
+                    debug_log!(
                         "【to_docx_run】基q全局收集创建脚注引用，ID: {}, 内容段落数: {}",
                         footnote_id,
                         fnote.children.len()
@@ -1907,8 +2445,15 @@ impl RunTrait for TextRun {
             run = run.add_break(crate::docx::adapter::BreakType::TextWrapping.to_docx_break());
         }
 
-        // 添加文本
-        run = run.add_text(&self.text);
+        // 添加文本，制表符输出为 w:tab
+        for (i, part) in self.text.split('\t').enumerate() {
+            if i > 0 {
+                run = run.add_tab();
+            }
+            if i == 0 || !part.is_empty() {
+                run = run.add_text(part);
+            }
+        }
 
         // 如果需要在文本后添加换行符
         if self.props.break_type.is_some() {
@@ -1950,6 +2495,32 @@ impl RunTrait for TextRun {
             }
         }
 
+        if let Some(true) = self.props.strike {
+            run = run.strike();
+        }
+
+        if let Some(highlight) = &self.props.highlight {
+            run = run.highlight(highlight.clone());
+        }
+
+        if let Some(shading) = &self.props.shading {
+            run = run.shading(
+                docx_rs::Shading::new()
+                    .shd_type(docx_rs::ShdType::Clear)
+                    .color("auto")
+                    .fill(shading.trim_start_matches('#').to_uppercase()),
+            );
+        }
+
+        if let Some(border) = &self.props.border {
+            run = run.text_border(
+                docx_rs::TextBorder::new()
+                    .border_type(docx_rs::BorderType::Single)
+                    .size(4)
+                    .color(border.trim_start_matches('#').to_uppercase()),
+            );
+        }
+
         if let Some(color) = &self.props.color {
             if color.starts_with("#") {
                 run = run.color(color[1..].to_uppercase());
@@ -2194,6 +2765,51 @@ impl PageNumberRun {
     }
 }
 
+/// 图片运行（嵌入式图片）
+#[derive(Debug, Clone)]
+pub struct ImageRun {
+    /// 图片文件内容（png/jpeg 等）
+    pub data: Vec<u8>,
+    /// 宽度（英寸）
+    pub width: Option<f32>,
+    /// 高度（英寸）
+    pub height: Option<f32>,
+}
+
+impl ImageRun {
+    /// 创建新的图片运行，宽高都不指定时使用图片原始尺寸，只指定一个时按原图比例缩放
+    pub fn new(data: Vec<u8>, width: Option<f32>, height: Option<f32>) -> Self {
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+}
+
+impl RunTrait for ImageRun {
+    fn to_docx_run(
+        &self,
+        _mstyles: Option<Styles>,
+        _footnotes: HashMap<usize, Footnote>,
+    ) -> docx_rs::Run {
+        const EMU_PER_INCH: f32 = 914400.0;
+        let pic = docx_rs::Pic::new(&self.data);
+        let (origin_width, origin_height) = (pic.size.0 as f32, pic.size.1 as f32);
+        let (width, height) = match (self.width, self.height) {
+            (Some(w), Some(h)) => (w * EMU_PER_INCH, h * EMU_PER_INCH),
+            (Some(w), None) if origin_width > 0.0 => {
+                (w * EMU_PER_INCH, w * EMU_PER_INCH * origin_height / origin_width)
+            }
+            (None, Some(h)) if origin_height > 0.0 => {
+                (h * EMU_PER_INCH * origin_width / origin_height, h * EMU_PER_INCH)
+            }
+            _ => (origin_width, origin_height),
+        };
+        docx_rs::Run::new().add_image(pic.size(width as u32, height as u32))
+    }
+}
+
 impl RunTrait for PageNumberRun {
     fn to_docx_run(
         &self,
@@ -2640,3 +3256,340 @@ impl TableCell {
         cell
     }
 }
+
+/// 需要在打包后直接写入 document.xml 的节属性（docx-rs 不支持 w:lnNumType 和页码格式）
+#[derive(Debug, Clone, Copy, Default)]
+struct SectionXmlPatch {
+    /// 行号间隔
+    line_numbers: Option<u32>,
+    /// 页码使用小写罗马数字
+    lower_roman: bool,
+}
+
+impl SectionXmlPatch {
+    fn of(section: &Section) -> Self {
+        let lower_roman = section
+            .properties
+            .page
+            .as_ref()
+            .and_then(|page| page.page_numbers.as_ref())
+            .is_some_and(|page_numbers| page_numbers.format == PageNumberFormat::LowerRoman);
+        Self {
+            line_numbers: section.properties.line_numbers,
+            lower_roman,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.line_numbers.is_none() && !self.lower_roman
+    }
+}
+
+/// 改写打包好的 .docx 中的 XML 部件，`rewrite` 返回 None 的部件保持不变
+fn rewrite_parts(
+    bytes: Vec<u8>,
+    rewrite: impl Fn(&str, &str) -> Option<String>,
+) -> DocxAdapterResult<Vec<u8>> {
+    use std::io::{Read, Write};
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_string();
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        if name.ends_with(".xml") {
+            if let Some(xml) = rewrite(&name, &String::from_utf8_lossy(&content)) {
+                content = xml.into_bytes();
+            }
+        }
+        writer.start_file(name, zip::write::FileOptions::default())?;
+        writer.write_all(&content)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+/// 确定性输出使用的固定时间，也是 zip 能记录的最早时间
+pub const DETERMINISTIC_TIMESTAMP: &str = "1980-01-01T00:00:00Z";
+
+lazy_static::lazy_static! {
+    static ref CORE_DATE_REGEX: regex::Regex =
+        regex::Regex::new(r"(<dcterms:(?:created|modified)[^>]*>)[^<]*(</dcterms:)").unwrap();
+    static ref PARA_ID_REGEX: regex::Regex =
+        regex::Regex::new(r#"(w1[45]:(?:paraId|textId|paraIdParent))="([0-9A-Fa-f]+)""#).unwrap();
+    static ref FOOTNOTE_REFERENCE_REGEX: regex::Regex =
+        regex::Regex::new(r"<w:footnoteReference\s+([^>]*?)/>").unwrap();
+    static ref GUID_REGEX: regex::Regex = regex::Regex::new(
+        r"\{[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\}"
+    )
+    .unwrap();
+}
+
+/// 重新打包为确定的内容：条目按名称排序（`[Content_Types].xml` 在最前），
+/// 修改时间和 docProps/core.xml 中的创建、修改日期固定为 `timestamp`；段落 id 和 GUID 按出现的先后重新编号，
+/// 编号在所有部件间一致，不影响部件之间的引用
+fn normalize_package(bytes: Vec<u8>, timestamp: &str) -> DocxAdapterResult<Vec<u8>> {
+    use std::io::{Read, Write};
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        entries.push((file.name().to_string(), content));
+    }
+    entries.sort_by(|(a, _), (b, _)| {
+        (a != "[Content_Types].xml", a).cmp(&(b != "[Content_Types].xml", b))
+    });
+
+    let mut para_ids: HashMap<String, String> = HashMap::new();
+    let mut guids: HashMap<String, String> = HashMap::new();
+    let options = zip::write::FileOptions::default().last_modified_time(zip_time(timestamp));
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, mut content) in entries {
+        if name.ends_with(".xml") || name.ends_with(".rels") {
+            let xml = String::from_utf8_lossy(&content).into_owned();
+            let xml = CORE_DATE_REGEX.replace_all(&xml, format!("${{1}}{}${{2}}", timestamp));
+            let xml = PARA_ID_REGEX.replace_all(&xml, |caps: &regex::Captures| {
+                let next = para_ids.len() + 1;
+                let id = para_ids
+                    .entry(caps[2].to_uppercase())
+                    .or_insert_with(|| format!("{:08X}", next));
+                format!("{}=\"{}\"", &caps[1], id)
+            });
+            let xml = GUID_REGEX.replace_all(&xml, |caps: &regex::Captures| {
+                let next = guids.len() + 1;
+                guids
+                    .entry(caps[0].to_uppercase())
+                    .or_insert_with(|| format!("{{00000000-0000-0000-0000-{:012X}}}", next))
+                    .clone()
+            });
+            content = xml.into_owned().into_bytes();
+        }
+        writer.start_file(name, options)?;
+        writer.write_all(&content)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+/// 把 RFC 3339 时间转换为压缩包条目的修改时间，zip 无法记录的时间（1980 年以前等）使用 1980-01-01
+fn zip_time(timestamp: &str) -> zip::DateTime {
+    use chrono::{Datelike, Timelike};
+
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .and_then(|time| {
+            zip::DateTime::from_date_and_time(
+                u16::try_from(time.year()).ok()?,
+                time.month() as u8,
+                time.day() as u8,
+                time.hour() as u8,
+                time.minute() as u8,
+                time.second() as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_default()
+}
+
+/// 按 `patches[k]` 修改第 k 个 `w:sectPr`：插入 `w:lnNumType`，为 `w:pgNumType` 加上小写罗马数字格式，
+/// 新增的子元素位置遵循 sectPr 子元素的顺序
+fn patch_section_properties(xml: &str, patches: &[SectionXmlPatch]) -> String {
+    // 排在 lnNumType 之后的子元素
+    const FOLLOWING: [&str; 11] = [
+        "<w:pgNumType",
+        "<w:cols",
+        "<w:formProt",
+        "<w:vAlign",
+        "<w:noEndnote",
+        "<w:titlePg",
+        "<w:textDirection",
+        "<w:bidi",
+        "<w:rtlGutter",
+        "<w:docGrid",
+        "<w:printerSettings",
+    ];
+
+    let mut result = String::with_capacity(xml.len());
+    let mut rest = xml;
+    let mut index = 0;
+    while let Some(start) = rest.find("<w:sectPr") {
+        let Some(end) = rest[start..].find("</w:sectPr>").map(|end| start + end) else {
+            break;
+        };
+        let section = &rest[start..end];
+        let patch = patches.get(index).copied().unwrap_or_default();
+        let insert_at = FOLLOWING
+            .iter()
+            .filter_map(|tag| section.find(tag))
+            .min()
+            .unwrap_or(section.len());
+        result.push_str(&rest[..start + insert_at]);
+        if let Some(count_by) = patch.line_numbers {
+            result.push_str(&format!(
+                "<w:lnNumType w:countBy=\"{}\" w:distance=\"360\" w:restart=\"continuous\" />",
+                count_by.max(1)
+            ));
+        }
+        let mut following = section[insert_at..].to_string();
+        if patch.lower_roman {
+            match following.find("<w:pgNumType") {
+                Some(at) => {
+                    following.insert_str(at + "<w:pgNumType".len(), " w:fmt=\"lowerRoman\"")
+                }
+                None => result.push_str("<w:pgNumType w:fmt=\"lowerRoman\" />"),
+            }
+        }
+        result.push_str(&following);
+        rest = &rest[end..];
+        index += 1;
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 在每个 `w:sectPr` 中加入脚注的编号格式和重新编号方式（`w:footnotePr`），参考标记格式时
+/// 脚注引用改为自定义标记 ※
+fn patch_footnote_references(xml: &str, layout: FootnoteLayout) -> String {
+    // 排在 footnotePr 之后的子元素
+    const FOLLOWING: [&str; 6] = [
+        "<w:endnotePr",
+        "<w:type",
+        "<w:pgSz",
+        "<w:pgMar",
+        "<w:paperSrc",
+        "<w:cols",
+    ];
+
+    let num_fmt = match layout.format {
+        FootnoteFormat::Decimal | FootnoteFormat::ReferenceMark => None,
+        FootnoteFormat::LowerLetter => Some("lowerLetter"),
+        FootnoteFormat::LowerRoman => Some("lowerRoman"),
+    };
+    let mut properties = String::new();
+    if let Some(num_fmt) = num_fmt {
+        properties.push_str(&format!("<w:numFmt w:val=\"{}\" />", num_fmt));
+    }
+    if layout.restart_each_page {
+        properties.push_str("<w:numRestart w:val=\"eachPage\" />");
+    }
+
+    let mut result = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while !properties.is_empty() {
+        let Some(start) = rest.find("<w:sectPr") else {
+            break;
+        };
+        let Some(end) = rest[start..].find("</w:sectPr>").map(|end| start + end) else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let section = &rest[start..end];
+        let insert_at = FOLLOWING
+            .iter()
+            .filter_map(|tag| section.find(tag))
+            .min()
+            .unwrap_or(section.len());
+        result.push_str(&section[..insert_at]);
+        result.push_str("<w:footnotePr>");
+        result.push_str(&properties);
+        result.push_str("</w:footnotePr>");
+        result.push_str(&section[insert_at..]);
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+
+    if layout.format == FootnoteFormat::ReferenceMark {
+        result = FOOTNOTE_REFERENCE_REGEX
+            .replace_all(
+                &result,
+                "<w:footnoteReference w:customMarkFollows=\"1\" ${1}/><w:t>※</w:t>",
+            )
+            .into_owned();
+    }
+    result
+}
+
+/// 去掉脚注上方的分隔线：已有的分隔线脚注清空，没有时加入空的分隔线脚注（否则 Word 使用默认的分隔线）；
+/// 参考标记格式时脚注正文中的编号也改为 ※
+fn patch_footnotes(xml: &str, layout: FootnoteLayout) -> String {
+    let mut result = xml.to_string();
+    if !layout.separator {
+        if result.contains("w:type=\"separator\"") {
+            result = result
+                .replace("<w:separator/>", "")
+                .replace("<w:separator />", "")
+                .replace("<w:continuationSeparator/>", "")
+                .replace("<w:continuationSeparator />", "");
+        } else if let Some(open) = result.find("<w:footnotes") {
+            if let Some(open_end) = result[open..].find('>').map(|end| open + end + 1) {
+                result.insert_str(
+                    open_end,
+                    "<w:footnote w:type=\"separator\" w:id=\"-1\"><w:p /></w:footnote>\
+                     <w:footnote w:type=\"continuationSeparator\" w:id=\"0\"><w:p /></w:footnote>",
+                );
+            }
+        }
+    }
+    if layout.format == FootnoteFormat::ReferenceMark {
+        result = result
+            .replace("<w:footnoteRef/>", "<w:t>※</w:t>")
+            .replace("<w:footnoteRef />", "<w:t>※</w:t>");
+    }
+    result
+}
+
+/// 在 settings.xml 中加入 `w:writeProtection`（建议只读）或 `w:documentProtection`（编辑限制），
+/// 位置遵循 settings 子元素的顺序
+fn insert_protection(xml: &str, protection: DocumentProtection) -> String {
+    // 排在 documentProtection 之后、docx-rs 会写出的子元素
+    const FOLLOWING: [&str; 7] = [
+        "<w:defaultTabStop",
+        "<w:evenAndOddHeaders",
+        "<w:characterSpacingControl",
+        "<w:compat",
+        "<w:docVars",
+        "<w:rsids",
+        "<w:themeFontLang",
+    ];
+
+    let Some(settings) = xml.find("<w:settings") else {
+        return xml.to_string();
+    };
+    let Some(settings_end) = xml[settings..].find('>').map(|end| settings + end + 1) else {
+        return xml.to_string();
+    };
+    let (insert_at, element) = match protection {
+        // writeProtection 是 settings 的第一个子元素
+        DocumentProtection::ReadOnlyRecommended => (
+            settings_end,
+            "<w:writeProtection w:recommended=\"1\" />".to_string(),
+        ),
+        _ => {
+            let edit = match protection {
+                DocumentProtection::Comments => "comments",
+                DocumentProtection::TrackedChanges => "trackedChanges",
+                _ => "readOnly",
+            };
+            let insert_at = FOLLOWING
+                .iter()
+                .filter_map(|tag| xml[settings_end..].find(tag))
+                .min()
+                .map(|at| settings_end + at)
+                .or_else(|| xml.rfind("</w:settings>"))
+                .unwrap_or(xml.len());
+            (
+                insert_at,
+                format!(
+                    "<w:documentProtection w:edit=\"{}\" w:enforcement=\"1\" />",
+                    edit
+                ),
+            )
+        }
+    };
+    let mut result = xml.to_string();
+    result.insert_str(insert_at, &element);
+    result
+}
@@ -2,6 +2,7 @@
 //!
 //! 该模块提供了与原始 TypeScript 版本 docxmaker.ts 兼容的 API
 
+use crate::logging::ExportTimings;
 use docx_rs;
 use std::collections::HashMap;
 use thiserror::Error;
@@ -284,6 +285,53 @@ impl WidthTypeConst {
     pub const PERCENTAGE: WidthType = WidthType::Percentage;
 }
 
+/// 制表位的对齐方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabAlignment {
+    /// 左对齐，文字从制表位开始
+    Left,
+    /// 居中，文字以制表位为中心
+    Center,
+    /// 右对齐，文字在制表位处结束
+    Right,
+}
+
+impl TabAlignment {
+    /// 转换为 docx-rs 的 TabValueType
+    pub fn to_docx_tab_value(&self) -> docx_rs::TabValueType {
+        match self {
+            TabAlignment::Left => docx_rs::TabValueType::Left,
+            TabAlignment::Center => docx_rs::TabValueType::Center,
+            TabAlignment::Right => docx_rs::TabValueType::Right,
+        }
+    }
+}
+
+/// 制表位的前导符，填充制表符跳过的空白
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabLeader {
+    /// 无前导符
+    None,
+    /// 点线
+    Dot,
+    /// 短横线
+    Hyphen,
+    /// 下划线
+    Underscore,
+}
+
+impl TabLeader {
+    /// 转换为 docx-rs 的 TabLeaderType
+    pub fn to_docx_tab_leader(&self) -> docx_rs::TabLeaderType {
+        match self {
+            TabLeader::None => docx_rs::TabLeaderType::None,
+            TabLeader::Dot => docx_rs::TabLeaderType::Dot,
+            TabLeader::Hyphen => docx_rs::TabLeaderType::Hyphen,
+            TabLeader::Underscore => docx_rs::TabLeaderType::Underscore,
+        }
+    }
+}
+
 /// 页码
 pub struct PageNumber;
 
@@ -348,6 +396,14 @@ pub struct RunProps {
     pub bold: Option<bool>,
     pub italic: Option<bool>,
     pub underline: Option<UnderlineType>,
+    /// 删除线
+    pub strike: Option<bool>,
+    /// 高亮颜色（Word 的高亮色名，如 "yellow"）
+    pub highlight: Option<String>,
+    /// 底纹颜色（#RRGGBB）
+    pub shading: Option<String>,
+    /// 文字边框颜色（#RRGGBB）
+    pub border: Option<String>,
     pub color: Option<String>,
     pub character_spacing: Option<i32>,
     pub superscript: Option<bool>,
@@ -464,6 +520,12 @@ pub struct StyleStash {
     pub bold: bool,
     pub italic: bool,
     pub underline: bool,
+    /// 删除线
+    pub strikethrough: bool,
+    /// 高亮
+    pub highlight: bool,
+    /// 彩色文字的颜色（#RRGGBB）
+    pub text_color: Option<String>,
     pub override_color: Option<String>,
     pub italic_global: bool,
     pub italic_dynamic: bool,
@@ -478,6 +540,9 @@ impl Default for StyleStash {
             bold: false,
             italic: false,
             underline: false,
+            strikethrough: false,
+            highlight: false,
+            text_color: None,
             override_color: None,
             italic_global: false,
             italic_dynamic: false,
@@ -508,6 +573,8 @@ pub struct DocxStats {
     pub page_count: usize,
     pub page_count_real: usize,
     pub line_map: HashMap<usize, LineStruct>,
+    /// 排版与保存的耗时
+    pub timings: ExportTimings,
 }
 
 /// DOCX Base64 结果
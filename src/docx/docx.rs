@@ -1,9 +1,18 @@
+use crate::diff::ChangeKind;
+use crate::logging::{debug_log, ExportTimings};
 use crate::models::{Conf, ScriptToken};
 use crate::parser::ParseOutput;
+use crate::progress::Progress;
+use crate::render::{HtmlBackend, OdtBackend, PdfBackend, RenderBackend};
 use std::collections::HashMap;
 
-use super::docx_maker::{generate_docx, DocxError, DocxOptions, DocxResult, PrintProfile};
-use super::metadata_extractor::{extract_metadata_from_parsed_document, ExtractedMetadata};
+use super::adapter::docx::{Revision, RevisionKind};
+use super::docx_maker::{
+    export_timestamp, generate_docx, DocxError, DocxOptions, DocxResult, PrintProfile,
+};
+use super::metadata_extractor::{
+    extract_metadata_from_parsed_document, parse_metadata_bool, ExtractedMetadata,
+};
 
 /// DOCX生成错误
 pub type DocxGenerateError = DocxError;
@@ -17,6 +26,33 @@ pub struct DocxStats {
     pub page_count: u32,
     pub page_count_real: u32,
     pub line_map: HashMap<u32, LineStruct>,
+    /// 排版与保存的耗时
+    pub timings: ExportTimings,
+}
+
+impl From<super::adapter::DocxStats> for DocxStats {
+    fn from(stats: super::adapter::DocxStats) -> Self {
+        DocxStats {
+            page_count: stats.page_count as u32,
+            page_count_real: stats.page_count_real as u32,
+            line_map: stats
+                .line_map
+                .into_iter()
+                .map(|(k, v)| {
+                    (
+                        k as u32,
+                        LineStruct {
+                            sections: v.sections,
+                            scene: v.scene,
+                            page: v.page as u32,
+                            cumulative_duration: v.cumulative_duration,
+                        },
+                    )
+                })
+                .collect(),
+            timings: stats.timings,
+        }
+    }
 }
 
 /// 行结构信息
@@ -47,40 +83,379 @@ pub struct DocxAsBase64 {
 ///
 /// 如果`output_path`为"$STATS$"，则返回统计信息
 /// 如果`output_path`为"$PREVIEW$"，则返回Base64编码的文档
-/// 否则，生成DOCX文件并返回Ok(())
+/// 否则，生成DOCX文件并返回这次排版的统计信息
 pub async fn generate_docx_document(
     output_path: &str,
     config: &Conf,
     parsed_document: &ParseOutput,
 ) -> DocxGenerateResult<Option<DocxStats>> {
-    println!("【generate_docx_document】开始生成 DOCX 文档");
-    println!("【generate_docx_document】文件路径: {}", output_path);
+    generate_docx_with_mode(output_path, config, parsed_document, ExportMode::default()).await
+}
+
+/// 与 `generate_docx_document` 相同，另外返回排版和保存的耗时（`parse` 为零，由调用方填写）
+pub async fn generate_docx_document_timed(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<(Option<DocxStats>, ExportTimings)> {
+    generate_docx_timed(output_path, config, parsed_document, ExportMode::default()).await
+}
+
+/// 与 `generate_docx_document` 相同，排版和保存时通过 `progress` 报告进度；
+/// 被取消时返回 `DocxError::Cancelled`，不会写出文件
+pub async fn generate_docx_document_with_progress(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    progress: Progress,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    let mode = ExportMode {
+        progress,
+        ..ExportMode::default()
+    };
+    generate_docx_with_mode(output_path, config, parsed_document, mode).await
+}
+
+/// 生成大纲DOCX文档（分场大纲）
+///
+/// 只输出章节、场景标题和概要，不包含对白和动作，沿用正文中章节的样式。
+/// 无论配置和标题页中是否关闭了章节的打印，大纲中都会输出章节；
+/// 概要是否输出由 `Conf::print_synopsis_outline` 决定，样式可由 `Conf::outline_synopsis_style` 单独设置。
+///
+/// `output_path` 的含义与 [`generate_docx_document`] 相同
+pub async fn generate_docx_outline(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    let mode = ExportMode {
+        outline_only: true,
+        ..ExportMode::default()
+    };
+    generate_docx_with_mode(output_path, config, parsed_document, mode).await
+}
+
+/// 生成试镜台词本（sides）DOCX文档
+///
+/// 只输出 `characters` 中的角色出场的场景，这些角色的对白加粗，其他角色的对白淡化为灰色。
+///
+/// `output_path` 的含义与 [`generate_docx_document`] 相同
+pub async fn generate_docx_sides(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    characters: &[String],
+) -> DocxGenerateResult<Option<DocxStats>> {
+    let mode = ExportMode {
+        sides_characters: characters.to_vec(),
+        ..ExportMode::default()
+    };
+    generate_docx_with_mode(output_path, config, parsed_document, mode).await
+}
+
+/// 生成带修订线的新稿DOCX文档
+///
+/// 与 `old_document` 对比，新稿中新增或改动的段落左侧加修订线（change bar）。
+///
+/// `output_path` 的含义与 [`generate_docx_document`] 相同
+pub async fn generate_docx_diff(
+    output_path: &str,
+    config: &Conf,
+    old_document: &ParseOutput,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    let changed_lines = crate::diff::compare(old_document, parsed_document).changed_lines();
+    let mode = ExportMode {
+        changed_lines,
+        ..ExportMode::default()
+    };
+    generate_docx_with_mode(output_path, config, parsed_document, mode).await
+}
+
+/// 生成修订模式的DOCX文档
+///
+/// 与 `old_document` 对比，新稿中新增的段落标记为修订插入，旧稿中被删除的段落插回原位并标记为修订删除，
+/// 在 Word 中可以逐条接受或拒绝修订。修订者为 `author`。
+///
+/// `output_path` 的含义与 [`generate_docx_document`] 相同
+pub async fn generate_docx_revisions(
+    output_path: &str,
+    config: &Conf,
+    old_document: &ParseOutput,
+    parsed_document: &ParseOutput,
+    author: &str,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    let revised = crate::diff::revised_draft(old_document, parsed_document);
+    let date = export_timestamp(config);
+    let revisions = revised
+        .revisions
+        .into_iter()
+        .map(|(line, kind)| {
+            let kind = if kind == ChangeKind::Removed {
+                RevisionKind::Delete
+            } else {
+                RevisionKind::Insert
+            };
+            (
+                line,
+                Revision {
+                    kind,
+                    author: author.to_string(),
+                    date: date.clone(),
+                },
+            )
+        })
+        .collect();
+    let mode = ExportMode {
+        revisions,
+        ..ExportMode::default()
+    };
+    generate_docx_with_mode(output_path, config, &revised.output, mode).await
+}
+
+/// 生成 A/V 两栏格式的DOCX文档
+///
+/// 广告、纪录片常用的格式：每个场景一张两栏表格，左栏为画面（动作、转场），
+/// 右栏为声音（对白、歌词，以及以 SFX、MUSIC、音效等开头的动作行）。
+///
+/// `output_path` 的含义与 [`generate_docx_document`] 相同
+pub async fn generate_docx_av(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    let mode = ExportMode {
+        av_script: true,
+        ..ExportMode::default()
+    };
+    generate_docx_with_mode(output_path, config, parsed_document, mode).await
+}
+
+/// 生成ODT文档（OpenDocument，供 LibreOffice 使用）
+///
+/// 排版与 [`generate_docx_document`] 完全相同，只是最后序列化为 .odt；
+/// `output_path` 为 "$STATS$" 时同样返回统计信息，"$PREVIEW$" 时返回的预览仍是 DOCX。
+pub async fn generate_odt_document(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    generate_document_with_backend(output_path, config, parsed_document, Box::new(OdtBackend))
+        .await
+}
+
+/// 生成PDF文档，排版与 [`generate_docx_document`] 相同，折行和分页由 PDF 后端完成
+pub async fn generate_pdf_document(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    generate_document_with_backend(output_path, config, parsed_document, Box::new(PdfBackend))
+        .await
+}
+
+/// 用指定的导出后端生成文档
+///
+/// 排版只做一次，由 `backend` 序列化为对应格式，保证各格式的分页和样式一致。
+pub async fn generate_document_with_backend(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    backend: Box<dyn RenderBackend>,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    let mode = ExportMode {
+        backend: Some(backend),
+        ..ExportMode::default()
+    };
+    generate_docx_with_mode(output_path, config, parsed_document, mode).await
+}
+
+/// 在内存中生成DOCX文档，返回文件内容，不写临时文件，适合 Web 服务直接返回给客户端
+pub async fn generate_docx_bytes(
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Vec<u8>> {
+    generate_bytes_with_mode(config, parsed_document, ExportMode::default()).await
+}
+
+/// 与 `generate_docx_bytes` 相同，另外返回行处理、排版和打包的耗时（`parse` 为零，由调用方填写）
+pub async fn generate_docx_bytes_timed(
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<(Vec<u8>, ExportTimings)> {
+    generate_bytes_timed(config, parsed_document, ExportMode::default()).await
+}
+
+/// 在内存中生成ODT文档，排版与 [`generate_docx_bytes`] 相同
+pub async fn generate_odt_bytes(
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Vec<u8>> {
+    generate_bytes_with_backend(config, parsed_document, Box::new(OdtBackend)).await
+}
+
+/// 在内存中生成按打印排版的 HTML（UTF-8），分页处以分隔线表示
+pub async fn generate_html_bytes(
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Vec<u8>> {
+    generate_bytes_with_backend(config, parsed_document, Box::new(HtmlBackend)).await
+}
+
+/// 在内存中生成PDF文档
+pub async fn generate_pdf_bytes(
+    config: &Conf,
+    parsed_document: &ParseOutput,
+) -> DocxGenerateResult<Vec<u8>> {
+    generate_bytes_with_backend(config, parsed_document, Box::new(PdfBackend)).await
+}
+
+/// 用指定的导出后端在内存中生成文档，返回文件内容
+pub async fn generate_bytes_with_backend(
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    backend: Box<dyn RenderBackend>,
+) -> DocxGenerateResult<Vec<u8>> {
+    let mode = ExportMode {
+        backend: Some(backend),
+        ..ExportMode::default()
+    };
+    generate_bytes_with_mode(config, parsed_document, mode).await
+}
+
+async fn generate_bytes_with_mode(
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    mode: ExportMode,
+) -> DocxGenerateResult<Vec<u8>> {
+    generate_bytes_timed(config, parsed_document, mode)
+        .await
+        .map(|(data, _)| data)
+}
+
+async fn generate_bytes_timed(
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    mode: ExportMode,
+) -> DocxGenerateResult<(Vec<u8>, ExportTimings)> {
+    let (docx_options, backend) = docx_options_for("", config, parsed_document, mode);
+    let built = super::docx_maker::get_bytes(docx_options, backend).await?;
+    Ok((built.data, built.stats.timings))
+}
+
+/// 导出模式，决定对解析结果做哪些取舍和标注
+#[derive(Default)]
+struct ExportMode {
+    /// 大纲模式
+    outline_only: bool,
+    /// 试镜台词本的角色
+    sides_characters: Vec<String>,
+    /// 加修订线的行
+    changed_lines: Vec<usize>,
+    /// 修订模式中标记为插入或删除的行
+    revisions: HashMap<usize, Revision>,
+    /// A/V 两栏格式
+    av_script: bool,
+    /// 导出后端，为空时直接输出 DOCX
+    backend: Option<Box<dyn RenderBackend>>,
+    /// 进度回调与取消令牌
+    progress: Progress,
+}
+
+async fn generate_docx_with_mode(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    mode: ExportMode,
+) -> DocxGenerateResult<Option<DocxStats>> {
+    generate_docx_timed(output_path, config, parsed_document, mode)
+        .await
+        .map(|(stats, _)| stats)
+}
+
+async fn generate_docx_timed(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    mode: ExportMode,
+) -> DocxGenerateResult<(Option<DocxStats>, ExportTimings)> {
+    let (docx_options, backend) = docx_options_for(output_path, config, parsed_document, mode);
+
+    // 根据输出路径处理不同的情况
+    if output_path == "$STATS$" {
+        // 返回统计信息
+        debug_log!("【generate_docx_document】开始获取统计信息 - get_docx_stats 分支");
+        debug_log!(
+            "【generate_docx_document】title_page_processed = {}",
+            docx_options.title_page_processed
+        );
+        let stats = super::docx_maker::get_docx_stats(docx_options).await?;
+        let timings = stats.timings;
+        Ok((Some(stats.into()), timings))
+    } else if output_path == "$PREVIEW$" {
+        // 返回Base64编码的文档
+        debug_log!("【generate_docx_document】开始生成预览 - get_docx_base64 分支");
+        debug_log!(
+            "【generate_docx_document】title_page_processed = {}",
+            docx_options.title_page_processed
+        );
+        let base64_result = super::docx_maker::get_docx_base64(docx_options).await?;
+        let timings = base64_result.stats.timings;
+        Ok((Some(base64_result.stats.into()), timings))
+    } else {
+        // 生成DOCX文件
+        debug_log!("【generate_docx_document】开始生成 DOCX 文件 - get_docx 分支");
+        debug_log!(
+            "【generate_docx_document】title_page_processed = {}",
+            docx_options.title_page_processed
+        );
+        let stats = if let Some(backend) = backend {
+            super::docx_maker::get_rendered(docx_options, backend).await?
+        } else {
+            super::docx_maker::get_docx(docx_options).await?
+        };
+        debug_log!("【generate_docx_document】DOCX 文件生成完成");
+        let timings = stats.timings;
+        Ok((Some(stats.into()), timings))
+    }
+}
+
+// 由解析结果和导出模式生成排版选项，导出后端单独返回
+fn docx_options_for(
+    output_path: &str,
+    config: &Conf,
+    parsed_document: &ParseOutput,
+    mode: ExportMode,
+) -> (DocxOptions, Option<Box<dyn RenderBackend>>) {
+    let outline_only = mode.outline_only;
+    debug_log!("【generate_docx_document】开始生成 DOCX 文档");
+    debug_log!("【generate_docx_document】文件路径: {}", output_path);
 
     // 创建一个可变的解析结果副本
     let mut parsed_document_copy = parsed_document.clone();
 
-    println!(
+    debug_log!(
         "【generate_docx_document】标题页元素数量: {}",
         parsed_document_copy.title_page.len()
     );
     for (key, tokens) in &parsed_document_copy.title_page {
-        println!(
+        debug_log!(
             "【generate_docx_document】标题页元素 {}: {} 个 token",
             key,
             tokens.len()
         );
         if key == "cc" {
             for (i, token) in tokens.iter().enumerate() {
-                println!("【generate_docx_document】cc token {}: {}", i, token.text);
+                debug_log!("【generate_docx_document】cc token {}: {}", i, token.text);
             }
         }
     }
 
     // 提取元数据
-    println!("【generate_docx_document】开始从标题页提取元数据");
+    debug_log!("【generate_docx_document】开始从标题页提取元数据");
     let extracted_metadata =
         extract_metadata_from_parsed_document(&parsed_document_copy, &config.font_family);
-    println!("【generate_docx_document】元数据提取完成");
+    debug_log!("【generate_docx_document】元数据提取完成");
 
     let metadata = extracted_metadata.metadata;
     let watermark = extracted_metadata.watermark;
@@ -94,21 +469,31 @@ pub async fn generate_docx_document(
     let mut print_sections = config.print_sections.clone();
     let mut print_synopsis = config.print_synopsis.clone();
 
-    if let Some(print_sections_str) = metadata.get("print.print_sections") {
-        if let Ok(_value) = print_sections_str.parse::<f32>() {
-            print_sections = print_sections_str != "0";
-        }
+    if let Some(value) = metadata
+        .get("print.print_sections")
+        .and_then(|v| parse_metadata_bool(v))
+    {
+        print_sections = value;
     }
 
-    if let Some(print_synopsis_str) = metadata.get("print.print_synopsis") {
-        if let Ok(_value) = print_synopsis_str.parse::<f32>() {
-            print_synopsis = print_synopsis_str != "0";
-        }
+    if let Some(value) = metadata
+        .get("print.print_synopsis")
+        .and_then(|v| parse_metadata_bool(v))
+    {
+        print_synopsis = value;
+    }
+
+    // 大纲模式下章节是主要内容，总是输出；概要由 print_synopsis_outline 决定
+    if outline_only {
+        print_sections = true;
+        print_synopsis = config.print_synopsis_outline;
     }
 
     // 预处理 tokens
     let mut current_index = 0;
     let mut previous_type = String::new();
+    // 当前页是否已有内容（分隔符不算）
+    let mut page_has_content = false;
     let mut invisible_sections = Vec::new();
 
     while current_index < parsed_document_copy.tokens.len() {
@@ -118,9 +503,9 @@ pub async fn generate_docx_document(
         // 检查是否需要跳过当前标记
         let skip = match current_token.token_type.as_str() {
             "dual_dialogue_begin" | "dialogue_begin" | "dialogue_end" | "dual_dialogue_end" => true,
-            "action" | "transition" | "centered" | "shot" => !config.print_actions,
+            "action" | "transition" | "centered" | "shot" | "image" => !config.print_actions,
             "note" => !config.print_notes,
-            "scene_heading" => !config.print_headers,
+            "scene_heading" => !config.print_headers && !outline_only,
             "section" => !print_sections,
             "synopsis" => !print_synopsis,
             "separator" => config.merge_empty_lines && previous_type == "separator",
@@ -156,6 +541,24 @@ pub async fn generate_docx_document(
             }
         }
 
+        // 设置了 page_break_before 的层级的章节另起一页，已在页首时不重复分页
+        let section_page_break = current_token.token_type == "section"
+            && current_token
+                .level
+                .and_then(|level| config.print_profile.section.level(level as usize))
+                .is_some_and(|level_config| level_config.page_break_before);
+        if section_page_break && page_has_content {
+            let page_break = ScriptToken {
+                token_type: "page_break".to_string(),
+                text: String::new(),
+                ..current_token.clone()
+            };
+            parsed_document_copy
+                .tokens
+                .insert(current_index, page_break);
+            current_index += 1;
+        }
+
         // 在场景之间添加额外的分隔符
         if config.double_space_between_scenes
             && current_token.token_type == "scene_heading"
@@ -184,11 +587,19 @@ pub async fn generate_docx_document(
                 characters_action: None,
                 play_time_sec: 0.0,
                 invisible_sections: None,
+                origin: None,
+                source_range: None,
+                transition: None,
             };
             parsed_document_copy.tokens.insert(current_index, separator);
             current_index += 1;
         }
 
+        match current_token.token_type.as_str() {
+            "page_break" => page_has_content = false,
+            "separator" => {}
+            _ => page_has_content = true,
+        }
         previous_type = current_token.token_type.clone();
         current_index += 1;
     }
@@ -317,6 +728,13 @@ pub async fn generate_docx_document(
                 print_profile.character_spacing = value;
             }
         }
+
+        if let Some(value) = metadata
+            .get("print.vertical_text")
+            .and_then(|v| parse_metadata_bool(v))
+        {
+            print_profile.vertical_text = value;
+        }
     }
 
     // 从元数据中读取 action 和 scene_heading 的 feed 值，如果没有则使用默认值
@@ -332,6 +750,13 @@ pub async fn generate_docx_document(
         }
     }
 
+    // 大纲单独设置的概要样式
+    if outline_only {
+        if let Some(style) = &config.outline_synopsis_style {
+            print_profile.synopsis = style.clone();
+        }
+    }
+
     // 重新 动态 计算 print_profile 各配置。
     print_profile.action.feed = print_profile.left_margin; //action feed 固定为文档的 left_margin
     print_profile.scene_heading.feed = print_profile.action.feed;
@@ -380,8 +805,8 @@ pub async fn generate_docx_document(
         .round()
         / 100.0;
 
-    println!("【bottom_margin】:");
-    println!("  bottom_margin: {} pt", print_profile.bottom_margin);
+    debug_log!("【bottom_margin】:");
+    debug_log!("  bottom_margin: {} pt", print_profile.bottom_margin);
 
     // 创建DOCX选项
     let mut docx_options = DocxOptions::default();
@@ -396,74 +821,11 @@ pub async fn generate_docx_document(
     docx_options.line_height = line_height;
     docx_options.metadata = Some(metadata);
     docx_options.for_preview = output_path == "$PREVIEW$";
-
-    // 根据输出路径处理不同的情况
-    if output_path == "$STATS$" {
-        // 返回统计信息
-        println!("【generate_docx_document】开始获取统计信息 - get_docx_stats 分支");
-        println!(
-            "【generate_docx_document】title_page_processed = {}",
-            docx_options.title_page_processed
-        );
-        let stats = super::docx_maker::get_docx_stats(docx_options).await?;
-        Ok(Some(DocxStats {
-            page_count: stats.page_count as u32,
-            page_count_real: stats.page_count_real as u32,
-            line_map: stats
-                .line_map
-                .into_iter()
-                .map(|(k, v)| {
-                    (
-                        k as u32,
-                        LineStruct {
-                            sections: v.sections,
-                            scene: v.scene,
-                            page: v.page as u32,
-                            cumulative_duration: v.cumulative_duration,
-                        },
-                    )
-                })
-                .collect(),
-        }))
-    } else if output_path == "$PREVIEW$" {
-        // 返回Base64编码的文档
-        println!("【generate_docx_document】开始生成预览 - get_docx_base64 分支");
-        println!(
-            "【generate_docx_document】title_page_processed = {}",
-            docx_options.title_page_processed
-        );
-        let base64_result = super::docx_maker::get_docx_base64(docx_options).await?;
-        let stats = DocxStats {
-            page_count: base64_result.stats.page_count as u32,
-            page_count_real: base64_result.stats.page_count_real as u32,
-            line_map: base64_result
-                .stats
-                .line_map
-                .into_iter()
-                .map(|(k, v)| {
-                    (
-                        k as u32,
-                        LineStruct {
-                            sections: v.sections,
-                            scene: v.scene,
-                            page: v.page as u32,
-                            cumulative_duration: v.cumulative_duration,
-                        },
-                    )
-                })
-                .collect(),
-        };
-
-        Ok(Some(stats))
-    } else {
-        // 生成DOCX文件
-        println!("【generate_docx_document】开始生成 DOCX 文件 - get_docx 分支");
-        println!(
-            "【generate_docx_document】title_page_processed = {}",
-            docx_options.title_page_processed
-        );
-        super::docx_maker::get_docx(docx_options).await?;
-        println!("【generate_docx_document】DOCX 文件生成完成");
-        Ok(None)
-    }
+    docx_options.outline_only = outline_only;
+    docx_options.sides_characters = mode.sides_characters;
+    docx_options.changed_lines = mode.changed_lines.into_iter().collect();
+    docx_options.revisions = mode.revisions;
+    docx_options.av_script = mode.av_script;
+    docx_options.progress = mode.progress;
+    (docx_options, mode.backend)
 }
@@ -2,11 +2,17 @@
 //!
 //! 该模块提供了与原始 TypeScript 版本 docxmaker.ts 兼容的 API
 
+use crate::logging::{debug_log, phase, ExportTimings};
 use crate::models::Conf;
 use crate::parser::fountain_parser::Line;
+use crate::parser::spec::Extension;
+use crate::parser::text_processor::parse_color;
 use crate::parser::ParseOutput;
-use crate::utils::is_blank_line_after_style;
-use std::collections::HashMap;
+use crate::progress::{Cancelled, ExportPhase, Progress};
+use crate::title_template::TitlePageTemplate;
+use crate::utils::{escape_style_chars, is_blank_line_after_style, unescape_style_chars};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use thiserror::Error;
 
 // 使用适配器中的类型
@@ -17,6 +23,7 @@ use crate::docx::adapter::{
 };
 
 use super::adapter::docx::ParagraphSpacing;
+use super::metadata_extractor::parse_metadata_bool;
 
 /// DOCX导出错误类型
 #[derive(Error, Debug)]
@@ -32,6 +39,21 @@ pub enum DocxError {
 
     #[error("无效的配置: {0}")]
     InvalidConfig(String),
+
+    #[error("导出错误: {0}")]
+    RenderError(#[from] crate::render::RenderError),
+
+    #[error("{0}")]
+    Cancelled(#[from] Cancelled),
+
+    #[error("{0}")]
+    IncludeError(#[from] crate::parser::IncludeError),
+
+    #[error("排版任务失败: {0}")]
+    TaskError(#[from] tokio::task::JoinError),
+
+    #[error("生成的 DOCX 不符合规范: {}", super::validate::describe(.0))]
+    InvalidOutput(Vec<super::validate::ValidationError>),
 }
 
 /// DOCX导出结果
@@ -76,6 +98,9 @@ pub struct PrintProfile {
     pub dialogue: ElementConfig,
     /// 括号配置
     pub parenthetical: ElementConfig,
+    /// 歌词配置
+    #[serde(default)]
+    pub lyric: LyricConfig,
     /// 章节配置
     pub section: SectionConfig,
     /// 概要配置
@@ -84,6 +109,18 @@ pub struct PrintProfile {
     pub note_line_height: f32,
     /// 字距 //磅
     pub character_spacing: f32,
+    /// 竖排（日文剧本常用）：文字从上到下、行从右到左，纸张横放，页边距随之旋转
+    #[serde(default)]
+    pub vertical_text: bool,
+    /// 超链接配置
+    #[serde(default)]
+    pub link: LinkConfig,
+    /// 镜头交切标记配置
+    #[serde(default)]
+    pub shot_cut: ShotCutConfig,
+    /// 分页规则
+    #[serde(default)]
+    pub pagination: PaginationConfig,
 }
 
 impl Default for PrintProfile {
@@ -128,15 +165,21 @@ impl Default for PrintProfile {
                 color: None,
                 italic: false,
             },
+            lyric: LyricConfig::default(),
             section: SectionConfig {
                 feed: 0.2,
                 color: Some("#555555".to_string()),
                 italic: false,
                 level_indent: 0.2,
+                levels: Vec::new(),
             },
             synopsis: SynopsisConfig::default(),
             note_line_height: 0.17,
             character_spacing: 1.0,
+            vertical_text: false,
+            link: LinkConfig::default(),
+            shot_cut: ShotCutConfig::default(),
+            pagination: PaginationConfig::default(),
         }
     }
 }
@@ -148,6 +191,24 @@ pub struct NoteConfig {
     pub color: String,
     /// 是否斜体
     pub italic: bool,
+    /// 字体，为空时与正文相同
+    #[serde(default)]
+    pub font: Option<String>,
+    /// 字号 //磅，为空时使用 note_font_size
+    #[serde(default)]
+    pub size: Option<f32>,
+    /// 在原位置显示的注释两侧加方括号
+    #[serde(default)]
+    pub brackets: bool,
+    /// 在原位置显示的注释的底纹颜色，如 "#F2F2F2"
+    #[serde(default)]
+    pub shading: Option<String>,
+    /// 在原位置显示的注释加上与注释同色的边框，像放在文本框中
+    #[serde(default)]
+    pub boxed: bool,
+    /// 页面底部脚注的排版
+    #[serde(default)]
+    pub footnote: FootnoteConfig,
 }
 
 impl Default for NoteConfig {
@@ -155,6 +216,60 @@ impl Default for NoteConfig {
         Self {
             color: "#888888".to_string(),
             italic: true,
+            font: None,
+            size: None,
+            brackets: false,
+            shading: None,
+            boxed: false,
+            footnote: FootnoteConfig::default(),
+        }
+    }
+}
+
+/// 页面底部脚注的配置，颜色、斜体、字体和字号与注释相同
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FootnoteConfig {
+    /// 脚注上方是否有分隔线
+    pub separator: bool,
+    /// 编号格式：1（阿拉伯数字）、a（小写字母）、i（小写罗马数字）或 ※（每个脚注都用 ※ 标记）
+    pub format: String,
+    /// 每页重新编号，否则全文连续编号
+    pub restart_each_page: bool,
+    /// 行距倍数，1.0 为单倍行距；为空时与注释的行高相同
+    pub line_spacing: Option<f32>,
+}
+
+impl Default for FootnoteConfig {
+    fn default() -> Self {
+        Self {
+            separator: true,
+            format: "1".to_string(),
+            restart_each_page: false,
+            line_spacing: None,
+        }
+    }
+}
+
+/// 分页规则：避免在不合适的位置分页，通过段落的 keep 属性交给排版软件执行，
+/// 每行的约束由 `line_processor::pagination_keeps` 计算
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PaginationConfig {
+    /// 角色名之后至少有几行对白与之在同一页，为 0 时不限制
+    pub min_dialogue_lines_after_character: usize,
+    /// 场景标题之后至少有几行动作与之在同一页，为 0 时不限制
+    pub min_action_lines_after_scene_heading: usize,
+    /// 括号注释不跨页，并与其后的对白在同一页
+    pub keep_parentheticals_together: bool,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            min_dialogue_lines_after_character: 1,
+            min_action_lines_after_scene_heading: 1,
+            keep_parentheticals_together: true,
         }
     }
 }
@@ -180,6 +295,30 @@ impl Default for ElementConfig {
     }
 }
 
+/// 歌词配置
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LyricConfig {
+    /// 缩进
+    pub feed: f32,
+    /// 颜色
+    pub color: Option<String>,
+    /// 是否斜体
+    pub italic: bool,
+    /// 对齐方式：left、center 或 right
+    pub alignment: String,
+}
+
+impl Default for LyricConfig {
+    fn default() -> Self {
+        Self {
+            feed: 1.2,
+            color: None,
+            italic: true,
+            alignment: "left".to_string(),
+        }
+    }
+}
+
 /// 章节配置
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct SectionConfig {
@@ -191,6 +330,32 @@ pub struct SectionConfig {
     pub italic: bool,
     /// 层级缩进
     pub level_indent: f32,
+    /// 按层级的排版，第 1 项对应 `#`，第 2 项对应 `##`，依此类推；没有对应项的层级使用上面的样式
+    #[serde(default)]
+    pub levels: Vec<SectionLevelConfig>,
+}
+
+impl SectionConfig {
+    /// 第 `level` 级（从 1 开始）章节的排版
+    pub fn level(&self, level: usize) -> Option<&SectionLevelConfig> {
+        self.levels.get(level.checked_sub(1)?)
+    }
+}
+
+/// 某一层级章节的排版
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SectionLevelConfig {
+    /// 字号 //磅，为空时与正文相同
+    pub size: Option<f32>,
+    /// 是否加粗
+    pub bold: bool,
+    /// 段前间距 //磅
+    pub spacing_before: Option<f32>,
+    /// 段后间距 //磅
+    pub spacing_after: Option<f32>,
+    /// 是否从新的一页开始（如每一幕另起一页）
+    pub page_break_before: bool,
 }
 
 /// 概要配置
@@ -208,6 +373,53 @@ pub struct SynopsisConfig {
     pub feed_with_last_section: bool,
 }
 
+/// 超链接配置：正文中的网址、邮箱地址导出为可点击的超链接
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LinkConfig {
+    /// 是否识别超链接
+    pub enabled: bool,
+    /// 颜色，为空时沿用所在元素的颜色
+    pub color: Option<String>,
+    /// 是否加下划线
+    pub underline: bool,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: Some("#0563C1".to_string()),
+            underline: true,
+        }
+    }
+}
+
+/// 镜头交切标记（`{+ +} ↓`、`{# #} ↓`、`{= =} ↓` 与结束标记 `{- -} ↑`）的版式
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ShotCutConfig {
+    /// "label"：括号包住的标签，如 "(镜头交切 ↓)"；"banner"：居中、四周加框的横幅，宽度与动作相同
+    pub style: String,
+    /// 是否按参与交切的场景自动生成开始标记的文字，如 "INTERCUT – 客厅 / 街道"
+    pub auto_label: bool,
+    /// 自动生成的文字的前缀
+    pub label_prefix: String,
+    /// 颜色，为空时与转场相同；横幅的边框也使用该颜色
+    pub color: Option<String>,
+}
+
+impl Default for ShotCutConfig {
+    fn default() -> Self {
+        Self {
+            style: "label".to_string(),
+            auto_label: false,
+            label_prefix: "INTERCUT".to_string(),
+            color: None,
+        }
+    }
+}
+
 impl Default for SectionConfig {
     fn default() -> Self {
         Self {
@@ -215,6 +427,7 @@ impl Default for SectionConfig {
             color: Some("#666666".to_string()),
             italic: false,
             level_indent: 0.2,
+            levels: Vec::new(),
         }
     }
 }
@@ -303,6 +516,20 @@ pub struct DocxOptions {
     pub for_preview: bool,
     /// 标题页是否已处理
     pub title_page_processed: bool,
+    /// 大纲模式：只输出章节、场景标题和概要（不含对白和动作），用于生成分场大纲
+    pub outline_only: bool,
+    /// 试镜台词本（sides）的角色：非空时只输出这些角色出场的场景，突出其对白、淡化其他对白
+    pub sides_characters: Vec<String>,
+    /// 与上一稿相比有改动的行（源文件行号），对应段落左侧加修订线
+    pub changed_lines: HashSet<usize>,
+    /// 修订模式：这些行（源文件行号）对应的段落整段标记为修订插入或删除
+    pub revisions: HashMap<usize, crate::docx::adapter::docx::Revision>,
+    /// A/V 两栏格式：画面（动作、转场）在左栏，声音（对白、歌词、音效）在右栏
+    pub av_script: bool,
+    /// 参考模板（.docx），剧本内容追加到模板之后，沿用模板中的样式、页眉页脚和 logo 等内容
+    pub reference_docx: Option<PathBuf>,
+    /// 进度回调与取消令牌：排版时报告处理到第几行，取消后导出返回 `DocxError::Cancelled`
+    pub progress: Progress,
 }
 
 impl Default for DocxOptions {
@@ -329,6 +556,13 @@ impl Default for DocxOptions {
             metadata: None,
             for_preview: false,
             title_page_processed: false,
+            outline_only: false,
+            sides_characters: Vec::new(),
+            changed_lines: HashSet::new(),
+            revisions: HashMap::new(),
+            av_script: false,
+            reference_docx: None,
+            progress: Progress::default(),
         }
     }
 }
@@ -373,14 +607,20 @@ pub struct DocxContext {
     pub force_note_orig: bool,
     pub current_note: CurrentNote,
     pub notes_len: usize,
+    /// 已创建的书签数，用于分配书签 id
+    pub bookmarks_len: usize,
     pub doc: Document,
     // china_format 缓存变量
     pub last_dial_gr: Option<CachedDialogueGroup>,
     pub last_dial_gr_left: Option<CachedDialogueGroup>,
     pub last_dial_gr_right: Option<CachedDialogueGroup>,
+    /// 三人同时对白的中栏
+    pub last_dial_gr_middle: Option<CachedDialogueGroup>,
     // 全局双对话表格缓存 - 参考原项目的lastDialTableLeft和lastDialTableRight
     pub last_dial_table_left: Vec<crate::docx::adapter::docx::Paragraph>,
     pub last_dial_table_right: Vec<crate::docx::adapter::docx::Paragraph>,
+    /// 三人同时对白的中栏，为空时表格只有左右两栏
+    pub last_dial_table_middle: Vec<crate::docx::adapter::docx::Paragraph>,
 }
 
 impl DocxContext {
@@ -407,7 +647,11 @@ impl DocxContext {
         }
 
         let font_size = (options.print_profile.font_size) as usize;
-        let note_font_size = (options.print_profile.note_font_size) as usize;
+        let note_font_size = options
+            .print_profile
+            .note
+            .size
+            .unwrap_or(options.print_profile.note_font_size) as usize;
 
         let run_normal = RunProps {
             size: Some(font_size),
@@ -475,12 +719,12 @@ impl DocxContext {
 
         let run_notes = RunProps {
             size: Some(note_font_size),
-            font: Some(
+            font: Some(options.print_profile.note.font.clone().unwrap_or_else(|| {
                 font_names
                     .get("normal")
                     .unwrap_or(&"Courier Prime".to_string())
-                    .clone(),
-            ),
+                    .clone()
+            })),
             color: Some("868686".to_string()),
             ..Default::default()
         };
@@ -504,6 +748,10 @@ impl DocxContext {
                 }
             }
         }
+        // 关闭国内剧本格式扩展时按国际格式排版
+        if !options.config.extension_enabled(Extension::ChinaFormat) {
+            china_format = 0;
+        }
 
         DocxContext {
             options,
@@ -520,14 +768,17 @@ impl DocxContext {
             force_note_orig: false,
             current_note: CurrentNote::default(),
             notes_len: 0,
+            bookmarks_len: 0,
             doc: Document::new(),
             // 初始化 china_format 缓存变量
             last_dial_gr: None,
             last_dial_gr_left: None,
             last_dial_gr_right: None,
+            last_dial_gr_middle: None,
             // 初始化全局双对话表格缓存
             last_dial_table_left: Vec::new(),
             last_dial_table_right: Vec::new(),
+            last_dial_table_middle: Vec::new(),
         }
     }
 
@@ -541,6 +792,12 @@ impl DocxContext {
         self.options.italic_dynamic = false;
     }
 
+    /// 创建名为 `name` 的书签，id 在文档内唯一
+    pub fn next_bookmark(&mut self, name: &str) -> crate::docx::adapter::docx::Bookmark {
+        self.bookmarks_len += 1;
+        crate::docx::adapter::docx::Bookmark::new(self.bookmarks_len, name)
+    }
+
     /// 创建页码运行
     pub fn create_page_number_runs(
         &self,
@@ -576,6 +833,9 @@ impl DocxContext {
             bold: self.format_state.bold,
             italic: self.format_state.italic,
             underline: self.format_state.underline,
+            strikethrough: self.format_state.strikethrough,
+            highlight: self.format_state.highlight,
+            text_color: self.format_state.text_color.clone(),
             override_color: self.format_state.override_color.clone(),
             italic_global: self.options.italic_global,
             italic_dynamic: self.options.italic_dynamic,
@@ -591,6 +851,9 @@ impl DocxContext {
             self.format_state.bold = stash.bold;
             self.format_state.italic = stash.italic;
             self.format_state.underline = stash.underline;
+            self.format_state.strikethrough = stash.strikethrough;
+            self.format_state.highlight = stash.highlight;
+            self.format_state.text_color = stash.text_color.clone();
             self.format_state.override_color = stash.override_color.clone();
             self.format_state.current_color = stash.current_color.clone();
             self.options.italic_global = stash.italic_global;
@@ -606,6 +869,9 @@ impl DocxContext {
             bold: self.format_state.bold,
             italic: self.format_state.italic,
             underline: self.format_state.underline,
+            strikethrough: self.format_state.strikethrough,
+            highlight: self.format_state.highlight,
+            text_color: self.format_state.text_color.clone(),
             override_color: self.format_state.override_color.clone(),
             italic_global: self.options.italic_global,
             italic_dynamic: self.options.italic_dynamic,
@@ -621,11 +887,14 @@ impl DocxContext {
             self.format_state.bold = stash.bold;
             self.format_state.italic = stash.italic;
             self.format_state.underline = stash.underline;
+            self.format_state.strikethrough = stash.strikethrough;
+            self.format_state.highlight = stash.highlight;
+            self.format_state.text_color = stash.text_color.clone();
             self.format_state.override_color = stash.override_color.clone();
             self.format_state.current_color = stash.current_color.clone();
             self.options.italic_global = stash.italic_global;
             self.options.italic_dynamic = stash.italic_dynamic;
-            println!(
+            debug_log!(
                 "【text2】====1设置脚注颜色: override_color={:?}",
                 self.format_state.override_color
             );
@@ -639,6 +908,9 @@ impl DocxContext {
             bold: self.format_state.bold,
             italic: self.format_state.italic,
             underline: self.format_state.underline,
+            strikethrough: self.format_state.strikethrough,
+            highlight: self.format_state.highlight,
+            text_color: self.format_state.text_color.clone(),
             override_color: self.format_state.override_color.clone(),
             italic_global: self.options.italic_global,
             italic_dynamic: self.options.italic_dynamic,
@@ -654,11 +926,14 @@ impl DocxContext {
             self.format_state.bold = stash.bold;
             self.format_state.italic = stash.italic;
             self.format_state.underline = stash.underline;
+            self.format_state.strikethrough = stash.strikethrough;
+            self.format_state.highlight = stash.highlight;
+            self.format_state.text_color = stash.text_color.clone();
             self.format_state.override_color = stash.override_color.clone();
             self.format_state.current_color = stash.current_color.clone();
             self.options.italic_global = stash.italic_global;
             self.options.italic_dynamic = stash.italic_dynamic;
-            println!(
+            debug_log!(
                 "【text2】====4设置脚注颜色: override_color={:?}",
                 self.format_state.override_color
             );
@@ -735,6 +1010,26 @@ impl DocxContext {
         action_style.spacing = Some(spacing.clone());
         styles.paragraph_styles.push(action_style);
 
+        // lyric 样式（歌词），右侧与 action 对齐
+        let lyric_indent = convert_inches_to_twip(print.lyric.feed - print.left_margin);
+        let mut lyric_style = crate::docx::adapter::docx::ParagraphStyle::new();
+        lyric_style.id = Some("lyric".to_string());
+        lyric_style.name = Some("Lyric".to_string());
+        lyric_style.based_on = Some("Normal".to_string());
+        lyric_style.next = Some("Normal".to_string());
+        lyric_style.indent = Some(crate::docx::adapter::docx::ParagraphIndent {
+            left: Some(lyric_indent),
+            right: Some(action_indent),
+            first_line: None,
+        });
+        let mut lyric_run = crate::docx::adapter::docx::RunStyle::new();
+        lyric_run.font = self.run_normal.font.clone();
+        lyric_run.italic = Some(print.lyric.italic);
+        lyric_run.color = print.lyric.color.clone();
+        lyric_style.run = Some(lyric_run);
+        lyric_style.spacing = Some(spacing.clone());
+        styles.paragraph_styles.push(lyric_style);
+
         // character 样式
         let mut character_style = crate::docx::adapter::docx::ParagraphStyle::new();
         character_style.id = Some("character".to_string());
@@ -876,8 +1171,17 @@ impl DocxContext {
             self.last_dial_table_right.push(paragraph);
         };
 
+        // 处理中栏对话缓存（三人同时对白）
+        if let Some(dial_gr_middle) = self.last_dial_gr_middle.take() {
+            self.last_dial_table_middle
+                .push(cached_group_paragraph(dial_gr_middle, spacing));
+        }
+
         // 如果有双对话内容，创建表格 - 使用局部变量，恢复原项目逻辑
-        if !self.last_dial_table_left.is_empty() || !self.last_dial_table_right.is_empty() {
+        if !self.last_dial_table_left.is_empty()
+            || !self.last_dial_table_right.is_empty()
+            || !self.last_dial_table_middle.is_empty()
+        {
             // println!(
             //     "【finish_double_dial】创建双对话表格，左侧段落: {}, 右侧段落: {}",
             //     self.last_dial_table_left.len(),
@@ -887,16 +1191,24 @@ impl DocxContext {
             let inner_width_twip =
                 convert_inches_to_twip(print.page_width - print.left_margin - print.right_margin);
             let action_indent = convert_inches_to_twip(print.action.feed - print.left_margin);
+            // 三人同时对白时有中栏，三栏平分宽度
+            let columns: Vec<&[crate::docx::adapter::docx::Paragraph]> =
+                if self.last_dial_table_middle.is_empty() {
+                    vec![&self.last_dial_table_left, &self.last_dial_table_right]
+                } else {
+                    vec![
+                        &self.last_dial_table_left,
+                        &self.last_dial_table_middle,
+                        &self.last_dial_table_right,
+                    ]
+                };
             let dial_double_tab_column_width =
-                (inner_width_twip - action_indent - action_indent) / 2;
+                (inner_width_twip - action_indent - action_indent) / columns.len() as i32;
 
             // 创建表格
             let mut table = crate::docx::adapter::docx::Table::new();
             table.without_borders(true);
-            table.columnWidths(vec![
-                dial_double_tab_column_width as usize,
-                dial_double_tab_column_width as usize,
-            ]);
+            table.columnWidths(vec![dial_double_tab_column_width as usize; columns.len()]);
 
             // 设置表格缩进
             table.indent = Some(crate::docx::adapter::docx::TableIndent {
@@ -938,30 +1250,30 @@ impl DocxContext {
                 }),
             });
 
-            // 创建表格行
-            let mut row = crate::docx::adapter::docx::TableRow::new();
-
-            // 左列
-            let mut left_cell = crate::docx::adapter::docx::TableCell::new();
-            left_cell.width = Some(crate::docx::adapter::docx::TableWidth {
-                width_type: crate::docx::adapter::WidthType::DXA,
-                size: dial_double_tab_column_width,
-            });
-            left_cell.children = self.last_dial_table_left.clone();
-
-            // 右列
-            let mut right_cell = crate::docx::adapter::docx::TableCell::new();
-            right_cell.width = Some(crate::docx::adapter::docx::TableWidth {
-                width_type: crate::docx::adapter::WidthType::DXA,
-                size: dial_double_tab_column_width,
-            });
-            right_cell.children = self.last_dial_table_right.clone();
-
-            // 添加单元格到行
-            row.cells = vec![left_cell, right_cell];
+            // 创建表格行：默认每栏各占一个单元格；开启 balance_dual_dialogue 时
+            // 各栏段落按类型逐段配对，每对一行，各栏长短悬殊时跨页也能保持对齐
+            let rows = dual_dialogue_rows(
+                &columns,
+                self.options.config.balance_dual_dialogue,
+                |paragraph| paragraph.style.clone(),
+            );
+            for cells in rows {
+                let mut row = crate::docx::adapter::docx::TableRow::new();
+
+                // 从左到右依次为左栏、中栏（如有）、右栏
+                for children in cells {
+                    let mut cell = crate::docx::adapter::docx::TableCell::new();
+                    cell.width = Some(crate::docx::adapter::docx::TableWidth {
+                        width_type: crate::docx::adapter::WidthType::DXA,
+                        size: dial_double_tab_column_width,
+                    });
+                    cell.children = children;
+                    row.cells.push(cell);
+                }
 
-            // 添加行到表格
-            table.rows.push(row);
+                // 添加行到表格
+                table.rows.push(row);
+            }
 
             // 添加表格到section
             section_main
@@ -983,6 +1295,7 @@ impl DocxContext {
             // 清空局部缓存 - 参考原项目逻辑
             self.last_dial_table_left.clear();
             self.last_dial_table_right.clear();
+            self.last_dial_table_middle.clear();
         }
     }
 
@@ -1018,7 +1331,7 @@ impl DocxContext {
 
         // 处理左侧对话缓存，添加到全局表格缓存 - 修复关键问题
         if let Some(dial_gr_left) = self.last_dial_gr_left.take() {
-            println!("【finish_china_dial_first】处理左侧对话缓存，添加到全局表格缓存");
+            debug_log!("【finish_china_dial_first】处理左侧对话缓存，添加到全局表格缓存");
             let mut paragraph =
                 crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
             paragraph.style(&dial_gr_left.style);
@@ -1038,9 +1351,15 @@ impl DocxContext {
             self.last_dial_table_left.push(paragraph);
         }
 
+        // 处理中栏对话缓存（三人同时对白）
+        if let Some(dial_gr_middle) = self.last_dial_gr_middle.take() {
+            self.last_dial_table_middle
+                .push(cached_group_paragraph(dial_gr_middle, spacing));
+        }
+
         // 处理右侧对话缓存，添加到全局表格缓存 - 修复关键问题
         if let Some(dial_gr_right) = self.last_dial_gr_right.take() {
-            println!("【finish_china_dial_first】处理右侧对话缓存，添加到全局表格缓存");
+            debug_log!("【finish_china_dial_first】处理右侧对话缓存，添加到全局表格缓存");
             let mut paragraph =
                 crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
             paragraph.style(&dial_gr_right.style);
@@ -1109,6 +1428,11 @@ impl DocxContext {
         let char_bold = style_chars.get("bold").unwrap();
         let char_bold_italic = style_chars.get("bold_italic").unwrap();
         let char_underline = style_chars.get("underline").unwrap();
+        let char_strikethrough = style_chars.get("strikethrough").unwrap();
+        let char_highlight = style_chars.get("highlight").unwrap();
+        let char_color_begin = style_chars.get("color_begin").unwrap();
+        let char_color_text = style_chars.get("color_text").unwrap();
+        let char_color_end = style_chars.get("color_end").unwrap();
         let char_style_left_stash = style_chars.get("style_left_stash").unwrap();
         let char_style_left_pop = style_chars.get("style_left_pop").unwrap();
         let char_style_right_stash = style_chars.get("style_right_stash").unwrap();
@@ -1135,11 +1459,14 @@ impl DocxContext {
         self.format_state.current_color = color.clone();
 
         // 处理中文格式的三角符号
-        let mut text = text.to_string();
+        // 场景标题等没有经过样式处理的文本中，反斜杠转义的字符按原样输出
+        let mut text = unescape_style_chars(&escape_style_chars(text));
 
         // 页面底部notes打印模式
         let mut catch_notes = false;
         let mut pushed = false;
+        // 正在读取彩色文字的颜色值
+        let mut reading_color = false;
 
         if current_line_notes.is_some() && notes_page.is_some() {
             catch_notes = true; // 页面底部notes打印模式
@@ -1169,8 +1496,28 @@ impl DocxContext {
             }
         }
 
-        // 处理注释斜体
+        // 在原位置显示的注释两侧加方括号；上一行未结束的注释接着判断
         let note_config = &self.options.print_profile.note;
+        if note_config.brackets {
+            let mut inline = self.force_note_orig
+                || (self.format_state.override_color.is_some() && !catch_notes);
+            let mut bracketed = String::with_capacity(text.len() + 2);
+            for c in text.chars() {
+                if inline && char_note_end.contains(c) {
+                    bracketed.push(']');
+                    inline = false;
+                }
+                bracketed.push(c);
+                if char_note_begin_ext.contains(c) || (!catch_notes && char_note_begin.contains(c))
+                {
+                    bracketed.push('[');
+                    inline = true;
+                }
+            }
+            text = bracketed;
+        }
+
+        // 处理注释斜体
         if note_config.italic {
             text = text
                 .replace(
@@ -1184,24 +1531,24 @@ impl DocxContext {
                 .replace(char_note_end, &format!("{}{}", char_note_end, char_italic));
         }
 
-        // 处理链接
-        // 定义链接结构体
-        #[derive(Debug, Clone)]
-        struct Link {
-            start: usize,
-            length: usize,
-            url: String,
-        }
-
-        let links: Vec<Link> = Vec::new();
-
-        // 处理链接
-        if let Some(links_option) = options.get("links") {
-            if links_option == "true" {
-                // 在完整实现中，这里应该使用正则表达式处理链接
-                // 例如：regex.link.exec(text)
-                // 但在简化版中，我们省略这部分
+        // 处理链接：识别网址和邮箱地址，元素的 options 中 links 为 false 时不识别
+        let mut links: Vec<crate::utils::Link> = Vec::new();
+        if self.options.print_profile.link.enabled
+            && options.get("links").map(String::as_str) != Some("false")
+        {
+            // 链接中的 _、* 不作为样式标记，换回原字符后重新计算位置
+            let mut linked = String::with_capacity(text.len());
+            let mut prev = 0;
+            for mut link in crate::utils::find_links(&text) {
+                linked.push_str(&text[prev..link.start]);
+                prev = link.start + link.length;
+                link.start = linked.len();
+                link.length = link.text.len();
+                linked.push_str(&link.text);
+                links.push(link);
             }
+            linked.push_str(&text[prev..]);
+            text = linked;
         }
 
         // 分割文本以处理格式化
@@ -1396,6 +1743,41 @@ impl DocxContext {
                     self.format_state.underline = !self.format_state.underline;
                     // println!("【text2】切换下划线状态: {}", self.format_state.underline);
                 }
+            } else if elem == *char_strikethrough || elem == *char_highlight {
+                if catch_notes && self.current_note.page_idx > -1 {
+                    if !pushed {
+                        self.current_note.note.text.push(elem.clone());
+                        pushed = true;
+                    } else if let Some(last) = self.current_note.note.text.last_mut() {
+                        *last += &elem;
+                    }
+                } else if elem == *char_strikethrough {
+                    self.format_state.strikethrough = !self.format_state.strikethrough;
+                } else {
+                    self.format_state.highlight = !self.format_state.highlight;
+                }
+            } else if elem == *char_color_begin
+                || elem == *char_color_text
+                || elem == *char_color_end
+            {
+                if catch_notes && self.current_note.page_idx > -1 {
+                    if !pushed {
+                        self.current_note.note.text.push(elem.clone());
+                        pushed = true;
+                    } else if let Some(last) = self.current_note.note.text.last_mut() {
+                        *last += &elem;
+                    }
+                } else if elem == *char_color_begin {
+                    // 之后的文本是颜色值
+                    reading_color = true;
+                } else if elem == *char_color_text {
+                    reading_color = false;
+                } else {
+                    self.format_state.text_color = None;
+                }
+            } else if reading_color && !(catch_notes && self.current_note.page_idx > -1) {
+                // 颜色值不输出
+                self.format_state.text_color = Some(elem.clone());
             } else if elem == *char_note_end {
                 // println!("【text2】处理 note_end 符号 ↻");
                 // println!("【text2】重置前的颜色: override_color={:?}, color={}", self.format_state.override_color, color);
@@ -1509,17 +1891,37 @@ impl DocxContext {
                     }
 
                     // 添加脚注引用 - 参考原项目逻辑
-                    if catch_notes {
+                    if catch_notes && self.options.config.notes_as_word_comments {
+                        // 批注模式：正文不留标记，注释内容之后填入批注
+                        text_objects.push(crate::docx::adapter::docx::TextRun::comment_reference(
+                            self.notes_len,
+                        ));
+                    } else if catch_notes && self.options.config.note_position_end {
+                        // 尾注模式：正文只留上标编号，链接到文末的注释，注释编号再链接回来
+                        let mut endnote_props = self.run_notes.clone();
+                        endnote_props.superscript = Some(true);
+                        let bookmark = self.next_bookmark(&note_ref_bookmark(self.notes_len));
+                        text_objects.push(
+                            crate::docx::adapter::docx::TextRun::with_props(
+                                &format!("[{}]", self.notes_len),
+                                endnote_props,
+                            )
+                            .link_to(&note_bookmark(self.notes_len))
+                            .bookmark(bookmark),
+                        );
+                    } else if catch_notes {
                         // 当收集脚注时，创建真正的脚注引用
 
                         // 将脚注文本转换为格式化的TextRun
                         let footnote_runs = Vec::new();
 
+                        let bookmark = self.next_bookmark(&note_ref_bookmark(self.notes_len));
                         let footnote_ref = crate::docx::adapter::docx::TextRun::footnote_reference(
                             self.notes_len,
                             footnote_runs,
                             self.run_notes.clone(),
-                        );
+                        )
+                        .bookmark(bookmark);
                         text_objects.push(footnote_ref);
                     }
                     // 当不收集脚注时（catch_notes = false），脚注开始标记不显示，但脚注内容会在原位置显示
@@ -1595,7 +1997,7 @@ impl DocxContext {
 
                         // 参考原项目逻辑：如果在脚注中（override_color存在），使用脚注字体大小
                         if self.format_state.override_color.is_some() {
-                            font_size = (self.options.print_profile.note_font_size) as usize;
+                            font_size = self.run_notes.size.unwrap_or(font_size);
                         }
 
                         // 检查 options 中的粗体设置
@@ -1638,6 +2040,29 @@ impl DocxContext {
                             run_props.underline = Some(UnderlineTypeConst::SINGLE);
                         }
 
+                        // 应用删除线和高亮
+                        if self.format_state.strikethrough {
+                            run_props.strike = Some(true);
+                        }
+                        if self.format_state.highlight {
+                            run_props.highlight = Some("yellow".to_string());
+                        }
+
+                        // 注释的字体、底纹和边框
+                        if let Some(font) = options.get("font") {
+                            run_props.font = Some(font.clone());
+                        }
+                        if self.force_note_orig || self.format_state.override_color.is_some() {
+                            let note_config = &self.options.print_profile.note;
+                            if note_config.font.is_some() {
+                                run_props.font = note_config.font.clone();
+                            }
+                            run_props.shading = note_config.shading.clone();
+                            if note_config.boxed {
+                                run_props.border = Some(note_config.color.clone());
+                            }
+                        }
+
                         // 检查是否有链接
                         let mut link_url = None;
                         for link in &links {
@@ -1648,8 +2073,12 @@ impl DocxContext {
                             }
                         }
 
-                        // 如果有链接，添加下划线
-                        if link_url.is_some() && run_props.underline.is_none() {
+                        // 如果有链接，按配置添加下划线
+                        let link_config = &self.options.print_profile.link;
+                        if link_url.is_some()
+                            && link_config.underline
+                            && run_props.underline.is_none()
+                        {
                             run_props.underline = Some(UnderlineTypeConst::SINGLE);
                         }
 
@@ -1662,18 +2091,37 @@ impl DocxContext {
                             // 如果有覆盖颜色，使用覆盖颜色
                             color = override_color.clone();
                         }
+                        // 彩色文字，注释的颜色优先
+                        let color = match &self.format_state.text_color {
+                            Some(text_color)
+                                if !self.force_note_orig
+                                    && self.format_state.override_color.is_none() =>
+                            {
+                                text_color.clone()
+                            }
+                            _ => color.clone(),
+                        };
 
                         // 设置 TextRun 的颜色
                         if !color.is_empty() && color != "#000000" {
                             run_props.color = Some(color.clone());
                         }
+                        // 链接的颜色，注释中的链接仍用注释的颜色
+                        if let (Some(_), Some(link_color)) = (&link_url, &link_config.color) {
+                            if !self.force_note_orig {
+                                run_props.color = Some(link_color.clone());
+                            }
+                        }
 
                         // 处理普通文本中的换行符
                         let lines: Vec<&str> = elem_to_draw.split('\n').collect();
 
                         if !lines.is_empty() {
                             // 第一行不添加换行符
-                            let run = TextRun::with_props(lines[0], run_props.clone());
+                            let mut run = TextRun::with_props(lines[0], run_props.clone());
+                            if let Some(url) = &link_url {
+                                run = run.link_to_url(url);
+                            }
                             text_objects.push(run);
 
                             // 后续行添加换行符（使用 break: 1 属性，与 TypeScript 版本一致）
@@ -1703,10 +2151,15 @@ impl DocxContext {
 }
 
 /// 初始化文档
-pub async fn init_doc(options: DocxOptions) -> DocxContext {
+pub fn init_doc(options: DocxOptions) -> DocxResult<DocxContext> {
     // 创建文档上下文
     let mut context = DocxContext::new(options.clone());
 
+    // 使用参考模板
+    if let Some(reference_docx) = &options.reference_docx {
+        context.doc = Document::from_template(reference_docx)?;
+    }
+
     // 设置文档属性
     context.doc.options.creator = "Arming".to_string();
     context.doc.options.description = "My screenplay document".to_string();
@@ -1730,6 +2183,9 @@ pub async fn init_doc(options: DocxOptions) -> DocxContext {
             }
         }
     }
+    if !options.config.extension_enabled(Extension::ChinaFormat) {
+        china_format = 0;
+    }
 
     // 更新上下文
     context.china_format = china_format;
@@ -1738,7 +2194,7 @@ pub async fn init_doc(options: DocxOptions) -> DocxContext {
     // 重置格式状态
     context.reset_format();
 
-    context
+    Ok(context)
 }
 /// 清理文本中的格式标记
 fn if_reset_format(input: String, line: &Line) -> String {
@@ -1845,6 +2301,60 @@ fn inline(text: &str) -> String {
     result.trim().to_string()
 }
 
+/// 正文页面的节属性（纸张大小、页边距、页码和文字方向）
+///
+/// 竖排时整页顺时针旋转 90°：纸张横放，原来的左边距（行首）到了上方，右边距（行尾）到了下方，
+/// 上边距（首行）到了右侧，下边距到了左侧；页眉页脚仍在纸张上下两端
+fn page_section_properties(
+    print: &PrintProfile,
+    line_height: f32,
+    vertical: bool,
+) -> crate::docx::adapter::docx::SectionProperties {
+    let (page_height, page_width, top_margin, right_margin, bottom_margin, left_margin) =
+        if vertical {
+            (
+                print.page_width,
+                print.page_height,
+                print.left_margin,
+                print.top_margin,
+                print.right_margin,
+                print.bottom_margin,
+            )
+        } else {
+            (
+                print.page_height,
+                print.page_width,
+                print.top_margin,
+                print.right_margin,
+                print.bottom_margin,
+                print.left_margin,
+            )
+        };
+    crate::docx::adapter::docx::SectionProperties {
+        page: Some(crate::docx::adapter::docx::PageProperties {
+            size: Some(crate::docx::adapter::docx::PageSize::new(
+                convert_inches_to_twip(page_height),
+                convert_inches_to_twip(page_width),
+            )),
+            margin: Some(crate::docx::adapter::docx::PageMargin::new(
+                convert_inches_to_twip(top_margin),
+                convert_inches_to_twip(right_margin),
+                convert_inches_to_twip(bottom_margin),
+                convert_inches_to_twip(left_margin),
+                convert_inches_to_twip(print.page_number_top_margin),
+                convert_inches_to_twip(if print.page_number_top_margin - line_height < 0.2 {
+                    0.2
+                } else {
+                    print.page_number_top_margin - line_height
+                }),
+            )),
+            page_numbers: Some(crate::docx::adapter::docx::PageNumbers::new(1)),
+        }),
+        vertical,
+        line_numbers: None,
+    }
+}
+
 /// 页面尺寸计算结果
 #[derive(Debug, Clone)]
 struct PageDimensions {
@@ -2002,6 +2512,30 @@ fn create_title_frame(
     }
 }
 
+/// 读取插图，生成图片运行
+///
+/// 从文件解析（`parse_file`）时相对路径已按剧本所在目录展开；其余相对路径先按当前目录查找，
+/// 找不到时再按输出文件所在目录查找
+fn load_image_run(
+    metadata: Option<&HashMap<String, String>>,
+    filepath: &str,
+) -> Option<crate::docx::adapter::docx::ImageRun> {
+    let metadata = metadata?;
+    let src = std::path::Path::new(metadata.get("src")?);
+    let path = if src.is_absolute() || src.exists() {
+        src.to_path_buf()
+    } else {
+        std::path::Path::new(filepath).parent()?.join(src)
+    };
+    let data = std::fs::read(&path).ok()?;
+    let size = |key: &str| metadata.get(key).and_then(|v| v.parse::<f32>().ok());
+    Some(crate::docx::adapter::docx::ImageRun::new(
+        data,
+        size("width"),
+        size("height"),
+    ))
+}
+
 /// 获取标题页位置的对齐方式
 fn get_title_alignment(position: &str) -> Option<crate::docx::adapter::AlignmentType> {
     match position {
@@ -2011,92 +2545,962 @@ fn get_title_alignment(position: &str) -> Option<crate::docx::adapter::Alignment
     }
 }
 
-/// 创建基础选项映射
-fn create_basic_options_map(color: &str) -> HashMap<String, String> {
-    let mut options_map = HashMap::new();
-    options_map.insert("color".to_string(), color.to_string());
-    options_map
+/// 页眉页脚中随页变化的字段
+#[derive(Debug, Clone, Default)]
+struct PageFields {
+    /// 当前章节路径
+    sections: Vec<String>,
+    /// 当前场景标题
+    scene: String,
+    /// 章节路径是否取自本页的章节标题
+    section_from_heading: bool,
+    /// 场景是否取自本页的场景标题
+    scene_from_heading: bool,
 }
 
-/// 完成中文格式对话和双对话处理的辅助函数
-fn finish_dialogue_processing(
-    doc: &mut DocxContext,
-    china_format: i32,
-    token_type: &str,
-    scene_or_section_or_tran_started: bool,
-    section_main: &mut crate::docx::adapter::docx::Section,
-    section_main_no_page_num: &mut crate::docx::adapter::docx::Section,
-    print: &PrintProfile,
-    spacing: &ParagraphSpacing,
-) {
-    // 完成中文格式对话处理
-    if china_format > 0 {
-        doc.finish_china_dial_first(
-            if scene_or_section_or_tran_started {
-                section_main
+/// 页眉页脚模板
+///
+/// `print_header`/`print_footer` 中可以使用占位符：`{title}`、`{author}`、`{date}` 取自标题页
+/// （没有日期时使用导出当天），`{scene}`、`{section}` 为当前页所在的场景和章节，`{page}` 为页码，
+/// `{total}` 为正文最后一页的页码
+struct HeaderFooter {
+    header: String,
+    footer: String,
+    page_numbers: String,
+    total: String,
+    section_in_header: bool,
+    title: String,
+    author: String,
+    date: String,
+    options: HashMap<String, String>,
+}
+
+impl HeaderFooter {
+    fn new(cfg: &Conf, parsed: &ParseOutput, options: HashMap<String, String>) -> Self {
+        let title_page_text = |keys: &[&str]| {
+            parsed
+                .title_page
+                .values()
+                .flatten()
+                .find(|t| keys.contains(&t.token_type.as_str()))
+                .map(|t| inline(&crate::utils::strip_style_chars(&t.text)).trim().to_string())
+                .unwrap_or_default()
+        };
+        let date = title_page_text(&["draft_date", "date"]);
+        Self {
+            header: cfg.print_header.clone(),
+            footer: cfg.print_footer.clone(),
+            page_numbers: cfg.show_page_numbers.clone(),
+            total: String::new(),
+            section_in_header: cfg.print_section_header,
+            title: title_page_text(&["title"]),
+            author: title_page_text(&["author", "authors"]),
+            date: if !date.is_empty() {
+                date
+            } else if let Some(timestamp) = deterministic_timestamp(cfg) {
+                timestamp[..10].to_string()
             } else {
-                section_main_no_page_num
+                chrono::Local::now().format("%Y-%m-%d").to_string()
             },
-            spacing,
-        );
+            options,
+        }
     }
 
-    // 检查是否需要完成双对话处理
-    let has_right_table_content = !doc.last_dial_table_right.is_empty();
-    let has_left_or_right_cache =
-        doc.last_dial_gr_left.is_some() || doc.last_dial_gr_right.is_some();
-    let has_global_table_content =
-        !doc.last_dial_table_left.is_empty() || !doc.last_dial_table_right.is_empty();
-
-    if has_right_table_content
-        || has_left_or_right_cache
-        || has_global_table_content
-        || token_type != "separator"
-    {
-        doc.finish_double_dial(
-            if scene_or_section_or_tran_started {
-                section_main
-            } else {
-                section_main_no_page_num
-            },
-            print,
-            spacing,
-        );
+    /// 是否含有随页变化的字段，需要按页拆分 section
+    fn per_page(&self) -> bool {
+        self.section_in_header
+            || [&self.header, &self.footer]
+                .iter()
+                .any(|t| t.contains("{scene}") || t.contains("{section}"))
     }
-}
 
-/// 添加段落到相应section并更新行映射的辅助函数
-fn add_paragraph_and_update_line_map(
-    child: crate::docx::adapter::docx::SectionChild,
-    line: &Line,
-    scene_or_section_or_tran_started: bool,
-    section_main: &mut crate::docx::adapter::docx::Section,
-    section_main_no_page_num: &mut crate::docx::adapter::docx::Section,
-    line_map: &mut Option<&mut HashMap<usize, LineStruct>>,
-    current_sections: &[String],
-    current_scene: &str,
-    current_page: usize,
-    current_duration: f64,
-) {
-    // 添加段落到相应的 section
-    if scene_or_section_or_tran_started {
-        section_main.children.push(child);
-    } else {
-        section_main_no_page_num.children.push(child);
+    fn expand(&self, template: &str, page: &PageFields) -> String {
+        let sections: Vec<String> = page
+            .sections
+            .iter()
+            .map(|s| crate::utils::strip_style_chars(s).trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        template
+            .replace("{title}", &self.title)
+            .replace("{author}", &self.author)
+            .replace("{date}", &self.date)
+            .replace("{total}", &self.total)
+            .replace("{scene}", crate::utils::strip_style_chars(&page.scene).trim())
+            .replace("{section}", &sections.join(" / "))
     }
 
-    // 更新行映射
-    if let Some(token_line) = line.token {
-        if let Some(ref mut lm) = line_map {
-            lm.insert(
-                token_line,
-                LineStruct {
-                    sections: current_sections.to_vec(),
-                    scene: current_scene.to_string(),
-                    page: current_page,
-                    cumulative_duration: current_duration as f32,
-                },
-            );
+    /// 页眉文本，开启 print_section_header 且模板中没有 {section} 时，章节路径以 " | " 接在后面
+    fn header_text(&self, page: &PageFields) -> String {
+        let mut text = self.expand(&self.header, page);
+        if self.section_in_header && !self.header.contains("{section}") {
+            let sections = self.expand("{section}", page);
+            if !sections.is_empty() {
+                if !text.is_empty() {
+                    text.push_str(" | ");
+                }
+                text.push_str(&sections);
+            }
+        }
+        text
+    }
+
+    fn footer_text(&self, page: &PageFields) -> String {
+        self.expand(&self.footer, page)
+    }
+
+    fn headers(
+        &self,
+        doc: &mut DocxContext,
+        page: &PageFields,
+    ) -> Option<crate::docx::adapter::docx::Headers> {
+        let text = self.header_text(page);
+        if text.is_empty() {
+            return None;
+        }
+        let header = crate::docx::adapter::docx::Header::new();
+        let mut headers = crate::docx::adapter::docx::Headers::new(header);
+        headers
+            .default
+            .children
+            .push(self.paragraph(doc, &text, crate::docx::adapter::AlignmentType::Center));
+        Some(headers)
+    }
+
+    /// 页脚：页脚文本居中，需要时在其后加上右对齐的页码
+    fn footers(
+        &self,
+        doc: &mut DocxContext,
+        page: &PageFields,
+        with_page_numbers: bool,
+    ) -> Option<crate::docx::adapter::docx::Footers> {
+        let mut footer_paragraphs = Vec::new();
+        let text = self.footer_text(page);
+        if !text.is_empty() {
+            footer_paragraphs.push(self.paragraph(
+                doc,
+                &text,
+                crate::docx::adapter::AlignmentType::Center,
+            ));
+        }
+        if with_page_numbers && !self.page_numbers.is_empty() {
+            footer_paragraphs.push(self.page_number_paragraph(doc));
+        }
+        if footer_paragraphs.is_empty() {
+            return None;
+        }
+        let footer = crate::docx::adapter::docx::Footer::new();
+        let mut footers = crate::docx::adapter::docx::Footers::new(footer);
+        footers.default.children = footer_paragraphs;
+        Some(footers)
+    }
+
+    /// 只有页码的页脚，用于没有页眉页脚的前置页
+    fn page_number_footers(
+        &self,
+        doc: &mut DocxContext,
+    ) -> Option<crate::docx::adapter::docx::Footers> {
+        if self.page_numbers.is_empty() {
+            return None;
+        }
+        let footer = crate::docx::adapter::docx::Footer::new();
+        let mut footers = crate::docx::adapter::docx::Footers::new(footer);
+        footers.default.children = vec![self.page_number_paragraph(doc)];
+        Some(footers)
+    }
+
+    // 右对齐的页码段落
+    fn page_number_paragraph(
+        &self,
+        doc: &mut DocxContext,
+    ) -> crate::docx::adapter::docx::Paragraph {
+        let mut page_number_paragraph = crate::docx::adapter::docx::Paragraph::new();
+        page_number_paragraph.align(crate::docx::adapter::AlignmentType::Right);
+
+        // 创建页码运行
+        let page_runs =
+            doc.create_page_number_runs(&self.page_numbers.replace("{total}", &self.total));
+        for run in page_runs {
+            page_number_paragraph.add_run(run);
+        }
+        page_number_paragraph
+    }
+
+    // 使用 text2 方法格式化文本，支持特殊字符，{page} 替换为页码域
+    fn paragraph(
+        &self,
+        doc: &mut DocxContext,
+        text: &str,
+        alignment: crate::docx::adapter::AlignmentType,
+    ) -> crate::docx::adapter::docx::Paragraph {
+        let mut paragraph = crate::docx::adapter::docx::Paragraph::new();
+        paragraph.align(alignment);
+        for (i, part) in text.split("{page}").enumerate() {
+            if i > 0 {
+                let mut page_run = crate::docx::adapter::docx::PageNumberRun::new();
+                page_run.add_page_number();
+                paragraph.add_run(crate::docx::adapter::docx::RunType::PageNumber(page_run));
+            }
+            if !part.is_empty() {
+                for run in doc.format_text(part, &self.options) {
+                    paragraph.add_text_run(run);
+                }
+            }
+        }
+        paragraph
+    }
+}
+
+/// 按每页的场景和章节把正文 section 在分页处拆开：页眉或页脚内容变化的分页符替换为分节（分节本身即换页），
+/// 新 section 沿用原有的页面属性，页码接续上一节，页眉页脚换成对应页的内容。
+/// `hide_first_page_number` 时第一页单独成节，页脚中不含页码
+fn split_section_by_page(
+    doc: &mut DocxContext,
+    section: crate::docx::adapter::docx::Section,
+    page_breaks: &[(usize, usize)],
+    page_fields: &HashMap<usize, PageFields>,
+    start_page: usize,
+    header_footer: &HeaderFooter,
+    hide_first_page_number: bool,
+) -> Vec<crate::docx::adapter::docx::Section> {
+    let crate::docx::adapter::docx::Section {
+        properties,
+        children,
+        ..
+    } = section;
+    let mut continued_properties = properties.clone();
+    if let Some(page) = continued_properties.page.as_mut() {
+        page.page_numbers = Some(crate::docx::adapter::docx::PageNumbers::continued());
+    }
+    let new_section = |doc: &mut DocxContext, page: &PageFields, first: bool| {
+        let mut section = crate::docx::adapter::docx::Section::new();
+        section.properties = if first {
+            properties.clone()
+        } else {
+            continued_properties.clone()
+        };
+        section.headers = header_footer.headers(doc, page);
+        section.footers = header_footer.footers(doc, page, !(first && hide_first_page_number));
+        section
+    };
+    let fields_of = |page: usize| page_fields.get(&page).cloned().unwrap_or_default();
+    let texts_of = |page: &PageFields| {
+        (
+            header_footer.header_text(page),
+            header_footer.footer_text(page),
+        )
+    };
+
+    let first = fields_of(start_page);
+    let mut current = texts_of(&first);
+    let mut result = vec![new_section(doc, &first, true)];
+    let mut breaks = page_breaks.iter().peekable();
+    for (i, child) in children.into_iter().enumerate() {
+        if breaks.peek().is_some_and(|(index, _)| *index == i) {
+            let (_, page) = breaks.next().unwrap();
+            let fields = fields_of(*page);
+            let texts = texts_of(&fields);
+            if texts != current || (hide_first_page_number && result.len() == 1) {
+                current = texts;
+                result.push(new_section(doc, &fields, false));
+                continue;
+            }
+        }
+        result.last_mut().unwrap().children.push(child);
+    }
+    result
+}
+
+/// A/V 两栏表格中的一行：左栏画面，右栏声音
+#[derive(Default)]
+struct AvRow {
+    video: Vec<crate::docx::adapter::docx::Paragraph>,
+    audio: Vec<crate::docx::adapter::docx::Paragraph>,
+}
+
+/// 以这些前缀开头的动作行视为音效或音乐，放在声音栏
+const AV_AUDIO_PREFIXES: [&str; 6] = ["SFX", "MUSIC", "SOUND", "音效", "音乐", "声音"];
+
+/// 生成 A/V 两栏格式的正文
+///
+/// 每个场景一张占满版心宽度的两栏表格，首行为栏目标题。动作、转场、居中文本放在左栏，
+/// 角色名、括号、对白、歌词以及以 SFX、MUSIC、音效等开头的动作行放在右栏；
+/// 画面之后出现的声音与该画面同行，声音之后再出现画面时另起一行。
+/// 场景标题和章节在表格之外单独成段，分页符结束当前表格并换页。
+fn add_av_script(
+    doc: &mut DocxContext,
+    parsed: &ParseOutput,
+    spacing: &ParagraphSpacing,
+    text_options: &HashMap<String, String>,
+    section: &mut crate::docx::adapter::docx::Section,
+) {
+    let print = doc.options.print_profile.clone();
+    let column_width =
+        convert_inches_to_twip(print.page_width - print.left_margin - print.right_margin) / 2;
+    use crate::utils::fountain_constants::FountainConstants;
+    let style_chars = FountainConstants::style_chars();
+    let bold = style_chars.get("bold").unwrap().to_string();
+    let italic = style_chars.get("italic").unwrap().to_string();
+
+    let mut rows: Vec<AvRow> = Vec::new();
+
+    for token in &parsed.tokens {
+        if token.ignore {
+            continue;
+        }
+        let text = token.text.as_str();
+        match token.token_type.as_str() {
+            "scene_heading" | "section" => {
+                flush_av_table(doc, &mut rows, column_width, spacing, text_options, &bold, section);
+                let mut paragraph =
+                    crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+                paragraph.style(if token.token_type == "section" {
+                    "section"
+                } else {
+                    "scene"
+                });
+                for run in doc.text2(text, text_options, None, None) {
+                    paragraph.add_text_run(run);
+                }
+                section
+                    .children
+                    .push(crate::docx::adapter::docx::SectionChild::Paragraph(paragraph));
+            }
+            "page_break" => {
+                flush_av_table(doc, &mut rows, column_width, spacing, text_options, &bold, section);
+                section
+                    .children
+                    .push(crate::docx::adapter::docx::SectionChild::PageBreak);
+            }
+            "action" | "centered" | "transition" => {
+                let plain = crate::utils::strip_style_chars(text);
+                let plain = plain.trim_start().to_uppercase();
+                if AV_AUDIO_PREFIXES.iter().any(|p| plain.starts_with(p)) {
+                    let paragraphs = av_paragraphs(doc, text, "", text_options, spacing);
+                    av_current_row(&mut rows).audio.extend(paragraphs);
+                } else {
+                    // 声音之后的画面另起一行
+                    if rows.last().is_none_or(|row| !row.audio.is_empty()) {
+                        rows.push(AvRow::default());
+                    }
+                    let paragraphs = av_paragraphs(doc, text, "", text_options, spacing);
+                    rows.last_mut().unwrap().video.extend(paragraphs);
+                }
+            }
+            "character" => {
+                let paragraphs = av_paragraphs(doc, text, &bold, text_options, spacing);
+                av_current_row(&mut rows).audio.extend(paragraphs);
+            }
+            "parenthetical" => {
+                let paragraphs = av_paragraphs(doc, text, &italic, text_options, spacing);
+                av_current_row(&mut rows).audio.extend(paragraphs);
+            }
+            "dialogue" => {
+                let paragraphs = av_paragraphs(doc, text, "", text_options, spacing);
+                av_current_row(&mut rows).audio.extend(paragraphs);
+            }
+            "lyric" => {
+                let paragraphs =
+                    av_paragraphs(doc, &format!("♪ {}", text), &italic, text_options, spacing);
+                av_current_row(&mut rows).audio.extend(paragraphs);
+            }
+            _ => {}
+        }
+    }
+    flush_av_table(doc, &mut rows, column_width, spacing, text_options, &bold, section);
+}
+
+fn av_current_row(rows: &mut Vec<AvRow>) -> &mut AvRow {
+    if rows.is_empty() {
+        rows.push(AvRow::default());
+    }
+    rows.last_mut().unwrap()
+}
+
+// 每行文本一个段落，`marker` 为包在每行首尾的样式标记（如粗体），为空时不加
+fn av_paragraphs(
+    doc: &mut DocxContext,
+    text: &str,
+    marker: &str,
+    options: &HashMap<String, String>,
+    spacing: &ParagraphSpacing,
+) -> Vec<crate::docx::adapter::docx::Paragraph> {
+    text.split('\n')
+        .map(|line| {
+            let mut paragraph =
+                crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+            let line = format!("{}{}{}", marker, line, marker);
+            for run in doc.text2(&line, options, None, None) {
+                paragraph.add_text_run(run);
+            }
+            paragraph
+        })
+        .collect()
+}
+
+/// 把已收集的行输出为一张两栏表格（带栏目标题行），表格后补一个空段落以免相邻表格合并
+fn flush_av_table(
+    doc: &mut DocxContext,
+    rows: &mut Vec<AvRow>,
+    column_width: i32,
+    spacing: &ParagraphSpacing,
+    text_options: &HashMap<String, String>,
+    bold: &str,
+    section: &mut crate::docx::adapter::docx::Section,
+) {
+    if rows.is_empty() {
+        return;
+    }
+    let cell = |children: Vec<crate::docx::adapter::docx::Paragraph>| {
+        let mut cell = crate::docx::adapter::docx::TableCell::new();
+        cell.width = Some(crate::docx::adapter::docx::TableWidth {
+            width_type: crate::docx::adapter::WidthType::DXA,
+            size: column_width,
+        });
+        // 空单元格也需要一个段落
+        cell.children = if children.is_empty() {
+            vec![crate::docx::adapter::docx::Paragraph::new_with_spacing(
+                spacing.clone(),
+            )]
+        } else {
+            children
+        };
+        cell
+    };
+
+    let mut table = crate::docx::adapter::docx::Table::new();
+    table.columnWidths(vec![column_width as usize, column_width as usize]);
+
+    let mut header = crate::docx::adapter::docx::TableRow::new();
+    header.cells = ["画面", "声音"]
+        .iter()
+        .map(|title| cell(av_paragraphs(doc, title, bold, text_options, spacing)))
+        .collect();
+    table.rows.push(header);
+
+    for row in rows.drain(..) {
+        let mut table_row = crate::docx::adapter::docx::TableRow::new();
+        table_row.cells = vec![cell(row.video), cell(row.audio)];
+        table.rows.push(table_row);
+    }
+
+    section
+        .children
+        .push(crate::docx::adapter::docx::SectionChild::Table(table));
+    section
+        .children
+        .push(crate::docx::adapter::docx::SectionChild::Paragraph(
+            crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone()),
+        ));
+}
+
+/// 附录页（角色表、地点表）：居中加粗的标题，之后每行文本一段，空字符串为空行；不设页眉页脚
+fn appendix_section(
+    doc: &mut DocxContext,
+    title: &str,
+    lines: &[String],
+    properties: &crate::docx::adapter::docx::SectionProperties,
+    spacing: &ParagraphSpacing,
+    text_options: &HashMap<String, String>,
+) -> crate::docx::adapter::docx::Section {
+    use crate::utils::fountain_constants::FountainConstants;
+    let bold = FountainConstants::style_chars()["bold"];
+
+    let mut section = crate::docx::adapter::docx::Section::new();
+    section.properties = properties.clone();
+
+    let mut heading = crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+    heading.align(crate::docx::adapter::AlignmentType::Center);
+    let title = format!("{}{}{}", bold, title, bold);
+    for run in doc.text2(&title, text_options, None, None) {
+        heading.add_text_run(run);
+    }
+    section
+        .children
+        .push(crate::docx::adapter::docx::SectionChild::Paragraph(heading));
+
+    for line in std::iter::once("").chain(lines.iter().map(String::as_str)) {
+        let mut paragraph =
+            crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+        if !line.is_empty() {
+            for run in doc.text2(line, text_options, None, None) {
+                paragraph.add_text_run(run);
+            }
+        }
+        section
+            .children
+            .push(crate::docx::adapter::docx::SectionChild::Paragraph(
+                paragraph,
+            ));
+    }
+    section
+}
+
+/// 目录页的标题
+const TOC_TITLE: &str = "目录";
+
+/// 目录条目：正文中的场景标题或章节，`bookmark` 为其所在段落的书签名
+struct TocEntry {
+    bookmark: String,
+    text: String,
+    /// 缩进层级，章节按层级递进，场景随所在章节
+    depth: usize,
+}
+
+impl TocEntry {
+    fn new(bookmark: String, line: &Line, depth: usize) -> Self {
+        let heading = crate::utils::strip_style_chars(&line.text)
+            .trim()
+            .to_string();
+        let text = match line.number.as_deref().map(|n| n.trim_start_matches('↑')) {
+            Some(number) if line.token_type == "scene_heading" && !number.is_empty() => {
+                format!("{} {}", number, heading)
+            }
+            _ => heading,
+        };
+        TocEntry {
+            bookmark,
+            text,
+            depth,
+        }
+    }
+}
+
+/// 目录页：标题与附录页相同，每个条目一段，按层级缩进，点击跳转到正文中对应的书签
+fn toc_section(
+    doc: &mut DocxContext,
+    entries: &[TocEntry],
+    print: &PrintProfile,
+    properties: &crate::docx::adapter::docx::SectionProperties,
+    spacing: &ParagraphSpacing,
+    text_options: &HashMap<String, String>,
+) -> crate::docx::adapter::docx::Section {
+    let mut section = appendix_section(doc, TOC_TITLE, &[], properties, spacing, text_options);
+    for entry in entries {
+        let mut paragraph =
+            crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+        paragraph.indent(convert_inches_to_twip(
+            entry.depth as f32 * print.section.level_indent,
+        ));
+        paragraph.add_text_run(
+            TextRun::with_props(&entry.text, doc.run_normal.clone()).link_to(&entry.bookmark),
+        );
+        section
+            .children
+            .push(crate::docx::adapter::docx::SectionChild::Paragraph(
+                paragraph,
+            ));
+    }
+    section
+}
+
+/// 版本记录页：居中加粗的标题，之后是带表头的版本记录表，各栏平分正文宽度；不设页眉页脚
+fn version_history_section(
+    doc: &mut DocxContext,
+    entries: &[crate::version_history::VersionEntry],
+    print: &PrintProfile,
+    properties: &crate::docx::adapter::docx::SectionProperties,
+    spacing: &ParagraphSpacing,
+    text_options: &HashMap<String, String>,
+) -> crate::docx::adapter::docx::Section {
+    use crate::utils::fountain_constants::FountainConstants;
+    use crate::version_history::{VERSION_HISTORY_COLUMNS, VERSION_HISTORY_TITLE};
+    let bold = FountainConstants::style_chars()["bold"];
+
+    // 标题和空行与附录页相同
+    let mut section = appendix_section(
+        doc,
+        VERSION_HISTORY_TITLE,
+        &[],
+        properties,
+        spacing,
+        text_options,
+    );
+
+    let column_width =
+        convert_inches_to_twip(print.page_width - print.left_margin - print.right_margin)
+            / VERSION_HISTORY_COLUMNS.len() as i32;
+    let row = |doc: &mut DocxContext, cells: [&str; 5], header: bool| {
+        let mut table_row = crate::docx::adapter::docx::TableRow::new();
+        for text in cells {
+            let mut paragraph =
+                crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+            let text = if header && !text.is_empty() {
+                format!("{}{}{}", bold, text, bold)
+            } else {
+                text.to_string()
+            };
+            for run in doc.text2(&text, text_options, None, None) {
+                paragraph.add_text_run(run);
+            }
+            let mut cell = crate::docx::adapter::docx::TableCell::new();
+            cell.width = Some(crate::docx::adapter::docx::TableWidth {
+                width_type: crate::docx::adapter::WidthType::DXA,
+                size: column_width,
+            });
+            cell.children = vec![paragraph];
+            table_row.cells.push(cell);
+        }
+        table_row
+    };
+
+    let mut table = crate::docx::adapter::docx::Table::new();
+    table.columnWidths(vec![column_width as usize; VERSION_HISTORY_COLUMNS.len()]);
+    table.rows.push(row(doc, VERSION_HISTORY_COLUMNS, true));
+    for entry in entries {
+        table.rows.push(row(doc, entry.cells(), false));
+    }
+    section
+        .children
+        .push(crate::docx::adapter::docx::SectionChild::Table(table));
+    section
+}
+
+/// 获取歌词的对齐方式
+fn get_lyric_alignment(alignment: &str) -> Option<crate::docx::adapter::AlignmentType> {
+    match alignment {
+        "center" => Some(crate::docx::adapter::AlignmentType::Center),
+        "right" => Some(crate::docx::adapter::AlignmentType::Right),
+        _ => None, // 默认左对齐
+    }
+}
+
+/// 创建基础选项映射
+fn create_basic_options_map(color: &str) -> HashMap<String, String> {
+    let mut options_map = HashMap::new();
+    options_map.insert("color".to_string(), color.to_string());
+    options_map
+}
+
+/// 去掉注释首尾的标记字符，返回注释正文的各行
+fn note_text_lines(note: &Note) -> Vec<String> {
+    let last = note.text.len().saturating_sub(1);
+    note.text
+        .iter()
+        .enumerate()
+        .map(|(k, text_line)| {
+            let mut text = text_line.clone();
+
+            // 去掉第一个字符（脚注开始标记）
+            if k == 0 {
+                if let Some(first_char) = text.chars().next() {
+                    text = text[first_char.len_utf8()..].to_string();
+                }
+            }
+
+            // 去掉最后一个字符（脚注结束标记）
+            if k == last {
+                if let Some(last_char) = text.chars().last() {
+                    text.truncate(text.len() - last_char.len_utf8());
+                }
+            }
+            text
+        })
+        .collect()
+}
+
+/// 尾注模式：在正文末尾另起一页，按编号列出所有注释，并注明注释所在的场景
+fn add_endnotes(
+    doc: &mut DocxContext,
+    notes: &[Vec<Note>],
+    note_scenes: &HashMap<usize, String>,
+    print: &PrintProfile,
+    section_main: &mut crate::docx::adapter::docx::Section,
+) {
+    section_main
+        .children
+        .push(crate::docx::adapter::docx::SectionChild::PageBreak);
+
+    let mut heading = crate::docx::adapter::docx::Paragraph::new();
+    heading.style("section");
+    heading.add_text_run(crate::docx::adapter::docx::TextRun::new("尾注"));
+    section_main
+        .children
+        .push(crate::docx::adapter::docx::SectionChild::Paragraph(heading));
+
+    let mut note_options = create_basic_options_map("#868686");
+    note_options.insert("fontSize".to_string(), print.note_font_size.to_string());
+    note_options.insert("characterSpacing".to_string(), "0".to_string());
+
+    for note in notes.iter().flatten() {
+        let mut lines = note_text_lines(note);
+        if let Some(first) = lines.first_mut() {
+            *first = format!(" {}", first);
+        }
+        // 回指注释所在的场景，场景之前的注释不加说明
+        if let Some(scene) = note_scenes.get(&note.no) {
+            let scene = crate::utils::strip_style_chars(scene).trim().to_string();
+            if !scene.is_empty() {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(&format!("（场景：{}）", scene));
+                }
+            }
+        }
+
+        for (k, text) in lines.iter().enumerate() {
+            let mut paragraph = crate::docx::adapter::docx::Paragraph::new();
+            paragraph.style("notes");
+            if k == 0 {
+                // 编号是注释的位置，点击回到正文中的引用
+                let bookmark = doc.next_bookmark(&note_bookmark(note.no));
+                paragraph.add_text_run(
+                    crate::docx::adapter::docx::TextRun::with_props(
+                        &format!("[{}]", note.no),
+                        doc.run_notes.clone(),
+                    )
+                    .bookmark(bookmark)
+                    .link_to(&note_ref_bookmark(note.no)),
+                );
+                paragraph.indent_first_line(convert_inches_to_twip(-2.0 * print.font_width)); //负数，悬挂缩进
+            }
+            for run in doc.format_text(text, &note_options) {
+                paragraph.add_text_run(run);
+            }
+            section_main
+                .children
+                .push(crate::docx::adapter::docx::SectionChild::Paragraph(paragraph));
+        }
+    }
+}
+
+/// 尾注中第 `no` 个注释的书签名（以下划线开头，在 Word 的书签列表中隐藏）
+fn note_bookmark(no: usize) -> String {
+    format!("_Note{}", no)
+}
+
+/// 正文中第 `no` 个注释引用处的书签名
+fn note_ref_bookmark(no: usize) -> String {
+    format!("_NoteRef{}", no)
+}
+
+/// 确定性输出使用的固定时间：设置了 `source_date_epoch` 时为该时间，否则为 1980-01-01；不是确定性输出时为 None
+pub(crate) fn deterministic_timestamp(config: &Conf) -> Option<String> {
+    config
+        .source_date_epoch
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|time| time.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .or_else(|| {
+            config
+                .deterministic
+                .then(|| crate::docx::adapter::docx::DETERMINISTIC_TIMESTAMP.to_string())
+        })
+}
+
+/// 批注、修订记录的时间，确定性输出时为固定的时间
+pub(crate) fn export_timestamp(config: &Conf) -> String {
+    deterministic_timestamp(config)
+        .unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string())
+}
+
+/// 把收集到的注释转换为 Word 批注，以注释编号为键
+fn note_comments(
+    doc: &mut DocxContext,
+    notes: &[Vec<Note>],
+    author: &str,
+    print: &PrintProfile,
+) -> HashMap<usize, crate::docx::adapter::docx::Comment> {
+    let date = export_timestamp(&doc.options.config);
+    let mut note_options = create_basic_options_map("#000000");
+    note_options.insert("fontSize".to_string(), print.note_font_size.to_string());
+    note_options.insert("characterSpacing".to_string(), "0".to_string());
+
+    let mut comments = HashMap::new();
+    for note in notes.iter().flatten() {
+        let mut comment = crate::docx::adapter::docx::Comment::new(note.no);
+        comment.author = author.to_string();
+        comment.date = date.clone();
+        for text in note_text_lines(note) {
+            let mut paragraph = crate::docx::adapter::docx::Paragraph::new();
+            for run in doc.format_text(&text, &note_options) {
+                paragraph.add_text_run(run);
+            }
+            comment.children.push(paragraph);
+        }
+        comments.insert(note.no, comment);
+    }
+    comments
+}
+
+/// 完成中文格式对话和双对话处理的辅助函数
+fn finish_dialogue_processing(
+    doc: &mut DocxContext,
+    china_format: i32,
+    token_type: &str,
+    scene_or_section_or_tran_started: bool,
+    section_main: &mut crate::docx::adapter::docx::Section,
+    section_main_no_page_num: &mut crate::docx::adapter::docx::Section,
+    print: &PrintProfile,
+    spacing: &ParagraphSpacing,
+) {
+    // 完成中文格式对话处理
+    if china_format > 0 {
+        doc.finish_china_dial_first(
+            if scene_or_section_or_tran_started {
+                section_main
+            } else {
+                section_main_no_page_num
+            },
+            spacing,
+        );
+    }
+
+    // 检查是否需要完成双对话处理
+    let has_right_table_content = !doc.last_dial_table_right.is_empty();
+    let has_left_or_right_cache = doc.last_dial_gr_left.is_some()
+        || doc.last_dial_gr_right.is_some()
+        || doc.last_dial_gr_middle.is_some();
+    let has_global_table_content = !doc.last_dial_table_left.is_empty()
+        || !doc.last_dial_table_right.is_empty()
+        || !doc.last_dial_table_middle.is_empty();
+
+    if has_right_table_content
+        || has_left_or_right_cache
+        || has_global_table_content
+        || token_type != "separator"
+    {
+        doc.finish_double_dial(
+            if scene_or_section_or_tran_started {
+                section_main
+            } else {
+                section_main_no_page_num
+            },
+            print,
+            spacing,
+        );
+    }
+}
+
+/// 双对话表格的各行，每行依次是各栏（左、中、右）单元格中的段落
+///
+/// 不平衡时只有一行，每栏放全部段落；平衡时按 `kind`（段落类型：角色名、括号注释、对白）逐段配对，
+/// 同类的段落放在同一行。某栏当前的段落在别的栏中还要等几段才出现时（如只有一边有括号注释），
+/// 这一栏在本行留空，等另一栏补上多出的段落；较短的栏在后面的行中留空。
+pub fn dual_dialogue_rows<T: Clone, K: PartialEq>(
+    columns: &[&[T]],
+    balance: bool,
+    kind: impl Fn(&T) -> K,
+) -> Vec<Vec<Vec<T>>> {
+    if !balance {
+        return vec![columns.iter().map(|column| column.to_vec()).collect()];
+    }
+    let mut next = vec![0; columns.len()];
+    let mut rows = Vec::new();
+    loop {
+        let heads: Vec<Option<K>> = columns
+            .iter()
+            .zip(&next)
+            .map(|(column, &i)| column.get(i).map(&kind))
+            .collect();
+        if heads.iter().all(Option::is_none) {
+            break;
+        }
+        // 当前段落的类型在另一栏后面才出现（那一栏当前是别的类型）时，本栏先等一行
+        let waiting: Vec<bool> = heads
+            .iter()
+            .enumerate()
+            .map(|(c, head)| {
+                let Some(head) = head else {
+                    return false;
+                };
+                columns
+                    .iter()
+                    .zip(&next)
+                    .enumerate()
+                    .any(|(d, (column, &i))| {
+                        d != c
+                            && heads[d].as_ref().is_some_and(|other| other != head)
+                            && column[i..].iter().any(|item| kind(item) == *head)
+                    })
+            })
+            .collect();
+        // 各栏互相等待（类型交错）时只输出最左边的一栏，保证每行至少前进一段
+        let first = heads.iter().position(Option::is_some).unwrap_or(0);
+        let all_waiting = heads
+            .iter()
+            .zip(&waiting)
+            .all(|(head, &wait)| head.is_none() || wait);
+        let row = columns
+            .iter()
+            .enumerate()
+            .map(|(c, column)| {
+                let take = heads[c].is_some() && if all_waiting { c == first } else { !waiting[c] };
+                if take {
+                    next[c] += 1;
+                    vec![column[next[c] - 1].clone()]
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+        rows.push(row);
+    }
+    rows
+}
+
+// 把缓存的对话段（角色名与对白拼在一起的中文格式）转为段落
+fn cached_group_paragraph(
+    group: CachedDialogueGroup,
+    spacing: &ParagraphSpacing,
+) -> crate::docx::adapter::docx::Paragraph {
+    let mut paragraph = crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+    paragraph.style(&group.style);
+    if let Some(left) = group.indent_left {
+        paragraph.indent(left);
+    }
+    if let Some(right) = group.indent_right {
+        paragraph.indent_right(right);
+    }
+    for run in group.children {
+        paragraph.add_text_run(run);
+    }
+    paragraph
+}
+
+/// 添加段落到相应section并更新行映射的辅助函数
+fn add_paragraph_and_update_line_map(
+    mut child: crate::docx::adapter::docx::SectionChild,
+    line: &Line,
+    options: &DocxOptions,
+    scene_or_section_or_tran_started: bool,
+    section_main: &mut crate::docx::adapter::docx::Section,
+    section_main_no_page_num: &mut crate::docx::adapter::docx::Section,
+    line_map: &mut Option<&mut HashMap<usize, LineStruct>>,
+    current_sections: &[String],
+    current_scene: &str,
+    current_page: usize,
+    current_duration: f64,
+) {
+    // 有改动的行加修订线或修订标记
+    if let (crate::docx::adapter::docx::SectionChild::Paragraph(paragraph), Some(token_line)) =
+        (&mut child, line.token)
+    {
+        if options.changed_lines.contains(&token_line) {
+            paragraph.change_bar(true);
+        }
+        if let Some(revision) = options.revisions.get(&token_line) {
+            paragraph.revision(revision.clone());
+        }
+    }
+
+    // 添加段落到相应的 section
+    if scene_or_section_or_tran_started {
+        section_main.children.push(child);
+    } else {
+        section_main_no_page_num.children.push(child);
+    }
+
+    // 更新行映射
+    if let Some(token_line) = line.token {
+        if let Some(ref mut lm) = line_map {
+            lm.insert(
+                token_line,
+                LineStruct {
+                    sections: current_sections.to_vec(),
+                    scene: current_scene.to_string(),
+                    page: current_page,
+                    cumulative_duration: current_duration as f32,
+                },
+            );
         }
     }
 }
@@ -2114,18 +3518,10 @@ fn add_text_runs_to_paragraph(
     }
 }
 
-/// 处理场景编号的辅助函数
+/// 处理场景编号的辅助函数：按 scenes_numbers 返回左侧和右侧的编号，不显示的一侧为空
 fn process_scene_number(scene_number: &str, scenes_numbers: &str) -> (String, String) {
-    let scene_text_length = scene_number.chars().count();
-
-    let left_char = if scene_text_length < 3 {
-        3 - scene_text_length
-    } else {
-        0
-    };
-
     let left_scene_number = if scenes_numbers == "both" || scenes_numbers == "left" {
-        format!("{}{}", " ".repeat(left_char), scene_number)
+        scene_number.to_string()
     } else {
         String::new()
     };
@@ -2174,7 +3570,19 @@ pub fn generate(
     let line_height = options.line_height;
     let mut print_title_page = cfg.print_title_page;
     let mut print_preface_page = cfg.print_preface_page;
+    let mut print_cast_list = cfg.print_cast_list;
+    let mut print_version_history = cfg.print_version_history;
+    let mut print_toc = cfg.print_toc;
+    let mut print_line_numbers = cfg.print_line_numbers;
+    let mut page_number_start = cfg.page_number_start;
+    let mut hide_first_page_number = cfg.hide_first_page_number;
+    let mut front_matter_page_numbers = cfg.front_matter_page_numbers;
+    let mut docx_protection = cfg.docx_protection.clone();
+    let mut print_locations_appendix = cfg.print_locations_appendix;
     let mut scenes_numbers = cfg.scenes_numbers.clone();
+    let mut scene_heading_color = cfg.scene_heading_color.clone();
+    let mut character_color = cfg.character_color.clone();
+    let mut transition_color = cfg.transition_color.clone();
 
     // 创建行间距配置
     // 使用adapter中的统一函数
@@ -2182,20 +3590,71 @@ pub fn generate(
     // 从元数据中获取配置
     if let Some(metadata) = &options.metadata {
         if metadata.contains_key("print") {
+            let flag = |key: &str| metadata.get(key).and_then(|v| parse_metadata_bool(v));
             if let Some(china_format_str) = metadata.get("print.chinaFormat") {
                 china_format = china_format_str.parse::<i32>().unwrap_or(0);
             }
-            if let Some(print_title_page_str) = metadata.get("print.print_title_page") {
-                print_title_page = print_title_page_str != "0";
+            if let Some(value) = flag("print.print_title_page") {
+                print_title_page = value;
+            }
+            if let Some(value) = flag("print.print_preface_page") {
+                print_preface_page = value;
+            }
+            if let Some(value) = flag("print.print_cast_list") {
+                print_cast_list = value;
+            }
+            if let Some(line_numbers_str) = metadata.get("print.print_line_numbers") {
+                print_line_numbers = line_numbers_str.parse::<u32>().unwrap_or(0);
+            }
+            if let Some(value) = flag("print.print_version_history") {
+                print_version_history = value;
+            }
+            if let Some(value) = flag("print.print_toc") {
+                print_toc = value;
+            }
+            if let Some(start_str) = metadata.get("print.page_number_start") {
+                page_number_start = start_str.parse::<u32>().unwrap_or(1);
             }
-            if let Some(print_preface_page_str) = metadata.get("print.print_preface_page") {
-                print_preface_page = print_preface_page_str != "0";
+            if let Some(value) = flag("print.hide_first_page_number") {
+                hide_first_page_number = value;
+            }
+            if let Some(value) = flag("print.front_matter_page_numbers") {
+                front_matter_page_numbers = value;
+            }
+            if let Some(protection_str) = metadata.get("print.docx_protection") {
+                docx_protection = protection_str.clone();
+            }
+            if let Some(value) = flag("print.print_locations_appendix") {
+                print_locations_appendix = value;
             }
             if let Some(scenes_numbers_str) = metadata.get("print.scenes_numbers") {
                 scenes_numbers = scenes_numbers_str.clone();
             }
+            if let Some(color) = metadata.get("print.scene_heading_color") {
+                scene_heading_color = color.clone();
+            }
+            if let Some(color) = metadata.get("print.character_color") {
+                character_color = color.clone();
+            }
+            if let Some(color) = metadata.get("print.transition_color") {
+                transition_color = color.clone();
+            }
         }
     }
+    // 关闭国内剧本格式扩展时按国际格式排版
+    if !cfg.extension_enabled(Extension::ChinaFormat) {
+        china_format = 0;
+    }
+    // 不认识的颜色忽略
+    let scene_heading_color = parse_color(&scene_heading_color);
+    let character_color = parse_color(&character_color);
+    let transition_color = parse_color(&transition_color);
+    // 试镜台词本选定的角色，别名换成规范角色名
+    let sides_characters: Vec<String> = options
+        .sides_characters
+        .iter()
+        .map(|name| parsed.properties.canonical_character(name))
+        .collect();
 
     // 设置文档属性
     let title_token = parsed.tokens.iter().find(|t| t.token_type == "title");
@@ -2217,36 +3676,16 @@ pub fn generate(
         doc.doc.custom_property("title", &title_text);
     }
 
-    let section_props = crate::docx::adapter::docx::SectionProperties {
-        page: Some(crate::docx::adapter::docx::PageProperties {
-            size: Some(crate::docx::adapter::docx::PageSize::new(
-                convert_inches_to_twip(print.page_height),
-                convert_inches_to_twip(print.page_width),
-            )),
-            margin: Some(crate::docx::adapter::docx::PageMargin::new(
-                convert_inches_to_twip(print.top_margin),
-                convert_inches_to_twip(print.right_margin),
-                convert_inches_to_twip(print.bottom_margin),
-                convert_inches_to_twip(print.left_margin),
-                convert_inches_to_twip(print.page_number_top_margin),
-                convert_inches_to_twip(if (print.page_number_top_margin - line_height < 0.2) {
-                    0.2
-                } else {
-                    print.page_number_top_margin - line_height
-                }),
-            )),
-            page_numbers: Some(crate::docx::adapter::docx::PageNumbers::new(1)),
-        }),
-    };
+    let section_props = page_section_properties(print, line_height, print.vertical_text);
 
     // 处理标题页
-    println!(
+    debug_log!(
         "【generate】开始处理标题页，print_title_page={}, 标题页元素数量={}, 标题页是否已处理={}",
         print_title_page,
         parsed.title_page.len(),
         doc.options.title_page_processed
     );
-    println!("【generate】DocxContext 对象 ID: {:p}", doc);
+    debug_log!("【generate】DocxContext 对象 ID: {:p}", doc);
 
     let mut section_title_page: Option<crate::docx::adapter::docx::Section> = None;
 
@@ -2260,24 +3699,29 @@ pub fn generate(
                 _ => false,
             });
 
-        println!("【generate】标题页内容检查结果: {}", has_title_content);
+        debug_log!("【generate】标题页内容检查结果: {}", has_title_content);
 
         if has_title_content {
             // 标记标题页已处理
             doc.options.title_page_processed = true;
-            println!("【generate】标题页处理标志已设置为 true");
+            debug_log!("【generate】标题页处理标志已设置为 true");
 
             // 创建标题页 section
             let mut title_section = crate::docx::adapter::docx::Section::new();
-            title_section.properties = section_props.clone();
+            // 标题页各部分用框架按页面坐标定位，竖排时仍保持横排
+            title_section.properties = page_section_properties(print, line_height, false);
 
             // 计算页面尺寸（一次性计算，避免重复）
             let dimensions = calculate_page_dimensions(print, convert_point_to_inches(12.0)); //标题页固定单倍行距s所以用240twip（12磅）,参数传入的单位需要的是 英寸
 
+            let title_template = TitlePageTemplate::from_name(&cfg.title_page_template);
+            let underline_char =
+                crate::utils::fountain_constants::FountainConstants::style_chars()["underline"];
+
             // 处理标题页内容（按固定顺序：tl | tc | tr | cc | bl | br）
             for key in ["tl", "tc", "tr", "cc", "bl", "br"] {
                 if let Some(tokens) = parsed.title_page.get(key) {
-                    println!(
+                    debug_log!(
                         "【generate】处理标题页元素: {} (包含 {} 个 token)",
                         key,
                         tokens.len()
@@ -2293,18 +3737,60 @@ pub fn generate(
                             }
                         });
 
-                        let mut text = String::new();
+                        // 各字段的文本和字体，字体由标题页模板决定
+                        let mut parts: Vec<(String, HashMap<String, String>)> = Vec::new();
+                        let mut images = Vec::new();
                         for token in sorted_tokens {
-                            if !text.is_empty() {
-                                text.push_str("\n\n");
+                            if token.token_type == "image" {
+                                match load_image_run(token.metadata.as_ref(), &options.filepath) {
+                                    Some(image) => images.push(image),
+                                    None => {
+                                        debug_log!("【generate】无法读取标题页图片: {}", token.text)
+                                    }
+                                }
+                                continue;
+                            }
+                            let mut part_text = if parts.is_empty() {
+                                String::new()
+                            } else {
+                                "\n\n".to_string()
+                            };
+                            let mut part_options = create_basic_options_map("#000000");
+                            match title_template.and_then(|t| t.field(&token.token_type)) {
+                                Some(field) => {
+                                    let field_text = if field.uppercase {
+                                        token.text.to_uppercase()
+                                    } else {
+                                        token.text.clone()
+                                    };
+                                    if field.underline {
+                                        part_text.push_str(&format!(
+                                            "{}{}{}",
+                                            underline_char, field_text, underline_char
+                                        ));
+                                    } else {
+                                        part_text.push_str(&field_text);
+                                    }
+                                    if let Some(size) = field.size {
+                                        part_options.insert(
+                                            "fontSize".to_string(),
+                                            size.round().to_string(),
+                                        );
+                                    }
+                                    if field.bold {
+                                        part_options.insert("bold".to_string(), "true".to_string());
+                                    }
+                                }
+                                None => part_text.push_str(&token.text),
                             }
-                            text.push_str(&token.text);
+                            parts.push((part_text, part_options));
                         }
+                        let text: String = parts.iter().map(|(text, _)| text.as_str()).collect();
 
-                        println!("【generate】标题页元素 {} 文本内容: {}", key, text);
+                        debug_log!("【generate】标题页元素 {} 文本内容: {}", key, text);
 
                         // 创建段落
-                        if !text.is_empty() {
+                        if !text.is_empty() || !images.is_empty() {
                             let mut paragraph = crate::docx::adapter::docx::Paragraph::new();
 
                             // 设置对齐方式
@@ -2315,13 +3801,22 @@ pub fn generate(
                             // 设置框架属性
                             paragraph.frame(create_title_frame(key, &dimensions));
 
-                            // 处理文本格式化
-                            let options_map = create_basic_options_map("#000000");
-                            let text_runs = doc.text2(&text, &options_map, None, None);
+                            // 图片在该区域文字之前
+                            let has_images = !images.is_empty();
+                            for image in images {
+                                paragraph.add_image_run(image);
+                            }
+                            if has_images && !text.is_empty() {
+                                paragraph.add_break_run(crate::docx::adapter::docx::BreakRun::new(
+                                    crate::docx::adapter::BreakType::TextWrapping,
+                                ));
+                            }
 
-                            // 添加文本运行到段落
-                            for run in text_runs {
-                                paragraph.add_text_run(run);
+                            // 处理文本格式化
+                            for (part_text, part_options) in &parts {
+                                for run in doc.text2(part_text, part_options, None, None) {
+                                    paragraph.add_text_run(run);
+                                }
                             }
 
                             // 添加段落到标题页 section
@@ -2329,16 +3824,16 @@ pub fn generate(
                                 crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
                             );
 
-                            println!("【generate】已添加标题页元素 {} 到标题页 section", key);
+                            debug_log!("【generate】已添加标题页元素 {} 到标题页 section", key);
                         }
                     }
                 }
             }
             section_title_page = Some(title_section);
         }
-        println!("【generate】标题页处理完成");
+        debug_log!("【generate】标题页处理完成");
     } else {
-        println!(
+        debug_log!(
             "【generate】跳过标题页处理，print_title_page={}, 标题页元素数量={}",
             print_title_page,
             parsed.title_page.len()
@@ -2352,7 +3847,7 @@ pub fn generate(
     // 序言页不设置页眉页脚
     // 根据您的要求：序言页应该不显示页码，也不显示页眉页脚
     // 这样适配器层就不会检测到序言页有页眉页脚，从而不会设置全局页眉页脚
-    println!("【generate】序言页不设置页眉页脚，确保序言页不显示页码");
+    debug_log!("【generate】序言页不设置页眉页脚，确保序言页不显示页码");
 
     // 预计算常用的选项映射（避免重复创建）
     let header_footer_options = create_basic_options_map("#777777");
@@ -2360,66 +3855,13 @@ pub fn generate(
     // 创建主要内容 section（有页码）
     let mut section_main = crate::docx::adapter::docx::Section::new();
     section_main.properties = section_props.clone();
-
-    // 添加页眉（有页码）
-    if !cfg.print_header.is_empty() {
-        let mut header_paragraph = crate::docx::adapter::docx::Paragraph::new();
-        header_paragraph.align(crate::docx::adapter::AlignmentType::Center);
-
-        // 使用 text2 方法格式化页眉文本，支持特殊字符
-        let header_runs = doc.format_text(&cfg.print_header, &header_footer_options);
-        for run in header_runs {
-            header_paragraph.add_text_run(run);
-        }
-
-        let header = crate::docx::adapter::docx::Header::new();
-        let mut headers = crate::docx::adapter::docx::Headers::new(header);
-        headers.default.children.push(header_paragraph);
-        section_main.headers = Some(headers.clone());
-        section_main_no_page_num.headers = Some(headers);
+    if print_line_numbers > 0 {
+        section_main.properties.line_numbers = Some(print_line_numbers);
     }
-
-    // 添加页脚（有页码）
-    let mut footer_paragraphs = Vec::new();
-
-    // 添加页脚文本
-    if !cfg.print_footer.is_empty() {
-        let mut footer_paragraph = crate::docx::adapter::docx::Paragraph::new();
-        footer_paragraph.align(crate::docx::adapter::AlignmentType::Center);
-
-        let footer_runs = doc.format_text(&cfg.print_footer, &header_footer_options);
-        for run in footer_runs {
-            footer_paragraph.add_text_run(run);
-        }
-
-        footer_paragraphs.push(footer_paragraph);
-    }
-    if !footer_paragraphs.is_empty() {
-        let footer = crate::docx::adapter::docx::Footer::new();
-        let mut footers = crate::docx::adapter::docx::Footers::new(footer);
-        footers.default.children = footer_paragraphs.clone();
-        section_main_no_page_num.footers = Some(footers);
-    }
-
-    // 添加页码
-    if !cfg.show_page_numbers.is_empty() {
-        let mut page_number_paragraph = crate::docx::adapter::docx::Paragraph::new();
-        page_number_paragraph.align(crate::docx::adapter::AlignmentType::Right);
-
-        // 创建页码运行
-        let page_runs = doc.create_page_number_runs(&cfg.show_page_numbers);
-        for run in page_runs {
-            page_number_paragraph.add_run(run);
-        }
-
-        footer_paragraphs.push(page_number_paragraph);
-    }
-
-    if !footer_paragraphs.is_empty() {
-        let footer = crate::docx::adapter::docx::Footer::new();
-        let mut footers = crate::docx::adapter::docx::Footers::new(footer);
-        footers.default.children = footer_paragraphs;
-        section_main.footers = Some(footers);
+    if let Some(page) = section_main.properties.page.as_mut() {
+        page.page_numbers = Some(crate::docx::adapter::docx::PageNumbers::new(
+            page_number_start as i32,
+        ));
     }
 
     // 处理主要内容
@@ -2429,37 +3871,68 @@ pub fn generate(
     // Outline 相关变量
     let mut outline_depth = 0; // 当前大纲深度
     let mut current_section_level = 0; // 当前章节层级
+    let mut toc_entries: Vec<TocEntry> = Vec::new(); // 目录条目，与正文中的书签一一对应
+    let (mut scene_bookmarks, mut section_bookmarks) = (0, 0);
 
     let _bottom_notes = cfg.note_position_bottom;
 
     // 获取内宽不再需要，因为我们不再使用框架
 
-    // 缩进计算
-    let shift_scene_number = if scenes_numbers == "both" || scenes_numbers == "left" {
-        convert_inches_to_twip(5.0 * print.font_width)
-    } else {
-        0
-    };
-
-    let scene_indent =
-        convert_inches_to_twip(print.scene_heading.feed - print.left_margin) - shift_scene_number;
+    // 左侧场景编号按最长的编号右对齐（至少 3 个字符），编号格式较长（如 "II-14"）时场景标题随之右移
+    let scene_number_width = parsed
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "scene_heading")
+        .filter_map(|t| t.number.as_ref())
+        .map(|n| n.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+
+    // 正文右边缘，右侧场景编号的右对齐制表位在这里
+    let text_edge =
+        convert_inches_to_twip(print.page_width - print.left_margin - print.right_margin);
+
+    // 缩进计算：左侧场景编号悬挂在场景标题之前，占最长编号加 2 个字符的宽度
+    let shift_scene_number =
+        convert_inches_to_twip((scene_number_width + 2) as f32 * print.font_width);
+    let scene_number_gap = convert_inches_to_twip(2.0 * print.font_width);
+
+    let scene_indent = convert_inches_to_twip(print.scene_heading.feed - print.left_margin);
     let action_indent = convert_inches_to_twip(print.action.feed - print.left_margin);
+    // 转场的右边缘：动作样式的右缩进与左缩进相同
+    let transition_edge = text_edge - action_indent;
+    let lyric_indent = convert_inches_to_twip(print.lyric.feed - print.left_margin);
     let shot_cut_indent = action_indent - convert_inches_to_twip(4.0 * print.font_width); // 镜头交切标志缩进
+    let banner_shot_cut = print.shot_cut.style == "banner";
+    // 参与交切的场景标题所在行 -> 地点，角色名后注明地点时使用
+    let intercut_locations = if cfg.intercut_character_locations {
+        crate::stats::intercut_scene_locations(parsed)
+    } else {
+        HashMap::new()
+    };
+    let mut current_scene_line: Option<usize> = None;
+    // 交切开始标记所在行 -> 参与交切的地点，自动生成标记文字时使用
+    let shot_cut_locations = if print.shot_cut.auto_label {
+        crate::stats::shot_cut_locations(parsed)
+    } else {
+        HashMap::new()
+    };
 
     // 行间距设置 - 使用合理的固定行距
     // 问题根源：options.line_height 基于 lines_per_page=20 计算，产生过大的行距 (451 twips = 1.9倍)
     // 解决方案：使用基于字体大小的合理倍数，而不是页面布局计算的动态行距
     let line_spacing_twips = convert_inches_to_twip(options.line_height); // 转换为 twips (1pt = 20 twips)
 
-    println!("【行距设置日志】正文样式 - 使用合理的固定行距:");
-    println!("  字体大小: {} pt", print.font_size);
-    println!("  行距倍数: 1.2 (120%)");
-    println!("  转换为 twips: {} twips", line_spacing_twips);
-    println!(
+    debug_log!("【行距设置日志】正文样式 - 使用合理的固定行距:");
+    debug_log!("  字体大小: {} pt", print.font_size);
+    debug_log!("  行距倍数: 1.2 (120%)");
+    debug_log!("  转换为 twips: {} twips", line_spacing_twips);
+    debug_log!(
         "  相对单倍行距: {:.1} 倍",
         line_spacing_twips as f32 / 240.0
     );
-    println!(
+    debug_log!(
         "  (避免使用页面布局的 options.line_height = {} 英寸 = {} twips)",
         options.line_height,
         convert_inches_to_twip(options.line_height)
@@ -2488,6 +3961,8 @@ pub fn generate(
     let character_indent = convert_inches_to_twip(print.character.feed - print.left_margin);
     let dialogue_indent = convert_inches_to_twip(print.dialogue.feed - print.left_margin);
     let parenthetical_indent = convert_inches_to_twip(print.parenthetical.feed - print.left_margin);
+    // 对白编号打印在左侧页边，与左侧场景编号的位置相同
+    let dialogue_number_indent = action_indent - convert_inches_to_twip(5.0 * print.font_width);
 
     // 预计算双对话的各种缩进组合
     let character_indent_out = dial_indent_out * 3;
@@ -2498,14 +3973,23 @@ pub fn generate(
     // 初始化脚注页面数据结构 - 参考原项目 docxmaker.ts 中的 notesPage
     let mut notes_page: Vec<Vec<Vec<Note>>> = Vec::new();
     let mut current_line_notes: Vec<Note> = Vec::new(); // 当前行的脚注列表
-    let bottom_notes = cfg.note_position_bottom; // 是否将脚注放在页面底部
+    let word_comments = cfg.notes_as_word_comments; // 是否将注释导出为 Word 批注
+    let end_notes = cfg.note_position_end && !word_comments; // 是否将注释汇总为文末尾注
+    let bottom_notes = cfg.note_position_bottom || cfg.note_position_end || word_comments; // 是否将脚注放在页面底部（尾注、批注同样需要收集注释）
+    let mut note_scenes: HashMap<usize, String> = HashMap::new(); // 注释编号 -> 所在场景
 
-    println!("【generate】脚注配置: bottom_notes = {}", bottom_notes);
+    debug_log!("【generate】脚注配置: bottom_notes = {}", bottom_notes);
 
     // 处理每一行
     let mut current_page = 0;
     let mut current_scene = String::new();
     let mut current_sections: Vec<String> = Vec::new();
+    // 页眉页脚按页变化时，每页对应的场景和章节：取该页第一个场景标题、章节标题处的值，
+    // 没有时沿用上一页末尾的值
+    let mut page_fields: HashMap<usize, PageFields> = HashMap::new();
+    // 正文中分页符在 section_main 中的位置及分页后的页码
+    let mut main_page_breaks: Vec<(usize, usize)> = Vec::new();
+    let mut main_start_page: Option<usize> = None;
     let current_duration = 0.0;
     let mut curr_type = String::new();
     let mut page_started = false;
@@ -2575,9 +4059,23 @@ pub fn generate(
         return true;
     }
 
-    // 如果有处理过的行，则使用处理过的行
-    if !parsed.lines.is_empty() {
+    if options.av_script {
+        // A/V 两栏格式不逐行排版，按 token 生成两栏表格，由 Word 自动分页
+        scene_or_section_or_tran_started = true;
+        add_av_script(doc, parsed, &spacing, &default_text_options, &mut section_main);
+    } else if !parsed.lines.is_empty() {
+        // 如果有处理过的行，则使用处理过的行
+        let keeps = crate::docx::line_processor::pagination_keeps(&parsed.lines, print);
         for (ii, line) in parsed.lines.iter().enumerate() {
+            // 已取消时不再排版剩下的行，由调用方返回 Cancelled
+            if options
+                .progress
+                .report(ExportPhase::Layout, ii, parsed.lines.len())
+                .is_err()
+            {
+                break;
+            }
+
             // 检查是否需要跳过空行
             if should_del_blank_line(&parsed.lines, ii, doc.rm_blank_line, &mut curr_type) {
                 // 只绘制样式，再跳过
@@ -2652,6 +4150,12 @@ pub fn generate(
 
                 // 更新当前场景
                 current_scene = line.text.clone();
+                current_scene_line = line.token;
+                let fields = page_fields.entry(current_page).or_default();
+                if !fields.scene_from_heading {
+                    fields.scene = current_scene.clone();
+                    fields.scene_from_heading = true;
+                }
 
                 // 创建段落
                 let mut paragraph =
@@ -2661,33 +4165,50 @@ pub fn generate(
                 // 设置缩进
                 paragraph.indent(scene_indent);
 
+                // 分页规则：场景标题不落在页末，标题本身也不拆开
+                paragraph
+                    .keep_next(keeps[ii].keep_next)
+                    .keep_lines(keeps[ii].keep_lines);
+
                 // 设置 outline level（场景头使用层级）
                 if cfg.create_bookmarks {
                     let do_outline = if cfg.print_sections { 8 } else { 0 }; // 固定最高级或最低级
                     paragraph.outline_level(do_outline);
                 }
 
-                // 处理场景编号
+                // 书签，目录条目链接到这里
+                if cfg.create_bookmarks && line.local_index == 0 {
+                    scene_bookmarks += 1;
+                    let name = format!("_Scene{}", scene_bookmarks);
+                    paragraph.bookmark(doc.next_bookmark(&name));
+                    toc_entries.push(TocEntry::new(name, line, current_section_level));
+                }
+
+                // 处理场景编号：左侧编号写在标题之前，右侧编号之后通过右对齐制表位放到正文右边缘
                 let mut text = line.text.clone();
+                let mut right_scene_number = String::new();
                 if let Some(number) = &line.number {
-                    if scenes_numbers == "both" || scenes_numbers == "left" {
-                        let scene_number = number.to_string();
-                        let scene_text_length = scene_number.chars().count(); // 使用字符数而不是字节数
-
-                        let left_char = if scene_text_length < 3 {
-                            3 - scene_text_length
+                    let (left, right) = process_scene_number(number, &scenes_numbers);
+                    if !left.is_empty() {
+                        // 编号悬挂在标题之前，右对齐到标题前 2 个字符处，标题从左缩进处开始
+                        paragraph.indent_first_line(-shift_scene_number);
+                        if scene_indent - scene_number_gap >= 0 {
+                            paragraph.tab_stop(crate::docx::adapter::docx::TabStop::new(
+                                scene_indent - scene_number_gap,
+                                crate::docx::adapter::TabAlignment::Right,
+                            ));
+                            paragraph.tab_stop(crate::docx::adapter::docx::TabStop::new(
+                                scene_indent,
+                                crate::docx::adapter::TabAlignment::Left,
+                            ));
+                            text = format!("\t{}\t{}", left, text);
                         } else {
-                            0
-                        };
-
-                        let left_spaces = if left_char > 0 {
-                            " ".repeat(left_char)
-                        } else {
-                            "".to_string()
-                        };
-
-                        text = format!("{}{}  {}", left_spaces, scene_number, text);
+                            // 标题伸出左边距时 DOCX 不能在页边距之外设置制表位，用空格补齐（等宽字体）
+                            let left = format!("{:>1$}", left, scene_number_width);
+                            text = format!("{}  {}", left, text);
+                        }
                     }
+                    right_scene_number = right;
                 }
 
                 // 处理样式
@@ -2710,6 +4231,9 @@ pub fn generate(
                 // 创建文本运行
                 let mut scene_options = default_text_options.clone();
                 scene_options.insert("characterSpacing".to_string(), "0".to_string());
+                if let Some(color) = &scene_heading_color {
+                    scene_options.insert("color".to_string(), color.clone());
+                }
 
                 let text_runs = doc.text2(
                     &text,
@@ -2727,10 +4251,26 @@ pub fn generate(
                     paragraph.add_text_run(run);
                 }
 
+                if !right_scene_number.is_empty() {
+                    paragraph.tab_stop(crate::docx::adapter::docx::TabStop::new(
+                        text_edge,
+                        crate::docx::adapter::TabAlignment::Right,
+                    ));
+                    let right_text = if cfg.embolden_scene_headers {
+                        format!("\t{0}{1}{0}", style_chars["bold"], right_scene_number)
+                    } else {
+                        format!("\t{}", right_scene_number)
+                    };
+                    for run in doc.text2(&right_text, &scene_options, None, None) {
+                        paragraph.add_text_run(run);
+                    }
+                }
+
                 // 添加段落到相应section并更新行映射
                 add_paragraph_and_update_line_map(
                     crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
                     line,
+                    options,
                     scene_or_section_or_tran_started,
                     &mut section_main,
                     &mut section_main_no_page_num,
@@ -2761,6 +4301,11 @@ pub fn generate(
                 // 设置缩进
                 paragraph.indent(action_indent);
 
+                // 分页规则：场景标题之后要求的动作行与标题在同一页
+                paragraph
+                    .keep_next(keeps[ii].keep_next)
+                    .keep_lines(keeps[ii].keep_lines);
+
                 // 处理文本
                 let mut text = line.text.clone();
                 text = if_reset_format(text, line);
@@ -2787,10 +4332,63 @@ pub fn generate(
                     paragraph.add_text_run(run);
                 }
 
-                // 添加段落到相应section并更新行映射
+                // 添加段落到相应section并更新行映射
+                add_paragraph_and_update_line_map(
+                    crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
+                    line,
+                    options,
+                    scene_or_section_or_tran_started,
+                    &mut section_main,
+                    &mut section_main_no_page_num,
+                    &mut line_map,
+                    &current_sections,
+                    &current_scene,
+                    current_page,
+                    current_duration,
+                );
+            } else if token_type == "image" {
+                finish_dialogue_processing(
+                    doc,
+                    china_format,
+                    token_type,
+                    scene_or_section_or_tran_started,
+                    &mut section_main,
+                    &mut section_main_no_page_num,
+                    &print,
+                    &spacing,
+                );
+
+                let mut paragraph =
+                    crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+                paragraph.style("action");
+                paragraph.indent(action_indent);
+
+                let metadata = parsed
+                    .tokens
+                    .iter()
+                    .find(|t| t.token_type == "image" && Some(t.line) == line.token)
+                    .and_then(|t| t.metadata.as_ref());
+                match load_image_run(metadata, &options.filepath) {
+                    Some(image) => {
+                        paragraph.add_image_run(image);
+                    }
+                    None => {
+                        // 图片读取失败时保留占位文字，方便发现问题
+                        for run in doc.text2(
+                            &format!("[图片: {}]", line.text),
+                            &default_text_options,
+                            None,
+                            None,
+                        ) {
+                            paragraph.add_text_run(run);
+                        }
+                    }
+                }
+
                 add_paragraph_and_update_line_map(
                     crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
                     line,
+                    options,
                     scene_or_section_or_tran_started,
                     &mut section_main,
                     &mut section_main_no_page_num,
@@ -2847,6 +4445,7 @@ pub fn generate(
                 add_paragraph_and_update_line_map(
                     crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
                     line,
+                    options,
                     scene_or_section_or_tran_started,
                     &mut section_main,
                     &mut section_main_no_page_num,
@@ -2929,6 +4528,7 @@ pub fn generate(
                 add_paragraph_and_update_line_map(
                     crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
                     line,
+                    options,
                     scene_or_section_or_tran_started,
                     &mut section_main,
                     &mut section_main_no_page_num,
@@ -2949,8 +4549,8 @@ pub fn generate(
 
                 // 检查是否需要处理双对话结束
                 if line.token_type == "character" {
-                    // 如果当前角色不是右侧对话，完成之前的双对话
-                    if line.dual.as_deref() != Some("right") {
+                    // 如果当前角色不是中栏或右侧对话，完成之前的双对话
+                    if !matches!(line.dual.as_deref(), Some("middle") | Some("right")) {
                         doc.finish_double_dial(
                             if scene_or_section_or_tran_started {
                                 &mut section_main
@@ -2968,6 +4568,19 @@ pub fn generate(
 
                 // 处理角色名
                 if line.token_type == "character" {
+                    // 交切中的角色名后注明地点，放在 (CONT'D) 之前
+                    let location = current_scene_line
+                        .and_then(|scene_line| intercut_locations.get(&scene_line))
+                        .filter(|_| line.local_index == 0);
+                    if let Some(location) = location {
+                        text = match text.strip_suffix(cfg.text_contd.as_str()) {
+                            Some(base) if !cfg.text_contd.is_empty() => {
+                                format!("{} ({}) {}", base.trim_end(), location, cfg.text_contd)
+                            }
+                            _ => format!("{} ({})", text.trim_end(), location),
+                        };
+                    }
+
                     // 添加粗体标记（如果启用）
                     if cfg.embolden_character_names {
                         // 使用样式字符常量
@@ -3003,14 +4616,50 @@ pub fn generate(
                         // 添加冒号
                         text = format!("{}: ", text);
                     }
+
+                    // 对白编号：单栏的国际格式打印在左侧页边，之后补空格到角色名的位置；其他情况放在角色名前
+                    if let Some(number) = line.take_number {
+                        if china_format == 0 && line.dual.is_none() {
+                            let number = format!("{:>3}", number);
+                            let gutter = ((character_indent - dialogue_number_indent) as f32
+                                / convert_inches_to_twip(print.font_width) as f32)
+                                .round() as usize;
+                            let pad = gutter.saturating_sub(number.chars().count()).max(1);
+                            text = format!("{}{}{}", number, " ".repeat(pad), text);
+                        } else {
+                            text = format!("{} - {}", number, text);
+                        }
+                    }
                 }
 
                 text = if_reset_format(text, line);
 
+                // 试镜台词本：选定角色的对白加粗，其他角色的对白淡化
+                let mut dial_text_options = default_text_options.clone();
+                if line.token_type == "character" {
+                    if let Some(color) = &character_color {
+                        dial_text_options.insert("color".to_string(), color.clone());
+                    }
+                }
+                if !options.sides_characters.is_empty() {
+                    let speaker = parsed
+                        .tokens
+                        .iter()
+                        .find(|t| Some(t.line) == line.token && t.character.is_some())
+                        .and_then(|t| t.character.as_deref());
+                    if speaker.is_some_and(|name| {
+                        crate::sides::is_sides_character(name, &sides_characters)
+                    }) {
+                        dial_text_options.insert("bold".to_string(), "true".to_string());
+                    } else {
+                        dial_text_options.insert("color".to_string(), "#A0A0A0".to_string());
+                    }
+                }
+
                 // 创建文本运行
                 let text_runs = doc.text2(
                     &text,
-                    &default_text_options,
+                    &dial_text_options,
                     if bottom_notes {
                         Some(&mut current_line_notes)
                     } else {
@@ -3034,6 +4683,14 @@ pub fn generate(
                                 indent_left: Some(dial_indent_out),
                                 indent_right: Some(dial_indent_in),
                             });
+                        } else if line.dual.as_deref() == Some("middle") {
+                            // 三人同时对白的中栏，两侧都向内缩进
+                            doc.last_dial_gr_middle = Some(CachedDialogueGroup {
+                                style: "dial".to_string(),
+                                children: text_runs,
+                                indent_left: Some(dial_indent_in),
+                                indent_right: Some(dial_indent_in),
+                            });
                         } else if line.dual.as_deref() == Some("right") {
                             // println!("【generate】缓存右侧双对话角色: {}", text);
                             // 右侧对话
@@ -3078,6 +4735,20 @@ pub fn generate(
                                 // );
                                 doc.last_dial_table_left.push(paragraph);
                             }
+                        } else if line.dual.as_deref() == Some("middle") {
+                            // 中栏对话的括号内容
+                            if let Some(ref mut dial_gr_middle) = doc.last_dial_gr_middle {
+                                dial_gr_middle.children.extend(text_runs);
+                            } else {
+                                let group = CachedDialogueGroup {
+                                    style: "dial".to_string(),
+                                    children: text_runs,
+                                    indent_left: Some(dial_indent_in),
+                                    indent_right: Some(dial_indent_in),
+                                };
+                                doc.last_dial_table_middle
+                                    .push(cached_group_paragraph(group, &spacing));
+                            }
                         } else if line.dual.as_deref() == Some("right") {
                             // 右侧对话的括号内容
                             if let Some(ref mut dial_gr_right) = doc.last_dial_gr_right {
@@ -3189,6 +4860,27 @@ pub fn generate(
                                 // );
                                 doc.last_dial_table_left.push(paragraph);
                             }
+                        } else if line.dual.as_deref() == Some("middle") {
+                            // 中栏对话
+                            if let Some(ref mut dial_gr_middle) = doc.last_dial_gr_middle {
+                                dial_gr_middle.children.extend(text_runs);
+
+                                // 与左右两栏一样，china_format 为 1、2 时立即输出
+                                if china_format == 1 || china_format == 2 {
+                                    let cached_group = doc.last_dial_gr_middle.take().unwrap();
+                                    doc.last_dial_table_middle
+                                        .push(cached_group_paragraph(cached_group, &spacing));
+                                }
+                            } else {
+                                let group = CachedDialogueGroup {
+                                    style: "dial".to_string(),
+                                    children: text_runs,
+                                    indent_left: Some(dial_indent_in),
+                                    indent_right: Some(dial_indent_in),
+                                };
+                                doc.last_dial_table_middle
+                                    .push(cached_group_paragraph(group, &spacing));
+                            }
                         } else if line.dual.as_deref() == Some("right") {
                             // 右侧对话
                             if let Some(ref mut dial_gr_right) = doc.last_dial_gr_right {
@@ -3308,8 +5000,10 @@ pub fn generate(
                     }
                 } else {
                     // 国际格式：根据 dual 属性处理
-                    if line.dual.as_deref() == Some("left") || line.dual.as_deref() == Some("right")
-                    {
+                    if matches!(
+                        line.dual.as_deref(),
+                        Some("left") | Some("middle") | Some("right")
+                    ) {
                         // 双对话：添加到全局表格缓存
                         // println!("【generate】国际格式双对话: {} - {}", line.token_type, text);
 
@@ -3322,6 +5016,9 @@ pub fn generate(
                             if line.dual.as_deref() == Some("left") {
                                 paragraph.indent(dial_indent_out);
                                 paragraph.indent_right(dial_indent_in);
+                            } else if line.dual.as_deref() == Some("middle") {
+                                paragraph.indent(dial_indent_in);
+                                paragraph.indent_right(dial_indent_in);
                             } else {
                                 paragraph.indent(dial_indent_in);
                                 paragraph.indent_right(dial_indent_out);
@@ -3331,6 +5028,9 @@ pub fn generate(
                             if line.dual.as_deref() == Some("left") {
                                 paragraph.indent(character_indent_out);
                                 paragraph.indent_right(character_indent_in);
+                            } else if line.dual.as_deref() == Some("middle") {
+                                paragraph.indent(character_indent_in);
+                                paragraph.indent_right(character_indent_in);
                             } else {
                                 paragraph.indent(character_indent_in);
                                 paragraph.indent_right(character_indent_out);
@@ -3340,6 +5040,9 @@ pub fn generate(
                             if line.dual.as_deref() == Some("left") {
                                 paragraph.indent(parenthetical_indent_out);
                                 paragraph.indent_right(parenthetical_indent_in);
+                            } else if line.dual.as_deref() == Some("middle") {
+                                paragraph.indent(parenthetical_indent_in);
+                                paragraph.indent_right(parenthetical_indent_in);
                             } else {
                                 paragraph.indent(parenthetical_indent_in);
                                 paragraph.indent_right(parenthetical_indent_out);
@@ -3354,6 +5057,8 @@ pub fn generate(
                         if line.dual.as_deref() == Some("left") {
                             // println!("【generate】添加到左侧全局表格缓存");
                             doc.last_dial_table_left.push(paragraph);
+                        } else if line.dual.as_deref() == Some("middle") {
+                            doc.last_dial_table_middle.push(paragraph);
                         } else {
                             // println!("【generate】添加到右侧全局表格缓存");
                             doc.last_dial_table_right.push(paragraph);
@@ -3370,11 +5075,21 @@ pub fn generate(
                         } else if line.token_type == "character" {
                             paragraph.style("character");
                             paragraph.indent(character_indent);
+                            if line.take_number.is_some() {
+                                // 首行从对白编号的位置开始
+                                paragraph
+                                    .indent_first_line(dialogue_number_indent - character_indent);
+                            }
                         } else if line.token_type == "parenthetical" {
                             paragraph.style("parenthetical");
                             paragraph.indent(parenthetical_indent);
                         }
 
+                        // 分页规则：角色名不单独留在页末，括号注释不跨页
+                        paragraph
+                            .keep_next(keeps[ii].keep_next)
+                            .keep_lines(keeps[ii].keep_lines);
+
                         for run in text_runs {
                             paragraph.add_text_run(run);
                         }
@@ -3437,15 +5152,35 @@ pub fn generate(
                     // 添加当前章节文本到 current_sections
                     current_sections.push(line.text.clone());
 
+                    let fields = page_fields.entry(current_page).or_default();
+                    if !fields.section_from_heading {
+                        fields.sections = current_sections.clone();
+                        fields.section_from_heading = true;
+                    }
+
                     // 更新 outline_depth
                     if cfg.create_bookmarks {
                         outline_depth = level as usize;
                     }
                 }
 
+                // 按层级的排版
+                let level_config = line
+                    .level
+                    .and_then(|level| print.section.level(level as usize));
+
                 // 创建段落
+                let mut section_spacing = spacing.clone();
+                if let Some(level_config) = level_config {
+                    if let Some(before) = level_config.spacing_before {
+                        section_spacing.before = Some(convert_point_to_twip(before));
+                    }
+                    if let Some(after) = level_config.spacing_after {
+                        section_spacing.after = Some(convert_point_to_twip(after));
+                    }
+                }
                 let mut paragraph =
-                    crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
+                    crate::docx::adapter::docx::Paragraph::new_with_spacing(section_spacing);
                 paragraph.style("section");
 
                 // 计算章节缩进 - 参考原项目使用 current_section_level
@@ -3460,17 +5195,32 @@ pub fn generate(
                 if cfg.create_bookmarks {
                     paragraph.outline_level(outline_depth);
                 }
+                if cfg.create_bookmarks && line.local_index == 0 {
+                    section_bookmarks += 1;
+                    let name = format!("_Section{}", section_bookmarks);
+                    paragraph.bookmark(doc.next_bookmark(&name));
+                    let depth = (line.level.unwrap_or(1).max(1) - 1) as usize;
+                    toc_entries.push(TocEntry::new(name, line, depth));
+                }
 
                 // 处理文本
                 let mut text = line.text.clone();
                 text = if_reset_format(text, line);
 
                 // 创建文本运行
-                let section_options = if let Some(color) = print.section.color.as_ref() {
+                let mut section_options = if let Some(color) = print.section.color.as_ref() {
                     create_basic_options_map(color)
                 } else {
                     default_text_options.clone()
                 };
+                if let Some(level_config) = level_config {
+                    if let Some(size) = level_config.size {
+                        section_options.insert("fontSize".to_string(), size.round().to_string());
+                    }
+                    if level_config.bold {
+                        section_options.insert("bold".to_string(), "true".to_string());
+                    }
+                }
 
                 let text_runs = doc.text2(
                     &text,
@@ -3492,6 +5242,7 @@ pub fn generate(
                 add_paragraph_and_update_line_map(
                     crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
                     line,
+                    options,
                     scene_or_section_or_tran_started,
                     &mut section_main,
                     &mut section_main_no_page_num,
@@ -3533,17 +5284,39 @@ pub fn generate(
                     // 镜头交切标志
                     is_shot_cut = true;
                     style = "shotCut";
-                    indent = shot_cut_indent;
+                    indent = if banner_shot_cut {
+                        action_indent
+                    } else {
+                        shot_cut_indent
+                    };
 
                     // 转换文本：去掉 {+ 和 +}，保留中间内容和箭头，用括号包围
-                    // 例如：{+镜头交切+} ↓ -> (镜头交切 ↓)
+                    // 例如：{+镜头交切+} ↓ -> (镜头交切 ↓)，横幅不加括号
                     // 使用字符索引而不是字节索引来处理 Unicode 字符
                     let chars: Vec<char> = text.chars().collect();
                     if chars.len() >= 6 {
                         // 至少需要 {+x+} ↓ 这样的格式
-                        let inner_text: String = chars[2..chars.len() - 4].iter().collect(); // 去掉 {+ 和 +}
+                        let mut inner_text: String = chars[2..chars.len() - 4].iter().collect(); // 去掉 {+ 和 +}
                         let arrow: String = chars[chars.len() - 2..].iter().collect(); // 获取箭头部分
-                        text = format!("({}{})", inner_text, arrow);
+
+                        // 开始标记的文字按参与交切的地点生成，如 INTERCUT – 客厅 / 街道
+                        let locations = line
+                            .token
+                            .and_then(|token_line| shot_cut_locations.get(&token_line))
+                            .filter(|locations| !locations.is_empty());
+                        if let Some(locations) = locations.filter(|_| !text.starts_with("{-")) {
+                            inner_text = format!(
+                                "{} – {}",
+                                print.shot_cut.label_prefix,
+                                locations.join(" / ")
+                            );
+                        }
+
+                        text = if banner_shot_cut {
+                            format!("{}{}", inner_text.trim(), arrow)
+                        } else {
+                            format!("({}{})", inner_text, arrow)
+                        };
                     }
                 }
 
@@ -3559,7 +5332,17 @@ pub fn generate(
                 let final_line_spacing_twips = convert_inches_to_twip(options.line_height);
 
                 // 处理转场对齐
-                if is_shot_cut {
+                if is_shot_cut && banner_shot_cut {
+                    // 镜头交切横幅：居中，四周加框
+                    paragraph.align(crate::docx::adapter::AlignmentType::Center);
+                    let border = print
+                        .shot_cut
+                        .color
+                        .as_ref()
+                        .or(transition_color.as_ref())
+                        .map_or("#000000", |color| color.as_str());
+                    paragraph.border(border);
+                } else if is_shot_cut {
                     // 镜头交切：左对齐
                     paragraph.align(crate::docx::adapter::AlignmentType::Left);
                 } else {
@@ -3568,16 +5351,28 @@ pub fn generate(
                         text = format!("({})", text);
                         paragraph.align(crate::docx::adapter::AlignmentType::Left);
                     } else {
-                        paragraph.align(crate::docx::adapter::AlignmentType::Right);
+                        // 右对齐制表位放在转场的右边缘
+                        paragraph.align(crate::docx::adapter::AlignmentType::Left);
+                        paragraph.tab_stop(crate::docx::adapter::docx::TabStop::new(
+                            transition_edge,
+                            crate::docx::adapter::TabAlignment::Right,
+                        ));
+                        text = format!("\t{}", text);
                     }
                 }
 
                 // 创建文本运行
                 let mut transition_options = default_text_options.clone();
+                if let Some(color) = &transition_color {
+                    transition_options.insert("color".to_string(), color.clone());
+                }
 
                 // 如果是镜头交切，设置粗体
                 if is_shot_cut {
                     transition_options.insert("bold".to_string(), "true".to_string());
+                    if let Some(color) = &print.shot_cut.color {
+                        transition_options.insert("color".to_string(), color.clone());
+                    }
                 }
 
                 text = if_reset_format(text, line);
@@ -3602,6 +5397,7 @@ pub fn generate(
                 add_paragraph_and_update_line_map(
                     crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
                     line,
+                    options,
                     scene_or_section_or_tran_started,
                     &mut section_main,
                     &mut section_main_no_page_num,
@@ -3626,11 +5422,23 @@ pub fn generate(
 
                 // 更新页码
                 current_page += 1;
+                page_fields.insert(
+                    current_page,
+                    PageFields {
+                        sections: current_sections.clone(),
+                        scene: current_scene.clone(),
+                        ..Default::default()
+                    },
+                );
+                if scene_or_section_or_tran_started {
+                    main_page_breaks.push((section_main.children.len(), current_page));
+                }
 
                 // 添加段落到相应section并更新行映射
                 add_paragraph_and_update_line_map(
                     crate::docx::adapter::docx::SectionChild::PageBreak,
                     line,
+                    options,
                     scene_or_section_or_tran_started,
                     &mut section_main,
                     &mut section_main_no_page_num,
@@ -3656,25 +5464,49 @@ pub fn generate(
                 // 处理其他类型的token
                 let mut paragraph =
                     crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone());
-                paragraph.style("action");
+                let mut text_options = default_text_options.clone();
+
+                if token_type == "lyric" {
+                    // 歌词按 lyric 配置单独设置缩进、对齐和颜色
+                    paragraph.style("lyric");
+                    paragraph.indent(lyric_indent);
+                    if let Some(alignment) = get_lyric_alignment(&print.lyric.alignment) {
+                        paragraph.align(alignment);
+                    }
+                    if let Some(color) = &print.lyric.color {
+                        text_options.insert("color".to_string(), color.clone());
+                    }
+                } else {
+                    paragraph.style("action");
+
+                    // 设置缩进
+                    paragraph.indent(action_indent);
+                }
+                paragraph
+                    .keep_next(keeps[ii].keep_next)
+                    .keep_lines(keeps[ii].keep_lines);
 
-                // 设置缩进
-                paragraph.indent(action_indent);
-                
-                
                 // 处理文本
                 let mut text = line.text.clone();
                 text = if_reset_format(text, line);
 
                 // 歌词处理
                 if token_type == "lyric" {
+                    // 歌词文本自带全局斜体标记，配置为不斜体时去掉
+                    if !print.lyric.italic {
+                        use crate::utils::fountain_constants::FountainConstants;
+                        let style_chars = FountainConstants::style_chars();
+                        text = text
+                            .replace(style_chars["italic_global_begin"], "")
+                            .replace(style_chars["italic_global_end"], "");
+                    }
                     text = format!("♪ {}", text);
                 }
 
                 // 创建文本运行
                 let text_runs = doc.text2(
                     &text,
-                    &default_text_options,
+                    &text_options,
                     if bottom_notes {
                         Some(&mut current_line_notes)
                     } else {
@@ -3692,6 +5524,7 @@ pub fn generate(
                 add_paragraph_and_update_line_map(
                     crate::docx::adapter::docx::SectionChild::Paragraph(paragraph),
                     line,
+                    options,
                     scene_or_section_or_tran_started,
                     &mut section_main,
                     &mut section_main_no_page_num,
@@ -3703,6 +5536,10 @@ pub fn generate(
                 );
             }
 
+            if scene_or_section_or_tran_started && main_start_page.is_none() {
+                main_start_page = Some(current_page);
+            }
+
             // 更新 after_section 状态 - 参考原项目逻辑
             if page_started {
                 if token_type == "section" {
@@ -3713,6 +5550,9 @@ pub fn generate(
             }
 
             // 每行处理结束后清空当前行的脚注列表 - 参考原项目逻辑
+            for note in &current_line_notes {
+                note_scenes.insert(note.no, current_scene.clone());
+            }
             current_line_notes.clear();
         }
     }
@@ -3731,18 +5571,34 @@ pub fn generate(
 
     // 处理脚注 - 参考原项目 docxmaker.ts 中的脚注处理逻辑
     // 只有当 bottom_notes = true 时才处理页面底部的脚注
-    if bottom_notes && !notes_page.is_empty() && !notes_page[0].is_empty() {
-        println!(
+    if word_comments && !notes_page.is_empty() && !notes_page[0].is_empty() {
+        let author = author_token
+            .map(|t| inline(&clear_formatting(&t.text)))
+            .filter(|a| !a.trim().is_empty())
+            .unwrap_or_else(|| "BetterFountain".to_string());
+        let comments = note_comments(doc, &notes_page[0], &author, &print);
+        section_main.fill_comments(&comments);
+        section_main_no_page_num.fill_comments(&comments);
+    } else if end_notes && !notes_page.is_empty() && !notes_page[0].is_empty() {
+        add_endnotes(
+            doc,
+            &notes_page[0],
+            &note_scenes,
+            &print,
+            &mut section_main,
+        );
+    } else if bottom_notes && !notes_page.is_empty() && !notes_page[0].is_empty() {
+        debug_log!(
             "【generate】开始处理页面底部脚注，脚注数量: {}",
             notes_page[0].len()
         );
 
         let notes = &notes_page[0];
         for (i, token_row) in notes.iter().enumerate() {
-            println!("【generate】处理脚注行 #{}: {} 个脚注", i, token_row.len());
+            debug_log!("【generate】处理脚注行 #{}: {} 个脚注", i, token_row.len());
 
             for (j, note) in token_row.iter().enumerate() {
-                println!(
+                debug_log!(
                     "【generate】处理脚注 #{}: 编号={}, 文本行数={}, 文本内容: {:?}",
                     j,
                     note.no,
@@ -3752,37 +5608,34 @@ pub fn generate(
 
                 let mut children = Vec::new();
 
-                for (k, text_line) in note.text.iter().enumerate() {
-                    let mut text = text_line.clone();
-
-                    // 去掉第一个字符（脚注开始标记）
-                    if k == 0 && !text.is_empty() {
-                        if let Some(first_char) = text.chars().next() {
-                            text = text[first_char.len_utf8()..].to_string();
-                        }
-                    }
-
-                    // 去掉最后一个字符（脚注结束标记）
-                    if k == note.text.len() - 1 && !text.is_empty() {
-                        if let Some(last_char) = text.chars().last() {
-                            let last_char_start = text.len() - last_char.len_utf8();
-                            text = text[..last_char_start].to_string();
-                        }
-                    }
+                for (k, text) in note_text_lines(note).into_iter().enumerate() {
 
                     // 创建脚注段落
-                    let mut paragraph = crate::docx::adapter::docx::Paragraph::new(); //底部脚注内容使用单倍行距
-                                                                                      // let mut paragraph =
-                                                                                      //     crate::docx::adapter::docx::Paragraph::new_with_spacing(spacing.clone().line(convert_inches_to_twip(options.print_profile.note_line_height)).line_rule(crate::docx::adapter::LineRuleType::AtLeast));
+                    let mut paragraph = crate::docx::adapter::docx::Paragraph::new();
                     paragraph.style("notes");
+                    if let Some(line_spacing) = print.note.footnote.line_spacing {
+                        paragraph.spacing(
+                            ParagraphSpacing::new()
+                                .line((240.0 * line_spacing) as i32)
+                                .line_rule(crate::docx::adapter::LineRuleType::Auto),
+                        );
+                    }
                     if k == 0 {
                         paragraph.indent_first_line(convert_inches_to_twip(-2.0 * print.font_width)); //负数，悬挂缩进
                     }
 
-                    // 创建文本运行 - 参考原项目使用固定颜色 #868686
-                    let mut footnote_options = create_basic_options_map("#868686");
-                    footnote_options
-                        .insert("fontSize".to_string(), print.note_font_size.to_string());
+                    // 创建文本运行，颜色和斜体与注释相同
+                    let mut footnote_options = create_basic_options_map(&print.note.color);
+                    if print.note.italic {
+                        footnote_options.insert("italic".to_string(), "true".to_string());
+                    }
+                    footnote_options.insert(
+                        "fontSize".to_string(),
+                        print.note.size.unwrap_or(print.note_font_size).to_string(),
+                    );
+                    if let Some(font) = &print.note.font {
+                        footnote_options.insert("font".to_string(), font.clone());
+                    }
                     footnote_options.insert("characterSpacing".to_string(), "0".to_string());
 
                     let text_runs = doc.format_text(&text, &footnote_options);
@@ -3795,13 +5648,23 @@ pub fn generate(
                     children.push(paragraph);
                 }
 
+                // 脚注末尾加上回到正文引用处的链接
+                if let Some(last) = children.last_mut() {
+                    let back = crate::docx::adapter::docx::TextRun::with_props(
+                        " ↩",
+                        doc.run_notes.clone(),
+                    )
+                    .link_to(&note_ref_bookmark(note.no));
+                    last.add_text_run(back);
+                }
+
                 // 创建脚注对象
                 let mut footnote = crate::docx::adapter::docx::Footnote::new();
                 footnote.children = children;
 
                 // 将脚注添加到文档
                 doc.doc.options.footnotes.insert(note.no, footnote);
-                println!(
+                debug_log!(
                     "【generate】已添加脚注 #{}: {} 个段落",
                     note.no,
                     doc.doc
@@ -3815,14 +5678,14 @@ pub fn generate(
             }
         }
 
-        println!(
+        debug_log!(
             "【generate】脚注处理完成，总脚注数: {}",
             doc.doc.options.footnotes.len()
         );
     } else if !bottom_notes {
-        println!("【generate】脚注配置为原位置显示，不处理页面底部脚注");
+        debug_log!("【generate】脚注配置为原位置显示，不处理页面底部脚注");
     } else {
-        println!("【generate】没有脚注需要处理");
+        debug_log!("【generate】没有脚注需要处理");
     }
 
     // 创建 section 属性
@@ -3841,254 +5704,438 @@ pub fn generate(
     // 但是，由于我们已经生成了文档，所以这里只是记录 print_preface_page 的使用
     let _print_preface_page_used = print_preface_page;
 
+    // 添加页眉页脚（页脚中有页码），序言页的页眉页脚不含页码。{total} 需要排完正文才知道
+    let mut header_footer = HeaderFooter::new(cfg, parsed, header_footer_options);
+    header_footer.total =
+        (current_page - main_start_page.unwrap_or(0) + page_number_start as usize).to_string();
+    let first_page = PageFields::default();
+    section_main.headers = header_footer.headers(doc, &first_page);
+    section_main.footers = header_footer.footers(doc, &first_page, true);
+    section_main_no_page_num.headers = section_main.headers.clone();
+    section_main_no_page_num.footers = header_footer.footers(doc, &first_page, false);
+
     // 将 sections 添加到文档
     doc.doc.options.sections.clear();
 
     if let Some(title_section) = section_title_page {
         doc.doc.options.sections.push(title_section);
-        println!("【generate】已添加标题页 section");
+        debug_log!("【generate】已添加标题页 section");
+    }
+    let title_sections = doc.doc.options.sections.len();
+
+    if print_version_history {
+        let entries = crate::version_history::version_history(parsed);
+        if !entries.is_empty() {
+            let history_section = version_history_section(
+                doc,
+                &entries,
+                print,
+                &section_props,
+                &spacing,
+                &default_text_options,
+            );
+            doc.doc.options.sections.push(history_section);
+            debug_log!("【generate】已添加版本记录 section");
+        }
+    }
+
+    if print_cast_list {
+        let members = crate::cast::cast_list(parsed);
+        if !members.is_empty() {
+            let lines: Vec<String> = members.iter().map(|m| m.display()).collect();
+            let cast_section = appendix_section(
+                doc,
+                crate::cast::CAST_TITLE,
+                &lines,
+                &section_props,
+                &spacing,
+                &default_text_options,
+            );
+            doc.doc.options.sections.push(cast_section);
+            debug_log!("【generate】已添加角色表 section");
+        }
+    }
+
+    if print_toc && cfg.create_bookmarks && !toc_entries.is_empty() {
+        let toc = toc_section(
+            doc,
+            &toc_entries,
+            print,
+            &section_props,
+            &spacing,
+            &default_text_options,
+        );
+        doc.doc.options.sections.push(toc);
+        debug_log!("【generate】已添加目录 section");
     }
 
     if !section_main_no_page_num.children.is_empty() && print_preface_page {
         doc.doc.options.sections.push(section_main_no_page_num);
-        println!("【generate】已添加序言页 section");
+        debug_log!("【generate】已添加序言页 section");
+    }
+
+    // 前置页（标题页、版本记录、角色表、目录、序言页）各自成节，以 i、ii、iii… 连续编页码，
+    // 标题页计为 i 但不显示页码；正文另起一节，页码重新从 page_number_start 开始
+    if front_matter_page_numbers {
+        for i in 0..doc.doc.options.sections.len() {
+            if i >= title_sections {
+                let footers = if doc.doc.options.sections[i].footers.is_some() {
+                    header_footer.footers(doc, &first_page, true)
+                } else {
+                    header_footer.page_number_footers(doc)
+                };
+                doc.doc.options.sections[i].footers = footers;
+            }
+            let page_numbers = if i == 0 {
+                crate::docx::adapter::docx::PageNumbers::new(1)
+            } else {
+                crate::docx::adapter::docx::PageNumbers::continued()
+            };
+            let section = &mut doc.doc.options.sections[i];
+            if let Some(page) = section.properties.page.as_mut() {
+                page.page_numbers = Some(
+                    page_numbers.format(crate::docx::adapter::docx::PageNumberFormat::LowerRoman),
+                );
+            }
+        }
     }
 
     if !section_main.children.is_empty() {
-        doc.doc.options.sections.push(section_main);
-        println!("【generate】已添加主要内容 section");
+        if header_footer.per_page() || hide_first_page_number {
+            // 页眉页脚变化的分页处拆分为新的 section，各自显示当前页的场景和章节
+            let sections = split_section_by_page(
+                doc,
+                section_main,
+                &main_page_breaks,
+                &page_fields,
+                main_start_page.unwrap_or(0),
+                &header_footer,
+                hide_first_page_number,
+            );
+            doc.doc.options.sections.extend(sections);
+        } else {
+            doc.doc.options.sections.push(section_main);
+        }
+        debug_log!("【generate】已添加主要内容 section");
+    }
+
+    if print_locations_appendix {
+        let locations = crate::stats::location_summaries(parsed);
+        if !locations.is_empty() {
+            let mut lines: Vec<String> = locations.iter().map(|l| l.display()).collect();
+            lines.push(String::new());
+            for (time_of_day, count) in crate::stats::scenes_by_time_of_day(parsed) {
+                lines.push(format!("{}: {}", time_of_day, count));
+            }
+            let locations_section = appendix_section(
+                doc,
+                crate::stats::LOCATIONS_TITLE,
+                &lines,
+                &section_props,
+                &spacing,
+                &default_text_options,
+            );
+            doc.doc.options.sections.push(locations_section);
+            debug_log!("【generate】已添加地点附录 section");
+        }
+    }
+
+    doc.doc.options.protection =
+        crate::docx::adapter::docx::DocumentProtection::from_name(&docx_protection);
+    if let Some(timestamp) = deterministic_timestamp(cfg) {
+        doc.doc.options.deterministic = true;
+        doc.doc.options.deterministic_timestamp = timestamp;
     }
+    doc.doc.options.footnote_layout = crate::docx::adapter::docx::FootnoteLayout {
+        format: crate::docx::adapter::docx::FootnoteFormat::from_name(&print.note.footnote.format),
+        restart_each_page: print.note.footnote.restart_each_page,
+        separator: print.note.footnote.separator,
+    };
 
     // 重新创建文档以使用 sections
     doc.doc.docx = doc.doc.create_document();
-    println!(
+    debug_log!(
         "【generate】已重新创建文档以使用 sections，总 section 数: {}",
         doc.doc.options.sections.len()
     );
 
+    let total = parsed.lines.len();
+    let _ = options.progress.report(ExportPhase::Layout, total, total);
+
     // 返回页数
     current_page + 1
 }
 
-/// 完成文档生成并保存
-pub fn finish_doc(doc: Document, filepath: &str) -> DocxResult<()> {
-    doc.save(filepath).map_err(|e| DocxError::AdapterError(e))
-}
-
-/// 生成DOCX文档
-pub async fn generate_docx(options: DocxOptions, parsed_document: &ParseOutput) -> DocxResult<()> {
-    // 创建一个可变的解析结果副本
-    let mut parsed_document_copy = parsed_document.clone();
-
-    // 处理行
-    crate::docx::line_processor::process_document_lines(&mut parsed_document_copy, &options.config);
-
-    // 更新选项中的解析结果
-    let mut options_with_lines = options.clone();
-    options_with_lines.parsed = Some(parsed_document_copy);
-
-    // 生成文档
-    let mut doc = init_doc(options_with_lines.clone()).await;
-    // 确保标题页处理标志被正确设置
-    doc.options.title_page_processed = options_with_lines.title_page_processed;
-    generate(&mut doc, &options_with_lines, None);
-    finish_doc(doc.doc, &options_with_lines.filepath)
-}
-
-/// 获取DOCX文档
-pub async fn get_docx(options: DocxOptions) -> DocxResult<()> {
-    println!("【get_docx】开始获取 DOCX 文档 - docx_maker::get_docx 函数");
-    println!(
-        "【get_docx】title_page_processed = {}",
-        options.title_page_processed
-    );
-
-    // 如果没有解析结果，则返回错误
-    if options.parsed.is_none() {
-        println!("【get_docx】错误：没有解析结果");
-        return Err(DocxError::InvalidConfig("没有解析结果".to_string()));
+/// 大纲模式下只保留章节、场景标题和概要，并合并多余的空行
+fn keep_outline_tokens(parsed_document: &mut ParseOutput) {
+    parsed_document.tokens.retain(|token| {
+        matches!(
+            token.token_type.as_str(),
+            "section" | "scene_heading" | "synopsis" | "separator"
+        )
+    });
+    parsed_document.tokens.dedup_by(|current, previous| {
+        current.token_type == "separator" && previous.token_type == "separator"
+    });
+    while parsed_document
+        .tokens
+        .first()
+        .is_some_and(|token| token.token_type == "separator")
+    {
+        parsed_document.tokens.remove(0);
     }
-
-    // 获取解析结果
-    let mut parsed_document_copy = options.parsed.as_ref().unwrap().clone();
-
-    println!(
-        "【get_docx】标题页元素数量: {}",
-        parsed_document_copy.title_page.len()
-    );
-    for (key, tokens) in &parsed_document_copy.title_page {
-        println!("【get_docx】标题页元素 {}: {} 个 token", key, tokens.len());
+    while parsed_document
+        .tokens
+        .last()
+        .is_some_and(|token| token.token_type == "separator")
+    {
+        parsed_document.tokens.pop();
     }
+}
 
-    // 处理行
-    println!("【get_docx】开始处理行");
-    crate::docx::line_processor::process_document_lines(&mut parsed_document_copy, &options.config);
-    println!("【get_docx】行处理完成");
-
-    // 更新选项中的解析结果
-    let mut options_with_lines = options.clone();
-    options_with_lines.parsed = Some(parsed_document_copy);
-    println!(
-        "【get_docx】标题页是否已处理: {}",
-        options_with_lines.title_page_processed
-    );
-
-    // 生成文档
-    println!("【get_docx】开始初始化文档");
-    let mut doc = init_doc(options_with_lines.clone()).await;
-    // 确保标题页处理标志被正确设置
-    let old_value = doc.options.title_page_processed;
-    doc.options.title_page_processed = options_with_lines.title_page_processed;
-    println!(
-        "【get_docx】文档初始化完成，标题页是否已处理: {} (原值: {})",
-        doc.options.title_page_processed, old_value
-    );
-    println!("【get_docx】DocxContext 对象 ID: {:p}", &doc);
+// 排版完成后、保存之前：已取消时不再保存，否则报告开始保存
+fn start_save(progress: &Progress) -> Result<(), Cancelled> {
+    progress.check()?;
+    progress.report(ExportPhase::Save, 0, 1)
+}
 
-    // 生成文档内容
-    println!("【get_docx】开始生成文档内容");
-    let page_count = generate(&mut doc, &options_with_lines, None);
-    println!("【get_docx】文档内容生成完成，页数: {}", page_count);
+// 保存完成；此时文件已经写出，不再理会取消
+fn end_save(progress: &Progress) {
+    let _ = progress.report(ExportPhase::Save, 1, 1);
+}
 
-    // 保存文档
-    println!("【get_docx】开始保存文档");
-    let result = finish_doc(doc.doc, &options_with_lines.filepath);
-    println!("【get_docx】文档保存完成");
+/// 完成文档生成并保存
+pub fn finish_doc(doc: Document, filepath: &str) -> DocxResult<()> {
+    doc.save(filepath).map_err(|e| DocxError::AdapterError(e))
+}
 
-    result
+/// 排版完成的文档
+struct LaidOut {
+    doc: DocxContext,
+    page_count: usize,
+    line_map: HashMap<usize, LineStruct>,
+    lines: std::time::Duration,
+    layout: std::time::Duration,
 }
 
-/// 获取DOCX统计信息
-pub async fn get_docx_stats(options: DocxOptions) -> DocxResult<DocxStats> {
-    println!("【get_docx_stats】开始获取 DOCX 统计信息 - docx_maker::get_docx_stats 函数");
-    println!(
-        "【get_docx_stats】title_page_processed = {}",
+// 行处理与排版，纯 CPU 计算，不读写文件（参考模板除外）
+fn layout(options: &DocxOptions) -> DocxResult<LaidOut> {
+    let Some(parsed) = options.parsed.as_ref() else {
+        debug_log!("【layout】错误：没有解析结果");
+        return Err(DocxError::InvalidConfig("没有解析结果".to_string()));
+    };
+    let lines = phase!("lines");
+    let mut parsed_document_copy = parsed.clone();
+    debug_log!(
+        "【layout】标题页元素数量: {}，title_page_processed = {}",
+        parsed_document_copy.title_page.len(),
         options.title_page_processed
     );
 
-    // 如果没有解析结果，则返回错误
-    if options.parsed.is_none() {
-        println!("【get_docx_stats】错误：没有解析结果");
-        return Err(DocxError::InvalidConfig("没有解析结果".to_string()));
+    // 处理行
+    if options.outline_only {
+        keep_outline_tokens(&mut parsed_document_copy);
     }
-
-    // 获取解析结果
-    let mut parsed_document_copy = options.parsed.as_ref().unwrap().clone();
-
-    println!(
-        "【get_docx_stats】标题页元素数量: {}",
-        parsed_document_copy.title_page.len()
-    );
-    for (key, tokens) in &parsed_document_copy.title_page {
-        println!(
-            "【get_docx_stats】标题页元素 {}: {} 个 token",
-            key,
-            tokens.len()
-        );
+    if !options.sides_characters.is_empty() {
+        crate::sides::keep_character_scenes(&mut parsed_document_copy, &options.sides_characters);
     }
-
-    // 处理行
-    println!("【get_docx_stats】开始处理行");
     crate::docx::line_processor::process_document_lines(&mut parsed_document_copy, &options.config);
-    println!("【get_docx_stats】行处理完成");
+    let lines = lines.finish();
+    debug_log!("【layout】行处理完成");
+    let phase = phase!("layout");
 
     // 更新选项中的解析结果
     let mut options_with_lines = options.clone();
     options_with_lines.parsed = Some(parsed_document_copy);
-    println!(
-        "【get_docx_stats】标题页是否已处理: {}",
-        options_with_lines.title_page_processed
-    );
 
     // 生成文档
-    println!("【get_docx_stats】开始初始化文档");
-    let mut doc = init_doc(options_with_lines.clone()).await;
+    let mut doc = init_doc(options_with_lines.clone())?;
     // 确保标题页处理标志被正确设置
-    let old_value = doc.options.title_page_processed;
     doc.options.title_page_processed = options_with_lines.title_page_processed;
-    println!(
-        "【get_docx_stats】文档初始化完成，标题页是否已处理: {} (原值: {})",
-        doc.options.title_page_processed, old_value
-    );
-    println!("【get_docx_stats】DocxContext 对象 ID: {:p}", &doc);
-
-    println!("【get_docx_stats】开始生成文档内容");
     let mut line_map = HashMap::new();
     let page_count = generate(&mut doc, &options_with_lines, Some(&mut line_map));
-    println!("【get_docx_stats】文档内容生成完成，页数: {}", page_count);
+    options.progress.check()?;
+    debug_log!("【layout】文档内容生成完成，页数: {}", page_count);
 
-    println!("【get_docx_stats】统计信息获取完成");
-    Ok(DocxStats {
+    Ok(LaidOut {
+        doc,
         page_count,
-        page_count_real: page_count,
         line_map,
+        lines,
+        layout: phase.finish(),
     })
 }
 
-/// 获取DOCX文档的Base64编码
-pub async fn get_docx_base64(options: DocxOptions) -> DocxResult<DocxAsBase64> {
-    println!("开始获取 DOCX 文档的 Base64 编码 - docx_maker::get_docx_base64 函数");
+/// 在内存中生成的文档及其统计信息
+#[derive(Debug, Clone)]
+pub struct DocxBytes {
+    /// 文件内容
+    pub data: Vec<u8>,
+    /// 统计信息，`timings.save` 为打包的耗时
+    pub stats: DocxStats,
+}
 
-    // 如果没有解析结果，则返回错误
-    if options.parsed.is_none() {
-        println!("错误：没有解析结果");
-        return Err(DocxError::InvalidConfig("没有解析结果".to_string()));
-    }
+/// 同步生成 DOCX 文档内容，不写文件，忽略 `options.filepath`。
+/// 排版和打包都是 CPU 密集的同步计算，在异步环境中应放到 `spawn_blocking` 中调用
+pub fn build_docx(options: &DocxOptions) -> DocxResult<DocxBytes> {
+    let built = pack(options, &crate::render::DocxBackend)?;
+    end_save(&options.progress);
+    Ok(built)
+}
+
+/// 同步按 DOCX 的排版流程生成文档，再由指定的导出后端序列化（DOCX、ODT、HTML 等），不写文件
+pub fn build_rendered(
+    options: &DocxOptions,
+    backend: &dyn crate::render::RenderBackend,
+) -> DocxResult<DocxBytes> {
+    let built = pack(options, backend)?;
+    end_save(&options.progress);
+    Ok(built)
+}
+
+/// 同步排版并返回统计信息，不生成文件内容
+pub fn build_docx_stats(options: &DocxOptions) -> DocxResult<DocxStats> {
+    let laid_out = layout(options)?;
+    Ok(DocxStats {
+        page_count: laid_out.page_count,
+        page_count_real: laid_out.page_count,
+        line_map: laid_out.line_map,
+        timings: ExportTimings {
+            lines: laid_out.lines,
+            layout: laid_out.layout,
+            ..Default::default()
+        },
+    })
+}
 
-    // 获取解析结果
-    let mut parsed_document_copy = options.parsed.as_ref().unwrap().clone();
+// 排版并由导出后端序列化为文件内容；保存阶段的结束由调用方在写出后报告
+fn pack(
+    options: &DocxOptions,
+    backend: &dyn crate::render::RenderBackend,
+) -> DocxResult<DocxBytes> {
+    let laid_out = layout(options)?;
+    start_save(&options.progress)?;
+    let save = phase!("save");
+    let data = backend.render(&laid_out.doc.doc)?;
+    if options.config.validate_output && backend.extension() == "docx" {
+        let errors = super::validate::validate_docx(&data);
+        if !errors.is_empty() {
+            return Err(DocxError::InvalidOutput(errors));
+        }
+    }
+    Ok(DocxBytes {
+        data,
+        stats: DocxStats {
+            page_count: laid_out.page_count,
+            page_count_real: laid_out.page_count,
+            line_map: laid_out.line_map,
+            timings: ExportTimings {
+                lines: laid_out.lines,
+                layout: laid_out.layout,
+                save: save.finish(),
+                ..Default::default()
+            },
+        },
+    })
+}
 
-    println!("标题页元素数量: {}", parsed_document_copy.title_page.len());
-    for (key, tokens) in &parsed_document_copy.title_page {
-        println!("标题页元素 {}: {} 个 token", key, tokens.len());
+// 在 tokio 运行时中把同步的排版放到阻塞线程池执行，不占用异步工作线程；没有运行时则直接执行
+async fn run_blocking<T, F>(f: F) -> DocxResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> DocxResult<T> + Send + 'static,
+{
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => handle.spawn_blocking(f).await?,
+        Err(_) => f(),
     }
+}
 
-    // 处理行
-    println!("开始处理行");
-    crate::docx::line_processor::process_document_lines(&mut parsed_document_copy, &options.config);
-    println!("行处理完成");
+/// 生成DOCX文档
+pub async fn generate_docx(
+    mut options: DocxOptions,
+    parsed_document: &ParseOutput,
+) -> DocxResult<()> {
+    options.parsed = Some(parsed_document.clone());
+    get_docx(options).await.map(|_| ())
+}
 
-    // 更新选项中的解析结果
-    let mut options_with_lines = options.clone();
-    options_with_lines.parsed = Some(parsed_document_copy);
+/// 获取DOCX文档，返回排版统计（页数、行映射及耗时）。排版在阻塞线程池中执行，文件异步写入
+pub async fn get_docx(options: DocxOptions) -> DocxResult<DocxStats> {
+    debug_log!("【get_docx】开始获取 DOCX 文档");
+    get_rendered(options, Box::new(crate::render::DocxBackend)).await
+}
 
-    // 确保标题页处理标志被正确传递
-    println!(
-        "标题页是否已处理: {}",
-        options_with_lines.title_page_processed
-    );
+/// 按 DOCX 的排版流程生成文档，再由指定的导出后端序列化（DOCX、ODT、HTML 等）并写入文件
+pub async fn get_rendered(
+    options: DocxOptions,
+    backend: Box<dyn crate::render::RenderBackend>,
+) -> DocxResult<DocxStats> {
+    let filepath = options.filepath.clone();
+    let progress = options.progress.clone();
+    let built = run_blocking(move || pack(&options, backend.as_ref())).await?;
+    write_file(&filepath, built, &progress).await
+}
 
-    // 生成文档
-    println!("开始初始化文档");
-    let mut doc = init_doc(options_with_lines.clone()).await;
-    // 确保标题页处理标志被正确设置
-    let old_value = doc.options.title_page_processed;
-    doc.options.title_page_processed = options_with_lines.title_page_processed;
-    println!(
-        "文档初始化完成，标题页是否已处理: {} (原值: {})",
-        doc.options.title_page_processed, old_value
-    );
-    println!("DocxContext 对象 ID: {:p}", &doc);
+// 异步写出已生成的内容，写文件的耗时计入保存阶段
+async fn write_file(
+    filepath: &str,
+    built: DocxBytes,
+    progress: &Progress,
+) -> DocxResult<DocxStats> {
+    let save = phase!("save");
+    tokio::fs::write(filepath, &built.data).await?;
+    end_save(progress);
+    debug_log!("【write_file】文档保存完成: {}", filepath);
+    let mut stats = built.stats;
+    stats.timings.save += save.finish();
+    Ok(stats)
+}
 
-    println!("开始生成文档内容");
-    let mut line_map = HashMap::new();
-    let page_count = generate(&mut doc, &options_with_lines, Some(&mut line_map));
-    println!("文档内容生成完成，页数: {}", page_count);
+/// 在内存中生成文档，不写文件；`backend` 为空时输出 DOCX。排版在阻塞线程池中执行
+pub async fn get_bytes(
+    options: DocxOptions,
+    backend: Option<Box<dyn crate::render::RenderBackend>>,
+) -> DocxResult<DocxBytes> {
+    let backend = backend.unwrap_or_else(|| Box::new(crate::render::DocxBackend));
+    run_blocking(move || build_rendered(&options, backend.as_ref())).await
+}
+
+/// 生成 DOCX 文档并写入任意异步输出（网络连接、HTTP 响应体、内存缓冲区等），不读写文件
+pub async fn write_docx<W>(options: DocxOptions, writer: &mut W) -> DocxResult<DocxStats>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt;
+
+    let progress = options.progress.clone();
+    let built = run_blocking(move || pack(&options, &crate::render::DocxBackend)).await?;
+    let save = phase!("save");
+    writer.write_all(&built.data).await?;
+    writer.flush().await?;
+    end_save(&progress);
+    let mut stats = built.stats;
+    stats.timings.save += save.finish();
+    Ok(stats)
+}
 
-    println!("开始转换为 Base64");
-    let data = doc
-        .doc
-        .to_base64()
-        .map_err(|e| DocxError::AdapterError(e))?;
-    println!("Base64 转换完成");
+/// 获取DOCX统计信息
+pub async fn get_docx_stats(options: DocxOptions) -> DocxResult<DocxStats> {
+    debug_log!("【get_docx_stats】开始获取 DOCX 统计信息");
+    run_blocking(move || build_docx_stats(&options)).await
+}
 
-    println!("docx_maker::get_docx_base64 函数处理完成");
+/// 获取DOCX文档的Base64编码
+pub async fn get_docx_base64(options: DocxOptions) -> DocxResult<DocxAsBase64> {
+    debug_log!("【get_docx_base64】开始获取 DOCX 文档的 Base64 编码");
+    let progress = options.progress.clone();
+    let mut built = run_blocking(move || pack(&options, &crate::render::DocxBackend)).await?;
+    let save = phase!("save");
+    let data = base64::encode(&built.data);
+    end_save(&progress);
+    built.stats.timings.save += save.finish();
     Ok(DocxAsBase64 {
         data,
-        stats: DocxStats {
-            page_count,
-            page_count_real: page_count,
-            line_map,
-        },
+        stats: built.stats,
     })
 }
@@ -1,6 +1,10 @@
-use crate::parser::ParseOutput;
+use crate::docx::PrintProfile;
 use crate::models::Conf;
+use crate::parser::fountain_parser::Line;
+use crate::parser::ParseOutput;
 use crate::pdf::liner::Liner;
+use crate::utils::{is_blank_line_after_style, strip_style_chars};
+use serde::{Deserialize, Serialize};
 
 /// 处理文档行
 pub fn process_document_lines(parsed_document: &mut ParseOutput, config: &Conf) {
@@ -18,3 +22,341 @@ pub fn process_document_lines(parsed_document: &mut ParseOutput, config: &Conf)
     // 更新解析结果
     parsed_document.lines = lines;
 }
+
+/// 按排版估算页数：各元素按所在栏宽折行后计算行数，每 `lines_per_page` 行一页，分页符处另起一页
+pub fn estimate_page_count(lines: &[Line], profile: &PrintProfile) -> usize {
+    let per_page = profile.lines_per_page.max(1);
+    lines
+        .split(|line| line.token_type == "page_break")
+        .map(|page| count_print_lines(page, profile))
+        .filter(|count| *count > 0)
+        .map(|count| count.div_ceil(per_page))
+        .sum()
+}
+
+/// 场景长度，以八分之一页计（制片中常用的单位）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneLength {
+    /// 场景编号
+    pub number: String,
+    /// 场景标题所在行（从 0 开始）
+    pub line: usize,
+    /// 折行后的打印行数
+    pub print_lines: usize,
+    /// 长度（八分之一页），不足八分之一页的按八分之一页计
+    pub eighths: usize,
+}
+
+impl SceneLength {
+    /// 惯用写法，如 "3/8"、"1 2/8"、"2"
+    pub fn display(&self) -> String {
+        let (pages, eighths) = (self.eighths / 8, self.eighths % 8);
+        match (pages, eighths) {
+            (0, e) => format!("{}/8", e),
+            (p, 0) => p.to_string(),
+            (p, e) => format!("{} {}/8", p, e),
+        }
+    }
+}
+
+/// 按排版估算每场戏的长度：场景标题到下一个场景标题之间的打印行数，按 `lines_per_page` 折算成八分之一页
+///
+/// 第一个场景之前的内容不计，分页符不占行。
+pub fn scene_lengths(lines: &[Line], profile: &PrintProfile) -> Vec<SceneLength> {
+    let per_page = profile.lines_per_page.max(1);
+    let mut scenes: Vec<(SceneLength, Vec<Line>)> = Vec::new();
+    for line in lines {
+        if line.token_type == "scene_heading" && line.local_index == 0 {
+            let scene = SceneLength {
+                number: line
+                    .number
+                    .as_deref()
+                    .unwrap_or_default()
+                    .trim_start_matches('↑')
+                    .to_string(),
+                line: line.token.unwrap_or_default(),
+                print_lines: 0,
+                eighths: 0,
+            };
+            scenes.push((scene, Vec::new()));
+        }
+        if line.token_type == "page_break" {
+            continue;
+        }
+        if let Some((_, scene_lines)) = scenes.last_mut() {
+            scene_lines.push(line.clone());
+        }
+    }
+    scenes
+        .into_iter()
+        .map(|(mut scene, scene_lines)| {
+            scene.print_lines = count_print_lines(&scene_lines, profile);
+            scene.eighths = (scene.print_lines * 8).div_ceil(per_page).max(1);
+            scene
+        })
+        .collect()
+}
+
+/// 折行后的打印行数
+///
+/// 每个元素的可用宽度由 `PrintProfile` 中的 feed 和页边距决定，对话和括号左右缩进对称；
+/// 双对话左右两栏（三人同时对白还有中栏）并排，取较长的一栏。
+pub fn count_print_lines(lines: &[Line], profile: &PrintProfile) -> usize {
+    let mut total = 0;
+    let mut dual_left = 0;
+    let mut dual_middle = 0;
+    let mut dual_right = 0;
+    for line in lines {
+        let count = wrap(
+            &strip_style_chars(&line.text),
+            element_columns(profile, &line.token_type, line.dual.is_some()),
+        )
+        .len();
+        match line.dual.as_deref() {
+            Some("left") => dual_left += count,
+            Some("middle") => dual_middle += count,
+            Some("right") => dual_right += count,
+            _ => {
+                total += dual_left.max(dual_middle).max(dual_right) + count;
+                dual_left = 0;
+                dual_middle = 0;
+                dual_right = 0;
+            }
+        }
+    }
+    total + dual_left.max(dual_middle).max(dual_right)
+}
+
+/// 分页规则算出的一行（一个段落）的分页约束
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineKeep {
+    /// 与下一行保持在同一页
+    pub keep_next: bool,
+    /// 本行折行后不跨页
+    pub keep_lines: bool,
+}
+
+/// 按 `PrintProfile::pagination` 中的分页规则计算每一行的分页约束，与 `lines` 一一对应
+///
+/// 场景标题与其后的空行、动作连在一起，直到动作累计达到要求的打印行数；角色名与其后的括号注释、
+/// 对白连在一起，直到对白达到要求的行数。要求的行数落在一个折行的段落中间时，该段落整段不跨页。
+/// 遇到其他内容时停止，但至少与下一个非空行在同一页。场景标题本身总是不跨页。
+pub fn pagination_keeps(lines: &[Line], profile: &PrintProfile) -> Vec<LineKeep> {
+    let rules = &profile.pagination;
+    let mut keeps = vec![LineKeep::default(); lines.len()];
+    for (i, line) in lines.iter().enumerate() {
+        match line.token_type.as_str() {
+            "scene_heading" => {
+                keeps[i].keep_lines = true;
+                let min = rules.min_action_lines_after_scene_heading;
+                keep_following(lines, profile, &mut keeps, i, "action", &[], min);
+            }
+            "character" => {
+                let min = rules.min_dialogue_lines_after_character;
+                let passed = ["parenthetical"];
+                keep_following(lines, profile, &mut keeps, i, "dialogue", &passed, min);
+            }
+            "parenthetical" if rules.keep_parentheticals_together => {
+                keeps[i].keep_next = true;
+                keeps[i].keep_lines = true;
+            }
+            _ => {}
+        }
+    }
+    keeps
+}
+
+// 从第 `start` 行起与下一行保持在同一页，直到类型为 `counted` 的行累计达到 `min` 个打印行；
+// 空行和 `passed` 中类型的行不计数，其他内容结束连接
+fn keep_following(
+    lines: &[Line],
+    profile: &PrintProfile,
+    keeps: &mut [LineKeep],
+    start: usize,
+    counted: &str,
+    passed: &[&str],
+    min: usize,
+) {
+    let mut remaining = min;
+    let mut i = start;
+    while remaining > 0 {
+        keeps[i].keep_next = true;
+        i += 1;
+        let Some(line) = lines.get(i) else {
+            break;
+        };
+        if line.token_type == "page_break" {
+            break;
+        }
+        if line.token_type == counted {
+            let count = print_line_count(line, profile);
+            if count >= remaining {
+                keeps[i].keep_lines |= remaining > 1;
+                break;
+            }
+            remaining -= count;
+        } else if !passed.contains(&line.token_type.as_str())
+            && !is_blank_line_after_style(&line.text)
+        {
+            break;
+        }
+    }
+}
+
+// 一行折行后的打印行数，空行也占一行
+fn print_line_count(line: &Line, profile: &PrintProfile) -> usize {
+    wrap(
+        &strip_style_chars(&line.text),
+        element_columns(profile, &line.token_type, line.dual.is_some()),
+    )
+    .len()
+    .max(1)
+}
+
+// 元素一行能容纳的半角字符数
+fn element_columns(profile: &PrintProfile, token_type: &str, dual: bool) -> usize {
+    let right = profile.page_width - profile.right_margin;
+    let action = profile.action.feed;
+    let width = if dual {
+        (right - action) / 2.0
+    } else {
+        match token_type {
+            "scene_heading" => right - profile.scene_heading.feed,
+            "character" => right - profile.character.feed,
+            "dialogue" => right - profile.dialogue.feed - (profile.dialogue.feed - action),
+            "parenthetical" => {
+                right - profile.parenthetical.feed - (profile.parenthetical.feed - action)
+            }
+            _ => right - action,
+        }
+    };
+    (width / profile.font_width.max(0.01)).floor().max(1.0) as usize
+}
+
+/// 按显示宽度折行，断行位置遵循 Unicode 换行算法（UAX #14）的基本规则
+///
+/// 拉丁文字在空格和连字符后断开；汉字、假名、谚文等字与字之间都可以断开，
+/// 但句号、逗号、右括号、长音符、小假名等不放在行首，左括号、左引号不放在行尾（避头尾）。
+/// 一行内没有可断开的位置时在宽度处直接断开。`width` 以半角字符计，全角字符占两列。
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut result = Vec::new();
+    for paragraph in text.split('\n') {
+        let chars: Vec<char> = paragraph.trim_end().chars().collect();
+        let mut line_start = 0;
+        let mut line_width = 0;
+        let mut last_break = None;
+        let mut i = 0;
+        while i < chars.len() {
+            if i > line_start && can_break_before(&chars, i) {
+                last_break = Some(i);
+            }
+            let w = char_width(chars[i]);
+            if line_width + w > width && i > line_start && !chars[i].is_whitespace() {
+                let cut = last_break.unwrap_or(i);
+                result.push(
+                    chars[line_start..cut]
+                        .iter()
+                        .collect::<String>()
+                        .trim_end()
+                        .to_string(),
+                );
+                line_start = cut;
+                while line_start < i && chars[line_start].is_whitespace() {
+                    line_start += 1;
+                }
+                line_width = chars[line_start..i].iter().map(|c| char_width(*c)).sum();
+                last_break = (line_start + 1..i)
+                    .rev()
+                    .find(|&k| can_break_before(&chars, k));
+                continue;
+            }
+            line_width += w;
+            i += 1;
+        }
+        result.push(chars[line_start..].iter().collect());
+    }
+    result
+}
+
+/// 显示宽度，全角字符占两列
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+// 全角字符（中日韩文字、全角标点）占两列
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6 => 2,
+        _ => 1,
+    }
+}
+
+/// 换行算法中的字符类别（UAX #14 的简化）
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BreakClass {
+    /// 空白
+    Space,
+    /// 表意文字、假名、谚文，前后都可以断开
+    Ideographic,
+    /// 左括号、左引号，之后不能断开
+    Open,
+    /// 右括号、句读、长音符、小假名等，之前不能断开
+    NonStarter,
+    /// 连字符，之后可以断开
+    Hyphen,
+    /// 破折号，前后可以断开，但连续的破折号之间不能断开
+    Dash,
+    /// 字母、数字等，彼此之间不能断开
+    Alphabetic,
+}
+
+const OPEN_CHARS: &str = "([{（［｛「『【〔〈《〖〘〝‘“";
+const NON_STARTER_CHARS: &str = ")]},.;:!?%、。，．：；！？）］｝」』】〕〉》〗〙〞’”｡､｣\
+ー々〻ゝゞヽヾぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶ・…‥";
+
+fn break_class(c: char) -> BreakClass {
+    if c.is_whitespace() {
+        BreakClass::Space
+    } else if OPEN_CHARS.contains(c) {
+        BreakClass::Open
+    } else if NON_STARTER_CHARS.contains(c) {
+        BreakClass::NonStarter
+    } else if matches!(c, '-' | '\u{2010}' | '\u{2013}') {
+        BreakClass::Hyphen
+    } else if matches!(c, '—' | '―') {
+        BreakClass::Dash
+    } else if matches!(c as u32,
+        0x2E80..=0x2FFF | 0x3000..=0x30FF | 0x3100..=0x31FF | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFF00..=0xFFEF
+        | 0x20000..=0x3FFFD)
+    {
+        BreakClass::Ideographic
+    } else {
+        BreakClass::Alphabetic
+    }
+}
+
+// chars[i - 1] 与 chars[i] 之间能否断开
+fn can_break_before(chars: &[char], i: usize) -> bool {
+    use BreakClass::*;
+    let before = break_class(chars[i - 1]);
+    let after = break_class(chars[i]);
+    match (before, after) {
+        (_, Space) | (_, NonStarter) | (Open, _) | (Dash, Dash) => false,
+        (Space, _) => true,
+        (Ideographic | Dash, _) | (_, Ideographic | Dash) => true,
+        (Hyphen, Alphabetic) => true,
+        _ => false,
+    }
+}
@@ -167,3 +167,15 @@ pub fn extract_metadata_from_parsed_document(
 
     result
 }
+
+/// 解析 metadata 中 `print.*` 的开关值
+///
+/// JSON 中的布尔值扁平化后为 "true"/"false"，数字为 "1"/"0" 等（非零为开）；
+/// 其它无法识别的值返回 None，由调用方保留配置中的值
+pub fn parse_metadata_bool(value: &str) -> Option<bool> {
+    let value = value.trim();
+    value
+        .parse::<bool>()
+        .ok()
+        .or_else(|| value.parse::<f64>().ok().map(|n| n != 0.0))
+}
@@ -4,11 +4,13 @@ pub mod docx;
 pub mod adapter;
 pub mod line_processor;
 pub mod metadata_extractor;
+pub mod validate;
 
 // 从 docx_maker 导出
 pub use docx_maker::{
     generate_docx, DocxOptions, DocxResult, PrintProfile,
-    DocxContext, CurrentNote
+    DocxContext, CurrentNote,
+    build_docx, build_docx_stats, build_rendered, write_docx, DocxBytes
 };
 
 // 从 docx_adapter 导出
@@ -18,7 +20,23 @@ pub use docx_adapter::{DocxAdapter, DocxAdapterError, DocxAdapterResult, TextSty
 pub use docx::{
     DocxGenerateError,
     DocxGenerateResult,
-    generate_docx_document
+    generate_docx_document,
+    generate_docx_document_timed,
+    generate_docx_document_with_progress,
+    generate_docx_outline,
+    generate_docx_sides,
+    generate_docx_diff,
+    generate_docx_revisions,
+    generate_docx_av,
+    generate_odt_document,
+    generate_pdf_document,
+    generate_document_with_backend,
+    generate_docx_bytes,
+    generate_docx_bytes_timed,
+    generate_odt_bytes,
+    generate_html_bytes,
+    generate_pdf_bytes,
+    generate_bytes_with_backend
 };
 pub use docx_maker::ExportConfig;
 
@@ -36,9 +54,12 @@ pub use adapter::{
 
 // 从 adapter::docx 导出
 pub use adapter::docx::{
-    Document, Paragraph, TextRun, BreakRun, RunTrait
+    Document, Paragraph, TextRun, BreakRun, RunTrait, Revision, RevisionKind, Comment
 };
 
+// 从 validate 导出
+pub use validate::{validate_docx, ValidationError};
+
 // 从 metadata_extractor 导出
 pub use metadata_extractor::{
     ExtractedMetadata, extract_metadata_from_parsed_document
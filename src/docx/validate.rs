@@ -0,0 +1,417 @@
+//! 检查生成的 DOCX 是否符合 OOXML 规范
+//!
+//! 排版结果有问题时，docx-rs 和后续的 XML 修补都不会报错，只有 Word 拒绝打开文件时才会发现。
+//! `validate_docx` 读取打包好的文件，检查 Word 最容易拒绝的几类问题：部件的内容类型、
+//! 关系 id 及其指向的部件、正文中的脚注和批注引用、节属性（页面尺寸、页边距、子元素顺序），
+//! 返回发现的所有问题。检查只基于文本匹配，不是完整的 schema 校验。
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use thiserror::Error;
+
+/// DOCX 中违反规范的地方
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("无法读取 DOCX 压缩包: {0}")]
+    InvalidPackage(String),
+
+    #[error("缺少部件 {0}")]
+    MissingPart(String),
+
+    #[error("部件 {0} 没有声明内容类型")]
+    MissingContentType(String),
+
+    #[error("{part} 的关系 id {id} 重复")]
+    DuplicateRelationshipId { part: String, id: String },
+
+    #[error("{part} 引用了不存在的关系 {id}")]
+    UnknownRelationship { part: String, id: String },
+
+    #[error("{part} 的关系 {id} 指向不存在的部件 {target}")]
+    MissingTarget {
+        part: String,
+        id: String,
+        target: String,
+    },
+
+    #[error("正文引用了不存在的脚注 {0}")]
+    UnknownFootnote(String),
+
+    #[error("正文引用了不存在的批注 {0}")]
+    UnknownComment(String),
+
+    #[error("第 {index} 个节的属性无效: {reason}")]
+    InvalidSection { index: usize, reason: String },
+}
+
+/// 把多个问题合并为一行说明，用于错误信息
+pub fn describe(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("；")
+}
+
+// w:sectPr 子元素的规定顺序，页眉和页脚引用可以交错
+const SECTION_ORDER: [&str; 21] = [
+    "headerReference",
+    "footerReference",
+    "footnotePr",
+    "endnotePr",
+    "type",
+    "pgSz",
+    "pgMar",
+    "paperSrc",
+    "pgBorders",
+    "lnNumType",
+    "pgNumType",
+    "cols",
+    "formProt",
+    "vAlign",
+    "noEndnote",
+    "titlePg",
+    "textDirection",
+    "bidi",
+    "rtlGutter",
+    "docGrid",
+    "printerSettings",
+];
+
+lazy_static! {
+    static ref ELEMENT_REGEX: Regex =
+        Regex::new(r#"<([\w:]+)((?:\s+[\w:]+\s*=\s*"[^"]*")*)\s*/?>"#).unwrap();
+    static ref ATTRIBUTE_REGEX: Regex = Regex::new(r#"([\w:]+)\s*=\s*"([^"]*)""#).unwrap();
+    static ref RELATIONSHIP_REF_REGEX: Regex =
+        Regex::new(r#"\br:(?:id|embed|link|pict|dm|lo|qs|cs|href)="([^"]*)""#).unwrap();
+    static ref SECTION_REGEX: Regex =
+        Regex::new(r"(?s)<w:sectPr\b[^>]*?(?:/>|>(.*?)</w:sectPr>)").unwrap();
+}
+
+/// 检查 DOCX 文件内容，返回发现的所有问题，没有问题时为空
+pub fn validate_docx(bytes: &[u8]) -> Vec<ValidationError> {
+    let parts = match read_parts(bytes) {
+        Ok(parts) => parts,
+        Err(e) => return vec![ValidationError::InvalidPackage(e.to_string())],
+    };
+    let mut errors = Vec::new();
+
+    let Some(content_types) = parts.get("[Content_Types].xml") else {
+        return vec![ValidationError::MissingPart(
+            "[Content_Types].xml".to_string(),
+        )];
+    };
+    check_content_types(&parts, content_types, &mut errors);
+    check_relationships(&parts, &mut errors);
+
+    let main = related_part(&parts, "", "/officeDocument")
+        .unwrap_or_else(|| "word/document.xml".to_string());
+    let Some(document) = parts.get(&main) else {
+        errors.push(ValidationError::MissingPart(main));
+        return errors;
+    };
+
+    let footnotes = related_part(&parts, &main, "/footnotes")
+        .and_then(|name| parts.get(&name))
+        .map(|xml| element_ids(xml, "w:footnote"))
+        .unwrap_or_default();
+    for id in reference_ids(document, "w:footnoteReference") {
+        if !footnotes.contains(&id) {
+            errors.push(ValidationError::UnknownFootnote(id));
+        }
+    }
+    let comments = related_part(&parts, &main, "/comments")
+        .and_then(|name| parts.get(&name))
+        .map(|xml| element_ids(xml, "w:comment"))
+        .unwrap_or_default();
+    let mut comment_refs = reference_ids(document, "w:commentReference");
+    comment_refs.extend(reference_ids(document, "w:commentRangeStart"));
+    for id in comment_refs {
+        if !comments.contains(&id) {
+            errors.push(ValidationError::UnknownComment(id));
+        }
+    }
+
+    check_sections(document, &mut errors);
+    errors
+}
+
+// 读出压缩包中的所有部件（目录除外）
+fn read_parts(bytes: &[u8]) -> zip::result::ZipResult<HashMap<String, String>> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let mut parts = HashMap::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.is_dir() {
+            continue;
+        }
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        // 图片等二进制部件只需要名称
+        let name = file.name().to_string();
+        let text = if name.ends_with(".xml") || name.ends_with(".rels") {
+            String::from_utf8_lossy(&content).into_owned()
+        } else {
+            String::new()
+        };
+        parts.insert(name, text);
+    }
+    Ok(parts)
+}
+
+// 每个部件都要有 Override 或按扩展名的 Default 内容类型
+fn check_content_types(
+    parts: &HashMap<String, String>,
+    content_types: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    let defaults: HashSet<String> = elements(content_types, "Default")
+        .iter()
+        .filter_map(|attrs| attrs.get("Extension"))
+        .map(|ext| ext.to_lowercase())
+        .collect();
+    let overrides: HashSet<String> = elements(content_types, "Override")
+        .iter()
+        .filter_map(|attrs| attrs.get("PartName"))
+        .map(|name| name.trim_start_matches('/').to_lowercase())
+        .collect();
+    let mut names: Vec<&String> = parts.keys().collect();
+    names.sort();
+    for name in names {
+        if name == "[Content_Types].xml" {
+            continue;
+        }
+        let lower = name.to_lowercase();
+        let extension = lower.rsplit_once('.').map_or("", |(_, ext)| ext);
+        if !overrides.contains(&lower) && !defaults.contains(extension) {
+            errors.push(ValidationError::MissingContentType(name.clone()));
+        }
+    }
+}
+
+// 关系 id 不能重复，内部关系指向的部件必须存在，部件中引用的关系 id 必须在它的关系部件中
+fn check_relationships(parts: &HashMap<String, String>, errors: &mut Vec<ValidationError>) {
+    let mut names: Vec<&String> = parts.keys().collect();
+    names.sort();
+    let mut ids_of: HashMap<String, HashSet<String>> = HashMap::new();
+    for name in &names {
+        let Some((dir, file)) = name
+            .strip_suffix(".rels")
+            .and_then(|source| source.rsplit_once("_rels/"))
+        else {
+            continue;
+        };
+        let source = format!("{}{}", dir, file);
+        let source_dir = dir.trim_end_matches('/');
+        let mut ids = HashSet::new();
+        for attrs in elements(&parts[*name], "Relationship") {
+            let id = attrs.get("Id").cloned().unwrap_or_default();
+            if !ids.insert(id.clone()) {
+                errors.push(ValidationError::DuplicateRelationshipId {
+                    part: name.to_string(),
+                    id: id.clone(),
+                });
+            }
+            if attrs.get("TargetMode").map(String::as_str) == Some("External") {
+                continue;
+            }
+            let target = attrs.get("Target").cloned().unwrap_or_default();
+            if !parts.contains_key(&resolve(source_dir, &target)) {
+                errors.push(ValidationError::MissingTarget {
+                    part: name.to_string(),
+                    id,
+                    target,
+                });
+            }
+        }
+        ids_of.insert(source, ids);
+    }
+
+    let empty = HashSet::new();
+    for name in names {
+        if !name.ends_with(".xml") || name == "[Content_Types].xml" {
+            continue;
+        }
+        let ids = ids_of.get(name.as_str()).unwrap_or(&empty);
+        let mut reported = HashSet::new();
+        for caps in RELATIONSHIP_REF_REGEX.captures_iter(&parts[name]) {
+            let id = &caps[1];
+            if !ids.contains(id) && reported.insert(id.to_string()) {
+                errors.push(ValidationError::UnknownRelationship {
+                    part: name.clone(),
+                    id: id.to_string(),
+                });
+            }
+        }
+    }
+}
+
+// 页面尺寸、页边距、页眉页脚引用和子元素顺序；最后一个节的属性必须是 body 的最后一个元素
+fn check_sections(document: &str, errors: &mut Vec<ValidationError>) {
+    let mut invalid = |index: usize, reason: String| {
+        errors.push(ValidationError::InvalidSection { index, reason });
+    };
+    let sections: Vec<_> = SECTION_REGEX.captures_iter(document).collect();
+    for (i, caps) in sections.iter().enumerate() {
+        let index = i + 1;
+        let body = caps.get(1).map_or("", |m| m.as_str());
+        let children: Vec<(String, HashMap<String, String>)> = ELEMENT_REGEX
+            .captures_iter(body)
+            .filter_map(|c| {
+                let name = c[1].strip_prefix("w:")?.to_string();
+                Some((name, attributes(&c[2])))
+            })
+            .collect();
+        let child = |name: &str| children.iter().find(|(n, _)| n == name).map(|(_, a)| a);
+        let number = |attrs: &HashMap<String, String>, key: &str| {
+            attrs.get(key).and_then(|v| v.parse::<i64>().ok())
+        };
+
+        let size = child("pgSz").map(|attrs| (number(attrs, "w:w"), number(attrs, "w:h")));
+        let (width, height) = match size {
+            None => {
+                invalid(index, "缺少页面尺寸 pgSz".to_string());
+                (None, None)
+            }
+            Some((Some(w), Some(h))) if w > 0 && h > 0 => (Some(w), Some(h)),
+            Some(_) => {
+                invalid(index, "页面尺寸 pgSz 的宽高必须为正数".to_string());
+                (None, None)
+            }
+        };
+        match child("pgMar") {
+            None => invalid(index, "缺少页边距 pgMar".to_string()),
+            Some(attrs) => {
+                let margin = |key: &str| number(attrs, key).unwrap_or(0);
+                if margin("w:left") < 0 || margin("w:right") < 0 {
+                    invalid(index, "左右页边距不能为负数".to_string());
+                }
+                if width.is_some_and(|w| margin("w:left") + margin("w:right") >= w) {
+                    invalid(index, "左右页边距之和超出页面宽度".to_string());
+                }
+                if height.is_some_and(|h| margin("w:top").abs() + margin("w:bottom").abs() >= h) {
+                    invalid(index, "上下页边距之和超出页面高度".to_string());
+                }
+            }
+        }
+
+        let mut references = HashSet::new();
+        for (name, attrs) in &children {
+            if name == "headerReference" || name == "footerReference" {
+                let kind = attrs.get("w:type").cloned().unwrap_or_default();
+                if !["default", "first", "even"].contains(&kind.as_str()) {
+                    invalid(index, format!("{} 的类型 {} 无效", name, kind));
+                } else if !references.insert((name.clone(), kind.clone())) {
+                    invalid(index, format!("{} 的类型 {} 重复", name, kind));
+                }
+            }
+        }
+
+        // pgBorders、cols 的子元素不在列表中，跳过
+        let mut last: Option<(usize, &str)> = None;
+        for (name, _) in &children {
+            let Some(rank) = SECTION_ORDER.iter().position(|n| n == name) else {
+                continue;
+            };
+            // 页眉和页脚引用同属第一组
+            let rank = rank.max(1);
+            if let Some((previous, previous_name)) = last {
+                if rank < previous {
+                    invalid(
+                        index,
+                        format!("子元素 {} 应排在 {} 之前", name, previous_name),
+                    );
+                }
+            }
+            last = Some((rank, name));
+        }
+    }
+
+    if let Some(last) = sections.last() {
+        let end = last.get(0).unwrap().end();
+        if !document[end..].trim_start().starts_with("</w:body>") {
+            invalid(
+                sections.len(),
+                "最后一个节的属性必须是 body 的最后一个元素".to_string(),
+            );
+        }
+    }
+}
+
+// 部件 `source`（包本身为空）的关系部件的名称
+fn rels_name(source: &str) -> String {
+    let (dir, file) = source.rsplit_once('/').unwrap_or(("", source));
+    if dir.is_empty() {
+        format!("_rels/{}.rels", file)
+    } else {
+        format!("{}/_rels/{}.rels", dir, file)
+    }
+}
+
+// 部件 `source` 的关系中类型以 `type_suffix` 结尾的第一个部件
+fn related_part(
+    parts: &HashMap<String, String>,
+    source: &str,
+    type_suffix: &str,
+) -> Option<String> {
+    let dir = source.rsplit_once('/').map_or("", |(d, _)| d);
+    elements(parts.get(&rels_name(source))?, "Relationship")
+        .into_iter()
+        .find(|attrs| attrs.get("Type").is_some_and(|t| t.ends_with(type_suffix)))
+        .and_then(|attrs| attrs.get("Target").map(|target| resolve(dir, target)))
+}
+
+// 文本中所有 `name` 元素的属性
+fn elements(xml: &str, name: &str) -> Vec<HashMap<String, String>> {
+    ELEMENT_REGEX
+        .captures_iter(xml)
+        .filter(|caps| &caps[1] == name)
+        .map(|caps| attributes(&caps[2]))
+        .collect()
+}
+
+fn attributes(text: &str) -> HashMap<String, String> {
+    ATTRIBUTE_REGEX
+        .captures_iter(text)
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+// 定义了 w:id 的 `name` 元素（脚注、批注）
+fn element_ids(xml: &str, name: &str) -> HashSet<String> {
+    elements(xml, name)
+        .into_iter()
+        .filter_map(|mut attrs| attrs.remove("w:id"))
+        .collect()
+}
+
+// 引用元素的 w:id，按出现顺序，去掉重复
+fn reference_ids(xml: &str, name: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    elements(xml, name)
+        .into_iter()
+        .filter_map(|mut attrs| attrs.remove("w:id"))
+        .filter(|id| seen.insert(id.clone()))
+        .collect()
+}
+
+// 按关系部件所在目录解析相对路径，以 / 开头的为包内绝对路径
+fn resolve(dir: &str, target: &str) -> String {
+    let mut segments: Vec<&str> = if target.starts_with('/') {
+        Vec::new()
+    } else {
+        dir.split('/').filter(|s| !s.is_empty()).collect()
+    };
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
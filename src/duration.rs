@@ -0,0 +1,190 @@
+//! 对白时长预估模型
+//!
+//! 默认按字符数和标点数估算（[`CharRateModel`]），也可以按每分钟词数（[`WordsPerMinuteModel`]）
+//! 或音节数（[`SyllableModel`]）估算；[`SpeakingRateModel`] 在任意模型之上按角色调整语速。
+//! 通过 `Conf::dialogue_duration_model` 选择模型，或用 `FountainParser::set_duration_model` 接入自定义模型。
+
+use crate::models::Conf;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
+
+lazy_static! {
+    static ref NON_SPOKEN_REGEX: Regex = Regex::new(r"\s|\p{P}|\p{S}").unwrap();
+    static ref PUNCTUATION_REGEX: Regex =
+        Regex::new(r"(\.|\?|\!|\:|。|？|！|：|．|｡|…)|(\,|，|;|；|、|､)").unwrap();
+    static ref VOWEL_GROUP_REGEX: Regex = Regex::new(r"(?i)[aeiouy]+").unwrap();
+}
+
+/// 对白时长预估模型
+pub trait DurationModel: Send + Sync {
+    /// 预估一段对白的时长（秒），`character` 为说话的角色
+    fn dialogue_duration(&self, text: &str, character: Option<&str>) -> f64;
+
+    /// 区分模型及其参数的标识，用作解析缓存键的一部分；默认为类型名，
+    /// 带参数的模型应当把参数也写进去，否则参数不同的两个模型会共用缓存
+    fn fingerprint(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+/// 按字符数估算：每个字符（不含空白和标点）固定耗时，长短标点各自另加停顿
+#[derive(Debug, Clone)]
+pub struct CharRateModel {
+    pub sec_per_char: f64,
+    pub sec_per_punc_short: f64,
+    pub sec_per_punc_long: f64,
+}
+
+impl Default for CharRateModel {
+    fn default() -> Self {
+        Self {
+            sec_per_char: 0.3,
+            sec_per_punc_short: 0.3,
+            sec_per_punc_long: 0.75,
+        }
+    }
+}
+
+impl DurationModel for CharRateModel {
+    fn fingerprint(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn dialogue_duration(&self, text: &str, _character: Option<&str>) -> f64 {
+        let chars = NON_SPOKEN_REGEX.replace_all(text, "").chars().count();
+        chars as f64 * self.sec_per_char
+            + punctuation_pause(text, self.sec_per_punc_short, self.sec_per_punc_long)
+    }
+}
+
+/// 按每分钟词数估算，中日文每个字算一个词，标点停顿同 [`CharRateModel`]
+#[derive(Debug, Clone)]
+pub struct WordsPerMinuteModel {
+    pub words_per_minute: f64,
+    pub sec_per_punc_short: f64,
+    pub sec_per_punc_long: f64,
+}
+
+impl DurationModel for WordsPerMinuteModel {
+    fn fingerprint(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn dialogue_duration(&self, text: &str, _character: Option<&str>) -> f64 {
+        if self.words_per_minute <= 0.0 {
+            return 0.0;
+        }
+        let words = text.unicode_words().count();
+        words as f64 * 60.0 / self.words_per_minute
+            + punctuation_pause(text, self.sec_per_punc_short, self.sec_per_punc_long)
+    }
+}
+
+/// 按音节数估算：汉字、假名、谚文每字一个音节，拉丁字母单词按元音组计数，数字每位一个音节
+#[derive(Debug, Clone)]
+pub struct SyllableModel {
+    pub syllables_per_sec: f64,
+    pub sec_per_punc_short: f64,
+    pub sec_per_punc_long: f64,
+}
+
+impl DurationModel for SyllableModel {
+    fn fingerprint(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn dialogue_duration(&self, text: &str, _character: Option<&str>) -> f64 {
+        if self.syllables_per_sec <= 0.0 {
+            return 0.0;
+        }
+        count_syllables(text) as f64 / self.syllables_per_sec
+            + punctuation_pause(text, self.sec_per_punc_short, self.sec_per_punc_long)
+    }
+}
+
+/// 按角色调整语速：倍率大于 1 表示说得快（时长相应缩短），未列出的角色按 1 计算
+pub struct SpeakingRateModel {
+    pub base: Arc<dyn DurationModel>,
+    pub rates: HashMap<String, f64>,
+}
+
+impl DurationModel for SpeakingRateModel {
+    fn dialogue_duration(&self, text: &str, character: Option<&str>) -> f64 {
+        let duration = self.base.dialogue_duration(text, character);
+        match character.and_then(|c| self.rates.get(c.trim())) {
+            Some(rate) if *rate > 0.0 => duration / rate,
+            _ => duration,
+        }
+    }
+
+    fn fingerprint(&self) -> String {
+        let mut rates: Vec<_> = self.rates.iter().collect();
+        rates.sort_by(|a, b| a.0.cmp(b.0));
+        format!("SpeakingRate({}, {:?})", self.base.fingerprint(), rates)
+    }
+}
+
+/// 按配置构建模型：`dialogue_duration_model` 为 "wpm"、"syllables" 或默认的 "chars"，
+/// 配置了 `speaking_rates` 时再套上按角色的语速调整
+pub fn model_from_conf(cfg: &Conf) -> Arc<dyn DurationModel> {
+    let short = cfg.dial_sec_per_punc_short;
+    let long = cfg.dial_sec_per_punc_long;
+    let base: Arc<dyn DurationModel> = match cfg.dialogue_duration_model.as_str() {
+        "wpm" => Arc::new(WordsPerMinuteModel {
+            words_per_minute: cfg.dialogue_words_per_minute,
+            sec_per_punc_short: short,
+            sec_per_punc_long: long,
+        }),
+        "syllables" => Arc::new(SyllableModel {
+            syllables_per_sec: cfg.dialogue_syllables_per_sec,
+            sec_per_punc_short: short,
+            sec_per_punc_long: long,
+        }),
+        _ => Arc::new(CharRateModel {
+            sec_per_char: cfg.dial_sec_per_char,
+            sec_per_punc_short: short,
+            sec_per_punc_long: long,
+        }),
+    };
+    if cfg.speaking_rates.is_empty() {
+        base
+    } else {
+        Arc::new(SpeakingRateModel {
+            base,
+            rates: cfg.speaking_rates.clone(),
+        })
+    }
+}
+
+fn punctuation_pause(text: &str, short: f64, long: f64) -> f64 {
+    PUNCTUATION_REGEX
+        .captures_iter(text)
+        .map(|cap| if cap.get(1).is_some() { long } else { short })
+        .sum()
+}
+
+fn count_syllables(text: &str) -> usize {
+    text.unicode_words()
+        .map(|word| {
+            let cjk = word.chars().filter(|c| is_syllabic_char(*c)).count();
+            let digits = word.chars().filter(|c| c.is_ascii_digit()).count();
+            let latin = VOWEL_GROUP_REGEX.find_iter(word).count();
+            // 只有辅音的拉丁单词（如缩写）至少算一个音节
+            let latin = if latin == 0 && word.chars().any(|c| c.is_ascii_alphabetic()) {
+                1
+            } else {
+                latin
+            };
+            cjk + digits + latin
+        })
+        .sum()
+}
+
+// 一字一音节的文字：汉字、平假名、片假名、谚文
+fn is_syllabic_char(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF)
+}
@@ -0,0 +1,214 @@
+//! Final Draft (.fdx) 导出
+//!
+//! 把解析结果转换成 Final Draft 的 XML 格式。章节、概要和注解在 Final Draft 中没有对应段落，导出时忽略。
+
+use crate::models::ScriptToken;
+use crate::parser::ParseOutput;
+use crate::utils::FountainConstants;
+
+/// 生成 FDX 文档内容
+pub fn generate_fdx(output: &ParseOutput) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\" ?>\n");
+    xml.push_str("<FinalDraft DocumentType=\"Script\" Template=\"No\" Version=\"5\">\n");
+
+    xml.push_str("  <Content>\n");
+    let mut new_page = false;
+    for token in &output.tokens {
+        let paragraph_type = match token.token_type.as_str() {
+            "scene_heading" => "Scene Heading",
+            "action" | "centered" | "lyric" => "Action",
+            "character" => "Character",
+            "dialogue" => "Dialogue",
+            "parenthetical" => "Parenthetical",
+            "transition" => "Transition",
+            "dual_dialogue_begin" => {
+                xml.push_str("    <Paragraph>\n      <DualDialogue>\n");
+                continue;
+            }
+            "dual_dialogue_end" => {
+                xml.push_str("      </DualDialogue>\n    </Paragraph>\n");
+                continue;
+            }
+            "page_break" => {
+                new_page = true;
+                continue;
+            }
+            _ => continue,
+        };
+
+        let mut attrs = format!(" Type=\"{}\"", paragraph_type);
+        if token.token_type == "centered" {
+            attrs.push_str(" Alignment=\"Center\"");
+        }
+        if token.token_type == "scene_heading" {
+            if let Some(number) = &token.number {
+                attrs.push_str(&format!(" Number=\"{}\"", escape_xml(number.trim_start_matches('↑'))));
+            }
+        }
+        if new_page {
+            attrs.push_str(" StartsNewPage=\"Yes\"");
+            new_page = false;
+        }
+        let text = if token.token_type == "lyric" {
+            format!("♪ {}", token.text)
+        } else {
+            token.text.clone()
+        };
+        xml.push_str(&format!("    <Paragraph{}>{}</Paragraph>\n", attrs, text_runs(&text)));
+    }
+    xml.push_str("  </Content>\n");
+
+    xml.push_str(&title_page_xml(output));
+    xml.push_str("</FinalDraft>\n");
+    xml
+}
+
+/// 标题页：按 title_page 的位置分组输出，位置决定段落对齐方式
+fn title_page_xml(output: &ParseOutput) -> String {
+    if output.title_page.is_empty() {
+        return String::new();
+    }
+    let mut xml = String::from("  <TitlePage>\n    <Content>\n");
+    for (position, alignment) in [
+        ("tl", "Left"),
+        ("tc", "Center"),
+        ("tr", "Right"),
+        ("cc", "Center"),
+        ("bl", "Left"),
+        ("br", "Right"),
+    ] {
+        let Some(tokens) = output.title_page.get(position) else {
+            continue;
+        };
+        let mut tokens: Vec<&ScriptToken> = tokens.iter().collect();
+        tokens.sort_by_key(|t| t.index);
+        for token in tokens {
+            xml.push_str(&format!(
+                "      <Paragraph Alignment=\"{}\">{}</Paragraph>\n",
+                alignment,
+                text_runs(&token.text)
+            ));
+        }
+        xml.push_str("      <Paragraph><Text></Text></Paragraph>\n");
+    }
+    xml.push_str("    </Content>\n  </TitlePage>\n");
+    xml
+}
+
+/// 一段样式相同的文字
+pub(crate) struct StyledRun {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+/// 按样式标记字符把文本拆成样式相同的片段，注解内容不输出
+pub(crate) fn styled_runs(text: &str) -> Vec<StyledRun> {
+    let chars = FountainConstants::style_chars();
+    let style_of = |c: char| -> Option<(bool, bool, bool)> {
+        let s = c.to_string();
+        let s = s.as_str();
+        // (粗体, 斜体, 下划线)
+        if s == chars["italic"] {
+            Some((false, true, false))
+        } else if s == chars["bold"] {
+            Some((true, false, false))
+        } else if s == chars["bold_italic"] {
+            Some((true, true, false))
+        } else if s == chars["underline"] {
+            Some((false, false, true))
+        } else if s == chars["italic_underline"] {
+            Some((false, true, true))
+        } else if s == chars["bold_underline"] {
+            Some((true, false, true))
+        } else if s == chars["bold_italic_underline"] {
+            Some((true, true, true))
+        } else {
+            None
+        }
+    };
+
+    let mut runs = Vec::new();
+    let (mut bold, mut italic, mut underline) = (false, false, false);
+    let mut current = String::new();
+    let mut in_note = 0;
+    // 彩色文字的颜色值不输出
+    let mut in_color = false;
+    let flush = |runs: &mut Vec<StyledRun>, current: &mut String, bold: bool, italic: bool, underline: bool| {
+        if current.is_empty() {
+            return;
+        }
+        runs.push(StyledRun {
+            text: std::mem::take(current),
+            bold,
+            italic,
+            underline,
+        });
+    };
+
+    for c in text.chars() {
+        let s = c.to_string();
+        if s == chars["note_begin"] || s == chars["note_begin_ext"] {
+            in_note += 1;
+        } else if s == chars["note_end"] {
+            in_note = (in_note - 1).max(0);
+        } else if in_note > 0 {
+            continue;
+        } else if s == chars["color_begin"] {
+            in_color = true;
+        } else if s == chars["color_text"] {
+            in_color = false;
+        } else if in_color {
+            continue;
+        } else if let Some((b, i, u)) = style_of(c) {
+            flush(&mut runs, &mut current, bold, italic, underline);
+            bold ^= b;
+            italic ^= i;
+            underline ^= u;
+        } else if !chars["all"].contains(c) {
+            current.push(c);
+        }
+    }
+    flush(&mut runs, &mut current, bold, italic, underline);
+    runs
+}
+
+/// 把带样式标记字符的文本拆成 `<Text Style="...">` 片段，注解内容不输出
+fn text_runs(text: &str) -> String {
+    let mut xml = String::new();
+    for run in styled_runs(text) {
+        let mut styles = Vec::new();
+        if run.bold {
+            styles.push("Bold");
+        }
+        if run.italic {
+            styles.push("Italic");
+        }
+        if run.underline {
+            styles.push("Underline");
+        }
+        if styles.is_empty() {
+            xml.push_str(&format!("<Text>{}</Text>", escape_xml(&run.text)));
+        } else {
+            xml.push_str(&format!(
+                "<Text Style=\"{}\">{}</Text>",
+                styles.join("+"),
+                escape_xml(&run.text)
+            ));
+        }
+    }
+
+    if xml.is_empty() {
+        xml.push_str("<Text></Text>");
+    }
+    xml
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,214 @@
+//! 角色关系图
+//!
+//! 统计角色之间同场的场景数和对白往来：同一场戏中前后相接的两段对白出自不同角色时，
+//! 记为这两个角色之间的一次对话往来。结果是一张带权无向图，可导出为 JSON 或 Graphviz DOT。
+
+use crate::cast::cast_list;
+use crate::parser::ParseOutput;
+use crate::utils::strip_style_chars;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 图中的角色
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterNode {
+    pub name: String,
+    /// 出场的场景数
+    pub scene_count: usize,
+    /// 对白段数
+    pub speeches: usize,
+    /// 对白字数（不含空白）
+    pub dialogue_chars: usize,
+}
+
+/// 两个角色之间的关系，`source` 是先出场的角色
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionEdge {
+    pub source: String,
+    pub target: String,
+    /// 两人同场的场景数
+    pub shared_scenes: usize,
+    /// 两人之间的对话往来次数
+    pub exchanges: usize,
+    /// 同场的场景中两人的对白字数之和
+    pub dialogue_chars: usize,
+}
+
+/// 角色关系图
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InteractionGraph {
+    /// 按首次出场顺序排列
+    pub nodes: Vec<CharacterNode>,
+    /// 按对话往来次数、同场场景数从多到少排列
+    pub edges: Vec<InteractionEdge>,
+}
+
+impl InteractionGraph {
+    /// 从解析结果计算关系图，没有同场也没有对话往来的角色之间不连边
+    pub fn from_output(output: &ParseOutput) -> Self {
+        let cast = cast_list(output);
+        let order: HashMap<&str, usize> = cast
+            .iter()
+            .enumerate()
+            .map(|(i, member)| (member.name.as_str(), i))
+            .collect();
+
+        // 每场戏中的对白：(角色, 字数)，第一个场景之前的对白单独算一组
+        let mut scenes: Vec<Vec<(String, usize)>> = vec![Vec::new()];
+        for token in &output.tokens {
+            match token.token_type.as_str() {
+                "scene_heading" => scenes.push(Vec::new()),
+                "character" => {
+                    if let Some(name) = &token.character {
+                        scenes.last_mut().unwrap().push((name.clone(), 0));
+                    }
+                }
+                "dialogue" => {
+                    if let Some(speech) = scenes.last_mut().unwrap().last_mut() {
+                        speech.1 += strip_style_chars(&token.text)
+                            .chars()
+                            .filter(|c| !c.is_whitespace())
+                            .count();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut nodes: Vec<CharacterNode> = cast
+            .iter()
+            .map(|member| CharacterNode {
+                name: member.name.clone(),
+                scene_count: member.scene_count,
+                speeches: 0,
+                dialogue_chars: 0,
+            })
+            .collect();
+        let mut edges: HashMap<(usize, usize), InteractionEdge> = HashMap::new();
+        let names: Vec<&str> = cast.iter().map(|member| member.name.as_str()).collect();
+
+        for speeches in &scenes {
+            let speeches: Vec<(usize, usize)> = speeches
+                .iter()
+                .filter_map(|(name, chars)| Some((*order.get(name.as_str())?, *chars)))
+                .collect();
+            for (speaker, chars) in &speeches {
+                nodes[*speaker].speeches += 1;
+                nodes[*speaker].dialogue_chars += chars;
+            }
+            for pair in speeches.windows(2) {
+                if pair[0].0 != pair[1].0 {
+                    edge(&mut edges, &names, pair[0].0, pair[1].0).exchanges += 1;
+                }
+            }
+        }
+
+        // 同场关系以 properties.characters 中记录的场景为准，不说话的出场也算
+        let mut scene_members: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (name, scene_indexes) in &output.properties.characters {
+            let Some(&index) = order.get(name.as_str()) else {
+                continue;
+            };
+            for scene in scene_indexes {
+                scene_members.entry(*scene).or_default().push(index);
+            }
+        }
+        let dialogue_by_scene: Vec<HashMap<usize, usize>> = scenes
+            .iter()
+            .skip(1)
+            .map(|speeches| {
+                let mut chars: HashMap<usize, usize> = HashMap::new();
+                for (name, count) in speeches {
+                    if let Some(&index) = order.get(name.as_str()) {
+                        *chars.entry(index).or_default() += count;
+                    }
+                }
+                chars
+            })
+            .collect();
+        for (scene, members) in &scene_members {
+            let chars = dialogue_by_scene.get(*scene);
+            let chars_of = |i: usize| chars.and_then(|c| c.get(&i)).copied().unwrap_or(0);
+            for (i, a) in members.iter().enumerate() {
+                for b in &members[i + 1..] {
+                    let edge = edge(&mut edges, &names, *a, *b);
+                    edge.shared_scenes += 1;
+                    edge.dialogue_chars += chars_of(*a) + chars_of(*b);
+                }
+            }
+        }
+
+        let mut edges: Vec<InteractionEdge> = edges.into_values().collect();
+        edges.sort_by(|a, b| {
+            b.exchanges
+                .cmp(&a.exchanges)
+                .then(b.shared_scenes.cmp(&a.shared_scenes))
+                .then_with(|| order[a.source.as_str()].cmp(&order[b.source.as_str()]))
+                .then_with(|| order[a.target.as_str()].cmp(&order[b.target.as_str()]))
+        });
+        InteractionGraph { nodes, edges }
+    }
+
+    /// 输出 JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// 输出 Graphviz DOT，节点大小随对白字数变化，边的粗细随对话往来次数变化
+    pub fn to_dot(&self) -> String {
+        let max_chars = self
+            .nodes
+            .iter()
+            .map(|n| n.dialogue_chars)
+            .max()
+            .unwrap_or(0);
+        let max_exchanges = self.edges.iter().map(|e| e.exchanges).max().unwrap_or(0);
+        let mut dot = String::from("graph characters {\n");
+        dot.push_str("  node [shape=ellipse];\n");
+        for node in &self.nodes {
+            let size = 1.0 + node.dialogue_chars as f64 / max_chars.max(1) as f64;
+            dot.push_str(&format!(
+                "  \"{}\" [label=\"{}\\n{}\", width={:.2}];\n",
+                escape_dot(&node.name),
+                escape_dot(&node.name),
+                node.speeches,
+                size
+            ));
+        }
+        for edge in &self.edges {
+            let width = 1.0 + 4.0 * edge.exchanges as f64 / max_exchanges.max(1) as f64;
+            dot.push_str(&format!(
+                "  \"{}\" -- \"{}\" [weight={}, penwidth={:.1}, label=\"{}/{}\"];\n",
+                escape_dot(&edge.source),
+                escape_dot(&edge.target),
+                edge.exchanges + edge.shared_scenes,
+                width,
+                edge.exchanges,
+                edge.shared_scenes
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// 两个角色（按出场顺序的下标）之间的边，没有时新建
+fn edge<'a>(
+    edges: &'a mut HashMap<(usize, usize), InteractionEdge>,
+    names: &[&str],
+    a: usize,
+    b: usize,
+) -> &'a mut InteractionEdge {
+    let key = (a.min(b), a.max(b));
+    edges.entry(key).or_insert_with(|| InteractionEdge {
+        source: names[key.0].to_string(),
+        target: names[key.1].to_string(),
+        shared_scenes: 0,
+        exchanges: 0,
+        dialogue_chars: 0,
+    })
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,3 +1,4 @@
+pub mod logging;
 pub mod models;
 pub mod utils;
 pub mod parser;
@@ -5,6 +6,27 @@ pub mod docx;
 pub mod pdf;
 pub mod api;
 pub mod statistics;
+pub mod stats;
+pub mod fdx;
+pub mod osf;
+pub mod odt;
+pub mod render;
+pub mod outline;
+pub mod sides;
+pub mod diff;
+pub mod duration;
+pub mod typewriter;
+pub mod typography;
+pub mod cast;
+pub mod interactions;
+pub mod adr;
+pub mod title_template;
+pub mod version_history;
+pub mod split;
+pub mod reorder;
+pub mod characters;
+pub mod progress;
+pub mod cache;
 
 pub use models::{
     ScriptToken,
@@ -15,13 +37,17 @@ pub use models::{
     Position,
     Range,
     Synopsis,
-    Note
+    Note,
+    Diagnostic,
+    DiagnosticSeverity
 };
 
 pub use parser::{
     FountainParser,
     ParseOutput,
-    TitleKeywordFormat
+    TitleKeywordFormat,
+    ParseError,
+    parse_strict
 };
 
 pub use docx::{
@@ -36,9 +62,26 @@ pub use api::{
     parse_fountain_text,
     export_to_docx,
     export_to_docx_base64,
+    extract_scenes,
+    ExtractedScene,
+    SceneSelector,
+    search,
+    SearchFilters,
+    SearchHit,
+    completion_context,
+    CompletionContext,
+    CompletionKind,
+    document_symbols,
+    folding_ranges,
+    DocumentSymbol,
+    FoldingRange,
+    SymbolKind,
+    PlaybackMap,
     test_connection
 };
 
+pub use logging::ExportTimings;
+
 /// 解析Fountain格式文本
 ///
 /// # Arguments
@@ -58,7 +101,7 @@ pub use api::{
 /// * `generate_html` - 是否生成 HTML 输出
 /// * `calc_statistics` - 是否计算统计数据（可选，默认 false）
 pub fn parse(script: &str, config: &Conf, generate_html: bool, calc_statistics: Option<bool>) -> ParseOutput {
-    let mut parser = FountainParser::new();
+    let parser = FountainParser::new();
     parser.parse(script, config, generate_html, calc_statistics)
 }
 
@@ -0,0 +1,84 @@
+//! 调试日志与耗时统计
+//!
+//! 开启 `tracing` feature 后，解析和 DOCX 生成的调试信息通过 `tracing` 输出，
+//! 解析、排版、保存各阶段带有 span，由调用方的 subscriber 决定是否显示；
+//! 未开启时这些日志不会编译进来，也不会打印到标准输出。
+//! 各阶段的耗时不依赖 feature，通过 `ExportTimings` 返回给调用方。
+
+use std::time::{Duration, Instant};
+
+/// 调试日志，开启 `tracing` feature 时等同于 `tracing::debug!`
+#[cfg(feature = "tracing")]
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        tracing::debug!($($arg)*)
+    };
+}
+
+/// 调试日志，未开启 `tracing` feature 时不输出
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug_log {
+    ($($arg:tt)*) => {{
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    }};
+}
+
+/// 开始一个阶段，开启 `tracing` feature 时同时创建同名的 span，`finish` 时关闭。
+/// span 不进入当前线程（`EnteredSpan` 不能跨 `.await`），subscriber 从创建到关闭得到阶段的耗时
+#[cfg(feature = "tracing")]
+macro_rules! phase {
+    ($name:literal) => {
+        $crate::logging::Phase {
+            start: std::time::Instant::now(),
+            _span: tracing::info_span!($name),
+        }
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! phase {
+    ($name:literal) => {
+        $crate::logging::Phase {
+            start: std::time::Instant::now(),
+        }
+    };
+}
+
+pub(crate) use debug_log;
+pub(crate) use phase;
+
+/// 进行中的阶段，由 `phase!` 创建
+pub(crate) struct Phase {
+    pub(crate) start: Instant,
+    #[cfg(feature = "tracing")]
+    pub(crate) _span: tracing::Span,
+}
+
+impl Phase {
+    /// 结束阶段，返回耗时
+    pub(crate) fn finish(self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// 一次导出各阶段的耗时
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportTimings {
+    /// 解析 Fountain 文本
+    pub parse: Duration,
+    /// 行处理（按打印配置把 token 拆分为行）
+    pub lines: Duration,
+    /// 排版（生成文档内容）
+    pub layout: Duration,
+    /// 序列化并写入文件或编码为 Base64
+    pub save: Duration,
+}
+
+impl ExportTimings {
+    /// 总耗时
+    pub fn total(&self) -> Duration {
+        self.parse + self.lines + self.layout + self.save
+    }
+}
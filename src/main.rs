@@ -1,38 +1,877 @@
-use betterfountain_rust::models::Conf;
-use betterfountain_rust::parser::fountain_parser::FountainParser;
+use betterfountain_rust::adr::{adr_cues, adr_cues_csv};
+use betterfountain_rust::diff::compare;
+use betterfountain_rust::docx::{
+    generate_docx_av, generate_docx_diff, generate_docx_document, generate_docx_revisions,
+    generate_docx_sides, generate_odt_document, generate_pdf_document,
+};
+use betterfountain_rust::fdx::generate_fdx;
+use betterfountain_rust::models::{Conf, DiagnosticSeverity};
+use betterfountain_rust::osf::{osf_to_fountain, read_fadein, write_fadein};
+use betterfountain_rust::outline::{export_outline, OutlineFormat};
+use betterfountain_rust::parser::{
+    check_strict, generate_html, generate_html_document, lint, FountainParser, ParseOutput,
+};
+use betterfountain_rust::split::{split_script, SplitBy};
+use betterfountain_rust::stats::{
+    dialogue_balance, location_summaries, location_summaries_csv, scene_durations,
+    scene_durations_csv, ScriptStats,
+};
+use betterfountain_rust::typewriter::generate_typewriter;
+use clap::{Parser, Subcommand, ValueEnum};
+use notify::{RecursiveMode, Watcher};
 use std::fs;
-use std::env;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// Fountain 剧本解析与导出工具
+#[derive(Parser)]
+#[command(name = "bf", version, about)]
+struct Cli {
+    /// 配置文件（JSON 或 TOML，字段同 Conf，只需写要覆盖的字段），
+    /// 在剧本旁自动发现的 .betterfountainrc / fountain.toml 之后合并
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
 
-    if args.len() < 2 {
-        println!("Usage: {} <fountain_file>", args[0]);
-        return;
+    /// 覆盖单个配置项，如 --set print_notes=false --set print_profile.font_size=12
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    overrides: Vec<String>,
+
+    /// 不自动查找项目配置文件
+    #[arg(long, global = true)]
+    no_project_config: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 解析剧本，输出 JSON 格式的解析结果
+    Parse {
+        /// 输入文件，"-" 表示从标准输入读取
+        #[arg(default_value = "-")]
+        input: String,
+        /// 输出文件，默认输出到标准输出
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// 格式化 JSON
+        #[arg(long)]
+        pretty: bool,
+        /// 严格模式：有未闭合的注解/注释、无法识别的标题页字段等结构问题时报错退出
+        #[arg(long)]
+        strict: bool,
+    },
+    /// 导出剧本，可一次导出多个文件（文件名部分支持 * 和 ? 通配符，目录部分不支持），并行处理后输出汇总表
+    Export {
+        #[arg(default_value = "-")]
+        inputs: Vec<String>,
+        #[arg(long, value_enum, default_value_t = Format::Docx)]
+        format: Format,
+        /// 输出文件，默认与输入文件同名、替换扩展名；只能用于单个输入
+        #[arg(long, conflicts_with = "out_dir")]
+        out: Option<PathBuf>,
+        /// 输出目录，默认与各输入文件相同
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+        /// 拆分导出（只支持 docx、html）："section" 每个顶层章节一个文件，数字 N 每 N 个场景一个文件，
+        /// 文件名加上序号，如 script.01.docx
+        #[arg(long, value_name = "section|N", value_parser = parse_split, conflicts_with = "out")]
+        split: Option<SplitBy>,
+    },
+    /// 输出剧本统计
+    Stats {
+        #[arg(default_value = "-")]
+        input: String,
+        /// 输出完整统计数据的 JSON
+        #[arg(long)]
+        json: bool,
+        /// 输出逐场时长报表（CSV），镜头交切的场景合并为一行
+        #[arg(long, conflicts_with = "json")]
+        scenes: bool,
+        /// 输出地点附录（CSV）：各地点的内/外景、时间、场景编号和总时长
+        #[arg(long, conflicts_with_all = ["json", "scenes"])]
+        locations: bool,
+        /// 按标题页元数据中的角色标签（如 gender）输出对白平衡报告（JSON）
+        #[arg(long, value_name = "TAG", conflicts_with_all = ["json", "scenes", "locations"])]
+        balance: Option<String>,
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// 导出 ADR/配音提示表（CSV）：每段对白的编号、角色、场景、入点/出点时间码和文本
+    Adr {
+        #[arg(default_value = "-")]
+        input: String,
+        /// 时间码的每秒帧数
+        #[arg(long, default_value_t = 24)]
+        fps: u32,
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// 导出章节/场景大纲
+    Outline {
+        #[arg(default_value = "-")]
+        input: String,
+        #[arg(long, value_enum, default_value_t = OutlineKind::Json)]
+        format: OutlineKind,
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// 导出试镜台词本（DOCX）：只保留指定角色出场的场景，突出其对白
+    Sides {
+        #[arg(default_value = "-")]
+        input: String,
+        /// 角色名，可重复指定多个
+        #[arg(long = "character", required = true)]
+        characters: Vec<String>,
+        /// 输出文件，默认为输入文件同名的 .sides.docx
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// 对比两稿，输出有改动的场景和行（JSON），或导出带修订线/修订标记的新稿（DOCX）
+    Diff {
+        /// 旧稿
+        old: String,
+        /// 新稿，"-" 表示从标准输入读取
+        input: String,
+        #[arg(long, value_enum, default_value_t = DiffKind::Json)]
+        format: DiffKind,
+        /// 输出文件，JSON 默认输出到标准输出，DOCX 默认为新稿同名的 .diff.docx
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// 修订模式（--format revisions）中的修订者
+        #[arg(long, default_value = "BetterFountain")]
+        author: String,
+    },
+    /// 监视剧本文件，修改后自动重新导出
+    Watch {
+        input: String,
+        /// 导出格式，可重复指定多个
+        #[arg(long, value_enum, default_values_t = [Format::Html])]
+        format: Vec<Format>,
+        /// 输出目录，默认与输入文件相同
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+        /// 文件停止变化多久后才重新导出（毫秒）
+        #[arg(long, default_value_t = 300)]
+        debounce_ms: u64,
+    },
+    /// 检查剧本中的问题
+    Lint {
+        #[arg(default_value = "-")]
+        input: String,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Docx,
+    Html,
+    Fdx,
+    Odt,
+    /// 打字机格式的纯文本（10 cpi 等宽排版）
+    Txt,
+    /// Fade In 文档（Open Screenplay Format）
+    Fadein,
+    /// A/V 两栏格式的 DOCX（左栏画面、右栏声音）
+    Av,
+    /// PDF，排版与 DOCX 相同
+    Pdf,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutlineKind {
+    Json,
+    Opml,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DiffKind {
+    Json,
+    /// 改动的段落左侧加修订线
+    Docx,
+    /// Word 修订模式，新增和删除的内容可逐条接受或拒绝
+    Revisions,
+}
+
+impl Command {
+    fn input(&self) -> &str {
+        match self {
+            Command::Export { inputs, .. } => inputs.first().map_or("-", |s| s.as_str()),
+            Command::Parse { input, .. }
+            | Command::Stats { input, .. }
+            | Command::Adr { input, .. }
+            | Command::Outline { input, .. }
+            | Command::Sides { input, .. }
+            | Command::Diff { input, .. }
+            | Command::Watch { input, .. }
+            | Command::Lint { input } => input,
+        }
     }
+}
 
-    let file_path = &args[1];
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Docx => "docx",
+            Format::Html => "html",
+            Format::Fdx => "fdx",
+            Format::Odt => "odt",
+            Format::Txt => "txt",
+            Format::Fadein => "fadein",
+            Format::Av => "av.docx",
+            Format::Pdf => "pdf",
+        }
+    }
+}
 
-    match fs::read_to_string(file_path) {
-        Ok(content) => {
-            let config = Conf::default();
-            let mut parser = FountainParser::new();
-            let result = parser.parse(&content, &config, true, Some(true));
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
 
-            println!("解析完成！");
-            println!("解析时间: {}ms", result.parse_time);
-            println!("Token数量: {}", result.tokens.len());
-            println!("场景数量: {}", result.properties.scenes.len());
-            println!("角色数量: {}", result.properties.characters.len());
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(2);
+        }
+    }
+}
 
-            if let Some(html) = result.script_html {
-                let html_path = format!("{}.html", file_path);
-                fs::write(&html_path, html).unwrap();
-                println!("HTML输出已保存到: {}", html_path);
+async fn run(cli: Cli) -> Result<i32, String> {
+    let config = load_config(&cli)?;
+
+    match cli.command {
+        Command::Parse {
+            input,
+            out,
+            pretty,
+            strict,
+        } => {
+            let mut result = parse_input(&input, &config, false, false)?;
+            if strict {
+                result = match check_strict(result) {
+                    Ok(result) => result,
+                    Err(errors) => {
+                        for e in &errors {
+                            eprintln!("{}", e);
+                        }
+                        return Ok(1);
+                    }
+                };
             }
-        },
-        Err(e) => {
-            println!("读取文件失败: {}", e);
+            let json = if pretty {
+                serde_json::to_string_pretty(&result)
+            } else {
+                serde_json::to_string(&result)
+            }
+            .map_err(|e| format!("序列化失败: {}", e))?;
+            write_output(out.as_deref(), &json)?;
+        }
+        Command::Export {
+            inputs,
+            format,
+            out,
+            out_dir,
+            split,
+        } => {
+            let inputs = expand_globs(&inputs)?;
+            if let Some(split) = split {
+                if inputs.len() > 1 {
+                    return Err("拆分导出只能用于单个输入文件".to_string());
+                }
+                export_split(&inputs[0], format, split, out_dir.as_deref(), &config).await?;
+                return Ok(0);
+            }
+            if inputs.len() > 1 || out_dir.is_some() {
+                if out.is_some() {
+                    return Err("导出多个文件时请用 --out-dir 指定输出目录".to_string());
+                }
+                return batch_export(&inputs, format, out_dir.as_deref(), &config);
+            }
+            export(&inputs[0], format, out, &config).await?;
+        }
+        Command::Adr { input, fps, out } => {
+            let result = parse_input(&input, &config, false, false)?;
+            write_output(out.as_deref(), &adr_cues_csv(&adr_cues(&result), fps))?;
+        }
+        Command::Outline { input, format, out } => {
+            let result = parse_input(&input, &config, false, false)?;
+            let format = match format {
+                OutlineKind::Json => OutlineFormat::Json,
+                OutlineKind::Opml => OutlineFormat::Opml,
+            };
+            write_output(out.as_deref(), &export_outline(&result, format))?;
+        }
+        Command::Sides {
+            input,
+            characters,
+            out,
+        } => {
+            let out = match out {
+                Some(p) => p,
+                None if input != "-" => Path::new(&input).with_extension("sides.docx"),
+                None => return Err("从标准输入导出台词本时必须指定 --out".to_string()),
+            };
+            let result = parse_input(&input, &config, false, false)?;
+            generate_docx_sides(&out.to_string_lossy(), &config, &result, &characters)
+                .await
+                .map_err(|e| format!("导出失败: {}", e))?;
+            eprintln!("已导出到: {}", out.display());
+        }
+        Command::Diff {
+            old,
+            input,
+            format,
+            out,
+            author,
+        } => {
+            let old_result = parse_input(&old, &config, false, false)?;
+            let result = parse_input(&input, &config, false, false)?;
+            if format == DiffKind::Json {
+                write_output(out.as_deref(), &compare(&old_result, &result).to_json())?;
+                return Ok(0);
+            }
+            let out = match out {
+                Some(p) => p,
+                None if input != "-" => Path::new(&input).with_extension("diff.docx"),
+                None => return Err("从标准输入导出 DOCX 时必须指定 --out".to_string()),
+            };
+            let out_path = out.to_string_lossy();
+            let exported = if format == DiffKind::Revisions {
+                generate_docx_revisions(&out_path, &config, &old_result, &result, &author).await
+            } else {
+                generate_docx_diff(&out_path, &config, &old_result, &result).await
+            };
+            exported.map_err(|e| format!("导出失败: {}", e))?;
+            eprintln!("已导出到: {}", out.display());
+        }
+        Command::Watch {
+            input,
+            format,
+            out_dir,
+            debounce_ms,
+        } => {
+            watch(&input, &format, out_dir.as_deref(), debounce_ms, &config).await?;
+        }
+        Command::Stats {
+            input,
+            json,
+            scenes,
+            locations,
+            balance,
+            out,
+        } => {
+            let result = parse_input(&input, &config, false, true)?;
+            let text = if let Some(tag) = balance {
+                dialogue_balance(&result, &tag).to_json()
+            } else if scenes {
+                scene_durations_csv(&scene_durations(&result))
+            } else if locations {
+                location_summaries_csv(&location_summaries(&result))
+            } else if json {
+                serde_json::to_string_pretty(&result.statistics)
+                    .map_err(|e| format!("序列化失败: {}", e))?
+            } else {
+                let mut text = String::new();
+                text.push_str(&format!("解析时间: {}ms\n", result.parse_time));
+                text.push_str(&format!("Token数量: {}\n", result.tokens.len()));
+                text.push_str(&format!("场景数量: {}\n", result.properties.scenes.len()));
+                text.push_str(&format!("角色数量: {}\n", result.properties.characters.len()));
+                let stats = ScriptStats::from_output(&result);
+                text.push_str(&format!(
+                    "  内景 {} / 外景 {} / 内外景 {} / 未标记 {}\n",
+                    stats.scenes_by_type.int,
+                    stats.scenes_by_type.ext,
+                    stats.scenes_by_type.int_ext,
+                    stats.scenes_by_type.unknown
+                ));
+                text.push_str(&format!("平均场景时长: {:.0}秒\n", stats.average_scene_sec));
+                text.push_str(&format!("地点数量: {}\n", stats.locations.len()));
+                text.push_str(&format!("动作时长: {:.0}秒\n", stats.action_sec));
+                text.push_str(&format!(
+                    "对白时长: {:.0}秒（占 {:.0}%）\n",
+                    stats.dialogue_sec,
+                    stats.dialogue_ratio * 100.0
+                ));
+                text.push_str(&format!("预估总时长: {:.0}秒\n", stats.runtime_sec));
+                text
+            };
+            write_output(out.as_deref(), &text)?;
+        }
+        Command::Lint { input } => {
+            let result = parse_input(&input, &config, false, false)?;
+            let diagnostics = lint(&result, &config);
+            let name = if input == "-" { "<stdin>" } else { input.as_str() };
+            for d in &diagnostics {
+                let severity = match d.severity {
+                    DiagnosticSeverity::Error => "error",
+                    DiagnosticSeverity::Warning => "warning",
+                    DiagnosticSeverity::Information => "info",
+                    DiagnosticSeverity::Hint => "hint",
+                };
+                println!(
+                    "{}:{}:{}: {}[{}] {}",
+                    name,
+                    d.range.start.line + 1,
+                    d.range.start.character + 1,
+                    severity,
+                    d.code,
+                    d.message
+                );
+            }
+            if diagnostics
+                .iter()
+                .any(|d| d.severity == DiagnosticSeverity::Error)
+            {
+                return Ok(1);
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// 导出一种格式，未指定输出文件时使用输入文件同名、替换扩展名的路径
+///
+/// DOCX、ODT 和 PDF 返回排版后的页数，其它格式不分页，返回 None。
+async fn export(
+    input: &str,
+    format: Format,
+    out: Option<PathBuf>,
+    config: &Conf,
+) -> Result<Option<u32>, String> {
+    let out = match out {
+        Some(p) => Some(p),
+        None if input != "-" => Some(Path::new(input).with_extension(format.extension())),
+        None => None,
+    };
+    if let Some(out) = &out {
+        check_output(input, out)?;
+    }
+    match format {
+        Format::Docx => {
+            let out = out.ok_or("从标准输入导出 docx 时必须指定 --out")?;
+            let result = parse_input(input, config, false, false)?;
+            let stats = generate_docx_document(&out.to_string_lossy(), config, &result)
+                .await
+                .map_err(|e| format!("导出失败: {}", e))?;
+            eprintln!("已导出到: {}", out.display());
+            return Ok(stats.map(|stats| stats.page_count));
+        }
+        Format::Odt => {
+            let out = out.ok_or("从标准输入导出 odt 时必须指定 --out")?;
+            let result = parse_input(input, config, false, false)?;
+            let stats = generate_odt_document(&out.to_string_lossy(), config, &result)
+                .await
+                .map_err(|e| format!("导出失败: {}", e))?;
+            eprintln!("已导出到: {}", out.display());
+            return Ok(stats.map(|stats| stats.page_count));
+        }
+        Format::Pdf => {
+            let out = out.ok_or("从标准输入导出 pdf 时必须指定 --out")?;
+            let result = parse_input(input, config, false, false)?;
+            let stats = generate_pdf_document(&out.to_string_lossy(), config, &result)
+                .await
+                .map_err(|e| format!("导出失败: {}", e))?;
+            eprintln!("已导出到: {}", out.display());
+            return Ok(stats.map(|stats| stats.page_count));
+        }
+        Format::Av => {
+            let out = out.ok_or("从标准输入导出 av 时必须指定 --out")?;
+            let result = parse_input(input, config, false, false)?;
+            generate_docx_av(&out.to_string_lossy(), config, &result)
+                .await
+                .map_err(|e| format!("导出失败: {}", e))?;
+            eprintln!("已导出到: {}", out.display());
+        }
+        Format::Html => {
+            let result = parse_input(input, config, true, false)?;
+            let title = if input == "-" {
+                "screenplay".to_string()
+            } else {
+                Path::new(input)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            };
+            let html = generate_html_document(
+                &title,
+                result.title_html.as_deref().unwrap_or(""),
+                result.script_html.as_deref().unwrap_or(""),
+            );
+            write_output(out.as_deref(), &html)?;
+        }
+        Format::Fdx => {
+            let result = parse_input(input, config, false, false)?;
+            write_output(out.as_deref(), &generate_fdx(&result))?;
+        }
+        Format::Txt => {
+            let result = parse_input(input, config, false, false)?;
+            write_output(
+                out.as_deref(),
+                &generate_typewriter(&result, &config.print_profile),
+            )?;
+        }
+        Format::Fadein => {
+            let out = out.ok_or("从标准输入导出 fadein 时必须指定 --out")?;
+            let result = parse_input(input, config, false, false)?;
+            write_fadein(&result, &out.to_string_lossy())
+                .map_err(|e| format!("导出失败: {}", e))?;
+            eprintln!("已导出到: {}", out.display());
+        }
+    }
+    Ok(None)
+}
+
+/// 输出文件与输入文件是同一个文件时报错，避免导出覆盖剧本
+///
+/// 如 `script.txt` 导出 txt、`script.fadein` 导出 fadein 时，默认输出路径就是输入文件本身
+fn check_output(input: &str, out: &Path) -> Result<(), String> {
+    if input == "-" {
+        return Ok(());
+    }
+    let same = match (fs::canonicalize(input), fs::canonicalize(out)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => Path::new(input) == out,
+    };
+    if same {
+        return Err(format!(
+            "输出文件与输入文件相同，导出会覆盖剧本: {}，请用 --out 指定其他文件",
+            out.display()
+        ));
+    }
+    Ok(())
+}
+
+/// 按章节或场景拆分导出，各部分的文件名为输入文件名加上序号，如 script.01.docx
+///
+/// 场景编号沿用全剧的编号，DOCX 的页码接续上一部分
+async fn export_split(
+    input: &str,
+    format: Format,
+    split: SplitBy,
+    out_dir: Option<&Path>,
+    config: &Conf,
+) -> Result<(), String> {
+    if !matches!(format, Format::Docx | Format::Html) {
+        return Err("拆分导出只支持 docx 和 html 格式".to_string());
+    }
+    if input == "-" {
+        return Err("从标准输入读取时不能拆分导出".to_string());
+    }
+    let path = Path::new(input);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let dir = out_dir.unwrap_or(path.parent().unwrap_or(Path::new("")));
+    fs::create_dir_all(dir).map_err(|e| format!("无法创建输出目录 {}: {}", dir.display(), e))?;
+
+    let result = parse_input(input, config, format == Format::Html, false)?;
+    for (i, part) in split_script(&result, split).iter().enumerate() {
+        let out = dir.join(format!("{}.{:02}.{}", stem, i + 1, format.extension()));
+        if format == Format::Docx {
+            let mut part_config = config.clone();
+            part_config.page_number_start = config.page_number_start + part.first_page as u32 - 1;
+            generate_docx_document(&out.to_string_lossy(), &part_config, &part.output)
+                .await
+                .map_err(|e| format!("导出失败: {}", e))?;
+        } else {
+            let tokens: Vec<_> = part
+                .output
+                .tokens
+                .iter()
+                .filter(|token| config.print_synopsis_html || token.token_type != "synopsis")
+                .cloned()
+                .collect();
+            let title = if part.title.is_empty() {
+                stem.clone()
+            } else {
+                format!("{} - {}", stem, part.title)
+            };
+            let html = generate_html_document(
+                &title,
+                result.title_html.as_deref().unwrap_or(""),
+                &generate_html(&tokens),
+            );
+            write_output(Some(&out), &html)?;
+        }
+        eprintln!("已导出到: {}", out.display());
+    }
+    Ok(())
+}
+
+fn parse_split(value: &str) -> Result<SplitBy, String> {
+    SplitBy::from_name(value)
+        .ok_or_else(|| format!("无效的拆分方式 {}，应为 section 或正整数", value))
+}
+
+/// 展开输入中的 `*` 和 `?` 通配符（只支持文件名部分），按文件名排序
+fn expand_globs(inputs: &[String]) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        let parent = path.parent().filter(|d| !d.as_os_str().is_empty());
+        if parent.is_some_and(|d| d.to_string_lossy().contains(['*', '?'])) {
+            return Err(format!("通配符只能用于文件名部分: {}", input));
+        }
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+        let Some(name) = name.filter(|n| n.contains(['*', '?'])) else {
+            files.push(input.clone());
+            continue;
+        };
+        let pattern = format!(
+            "^{}$",
+            regex::escape(&name)
+                .replace(r"\*", ".*")
+                .replace(r"\?", ".")
+        );
+        let pattern = regex::Regex::new(&pattern).map_err(|e| e.to_string())?;
+        let dir = parent.unwrap_or(Path::new("."));
+        let entries =
+            fs::read_dir(dir).map_err(|e| format!("无法读取目录 {}: {}", dir.display(), e))?;
+        let mut matched: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .filter(|e| pattern.is_match(&e.file_name().to_string_lossy()))
+            .map(|e| match parent {
+                Some(d) => d.join(e.file_name()).to_string_lossy().to_string(),
+                None => e.file_name().to_string_lossy().to_string(),
+            })
+            .collect();
+        if matched.is_empty() {
+            return Err(format!("没有匹配 {} 的文件", input));
+        }
+        matched.sort();
+        files.extend(matched);
+    }
+    Ok(files)
+}
+
+/// 并行导出多个文件，最后输出每个文件的结果和页数；有失败时返回退出码 1
+fn batch_export(
+    inputs: &[String],
+    format: Format,
+    out_dir: Option<&Path>,
+    config: &Conf,
+) -> Result<i32, String> {
+    if let Some(d) = out_dir {
+        fs::create_dir_all(d).map_err(|e| format!("无法创建输出目录 {}: {}", d.display(), e))?;
+    }
+    let workers = std::thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .min(inputs.len());
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let results = std::sync::Mutex::new(vec![None; inputs.len()]);
+    let handle = tokio::runtime::Handle::current();
+
+    // 导出过程不都是 Send 的，所以每个工作线程用 block_on 驱动自己的任务
+    tokio::task::block_in_place(|| {
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let Some(input) = inputs.get(i) else {
+                        break;
+                    };
+                    let out = out_dir.map(|d| {
+                        d.join(Path::new(input).file_name().unwrap_or_default())
+                            .with_extension(format.extension())
+                    });
+                    let result = handle.block_on(export(input, format, out, config));
+                    results.lock().unwrap()[i] = Some(result);
+                });
+            }
+        });
+    });
+
+    let results = results.into_inner().unwrap();
+    let width = inputs
+        .iter()
+        .map(|i| i.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let mut failed = 0;
+    // 表头的中文每字占两列
+    eprintln!("{:<width$}  结果  页数  说明", "文件", width = width - 2);
+    for (input, result) in inputs.iter().zip(results) {
+        match result {
+            Some(Ok(pages)) => eprintln!(
+                "{:<width$}  成功  {:>4}",
+                input,
+                pages.map_or("-".to_string(), |p| p.to_string()),
+                width = width
+            ),
+            Some(Err(e)) => {
+                failed += 1;
+                eprintln!("{:<width$}  失败  {:>4}  {}", input, "-", e, width = width);
+            }
+            None => failed += 1,
+        }
+    }
+    eprintln!(
+        "共 {} 个文件，成功 {}，失败 {}",
+        inputs.len(),
+        inputs.len() - failed,
+        failed
+    );
+    Ok(if failed > 0 { 1 } else { 0 })
+}
+
+/// 监视剧本文件，变化停止 `debounce_ms` 毫秒后重新导出所有格式
+async fn watch(
+    input: &str,
+    formats: &[Format],
+    out_dir: Option<&Path>,
+    debounce_ms: u64,
+    config: &Conf,
+) -> Result<(), String> {
+    let path = fs::canonicalize(input).map_err(|e| format!("找不到文件 {}: {}", input, e))?;
+    let file_name = path.file_name().map(|n| n.to_os_string());
+    // 编辑器保存时常常是"写临时文件再改名"，所以监视所在目录而不是文件本身
+    let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<()>(16);
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            let touched = event.kind.is_modify() || event.kind.is_create();
+            if touched
+                && event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == file_name.as_deref())
+            {
+                let _ = tx.try_send(());
+            }
+        }
+    })
+    .map_err(|e| format!("无法监视文件: {}", e))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("无法监视目录 {}: {}", dir.display(), e))?;
+
+    if let Some(d) = out_dir {
+        fs::create_dir_all(d).map_err(|e| format!("无法创建输出目录 {}: {}", d.display(), e))?;
+    }
+
+    let input = path.to_string_lossy().to_string();
+    let outputs: Vec<(Format, Option<PathBuf>)> = formats
+        .iter()
+        .map(|f| {
+            let out = out_dir.map(|d| {
+                d.join(path.file_name().unwrap_or_default())
+                    .with_extension(f.extension())
+            });
+            (*f, out)
+        })
+        .collect();
+
+    let regenerate = || async {
+        for (format, out) in &outputs {
+            if let Err(e) = export(&input, *format, out.clone(), config).await {
+                eprintln!("错误: {}", e);
+            }
+        }
+    };
+
+    regenerate().await;
+    eprintln!("正在监视 {}，按 Ctrl+C 退出", path.display());
+    while rx.recv().await.is_some() {
+        // 防抖：等到一段时间内没有新的变化再导出
+        let quiet = Duration::from_millis(debounce_ms);
+        while let Ok(Some(())) = tokio::time::timeout(quiet, rx.recv()).await {}
+        eprintln!("检测到修改，重新导出");
+        regenerate().await;
+    }
+    Ok(())
+}
+
+/// 合并配置：默认配置 ← 项目配置文件 ← --config ← --set
+fn load_config(cli: &Cli) -> Result<Conf, String> {
+    let input = cli.command.input();
+    let mut config = Conf::default();
+    if !cli.no_project_config && input != "-" {
+        if let Some(path) = Conf::discover(Path::new(input)) {
+            config = config.merge_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+    if let Some(path) = &cli.config {
+        config = config.merge_file(path).map_err(|e| e.to_string())?;
+    }
+    if config.source_date_epoch.is_none() {
+        config.source_date_epoch = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|value| value.trim().parse().ok());
+    }
+    if !cli.overrides.is_empty() {
+        config = config
+            .merge_overrides(&cli.overrides)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(config)
+}
+
+fn read_input(input: &str) -> Result<String, String> {
+    if input == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| format!("读取标准输入失败: {}", e))?;
+        Ok(content)
+    } else {
+        fs::read_to_string(input).map_err(|e| format!("读取文件 {} 失败: {}", input, e))
+    }
+}
+
+fn parse_input(
+    input: &str,
+    config: &Conf,
+    generate_html: bool,
+    calc_statistics: bool,
+) -> Result<ParseOutput, String> {
+    let parser = FountainParser::new();
+    // Fade In / Open Screenplay Format 文档先转换为 Fountain 文本
+    let extension = Path::new(input)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    if let Some(extension @ ("fadein" | "osf")) = extension.as_deref() {
+        let xml = if extension == "fadein" {
+            read_fadein(input).map_err(|e| format!("读取文件 {} 失败: {}", input, e))?
+        } else {
+            read_input(input)?
+        };
+        let content = osf_to_fountain(&xml).map_err(|e| e.to_string())?;
+        return Ok(parser.parse(&content, config, generate_html, Some(calc_statistics)));
+    }
+    if input != "-" {
+        // 从文件读取时展开 {include: ...} 引用
+        return parser
+            .parse_file(
+                Path::new(input),
+                config,
+                generate_html,
+                Some(calc_statistics),
+            )
+            .map_err(|e| e.to_string());
+    }
+    let content = read_input(input)?;
+    Ok(parser.parse(&content, config, generate_html, Some(calc_statistics)))
+}
+
+fn write_output(out: Option<&Path>, content: &str) -> Result<(), String> {
+    match out {
+        Some(path) => {
+            fs::write(path, content).map_err(|e| format!("写入文件 {} 失败: {}", path.display(), e))
         }
+        None => io::stdout()
+            .write_all(content.as_bytes())
+            .map_err(|e| format!("写入标准输出失败: {}", e)),
     }
 }
@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
-use crate::docx::docx_maker::PrintProfile;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::docx::docx_maker::{PrintProfile, SynopsisConfig};
+use crate::parser::spec::Extension;
 
 /// 页面边距
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,16 +26,29 @@ impl Default for Margins {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Conf {
     pub print_notes: bool,
+    /// 只打印这些分类的带标签注解（如 `[[todo: ...]]` 的 todo），为空时不限制
+    pub print_note_tags: Vec<String>,
+    /// 不打印这些分类的带标签注解，优先于 print_note_tags
+    pub hide_note_tags: Vec<String>,
     pub merge_empty_lines: bool,
     pub each_scene_on_new_page: bool,
+    /// 该层级及以上的章节另起一页，如 1 表示每个 `#`（幕）从新的一页开始，为空时不分页
+    pub page_break_before_sections: Option<usize>,
     pub use_dual_dialogue: bool,
+    /// 双对话表格中左右两栏逐段对齐，每对段落占一行，避免两栏长短悬殊时留下大片空白
+    pub balance_dual_dialogue: bool,
     pub dialogue_foldable: bool,
     pub print_dialogue_numbers: bool,
+    /// 对白编号方式："global"（全剧连续编号，默认）或 "character"（每个角色各自编号）
+    pub dialogue_numbering: String,
     pub emitalic_dialog: bool,
     pub embolden_character_names: bool,
     pub text_contd: String,
+    /// 镜头交切中的角色名后注明所在场景的地点，如 "阿强 (客厅)"，便于分辨交切的哪一边在说话
+    pub intercut_character_locations: bool,
     /// 对白中每字符耗时预估(不含标点)
     pub dial_sec_per_char: f64,
     /// 对白中每个短标点耗时预估(逗号顿号等)
@@ -40,28 +57,75 @@ pub struct Conf {
     pub dial_sec_per_punc_long: f64,
     /// action文本中每字符转化成影片时长预估(不含标点)
     pub action_sec_per_char: f64,
+    /// 对白时长预估模型："chars"（按字符数，默认）、"wpm"（按每分钟词数）、"syllables"（按音节数）
+    pub dialogue_duration_model: String,
+    /// "wpm" 模型的每分钟词数，中日文每个字算一个词
+    pub dialogue_words_per_minute: f64,
+    /// "syllables" 模型的每秒音节数
+    pub dialogue_syllables_per_sec: f64,
+    /// 按角色调整语速的倍率，大于 1 表示说得快，如 {"阿强": 1.2}
+    ///
+    /// 剧本中按角色的语速写在标题页 metadata 的 "rates" 里，单位是每字符耗时（秒），
+    /// 按 `dial_sec_per_char / 耗时` 换算为倍率；同一角色两处都设置时以 metadata 为准
+    pub speaking_rates: HashMap<String, f64>,
     /// 是否打印标题页
     pub print_title_page: bool,
+    /// 标题页模板："us"（美国规范）、"bbc"、"china"（中文封面），为空时使用默认版式
+    pub title_page_template: String,
     /// 是否打印前言页
     pub print_preface_page: bool,
+    /// 是否在标题页之后插入角色表（CAST OF CHARACTERS）页
+    pub print_cast_list: bool,
+    /// 是否在标题页之后插入版本记录（审批）页，内容取自标题页的 Revision、Draft date 和 Metadata 中的 history
+    pub print_version_history: bool,
+    /// 是否在正文之后附上地点表（各地点的内/外景、时间、场景编号和总时长，以及日/夜场景数）
+    pub print_locations_appendix: bool,
     /// 场景编号位置
     pub scenes_numbers: String,
-    /// 是否显示页码
+    /// 场景标题的规范方式："upper"（默认，转为大写，连字符两侧各留一个空格）、
+    /// "preserve"（保留原文的大小写和连字符）、"locale:tr" 等（按语言规则转为大写，如土耳其语的 i → İ）
+    pub scene_heading_normalization: String,
+    /// 自动场景编号的格式，{n} 为场景序号，{act} 为所在幕（一级章节）的序号，{act_roman} 为幕序号的大写罗马数字，
+    /// 冒号后的数字表示补零后的位数（如 {n:3} 得到 "007"）；含 {act} 或 {act_roman} 时场景序号在每一幕重新从 1 开始，
+    /// 第一幕之前的场景幕序号为 0（罗马数字为空）。场景标题中手动指定的编号不受影响
+    pub scene_number_format: String,
+    /// 页码格式，{n} 为页码，{total} 为正文总页数，为空时不显示页码
     pub show_page_numbers: String,
+    /// 正文第一页的页码
+    pub page_number_start: u32,
+    /// 正文第一页不显示页码（仍然计数）
+    pub hide_first_page_number: bool,
+    /// 标题页、版本记录、角色表、序言页等前置页是否以小写罗马数字（i、ii、iii…）连续编页码（标题页计为 i，不显示），
+    /// 正文另起从 page_number_start 开始；关闭时前置页不显示页码
+    pub front_matter_page_numbers: bool,
     /// 是否加粗场景标题
     pub embolden_scene_headers: bool,
     /// 是否为场景标题添加下划线
     pub underline_scene_headers: bool,
-    /// 页眉
+    /// 场景标题的文字颜色（"#RRGGBB"、"#RGB" 或 red、blue 等颜色名），为空时不设置
+    pub scene_heading_color: String,
+    /// 角色名的文字颜色，写法同 scene_heading_color
+    pub character_color: String,
+    /// 转场的文字颜色，写法同 scene_heading_color
+    pub transition_color: String,
+    /// 页眉，可用占位符 {title}、{author}、{date}、{scene}、{section}、{page}、{total}
     pub print_header: String,
-    /// 页脚
+    /// 页脚，占位符同页眉
     pub print_footer: String,
+    /// 是否在页眉中显示当前所在的章节（幕），随页更新
+    pub print_section_header: bool,
     /// 是否为章节添加编号
     pub number_sections: bool,
     /// 是否创建书签
     pub create_bookmarks: bool,
+    /// 是否在正文之前插入目录页，列出各章节和场景并链接到正文中的书签，需同时开启 create_bookmarks
+    pub print_toc: bool,
     /// 注释位置是否在底部
     pub note_position_bottom: bool,
+    /// 注释是否汇总为文末尾注，优先于 note_position_bottom
+    pub note_position_end: bool,
+    /// 注释是否导出为 Word 批注（锚定注释所在的段落），优先于 note_position_end 和 note_position_bottom
+    pub notes_as_word_comments: bool,
     /// 是否打印动作
     pub print_actions: bool,
     /// 是否打印对话
@@ -72,6 +136,27 @@ pub struct Conf {
     pub print_sections: bool,
     /// 是否打印概要
     pub print_synopsis: bool,
+    /// 大纲 DOCX 中是否打印概要，与 print_synopsis 无关
+    pub print_synopsis_outline: bool,
+    /// HTML 预览（`ParseOutput::script_html`）中是否显示概要
+    pub print_synopsis_html: bool,
+    /// 大纲 DOCX 中概要的颜色、斜体等样式，为空时与正文相同（print_profile.synopsis）
+    pub outline_synopsis_style: Option<SynopsisConfig>,
+    /// DOCX 导出时在正文左侧页边标连续的行号，每隔几行标一次（1 为每行，5 为每五行），0 不标
+    pub print_line_numbers: u32,
+    /// DOCX 的编辑限制，用于发给审阅者的稿子："read_only_recommended"（打开时建议只读）、"read_only"（只读）、
+    /// "comments"（只能加批注）、"tracked_changes"（修改都记为修订），为空时不限制。不设密码
+    pub docx_protection: String,
+    /// 确定性输出：固定 DOCX 中的创建和修改日期、批注和修订的日期、压缩包条目的顺序和时间、段落 id 和 GUID，
+    /// 相同的剧本和配置每次生成字节完全相同的文件，便于在 CI 中比较。日期固定为 1980-01-01，
+    /// 标题页没有日期时页眉页脚中的 `{date}` 也使用这个日期
+    pub deterministic: bool,
+    /// 确定性输出使用的时间（Unix 时间戳，秒），与 `SOURCE_DATE_EPOCH` 环境变量的约定相同。
+    /// 设置后即按确定性输出生成，日期使用这个时间而不是 1980-01-01；命令行未在配置中设置时读取该环境变量
+    pub source_date_epoch: Option<i64>,
+    /// 生成 DOCX 后检查是否符合 OOXML 规范（关系 id、脚注引用、节属性等），有问题时导出失败并列出所有问题，
+    /// 而不是写出 Word 无法打开的文件
+    pub validate_output: bool,
     /// 是否在场景之间添加双倍空格
     pub double_space_between_scenes: bool,
     /// 页面大小
@@ -84,46 +169,277 @@ pub struct Conf {
     pub print_profile: PrintProfile,
     /// 水印
     pub print_watermark: String,
+    /// 剧本变量，正文中的 `{{名称}}` 在解析时替换为对应的值；
+    /// 标题页 Metadata 中 "variables" 定义的同名变量优先
+    pub variables: HashMap<String, String>,
+    /// 额外的场景标题前缀（如 "INNEN"、"EXTÉRIEUR"、"ИНТ"），不区分大小写，
+    /// 与内置的 INT、EXT、EST、INT./EXT.、I/E 一样后接句点或空格
+    pub scene_heading_prefixes: Vec<String>,
+    /// 额外的转场关键字（如 "SMASH CUT TO:"、"切至："），不区分大小写，
+    /// 以关键字结尾的行识别为转场，匹配到的关键字记在 token 的 `transition` 上
+    pub transition_keywords: Vec<String>,
+    /// 排版美化的语言（"en"、"de"、"fr"、"zh"、"ja"）：按该语言习惯替换引号、破折号和省略号，为空时不处理
+    pub typography: String,
+    /// 严格按 Fountain 规范解析，关闭镜头交切、`[[|` 注解、全角括号等所有扩展语法（见 `parser::spec`）
+    pub spec_mode: bool,
+    /// 单独关闭的扩展语法，名称见 `parser::spec::Extension::name`（如 "shot_cut"、"inline_note"、"china_format"）
+    pub disabled_extensions: Vec<String>,
 }
 
 impl Default for Conf {
     fn default() -> Self {
         Conf {
             print_notes: true,
+            print_note_tags: Vec::new(),
+            hide_note_tags: Vec::new(),
             merge_empty_lines: true,
             each_scene_on_new_page: false,
+            page_break_before_sections: None,
             use_dual_dialogue: true,
+            balance_dual_dialogue: false,
             dialogue_foldable: false,
             print_dialogue_numbers: false,
+            dialogue_numbering: "global".to_string(),
             emitalic_dialog: true,
             embolden_character_names: true,
             text_contd: "(CONT'D)".to_string(),
+            intercut_character_locations: false,
             dial_sec_per_char: 0.3,
             dial_sec_per_punc_short: 0.3,
             dial_sec_per_punc_long: 0.75,
             action_sec_per_char: 0.4,
+            dialogue_duration_model: "chars".to_string(),
+            dialogue_words_per_minute: 160.0,
+            dialogue_syllables_per_sec: 4.0,
+            speaking_rates: HashMap::new(),
             print_title_page: true,
+            title_page_template: String::new(),
             print_preface_page: true,
+            print_cast_list: false,
+            print_version_history: false,
+            print_locations_appendix: false,
             scenes_numbers: "both".to_string(),
+            scene_heading_normalization: "upper".to_string(),
+            scene_number_format: "{n}".to_string(),
             show_page_numbers: "(第{n}页)".to_string(),
+            page_number_start: 1,
+            hide_first_page_number: false,
+            front_matter_page_numbers: false,
             embolden_scene_headers: true,
             underline_scene_headers: false,
+            scene_heading_color: "".to_string(),
+            character_color: "".to_string(),
+            transition_color: "".to_string(),
             print_header: "".to_string(),
             print_footer: "".to_string(),
+            print_section_header: false,
             number_sections: true,
             create_bookmarks: true,
+            print_toc: false,
             note_position_bottom: true,
+            note_position_end: false,
+            notes_as_word_comments: false,
             print_actions: true,
             print_dialogues: true,
             print_headers: true,
             print_sections: false,
             print_synopsis: true,
+            print_synopsis_outline: true,
+            print_synopsis_html: true,
+            outline_synopsis_style: None,
+            print_line_numbers: 0,
+            docx_protection: String::new(),
+            deterministic: false,
+            source_date_epoch: None,
+            validate_output: false,
             double_space_between_scenes: false,
             page_size: "A4".to_string(),
             font_family: "Courier Prime".to_string(),
             emitalic_dialogue: false,
             print_profile: PrintProfile::default(),
             print_watermark: "".to_string(),
+            variables: HashMap::new(),
+            scene_heading_prefixes: Vec::new(),
+            transition_keywords: Vec::new(),
+            typography: String::new(),
+            spec_mode: false,
+            disabled_extensions: Vec::new(),
         }
     }
 }
+
+/// 项目配置文件名，按顺序在剧本所在目录及其上级目录中查找
+pub const CONFIG_FILE_NAMES: [&str; 3] = [".betterfountainrc", ".betterfountainrc.json", "fountain.toml"];
+
+/// 配置加载错误
+#[derive(Error, Debug)]
+pub enum ConfError {
+    #[error("读取配置文件 {0} 失败: {1}")]
+    Io(String, #[source] std::io::Error),
+
+    #[error("JSON 配置格式错误: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("TOML 配置格式错误: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("无效的配置项 {0}")]
+    InvalidOverride(String),
+}
+
+impl Conf {
+    /// 在默认配置上合并部分配置，未写出的字段保持原值；嵌套对象（如 print_profile）按字段合并
+    ///
+    /// 配置中没有的字段（如拼错的字段名）返回 [`ConfError::InvalidOverride`]
+    pub fn merge(&self, overrides: serde_json::Value) -> Result<Conf, ConfError> {
+        check_keys(&serde_json::to_value(Conf::default())?, &overrides, "")?;
+        let mut merged = serde_json::to_value(self)?;
+        merge_json(&mut merged, overrides);
+        Ok(serde_json::from_value(merged)?)
+    }
+
+    /// 从 JSON 文本合并配置
+    pub fn merge_json_str(&self, content: &str) -> Result<Conf, ConfError> {
+        self.merge(serde_json::from_str(content)?)
+    }
+
+    /// 从 TOML 文本合并配置
+    pub fn merge_toml_str(&self, content: &str) -> Result<Conf, ConfError> {
+        self.merge(toml::from_str::<serde_json::Value>(content)?)
+    }
+
+    /// 合并配置文件，`.toml` 按 TOML 解析，其它（包括 `.betterfountainrc`）按 JSON 解析
+    pub fn merge_file(&self, path: &Path) -> Result<Conf, ConfError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ConfError::Io(path.display().to_string(), e))?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            self.merge_toml_str(&content)
+        } else {
+            self.merge_json_str(&content)
+        }
+    }
+
+    /// 合并命令行的 `key=value` 覆盖项，key 可以用 `.` 指定嵌套字段（如 `print_profile.font_size=12`）
+    ///
+    /// value 能按 JSON 解析时按 JSON 处理（数字、布尔等），否则当作字符串
+    pub fn merge_overrides(&self, overrides: &[String]) -> Result<Conf, ConfError> {
+        let mut root = serde_json::Map::new();
+        for item in overrides {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| ConfError::InvalidOverride(item.clone()))?;
+            let value = serde_json::from_str(value.trim())
+                .unwrap_or_else(|_| serde_json::Value::String(value.trim().to_string()));
+
+            let mut keys: Vec<&str> = key.trim().split('.').collect();
+            let last = keys.pop().filter(|k| !k.is_empty());
+            let Some(last) = last else {
+                return Err(ConfError::InvalidOverride(item.clone()));
+            };
+            let mut node = &mut root;
+            for k in keys {
+                node = node
+                    .entry(k.to_string())
+                    .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+                    .as_object_mut()
+                    .ok_or_else(|| ConfError::InvalidOverride(item.clone()))?;
+            }
+            node.insert(last.to_string(), value);
+        }
+        self.merge(serde_json::Value::Object(root))
+    }
+
+    /// 是否打印某个注解，`tag` 为注解的分类标签，没有标签的注解只受 print_notes 控制
+    pub fn should_print_note(&self, tag: Option<&str>) -> bool {
+        if !self.print_notes {
+            return false;
+        }
+        let Some(tag) = tag else {
+            return true;
+        };
+        let matches = |tags: &[String]| tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+        if matches(&self.hide_note_tags) {
+            return false;
+        }
+        self.print_note_tags.is_empty() || matches(&self.print_note_tags)
+    }
+
+    /// 是否启用某个扩展语法：spec_mode 下全部关闭，否则只关闭 disabled_extensions 中列出的
+    pub fn extension_enabled(&self, extension: Extension) -> bool {
+        !self.spec_mode
+            && !self
+                .disabled_extensions
+                .iter()
+                .any(|name| Extension::from_name(name) == Some(extension))
+    }
+
+    /// 从剧本所在目录向上查找项目配置文件
+    pub fn discover(script_path: &Path) -> Option<PathBuf> {
+        let start = if script_path.is_dir() {
+            script_path
+        } else {
+            script_path.parent()?
+        };
+        for dir in start.ancestors() {
+            for name in CONFIG_FILE_NAMES {
+                let candidate = dir.join(name);
+                if candidate.is_file() {
+                    return Some(candidate);
+                }
+            }
+        }
+        None
+    }
+
+    /// 加载剧本对应的项目配置：默认配置 ← 项目配置文件，没有找到配置文件时返回默认配置
+    pub fn load_for_script(script_path: &Path) -> Result<Conf, ConfError> {
+        match Conf::discover(script_path) {
+            Some(path) => Conf::default().merge_file(&path),
+            None => Ok(Conf::default()),
+        }
+    }
+}
+
+/// 检查 `overrides` 中的字段都出现在默认配置 `schema` 中，`path` 为上层字段名
+///
+/// 默认值为空对象的字段是映射（如 variables），其中的键不检查
+fn check_keys(
+    schema: &serde_json::Value,
+    overrides: &serde_json::Value,
+    path: &str,
+) -> Result<(), ConfError> {
+    let (Some(schema), Some(overrides)) = (schema.as_object(), overrides.as_object()) else {
+        return Ok(());
+    };
+    if schema.is_empty() {
+        return Ok(());
+    }
+    for (key, value) in overrides {
+        let key_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        let field = schema
+            .get(key)
+            .ok_or_else(|| ConfError::InvalidOverride(key_path.clone()))?;
+        check_keys(field, value, &key_path)?;
+    }
+    Ok(())
+}
+
+fn merge_json(base: &mut serde_json::Value, overrides: serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (k, v) in overrides {
+                match base.get_mut(&k) {
+                    Some(existing) => merge_json(existing, v),
+                    None => {
+                        base.insert(k, v);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
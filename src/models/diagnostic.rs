@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use super::struct_token::{Position, Range};
+
+/// 诊断级别，与编辑器（LSP）的级别对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// 剧本检查结果，range 用于编辑器中的波浪线提示
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: DiagnosticSeverity,
+    /// 规则代码，如 "unclosed-note"
+    pub code: String,
+    pub message: String,
+    /// 附加数据，如无法识别的字段名、重复的场景编号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(range: Range, severity: DiagnosticSeverity, code: &str, message: String) -> Self {
+        Diagnostic {
+            range,
+            severity,
+            code: code.to_string(),
+            message,
+            data: None,
+        }
+    }
+
+    /// 设置附加数据
+    pub fn with_data(mut self, data: impl Into<String>) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// 覆盖一整行的诊断（列按字符计）
+    pub fn line(
+        line: usize,
+        text: &str,
+        severity: DiagnosticSeverity,
+        code: &str,
+        message: String,
+    ) -> Self {
+        let start = text.chars().take_while(|c| c.is_whitespace()).count();
+        let end = text.trim_end().chars().count().max(start);
+        Diagnostic::new(
+            Range {
+                start: Position { line, character: start },
+                end: Position { line, character: end },
+            },
+            severity,
+            code,
+            message,
+        )
+    }
+}
@@ -2,7 +2,14 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Location {
+    /// 完整的地点名称，多级地点用 " - " 连接，如 "HOUSE - KITCHEN"
     pub name: String,
+    /// 主地点，如 "HOUSE - KITCHEN" 中的 "HOUSE"
+    #[serde(default)]
+    pub master: String,
+    /// 主地点之下的子地点，由外到内排列，如 "HOUSE - KITCHEN" 中的 ["KITCHEN"]
+    #[serde(default)]
+    pub sub_locations: Vec<String>,
     pub interior: bool,
     pub exterior: bool,
     pub time_of_day: String,
@@ -19,6 +26,8 @@ impl Location {
         time_of_day: String
     ) -> Self {
         Location {
+            master: name.clone(),
+            sub_locations: Vec::new(),
             name,
             interior,
             exterior,
@@ -28,4 +37,14 @@ impl Location {
             start_play_sec: 0.0,
         }
     }
+
+    /// 从主地点到该地点的各级名称；旧数据没有主地点时只有完整名称一级
+    pub fn path(&self) -> Vec<String> {
+        if self.master.is_empty() {
+            return vec![self.name.clone()];
+        }
+        let mut path = vec![self.master.clone()];
+        path.extend(self.sub_locations.iter().cloned());
+        path
+    }
 }
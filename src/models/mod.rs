@@ -3,9 +3,11 @@ pub mod struct_token;
 pub mod location;
 pub mod screenplay_properties;
 pub mod conf;
+pub mod diagnostic;
 
-pub use script_token::ScriptToken;
+pub use script_token::{ScriptToken, SourceOrigin, SourceRange};
 pub use struct_token::{StructToken, Synopsis, Note, Range, Position};
 pub use location::Location;
 pub use screenplay_properties::ScreenplayProperties;
-pub use conf::Conf;
+pub use conf::{Conf, ConfError};
+pub use diagnostic::{Diagnostic, DiagnosticSeverity};
@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use crate::models::location::Location;
-use crate::models::struct_token::StructToken;
+use crate::models::struct_token::{Note, StructToken};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScreenplayProperties {
@@ -22,6 +22,14 @@ pub struct ScreenplayProperties {
     pub character_describe: Option<HashMap<String, String>>,
     pub character_scene_number: Option<HashMap<String, HashSet<String>>>,
     pub scene_number_vars: Option<HashSet<String>>,
+    /// 镜头交切分组（{+ +}、{# #}、{= =}），每组包含 type、line、scene_ids 和 duration
+    pub shot_cuts: Vec<HashMap<String, serde_json::Value>>,
+    /// 带标签的注解（如 `[[todo: ...]]`、`[[prod: ...]]`），按小写标签分组，注解文本不含标签
+    pub notes_by_tag: HashMap<String, Vec<Note>>,
+    /// 删去的场景（OMITTED）的编号，这些场景保留编号但不输出内容
+    pub omitted_scenes: Vec<String>,
+    /// 标题页 metadata 中 "aliases" 声明的角色别名，键为大写的别名，值为规范角色名
+    pub character_aliases: HashMap<String, String>,
 }
 
 impl ScreenplayProperties {
@@ -44,8 +52,21 @@ impl ScreenplayProperties {
             character_describe: Some(HashMap::new()),
             character_scene_number: Some(HashMap::new()),
             scene_number_vars: Some(HashSet::new()),
+            shot_cuts: Vec::new(),
+            notes_by_tag: HashMap::new(),
+            omitted_scenes: Vec::new(),
+            character_aliases: HashMap::new(),
         }
     }
+
+    /// 把别名换成规范角色名，不是别名时原样返回（去掉首尾空白）
+    pub fn canonical_character(&self, name: &str) -> String {
+        let name = name.trim();
+        self.character_aliases
+            .get(&name.to_uppercase())
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
 }
 
 impl Default for ScreenplayProperties {
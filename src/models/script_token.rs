@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crate::models::location::Location;
+use crate::utils::{escape_style_chars, unescape_style_chars};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScriptToken {
@@ -25,6 +26,36 @@ pub struct ScriptToken {
     pub characters_action: Option<Vec<String>>, // action 类型专用，该 action 行包含哪些角色
     pub play_time_sec: f64,  // 对应行结束后在影片中的时间进度
     pub invisible_sections: Option<Vec<ScriptToken>>, // 不可见的章节（用于创建书签和生成docx侧边栏）
+    pub origin: Option<SourceOrigin>, // 展开 {include: ...} 后，该行所在的原始文件和行号
+    pub source_range: Option<SourceRange>, // 在传入解析器的完整剧本文本中的绝对位置，展开引用时为 origin 所在文件中的位置
+    pub transition: Option<String>, // 转场关键字(仅转场有效)，如 "CUT TO:" 或配置中匹配到的自定义关键字
+}
+
+/// 展开 `{include: ...}` 之后，一行文本在原始文件中的位置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceOrigin {
+    pub file: String,
+    /// 在原始文件中的行号（从 0 开始）
+    pub line: usize,
+}
+
+/// token 在完整剧本文本中的绝对范围（左闭右开），同时给出字节偏移和 UTF-16 偏移（供编辑器使用）
+///
+/// 用 `FountainParser::parse_file` 展开引用后解析时，范围是在 `origin` 所指文件原文中的范围，
+/// 原文中的 `\r\n` 按两个字节计。
+///
+/// 范围覆盖 token 所在的源码行（去掉首尾空白），包括 `.`、`@`、`#1#` 等在 `text` 中已去掉的标记；
+/// 多行 token（如多行的标题页字段）覆盖到最后一行。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceRange {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub utf16_start: usize,
+    pub utf16_end: usize,
+    /// 起点在第一行中的列（按字符计）
+    pub column_start: usize,
+    /// 终点在最后一行中的列（按字符计）
+    pub column_end: usize,
 }
 
 impl ScriptToken {
@@ -57,6 +88,9 @@ impl ScriptToken {
             characters_action: None,
             play_time_sec: 0.0,
             invisible_sections: None,
+            origin: None,
+            source_range: None,
+            transition: None,
         }
     }
 
@@ -84,6 +118,9 @@ impl ScriptToken {
             characters_action: None,
             play_time_sec: 0.0,
             invisible_sections: None,
+            origin: None,
+            source_range: None,
+            transition: None,
         }
     }
 
@@ -92,27 +129,54 @@ impl ScriptToken {
         types.contains(&self.token_type.as_str())
     }
 
+    /// 是否为删去的场景（OMITTED）的场景标题
+    pub fn is_omitted(&self) -> bool {
+        self.token_type == "scene_heading"
+            && self
+                .metadata
+                .as_ref()
+                .is_some_and(|m| m.contains_key("omitted"))
+    }
+
     // 获取清理后的文本(去除格式标记等)
     pub fn clean_text(&self) -> String {
-        // 移除星号和下划线样式的标记
-        let mut t = self.text.replace(&['*', '_'][..], "");
-
-        // 移除内联注释 [[...]] 或 /* ... */
-        t = t.replace(r"[[", "").replace(r"]]", "")
-            .replace(r"/*", "").replace(r"*/", "");
-
+        let t = match self.token_type.as_str() {
+            // 这几种 token 不做样式处理，移除星号和下划线样式的标记，反斜杠转义的保留
+            "scene_heading" | "character" | "transition" => {
+                let t = escape_style_chars(&self.text).replace(&['*', '_'][..], "");
+                unescape_style_chars(&t)
+            }
+            // 其他 token 的样式标记和注解已换成特殊符号，剩下的都是转义后的原字符
+            _ => self.text.clone(),
+        };
         t.trim().to_string()
     }
 
     // 转换为HTML格式(用于预览)
     pub fn to_html(&self) -> String {
-        let cleaned = self.clean_text();
+        let cleaned = crate::utils::style_chars_to_html(&self.clean_text());
         match self.token_type.as_str() {
             "scene_heading" => format!("<div class=\"scene-heading\">{}</div>", cleaned),
             "character" => format!("<div class=\"character\">{}</div>", cleaned),
             "dialogue" => format!("<div class=\"dialogue\">{}</div>", cleaned),
             "parenthetical" => format!("<div class=\"parenthetical\">{}</div>", cleaned),
             "action" => format!("<div class=\"action\">{}</div>", cleaned),
+            "lyric" => format!("<div class=\"lyric\"><em>♪ {}</em></div>", cleaned),
+            "image" => {
+                let attr = |key: &str| {
+                    self.metadata
+                        .as_ref()
+                        .and_then(|m| m.get(key))
+                        .map(|v| format!(" {}: {}in;", key, v))
+                        .unwrap_or_default()
+                };
+                format!(
+                    "<div class=\"image\"><img src=\"{}\" style=\"max-width: 100%;{}{}\"></div>",
+                    self.text.replace('&', "&amp;").replace('"', "&quot;"),
+                    attr("width"),
+                    attr("height")
+                )
+            }
             _ => format!("<div class=\"fountain-{}\">{}</div>", self.token_type, cleaned),
         }
     }
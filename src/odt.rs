@@ -0,0 +1,844 @@
+//! OpenDocument 文本（.odt）导出
+//!
+//! 与 DOCX 共用同一套排版流程：docx_maker 生成的抽象文档模型（段落样式、缩进、双对白表格、脚注等）
+//! 在这里序列化为 OpenDocument 格式，供 LibreOffice 等使用。
+//! 段落框架（标题页定位）不导出；修订以删除线/下划线近似表示，不生成 ODF 的修订记录。
+
+use crate::docx::adapter::docx::{
+    Document, Footnote, PageNumberChild, Paragraph, ParagraphIndent, ParagraphSpacing,
+    RevisionKind, RunStyle, RunType, Section, SectionChild, TabStop, Table, TextRun,
+};
+use crate::docx::adapter::{
+    AlignmentType, LineRuleType, RunProps, TabAlignment, TabLeader, UnderlineType, WidthType,
+};
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use thiserror::Error;
+
+/// ODT 导出错误
+#[derive(Error, Debug)]
+pub enum OdtError {
+    #[error("IO错误: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("ZIP错误: {0}")]
+    Zip(#[from] zip::result::ZipError),
+}
+
+const OFFICE_NS: &str = "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" \
+xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" \
+xmlns:draw=\"urn:oasis:names:tc:opendocument:xmlns:drawing:1.0\" \
+xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" \
+xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+xmlns:meta=\"urn:oasis:names:tc:opendocument:xmlns:meta:1.0\" \
+xmlns:svg=\"urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0\" \
+office:version=\"1.2\"";
+
+/// 把文档写入 .odt 文件
+pub fn write_odt(doc: &Document, filepath: &str) -> Result<(), OdtError> {
+    std::fs::write(filepath, to_odt_bytes(doc)?)?;
+    Ok(())
+}
+
+/// 生成 .odt 文件内容
+pub fn to_odt_bytes(doc: &Document) -> Result<Vec<u8>, OdtError> {
+    let mut writer = OdtWriter::new(&doc.options.footnotes);
+    let body = writer.body(&doc.options.sections);
+    let content = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-content {}>\
+<office:automatic-styles>{}</office:automatic-styles>\
+<office:body><office:text>{}</office:text></office:body></office:document-content>",
+        OFFICE_NS,
+        writer.auto_styles.concat(),
+        body
+    );
+    let styles = writer.styles_xml(doc);
+    let meta = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-meta {}><office:meta>\
+<meta:generator>betterfountain_rust</meta:generator><dc:title>{}</dc:title>\
+<meta:initial-creator>{}</meta:initial-creator></office:meta></office:document-meta>",
+        OFFICE_NS,
+        escape_xml(&doc.options.title),
+        escape_xml(&doc.options.creator)
+    );
+
+    let mut manifest = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest:manifest \
+xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.2\">\
+<manifest:file-entry manifest:full-path=\"/\" manifest:media-type=\"application/vnd.oasis.opendocument.text\"/>\
+<manifest:file-entry manifest:full-path=\"content.xml\" manifest:media-type=\"text/xml\"/>\
+<manifest:file-entry manifest:full-path=\"styles.xml\" manifest:media-type=\"text/xml\"/>\
+<manifest:file-entry manifest:full-path=\"meta.xml\" manifest:media-type=\"text/xml\"/>",
+    );
+    for (path, _, mime) in &writer.images {
+        manifest.push_str(&format!(
+            "<manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"{}\"/>",
+            path, mime
+        ));
+    }
+    manifest.push_str("</manifest:manifest>");
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    // mimetype 必须是第一个文件且不压缩
+    let stored =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/vnd.oasis.opendocument.text")?;
+    let deflated = zip::write::FileOptions::default();
+    for (name, data) in [
+        ("content.xml", content.as_bytes()),
+        ("styles.xml", styles.as_bytes()),
+        ("meta.xml", meta.as_bytes()),
+        ("META-INF/manifest.xml", manifest.as_bytes()),
+    ] {
+        zip.start_file(name, deflated)?;
+        zip.write_all(data)?;
+    }
+    for (path, data, _) in &writer.images {
+        zip.start_file(path.as_str(), deflated)?;
+        zip.write_all(data)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+struct OdtWriter<'a> {
+    footnotes: &'a HashMap<usize, Footnote>,
+    // 自动样式的 XML 及其去重表（样式属性 -> 样式名）
+    auto_styles: Vec<String>,
+    style_names: HashMap<String, String>,
+    // (包内路径, 内容, MIME 类型)
+    images: Vec<(String, Vec<u8>, &'static str)>,
+    tables: usize,
+    // 下一个段落或表格之前需要分页
+    page_break: bool,
+}
+
+impl<'a> OdtWriter<'a> {
+    fn new(footnotes: &'a HashMap<usize, Footnote>) -> Self {
+        Self {
+            footnotes,
+            auto_styles: Vec::new(),
+            style_names: HashMap::new(),
+            images: Vec::new(),
+            tables: 0,
+            page_break: false,
+        }
+    }
+
+    fn body(&mut self, sections: &[Section]) -> String {
+        let mut xml = String::new();
+        for (i, section) in sections.iter().enumerate() {
+            // 每一节从新的一页开始
+            self.page_break = i > 0;
+            for child in &section.children {
+                match child {
+                    SectionChild::Paragraph(paragraph) => xml.push_str(&self.paragraph(paragraph)),
+                    SectionChild::Table(table) => xml.push_str(&self.table(table)),
+                    SectionChild::PageBreak => {
+                        // 连续分页时插入空白页
+                        if self.page_break {
+                            xml.push_str(&self.paragraph(&Paragraph::new()));
+                        }
+                        self.page_break = true;
+                    }
+                }
+            }
+        }
+        xml
+    }
+
+    // 按属性去重注册自动样式，返回样式名
+    fn auto_style(
+        &mut self,
+        prefix: &str,
+        family: &str,
+        parent: Option<&str>,
+        props: &str,
+    ) -> String {
+        let key = format!("{}|{}|{:?}|{}", prefix, family, parent, props);
+        if let Some(name) = self.style_names.get(&key) {
+            return name.clone();
+        }
+        let name = format!("{}{}", prefix, self.auto_styles.len() + 1);
+        let parent = parent
+            .map(|p| format!(" style:parent-style-name=\"{}\"", escape_xml(p)))
+            .unwrap_or_default();
+        self.auto_styles.push(format!(
+            "<style:style style:name=\"{}\" style:family=\"{}\"{}>{}</style:style>",
+            name, family, parent, props
+        ));
+        self.style_names.insert(key, name.clone());
+        name
+    }
+
+    fn paragraph(&mut self, paragraph: &Paragraph) -> String {
+        let mut props = paragraph_properties(
+            paragraph.indent.as_ref(),
+            paragraph.spacing.as_ref(),
+            paragraph.alignment.as_ref(),
+        );
+        if std::mem::take(&mut self.page_break) {
+            props.push_str(" fo:break-before=\"page\"");
+        }
+        if paragraph.keep_next {
+            props.push_str(" fo:keep-with-next=\"always\"");
+        }
+        if paragraph.keep_lines {
+            props.push_str(" fo:keep-together=\"always\"");
+        }
+        if paragraph.change_bar {
+            props.push_str(" fo:border-left=\"1pt solid #000000\" fo:padding-left=\"0.1in\"");
+        }
+        if let Some(border) = &paragraph.border {
+            props.push_str(&format!(
+                " fo:border=\"0.5pt solid #{}\" fo:padding=\"0.04in\"",
+                border.trim_start_matches('#')
+            ));
+        }
+        let properties =
+            paragraph_properties_element(&props, &paragraph.tabs, paragraph.indent.as_ref());
+        let style = self.auto_style("P", "paragraph", paragraph.style.as_deref(), &properties);
+
+        let revision = match paragraph.revision.as_ref().map(|r| r.kind) {
+            Some(RevisionKind::Delete) => " style:text-line-through-style=\"solid\"",
+            Some(RevisionKind::Insert) => {
+                " style:text-underline-style=\"solid\" style:text-underline-width=\"auto\""
+            }
+            None => "",
+        };
+        let mut content = String::new();
+        for run in &paragraph.runs {
+            content.push_str(&self.run(run, revision));
+        }
+
+        match paragraph.outline_level {
+            Some(level) => format!(
+                "<text:h text:style-name=\"{}\" text:outline-level=\"{}\">{}</text:h>",
+                style,
+                level + 1,
+                content
+            ),
+            None => format!("<text:p text:style-name=\"{}\">{}</text:p>", style, content),
+        }
+    }
+
+    fn run(&mut self, run: &RunType, revision: &str) -> String {
+        match run {
+            RunType::Text(text_run) => self.text_run(text_run, revision),
+            RunType::Break(_) => "<text:line-break/>".to_string(),
+            RunType::Hyperlink(link) => format!(
+                "<text:a xlink:type=\"simple\" xlink:href=\"{}\">{}</text:a>",
+                escape_xml(&link.url),
+                self.span(&link.props, revision, &escape_text(&link.text))
+            ),
+            RunType::PageNumber(page_number) => {
+                let mut content = String::new();
+                for child in &page_number.children {
+                    match child {
+                        PageNumberChild::Text(text) => content.push_str(&escape_text(text)),
+                        PageNumberChild::PageNumber => content.push_str(
+                            "<text:page-number text:select-page=\"current\">1</text:page-number>",
+                        ),
+                    }
+                }
+                self.span(&page_number.props, revision, &content)
+            }
+            RunType::Image(image) => {
+                const EMU_PER_INCH: f32 = 914400.0;
+                let size = docx_rs::Pic::new(&image.data).size;
+                let (origin_width, origin_height) =
+                    (size.0 as f32 / EMU_PER_INCH, size.1 as f32 / EMU_PER_INCH);
+                let (width, height) = match (image.width, image.height) {
+                    (Some(w), Some(h)) => (w, h),
+                    (Some(w), None) if origin_width > 0.0 => (w, w * origin_height / origin_width),
+                    (None, Some(h)) if origin_height > 0.0 => (h * origin_width / origin_height, h),
+                    _ => (origin_width, origin_height),
+                };
+                let (extension, mime) = if image.data.starts_with(&[0xFF, 0xD8]) {
+                    ("jpg", "image/jpeg")
+                } else {
+                    ("png", "image/png")
+                };
+                let path = format!("Pictures/image{}.{}", self.images.len() + 1, extension);
+                let xml = format!(
+                    "<draw:frame text:anchor-type=\"as-char\" svg:width=\"{:.4}in\" svg:height=\"{:.4}in\">\
+<draw:image xlink:href=\"{}\" xlink:type=\"simple\" xlink:show=\"embed\" xlink:actuate=\"onLoad\"/></draw:frame>",
+                    width, height, path
+                );
+                self.images.push((path, image.data.clone(), mime));
+                xml
+            }
+        }
+    }
+
+    fn text_run(&mut self, run: &TextRun, revision: &str) -> String {
+        if let Some(comment) = &run.comment {
+            let mut xml = format!(
+                "<office:annotation><dc:creator>{}</dc:creator><dc:date>{}</dc:date>",
+                escape_xml(&comment.author),
+                escape_xml(comment.date.trim_end_matches('Z'))
+            );
+            for paragraph in &comment.children {
+                xml.push_str(&self.inline_paragraph(paragraph));
+            }
+            xml.push_str("</office:annotation>");
+            return xml;
+        }
+
+        if let Some(id) = run.footnote_id {
+            let footnotes = self.footnotes;
+            let content = match &run.footnote_content {
+                Some(content) if !content.is_empty() => content.as_slice(),
+                _ => footnotes
+                    .get(&id)
+                    .map(|f| f.children.as_slice())
+                    .unwrap_or_default(),
+            };
+            let mut body = String::new();
+            for paragraph in content {
+                body.push_str(&self.inline_paragraph(paragraph));
+            }
+            return format!(
+                "<text:note text:id=\"ftn{}\" text:note-class=\"footnote\">\
+<text:note-citation>{}</text:note-citation><text:note-body>{}</text:note-body></text:note>",
+                id, id, body
+            );
+        }
+
+        let mut content = String::new();
+        if run.break_before || run.props.break_before == Some(true) {
+            content.push_str("<text:line-break/>");
+        }
+        content.push_str(&escape_text(&run.text));
+        if run.props.break_type.is_some() {
+            content.push_str("<text:line-break/>");
+        }
+        let mut xml = if content.is_empty() {
+            String::new()
+        } else {
+            self.span(&run.props, revision, &content)
+        };
+        if let Some(url) = run.url.as_ref().filter(|_| !xml.is_empty()) {
+            xml = format!(
+                "<text:a xlink:type=\"simple\" xlink:href=\"{}\">{}</text:a>",
+                escape_xml(url),
+                xml
+            );
+        }
+        for child in &run.children {
+            xml.push_str(&self.run(child, revision));
+        }
+        xml
+    }
+
+    // 脚注、批注中的段落：不参与分页
+    fn inline_paragraph(&mut self, paragraph: &Paragraph) -> String {
+        let page_break = std::mem::take(&mut self.page_break);
+        let xml = self.paragraph(paragraph);
+        self.page_break = page_break;
+        xml
+    }
+
+    fn span(&mut self, props: &RunProps, revision: &str, content: &str) -> String {
+        let text_props = format!("{}{}", run_props_properties(props), revision);
+        if text_props.is_empty() {
+            return content.to_string();
+        }
+        let style = self.auto_style(
+            "T",
+            "text",
+            None,
+            &format!("<style:text-properties{}/>", text_props),
+        );
+        format!(
+            "<text:span text:style-name=\"{}\">{}</text:span>",
+            style, content
+        )
+    }
+
+    fn table(&mut self, table: &Table) -> String {
+        self.tables += 1;
+        let name = format!("Table{}", self.tables);
+        let width: usize = table.columnWidths.iter().sum();
+        let mut props = format!(
+            " style:width=\"{}\" table:align=\"left\"",
+            twips(width as i32)
+        );
+        if let Some(indent) = &table.indent {
+            if matches!(indent.width_type, WidthType::DXA) {
+                props.push_str(&format!(" fo:margin-left=\"{}\"", twips(indent.size)));
+            }
+        }
+        if std::mem::take(&mut self.page_break) {
+            props.push_str(" fo:break-before=\"page\"");
+        }
+        self.auto_styles.push(format!(
+            "<style:style style:name=\"{}\" style:family=\"table\"><style:table-properties{}/></style:style>",
+            name, props
+        ));
+
+        let mut xml = format!(
+            "<table:table table:name=\"{}\" table:style-name=\"{}\">",
+            name, name
+        );
+        for (i, column_width) in table.columnWidths.iter().enumerate() {
+            let column_style = format!("{}.C{}", name, i + 1);
+            self.auto_styles.push(format!(
+                "<style:style style:name=\"{}\" style:family=\"table-column\">\
+<style:table-column-properties style:column-width=\"{}\"/></style:style>",
+                column_style,
+                twips(*column_width as i32)
+            ));
+            xml.push_str(&format!(
+                "<table:table-column table:style-name=\"{}\"/>",
+                column_style
+            ));
+        }
+        let border = if table.without_borders {
+            "fo:border=\"none\""
+        } else {
+            "fo:border=\"0.5pt solid #000000\""
+        };
+        let cell_style = self.auto_style(
+            "C",
+            "table-cell",
+            None,
+            &format!(
+                "<style:table-cell-properties fo:padding=\"0in\" {}/>",
+                border
+            ),
+        );
+        for row in &table.rows {
+            xml.push_str("<table:table-row>");
+            for cell in &row.cells {
+                xml.push_str(&format!(
+                    "<table:table-cell table:style-name=\"{}\" office:value-type=\"string\">",
+                    cell_style
+                ));
+                for paragraph in &cell.children {
+                    xml.push_str(&self.paragraph(paragraph));
+                }
+                // 单元格中至少要有一个段落
+                if cell.children.is_empty() {
+                    xml.push_str("<text:p/>");
+                }
+                xml.push_str("</table:table-cell>");
+            }
+            xml.push_str("</table:table-row>");
+        }
+        xml.push_str("</table:table>");
+        xml
+    }
+
+    // styles.xml：命名样式、页面布局和页眉页脚
+    fn styles_xml(&mut self, doc: &Document) -> String {
+        let mut styles = String::new();
+        if let Some(doc_styles) = &doc.options.styles {
+            styles.push_str(&format!(
+                "<style:default-style style:family=\"paragraph\"><style:text-properties{}/></style:default-style>",
+                run_style_properties(&doc_styles.default.document.run)
+            ));
+            for paragraph_style in &doc_styles.paragraph_styles {
+                let Some(id) = &paragraph_style.id else {
+                    continue;
+                };
+                let mut attrs = format!(
+                    " style:name=\"{}\" style:family=\"paragraph\"",
+                    escape_xml(id)
+                );
+                if let Some(name) = &paragraph_style.name {
+                    attrs.push_str(&format!(" style:display-name=\"{}\"", escape_xml(name)));
+                }
+                if let Some(based_on) = &paragraph_style.based_on {
+                    attrs.push_str(&format!(
+                        " style:parent-style-name=\"{}\"",
+                        escape_xml(based_on)
+                    ));
+                }
+                if let Some(next) = &paragraph_style.next {
+                    attrs.push_str(&format!(" style:next-style-name=\"{}\"", escape_xml(next)));
+                }
+                let props = paragraph_properties(
+                    paragraph_style.indent.as_ref(),
+                    paragraph_style.spacing.as_ref(),
+                    None,
+                );
+                styles.push_str(&format!(
+                    "<style:style{}>{}<style:text-properties{}/></style:style>",
+                    attrs,
+                    paragraph_properties_element(
+                        &props,
+                        &paragraph_style.tabs,
+                        paragraph_style.indent.as_ref()
+                    ),
+                    paragraph_style
+                        .run
+                        .as_ref()
+                        .map(run_style_properties)
+                        .unwrap_or_default()
+                ));
+            }
+            for character_style in &doc_styles.character_styles {
+                let Some(id) = &character_style.id else {
+                    continue;
+                };
+                styles.push_str(&format!(
+                    "<style:style style:name=\"{}\" style:family=\"text\"><style:text-properties{}/></style:style>",
+                    escape_xml(id),
+                    character_style
+                        .run
+                        .as_ref()
+                        .map(run_style_properties)
+                        .unwrap_or_default()
+                ));
+            }
+        }
+
+        // 页面布局以最后一个设置了页面属性的节（正文）为准
+        let page = doc
+            .options
+            .sections
+            .iter()
+            .rev()
+            .find_map(|s| s.properties.page.as_ref());
+        let mut layout = String::new();
+        if let Some(size) = page.and_then(|p| p.size.as_ref()) {
+            layout.push_str(&format!(
+                " fo:page-width=\"{}\" fo:page-height=\"{}\"",
+                twips(size.width),
+                twips(size.height)
+            ));
+        }
+        if doc.options.sections.iter().any(|s| s.properties.vertical) {
+            layout.push_str(" style:writing-mode=\"tb-rl\"");
+        }
+        let mut header_style = String::new();
+        let mut footer_style = String::new();
+        if let Some(margin) = page.and_then(|p| p.margin.as_ref()) {
+            layout.push_str(&format!(
+                " fo:margin-top=\"{}\" fo:margin-bottom=\"{}\" fo:margin-left=\"{}\" fo:margin-right=\"{}\"",
+                twips(margin.header),
+                twips(margin.footer),
+                twips(margin.left),
+                twips(margin.right)
+            ));
+            header_style = format!(
+                "<style:header-style><style:header-footer-properties fo:min-height=\"0in\" fo:margin-bottom=\"{}\"/></style:header-style>",
+                twips((margin.top - margin.header).max(0))
+            );
+            footer_style = format!(
+                "<style:footer-style><style:header-footer-properties fo:min-height=\"0in\" fo:margin-top=\"{}\"/></style:footer-style>",
+                twips((margin.bottom - margin.footer).max(0))
+            );
+        }
+
+        // 页眉页脚以最后一个设置了页眉页脚的节为准
+        let mut master = String::new();
+        if let Some(header) = doc
+            .options
+            .sections
+            .iter()
+            .rev()
+            .find_map(|s| s.headers.as_ref())
+        {
+            master.push_str("<style:header>");
+            for paragraph in &header.default.children {
+                master.push_str(&self.inline_paragraph(paragraph));
+            }
+            master.push_str("</style:header>");
+        }
+        if let Some(footer) = doc
+            .options
+            .sections
+            .iter()
+            .rev()
+            .find_map(|s| s.footers.as_ref())
+        {
+            master.push_str("<style:footer>");
+            for paragraph in &footer.default.children {
+                master.push_str(&self.inline_paragraph(paragraph));
+            }
+            master.push_str("</style:footer>");
+        }
+
+        // 页眉页脚中的段落样式也要写入 styles.xml 的自动样式
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-styles {}>\
+<office:styles>{}</office:styles>\
+<office:automatic-styles>{}<style:page-layout style:name=\"pm1\"><style:page-layout-properties{}/>{}{}</style:page-layout></office:automatic-styles>\
+<office:master-styles><style:master-page style:name=\"Standard\" style:page-layout-name=\"pm1\">{}</style:master-page></office:master-styles>\
+</office:document-styles>",
+            OFFICE_NS,
+            styles,
+            self.auto_styles.concat(),
+            layout,
+            header_style,
+            footer_style,
+            master
+        )
+    }
+}
+
+fn paragraph_properties(
+    indent: Option<&ParagraphIndent>,
+    spacing: Option<&ParagraphSpacing>,
+    alignment: Option<&AlignmentType>,
+) -> String {
+    let mut props = String::new();
+    if let Some(indent) = indent {
+        if let Some(left) = indent.left {
+            props.push_str(&format!(" fo:margin-left=\"{}\"", twips(left)));
+        }
+        if let Some(right) = indent.right {
+            props.push_str(&format!(" fo:margin-right=\"{}\"", twips(right)));
+        }
+        // 负数为悬挂缩进
+        if let Some(first_line) = indent.first_line {
+            props.push_str(&format!(" fo:text-indent=\"{}\"", twips(first_line)));
+        }
+    }
+    if let Some(spacing) = spacing {
+        // 按行数的段前段后间距（百分之一行）按 12 磅一行换算
+        let before = spacing
+            .before
+            .or(spacing.before_lines.map(|l| l * 240 / 100));
+        let after = spacing.after.or(spacing.after_lines.map(|l| l * 240 / 100));
+        if let Some(before) = before {
+            props.push_str(&format!(" fo:margin-top=\"{}\"", twips(before)));
+        }
+        if let Some(after) = after {
+            props.push_str(&format!(" fo:margin-bottom=\"{}\"", twips(after)));
+        }
+        if let Some(line) = spacing.line {
+            match spacing.line_rule {
+                Some(LineRuleType::Exact) => {
+                    props.push_str(&format!(" fo:line-height=\"{}\"", twips(line)))
+                }
+                Some(LineRuleType::AtLeast) => {
+                    props.push_str(&format!(" style:line-height-at-least=\"{}\"", twips(line)))
+                }
+                // 自动行距以 240 为单倍行距
+                _ => props.push_str(&format!(" fo:line-height=\"{}%\"", line * 100 / 240)),
+            }
+        }
+    }
+    if let Some(alignment) = alignment {
+        let align = match alignment {
+            AlignmentType::Left => "start",
+            AlignmentType::Center => "center",
+            AlignmentType::Right => "end",
+            AlignmentType::Justify => "justify",
+        };
+        props.push_str(&format!(" fo:text-align=\"{}\"", align));
+    }
+    props
+}
+
+// 段落属性元素，有制表位时带上 style:tab-stops
+fn paragraph_properties_element(
+    props: &str,
+    tabs: &[TabStop],
+    indent: Option<&ParagraphIndent>,
+) -> String {
+    if tabs.is_empty() {
+        return format!("<style:paragraph-properties{}/>", props);
+    }
+    format!(
+        "<style:paragraph-properties{}><style:tab-stops>{}</style:tab-stops></style:paragraph-properties>",
+        props,
+        tab_stops(tabs, indent)
+    )
+}
+
+// ODF 的制表位从段落左缩进算起，DOCX 从页面左边距算起
+fn tab_stops(tabs: &[TabStop], indent: Option<&ParagraphIndent>) -> String {
+    let left = indent.and_then(|indent| indent.left).unwrap_or(0);
+    tabs.iter()
+        .map(|tab| {
+            let kind = match tab.alignment {
+                TabAlignment::Left => "left",
+                TabAlignment::Center => "center",
+                TabAlignment::Right => "right",
+            };
+            let leader = match tab.leader {
+                TabLeader::None => "",
+                TabLeader::Dot => " style:leader-text=\".\"",
+                TabLeader::Hyphen => " style:leader-text=\"-\"",
+                TabLeader::Underscore => " style:leader-text=\"_\"",
+            };
+            format!(
+                "<style:tab-stop style:position=\"{}\" style:type=\"{}\"{}/>",
+                twips(tab.position - left),
+                kind,
+                leader
+            )
+        })
+        .collect()
+}
+
+fn run_style_properties(run: &RunStyle) -> String {
+    text_properties(
+        run.font.as_deref(),
+        run.size,
+        run.bold,
+        run.italic,
+        run.color.as_deref(),
+        run.character_spacing,
+    )
+}
+
+fn run_props_properties(props: &RunProps) -> String {
+    let mut xml = text_properties(
+        props.font.as_deref(),
+        props.size,
+        props.bold,
+        props.italic,
+        props.color.as_deref(),
+        props.character_spacing,
+    );
+    let underline = match props.underline {
+        Some(UnderlineType::Single) => Some("solid\" style:text-underline-type=\"single"),
+        Some(UnderlineType::Double) => Some("solid\" style:text-underline-type=\"double"),
+        Some(UnderlineType::Dash) => Some("dash"),
+        Some(UnderlineType::Dotted) => Some("dotted"),
+        Some(UnderlineType::Wave) => Some("wave"),
+        Some(UnderlineType::None) | None => None,
+    };
+    if let Some(underline) = underline {
+        xml.push_str(&format!(
+            " style:text-underline-style=\"{}\" style:text-underline-width=\"auto\"",
+            underline
+        ));
+    }
+    if props.strike == Some(true) {
+        xml.push_str(" style:text-line-through-style=\"solid\"");
+    }
+    if let Some(highlight) = &props.highlight {
+        xml.push_str(&format!(
+            " fo:background-color=\"#{}\"",
+            highlight_rgb(highlight)
+        ));
+    } else if let Some(shading) = &props.shading {
+        xml.push_str(&format!(
+            " fo:background-color=\"#{}\"",
+            shading.trim_start_matches('#')
+        ));
+    }
+    if let Some(border) = &props.border {
+        xml.push_str(&format!(
+            " fo:border=\"0.5pt solid #{}\"",
+            border.trim_start_matches('#')
+        ));
+    }
+    if props.superscript == Some(true) {
+        xml.push_str(" style:text-position=\"super 58%\"");
+    } else if props.subscript == Some(true) {
+        xml.push_str(" style:text-position=\"sub 58%\"");
+    }
+    xml
+}
+
+fn text_properties(
+    font: Option<&str>,
+    size: Option<usize>,
+    bold: Option<bool>,
+    italic: Option<bool>,
+    color: Option<&str>,
+    character_spacing: Option<i32>,
+) -> String {
+    let mut xml = String::new();
+    if let Some(font) = font {
+        let font = escape_xml(font);
+        xml.push_str(&format!(
+            " fo:font-family=\"{}\" style:font-family-asian=\"{}\"",
+            font, font
+        ));
+    }
+    if let Some(size) = size {
+        xml.push_str(&format!(
+            " fo:font-size=\"{}pt\" style:font-size-asian=\"{}pt\"",
+            size, size
+        ));
+    }
+    if bold == Some(true) {
+        xml.push_str(" fo:font-weight=\"bold\" style:font-weight-asian=\"bold\"");
+    }
+    if italic == Some(true) {
+        xml.push_str(" fo:font-style=\"italic\" style:font-style-asian=\"italic\"");
+    }
+    if let Some(color) = color {
+        xml.push_str(&format!(
+            " fo:color=\"#{}\"",
+            escape_xml(color.trim_start_matches('#'))
+        ));
+    }
+    // 字符间距单位为 1/20 磅
+    if let Some(spacing) = character_spacing.filter(|s| *s != 0) {
+        xml.push_str(&format!(
+            " fo:letter-spacing=\"{:.2}pt\"",
+            spacing as f32 / 20.0
+        ));
+    }
+    xml
+}
+
+// Word 高亮色名对应的 RGB，不认识的按黄色处理
+pub(crate) fn highlight_rgb(name: &str) -> &'static str {
+    match name.to_lowercase().as_str() {
+        "green" => "00ff00",
+        "cyan" => "00ffff",
+        "magenta" => "ff00ff",
+        "red" => "ff0000",
+        "blue" => "0000ff",
+        "lightgray" => "c0c0c0",
+        _ => "ffff00",
+    }
+}
+
+// twips（1/1440 英寸）转为英寸长度
+fn twips(value: i32) -> String {
+    format!("{:.4}in", value as f32 / 1440.0)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 转义文本，并把 ODF 会折叠的连续空格、制表符和换行写成对应元素
+fn escape_text(text: &str) -> String {
+    let mut xml = String::new();
+    let mut spaces = 0;
+    let mut at_start = true;
+    let flush = |xml: &mut String, spaces: &mut usize, at_start: bool| {
+        match *spaces {
+            0 => {}
+            1 if !at_start => xml.push(' '),
+            n => xml.push_str(&format!("<text:s text:c=\"{}\"/>", n)),
+        }
+        *spaces = 0;
+    };
+    for c in text.chars() {
+        if c == ' ' {
+            spaces += 1;
+            continue;
+        }
+        flush(&mut xml, &mut spaces, at_start);
+        at_start = false;
+        match c {
+            '\n' => xml.push_str("<text:line-break/>"),
+            '\t' => xml.push_str("<text:tab/>"),
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            c => xml.push(c),
+        }
+    }
+    flush(&mut xml, &mut spaces, at_start);
+    xml
+}
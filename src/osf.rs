@@ -0,0 +1,363 @@
+//! Open Screenplay Format（Fade In 使用的 XML 格式，Celtx 也可读取）导入导出
+//!
+//! 导出时每个 token 对应一个 `<para>`，段落类型写在 `<style basestylename="...">` 中，
+//! 粗体、斜体、下划线写成 `<text>` 的属性，场景编号写在 `<para number="...">` 上，
+//! 分页写成 `page_break="1"`。章节、概要和注解在 OSF 中没有对应段落，导出时忽略。
+//!
+//! 导入时把段落还原为 Fountain 文本再解析，需要时加上强制标记（`.`、`@`、`>`、`!`），
+//! 保证每段的类型与原文一致。`.fadein` 文件是内含 `document.xml` 的 zip 包。
+
+use crate::fdx::{escape_xml, styled_runs};
+use crate::models::{Conf, ScriptToken};
+use crate::parser::{FountainParser, ParseOutput};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::io::{Cursor, Read, Write};
+use thiserror::Error;
+
+lazy_static! {
+    static ref PARA_REGEX: Regex = Regex::new(r"(?s)<para\b([^>]*?)(?:/>|>(.*?)</para>)").unwrap();
+    static ref STYLE_REGEX: Regex = Regex::new(r"<style\b([^>]*)/?>").unwrap();
+    static ref TEXT_REGEX: Regex = Regex::new(r"(?s)<text\b([^>]*?)(?:/>|>(.*?)</text>)").unwrap();
+    static ref ATTR_REGEX: Regex = Regex::new(r#"([\w:-]+)\s*=\s*"([^"]*)""#).unwrap();
+    static ref TITLE_PAGE_REGEX: Regex =
+        Regex::new(r"(?s)<titlepage\b[^>]*>(.*?)</titlepage>").unwrap();
+    static ref PARAGRAPHS_REGEX: Regex =
+        Regex::new(r"(?s)<paragraphs\b[^>]*>(.*?)</paragraphs>").unwrap();
+    static ref HEADING_PREFIX_REGEX: Regex =
+        Regex::new(r"(?i)^(INT|EXT|EST|INT\.?/EXT|I/E)[\. ]").unwrap();
+}
+
+/// OSF 导入导出错误
+#[derive(Error, Debug)]
+pub enum OsfError {
+    #[error("IO错误: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("ZIP错误: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("不是有效的 Open Screenplay Format 文档: {0}")]
+    Invalid(String),
+}
+
+/// 生成 OSF 文档内容
+pub fn generate_osf(output: &ParseOutput) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<document type=\"Open Screenplay Format document\" version=\"40\">\n");
+
+    xml.push_str(&title_page_xml(output));
+
+    xml.push_str("  <paragraphs>\n");
+    let mut page_break = false;
+    for token in &output.tokens {
+        let style = match token.token_type.as_str() {
+            "scene_heading" => "Scene Heading",
+            "action" | "centered" => "Action",
+            "character" => "Character",
+            "dialogue" => "Dialogue",
+            "parenthetical" => "Parenthetical",
+            "transition" => "Transition",
+            "lyric" => "Lyrics",
+            "page_break" => {
+                page_break = true;
+                continue;
+            }
+            _ => continue,
+        };
+
+        let mut attrs = String::new();
+        if token.token_type == "scene_heading" {
+            if let Some(number) = &token.number {
+                attrs.push_str(&format!(
+                    " number=\"{}\"",
+                    escape_xml(number.trim_start_matches('↑'))
+                ));
+            }
+        }
+        if page_break {
+            attrs.push_str(" page_break=\"1\"");
+            page_break = false;
+        }
+        let mut style_attrs = format!(" basestylename=\"{}\"", style);
+        if token.token_type == "centered" {
+            style_attrs.push_str(" align=\"center\"");
+        }
+        if token.dual.as_deref() == Some("right") && token.token_type == "character" {
+            style_attrs.push_str(" dual=\"1\"");
+        }
+        xml.push_str(&format!(
+            "    <para{}><style{}/>{}</para>\n",
+            attrs,
+            style_attrs,
+            text_xml(&token.text)
+        ));
+    }
+    xml.push_str("  </paragraphs>\n");
+    xml.push_str("</document>\n");
+    xml
+}
+
+/// 标题页：按 title_page 的位置依次输出，位置决定段落对齐方式
+fn title_page_xml(output: &ParseOutput) -> String {
+    if output.title_page.values().all(|tokens| tokens.is_empty()) {
+        return String::new();
+    }
+    let mut xml = String::from("  <titlepage>\n");
+    for (position, alignment) in [
+        ("tl", "left"),
+        ("tc", "center"),
+        ("tr", "right"),
+        ("cc", "center"),
+        ("bl", "left"),
+        ("br", "right"),
+    ] {
+        let Some(tokens) = output.title_page.get(position) else {
+            continue;
+        };
+        let mut tokens: Vec<&ScriptToken> = tokens.iter().collect();
+        tokens.sort_by_key(|t| t.index);
+        for token in tokens {
+            let style = if token.token_type == "title" {
+                "Title"
+            } else {
+                "Normal"
+            };
+            xml.push_str(&format!(
+                "    <para><style basestylename=\"{}\" align=\"{}\"/>{}</para>\n",
+                style,
+                alignment,
+                text_xml(&token.text)
+            ));
+        }
+    }
+    xml.push_str("  </titlepage>\n");
+    xml
+}
+
+fn text_xml(text: &str) -> String {
+    let mut xml = String::new();
+    for run in styled_runs(text) {
+        let mut attrs = String::new();
+        if run.bold {
+            attrs.push_str(" bold=\"1\"");
+        }
+        if run.italic {
+            attrs.push_str(" italic=\"1\"");
+        }
+        if run.underline {
+            attrs.push_str(" underline=\"1\"");
+        }
+        xml.push_str(&format!("<text{}>{}</text>", attrs, escape_xml(&run.text)));
+    }
+    if xml.is_empty() {
+        xml.push_str("<text></text>");
+    }
+    xml
+}
+
+/// 把 OSF 文档转换为 Fountain 文本
+pub fn osf_to_fountain(xml: &str) -> Result<String, OsfError> {
+    let paragraphs = PARAGRAPHS_REGEX
+        .captures(xml)
+        .and_then(|c| c.get(1))
+        .ok_or_else(|| OsfError::Invalid("缺少 <paragraphs> 元素".to_string()))?;
+
+    let mut blocks: Vec<String> = Vec::new();
+
+    // 标题页只能可靠地还原标题，其余字段的含义在 OSF 中没有约定
+    if let Some(title_page) = TITLE_PAGE_REGEX.captures(xml).and_then(|c| c.get(1)) {
+        let title = PARA_REGEX
+            .captures_iter(title_page.as_str())
+            .map(|c| paragraph_text(c.get(2).map_or("", |m| m.as_str())))
+            .find(|t| !t.trim().is_empty());
+        if let Some(title) = title {
+            blocks.push(format!("Title: {}", title.trim()));
+        }
+    }
+
+    // 角色、括号和对话连在一起组成一个块
+    let mut in_dialogue = false;
+    for captures in PARA_REGEX.captures_iter(paragraphs.as_str()) {
+        let para_attrs = attributes(captures.get(1).map_or("", |m| m.as_str()));
+        let body = captures.get(2).map_or("", |m| m.as_str());
+        let style_attrs = STYLE_REGEX
+            .captures(body)
+            .map(|c| attributes(&c[1]))
+            .unwrap_or_default();
+        let style = get_attr(&style_attrs, "basestylename")
+            .or_else(|| get_attr(&style_attrs, "basestyle"))
+            .unwrap_or("Action")
+            .to_lowercase();
+        let text = paragraph_text(body);
+
+        if get_attr(&para_attrs, "page_break") == Some("1") {
+            blocks.push("===".to_string());
+            in_dialogue = false;
+        }
+
+        match style.as_str() {
+            "character" => {
+                let name = text.trim();
+                let forced = if name.chars().any(|c| c.is_lowercase())
+                    || !name.chars().any(|c| c.is_uppercase())
+                {
+                    "@"
+                } else {
+                    ""
+                };
+                let dual = if get_attr(&style_attrs, "dual") == Some("1") {
+                    " ^"
+                } else {
+                    ""
+                };
+                blocks.push(format!("{}{}{}", forced, name, dual));
+                in_dialogue = true;
+            }
+            "dialogue" | "parenthetical" if in_dialogue => {
+                if let Some(block) = blocks.last_mut() {
+                    block.push('\n');
+                    block.push_str(text.trim());
+                }
+            }
+            "scene heading" => {
+                let heading = text.trim();
+                let forced = if HEADING_PREFIX_REGEX.is_match(heading) {
+                    ""
+                } else {
+                    "."
+                };
+                let number = get_attr(&para_attrs, "number")
+                    .filter(|n| !n.is_empty())
+                    .map(|n| format!(" #{}#", n))
+                    .unwrap_or_default();
+                blocks.push(format!("{}{}{}", forced, heading, number));
+                in_dialogue = false;
+            }
+            "transition" => {
+                let transition = text.trim();
+                let natural =
+                    transition.ends_with("TO:") && !transition.chars().any(|c| c.is_lowercase());
+                blocks.push(if natural {
+                    transition.to_string()
+                } else {
+                    format!("> {}", transition)
+                });
+                in_dialogue = false;
+            }
+            "lyrics" => {
+                let lyric: Vec<String> = text.lines().map(|l| format!("~{}", l)).collect();
+                blocks.push(lyric.join("\n"));
+                in_dialogue = false;
+            }
+            _ => {
+                if text.trim().is_empty() {
+                    in_dialogue = false;
+                    continue;
+                }
+                if get_attr(&style_attrs, "align") == Some("center") {
+                    blocks.push(format!("> {} <", text.trim()));
+                } else {
+                    blocks.push(forced_action(&text));
+                }
+                in_dialogue = false;
+            }
+        }
+    }
+
+    let mut fountain = blocks.join("\n\n");
+    fountain.push('\n');
+    Ok(fountain)
+}
+
+/// 读取 OSF 文档并解析为剧本
+pub fn import_osf(xml: &str, config: &Conf) -> Result<ParseOutput, OsfError> {
+    let fountain = osf_to_fountain(xml)?;
+    let parser = FountainParser::new();
+    Ok(parser.parse(&fountain, config, false, None))
+}
+
+/// 读取 .fadein 文件（zip 包）中的 OSF 文档
+pub fn read_fadein(path: &str) -> Result<String, OsfError> {
+    let file = std::fs::File::open(path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entry = archive.by_name("document.xml")?;
+    let mut xml = String::new();
+    entry.read_to_string(&mut xml)?;
+    Ok(xml)
+}
+
+/// 把剧本写成 .fadein 文件
+pub fn write_fadein(output: &ParseOutput, path: &str) -> Result<(), OsfError> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("document.xml", zip::write::FileOptions::default())?;
+    zip.write_all(generate_osf(output).as_bytes())?;
+    std::fs::write(path, zip.finish()?.into_inner())?;
+    Ok(())
+}
+
+// 动作行看起来像角色名、场景标题或转场时加 `!` 强制为动作
+fn forced_action(text: &str) -> String {
+    let first = text.lines().next().unwrap_or_default().trim();
+    let looks_special = HEADING_PREFIX_REGEX.is_match(first)
+        || first.starts_with(['.', '@', '>', '~', '!', '#', '=', '['])
+        || (first.chars().any(|c| c.is_uppercase()) && !first.chars().any(|c| c.is_lowercase()));
+    if looks_special {
+        format!("!{}", text.trim_end())
+    } else {
+        text.trim_end().to_string()
+    }
+}
+
+// 段落中所有 <text> 的内容，带样式的片段写回 Fountain 的强调标记
+fn paragraph_text(body: &str) -> String {
+    let mut text = String::new();
+    for captures in TEXT_REGEX.captures_iter(body) {
+        let attrs = attributes(captures.get(1).map_or("", |m| m.as_str()));
+        let content = unescape_xml(captures.get(2).map_or("", |m| m.as_str()));
+        if content.is_empty() {
+            continue;
+        }
+        let mut marker = String::new();
+        if get_attr(&attrs, "bold") == Some("1") {
+            marker.push_str("**");
+        }
+        if get_attr(&attrs, "italic") == Some("1") {
+            marker.push('*');
+        }
+        let underline = get_attr(&attrs, "underline") == Some("1");
+        if underline {
+            text.push('_');
+        }
+        text.push_str(&marker);
+        text.push_str(&content);
+        text.push_str(&marker);
+        if underline {
+            text.push('_');
+        }
+    }
+    text
+}
+
+fn attributes(text: &str) -> Vec<(String, String)> {
+    ATTR_REGEX
+        .captures_iter(text)
+        .map(|c| (c[1].to_lowercase(), unescape_xml(&c[2])))
+        .collect()
+}
+
+fn get_attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,153 @@
+//! 大纲导出
+//!
+//! 把 `ScreenplayProperties.structure` 中的章节、场景、注解和书签导出为 JSON 或 OPML，
+//! 每个节点带有预估时长，方便在大纲软件中审阅剧本结构。
+
+use crate::models::StructToken;
+use crate::parser::ParseOutput;
+use crate::utils::strip_style_chars;
+use serde::{Deserialize, Serialize};
+
+/// 大纲导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutlineFormat {
+    Json,
+    Opml,
+}
+
+/// 大纲节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlineNode {
+    /// section / scene / note / bookmark
+    pub kind: String,
+    pub title: String,
+    /// 在剧本中的行号（从 0 开始）
+    pub line: Option<usize>,
+    pub level: usize,
+    /// 预估时长（秒），章节为其下所有场景之和
+    pub duration_sec: f64,
+    /// 在影片中的开始时间（秒）
+    pub play_sec: f64,
+    pub synopses: Vec<String>,
+    pub notes: Vec<String>,
+    pub children: Vec<OutlineNode>,
+}
+
+/// 从结构树生成大纲，对白角色节点不计入大纲
+pub fn build_outline(structure: &[StructToken]) -> Vec<OutlineNode> {
+    let mut nodes: Vec<OutlineNode> = structure
+        .iter()
+        .filter(|t| !t.ischartor)
+        .map(build_node)
+        .collect();
+    // 注解和书签在解析结束时才追加到顶层，按行号重新排序
+    nodes.sort_by_key(|n| n.line.unwrap_or(usize::MAX));
+    nodes
+}
+
+fn build_node(token: &StructToken) -> OutlineNode {
+    let kind = if token.section {
+        "section"
+    } else if token.isscene {
+        "scene"
+    } else if token.isnote {
+        "note"
+    } else if token.is_bookmark {
+        "bookmark"
+    } else {
+        "other"
+    };
+
+    let children: Vec<OutlineNode> = token
+        .children
+        .iter()
+        .filter(|t| !t.ischartor)
+        .map(build_node)
+        .collect();
+
+    let duration_sec = if token.section {
+        children.iter().map(|c| c.duration_sec).sum()
+    } else {
+        token.duration_sec
+    };
+
+    OutlineNode {
+        kind: kind.to_string(),
+        title: strip_style_chars(&token.text)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        line: token.range.as_ref().map(|r| r.start.line),
+        level: token.level,
+        duration_sec,
+        play_sec: token.play_sec,
+        synopses: token.synopses.iter().map(|s| s.synopsis.clone()).collect(),
+        notes: token.notes.iter().map(|n| n.note.clone()).collect(),
+        children,
+    }
+}
+
+/// 导出大纲
+pub fn export_outline(output: &ParseOutput, format: OutlineFormat) -> String {
+    let nodes = build_outline(&output.properties.structure);
+    match format {
+        OutlineFormat::Json => serde_json::to_string_pretty(&nodes).unwrap_or_else(|_| "[]".to_string()),
+        OutlineFormat::Opml => {
+            let title = output
+                .title_page
+                .values()
+                .flatten()
+                .find(|t| t.token_type == "title")
+                .map(|t| strip_style_chars(&t.text).trim().to_string())
+                .unwrap_or_default();
+            let mut opml = String::new();
+            opml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            opml.push_str("<opml version=\"2.0\">\n");
+            opml.push_str(&format!("  <head>\n    <title>{}</title>\n  </head>\n", escape_xml(&title)));
+            opml.push_str("  <body>\n");
+            for node in &nodes {
+                write_opml_node(&mut opml, node, 2);
+            }
+            opml.push_str("  </body>\n</opml>\n");
+            opml
+        }
+    }
+}
+
+fn write_opml_node(opml: &mut String, node: &OutlineNode, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let mut attrs = format!(
+        "text=\"{}\" _type=\"{}\" _duration=\"{:.1}\" _playSec=\"{:.1}\"",
+        escape_xml(&node.title),
+        node.kind,
+        node.duration_sec,
+        node.play_sec
+    );
+    if let Some(line) = node.line {
+        attrs.push_str(&format!(" _line=\"{}\"", line));
+    }
+    // 概要放在 _note 中，OmniOutliner、Scrivener 等会把它显示为节点备注
+    let mut note_lines = node.synopses.clone();
+    note_lines.extend(node.notes.iter().cloned());
+    if !note_lines.is_empty() {
+        attrs.push_str(&format!(" _note=\"{}\"", escape_xml(&note_lines.join("\n"))));
+    }
+
+    if node.children.is_empty() {
+        opml.push_str(&format!("{}<outline {}/>\n", indent, attrs));
+    } else {
+        opml.push_str(&format!("{}<outline {}>\n", indent, attrs));
+        for child in &node.children {
+            write_opml_node(opml, child, depth + 1);
+        }
+        opml.push_str(&format!("{}</outline>\n", indent));
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+}
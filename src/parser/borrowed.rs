@@ -0,0 +1,227 @@
+//! 借用原文的 token
+//!
+//! `ParseOutput` 中每个 token 都持有自己的 `String`，同时还保留了行信息、HTML 等中间结果，
+//! 大剧本解析后常驻内存中的大部分是这些副本。`BorrowedOutput` 只保留 token 的常用字段，
+//! 文本尽量直接借用原文（`Cow::Borrowed`），只有被解析器改写过、在原文中找不到的文本（去掉了样式标记、
+//! 替换了变量、合并了多行等）才单独分配；token 类型使用静态字符串。
+//! 适合只需要遍历 token 的场景（统计、检索、索引等）。
+//!
+//! [`FountainParser::parse_borrowed`] 让解析器每遇到场景标题就交出之前的 token，当场转换后释放，
+//! 解析过程中同时存在的完整 `ScriptToken` 只有一个场景的量，峰值内存也低于 [`FountainParser::parse`]。
+//! 已有的 `ParseOutput` 也可以用 [`BorrowedOutput::new`] 转换，这时只减少转换后常驻的内存。
+
+use crate::models::{Conf, Diagnostic, ScreenplayProperties, ScriptToken, SourceRange};
+use crate::parser::fountain_parser::{finish_tokens, FinishToken};
+use crate::parser::{FountainParser, ParseOutput};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+// 常见的 token 类型和双对白位置，转换时直接使用静态字符串
+const STATIC_NAMES: &[&str] = &[
+    "action",
+    "author",
+    "authors",
+    "boneyard_begin",
+    "boneyard_end",
+    "centered",
+    "character",
+    "dialogue",
+    "dialogue_begin",
+    "dialogue_end",
+    "draft_date",
+    "dual_dialogue_begin",
+    "dual_dialogue_end",
+    "image",
+    "lyric",
+    "metadata",
+    "note",
+    "page_break",
+    "page_switch",
+    "parenthetical",
+    "redraw",
+    "scene_heading",
+    "section",
+    "separator",
+    "source",
+    "synopsis",
+    "title",
+    "transition",
+    "left",
+    "right",
+];
+
+/// 借用原文文本的 token，字段含义与 [`ScriptToken`] 相同
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorrowedToken<'a> {
+    pub token_type: Cow<'static, str>,
+    pub text: Cow<'a, str>,
+    pub line: usize,
+    pub source_range: Option<SourceRange>,
+    pub character: Option<Cow<'a, str>>,
+    pub number: Option<Cow<'a, str>>,
+    pub dual: Option<Cow<'static, str>>,
+    pub is_dual_dialogue: bool,
+    pub duration_sec: Option<f64>,
+    pub time: Option<f64>,
+    pub level: Option<i32>,
+    pub ignore: bool,
+    /// 是否是删去的场景标题
+    pub omitted: bool,
+}
+
+impl<'a> BorrowedToken<'a> {
+    /// 由解析结果中的 token 转换，文本能在原文对应范围内找到时借用原文
+    pub fn new(source: &'a str, token: ScriptToken) -> Self {
+        // 原文中 token 所在的范围，没有范围时（辅助 token）不借用
+        let span = token
+            .source_range
+            .and_then(|range| source.get(range.byte_start..range.byte_end))
+            .unwrap_or("");
+        let omitted = token.is_omitted();
+        BorrowedToken {
+            token_type: intern(token.token_type),
+            text: borrow_from(span, token.text),
+            line: token.line,
+            source_range: token.source_range,
+            character: token.character.map(|c| borrow_from(span, c)),
+            number: token.number.map(|n| borrow_from(span, n)),
+            dual: token.dual.map(intern),
+            is_dual_dialogue: token.is_dual_dialogue,
+            duration_sec: token.duration_sec,
+            time: token.time,
+            level: token.level,
+            ignore: token.ignore,
+            omitted,
+        }
+    }
+
+    pub fn is_type(&self, types: &[&str]) -> bool {
+        types.contains(&self.token_type.as_ref())
+    }
+
+    /// 文本是否直接借用原文
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.text, Cow::Borrowed(_))
+    }
+}
+
+/// 借用原文的解析结果
+#[derive(Debug, Clone)]
+pub struct BorrowedOutput<'a> {
+    /// 解析的原文
+    pub source: &'a str,
+    pub tokens: Vec<BorrowedToken<'a>>,
+    pub title_page: HashMap<String, Vec<BorrowedToken<'a>>>,
+    pub properties: ScreenplayProperties,
+    pub length_action: f64,
+    pub length_dialogue: f64,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> BorrowedOutput<'a> {
+    /// 由 `source` 的解析结果转换，`output` 必须是解析 `source` 得到的
+    pub fn new(source: &'a str, output: ParseOutput) -> Self {
+        // 不用 collect：collect 会原地复用 `Vec<ScriptToken>` 较大的内存
+        let convert = |tokens: Vec<ScriptToken>| -> Vec<BorrowedToken<'a>> {
+            let mut converted = Vec::with_capacity(tokens.len());
+            converted.extend(
+                tokens
+                    .into_iter()
+                    .map(|token| BorrowedToken::new(source, token)),
+            );
+            converted
+        };
+        BorrowedOutput {
+            source,
+            tokens: convert(output.tokens),
+            title_page: output
+                .title_page
+                .into_iter()
+                .map(|(key, tokens)| (key, convert(tokens)))
+                .collect(),
+            properties: output.properties,
+            length_action: output.length_action,
+            length_dialogue: output.length_dialogue,
+            diagnostics: output.diagnostics,
+        }
+    }
+
+    /// 文本单独分配（没有借用原文）的 token 数量
+    pub fn owned_count(&self) -> usize {
+        self.tokens.iter().filter(|t| !t.is_borrowed()).count()
+    }
+}
+
+impl FinishToken for BorrowedToken<'_> {
+    fn token_kind(&self) -> &str {
+        &self.token_type
+    }
+
+    fn token_text(&self) -> &str {
+        &self.text
+    }
+
+    fn omitted(&self) -> bool {
+        self.omitted
+    }
+
+    // 借用的 token 不保留 characters_action
+    fn add_action_character(&mut self, _name: String) {}
+
+    fn typeset(&mut self, language: &str) {
+        let text = crate::typography::typeset(&self.text, language);
+        if text != self.text {
+            self.text = Cow::Owned(text);
+        }
+    }
+}
+
+impl FountainParser {
+    /// 解析剧本，返回借用原文的结果；不生成 HTML，也不计算统计信息
+    ///
+    /// token 在解析过程中逐场景转换，见模块说明
+    pub fn parse_borrowed<'a>(&self, script: &'a str, cfg: &Conf) -> BorrowedOutput<'a> {
+        let mut tokens = Vec::new();
+        let mut output = self.parse_streaming(script, cfg, &mut |token| {
+            tokens.push(BorrowedToken::new(script, token))
+        });
+        finish_tokens(&mut output.properties, &mut tokens, cfg);
+        let convert = |tokens: Vec<ScriptToken>| -> Vec<BorrowedToken<'a>> {
+            tokens
+                .into_iter()
+                .map(|token| BorrowedToken::new(script, token))
+                .collect()
+        };
+        BorrowedOutput {
+            source: script,
+            tokens,
+            title_page: output
+                .title_page
+                .into_iter()
+                .map(|(key, tokens)| (key, convert(tokens)))
+                .collect(),
+            properties: output.properties,
+            length_action: output.length_action,
+            length_dialogue: output.length_dialogue,
+            diagnostics: output.diagnostics,
+        }
+    }
+}
+
+fn intern(name: String) -> Cow<'static, str> {
+    match STATIC_NAMES.iter().find(|n| **n == name) {
+        Some(n) => Cow::Borrowed(*n),
+        None => Cow::Owned(name),
+    }
+}
+
+// 在原文范围内找到相同的文本时借用原文，否则保留已有的 String
+fn borrow_from(span: &str, text: String) -> Cow<'_, str> {
+    if text.is_empty() {
+        return Cow::Borrowed("");
+    }
+    match span.find(text.as_str()) {
+        Some(pos) => Cow::Borrowed(&span[pos..pos + text.len()]),
+        None => Cow::Owned(text),
+    }
+}
@@ -1,12 +1,63 @@
 use crate::models::{
-    Conf, Location, Note, Position, Range, ScreenplayProperties, ScriptToken, StructToken, Synopsis,
+    Conf, Diagnostic, DiagnosticSeverity, Location, Note, Position, Range, ScreenplayProperties,
+    ScriptToken, StructToken, Synopsis,
 };
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use crate::parser::include::{expand_includes, ExpandedScript, IncludeError};
+use crate::parser::source_map::SourceMap;
+use crate::duration::{self, CharRateModel, DurationModel};
+use crate::title_template::TitlePageTemplate;
+use crate::logging::phase;
+use crate::stats::normalize_time_of_day;
+use crate::progress::{Cancelled, ExportPhase, Progress};
+use std::sync::{Arc, Mutex, OnceLock};
+
+lazy_static! {
+    // 内置的转场写法，整行为大写的 "XXX TO:"
+    static ref BUILTIN_TRANSITION: Regex = Regex::new(r"^[A-Z ]+TO:$").unwrap();
+    // 默认配置下的正则只编译一次，各次解析共用
+    static ref BASE_REGEX: RegexMap = Arc::new(base_regex());
+    // 计算时长时不计入的空白、标点和符号
+    static ref NON_COUNTED_CHARS: Regex = Regex::new(r"\s|\p{P}|\p{S}").unwrap();
+    static ref FORCE_CHARACTER: Regex = Regex::new(r"^[ \t]*@").unwrap();
+    static ref CHARACTER_EXTENSION: Regex = Regex::new(r"[ \t]*(\(.*\))[ \t]*([ \t]*\^+)?$").unwrap();
+    static ref CHARACTER_EXTENSION_FULL_WIDTH: Regex =
+        Regex::new(r"[ \t]*(\(.*\)|（.*）)[ \t]*([ \t]*\^+)?$").unwrap();
+    static ref LOCATION_SEPARATOR: Regex = Regex::new(r"[\-–—−]").unwrap();
+    static ref SCENE_NUMBER_PLACEHOLDER: Regex = Regex::new(r"\{(n|act|act_roman)(?::(\d+))?\}").unwrap();
+    static ref METADATA_LINE: Regex = Regex::new(r"(?i)^\s*metadata\s*:(.*)").unwrap();
+    // 注释和注解的开始、结束符号
+    static ref NOTE_DELIMITERS: Regex = Regex::new(r"(\/\*\||\/\*|\*\/|\[\[\||\[\[|\]\])").unwrap();
+    static ref TITLE_PAGE_FONT: Regex =
+        Regex::new(r"(?i)^\s*(font|font italic|font bold|font bold italic|metadata)\:(.*)").unwrap();
+    static ref TITLE_PAGE_KEY: Regex = Regex::new(r"(?i)^(.*?↻)??\s*(title|credit|author[s]?|source|notes|draft date|date|watermark|contact(?: info)?|revision|copyright|image|tl|tc|tr|cc|br|bl|header|footer)\:(.*)").unwrap();
+    static ref STYLE_CHARS: Regex = Regex::new(&format!(
+        r"[{}]",
+        regex::escape(FountainConstants::style_chars()["all"])
+    ))
+    .unwrap();
+    static ref FORCED_SCENE_HEADING: Regex = Regex::new(r"^[ \t]*\.").unwrap();
+    static ref WHITESPACE: Regex = Regex::new(r"\s+").unwrap();
+    static ref FORCED_TRANSITION: Regex = Regex::new(r"^\s*>\s*").unwrap();
+    // 角色名后的双对白标记 ^（三人同时对白为 ^^），及其后跟注释、修订标记的情况
+    static ref DUAL_MARK: Regex = Regex::new(r"\^+\s*$").unwrap();
+    static ref DUAL_MARK_NOTE: Regex = Regex::new(r"(\^+\s*)(இ.*$)").unwrap();
+    static ref DUAL_MARK_REVISION: Regex = Regex::new(r"(\^+\s*)(↺.*$)").unwrap();
+    // 以下匹配已处理过注释、修订标记的显示文本，↻ 之前是行首的标记
+    static ref FORCED_CHARACTER_TEXT: Regex = Regex::new(r"^(.*?↻)?[ \t]*@").unwrap();
+    static ref FORCED_ACTION_TEXT: Regex = Regex::new(r"^((?:.*?↻)?\s*)(\!)(.*)").unwrap();
+    static ref CENTERED_TEXT: Regex =
+        Regex::new(r"((?:^.*?↻)|^)[ \t]*>\s*(.+)\s*?<\s*((?:இ.*$)|(?:↺.*$)|$)").unwrap();
+    static ref SECTION_TEXT: Regex = Regex::new(r"^((?:.*?↻)?\s*)(#+)(?:\s*)(.*)").unwrap();
+    static ref SYNOPSIS_TEXT: Regex = Regex::new(r"^((?:.*?↻)?\s*)(?:\=)(.*)").unwrap();
+    static ref LYRIC_TEXT: Regex = Regex::new(r"^((?:.*?↻)?\s*)(\~)(\s*)(.*)").unwrap();
+}
 
 /// 行结构体，用于存储处理后的行信息
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Line {
     /// 行类型
     pub token_type: String,
@@ -27,12 +78,15 @@ pub struct Line {
     /// 双对话位置: 'left' 或 'right'
     pub dual: Option<String>,
     pub level: Option<i32>, // 层级
+    /// 对白编号，打印对白编号时角色名的第一行才有
+    pub take_number: Option<i32>,
 }
+use crate::parser::spec::Extension;
 use crate::parser::text_processor::{
     generate_html, generate_title_html, process_token_text_style_char,
 };
 use crate::utils::fountain_constants::BLOCK_REGEX;
-use crate::utils::{is_blank_line_after_style, FountainConstants};
+use crate::utils::{is_blank_line_after_style, to_uppercase_locale, FountainConstants};
 
 // 扩展Vec类型添加pushSorted方法
 pub trait SortedList<T> {
@@ -70,7 +124,7 @@ pub struct TitleKeywordFormat {
     pub index: i32,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ParseOutput {
     pub tokens: Vec<ScriptToken>,
     pub properties: ScreenplayProperties,
@@ -88,6 +142,8 @@ pub struct ParseOutput {
     pub action_sec_per_char: f64,
     pub lines: Vec<Line>,
     pub statistics: Option<crate::statistics::Statistics>,
+    /// 解析过程中发现的问题（未闭合的注释/注解、重复场景编号等），完整检查见 `parser::lint`
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl ParseOutput {
@@ -109,6 +165,7 @@ impl ParseOutput {
             action_sec_per_char: 0.4,
             lines: Vec::new(),
             statistics: None,
+            diagnostics: Vec::new(),
         }
     }
 }
@@ -119,22 +176,186 @@ impl Default for ParseOutput {
     }
 }
 
+/// Fountain 解析器
+///
+/// 解析状态只在每次 `parse` 内部存在，同一个解析器可以反复解析，也可以在多个线程间共享（`&self` 即可解析）
+#[derive(Clone, Default)]
 pub struct FountainParser {
+    /// 通过 set_duration_model 接入的自定义对白时长模型，优先于配置
+    custom_duration_model: Option<Arc<dyn DurationModel>>,
+}
+
+impl FountainParser {
+    pub fn new() -> Self {
+        FountainParser {
+            custom_duration_model: None,
+        }
+    }
+
+    // 去除空格、标点和特殊字符
+    pub fn calculate_chars(&self, text: &str) -> String {
+        strip_non_counted_chars(text)
+    }
+
+    // 计算动作持续时间
+    pub fn calculate_action_duration(&self, text: &str, config_x: Option<f64>) -> f64 {
+        action_duration(text, config_x)
+    }
+
+    /// 接入自定义的对白时长模型，之后的解析都用它估算对白时长
+    pub fn set_duration_model(&mut self, model: Arc<dyn DurationModel>) {
+        self.custom_duration_model = Some(model);
+    }
+
+    /// 解析器自身影响解析结果的设置（目前只有自定义的对白时长模型）的标识，没有时为空
+    pub fn fingerprint(&self) -> String {
+        self.custom_duration_model
+            .as_ref()
+            .map(|model| model.fingerprint())
+            .unwrap_or_default()
+    }
+
+    // 计算对话持续时间
+    // 接入了自定义模型时由模型估算，否则按字符数估算，参数为每字符和长短标点的耗时
+    pub fn calculate_dialogue_duration(
+        &self,
+        text: &str,
+        config_x: Option<f64>,
+        config_long: Option<f64>,
+        config_short: Option<f64>,
+    ) -> f64 {
+        match &self.custom_duration_model {
+            Some(model) => model.dialogue_duration(text, None),
+            None => char_rate_duration(text, config_x, config_long, config_short),
+        }
+    }
+
+    /// 读取剧本文件并解析，先展开其中的 `{include: ...}` 引用，
+    /// 每个 token 的 `origin` 记录它在原始文件中的位置，`source_range` 也是在该文件原文中的范围
+    pub fn parse_file(
+        &self,
+        path: &Path,
+        cfg: &Conf,
+        generate_html: bool,
+        calc_statistics: Option<bool>,
+    ) -> Result<ParseOutput, IncludeError> {
+        let expanded = expand_includes(path)?;
+        Ok(self.parse_expanded(&expanded, cfg, generate_html, calc_statistics))
+    }
+
+    /// 解析已展开引用的剧本，见 [`FountainParser::parse_file`]
+    pub(crate) fn parse_expanded(
+        &self,
+        expanded: &ExpandedScript,
+        cfg: &Conf,
+        generate_html: bool,
+        calc_statistics: Option<bool>,
+    ) -> ParseOutput {
+        let mut output = self.parse(&expanded.script, cfg, generate_html, calc_statistics);
+        let source_map = SourceMap::new(&expanded.script);
+        let tokens = output
+            .tokens
+            .iter_mut()
+            .chain(output.title_page.values_mut().flatten());
+        for token in tokens {
+            token.origin = expanded.origins.get(token.line).cloned();
+            if token.source_range.is_some() {
+                let last = token.line + token.text.matches('\n').count();
+                token.source_range = source_map.rebase(expanded, token.line, last);
+            }
+            // 插图的相对路径以所在文件的目录为准，与输出位置和当前目录无关
+            if let (Some(origin), Some(metadata)) = (&token.origin, token.metadata.as_mut()) {
+                if token.token_type == "image" {
+                    if let Some(src) = metadata.get_mut("src") {
+                        if Path::new(src.as_str()).is_relative() {
+                            let dir = Path::new(&origin.file).parent().unwrap_or(Path::new(""));
+                            *src = dir.join(&*src).to_string_lossy().to_string();
+                        }
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    /// 解析Fountain格式文本
+    ///
+    /// # Arguments
+    ///
+    /// * `script` - Fountain 格式的剧本文本
+    /// * `cfg` - 配置对象
+    /// * `generate_html` - 是否生成 HTML 输出
+    /// * `calc_statistics` - 是否计算统计数据（可选，默认 false）
+    pub fn parse(
+        &self,
+        script: &str,
+        cfg: &Conf,
+        generate_html: bool,
+        calc_statistics: Option<bool>,
+    ) -> ParseOutput {
+        // 默认的 Progress 不会被取消
+        self.parse_with_progress(
+            script,
+            cfg,
+            generate_html,
+            calc_statistics,
+            &Progress::default(),
+        )
+        .unwrap_or_default()
+    }
+
+    /// 与 `parse` 相同，解析过程中通过 `progress` 报告处理到第几行；被取消时返回 `Cancelled`
+    pub fn parse_with_progress(
+        &self,
+        script: &str,
+        cfg: &Conf,
+        generate_html: bool,
+        calc_statistics: Option<bool>,
+        progress: &Progress,
+    ) -> Result<ParseOutput, Cancelled> {
+        let _phase = phase!("parse");
+        let mut state = ParseState::new(self.custom_duration_model.clone());
+        state.progress = progress.clone();
+        let output = state.parse(script, cfg, generate_html, calc_statistics, None);
+        progress.check()?;
+        Ok(output)
+    }
+
+    /// 解析剧本，token 逐场景交给 `sink` 而不是留在返回的结果中，见 `ParseState::parse`
+    pub(crate) fn parse_streaming(
+        &self,
+        script: &str,
+        cfg: &Conf,
+        sink: &mut dyn FnMut(ScriptToken),
+    ) -> ParseOutput {
+        let _phase = phase!("parse");
+        let mut state = ParseState::new(self.custom_duration_model.clone());
+        state.parse(script, cfg, false, Some(false), Some(sink))
+    }
+}
+
+// 一次解析的状态
+struct ParseState {
     result: ParseOutput,
     length_action_so_far: f64,
     length_dialogue_so_far: f64,
     current_depth: usize,
     scene_number: usize,
+    // 当前幕（一级章节）的序号和幕内的场景序号，用于按幕编号
+    act_number: usize,
+    act_scene_number: usize,
     play_time_sec: f64,
     last_scen_structure_token_index: Option<usize>,
     last_scen_structure_token_index_pre: Option<usize>,
     last_scen_in_children: bool,
     last_scen_id: Option<String>,
     last_scen_id_pre: Option<String>,
-            previous_character: Option<String>,
+    previous_character: Option<String>,
     last_chartor_structure_token: Option<StructToken>,
     force_not_dual: bool,
     take_count: usize,
+    /// 按角色编号时每个角色已有的对白段数
+    character_take_counts: HashMap<String, usize>,
     lines_length: usize,
     current_cursor: usize,
     new_line_length: usize,
@@ -146,24 +367,45 @@ pub struct FountainParser {
     current_outline_note_linenum: Vec<usize>,
     nested_comments: i32,
     nested_notes: i32,
+    /// 当前未闭合的 /* 和 [[ 的起始位置
+    comment_open_at: Option<Position>,
+    note_open_at: Option<Position>,
     need_process_outline_note: i32,
+    /// 当前注解是否打印，按注解标签在 [[ 处决定
+    print_current_note: bool,
     // Bookmark fields
     bookmark_text: Vec<String>,
     bookmark_linenum: Vec<usize>,
     bookmark_started: bool,
     text_bookmark: String,
-    regex: HashMap<String, Regex>,
+    regex: RegexMap,
     title_page_display: HashMap<String, TitleKeywordFormat>,
+    /// 解析器接入的自定义对白时长模型，优先于配置
+    custom_duration_model: Option<Arc<dyn DurationModel>>,
+    /// 本次解析使用的对白时长模型，为 None 时按字符数估算
+    duration_model: Option<Arc<dyn DurationModel>>,
+    /// 构建对白时长模型的配置，会被标题页 metadata 覆盖
+    duration_conf: Conf,
+    /// 标题页 metadata 中 "rates" 指定的角色对白每字符耗时（秒），换算为语速倍率后覆盖配置中同一角色的 speaking_rates
+    character_sec_per_char: HashMap<String, f64>,
+    /// 标题页 metadata 中 "omitted" 列出的删去场景的编号
+    omitted_scene_numbers: HashSet<String>,
+    /// 已编入 `regex` 的配置值
+    regex_key: RegexKey,
+    /// 进度回调与取消令牌
+    progress: Progress,
 }
 
-impl FountainParser {
-    pub fn new() -> Self {
-        let mut parser = FountainParser {
+impl ParseState {
+    fn new(custom_duration_model: Option<Arc<dyn DurationModel>>) -> Self {
+        let mut state = ParseState {
             result: ParseOutput::new(),
             length_action_so_far: 0.0,
             length_dialogue_so_far: 0.0,
             current_depth: 0,
             scene_number: 1,
+            act_number: 0,
+            act_scene_number: 1,
             play_time_sec: 0.0,
             last_scen_structure_token_index: None,
             last_scen_structure_token_index_pre: None,
@@ -174,6 +416,7 @@ impl FountainParser {
             last_chartor_structure_token: None,
             force_not_dual: true,
             take_count: 1,
+            character_take_counts: HashMap::new(),
             lines_length: 0,
             current_cursor: 0,
             new_line_length: 1,
@@ -185,64 +428,73 @@ impl FountainParser {
             current_outline_note_linenum: Vec::new(),
             nested_comments: 0,
             nested_notes: 0,
-need_process_outline_note: 0,
+            comment_open_at: None,
+            note_open_at: None,
+            need_process_outline_note: 0,
+            print_current_note: true,
             bookmark_text: Vec::new(),
             bookmark_linenum: Vec::new(),
             bookmark_started: false,
             text_bookmark: String::new(),
-            regex: HashMap::new(),
+            regex: BASE_REGEX.clone(),
             title_page_display: HashMap::new(),
+            custom_duration_model,
+            duration_model: None,
+            duration_conf: Conf::default(),
+            character_sec_per_char: HashMap::new(),
+            omitted_scene_numbers: HashSet::new(),
+            regex_key: RegexKey::default(),
+            progress: Progress::default(),
         };
 
-        // 初始化正则表达式
-        parser.init_regex();
         // 初始化标题页显示配置
-        parser.init_title_page_display();
+        state.init_title_page_display();
 
-        parser
-    }
-
-    // 去除空格、标点和特殊字符
-    pub fn calculate_chars(&self, text: &str) -> String {
-        let re = Regex::new(r"\s|\p{P}|\p{S}").unwrap();
-        re.replace_all(text, "").to_string()
+        state
     }
 
     // 计算动作持续时间
-    pub fn calculate_action_duration(&self, text: &str, config_x: Option<f64>) -> f64 {
-        let x = config_x.unwrap_or(0.4); // 默认值: 0.4秒/字符
-
-        let sanitized = self.calculate_chars(text);
-        sanitized.chars().count() as f64 * x
+    fn calculate_action_duration(&self, text: &str, config_x: Option<f64>) -> f64 {
+        action_duration(text, config_x)
     }
 
     // 计算对话持续时间
-    pub fn calculate_dialogue_duration(
+    // 解析中配置了其它模型（或自定义模型）时由模型估算，否则按字符数估算，参数为每字符和长短标点的耗时
+    fn calculate_dialogue_duration(
         &self,
         text: &str,
         config_x: Option<f64>,
         config_long: Option<f64>,
         config_short: Option<f64>,
     ) -> f64 {
-        let mut duration = 0.0;
-        let x = config_x.unwrap_or(0.3); // 默认值: 0.3秒/字符
-        let long = config_long.unwrap_or(0.75); // 长标点默认值: 0.75秒
-        let short = config_short.unwrap_or(0.3); // 短标点默认值: 0.3秒
-
-        let sanitized = self.calculate_chars(text);
-        duration += sanitized.chars().count() as f64 * x;
-
-        // 处理标点符号
-        let rec = Regex::new(r"(\.|\?|\!|\:|。|？|！|：)|(\,|，|;|；|、)").unwrap();
-        for cap in rec.captures_iter(text) {
-            if cap.get(1).is_some() {
-                duration += long; // 长标点
-            } else if cap.get(2).is_some() {
-                duration += short; // 短标点
-            }
+        if let Some(model) = &self.duration_model {
+            return model.dialogue_duration(text, self.previous_character.as_deref());
         }
+        char_rate_duration(text, config_x, config_long, config_short)
+    }
 
-        duration
+    // 按配置（含标题页 metadata 的覆盖）选择对白时长模型
+    fn update_duration_model(&mut self) {
+        self.duration_conf.dial_sec_per_char = self.result.dial_sec_per_char;
+        self.duration_conf.dial_sec_per_punc_short = self.result.dial_sec_per_punc_short;
+        self.duration_conf.dial_sec_per_punc_long = self.result.dial_sec_per_punc_long;
+        // 角色的每字符耗时换算为相对默认耗时的语速倍率，耗时越长说得越慢
+        if self.result.dial_sec_per_char > 0.0 {
+            for (name, sec) in &self.character_sec_per_char {
+                if *sec > 0.0 {
+                    self.duration_conf
+                        .speaking_rates
+                        .insert(name.clone(), self.result.dial_sec_per_char / sec);
+                }
+            }
+        }
+        let flat = matches!(self.duration_conf.dialogue_duration_model.as_str(), "" | "chars")
+            && self.duration_conf.speaking_rates.is_empty();
+        self.duration_model = match &self.custom_duration_model {
+            Some(model) => Some(model.clone()),
+            None if flat => None,
+            None => Some(duration::model_from_conf(&self.duration_conf)),
+        };
     }
 
     // 创建token
@@ -276,6 +528,9 @@ need_process_outline_note: 0,
             characters_action: None,
             play_time_sec: 0.0,
             invisible_sections: None,
+            origin: None,
+            source_range: None,
+            transition: None,
         }
     }
 
@@ -284,6 +539,16 @@ need_process_outline_note: 0,
         self.result.tokens.push(token);
     }
 
+    // 最后一个分页符之后是否已有内容（空行不算）
+    fn page_has_content(&self) -> bool {
+        self.result
+            .tokens
+            .iter()
+            .rev()
+            .take_while(|token| token.token_type != "page_break")
+            .any(|token| token.token_type != "separator")
+    }
+
     // 处理对话块
     fn process_dialogue_block(&mut self, mut token: ScriptToken) -> ScriptToken {
         // 传播角色名到对白token
@@ -293,7 +558,7 @@ need_process_outline_note: 0,
             token.text_no_notes = Some(self.text_valid.clone());
             let text_without_notes = &self.text_valid;
 
-            // 计算对话持续时间
+            // 计算对话持续时间，metadata 中为该角色指定了语速时按角色语速计算
             let time = self.calculate_dialogue_duration(
                 text_without_notes,
                 Some(self.result.dial_sec_per_char),
@@ -344,13 +609,16 @@ need_process_outline_note: 0,
 
     // 去除角色名前的@符号
     fn trim_character_force_symbol(&self, text: &str) -> String {
-        let re = Regex::new(r"^[ \t]*@").unwrap();
-        re.replace(text, "").to_string()
+        FORCE_CHARACTER.replace(text, "").to_string()
     }
 
     // 去除角色名后的扩展部分
     fn trim_character_extension(&self, text: &str) -> String {
-        let re = Regex::new(r"[ \t]*(\(.*\)|（.*）)[ \t]*([ \t]*\^)?$").unwrap();
+        let re: &Regex = if self.regex_key.full_width_markers {
+            &CHARACTER_EXTENSION_FULL_WIDTH
+        } else {
+            &CHARACTER_EXTENSION
+        };
         re.replace(text, "").to_string()
     }
 
@@ -444,19 +712,29 @@ need_process_outline_note: 0,
             return None;
         }
 
-        let split_location_from_time = Regex::new(r"(.*?)[\-–—−](.*)")
-            .ok()?
-            .captures(match_result.get(2)?.as_str());
-
         let group1 = match_result.get(1)?.as_str();
         let mut i = group1.contains('I');
         let mut e = group1.contains("EX") || group1.contains("E.");
 
-        let mut n = if let Some(time_match) = &split_location_from_time {
-            time_match.get(1)?.as_str().trim().to_string()
-        } else {
-            match_result.get(2)?.as_str().trim().to_string()
-        };
+        // 按连字符拆分为多段：从第一个标准时间（日、NIGHT 等）开始是时间，没有标准时间时最后一段是时间，
+        // 其余各段是由外到内的多级地点，如 "HOUSE - KITCHEN - NIGHT"
+        let location_and_time = match_result.get(2)?.as_str();
+        let mut bounds = vec![0];
+        for separator in LOCATION_SEPARATOR.find_iter(location_and_time) {
+            bounds.push(separator.start());
+            bounds.push(separator.end());
+        }
+        bounds.push(location_and_time.len());
+        let parts: Vec<&str> = bounds
+            .chunks(2)
+            .map(|b| location_and_time[b[0]..b[1]].trim())
+            .collect();
+        let time_index = (1..parts.len())
+            .find(|k| normalize_time_of_day(parts[*k]).is_some())
+            .unwrap_or(parts.len().max(2) - 1);
+        let mut location_parts: Vec<String> =
+            parts[..time_index].iter().map(|p| p.to_string()).collect();
+        let mut n = location_parts.first().cloned().unwrap_or_default();
 
         // 处理中文场景标记 - 严格按照 TypeScript 逻辑
         if n.starts_with("(内景)") || n.starts_with("（内景）") {
@@ -480,14 +758,26 @@ need_process_outline_note: 0,
         }
 
         // 标准化地点名称
-        n = n.to_uppercase().replace(|c: char| c.is_whitespace(), " ");
-
-        // 处理时间部分
-        let day_t = if let Some(time_match) = &split_location_from_time {
-            time_match.get(2).map(|m| m.as_str().trim()).unwrap_or("")
+        if let Some(first) = location_parts.first_mut() {
+            *first = n;
+        }
+        let mut location_parts: Vec<String> = location_parts
+            .into_iter()
+            .map(|p| p.to_uppercase().replace(|c: char| c.is_whitespace(), " "))
+            .filter(|p| !p.is_empty())
+            .collect();
+        let n = location_parts.join(" - ");
+        let master = if location_parts.is_empty() {
+            String::new()
         } else {
-            ""
+            location_parts.remove(0)
         };
+
+        // 处理时间部分
+        let day_t = bounds
+            .get(time_index * 2)
+            .map(|start| location_and_time[*start..].trim())
+            .unwrap_or("");
         let day_t = day_t
             .to_uppercase()
             .replace(|c: char| c.is_whitespace(), " ");
@@ -503,6 +793,8 @@ need_process_outline_note: 0,
 
         Some(Location {
             name: n,
+            master,
+            sub_locations: location_parts,
             interior: i,
             exterior: e,
             time_of_day: day_t,
@@ -525,6 +817,39 @@ need_process_outline_note: 0,
         self.title_page_display.get(key)
     }
 
+    // 解析插图行 [[img: path.png | 宽x高]]
+    fn parse_image_line(&self, text: &str, line: usize) -> Option<ScriptToken> {
+        let captures = self.regex.get("image")?.captures(text)?;
+        let metadata = self.parse_image_spec(captures.get(1)?.as_str())?;
+        Some(ScriptToken {
+            token_type: "image".to_string(),
+            text: metadata["src"].clone(),
+            line,
+            start: 0,
+            end: text.len(),
+            metadata: Some(metadata),
+            ..ScriptToken::empty()
+        })
+    }
+
+    // 解析 "path.png | 宽x高"，得到 src、width、height（英寸）
+    fn parse_image_spec(&self, spec: &str) -> Option<HashMap<String, String>> {
+        let captures = self.regex.get("image_spec")?.captures(spec)?;
+        let src = captures.get(1)?.as_str().trim();
+        if src.is_empty() {
+            return None;
+        }
+        let mut metadata = HashMap::new();
+        metadata.insert("src".to_string(), src.to_string());
+        if let Some(width) = captures.get(2) {
+            metadata.insert("width".to_string(), width.as_str().to_string());
+        }
+        if let Some(height) = captures.get(3) {
+            metadata.insert("height".to_string(), height.as_str().to_string());
+        }
+        Some(metadata)
+    }
+
     // 查找指定深度下最新的section
     fn latest_section(&self, depth: usize) -> Option<StructToken> {
         // 查找第一层中最后一个符合条件的token
@@ -691,6 +1016,17 @@ need_process_outline_note: 0,
                 if !self.current_outline_note_text[i].trim().is_empty() {
                     let line_number = self.current_outline_note_linenum[i];
                     let note_text = self.current_outline_note_text[i].trim().to_string();
+                    if let Some((tag, body)) = self.split_note_tag(&note_text) {
+                        self.result
+                            .properties
+                            .notes_by_tag
+                            .entry(tag)
+                            .or_default()
+                            .push(Note {
+                                note: body.to_string(),
+                                line: line_number,
+                            });
+                    }
 
                     let struct_token = StructToken {
                         text: note_text.clone(),
@@ -769,14 +1105,44 @@ need_process_outline_note: 0,
         }
     }
 
-    // 添加对话编号装饰
-    fn add_dialogue_number_decoration(&self, _token: &mut ScriptToken) {
-        // 在Rust实现中可以根据需要添加具体实现
+    // 记录未闭合的注释/注解诊断，范围覆盖起始的两个字符
+    fn push_unclosed_diagnostic(&mut self, at: Position, code: &str, message: &str) {
+        let end = Position {
+            line: at.line,
+            character: at.character + 2,
+        };
+        self.result.diagnostics.push(Diagnostic::new(
+            Range { start: at, end },
+            DiagnosticSeverity::Error,
+            code,
+            message.to_string(),
+        ));
+    }
+
+    // 添加对话编号装饰：默认沿用全剧连续的编号，按角色编号时改为该角色的第几段对白
+    fn add_dialogue_number_decoration(&mut self, token: &mut ScriptToken, cfg: &Conf) {
+        if cfg.dialogue_numbering != "character" {
+            return;
+        }
+        if let Some(character) = &token.character {
+            let count = self
+                .character_take_counts
+                .entry(character.clone())
+                .or_insert(0);
+            *count += 1;
+            token.take_number = Some(*count as i32);
+        }
     }
 
     // 处理注释和注解
     fn process_comments_and_notes(&mut self, parts: Vec<&str>, line_num: usize, cfg: &Conf) {
-        for part in parts {
+        let mut column = 0;
+        for (idx, &part) in parts.iter().enumerate() {
+            let part_at = Position {
+                line: line_num,
+                character: column,
+            };
+            column += part.chars().count();
             if !part.is_empty() {
                 if part == "/*" || part == "/*|" {
                     if self.nested_notes == 0 {
@@ -784,10 +1150,13 @@ need_process_outline_note: 0,
                             self.bookmark_started = true;
                             self.bookmark_linenum.push(line_num);
                         }
+                        if self.nested_comments == 0 {
+                            self.comment_open_at = Some(part_at);
+                        }
                         self.nested_comments += 1;
                     } else {
                         self.add_outline_note("/*", line_num);
-                        if cfg.print_notes {
+                        if self.print_current_note {
                             self.text_display.push_str(part);
                         }
                     }
@@ -795,6 +1164,7 @@ need_process_outline_note: 0,
                     if self.nested_comments > 0 {
                         self.nested_comments -= 1;
                         if self.nested_comments == 0 {
+                            self.comment_open_at = None;
                             if self.bookmark_started {
                                 self.bookmark_text.push(self.text_bookmark.clone());
                                 self.text_bookmark = String::new();
@@ -811,7 +1181,7 @@ need_process_outline_note: 0,
                             self.text_valid.push_str(part);
                         } else {
                             self.add_outline_note("*/", line_num);
-                            if cfg.print_notes {
+                            if self.print_current_note {
                                 self.text_display.push_str(part);
                             }
                         }
@@ -820,11 +1190,18 @@ need_process_outline_note: 0,
                     if self.nested_comments == 0 {
                         self.nested_notes += 1;
                         if self.nested_notes == 1 {
+                            // 标签须紧跟在 [[ 之后，与 [[ 在同一行
+                            let tag = parts
+                                .get(idx + 1)
+                                .and_then(|next| self.split_note_tag(next))
+                                .map(|(tag, _)| tag);
+                            self.print_current_note = cfg.should_print_note(tag.as_deref());
+                            self.note_open_at = Some(part_at);
                             self.need_process_outline_note += 1;
                             self.current_outline_note_text.push(String::new());
                             self.current_outline_note_linenum.push(line_num);
-                            if cfg.print_notes {
-                                if part == "[[|" {
+                            if self.print_current_note {
+                                if part == "[[|" && cfg.extension_enabled(Extension::InlineNote) {
                                     self.text_display.push_str(&format!(
                                         "{}[",
                                         FountainConstants::style_chars()["note_begin_ext"]
@@ -838,7 +1215,7 @@ need_process_outline_note: 0,
                             }
                         } else {
                             self.add_outline_note("[", line_num);
-                            if cfg.print_notes {
+                            if self.print_current_note {
                                 self.text_display.push_str("[");
                             }
                         }
@@ -851,7 +1228,8 @@ need_process_outline_note: 0,
                     if self.nested_notes > 0 {
                         self.nested_notes -= 1;
                         if self.nested_notes == 0 {
-                            if cfg.print_notes {
+                            self.note_open_at = None;
+                            if self.print_current_note {
                                 self.text_display.push_str(&format!(
                                     "]{}",
                                     FountainConstants::style_chars()["note_end"]
@@ -859,7 +1237,7 @@ need_process_outline_note: 0,
                             }
                         } else {
                             self.add_outline_note("]", line_num);
-                            if cfg.print_notes {
+                            if self.print_current_note {
                                 self.text_display.push_str("]");
                             }
                         }
@@ -880,7 +1258,7 @@ need_process_outline_note: 0,
                         }
                     } else if self.nested_notes > 0 {
                         self.add_outline_note(if !part.is_empty() { part } else { "" }, line_num);
-                        if cfg.print_notes {
+                        if self.print_current_note {
                             self.text_display.push_str(part);
                         }
                     } else {
@@ -892,6 +1270,13 @@ need_process_outline_note: 0,
         }
     }
 
+    // 拆分注解开头的分类标签，返回小写标签和去掉标签后的文本
+    fn split_note_tag<'a>(&self, text: &'a str) -> Option<(String, &'a str)> {
+        let captures = self.regex.get("note_tag")?.captures(text)?;
+        let tag = captures.get(1)?.as_str().to_lowercase();
+        Some((tag, &text[captures.get(0)?.end()..]))
+    }
+
     // 添加大纲注解
     fn add_outline_note(&mut self, note: &str, line: usize) {
         if !self.current_outline_note_text.is_empty() {
@@ -932,6 +1317,90 @@ need_process_outline_note: 0,
         }
     }
 
+    // 替换剧本中的 {{变量}}：先取配置中的变量，再用标题页 Metadata 中的 "variables" 覆盖，
+    // 未定义的变量保持原样并记录诊断
+    fn expand_variables(&mut self, script: &str, cfg: &Conf) -> String {
+        if !script.contains("{{") || !cfg.extension_enabled(Extension::Variable) {
+            return script.to_string();
+        }
+        let mut variables = cfg.variables.clone();
+        variables.extend(self.title_page_variables(script));
+
+        let variable_regex = self.regex.get("variable").unwrap().clone();
+        let mut expanded = String::with_capacity(script.len());
+        let mut line_start = 0;
+        for (i, line) in script.split(&['\r', '\n'][..]).enumerate() {
+            if line_start > 0 {
+                // 保留原来的换行符
+                expanded.push_str(&script[line_start - 1..line_start]);
+            }
+            line_start += line.len() + 1;
+
+            let mut undefined = Vec::new();
+            let replaced = variable_regex.replace_all(line, |caps: &regex::Captures| {
+                match variables.get(&caps[1]) {
+                    // 值中的换行会打乱行号，替换为空格
+                    Some(value) => value.replace(['\r', '\n'], " "),
+                    None => {
+                        undefined.push(caps[1].to_string());
+                        caps[0].to_string()
+                    }
+                }
+            });
+            expanded.push_str(&replaced);
+            for name in undefined {
+                self.result.diagnostics.push(
+                    Diagnostic::line(
+                        i,
+                        line,
+                        DiagnosticSeverity::Warning,
+                        "undefined-variable",
+                        format!("未定义的变量 \"{}\"", name),
+                    )
+                    .with_data(name),
+                );
+            }
+        }
+        expanded
+    }
+
+    // 标题页 Metadata（JSON）中的 "variables" 对象
+    fn title_page_variables(&self, script: &str) -> HashMap<String, String> {
+        let mut variables = HashMap::new();
+        let title_regex = self.regex.get("title_page").unwrap();
+        let mut lines = script.split(&['\r', '\n'][..]);
+        if !lines.clone().next().is_some_and(|l| title_regex.is_match(l)) {
+            return variables;
+        }
+
+        let mut json_text: Option<String> = None;
+        for line in lines.by_ref().take_while(|l| !l.trim().is_empty()) {
+            if let Some(captures) = METADATA_LINE.captures(line) {
+                json_text = Some(captures[1].trim().to_string());
+            } else if title_regex.is_match(line) {
+                if json_text.is_some() {
+                    break;
+                }
+            } else if let Some(text) = json_text.as_mut() {
+                // Metadata 的续行
+                text.push(' ');
+                text.push_str(line.trim());
+            }
+        }
+
+        let metadata = json_text.and_then(|t| serde_json::from_str::<serde_json::Value>(&t).ok());
+        if let Some(vars) = metadata.as_ref().and_then(|m| m.get("variables")).and_then(|v| v.as_object()) {
+            for (name, value) in vars {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                variables.insert(name.clone(), value);
+            }
+        }
+        variables
+    }
+
     /// 解析Fountain格式文本
     ///
     /// # Arguments
@@ -950,7 +1419,16 @@ need_process_outline_note: 0,
     /// * `cfg` - 配置对象
     /// * `generate_html` - 是否生成 HTML 输出
     /// * `calc_statistics` - 是否计算统计数据（可选，默认 false）
-    pub fn parse(&mut self, script: &str, cfg: &Conf, generate_html: bool, calc_statistics: Option<bool>) -> ParseOutput {
+    /// * `sink` - 给出时每遇到场景标题就把之前已确定的 token（已补上原文位置）交给它，不再留在结果中，
+    ///   剩下的 token 在解析结束时交出；此时不做 [`finish_tokens`]，由调用方对交出的 token 完成
+    fn parse(
+        &mut self,
+        script: &str,
+        cfg: &Conf,
+        generate_html: bool,
+        calc_statistics: Option<bool>,
+        mut sink: Option<&mut dyn FnMut(ScriptToken)>,
+    ) -> ParseOutput {
         // 初始化解析结果
         self.result = ParseOutput::new();
         if script.is_empty() {
@@ -962,6 +1440,16 @@ need_process_outline_note: 0,
         self.result.dial_sec_per_punc_short = cfg.dial_sec_per_punc_short;
         self.result.dial_sec_per_punc_long = cfg.dial_sec_per_punc_long;
         self.result.action_sec_per_char = cfg.action_sec_per_char;
+        self.duration_conf = cfg.clone();
+        self.character_sec_per_char.clear();
+        self.omitted_scene_numbers.clear();
+        self.update_duration_model();
+        self.update_regex(RegexKey {
+            scene_heading_prefixes: cfg.scene_heading_prefixes.clone(),
+            transition_keywords: cfg.transition_keywords.clone(),
+            full_width_markers: cfg.extension_enabled(Extension::ChineseMarkers),
+        });
+        self.update_title_page_display(&cfg.title_page_template);
 
         // 记录开始时间
         self.result.parse_time = std::time::SystemTime::now()
@@ -969,6 +1457,11 @@ need_process_outline_note: 0,
             .unwrap_or_default()
             .as_millis() as u64;
 
+        // 替换 {{变量}}，源码位置以替换前的原文为准
+        let source_map = SourceMap::new(script);
+        let script = self.expand_variables(script, cfg);
+        let script = script.as_str();
+
         // 处理换行符差异
         self.new_line_length = if script.contains("\r\n") { 2 } else { 1 };
         let lines: Vec<&str> = script.split(&['\r', '\n'][..]).collect();
@@ -976,9 +1469,14 @@ need_process_outline_note: 0,
         // 解析状态跟踪
         self.result.state = "normal".to_string(); // normal, title, dialogue
         self.scene_number = 1;
+        self.act_number = 0;
+        self.act_scene_number = 1;
         self.nested_comments = 0;
         self.nested_notes = 0;
+        self.comment_open_at = None;
+        self.note_open_at = None;
         self.need_process_outline_note = 0;
+        self.print_current_note = cfg.print_notes;
         self.current_outline_note_text.clear();
         self.current_outline_note_linenum.clear();
         self.text_display = String::new();
@@ -988,7 +1486,7 @@ need_process_outline_note: 0,
         let mut last_title_page_token: Option<ScriptToken> = None;
         self.last_scen_structure_token_index = None;
         self.last_scen_structure_token_index_pre = None;
-        let mut last_character_index = 0;
+        let mut last_character_index: usize = 0;
         let mut _previous_character: Option<String> = None;
         self.previous_character = None;
 
@@ -1016,6 +1514,14 @@ need_process_outline_note: 0,
         let mut note_token: Option<ScriptToken> = None; // page_break 的打印注解
 
         for i in 0..lines.len() {
+            // 已取消时放弃剩下的行，由 parse_with_progress 返回 Cancelled
+            if self
+                .progress
+                .report(ExportPhase::Parse, i, lines.len())
+                .is_err()
+            {
+                return std::mem::take(&mut self.result);
+            }
             let text = lines[i];
 
             self.text_display = String::new(); // 视乎打印设置是否打印note，可以包含 note 内容
@@ -1033,7 +1539,7 @@ need_process_outline_note: 0,
                 self.text_display = text.to_string();
                 if self.nested_comments > 0 || self.nested_notes > 0 {
                     // 如果是在注释中，那么直接忽略
-                    if self.nested_notes > 0 && cfg.print_notes && match_line_break {
+                    if self.nested_notes > 0 && self.print_current_note && match_line_break {
                         // note 空行，双空格表示保留一个空行，否则直接去掉空行
                     } else {
                         continue;
@@ -1056,13 +1562,33 @@ need_process_outline_note: 0,
             } else {
                 // 至少不是空行了
 
+                // 单独成行的插图
+                if self.nested_comments == 0
+                    && self.nested_notes == 0
+                    && cfg.extension_enabled(Extension::Image)
+                {
+                    if let Some(image_token) = self.parse_image_line(text, i) {
+                        if self.result.state == "title_page" && !is_block_inner {
+                            self.process_title_page_end(i);
+                        }
+                        if self.result.state == "normal" {
+                            is_block_inner = true;
+                            self.push_token(image_token);
+                            continue;
+                        }
+                    }
+                }
+
                 // 分割注释和注解
-                let re = Regex::new(r"(\/\*\||\/\*|\*\/|\[\[\||\[\[|\]\])").unwrap();
                 let mut parts = Vec::new();
                 let mut last_end = 0;
 
-                for cap in re.captures_iter(text) {
+                for cap in NOTE_DELIMITERS.captures_iter(text) {
                     let m = cap.get(0).unwrap();
+                    // 反斜杠转义的 \[[ 等按普通文本处理
+                    if text[..m.start()].ends_with('\\') {
+                        continue;
+                    }
                     if m.start() > last_end {
                         parts.push(&text[last_end..m.start()]);
                     }
@@ -1113,6 +1639,9 @@ need_process_outline_note: 0,
                 characters_action: None,
                 play_time_sec: 0.0,
                 invisible_sections: None,
+                origin: None,
+                source_range: None,
+                transition: None,
             };
             this_token.play_time_sec = self.play_time_sec;
 
@@ -1283,21 +1812,26 @@ need_process_outline_note: 0,
                     this_token.token_type =
                         self.text_valid[..index].to_lowercase().replace(' ', "_");
 
-                    let font_mt = Regex::new(
-                        r"(?i)^\s*(font|font italic|font bold|font bold italic|metadata)\:(.*)",
-                    )
-                    .unwrap()
-                    .captures(&self.text_valid);
+                    let font_mt = TITLE_PAGE_FONT.captures(&self.text_valid);
 
                     if let Some(captures) = font_mt {
                         font_title = true;
                         this_token.text = captures.get(2).unwrap().as_str().trim().to_string();
                     } else {
                         font_title = false;
-                        let mt = Regex::new(r"(?i)^(.*?↻)??\s*(title|credit|author[s]?|source|notes|draft date|date|watermark|contact(?: info)?|revision|copyright|tl|tc|tr|cc|br|bl|header|footer)\:(.*)").unwrap()
-                            .captures(&self.text_display);
+                        let mt = TITLE_PAGE_KEY.captures(&self.text_display);
 
-                        if let Some(captures) = mt {
+                        if this_token.token_type == "image" {
+                            // 封面图片：路径不做样式处理，宽高放在 metadata 中
+                            let spec = mt.as_ref().and_then(|c| c.get(3)).map_or("", |m| m.as_str());
+                            this_token.metadata = self.parse_image_spec(spec);
+                            this_token.text = this_token
+                                .metadata
+                                .as_ref()
+                                .map(|m| m["src"].clone())
+                                .unwrap_or_default();
+                            last_is_blank_title = false;
+                        } else if let Some(captures) = mt {
                             this_token.text = captures.get(3).unwrap().as_str().trim().to_string();
                             process_token_text_style_char(&mut this_token);
                             this_token.text = format!(
@@ -1354,6 +1888,26 @@ need_process_outline_note: 0,
                         .unwrap()
                         .is_match(&self.text_valid)
                 {
+                    // 顶格写的 "xxx:" 看起来是标题页字段，但不是支持的关键字
+                    if let Some(caps) = self
+                        .regex
+                        .get("title_page_key_like")
+                        .unwrap()
+                        .captures(&self.text_valid)
+                    {
+                        let key = caps.get(1).unwrap().as_str().trim().to_string();
+                        self.result.diagnostics.push(
+                            Diagnostic::line(
+                                i,
+                                lines[i],
+                                DiagnosticSeverity::Warning,
+                                "unknown-title-page-key",
+                                format!("无法识别的标题页字段 \"{}\"，该行会被当作上一个字段的续行", key),
+                            )
+                            .with_data(key),
+                        );
+                    }
+
                     // 标题页字段内容的换行内容，或者标题页状态下的其他内容
                     if font_title {
                         this_token.text = self.text_valid.trim().to_string();
@@ -1392,13 +1946,7 @@ need_process_outline_note: 0,
                             if curr_blank && last_is_blank_title && last_title_page_token.is_some()
                             {
                                 handled = true;
-                                let t = Regex::new(&format!(
-                                    r"[{}]",
-                                    regex::escape(FountainConstants::style_chars()["all"])
-                                ))
-                                .unwrap()
-                                .replace_all(&this_token.text, "")
-                                .to_string();
+                                let t = STYLE_CHARS.replace_all(&this_token.text, "").to_string();
                                 last_title_page_token.as_mut().unwrap().text.push_str(&t);
                             }
                             last_is_blank_title = curr_blank;
@@ -1545,11 +2093,19 @@ need_process_outline_note: 0,
                 let mut action = false;
                 if is_block_begin_line {
                     // 检查是否是场景标题
-                    if self
+                    // 标准 Fountain 中以省略号开头的是动作或对白，不是强制场景标题
+                    if (self
                         .regex
                         .get("scene_heading")
                         .unwrap()
                         .is_match(&self.text_valid)
+                        && !(cfg.spec_mode && self.text_valid.trim_start().starts_with("..")))
+                        || (cfg.extension_enabled(Extension::Omitted)
+                            && self
+                                .regex
+                                .get("omitted_scene")
+                                .unwrap()
+                                .is_match(&self.text_valid))
                     {
                         self.process_title_page_end(i);
 
@@ -1598,6 +2154,71 @@ need_process_outline_note: 0,
                                                 self.result.action_sec_per_char =
                                                     action_sec_per_char;
                                             }
+                                            if let Some(model) = metadata
+                                                .get("dialogue_duration_model")
+                                                .and_then(|v| v.as_str())
+                                            {
+                                                self.duration_conf.dialogue_duration_model =
+                                                    model.to_string();
+                                            }
+                                            if let Some(wpm) = metadata
+                                                .get("dialogue_words_per_minute")
+                                                .and_then(|v| v.as_f64())
+                                            {
+                                                self.duration_conf.dialogue_words_per_minute = wpm;
+                                            }
+                                            if let Some(rate) = metadata
+                                                .get("dialogue_syllables_per_sec")
+                                                .and_then(|v| v.as_f64())
+                                            {
+                                                self.duration_conf.dialogue_syllables_per_sec =
+                                                    rate;
+                                            }
+                                            // 按角色指定对白每字符耗时，如 {"rates": {"GRANDPA": 0.45}}；
+                                            // 这是 metadata 中唯一的按角色语速设置，同一角色优先于配置的 speaking_rates
+                                            if let Some(rates) =
+                                                metadata.get("rates").and_then(|v| v.as_object())
+                                            {
+                                                for (name, sec) in rates {
+                                                    if let Some(sec) = sec.as_f64() {
+                                                        self.character_sec_per_char.insert(
+                                                            name.trim().to_string(),
+                                                            sec,
+                                                        );
+                                                    }
+                                                }
+                                            }
+                                            // 删去的场景编号，如 {"omitted": ["12", 15]}
+                                            if let Some(omitted) =
+                                                metadata.get("omitted").and_then(|v| v.as_array())
+                                            {
+                                                for number in omitted {
+                                                    let number = match number {
+                                                        serde_json::Value::String(s) => {
+                                                            s.trim().to_string()
+                                                        }
+                                                        other => other.to_string(),
+                                                    };
+                                                    self.omitted_scene_numbers.insert(number);
+                                                }
+                                            }
+                                            // 角色别名，如 {"aliases": {"BOB": "ROBERT"}}
+                                            if let Some(aliases) =
+                                                metadata.get("aliases").and_then(|v| v.as_object())
+                                            {
+                                                for (alias, name) in aliases {
+                                                    if let Some(name) = name.as_str() {
+                                                        self.result
+                                                            .properties
+                                                            .character_aliases
+                                                            .insert(
+                                                                alias.trim().to_uppercase(),
+                                                                name.trim().to_string(),
+                                                            );
+                                                    }
+                                                }
+                                            }
+                                            self.update_duration_model();
                                         }
                                         break;
                                     }
@@ -1606,9 +2227,17 @@ need_process_outline_note: 0,
                         }
 
                         self.force_not_dual = true;
+                        // 之前的 token 不会再被修改（双对白不会跨过场景标题往回找），交给 sink
+                        if let Some(sink) = sink.as_mut() {
+                            let flushed = self.result.tokens.len();
+                            for mut token in self.result.tokens.drain(..) {
+                                source_map.assign(std::iter::once(&mut token));
+                                sink(token);
+                            }
+                            last_character_index = last_character_index.saturating_sub(flushed);
+                        }
                         // 去掉前面的点号
-                        self.text_display = Regex::new(r"^[ \t]*\.")
-                            .unwrap()
+                        self.text_display = FORCED_SCENE_HEADING
                             .replace(&self.text_display, "")
                             .to_string();
 
@@ -1625,12 +2254,17 @@ need_process_outline_note: 0,
                         }
 
                         this_token.token_type = "scene_heading".to_string();
-                        let mut nb = self.scene_number.to_string();
+                        let mut nb = format_scene_number(
+                            &cfg.scene_number_format,
+                            self.scene_number,
+                            self.act_number,
+                            self.act_scene_number,
+                        );
                         let mut scene_number_dup = false;
+                        let mut scene_number_var_repeat = false; // 通过 ${var} 有意复用之前的编号
 
                         // 处理场景编号
-                        let mut text_for_token = Regex::new(r"^[ \t]*\.")
-                            .unwrap()
+                        let mut text_for_token = FORCED_SCENE_HEADING
                             .replace(&self.text_valid, "")
                             .to_string();
 
@@ -1664,6 +2298,7 @@ need_process_outline_note: 0,
                                 if !group1.as_str().trim().is_empty() {
                                     let tab = group1.as_str().trim().to_string();
                                     if dup_scence_nuber.contains_key(&tab) {
+                                        scene_number_var_repeat = true;
                                         nb = dup_scence_nuber.get(&tab).unwrap().clone();
                                     } else {
                                         dup_scence_nuber.insert(tab, nb.clone());
@@ -1679,6 +2314,19 @@ need_process_outline_note: 0,
                             scence_numbers.insert(nb.clone());
                         }
 
+                        if scene_number_dup && !scene_number_var_repeat {
+                            self.result.diagnostics.push(
+                                Diagnostic::line(
+                                    i,
+                                    lines[i],
+                                    DiagnosticSeverity::Warning,
+                                    "duplicate-scene-number",
+                                    format!("场景编号 {} 与之前的场景重复", nb),
+                                )
+                                .with_data(nb.clone()),
+                            );
+                        }
+
                         // 设置场景编号
                         if scene_number_dup {
                             this_token.number = Some(format!("↑{}", nb)); // 用 ↑ 打印标记提示重复
@@ -1687,36 +2335,11 @@ need_process_outline_note: 0,
                         }
 
                         // 标准化场景标题格式
-                        let mut idx = text_for_token.find('-');
-                        if let Some(pos) = idx {
-                            text_for_token = format!(
-                                "{} - {}",
-                                &text_for_token[..pos],
-                                &text_for_token[pos + 1..]
-                            );
-                        }
-
-                        text_for_token = text_for_token
-                            .to_uppercase()
-                            .replace(|c: char| c.is_whitespace(), " ");
-
-                        idx = self.text_display.find('-');
-                        if let Some(pos) = idx {
-                            self.text_display = format!(
-                                "{} - {}",
-                                &self.text_display[..pos],
-                                &self.text_display[pos + 1..]
-                            );
-                        }
-
-                        self.text_display = self
-                            .text_display
-                            .to_uppercase()
-                            .replace(|c: char| c.is_whitespace(), " ");
+                        text_for_token = normalize_scene_heading(&text_for_token, cfg);
+                        self.text_display = normalize_scene_heading(&self.text_display, cfg);
 
                         // 规范化空格：将多个连续空格替换为单个空格，并trim两端
-                        self.text_display = Regex::new(r"\s+")
-                            .unwrap()
+                        self.text_display = WHITESPACE
                             .replace_all(&self.text_display, " ")
                             .trim()
                             .to_string();
@@ -1735,6 +2358,25 @@ need_process_outline_note: 0,
                         }
                         this_token.text_no_notes = Some(text_for_token.clone());
 
+                        // 删去的场景（OMITTED）：保留编号，只打印 "SCENE 12 – OMITTED"，场景内容不输出
+                        let omitted = cfg.extension_enabled(Extension::Omitted)
+                            && (self
+                                .regex
+                                .get("omitted_scene")
+                                .unwrap()
+                                .is_match(&self.text_valid)
+                                || self.omitted_scene_numbers.contains(&nb));
+                        if omitted {
+                            this_token.text = format!("SCENE {} – OMITTED", nb);
+                            text_for_token = "OMITTED".to_string();
+                            this_token.text_no_notes = Some(this_token.text.clone());
+                            this_token
+                                .metadata
+                                .get_or_insert_with(HashMap::new)
+                                .insert("omitted".to_string(), "true".to_string());
+                            self.result.properties.omitted_scenes.push(nb.clone());
+                        }
+
                         // 创建结构树节点
                         let mut cobj = StructToken {
                             text: format!(
@@ -1881,7 +2523,10 @@ need_process_outline_note: 0,
                             .push(self.text_valid.clone());
 
                         // 处理场景位置信息
-                        if let Some(location) = self.parse_location_information(&self.text_valid) {
+                        if let Some(location) = self
+                            .parse_location_information(&self.text_valid)
+                            .filter(|_| !omitted)
+                        {
                             let location_slug = self.slugify(&location.name);
                             let lslugs: Vec<String> = vec![location_slug.clone()]
                                 .into_iter()
@@ -1910,6 +2555,7 @@ need_process_outline_note: 0,
                         // 更新场景编号
                         if !scene_number_dup {
                             self.scene_number += 1;
+                            self.act_scene_number += 1;
                         }
 
                         self.push_token(this_token);
@@ -1934,7 +2580,8 @@ need_process_outline_note: 0,
                             .regex
                             .get("transition")
                             .unwrap()
-                            .captures(&self.text_valid);
+                            .captures(&self.text_valid)
+                            .filter(|_| cfg.extension_enabled(Extension::ShotCut));
 
                         if let Some(captures) = match_display {
                             if captures.len() > 2 && captures.get(2).is_some() {
@@ -1943,6 +2590,14 @@ need_process_outline_note: 0,
                                 if tx.starts_with("{+") && tx.ends_with("+} ↓") {
                                     self.shot_cut = 1;
                                     let mut shot_cut_map = HashMap::new();
+                                    shot_cut_map.insert(
+                                        "type".to_string(),
+                                        serde_json::to_value("+").unwrap(),
+                                    );
+                                    shot_cut_map.insert(
+                                        "line".to_string(),
+                                        serde_json::to_value(i).unwrap(),
+                                    );
                                     shot_cut_map.insert(
                                         "duration".to_string(),
                                         serde_json::to_value(0.0).unwrap(),
@@ -1962,6 +2617,14 @@ need_process_outline_note: 0,
                                 } else if tx.starts_with("{#") && tx.ends_with("#} ↓") {
                                     self.shot_cut = 2;
                                     let mut shot_cut_map = HashMap::new();
+                                    shot_cut_map.insert(
+                                        "type".to_string(),
+                                        serde_json::to_value("#").unwrap(),
+                                    );
+                                    shot_cut_map.insert(
+                                        "line".to_string(),
+                                        serde_json::to_value(i).unwrap(),
+                                    );
                                     shot_cut_map.insert(
                                         "duration".to_string(),
                                         serde_json::to_value(0.0).unwrap(),
@@ -1983,6 +2646,14 @@ need_process_outline_note: 0,
                                 } else if tx.starts_with("{=") && tx.ends_with("=} ↓") {
                                     self.shot_cut = 3;
                                     let mut shot_cut_map = HashMap::new();
+                                    shot_cut_map.insert(
+                                        "type".to_string(),
+                                        serde_json::to_value("=").unwrap(),
+                                    );
+                                    shot_cut_map.insert(
+                                        "line".to_string(),
+                                        serde_json::to_value(i).unwrap(),
+                                    );
                                     shot_cut_map.insert(
                                         "duration".to_string(),
                                         serde_json::to_value(0.0).unwrap(),
@@ -1999,13 +2670,13 @@ need_process_outline_note: 0,
                         }
 
                         self.process_title_page_end(i);
-                        this_token.text = Regex::new(r"^\s*>\s*")
-                            .unwrap()
+                        this_token.text = FORCED_TRANSITION
                             .replace(&self.text_display, "")
                             .to_string();
 
                         process_token_text_style_char(&mut this_token);
                         this_token.token_type = "transition".to_string();
+                        this_token.transition = self.transition_keyword(&self.text_valid);
 
                         self.push_token(this_token);
                         continue;
@@ -2014,6 +2685,11 @@ need_process_outline_note: 0,
                         .get("character")
                         .unwrap()
                         .is_match(&self.text_valid)
+                        // 标准 Fountain 中角色名下一行必须有内容，否则是全大写的动作
+                        && !(cfg.spec_mode
+                            && lines
+                                .get(i + self.new_line_length)
+                                .is_none_or(|next| next.trim().is_empty()))
                     {
                         // 处理角色
                         self.process_title_page_end(i);
@@ -2027,7 +2703,51 @@ need_process_outline_note: 0,
 
                         // 检查是否是双对话
                         if text_valid.ends_with("^") {
-                            if cfg.use_dual_dialogue && !self.force_not_dual {
+                            // 三人同时对白：紧跟在一组双对白右栏之后的 `^^`，
+                            // 原来的右栏改为中栏，本段对白放在右栏
+                            let triple = cfg.extension_enabled(Extension::TripleDialogue)
+                                && text_valid.ends_with("^^")
+                                && self
+                                    .result
+                                    .tokens
+                                    .get(last_character_index)
+                                    .and_then(|t| t.dual.as_deref())
+                                    == Some("right")
+                                && !self.result.tokens[..last_character_index]
+                                    .iter()
+                                    .rev()
+                                    .take_while(|t| t.token_type != "dual_dialogue_begin")
+                                    .any(|t| t.dual.as_deref() == Some("middle"));
+                            if triple && cfg.use_dual_dialogue && !self.force_not_dual {
+                                self.result.state = "dual_dialogue".to_string();
+
+                                let dialogue_tokens = ["dialogue", "character", "parenthetical"];
+                                for token in self.result.tokens[last_character_index..]
+                                    .iter_mut()
+                                    .take_while(|t| {
+                                        dialogue_tokens.contains(&t.token_type.as_str())
+                                    })
+                                {
+                                    token.dual = Some("middle".to_string());
+                                }
+
+                                // 删除上一组双对白的 dual_dialogue_end，三栏共用一组
+                                let mut temp_index = self.result.tokens.len();
+                                while temp_index > 0 {
+                                    temp_index -= 1;
+                                    match self.result.tokens[temp_index].token_type.as_str() {
+                                        "dual_dialogue_end" => {
+                                            self.result.tokens.truncate(temp_index);
+                                        }
+                                        "separator" | "character" | "dialogue"
+                                        | "parenthetical" => {}
+                                        _ => break,
+                                    }
+                                }
+
+                                self.result.dual_str = Some("right".to_string());
+                                this_token.dual = self.result.dual_str.clone();
+                            } else if cfg.use_dual_dialogue && !self.force_not_dual {
                                 self.result.state = "dual_dialogue".to_string();
 
                                 // 更新上一个对话为dual:left
@@ -2123,35 +2843,26 @@ need_process_outline_note: 0,
                                 ));
                             }
 
-                            // 移除角色名后的^符号
-                            text_valid = Regex::new(r"\^\s*$")
-                                .unwrap()
-                                .replace(&text_valid, "")
-                                .to_string();
+                            // 移除角色名后的^符号（三人同时对白为^^）
+                            text_valid = DUAL_MARK.replace(&text_valid, "").to_string();
 
                             // 替代前瞻性判断的实现：分别处理三种情况
                             // 1. ^空白字符后跟注释开始符号 இ
-                            if let Some(captures) = Regex::new(r"(\^\s*)(இ.*$)")
-                                .unwrap()
-                                .captures(&self.text_display)
-                            {
+                            if let Some(captures) = DUAL_MARK_NOTE.captures(&self.text_display) {
                                 let note_part = captures.get(2).map_or("", |m| m.as_str());
                                 self.text_display = note_part.to_string();
                             }
                             // 2. ^空白字符后跟注释开始符号 ↺
-                            else if let Some(captures) = Regex::new(r"(\^\s*)(↺.*$)")
-                                .unwrap()
-                                .captures(&self.text_display)
+                            else if let Some(captures) =
+                                DUAL_MARK_REVISION.captures(&self.text_display)
                             {
                                 let note_part = captures.get(2).map_or("", |m| m.as_str());
                                 self.text_display = note_part.to_string();
                             }
                             // 3. ^空白字符在行尾
                             else {
-                                self.text_display = Regex::new(r"\^\s*$")
-                                    .unwrap()
-                                    .replace(&self.text_display, "")
-                                    .to_string();
+                                self.text_display =
+                                    DUAL_MARK.replace(&self.text_display, "").to_string();
                             }
                         } else {
                             self.push_token(self.create_token(
@@ -2165,9 +2876,9 @@ need_process_outline_note: 0,
 
                         self.force_not_dual = false;
                         let character = self
-                            .trim_character_extension(&text_valid)
-                            .trim()
-                            .to_string();
+                            .result
+                            .properties
+                            .canonical_character(&self.trim_character_extension(&text_valid));
                         self.previous_character = Some(character.clone());
                         this_token.character = Some(character.clone());
 
@@ -2289,15 +3000,14 @@ need_process_outline_note: 0,
                         }
 
                         // 处理角色名格式
-                        self.text_display = Regex::new(r"^(.*?↻)?[ \t]*@")
-                            .unwrap()
+                        self.text_display = FORCED_CHARACTER_TEXT
                             .replace(&self.text_display, "")
                             .trim()
                             .to_string();
                         this_token.text = self.text_display.clone();
 
                         if cfg.print_dialogue_numbers {
-                            self.add_dialogue_number_decoration(&mut this_token);
+                            self.add_dialogue_number_decoration(&mut this_token, cfg);
                         }
 
                         self.push_token(this_token);
@@ -2311,9 +3021,7 @@ need_process_outline_note: 0,
                         self.process_title_page_end(i);
                         this_token.token_type = "action".to_string();
 
-                        let mt = Regex::new(r"^((?:.*?↻)?\s*)(\!)(.*)")
-                            .unwrap()
-                            .captures(&self.text_display);
+                        let mt = FORCED_ACTION_TEXT.captures(&self.text_display);
 
                         if let Some(captures) = mt {
                             let group1 = captures.get(1).map_or("", |m| m.as_str());
@@ -2344,10 +3052,7 @@ need_process_outline_note: 0,
                         self.process_title_page_end(i);
                         this_token.token_type = "centered".to_string();
 
-                        let mt =
-                            Regex::new(r"((?:^.*?↻)|^)[ \t]*>\s*(.+)\s*?<\s*((?:இ.*$)|(?:↺.*$)|$)")
-                                .unwrap()
-                                .captures(&self.text_display);
+                        let mt = CENTERED_TEXT.captures(&self.text_display);
 
                         if let Some(captures) = mt {
                             let group1 = captures.get(1).map_or("", |m| m.as_str()).trim();
@@ -2370,9 +3075,7 @@ need_process_outline_note: 0,
                         self.process_title_page_end(i);
                         this_token.token_type = "section".to_string();
 
-                        let mt = Regex::new(r"^((?:.*?↻)?\s*)(#+)(?:\s*)(.*)")
-                            .unwrap()
-                            .captures(&self.text_display);
+                        let mt = SECTION_TEXT.captures(&self.text_display);
 
                         if let Some(captures) = mt {
                             let group1 = captures.get(1).map_or("", |m| m.as_str());
@@ -2421,6 +3124,8 @@ need_process_outline_note: 0,
                             self.current_depth = group2.len();
 
                             if self.current_depth == 1 {
+                                self.act_number += 1;
+                                self.act_scene_number = 1;
                                 cobj.id = Some(format!("/{}", this_token.line));
                                 self.result.properties.structure.push(cobj);
                             } else {
@@ -2452,6 +3157,21 @@ need_process_outline_note: 0,
                             }
                         }
 
+                        // 配置要求该层级及以上的章节另起一页时添加分页符，已在页首时不重复分页
+                        let max_level = cfg.page_break_before_sections.unwrap_or(0) as i32;
+                        if this_token.level.is_some_and(|level| level <= max_level)
+                            && self.page_has_content()
+                        {
+                            let page_break = self.create_token(
+                                Some(""),
+                                Some(0),
+                                Some(i),
+                                Some(text.len()),
+                                "page_break",
+                            );
+                            self.push_token(page_break);
+                        }
+
                         self.push_token(this_token);
                         continue;
                     } else if self
@@ -2477,9 +3197,7 @@ need_process_outline_note: 0,
                         self.process_title_page_end(i);
                         this_token.token_type = "synopsis".to_string();
 
-                        let mt = Regex::new(r"^((?:.*?↻)?\s*)(?:\=)(.*)")
-                            .unwrap()
-                            .captures(&self.text_display);
+                        let mt = SYNOPSIS_TEXT.captures(&self.text_display);
 
                         if let Some(captures) = mt {
                             let group1 = captures.get(1).map_or("", |m| m.as_str());
@@ -2516,9 +3234,7 @@ need_process_outline_note: 0,
                         self.process_title_page_end(i);
                         this_token.token_type = "lyric".to_string();
 
-                        let mt = Regex::new(r"^((?:.*?↻)?\s*)(\~)(\s*)(.*)")
-                            .unwrap()
-                            .captures(&self.text_display);
+                        let mt = LYRIC_TEXT.captures(&self.text_display);
 
                         if let Some(captures) = mt {
                             let group1 = captures.get(1).map_or("", |m| m.as_str());
@@ -2561,9 +3277,10 @@ need_process_outline_note: 0,
             last_was_separator = false;
 
             if self.result.state != "ignore" {
-                if this_token.token_type == "scene_heading" || this_token.token_type == "transition"
-                {
+                if this_token.token_type == "transition" {
                     this_token.text = this_token.text.to_uppercase();
+                } else if this_token.token_type == "scene_heading" {
+                    this_token.text = scene_heading_case(&this_token.text, cfg);
                 }
 
                 if this_token.token_type != "action" && this_token.token_type != "dialogue" {
@@ -2582,6 +3299,17 @@ need_process_outline_note: 0,
                 }
             }
         }
+        let _ = self
+            .progress
+            .report(ExportPhase::Parse, lines.len(), lines.len());
+
+        // 所有文档行解析完后，仍未闭合的注释和注解
+        if let Some(at) = self.comment_open_at.take() {
+            self.push_unclosed_diagnostic(at, "unclosed-comment", "注释 /* 没有对应的 */，之后的内容全部被注释");
+        }
+        if let Some(at) = self.note_open_at.take() {
+            self.push_unclosed_diagnostic(at, "unclosed-note", "注解 [[ 没有对应的 ]]，之后的内容全部被当作注解");
+        }
 
         // 所有文档行解析完后，如果是直接截断的对话block，额外添加token
         if self.result.state == "dialogue" {
@@ -2634,110 +3362,33 @@ need_process_outline_note: 0,
             }
         }
 
+        self.result.properties.shot_cuts = self.shot_cut_strct_tokens.clone();
+
         // 保存场景变量
         self.result.properties.scene_number_vars = Some(dup_scence_nuber.keys().cloned().collect());
 
-        // 处理完所有的token，进行最后的处理
-        // 所有action里面出现过的角色，也应该计入在场景中出现过
-        let mut last_scene_idx: i32 = -1;
-        for token in &mut self.result.tokens {
-            if token.token_type == "scene_heading" {
-                last_scene_idx += 1;
-            } else if last_scene_idx >= 0 && token.token_type == "action" {
-                if !token.text.is_empty() {
-                    let mut char_map: HashMap<String, Vec<usize>> = HashMap::new(); // 角色，在行中字符index的start和end
-
-                    // 先将result.properties.characters按照角色名的长度排序，长的在前面
-                    let mut sorted_keys: Vec<String> =
-                        self.result.properties.characters.keys().cloned().collect();
-                    sorted_keys.sort_by(|a, b| b.len().cmp(&a.len()));
-
-                    for k in sorted_keys {
-                        // 需要判断，角色名匹配，不能在同一行中索引重叠
-                        let mut added = false;
-                        let mut i = 0;
-
-                        while i < token.text.len() {
-                            if let Some(idx) = token.text[i..].find(&k) {
-                                let start = i + idx;
-                                let end = start + k.len();
-                                let mut overlap = false;
-
-                                for (_existing_k, v) in &char_map {
-                                    if v[0] < end && v[1] > start {
-                                        overlap = true;
-                                        break;
-                                    }
-                                }
-
-                                if !overlap {
-                                    char_map.insert(k.clone(), vec![start, end]);
-                                    added = true;
-                                    break;
-                                } else {
-                                    i = end; // 继续查找下一个
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-
-                        if added {
-                            if let Some(v) = self.result.properties.characters.get_mut(&k) {
-                                // 角色在action中出现过，也算在场景中出现过
-                                if !v.contains(&(last_scene_idx as usize)) {
-                                    v.push(last_scene_idx as usize);
-                                }
-                            }
-
-                            if token.characters_action.is_none() {
-                                token.characters_action = Some(Vec::new());
-                            }
-
-                            if let Some(chars) = &mut token.characters_action {
-                                chars.push(k);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        // 转换result.properties.characters成result.properties.characterSceneNumber
-        let mut character_scene_number: HashMap<String, HashSet<String>> = HashMap::new();
-
-        for (k, v) in &self.result.properties.characters {
-            let mut scene_numbers = HashSet::new();
-
-            // 按照场景头索引排序，去掉负数的场景号
-            let mut sorted_indices: Vec<&usize> = v.iter().collect();
-            sorted_indices.sort();
-
-            for &idx in sorted_indices {
-                if idx < self.result.properties.scenes.len() {
-                    if let Some(scene) = self.result.properties.scenes.get(idx) {
-                        if let Some(number) = scene.get("number") {
-                            if let Some(number_str) = number.as_str() {
-                                scene_numbers.insert(number_str.to_string());
-                            }
-                        }
-                    }
-                }
+        if let Some(sink) = sink.as_mut() {
+            for mut token in self.result.tokens.drain(..) {
+                source_map.assign(std::iter::once(&mut token));
+                sink(token);
             }
-
-            character_scene_number.insert(k.clone(), scene_numbers);
+        } else {
+            finish_tokens(&mut self.result.properties, &mut self.result.tokens, cfg);
         }
 
-        self.result.properties.character_scene_number = Some(character_scene_number);
-
         // 生成HTML输出
         if generate_html {
-            self.result.script_html = Some(crate::parser::text_processor::generate_html(
-                &self.result.tokens,
-            ));
+            let html_tokens: Vec<ScriptToken> = self
+                .result
+                .tokens
+                .iter()
+                .filter(|token| cfg.print_synopsis_html || token.token_type != "synopsis")
+                .cloned()
+                .collect();
+            self.result.script_html =
+                Some(crate::parser::text_processor::generate_html(&html_tokens));
             self.result.title_html = Some(crate::parser::text_processor::generate_title_html(
-                &self.result.properties.title_keys,
-                &self.result.tokens,
+                &self.result.title_page,
             ));
         }
 
@@ -2749,116 +3400,75 @@ need_process_outline_note: 0,
 
         self.result.parse_time = end_time - self.result.parse_time;
 
+        // 补上每个 token 在原文中的绝对位置
+        let tokens = self
+            .result
+            .tokens
+            .iter_mut()
+            .chain(self.result.title_page.values_mut().flatten());
+        source_map.assign(tokens);
+
         // 根据参数决定是否计算统计数据（默认不计算，提高性能）
         let should_calc_stats = calc_statistics.unwrap_or(false);
         if should_calc_stats {
+            let duration_model: Arc<dyn DurationModel> = match &self.duration_model {
+                Some(model) => model.clone(),
+                None => Arc::new(CharRateModel {
+                    sec_per_char: self.result.dial_sec_per_char,
+                    sec_per_punc_short: self.result.dial_sec_per_punc_short,
+                    sec_per_punc_long: self.result.dial_sec_per_punc_long,
+                }),
+            };
             self.result.statistics = Some(crate::statistics::calculate_statistics(
                 &self.result.tokens,
                 &self.result.properties,
                 self.result.length_dialogue,
                 self.result.length_action,
-                self.result.dial_sec_per_char,
-                self.result.dial_sec_per_punc_short,
-                self.result.dial_sec_per_punc_long,
+                &*duration_model,
             ));
         }
 
-        self.result.clone()
+        std::mem::take(&mut self.result)
     }
 
-    // 初始化正则表达式
-    fn init_regex(&mut self) {
-        self.regex.insert(
-            "title_page".to_string(),
-            Regex::new(r"(?i)^[ \t]*(title|credit|author[s]?|source|notes|draft date|date|watermark|contact( info)?|revision|copyright|font|font italic|font bold|font bold italic|metadata|tl|tc|tr|cc|br|bl|header|footer)\:.*").unwrap()
-        );
-        self.regex.insert(
-            "section".to_string(),
-            Regex::new(r"^[ \t]*(#+)(?:\s*)(.*)").unwrap(),
-        );
-        self.regex.insert(
-            "synopsis".to_string(),
-            Regex::new(r"^[ \t]*(?:\=)(.*)").unwrap(),
-        );
-        self.regex.insert(
-            "scene_heading".to_string(),
-            Regex::new(r"^[ \t]*([.]|(?i:int|ext|est|int[.]?\/ext|i[.]?\/e)[. ])\s*([^#]*)(#\s*[^\s].*#)?\s*$").unwrap()
-        );
-        self.regex.insert(
-            "scene_number".to_string(),
-            Regex::new(r"#\s*(?:\$\{\s*([^\}\s]*)\s*\})?\s*([^#]*)\s*#").unwrap(),
-        );
-        self.regex.insert(
-            "transition".to_string(),
-            Regex::new(r"^\s*(?:(>)([^\n\r<]*)|[A-Z ]+TO:)$").unwrap(),
-        );
-        self.regex.insert(
-            "character".to_string(),
-            Regex::new(
-                r"^[ \t]*((\p{Lu}[^\p{Ll}\r\n@]*)|(@[^\r\n\(（\^]*))(\(.*\)|（.*）)?(\s*\^)?\s*$",
-            )
-            .unwrap(),
-        );
-        self.regex.insert(
-            "parenthetical".to_string(),
-            Regex::new(r"^[ \t]*(\(.+\)|（.+）)\s*$").unwrap(),
-        );
-        self.regex.insert(
-            "parenthetical_start".to_string(),
-            Regex::new(r"^[ \t]*(?:\(|（)[^\)）]*$").unwrap(),
-        );
-        self.regex.insert(
-            "parenthetical_end".to_string(),
-            Regex::new(r"^.*(?:\)|）)\s*$").unwrap(),
-        );
-        self.regex
-            .insert("action".to_string(), Regex::new(r"^(.+)").unwrap());
-        self.regex.insert(
-            "centered".to_string(),
-            Regex::new(r"^[ \t]*>\s*(.+)\s*<\s*$").unwrap(),
-        );
-        self.regex.insert(
-            "page_break".to_string(),
-            Regex::new(r"^\s*\={3,}\s*$").unwrap(),
-        );
-        self.regex
-            .insert("line_break".to_string(), Regex::new(r"^ {2,}$").unwrap());
-        self.regex.insert(
-            "note_inline".to_string(),
-            Regex::new(r"\[{2}([^\[].+?)\]{2}").unwrap(),
-        );
-        self.regex.insert(
-            "emphasis".to_string(),
-            Regex::new(r"(_|\*{1,3}|_\*{1,3}|\*{1,3}_)(.+)(_|\*{1,3}|_\*{1,3}|\*{1,3}_)").unwrap(),
-        );
-        self.regex.insert(
-            "bold_italic_underline".to_string(),
-            Regex::new(r"(_{1}\*{3}|\*{3}_{1})(.+?)(\*{3}_{1}|_{1}\*{3})").unwrap(),
-        );
-        self.regex.insert(
-            "bold_underline".to_string(),
-            Regex::new(r"(_{1}\*{2}|\*{2}_{1})(.+?)(\*{2}_{1}|_{1}\*{2})").unwrap(),
-        );
-        self.regex.insert(
-            "italic_underline".to_string(),
-            Regex::new(r"(_{1}\*{1}|\*{1}_{1})(.+?)(\*{1}_{1}|_{1}\*{1})").unwrap(),
-        );
-        self.regex.insert(
-            "bold_italic".to_string(),
-            Regex::new(r"(\*{3})(.+?)(\*{3})").unwrap(),
-        );
-        self.regex.insert(
-            "bold".to_string(),
-            Regex::new(r"(\*{2})(.+?)(\*{2})").unwrap(),
-        );
-        self.regex.insert(
-            "italic".to_string(),
-            Regex::new(r"(\*{1})(.+?)(\*{1})").unwrap(),
-        );
-        self.regex.insert(
-            "underline".to_string(),
-            Regex::new(r"(_{1})(.+?)(_{1})").unwrap(),
-        );
+    /// 配置的场景标题前缀、转场关键字或全角括号设置变化时换用对应的正则
+    fn update_regex(&mut self, key: RegexKey) {
+        if self.regex_key == key {
+            return;
+        }
+        self.regex = compiled_regex(&key);
+        self.regex_key = key;
+    }
+
+    /// 转场行匹配到的关键字：优先取配置的自定义关键字，其次是 "CUT TO:" 这样的内置写法（整行），
+    /// 其它以 > 强制的转场没有关键字
+    fn transition_keyword(&self, text: &str) -> Option<String> {
+        let text = text.trim().trim_start_matches('>').trim();
+        let upper = text.to_uppercase();
+        self.regex_key
+            .transition_keywords
+            .iter()
+            .map(|k| k.trim())
+            .find(|k| !k.is_empty() && upper.ends_with(&k.to_uppercase()))
+            .map(|k| k.to_string())
+            .or_else(|| BUILTIN_TRANSITION.is_match(text).then(|| text.to_string()))
+    }
+
+    // 按标题页模板调整各字段的位置，模板中没有的字段沿用默认位置
+    fn update_title_page_display(&mut self, template: &str) {
+        self.title_page_display.clear();
+        self.init_title_page_display();
+        if let Some(template) = TitlePageTemplate::from_name(template) {
+            for field in template.fields() {
+                self.title_page_display.insert(
+                    field.key.to_string(),
+                    TitleKeywordFormat {
+                        position: field.position.to_string(),
+                        index: field.index,
+                    },
+                );
+            }
+        }
     }
 
     // 初始化标题页显示配置
@@ -3007,6 +3617,14 @@ need_process_outline_note: 0,
             },
         );
 
+        self.title_page_display.insert(
+            "image".to_string(),
+            TitleKeywordFormat {
+                position: "tc".to_string(),
+                index: 0,
+            },
+        );
+
         self.title_page_display.insert(
             "br".to_string(),
             TitleKeywordFormat {
@@ -3051,3 +3669,496 @@ need_process_outline_note: 0,
         );
     }
 }
+
+// 不依赖配置的正则，场景标题、转场、角色和括号注释按默认配置编译，解析时按配置替换
+/// 按名称取用的一套正则，各次解析共用
+type RegexMap = Arc<HashMap<String, Regex>>;
+
+/// 随配置变化的正则所取决的配置值
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RegexKey {
+    /// 编入 scene_heading 正则的自定义场景标题前缀
+    scene_heading_prefixes: Vec<String>,
+    /// 编入 transition 正则的自定义转场关键字
+    transition_keywords: Vec<String>,
+    /// 角色、括号注释的正则是否接受全角括号
+    full_width_markers: bool,
+}
+
+impl Default for RegexKey {
+    // 与 BASE_REGEX 对应
+    fn default() -> Self {
+        RegexKey {
+            scene_heading_prefixes: Vec::new(),
+            transition_keywords: Vec::new(),
+            full_width_markers: true,
+        }
+    }
+}
+
+/// 按配置值取得编译好的整套正则，同样的配置只编译一次，供各次解析共用
+fn compiled_regex(key: &RegexKey) -> RegexMap {
+    static CACHE: OnceLock<Mutex<HashMap<RegexKey, RegexMap>>> = OnceLock::new();
+    if *key == RegexKey::default() {
+        return BASE_REGEX.clone();
+    }
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(regex) = cache.get(key) {
+        return regex.clone();
+    }
+
+    let mut regex = HashMap::clone(&BASE_REGEX);
+    regex.insert(
+        "scene_heading".to_string(),
+        scene_heading_regex(&key.scene_heading_prefixes),
+    );
+    regex.insert(
+        "transition".to_string(),
+        transition_regex(&key.transition_keywords),
+    );
+    for (name, re) in marker_regexes(key.full_width_markers) {
+        regex.insert(name.to_string(), re);
+    }
+    let regex = Arc::new(regex);
+    // 配置组合通常只有几种，编辑器中反复修改配置时才会越积越多
+    if cache.len() >= 32 {
+        cache.clear();
+    }
+    cache.insert(key.clone(), regex.clone());
+    regex
+}
+
+fn base_regex() -> HashMap<String, Regex> {
+    let mut regex = HashMap::new();
+    regex.insert(
+        "title_page".to_string(),
+        Regex::new(r"(?i)^[ \t]*(title|credit|author[s]?|source|notes|draft date|date|watermark|contact( info)?|revision|copyright|font|font italic|font bold|font bold italic|metadata|image|tl|tc|tr|cc|br|bl|header|footer)\:.*").unwrap()
+    );
+    regex.insert(
+        "title_page_key_like".to_string(),
+        Regex::new(r"^([^\s:：][^:：]{0,29})[:：]").unwrap(),
+    );
+    regex.insert(
+        "section".to_string(),
+        Regex::new(r"^[ \t]*(#+)(?:\s*)(.*)").unwrap(),
+    );
+    regex.insert(
+        "synopsis".to_string(),
+        Regex::new(r"^[ \t]*(?:\=)(.*)").unwrap(),
+    );
+    regex.insert("scene_heading".to_string(), scene_heading_regex(&[]));
+    // 删去的场景：单独一行大写的 OMIT 或 OMITTED，可带场景编号，如 "OMITTED #12#"
+    regex.insert(
+        "omitted_scene".to_string(),
+        Regex::new(r"^[ \t]*[.]?(?:OMIT|OMITTED)[ \t]*(#[^#]*#)?[ \t]*$").unwrap(),
+    );
+    regex.insert(
+        "scene_number".to_string(),
+        Regex::new(r"#\s*(?:\$\{\s*([^\}\s]*)\s*\})?\s*([^#]*)\s*#").unwrap(),
+    );
+    // 插图扩展语法：单独成行的 [[img: path.png | 宽x高]]，宽高单位为英寸，可省略
+    regex.insert(
+        "image".to_string(),
+        Regex::new(r"^\s*\[\[\s*img\s*[:：](.*)\]\]\s*$").unwrap(),
+    );
+    regex.insert(
+        "image_spec".to_string(),
+        Regex::new(r"^\s*([^|]*?)\s*(?:\|\s*([\d.]+)?\s*(?:[xX×*]\s*([\d.]+))?\s*)?$").unwrap(),
+    );
+    // 剧本变量：{{PROTAGONIST}}
+    regex.insert(
+        "variable".to_string(),
+        Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap(),
+    );
+    // 注解分类标签：[[todo: ...]]、[[prod: ...]]
+    regex.insert(
+        "note_tag".to_string(),
+        Regex::new(r"^\s*([A-Za-z][\w-]*)\s*[:：]\s*").unwrap(),
+    );
+    regex.insert("transition".to_string(), transition_regex(&[]));
+    for (name, marker) in marker_regexes(true) {
+        regex.insert(name.to_string(), marker);
+    }
+    regex.insert("action".to_string(), Regex::new(r"^(.+)").unwrap());
+    regex.insert(
+        "centered".to_string(),
+        Regex::new(r"^[ \t]*>\s*(.+)\s*<\s*$").unwrap(),
+    );
+    regex.insert(
+        "page_break".to_string(),
+        Regex::new(r"^\s*\={3,}\s*$").unwrap(),
+    );
+    regex.insert("line_break".to_string(), Regex::new(r"^ {2,}$").unwrap());
+    regex.insert(
+        "note_inline".to_string(),
+        Regex::new(r"\[{2}([^\[].+?)\]{2}").unwrap(),
+    );
+    regex.insert(
+        "emphasis".to_string(),
+        Regex::new(r"(_|\*{1,3}|_\*{1,3}|\*{1,3}_)(.+)(_|\*{1,3}|_\*{1,3}|\*{1,3}_)").unwrap(),
+    );
+    regex.insert(
+        "bold_italic_underline".to_string(),
+        Regex::new(r"(_{1}\*{3}|\*{3}_{1})(.+?)(\*{3}_{1}|_{1}\*{3})").unwrap(),
+    );
+    regex.insert(
+        "bold_underline".to_string(),
+        Regex::new(r"(_{1}\*{2}|\*{2}_{1})(.+?)(\*{2}_{1}|_{1}\*{2})").unwrap(),
+    );
+    regex.insert(
+        "italic_underline".to_string(),
+        Regex::new(r"(_{1}\*{1}|\*{1}_{1})(.+?)(\*{1}_{1}|_{1}\*{1})").unwrap(),
+    );
+    regex.insert(
+        "bold_italic".to_string(),
+        Regex::new(r"(\*{3})(.+?)(\*{3})").unwrap(),
+    );
+    regex.insert(
+        "bold".to_string(),
+        Regex::new(r"(\*{2})(.+?)(\*{2})").unwrap(),
+    );
+    regex.insert(
+        "italic".to_string(),
+        Regex::new(r"(\*{1})(.+?)(\*{1})").unwrap(),
+    );
+    regex.insert(
+        "underline".to_string(),
+        Regex::new(r"(_{1})(.+?)(_{1})").unwrap(),
+    );
+    regex
+}
+
+// 去除空格、标点和特殊字符
+fn strip_non_counted_chars(text: &str) -> String {
+    NON_COUNTED_CHARS.replace_all(text, "").to_string()
+}
+
+// 按字符数估算动作时长
+fn action_duration(text: &str, config_x: Option<f64>) -> f64 {
+    let x = config_x.unwrap_or(0.4); // 默认值: 0.4秒/字符
+    strip_non_counted_chars(text).chars().count() as f64 * x
+}
+
+// 按字符数和标点估算对白时长
+fn char_rate_duration(
+    text: &str,
+    config_x: Option<f64>,
+    config_long: Option<f64>,
+    config_short: Option<f64>,
+) -> f64 {
+    let model = CharRateModel {
+        sec_per_char: config_x.unwrap_or(0.3), // 默认值: 0.3秒/字符
+        sec_per_punc_long: config_long.unwrap_or(0.75), // 长标点默认值: 0.75秒
+        sec_per_punc_short: config_short.unwrap_or(0.3), // 短标点默认值: 0.3秒
+    };
+    model.dialogue_duration(text, None)
+}
+
+// 按 scene_heading_normalization 规范场景标题：第一个连字符两侧各留一个空格、空白换为空格，
+// 再按模式转换大小写；"preserve" 时只规范空白，保留原文的大小写和连字符
+fn normalize_scene_heading(text: &str, cfg: &Conf) -> String {
+    let text = match text.find('-') {
+        Some(pos) if cfg.scene_heading_normalization != "preserve" => {
+            format!("{} - {}", &text[..pos], &text[pos + 1..])
+        }
+        _ => text.to_string(),
+    };
+    scene_heading_case(&text, cfg).replace(|c: char| c.is_whitespace(), " ")
+}
+
+// 场景标题的大小写："upper"（默认）转为大写，"preserve" 保留原文，"locale:tr" 等按语言规则转为大写
+fn scene_heading_case(text: &str, cfg: &Conf) -> String {
+    let mode = cfg.scene_heading_normalization.as_str();
+    match mode.strip_prefix("locale:") {
+        Some(lang) => to_uppercase_locale(text, lang),
+        None if mode == "preserve" => text.to_string(),
+        None => text.to_uppercase(),
+    }
+}
+
+// 按 scene_number_format 生成自动场景编号，含幕序号时使用幕内的场景序号
+fn format_scene_number(format: &str, scene: usize, act: usize, act_scene: usize) -> String {
+    let per_act = format.contains("{act");
+    SCENE_NUMBER_PLACEHOLDER
+        .replace_all(format, |caps: &regex::Captures| {
+            let width = caps.get(2).map_or(0, |m| m.as_str().parse().unwrap_or(0));
+            match &caps[1] {
+                "act_roman" => to_roman(act),
+                "act" => format!("{:0width$}", act, width = width),
+                _ if per_act => format!("{:0width$}", act_scene, width = width),
+                _ => format!("{:0width$}", scene, width = width),
+            }
+        })
+        .to_string()
+}
+
+// 大写罗马数字，0 为空
+fn to_roman(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut roman = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            roman.push_str(numeral);
+            n -= value;
+        }
+    }
+    roman
+}
+
+/// 解析完所有行后对 token 的最后处理：action 中出现的角色计入所在场景、删去场景的内容、排版美化
+///
+/// 逐场景交出 token 的解析（`FountainParser::parse_streaming`）不做这一步，由调用方对交出后的 token 完成
+pub(crate) fn finish_tokens<T: FinishToken>(
+    properties: &mut ScreenplayProperties,
+    tokens: &mut Vec<T>,
+    cfg: &Conf,
+) {
+    // 所有action里面出现过的角色，也应该计入在场景中出现过
+    let mut last_scene_idx: i32 = -1;
+    for token in tokens.iter_mut() {
+        if token.token_kind() == "scene_heading" {
+            last_scene_idx += 1;
+        } else if last_scene_idx >= 0 && token.token_kind() == "action" {
+            if !token.token_text().is_empty() {
+                let mut char_map: HashMap<String, Vec<usize>> = HashMap::new(); // 角色，在行中字符index的start和end
+
+                // 先将result.properties.characters按照角色名的长度排序，长的在前面
+                // 别名也参与匹配，匹配到后记为规范角色名
+                let mut sorted_keys: Vec<String> = properties.characters.keys().cloned().collect();
+                sorted_keys.extend(
+                    properties
+                        .character_aliases
+                        .iter()
+                        .filter(|(_, name)| properties.characters.contains_key(*name))
+                        .map(|(alias, _)| alias.clone()),
+                );
+                sorted_keys.sort_by(|a, b| b.len().cmp(&a.len()));
+
+                for k in sorted_keys {
+                    // 需要判断，角色名匹配，不能在同一行中索引重叠
+                    let mut added = false;
+                    let mut i = 0;
+
+                    while i < token.token_text().len() {
+                        if let Some(idx) = token.token_text()[i..].find(&k) {
+                            let start = i + idx;
+                            let end = start + k.len();
+                            let mut overlap = false;
+
+                            for (_existing_k, v) in &char_map {
+                                if v[0] < end && v[1] > start {
+                                    overlap = true;
+                                    break;
+                                }
+                            }
+
+                            if !overlap {
+                                char_map.insert(k.clone(), vec![start, end]);
+                                added = true;
+                                break;
+                            } else {
+                                i = end; // 继续查找下一个
+                            }
+                        } else {
+                            break;
+                        }
+                    }
+
+                    if added {
+                        let k = properties.canonical_character(&k);
+                        if let Some(v) = properties.characters.get_mut(&k) {
+                            // 角色在action中出现过，也算在场景中出现过
+                            if !v.contains(&(last_scene_idx as usize)) {
+                                v.push(last_scene_idx as usize);
+                            }
+                        }
+
+                        token.add_action_character(k);
+                    }
+                }
+            }
+        }
+    }
+
+    // 转换result.properties.characters成result.properties.characterSceneNumber
+    let mut character_scene_number: HashMap<String, HashSet<String>> = HashMap::new();
+
+    for (k, v) in &properties.characters {
+        let mut scene_numbers = HashSet::new();
+
+        // 按照场景头索引排序，去掉负数的场景号
+        let mut sorted_indices: Vec<&usize> = v.iter().collect();
+        sorted_indices.sort();
+
+        for &idx in sorted_indices {
+            if idx < properties.scenes.len() {
+                if let Some(scene) = properties.scenes.get(idx) {
+                    if let Some(number) = scene.get("number") {
+                        if let Some(number_str) = number.as_str() {
+                            scene_numbers.insert(number_str.to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        character_scene_number.insert(k.clone(), scene_numbers);
+    }
+
+    properties.character_scene_number = Some(character_scene_number);
+
+    // 删去的场景只保留场景标题，到下一个场景标题或章节为止的内容都不输出
+    if !properties.omitted_scenes.is_empty() {
+        let mut in_omitted = false;
+        tokens.retain(|token| {
+            if matches!(token.token_kind(), "scene_heading" | "section") {
+                in_omitted = token.omitted();
+                return true;
+            }
+            !in_omitted || token.token_kind() == "page_break"
+        });
+    }
+
+    // 排版美化（引号、破折号、省略号），在生成 HTML 之前
+    if !cfg.typography.is_empty() {
+        for token in tokens.iter_mut() {
+            if matches!(
+                token.token_kind(),
+                "action" | "dialogue" | "parenthetical" | "lyric" | "centered" | "synopsis"
+            ) {
+                token.typeset(&cfg.typography);
+            }
+        }
+    }
+}
+
+/// [`finish_tokens`] 需要的 token 操作
+pub(crate) trait FinishToken {
+    fn token_kind(&self) -> &str;
+    fn token_text(&self) -> &str;
+    /// 是否是删去的场景标题
+    fn omitted(&self) -> bool;
+    /// 记录 action 中出现的角色
+    fn add_action_character(&mut self, name: String);
+    /// 按 `language` 美化文本
+    fn typeset(&mut self, language: &str);
+}
+
+impl FinishToken for ScriptToken {
+    fn token_kind(&self) -> &str {
+        &self.token_type
+    }
+
+    fn token_text(&self) -> &str {
+        &self.text
+    }
+
+    fn omitted(&self) -> bool {
+        self.is_omitted()
+    }
+
+    fn add_action_character(&mut self, name: String) {
+        let chars = self.characters_action.get_or_insert_with(Vec::new);
+        if !chars.contains(&name) {
+            chars.push(name);
+        }
+    }
+
+    fn typeset(&mut self, language: &str) {
+        self.text = crate::typography::typeset(&self.text, language);
+        if let Some(text) = &self.text_no_notes {
+            self.text_no_notes = Some(crate::typography::typeset(text, language));
+        }
+    }
+}
+
+/// 场景标题正则：以 "." 强制，或以 INT/EXT 等前缀（及 `extra_prefixes`）开头后接句点或空格
+pub(crate) fn scene_heading_regex(extra_prefixes: &[String]) -> Regex {
+    let mut prefixes = String::from(r"int|ext|est|int[.]?\/ext|i[.]?\/e");
+    for prefix in extra_prefixes {
+        let prefix = prefix.trim().trim_end_matches('.');
+        if !prefix.is_empty() {
+            prefixes.push('|');
+            prefixes.push_str(&regex::escape(prefix));
+        }
+    }
+    Regex::new(&format!(
+        r"^[ \t]*([.]|(?i:{})[. ])\s*([^#]*)(#\s*[^\s].*#)?\s*$",
+        prefixes
+    ))
+    .unwrap()
+}
+
+/// 角色、括号注释的正则，`full_width` 为 true 时括号也可以是全角的（）
+fn marker_regexes(full_width: bool) -> [(&'static str, Regex); 4] {
+    let (open, close) = if full_width {
+        (r"\(（", r"\)）")
+    } else {
+        (r"\(", r"\)")
+    };
+    let parenthesized = if full_width {
+        r"\(.+\)|（.+）"
+    } else {
+        r"\(.+\)"
+    };
+    let extension = if full_width {
+        r"\(.*\)|（.*）"
+    } else {
+        r"\(.*\)"
+    };
+    [
+        (
+            "character",
+            Regex::new(&format!(
+                r"^[ \t]*((\p{{Lu}}[^\p{{Ll}}\r\n@]*)|(@[^\r\n{}\^]*))({})?(\s*\^\^?)?\s*$",
+                open, extension
+            ))
+            .unwrap(),
+        ),
+        (
+            "parenthetical",
+            Regex::new(&format!(r"^[ \t]*({})\s*$", parenthesized)).unwrap(),
+        ),
+        (
+            "parenthetical_start",
+            Regex::new(&format!(r"^[ \t]*[{}][^{}]*$", open, close)).unwrap(),
+        ),
+        (
+            "parenthetical_end",
+            Regex::new(&format!(r"^.*[{}]\s*$", close)).unwrap(),
+        ),
+    ]
+}
+
+/// 转场正则：以 > 强制（捕获组 1、2），或大写的 "XXX TO:"，或以 `extra_keywords` 中的关键字结尾
+fn transition_regex(extra_keywords: &[String]) -> Regex {
+    let keywords: Vec<String> = extra_keywords
+        .iter()
+        .map(|k| k.trim())
+        .filter(|k| !k.is_empty())
+        .map(regex::escape)
+        .collect();
+    let extra = if keywords.is_empty() {
+        String::new()
+    } else {
+        format!(r"|.*(?i:{})\s*", keywords.join("|"))
+    };
+    Regex::new(&format!(r"^\s*(?:(>)([^\n\r<]*)|[A-Z ]+TO:{})$", extra)).unwrap()
+}
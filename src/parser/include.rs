@@ -0,0 +1,113 @@
+//! 引用指令预处理
+//!
+//! 在解析前把单独成行的 `{include: 第一幕.fountain}` 替换为被引用文件的内容（相对路径以所在文件的目录为准），
+//! 可以多层嵌套，出现循环引用时报错。展开后的每一行都记录了原始文件、行号和该行在原文中的偏移。
+
+use crate::models::SourceOrigin;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+lazy_static! {
+    static ref INCLUDE_REGEX: Regex =
+        Regex::new(r"(?i)^\s*\{\s*include\s*[:：]\s*(.+?)\s*\}\s*$").unwrap();
+}
+
+/// 引用展开错误
+#[derive(Error, Debug)]
+pub enum IncludeError {
+    #[error("读取文件 {0} 失败: {1}")]
+    Io(String, #[source] std::io::Error),
+
+    #[error("循环引用: {0}")]
+    Cycle(String),
+}
+
+/// 展开引用后的剧本
+#[derive(Debug, Clone)]
+pub struct ExpandedScript {
+    pub script: String,
+    /// 与 `script` 的每一行一一对应
+    pub origins: Vec<SourceOrigin>,
+    /// 与 `script` 的每一行一一对应，该行在原始文件原文（未统一换行符）中的起始字节偏移和 UTF-16 偏移
+    pub line_starts: Vec<(usize, usize)>,
+}
+
+/// 读取剧本文件并展开其中的引用指令
+pub fn expand_includes(path: &Path) -> Result<ExpandedScript, IncludeError> {
+    let mut expanded = ExpandedScript {
+        script: String::new(),
+        origins: Vec::new(),
+        line_starts: Vec::new(),
+    };
+    let mut lines = Vec::new();
+    expand_file(path, &mut Vec::new(), &mut lines, &mut expanded)?;
+    expanded.script = lines.join("\n");
+    Ok(expanded)
+}
+
+fn expand_file(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    lines: &mut Vec<String>,
+    expanded: &mut ExpandedScript,
+) -> Result<(), IncludeError> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| IncludeError::Io(path.display().to_string(), e))?;
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(IncludeError::Cycle(chain.join(" -> ")));
+    }
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| IncludeError::Io(path.display().to_string(), e))?;
+    let line_starts = line_starts(&raw);
+    let content = raw.replace("\r\n", "\n");
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let file = path.display().to_string();
+
+    stack.push(canonical);
+    // 与解析器一致，\r 和 \n 都当作换行
+    let mut file_lines: Vec<&str> = content.split(&['\r', '\n'][..]).collect();
+    // 被引用文件末尾的换行不额外产生空行
+    if stack.len() > 1 && file_lines.last() == Some(&"") {
+        file_lines.pop();
+    }
+    for (line_no, line) in file_lines.into_iter().enumerate() {
+        if let Some(captures) = INCLUDE_REGEX.captures(line) {
+            expand_file(&dir.join(&captures[1]), stack, lines, expanded)?;
+            continue;
+        }
+        lines.push(line.to_string());
+        expanded.origins.push(SourceOrigin {
+            file: file.clone(),
+            line: line_no,
+        });
+        expanded.line_starts.push(line_starts[line_no]);
+    }
+    stack.pop();
+    Ok(())
+}
+
+// 原文每一行的起始字节偏移和 UTF-16 偏移，`\r\n` 算一个换行，行号与统一换行符后的行号一致
+fn line_starts(raw: &str) -> Vec<(usize, usize)> {
+    let mut starts = vec![(0, 0)];
+    let mut utf16 = 0;
+    let mut chars = raw.char_indices().peekable();
+    while let Some((byte, c)) = chars.next() {
+        utf16 += c.len_utf16();
+        if c == '\r' && chars.peek().is_some_and(|&(_, next)| next == '\n') {
+            chars.next();
+            utf16 += 1;
+            starts.push((byte + 2, utf16));
+        } else if c == '\r' || c == '\n' {
+            starts.push((byte + 1, utf16));
+        }
+    }
+    starts
+}
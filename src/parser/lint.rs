@@ -0,0 +1,143 @@
+//! 剧本检查
+//!
+//! 在解析结果上做一遍静态检查，输出带行列范围的诊断信息，供编辑器显示波浪线。
+//! 解析过程中才能发现的问题（未闭合的注释/注解、重复场景编号、无法识别的标题页字段）
+//! 由解析器记录在 `ParseOutput::diagnostics` 中，这里一并返回。
+
+use crate::models::{Conf, Diagnostic, DiagnosticSeverity, Position, Range, ScriptToken};
+use crate::parser::ParseOutput;
+use crate::utils::is_blank_line_after_style;
+use std::collections::HashMap;
+
+/// 检查解析结果，返回按位置排序的诊断列表
+///
+/// 诊断的列取自 token 的 [`SourceRange`](crate::models::SourceRange)，按原文计算，
+/// 不受解析器去掉的样式标记、强制符号等的影响
+pub fn lint(output: &ParseOutput, cfg: &Conf) -> Vec<Diagnostic> {
+    let mut diagnostics = output.diagnostics.clone();
+
+    check_orphan_parentheticals(&output.tokens, &mut diagnostics);
+    check_single_line_characters(&output.tokens, &mut diagnostics);
+    check_empty_scenes(&output.tokens, cfg, &mut diagnostics);
+
+    diagnostics.sort_by(|a, b| {
+        (a.range.start.line, a.range.start.character)
+            .cmp(&(b.range.start.line, b.range.start.character))
+    });
+    diagnostics
+}
+
+/// 覆盖 token 所在源码行的诊断，没有源码范围时按 token 文本计算列
+fn token_diagnostic(
+    token: &ScriptToken,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+) -> Diagnostic {
+    let Some(source) = token.source_range else {
+        return Diagnostic::line(token.line, &token.text, severity, code, message);
+    };
+    let last_line = token.line + token.text.matches('\n').count();
+    let range = Range {
+        start: Position {
+            line: token.line,
+            character: source.column_start,
+        },
+        end: Position {
+            line: last_line,
+            character: source.column_end,
+        },
+    };
+    Diagnostic::new(range, severity, code, message)
+}
+
+/// 对话块之外的括号行，通常是角色名写错或漏了角色名
+fn check_orphan_parentheticals(tokens: &[ScriptToken], diagnostics: &mut Vec<Diagnostic>) {
+    for token in tokens.iter().filter(|t| t.token_type == "action") {
+        let text = token.text.trim();
+        let opened = text.starts_with('(') || text.starts_with('（');
+        let closed = text.ends_with(')') || text.ends_with('）');
+        if opened && closed {
+            diagnostics.push(token_diagnostic(
+                token,
+                DiagnosticSeverity::Warning,
+                "orphan-parenthetical",
+                "括号行不在对话中，会被当作动作描述".to_string(),
+            ));
+        }
+    }
+}
+
+/// 只说过一句话的角色，常见于角色名拼写不一致
+fn check_single_line_characters(tokens: &[ScriptToken], diagnostics: &mut Vec<Diagnostic>) {
+    let mut cues: HashMap<&str, Vec<&ScriptToken>> = HashMap::new();
+    for token in tokens.iter().filter(|t| t.token_type == "character") {
+        if let Some(name) = &token.character {
+            cues.entry(name.as_str()).or_default().push(token);
+        }
+    }
+
+    for (name, list) in cues {
+        if list.len() == 1 {
+            diagnostics.push(token_diagnostic(
+                list[0],
+                DiagnosticSeverity::Information,
+                "single-line-character",
+                format!("角色 {} 只有一句对白，请确认角色名是否写错", name),
+            ));
+        }
+    }
+}
+
+/// 没有任何动作或对白的场景
+fn check_empty_scenes(tokens: &[ScriptToken], cfg: &Conf, diagnostics: &mut Vec<Diagnostic>) {
+    // 每场另起一页时，空场景会输出一张空白页
+    let severity = if cfg.each_scene_on_new_page {
+        DiagnosticSeverity::Warning
+    } else {
+        DiagnosticSeverity::Information
+    };
+
+    let mut open_scene: Option<&ScriptToken> = None;
+    let mut has_content = false;
+    for token in tokens {
+        match token.token_type.as_str() {
+            "scene_heading" | "section" => {
+                if let Some(scene) = open_scene.take() {
+                    if !has_content {
+                        push_empty_scene(scene, severity, diagnostics);
+                    }
+                }
+                if token.token_type == "scene_heading" {
+                    open_scene = Some(token);
+                    has_content = false;
+                }
+            }
+            "character" | "dialogue" | "parenthetical" | "lyric" | "centered" | "transition" => {
+                has_content = true;
+            }
+            "action" if !is_blank_line_after_style(&token.text) => {
+                has_content = true;
+            }
+            _ => {}
+        }
+    }
+    if let Some(scene) = open_scene {
+        if !has_content {
+            push_empty_scene(scene, severity, diagnostics);
+        }
+    }
+}
+
+fn push_empty_scene(
+    scene: &ScriptToken,
+    severity: DiagnosticSeverity,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    diagnostics.push(token_diagnostic(
+        scene,
+        severity,
+        "empty-scene",
+        "场景没有任何动作或对白".to_string(),
+    ));
+}
@@ -0,0 +1,147 @@
+//! 多文件剧本合并
+//!
+//! 把按集、按幕拆分的多个 Fountain 文件拼接成一个剧本再解析：
+//! 标题页按文件顺序合并，同一字段以先出现的文件为准；各文件中显式写出的场景编号被去掉，
+//! 由解析器在合并后的剧本中连续编号；同时记录每个文件在合并剧本中的行范围，便于把诊断信息定位回原文件。
+
+use crate::models::Conf;
+use crate::parser::scene_numbers::{scan_scene_numbers, SCENE_NUMBER_REGEX};
+use crate::parser::{FountainParser, ParseOutput};
+use serde::{Deserialize, Serialize};
+
+/// 参与合并的一个剧本文件
+#[derive(Debug, Clone)]
+pub struct MergeSource {
+    /// 文件名，用于错误定位
+    pub name: String,
+    pub script: String,
+}
+
+/// 一个文件的正文在合并剧本中的位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceLineMap {
+    pub name: String,
+    /// 正文在合并剧本中的起始行（从 0 开始）
+    pub merged_start: usize,
+    /// 正文在原文件中的起始行（跳过标题页）
+    pub source_start: usize,
+    /// 正文行数
+    pub line_count: usize,
+}
+
+/// 合并结果
+#[derive(Debug, Clone)]
+pub struct MergedScript {
+    /// 合并后的 Fountain 文本
+    pub script: String,
+    pub output: ParseOutput,
+    pub sources: Vec<SourceLineMap>,
+}
+
+impl MergedScript {
+    /// 把合并剧本中的行号换算为 (文件名, 原文件行号)，标题页和文件之间的空行返回 None
+    pub fn locate(&self, line: usize) -> Option<(&str, usize)> {
+        self.sources
+            .iter()
+            .find(|s| (s.merged_start..s.merged_start + s.line_count).contains(&line))
+            .map(|s| (s.name.as_str(), s.source_start + line - s.merged_start))
+    }
+}
+
+/// 合并多个剧本文件并解析
+pub fn merge_scripts(sources: &[MergeSource], config: &Conf) -> MergedScript {
+    // 标题页字段：(小写字段名, 原文各行)
+    let mut title_entries: Vec<(String, Vec<String>)> = Vec::new();
+    let mut bodies: Vec<(usize, Vec<String>)> = Vec::new();
+
+    for source in sources {
+        let script = source.script.replace("\r\n", "\n");
+        let mut lines: Vec<String> = script.split(&['\r', '\n'][..]).map(String::from).collect();
+
+        // 去掉显式场景编号，合并后统一连续编号
+        for entry in scan_scene_numbers(&script, config) {
+            if entry.explicit.is_some() || entry.var.is_some() {
+                if let Some(line) = lines.get_mut(entry.line) {
+                    *line = SCENE_NUMBER_REGEX.replace(line, "").trim_end().to_string();
+                }
+            }
+        }
+
+        let title_len = title_page_len(&script, &lines, config);
+        for entry in split_title_entries(&lines[..title_len]) {
+            if !title_entries.iter().any(|(key, _)| *key == entry.0) {
+                title_entries.push(entry);
+            }
+        }
+
+        // 跳过标题页之后的空行
+        let body_start = lines[title_len..]
+            .iter()
+            .position(|l| !l.trim().is_empty())
+            .map_or(lines.len(), |p| title_len + p);
+        let mut body = lines.split_off(body_start);
+        while body.last().is_some_and(|l| l.trim().is_empty()) {
+            body.pop();
+        }
+        bodies.push((body_start, body));
+    }
+
+    let mut merged: Vec<String> = title_entries.into_iter().flat_map(|(_, lines)| lines).collect();
+    let mut maps = Vec::new();
+    for (source, (source_start, body)) in sources.iter().zip(bodies) {
+        if !merged.is_empty() {
+            merged.push(String::new());
+        }
+        maps.push(SourceLineMap {
+            name: source.name.clone(),
+            merged_start: merged.len(),
+            source_start,
+            line_count: body.len(),
+        });
+        merged.extend(body);
+    }
+
+    let mut script = merged.join("\n");
+    script.push('\n');
+    let parser = FountainParser::new();
+    let output = parser.parse(&script, config, false, None);
+    MergedScript {
+        script,
+        output,
+        sources: maps,
+    }
+}
+
+// 标题页的行数：解析出标题页时，标题页到第一个空行为止
+fn title_page_len(script: &str, lines: &[String], config: &Conf) -> usize {
+    let parser = FountainParser::new();
+    let output = parser.parse(script, config, false, Some(false));
+    if output.title_page.values().all(|tokens| tokens.is_empty()) {
+        return 0;
+    }
+    lines
+        .iter()
+        .position(|l| l.trim().is_empty())
+        .unwrap_or(lines.len())
+}
+
+// 按字段拆分标题页，缩进的行是上一个字段的续行
+fn split_title_entries(lines: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut entries: Vec<(String, Vec<String>)> = Vec::new();
+    for line in lines {
+        let continuation = line.starts_with([' ', '\t']) || !line.contains(':');
+        match entries.last_mut() {
+            Some((_, entry)) if continuation => entry.push(line.clone()),
+            _ => {
+                let key = line
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_lowercase();
+                entries.push((key, vec![line.clone()]));
+            }
+        }
+    }
+    entries
+}
@@ -1,5 +1,13 @@
 pub mod fountain_parser;
 pub mod text_processor;
+pub mod scene_numbers;
+pub mod lint;
+pub mod merge;
+pub mod include;
+pub mod strict;
+pub mod source_map;
+pub mod spec;
+pub mod borrowed;
 
 pub use fountain_parser::FountainParser;
 pub use fountain_parser::ParseOutput;
@@ -7,6 +15,24 @@ pub use fountain_parser::TitleKeywordFormat;
 pub use text_processor::{
     process_token_text_style_char,
     generate_html,
-    generate_title_html
+    generate_title_html,
+    generate_html_document,
+    line_anchor_id,
+    HtmlLineMap
+};
+pub use lint::lint;
+pub use borrowed::{BorrowedOutput, BorrowedToken};
+pub use spec::{detect_extensions, used_extensions, Extension, ExtensionUse};
+pub use strict::{check_strict, parse_strict, ParseError};
+pub use include::{expand_includes, ExpandedScript, IncludeError};
+pub use merge::{merge_scripts, MergeSource, MergedScript, SourceLineMap};
+pub use scene_numbers::{
+    LockedNumberStyle,
+    SceneNumberEntry,
+    DuplicateSceneNumber,
+    scan_scene_numbers,
+    find_duplicate_scene_numbers,
+    lock_scene_numbers,
+    repair_duplicate_scene_numbers
 };
 pub use crate::utils::is_blank_line_after_style;
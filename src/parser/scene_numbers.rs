@@ -0,0 +1,323 @@
+//! 场景编号工具
+//!
+//! 为剧本中的场景标题写入显式的 `#n#` 编号，并锁定已有编号：
+//! 锁定后新增的场景不会打乱原有编号，而是按 12A（后缀）或 A12（前缀）的惯例插入。
+//! 同时提供重复编号（解析器仅用 "↑" 标记）的检测与修复。
+
+use crate::models::Conf;
+use crate::parser::FountainParser;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+lazy_static! {
+    /// 与解析器中 scene_number 正则保持一致：`#${var} num#`
+    pub(crate) static ref SCENE_NUMBER_REGEX: Regex =
+        Regex::new(r"#\s*(?:\$\{\s*([^\}\s]*)\s*\})?\s*([^#]*)\s*#").unwrap();
+}
+
+/// 新增场景的编号惯例
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LockedNumberStyle {
+    /// 12A、12B（英式）
+    #[default]
+    Suffix,
+    /// A12、B12（美式）
+    Prefix,
+}
+
+/// 剧本中一个场景标题的编号信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneNumberEntry {
+    /// 场景标题所在行（从 0 开始）
+    pub line: usize,
+    /// 源文本中显式写出的编号
+    pub explicit: Option<String>,
+    /// `${var}` 变量名
+    pub var: Option<String>,
+    /// 解析器计算出的实际编号（已去掉重复标记 "↑"）
+    pub number: String,
+    /// 与前面的场景编号重复，且不是通过同一变量有意复用
+    pub duplicate: bool,
+}
+
+/// 重复的场景编号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateSceneNumber {
+    pub number: String,
+    /// 使用了该编号的场景标题行（从 0 开始）
+    pub lines: Vec<usize>,
+}
+
+/// 扫描剧本中所有场景标题的编号
+pub fn scan_scene_numbers(script: &str, config: &Conf) -> Vec<SceneNumberEntry> {
+    let lines: Vec<&str> = script.split(&['\r', '\n'][..]).collect();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, config, false, Some(false));
+
+    let mut entries = Vec::new();
+    let mut seen_vars: HashSet<String> = HashSet::new();
+    for token in output.tokens.iter().filter(|t| t.is_type(&["scene_heading"])) {
+        let raw = lines.get(token.line).copied().unwrap_or("");
+        let mut explicit = None;
+        let mut var = None;
+        if let Some(caps) = SCENE_NUMBER_REGEX.captures(raw) {
+            var = caps
+                .get(1)
+                .map(|m| m.as_str().trim().to_string())
+                .filter(|s| !s.is_empty());
+            explicit = caps
+                .get(2)
+                .map(|m| m.as_str().trim().to_string())
+                .filter(|s| !s.is_empty());
+        }
+
+        let parsed = token.number.clone().unwrap_or_default();
+        let marked = parsed.starts_with('↑');
+        let number = parsed.trim_start_matches('↑').to_string();
+
+        // 同一变量的后续场景是有意复用编号（回到之前的场景），不算重复
+        let var_repeat = match &var {
+            Some(v) => !seen_vars.insert(v.clone()),
+            None => false,
+        };
+
+        entries.push(SceneNumberEntry {
+            line: token.line,
+            explicit,
+            var,
+            number,
+            duplicate: marked && !var_repeat,
+        });
+    }
+    entries
+}
+
+/// 找出所有重复的场景编号
+pub fn find_duplicate_scene_numbers(script: &str, config: &Conf) -> Vec<DuplicateSceneNumber> {
+    let entries = scan_scene_numbers(script, config);
+    let mut result: Vec<DuplicateSceneNumber> = Vec::new();
+    for entry in entries.iter().filter(|e| e.duplicate) {
+        if let Some(dup) = result.iter_mut().find(|d| d.number == entry.number) {
+            dup.lines.push(entry.line);
+            continue;
+        }
+        // 第一个使用该编号的场景
+        let first = entries
+            .iter()
+            .find(|e| e.number == entry.number && !e.duplicate)
+            .map(|e| e.line);
+        let mut lines: Vec<usize> = first.into_iter().collect();
+        lines.push(entry.line);
+        result.push(DuplicateSceneNumber {
+            number: entry.number.clone(),
+            lines,
+        });
+    }
+    result
+}
+
+/// 为所有场景写入显式编号并锁定
+///
+/// 已有显式编号的场景保持不变；没有编号的场景在相邻锁定编号之间按整数递增，
+/// 放不下时按 `style` 生成 12A / A12 形式的插入编号。
+pub fn lock_scene_numbers(script: &str, config: &Conf, style: LockedNumberStyle) -> String {
+    let entries = scan_scene_numbers(script, config);
+    // 没有显式编号、也不是复用变量编号的场景需要重新分配
+    let mut var_numbered: HashSet<String> = HashSet::new();
+    let unlocked: Vec<bool> = entries
+        .iter()
+        .map(|e| {
+            if e.explicit.is_some() {
+                if let Some(v) = &e.var {
+                    var_numbered.insert(v.clone());
+                }
+                return false;
+            }
+            match &e.var {
+                Some(v) => !var_numbered.contains(v) && var_numbered.insert(v.clone()),
+                None => true,
+            }
+        })
+        .collect();
+    rewrite_scene_numbers(script, &entries, &unlocked, false, style)
+}
+
+/// 修复重复的场景编号
+///
+/// 仅重新分配被标记为重复的场景，其它场景的编号保持不变。
+/// 修复后重复场景不再跳过自动计数，因此其它自动编号的场景也会写入当前编号，避免随之移位。
+pub fn repair_duplicate_scene_numbers(
+    script: &str,
+    config: &Conf,
+    style: LockedNumberStyle,
+) -> String {
+    let entries = scan_scene_numbers(script, config);
+    let unlocked: Vec<bool> = entries.iter().map(|e| e.duplicate).collect();
+    rewrite_scene_numbers(script, &entries, &unlocked, true, style)
+}
+
+/// 为 `unlocked` 的场景分配新编号并写回源文本，`lock_all` 时其余没有显式编号的场景也写入当前编号
+fn rewrite_scene_numbers(
+    script: &str,
+    entries: &[SceneNumberEntry],
+    unlocked: &[bool],
+    lock_all: bool,
+    style: LockedNumberStyle,
+) -> String {
+    // 锁定的编号都视为已占用
+    let mut used: HashSet<String> = entries
+        .iter()
+        .zip(unlocked)
+        .filter(|(_, u)| !**u)
+        .map(|(e, _)| e.number.clone())
+        .collect();
+
+    let mut assigned: HashMap<usize, String> = HashMap::new();
+    let mut seen_vars: HashSet<String> = HashSet::new();
+    let mut prev: Option<String> = None;
+    for (idx, entry) in entries.iter().enumerate() {
+        if !unlocked[idx] {
+            let var_repeat = match &entry.var {
+                Some(v) => !seen_vars.insert(v.clone()),
+                None => false,
+            };
+            if lock_all && entry.explicit.is_none() && !var_repeat {
+                assigned.insert(entry.line, entry.number.clone());
+            }
+            prev = Some(entry.number.clone());
+            continue;
+        }
+
+        // 下一个锁定编号的整数部分，整数递增不能超过它
+        let next_base = entries[idx + 1..]
+            .iter()
+            .zip(&unlocked[idx + 1..])
+            .find(|(_, u)| !**u)
+            .and_then(|(e, _)| split_number(&e.number).map(|(base, _)| base));
+
+        let number = next_scene_number(prev.as_deref(), next_base, &used, style);
+        used.insert(number.clone());
+        if let Some(v) = &entry.var {
+            seen_vars.insert(v.clone());
+        }
+        assigned.insert(entry.line, number.clone());
+        prev = Some(number);
+    }
+
+    let mut out = String::with_capacity(script.len() + assigned.len() * 6);
+    for (i, segment) in script.split_inclusive(&['\r', '\n'][..]).enumerate() {
+        let body = segment.trim_end_matches(&['\r', '\n'][..]);
+        let ending = &segment[body.len()..];
+        match assigned.get(&i) {
+            Some(number) => {
+                let var = entries.iter().find(|e| e.line == i).and_then(|e| e.var.clone());
+                out.push_str(&set_scene_number(body, var.as_deref(), number));
+            }
+            None => out.push_str(body),
+        }
+        out.push_str(ending);
+    }
+    out
+}
+
+/// 计算前一个编号之后的新编号
+fn next_scene_number(
+    prev: Option<&str>,
+    next_base: Option<u32>,
+    used: &HashSet<String>,
+    style: LockedNumberStyle,
+) -> String {
+    let (base, letters) = match prev {
+        Some(p) => match split_number(p) {
+            Some(parts) => parts,
+            None => {
+                // 非数字编号（如 "X"）只能追加字母
+                let mut letters = String::from("A");
+                loop {
+                    let candidate = format!("{}{}", p, letters);
+                    if !used.contains(&candidate) {
+                        return candidate;
+                    }
+                    letters = next_letters(&letters);
+                }
+            }
+        },
+        None => (0, String::new()),
+    };
+
+    if letters.is_empty() {
+        let candidate = base + 1;
+        let fits = next_base.is_none_or(|nb| candidate < nb);
+        if fits && !used.contains(&candidate.to_string()) {
+            return candidate.to_string();
+        }
+    }
+
+    let mut letters = if letters.is_empty() {
+        String::from("A")
+    } else {
+        next_letters(&letters)
+    };
+    loop {
+        let candidate = match style {
+            LockedNumberStyle::Suffix => format!("{}{}", base, letters),
+            LockedNumberStyle::Prefix => format!("{}{}", letters, base),
+        };
+        if !used.contains(&candidate) {
+            return candidate;
+        }
+        letters = next_letters(&letters);
+    }
+}
+
+/// 把 "12"、"12A"、"A12" 拆分为整数部分和字母部分
+fn split_number(number: &str) -> Option<(u32, String)> {
+    let number = number.trim();
+    let digits: String = number.chars().filter(|c| c.is_ascii_digit()).collect();
+    let letters: String = number.chars().filter(|c| !c.is_ascii_digit()).collect();
+    if digits.is_empty() || !letters.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    // 字母只能整体出现在开头或结尾
+    if !(number.starts_with(&digits) || number.ends_with(&digits)) {
+        return None;
+    }
+    digits.parse().ok().map(|base| (base, letters))
+}
+
+/// A -> B, Z -> AA, AZ -> BA
+fn next_letters(letters: &str) -> String {
+    let mut chars: Vec<char> = letters.chars().collect();
+    let mut i = chars.len();
+    loop {
+        if i == 0 {
+            chars.insert(0, 'A');
+            break;
+        }
+        i -= 1;
+        if chars[i] == 'Z' {
+            chars[i] = 'A';
+        } else {
+            chars[i] = (chars[i] as u8 + 1) as char;
+            break;
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// 替换或追加场景标题行末尾的 `#...#` 编号
+pub(crate) fn set_scene_number(line: &str, var: Option<&str>, number: &str) -> String {
+    let tag = match var {
+        Some(v) => format!("#${{{}}} {}#", v, number),
+        None => format!("#{}#", number),
+    };
+    if SCENE_NUMBER_REGEX.is_match(line) {
+        SCENE_NUMBER_REGEX
+            .replace(line, regex::NoExpand(&tag))
+            .to_string()
+    } else {
+        format!("{} {}", line.trim_end(), tag)
+    }
+}
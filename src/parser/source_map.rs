@@ -0,0 +1,118 @@
+//! token 的绝对源码位置
+//!
+//! 解析器按行工作，token 只记录行号。解析结束后按与解析器相同的断行方式（`\r` 和 `\n` 都算换行）
+//! 计算每行在原文中的字节偏移和 UTF-16 偏移，给 token 补上 [`SourceRange`]。
+//! 变量替换（`{{name}}`）在解析前进行，范围以替换前的原文为准。
+//! 展开引用后解析时，再用 [`SourceMap::rebase`] 把范围换算到各行所在的原始文件中。
+
+use crate::models::{ScriptToken, SourceRange};
+use crate::parser::include::ExpandedScript;
+
+// 不对应任何源码行的辅助 token
+const SYNTHETIC_TYPES: [&str; 4] = [
+    "dialogue_begin",
+    "dialogue_end",
+    "dual_dialogue_begin",
+    "dual_dialogue_end",
+];
+
+/// 一行在原文中的位置
+struct LineSpan {
+    byte: usize,
+    utf16: usize,
+    text: String,
+}
+
+/// 按行计算的源码索引
+pub struct SourceMap {
+    lines: Vec<LineSpan>,
+}
+
+impl SourceMap {
+    pub fn new(script: &str) -> Self {
+        let mut lines = Vec::new();
+        let (mut byte, mut utf16) = (0, 0);
+        for text in script.split(&['\r', '\n'][..]) {
+            lines.push(LineSpan {
+                byte,
+                utf16,
+                text: text.to_string(),
+            });
+            byte += text.len() + 1;
+            utf16 += text.encode_utf16().count() + 1;
+        }
+        SourceMap { lines }
+    }
+
+    /// 从 `first` 行到 `last` 行（含）去掉首尾空白后的范围，行号越界时返回 None
+    pub fn range(&self, first: usize, last: usize) -> Option<SourceRange> {
+        let start_line = self.lines.get(first)?;
+        let end_line = self.lines.get(last.max(first))?;
+
+        let leading = start_line.text.len() - start_line.text.trim_start().len();
+        let byte_start = start_line.byte + leading;
+        let utf16_start = start_line.utf16 + start_line.text[..leading].encode_utf16().count();
+
+        let column_start = start_line.text[..leading].chars().count();
+
+        let content = end_line.text.trim_end();
+        let byte_end = (end_line.byte + content.len()).max(byte_start);
+        let utf16_end = (end_line.utf16 + content.encode_utf16().count()).max(utf16_start);
+        let mut column_end = content.chars().count();
+        if last <= first {
+            column_end = column_end.max(column_start);
+        }
+
+        Some(SourceRange {
+            byte_start,
+            byte_end,
+            utf16_start,
+            utf16_end,
+            column_start,
+            column_end,
+        })
+    }
+
+    /// 给 token 补上源码范围，多行 token 按其文本的行数向后延伸
+    pub fn assign<'a>(&self, tokens: impl Iterator<Item = &'a mut ScriptToken>) {
+        for token in tokens {
+            if SYNTHETIC_TYPES.contains(&token.token_type.as_str()) {
+                continue;
+            }
+            let extra_lines = token.text.matches('\n').count();
+            token.source_range = self.range(token.line, token.line + extra_lines);
+        }
+    }
+
+    /// 把展开引用后的剧本中 `first` 行到 `last` 行的范围换算为第一行所在原始文件原文中的范围，
+    /// 原文中的 `\r\n` 按两个字节计；跨到其它文件的多行 token 只保留第一行
+    pub fn rebase(
+        &self,
+        expanded: &ExpandedScript,
+        first: usize,
+        last: usize,
+    ) -> Option<SourceRange> {
+        let origin = expanded.origins.get(first)?;
+        let contiguous = |line: usize| {
+            expanded
+                .origins
+                .get(line)
+                .is_some_and(|o| o.file == origin.file && o.line + first == origin.line + line)
+        };
+        let last = if contiguous(last) { last } else { first };
+        let mut range = self.range(first, last)?;
+
+        // 换算时每行只差一个常量偏移，按起点和终点各自所在行换算
+        let shift = |line: usize, byte: usize, utf16: usize| {
+            let span = &self.lines[line];
+            let (file_byte, file_utf16) = expanded.line_starts[line];
+            (
+                byte - span.byte + file_byte,
+                utf16 - span.utf16 + file_utf16,
+            )
+        };
+        (range.byte_start, range.utf16_start) = shift(first, range.byte_start, range.utf16_start);
+        (range.byte_end, range.utf16_end) = shift(last, range.byte_end, range.utf16_end);
+        Some(range)
+    }
+}
@@ -0,0 +1,176 @@
+//! Fountain 规范兼容
+//!
+//! 列出本解析器在 Fountain 1.1 规范之外支持的扩展语法，并检查剧本用到了哪些扩展。
+//! 开启 `Conf::spec_mode` 后解析器关闭这些扩展，按标准 Fountain 解析，
+//! 便于与其它 Fountain 工具交换剧本前确认没有依赖非标准写法。
+
+use crate::models::Conf;
+use crate::parser::FountainParser;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    /// 镜头交切的开始与结束标记：`> {+ ... +} ↓`、`{# #}`、`{= =}`、`{- -} ↑`
+    static ref SHOT_CUT_REGEX: Regex = Regex::new(r"^\s*>\s*\{([+#=-]).*([+#=-])\}\s*[↓↑]").unwrap();
+    static ref VARIABLE_REGEX: Regex = Regex::new(r"\{\{\s*[^{}\s]+\s*\}\}").unwrap();
+}
+
+/// 非标准的扩展语法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Extension {
+    /// 镜头交切 `> {+ ... +} ↓`、`{# #}`、`{= =}`，以 `> {- ... -} ↑` 结束
+    ShotCut,
+    /// 强制在正文中显示的注解 `[[| ... ]]`
+    InlineNote,
+    /// 全角括号 `（）` 写的括号注释和角色扩展
+    ChineseMarkers,
+    /// 三人同时对白 `^^`
+    TripleDialogue,
+    /// 插图 `[[img: path.png]]`
+    Image,
+    /// 剧本变量 `{{NAME}}`
+    Variable,
+    /// 删去的场景 `OMITTED`
+    Omitted,
+    /// 国内剧本格式排版（标题页 metadata 中的 print.chinaFormat）：动作前加 △，角色名后加冒号接对白
+    ChinaFormat,
+}
+
+impl Extension {
+    /// 所有扩展
+    pub const ALL: [Extension; 8] = [
+        Extension::ShotCut,
+        Extension::InlineNote,
+        Extension::ChineseMarkers,
+        Extension::TripleDialogue,
+        Extension::Image,
+        Extension::Variable,
+        Extension::Omitted,
+        Extension::ChinaFormat,
+    ];
+
+    /// 配置和报告中使用的名称，如 "shot_cut"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Extension::ShotCut => "shot_cut",
+            Extension::InlineNote => "inline_note",
+            Extension::ChineseMarkers => "chinese_markers",
+            Extension::TripleDialogue => "triple_dialogue",
+            Extension::Image => "image",
+            Extension::Variable => "variable",
+            Extension::Omitted => "omitted",
+            Extension::ChinaFormat => "china_format",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Extension::ALL
+            .into_iter()
+            .find(|e| e.name().eq_ignore_ascii_case(name))
+    }
+
+    /// 扩展的说明
+    pub fn description(&self) -> &'static str {
+        match self {
+            Extension::ShotCut => "镜头交切 {+ +}、{# #}、{= =}",
+            Extension::InlineNote => "正文中显示的注解 [[| ]]",
+            Extension::ChineseMarkers => "全角括号（）写的括号注释和角色扩展",
+            Extension::TripleDialogue => "三人同时对白 ^^",
+            Extension::Image => "插图 [[img: ]]",
+            Extension::Variable => "剧本变量 {{ }}",
+            Extension::Omitted => "删去的场景 OMITTED",
+            Extension::ChinaFormat => "国内剧本格式 △ 与冒号对白",
+        }
+    }
+}
+
+/// 剧本中一处扩展语法
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtensionUse {
+    pub extension: Extension,
+    /// 所在行（从 0 开始）
+    pub line: usize,
+}
+
+/// 列出剧本用到的扩展语法，按行排列；结果为空说明剧本符合 Fountain 规范
+pub fn detect_extensions(script: &str, config: &Conf) -> Vec<ExtensionUse> {
+    let lines: Vec<&str> = script.split(&['\r', '\n'][..]).collect();
+    let config = Conf {
+        spec_mode: false,
+        disabled_extensions: Vec::new(),
+        ..config.clone()
+    };
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &config, false, Some(false));
+
+    let mut uses = Vec::new();
+    let mut add = |extension: Extension, line: usize| {
+        let found = ExtensionUse { extension, line };
+        if !uses.contains(&found) {
+            uses.push(found);
+        }
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if SHOT_CUT_REGEX.is_match(line) {
+            add(Extension::ShotCut, i);
+        }
+        if line.contains("[[|") {
+            add(Extension::InlineNote, i);
+        }
+        if VARIABLE_REGEX.is_match(line) {
+            add(Extension::Variable, i);
+        }
+    }
+    for token in &output.tokens {
+        let source = lines.get(token.line).copied().unwrap_or("");
+        match token.token_type.as_str() {
+            "parenthetical" if source.contains('（') => add(Extension::ChineseMarkers, token.line),
+            "character" => {
+                if source.contains('（') {
+                    add(Extension::ChineseMarkers, token.line);
+                }
+                if source.trim_end().ends_with("^^") {
+                    add(Extension::TripleDialogue, token.line);
+                }
+            }
+            "image" => add(Extension::Image, token.line),
+            _ if token.is_omitted() => add(Extension::Omitted, token.line),
+            _ => {}
+        }
+    }
+    // 国内剧本格式在标题页 metadata 中开启，如 {"print": {"chinaFormat": 1}}
+    for token in output.title_page.get("hidden").into_iter().flatten() {
+        if token.token_type != "metadata" {
+            continue;
+        }
+        let json = token.text.trim_start_matches("Metadata:").trim();
+        let china_format = serde_json::from_str::<serde_json::Value>(json)
+            .ok()
+            .and_then(|metadata| {
+                let value = metadata.get("print")?.get("chinaFormat")?;
+                value
+                    .as_i64()
+                    .or_else(|| value.as_str()?.trim().parse().ok())
+            });
+        if china_format.is_some_and(|f| f > 0) {
+            add(Extension::ChinaFormat, token.line);
+        }
+    }
+
+    uses.sort_by_key(|u| (u.line, u.extension));
+    uses
+}
+
+/// 剧本用到了哪些扩展（去重）
+pub fn used_extensions(script: &str, config: &Conf) -> Vec<Extension> {
+    let mut extensions: Vec<Extension> = detect_extensions(script, config)
+        .into_iter()
+        .map(|u| u.extension)
+        .collect();
+    extensions.sort();
+    extensions.dedup();
+    extensions
+}
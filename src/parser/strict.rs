@@ -0,0 +1,86 @@
+//! 严格模式解析
+//!
+//! 解析器对结构问题一向宽容：未闭合的注解会吞掉后文，写错的标题页字段被当作续行，但总会返回解析结果。
+//! 严格模式把这些问题（即 `ParseOutput::diagnostics` 中解析阶段记录的问题）转换为带位置的错误，
+//! 有任何一项时返回错误列表而不是解析结果，适合在 CI 或导出前做把关。
+
+use crate::models::{Conf, Diagnostic, Range};
+use crate::parser::{FountainParser, ParseOutput};
+use serde::Serialize;
+use thiserror::Error;
+
+/// 严格模式下的解析错误，`range` 的行列均从 0 开始
+#[derive(Error, Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParseError {
+    #[error("第 {} 行: 注解 [[ 没有对应的 ]]", .range.start.line + 1)]
+    UnclosedNote { range: Range },
+
+    #[error("第 {} 行: 注释 /* 没有对应的 */", .range.start.line + 1)]
+    UnterminatedComment { range: Range },
+
+    #[error("第 {} 行: 无法识别的标题页字段 \"{key}\"", .range.start.line + 1)]
+    InvalidTitleKey { key: String, range: Range },
+
+    #[error("第 {} 行: 场景编号 {number} 与之前的场景重复", .range.start.line + 1)]
+    DuplicateSceneNumber { number: String, range: Range },
+
+    #[error("第 {} 行: 未定义的变量 \"{name}\"", .range.start.line + 1)]
+    UndefinedVariable { name: String, range: Range },
+}
+
+impl ParseError {
+    /// 出错的位置
+    pub fn range(&self) -> &Range {
+        match self {
+            ParseError::UnclosedNote { range }
+            | ParseError::UnterminatedComment { range }
+            | ParseError::InvalidTitleKey { range, .. }
+            | ParseError::DuplicateSceneNumber { range, .. }
+            | ParseError::UndefinedVariable { range, .. } => range,
+        }
+    }
+
+    /// 由解析器记录的诊断转换，不属于结构问题的诊断返回 None
+    pub fn from_diagnostic(diagnostic: &Diagnostic) -> Option<Self> {
+        let range = diagnostic.range.clone();
+        let data = diagnostic.data.clone().unwrap_or_default();
+        match diagnostic.code.as_str() {
+            "unclosed-note" => Some(ParseError::UnclosedNote { range }),
+            "unclosed-comment" => Some(ParseError::UnterminatedComment { range }),
+            "unknown-title-page-key" => Some(ParseError::InvalidTitleKey { key: data, range }),
+            "duplicate-scene-number" => Some(ParseError::DuplicateSceneNumber {
+                number: data,
+                range,
+            }),
+            "undefined-variable" => Some(ParseError::UndefinedVariable { name: data, range }),
+            _ => None,
+        }
+    }
+}
+
+/// 严格模式解析：没有结构问题时返回解析结果，否则返回按位置排序的全部错误
+pub fn parse_strict(
+    script: &str,
+    cfg: &Conf,
+    generate_html: bool,
+    calc_statistics: Option<bool>,
+) -> Result<ParseOutput, Vec<ParseError>> {
+    let parser = FountainParser::new();
+    let output = parser.parse(script, cfg, generate_html, calc_statistics);
+    check_strict(output)
+}
+
+/// 检查已有的解析结果，有结构问题时返回错误列表
+pub fn check_strict(output: ParseOutput) -> Result<ParseOutput, Vec<ParseError>> {
+    let mut errors: Vec<ParseError> = output
+        .diagnostics
+        .iter()
+        .filter_map(ParseError::from_diagnostic)
+        .collect();
+    if errors.is_empty() {
+        return Ok(output);
+    }
+    errors.sort_by_key(|e| (e.range().start.line, e.range().start.character));
+    Err(errors)
+}
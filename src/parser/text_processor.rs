@@ -1,10 +1,16 @@
 use regex::Regex;
 use crate::models::ScriptToken;
-use crate::utils::FountainConstants;
+use crate::utils::{
+    escape_style_chars, strip_style_chars, unescape_style_chars, FountainConstants,
+};
+use std::collections::{HashMap, HashSet};
 
 // 处理token文本样式
 pub fn process_token_text_style_char(token: &mut ScriptToken) -> String {
     if !token.text.is_empty() {
+        // \* \_ \[[ 等转义字符先保护起来
+        token.text = escape_style_chars(&token.text);
+
         // 三 *** 换成当个特殊符号，以防下面split_token分行截断
         token.text = Regex::new(r"\*{3}")
             .unwrap()
@@ -29,37 +35,273 @@ pub fn process_token_text_style_char(token: &mut ScriptToken) -> String {
             .replace_all(&token.text, FountainConstants::style_chars()["underline"])
             .to_string();
 
-        // 处理转义字符
-        token.text = token.text.replace(r"\*", "*").replace(r"\_", "_");
+        // 成对的 ~~删除线~~ 和 ==高亮== 换成特殊符号，落单的不处理
+        token.text = Regex::new(r"~~(.+?)~~")
+            .unwrap()
+            .replace_all(&token.text, |caps: &regex::Captures| {
+                let c = FountainConstants::style_chars()["strikethrough"];
+                format!("{}{}{}", c, &caps[1], c)
+            })
+            .to_string();
+        token.text = Regex::new(r"==(.+?)==")
+            .unwrap()
+            .replace_all(&token.text, |caps: &regex::Captures| {
+                let c = FountainConstants::style_chars()["highlight"];
+                format!("{}{}{}", c, &caps[1], c)
+            })
+            .to_string();
+
+        // {color:red}彩色文字{/color} 换成特殊符号，颜色值规范为 #RRGGBB，不认识的颜色不处理
+        token.text = Regex::new(r"\{color:\s*([#\w]+)\s*\}(.*?)\{/color\}")
+            .unwrap()
+            .replace_all(&token.text, |caps: &regex::Captures| {
+                match parse_color(&caps[1]) {
+                    Some(color) => {
+                        let chars = FountainConstants::style_chars();
+                        format!(
+                            "{}{}{}{}{}",
+                            chars["color_begin"],
+                            color,
+                            chars["color_text"],
+                            &caps[2],
+                            chars["color_end"]
+                        )
+                    }
+                    None => caps[0].to_string(),
+                }
+            })
+            .to_string();
+
+        // 转义字符换回原字符
+        token.text = unescape_style_chars(&token.text);
     }
 
     token.text.clone()
 }
 
+/// 把颜色名或 `#RGB`、`#RRGGBB` 规范为 `#RRGGBB`，不认识的返回 None
+pub(crate) fn parse_color(color: &str) -> Option<String> {
+    let color = color.trim().to_lowercase();
+    if let Some(hex) = color.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        return match hex.len() {
+            6 => Some(format!("#{}", hex.to_uppercase())),
+            3 => Some(format!(
+                "#{}",
+                hex.chars()
+                    .flat_map(|c| [c, c])
+                    .collect::<String>()
+                    .to_uppercase()
+            )),
+            _ => None,
+        };
+    }
+    let hex = match color.as_str() {
+        "black" => "000000",
+        "gray" | "grey" => "808080",
+        "red" => "FF0000",
+        "orange" => "FFA500",
+        "yellow" => "FFFF00",
+        "green" => "008000",
+        "cyan" => "00FFFF",
+        "blue" => "0000FF",
+        "purple" => "800080",
+        "magenta" => "FF00FF",
+        "pink" => "FFC0CB",
+        "brown" => "A52A2A",
+        _ => return None,
+    };
+    Some(format!("#{}", hex))
+}
+
 // 使用 utils/mod.rs 中的 is_blank_line_after_style 函数
 
 // 生成HTML输出
+// 双对白（dual == left/right）渲染为左右两栏，与 DOCX 中的表格排版一致；
+// 三人同时对白另有中栏（dual == middle），渲染为三栏
+// 每个元素都带 data-line（源文本行，从 0 开始），每行第一个元素带锚点 id，见 `HtmlLineMap`
 pub fn generate_html(tokens: &[ScriptToken]) -> String {
     let mut buffer = String::new();
-    for token in tokens {
-        buffer.push_str(&token.to_html());
+    let mut anchored: HashSet<usize> = HashSet::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        if token.token_type != "dual_dialogue_begin" {
+            buffer.push_str(&with_line_attrs(
+                &token.to_html(),
+                token.line,
+                &mut anchored,
+            ));
+            buffer.push('\n');
+            continue;
+        }
+
+        let wrapper = with_line_attrs("<div class=\"dual-dialogue\">", token.line, &mut anchored);
+        let mut left = String::new();
+        let mut middle = String::new();
+        let mut right = String::new();
+        let mut column_lines: [Option<usize>; 3] = [None; 3];
+        let mut column_name = "left";
+        while i < tokens.len() && tokens[i].token_type != "dual_dialogue_end" {
+            let t = &tokens[i];
+            i += 1;
+            if let Some(dual @ ("left" | "middle" | "right")) = t.dual.as_deref() {
+                column_name = dual;
+            }
+            let (column, column_line) = match column_name {
+                "middle" => (&mut middle, &mut column_lines[1]),
+                "right" => (&mut right, &mut column_lines[2]),
+                _ => (&mut left, &mut column_lines[0]),
+            };
+            column_line.get_or_insert(t.line);
+            column.push_str(&with_line_attrs(&t.to_html(), t.line, &mut anchored));
+            column.push('\n');
+        }
+        // 跳过 dual_dialogue_end
+        i += 1;
+
+        let column_div = |class: &str, line: Option<usize>, html: &str| {
+            let line = line.unwrap_or(token.line);
+            format!(
+                "<div class=\"{}\" data-line=\"{}\">\n{}</div>\n",
+                class, line, html
+            )
+        };
+        buffer.push_str(&wrapper);
         buffer.push('\n');
+        buffer.push_str(&column_div("dual-dialogue-left", column_lines[0], &left));
+        if !middle.is_empty() {
+            buffer.push_str(&column_div(
+                "dual-dialogue-middle",
+                column_lines[1],
+                &middle,
+            ));
+        }
+        buffer.push_str(&column_div("dual-dialogue-right", column_lines[2], &right));
+        buffer.push_str("</div>\n");
     }
     buffer
 }
 
-// 生成标题页HTML输出
-pub fn generate_title_html(title_keys: &[String], tokens: &[ScriptToken]) -> String {
-    let mut buffer = String::new();
-    for key in title_keys {
-        if let Some(token) = tokens.iter().find(|t| {
-            t.metadata.as_ref()
-                .and_then(|m| m.get("key"))
-                .map_or(false, |k| k == key)
-        }) {
-            buffer.push_str(&token.to_html());
-            buffer.push('\n');
+/// 预览中源文本第 `line` 行（从 0 开始）的锚点 id
+pub fn line_anchor_id(line: usize) -> String {
+    format!("line-{}", line)
+}
+
+// 给元素加上 data-line，每行第一个元素再加上锚点 id
+fn with_line_attrs(html: &str, line: usize, anchored: &mut HashSet<usize>) -> String {
+    let Some(rest) = html.strip_prefix("<div") else {
+        return html.to_string();
+    };
+    if anchored.insert(line) {
+        format!(
+            "<div id=\"{}\" data-line=\"{}\"{}",
+            line_anchor_id(line),
+            line,
+            rest
+        )
+    } else {
+        format!("<div data-line=\"{}\"{}", line, rest)
+    }
+}
+
+/// 源文本行与 `generate_html` 预览锚点的对应关系，供编辑器插件同步滚动源文本和预览
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HtmlLineMap {
+    /// 有锚点的行，从小到大
+    lines: Vec<usize>,
+}
+
+impl HtmlLineMap {
+    /// 与 `generate_html(tokens)` 生成的锚点一一对应
+    pub fn new(tokens: &[ScriptToken]) -> Self {
+        let mut lines: Vec<usize> = tokens
+            .iter()
+            .filter(|token| token.token_type != "dual_dialogue_end")
+            .map(|token| token.line)
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        HtmlLineMap { lines }
+    }
+
+    /// 有锚点的行，从小到大
+    pub fn lines(&self) -> &[usize] {
+        &self.lines
+    }
+
+    /// 源文本行对应的锚点：该行本身没有元素时取前面最近的一行，在第一个元素之前时取第一个锚点
+    pub fn anchor_for_line(&self, line: usize) -> Option<String> {
+        let index = match self.lines.binary_search(&line) {
+            Ok(index) => index,
+            Err(index) => index.saturating_sub(1),
+        };
+        self.lines.get(index).map(|&l| line_anchor_id(l))
+    }
+
+    /// 锚点 id 对应的源文本行，不是本预览中的锚点时返回 None
+    pub fn line_for_anchor(&self, id: &str) -> Option<usize> {
+        let line = id.strip_prefix("line-")?.parse().ok()?;
+        self.lines.binary_search(&line).ok().map(|_| line)
+    }
+}
+
+// 生成标题页HTML输出，按 tl/tc/tr、cc、bl/br 分区排版，与 DOCX 标题页的文本框位置一致
+pub fn generate_title_html(title_page: &HashMap<String, Vec<ScriptToken>>) -> String {
+    if title_page.values().all(|tokens| tokens.is_empty()) {
+        return String::new();
+    }
+
+    let region = |position: &str| -> String {
+        let mut tokens: Vec<&ScriptToken> =
+            title_page.get(position).map(|t| t.iter().collect()).unwrap_or_default();
+        tokens.sort_by_key(|t| t.index);
+        let mut html = format!("<div class=\"title-{}\">\n", position);
+        for token in tokens {
+            if token.token_type == "image" {
+                html.push_str(&token.to_html());
+                html.push('\n');
+                continue;
+            }
+            let text = escape_html(strip_style_chars(&token.text).trim()).replace('\n', "<br>");
+            html.push_str(&format!(
+                "<div class=\"title-{}\">{}</div>\n",
+                token.token_type, text
+            ));
         }
+        html.push_str("</div>\n");
+        html
+    };
+
+    let mut buffer = String::new();
+    buffer.push_str("<div class=\"title-page-top\">\n");
+    for position in ["tl", "tc", "tr"] {
+        buffer.push_str(&region(position));
     }
+    buffer.push_str("</div>\n");
+    buffer.push_str(&region("cc"));
+    buffer.push_str("<div class=\"title-page-bottom\">\n");
+    for position in ["bl", "br"] {
+        buffer.push_str(&region(position));
+    }
+    buffer.push_str("</div>\n");
     buffer
 }
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// 生成完整的 HTML 文档（标题页 + 正文），用于独立文件导出
+pub fn generate_html_document(title: &str, title_html: &str, script_html: &str) -> String {
+    let title = escape_html(title);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n.title-page {{ display: flex; flex-direction: column; min-height: 90vh; page-break-after: always; }}\n.title-page-top, .title-page-bottom {{ display: flex; }}\n.title-page-top > div, .title-page-bottom > div {{ flex: 1; }}\n.title-tc, .title-cc {{ text-align: center; }}\n.title-tr, .title-br {{ text-align: right; }}\n.title-cc {{ flex: 1; display: flex; flex-direction: column; justify-content: center; }}\n.dual-dialogue {{ display: flex; gap: 2em; }}\n.dual-dialogue > div {{ flex: 1; }}\n.fountain-synopsis {{ color: #888888; font-style: italic; }}\n</style>\n</head>\n<body>\n<div class=\"title-page\">\n{}</div>\n<div class=\"screenplay\">\n{}</div>\n</body>\n</html>\n",
+        title, title_html, script_html
+    )
+}
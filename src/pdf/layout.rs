@@ -0,0 +1,1187 @@
+//! 把 docx_maker 的排版结果（adapter 中的 Document 模型）排成 PDF 页面
+//!
+//! Word 和 LibreOffice 打开 DOCX/ODT 时自己折行分页，PDF 则要在这里完成：按字符宽度折行，
+//! 段前段后间距、缩进、对齐、制表位、行距都按模型中的数值换算（1pt = 20 twips），
+//! 双对白表格逐行放置，页眉页脚在每页生成时填入当前页码，`keep_next`/`keep_lines` 的段落不在中间分页。
+//! 段落框架（标题页各处的内容）按框架的对齐方式放在页边距内相应位置；竖排的节仍按横排输出；
+//! 批注不输出，脚注统一列在文末。
+
+use super::writer::{text_width, Color, FontStyle, PdfWriter};
+use crate::docx::adapter::docx::{
+    Document, PageNumberChild, PageNumberFormat, Paragraph, ParagraphIndent, ParagraphSpacing,
+    ParagraphStyle, RevisionKind, RunType, Section, SectionChild, Table, TextRun,
+};
+use crate::docx::adapter::{
+    AlignmentType, BreakType, HorizontalPositionAlign, LineRuleType, RunProps, TabAlignment,
+    UnderlineType, VerticalPositionAlign, WidthType,
+};
+use std::collections::HashMap;
+
+const TWIPS_PER_PT: f32 = 20.0;
+const DEFAULT_FONT_SIZE: f32 = 12.0;
+// 没有自定义制表位时，默认每 0.5 英寸一个
+const DEFAULT_TAB: f32 = 36.0;
+
+/// 把排版结果排成 PDF，返回文件内容
+pub fn render(doc: &Document) -> Vec<u8> {
+    let mut layout = Layout::new(doc);
+    for section in &doc.options.sections {
+        layout.section(section);
+    }
+    layout.footnotes();
+    layout.writer.finish(&doc.options.title)
+}
+
+// 页面尺寸和页边距（磅）
+#[derive(Debug, Clone, Copy)]
+struct Geometry {
+    width: f32,
+    height: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    left: f32,
+    header: f32,
+    footer: f32,
+}
+
+impl Geometry {
+    fn of(section: &Section) -> Self {
+        let page = section.properties.page.as_ref();
+        let (width, height) = page
+            .and_then(|page| page.size.as_ref())
+            .map(|size| (pt(size.width), pt(size.height)))
+            .unwrap_or((612.0, 792.0));
+        match page.and_then(|page| page.margin.as_ref()) {
+            Some(margin) => Self {
+                width,
+                height,
+                top: pt(margin.top),
+                right: pt(margin.right),
+                bottom: pt(margin.bottom),
+                left: pt(margin.left),
+                header: pt(margin.header),
+                footer: pt(margin.footer),
+            },
+            None => Self {
+                width,
+                height,
+                top: 72.0,
+                right: 72.0,
+                bottom: 72.0,
+                left: 72.0,
+                header: 36.0,
+                footer: 36.0,
+            },
+        }
+    }
+
+    fn content_width(&self) -> f32 {
+        self.width - self.left - self.right
+    }
+
+    fn content_height(&self) -> f32 {
+        self.height - self.top - self.bottom
+    }
+}
+
+// 文字样式
+#[derive(Debug, Clone, PartialEq)]
+struct Style {
+    size: f32,
+    font: FontStyle,
+    color: Option<Color>,
+    background: Option<Color>,
+    underline: bool,
+    strike: bool,
+    // 上标为正、下标为负的基线偏移
+    rise: f32,
+    url: Option<String>,
+}
+
+// 段落内容展开后的排版单元
+enum Item {
+    Text(String, Style),
+    Tab,
+    LineBreak,
+    PageBreak,
+    // 无法嵌入的图片 id 为 None，画一个占位框
+    Image {
+        id: Option<usize>,
+        width: f32,
+        height: f32,
+    },
+}
+
+// 行内已定位的内容，x 从段落左缩进处算起
+enum Piece {
+    Text {
+        x: f32,
+        text: String,
+        style: Style,
+    },
+    Image {
+        x: f32,
+        id: Option<usize>,
+        width: f32,
+        height: f32,
+    },
+}
+
+impl Piece {
+    fn shift(&mut self, dx: f32) {
+        match self {
+            Piece::Text { x, .. } | Piece::Image { x, .. } => *x += dx,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Line {
+    pieces: Vec<Piece>,
+    // 内容右端
+    width: f32,
+    height: f32,
+    // 基线到行顶的距离
+    baseline: f32,
+    page_break_before: bool,
+}
+
+// 折行后的段落
+struct Block {
+    lines: Vec<Line>,
+    // 左缩进处的横坐标和可用宽度
+    x: f32,
+    width: f32,
+    alignment: Option<AlignmentType>,
+    before: f32,
+    after: f32,
+    keep_next: bool,
+    keep_lines: bool,
+    change_bar: bool,
+    border: Option<Color>,
+    page_break_after: bool,
+}
+
+impl Block {
+    fn height(&self) -> f32 {
+        self.before + self.lines.iter().map(|line| line.height).sum::<f32>() + self.after
+    }
+
+    // 与下一段保持在同一页时，下一段至少要放下段前间距和第一行
+    fn head_height(&self) -> f32 {
+        self.before + self.lines.first().map_or(0.0, |line| line.height)
+    }
+}
+
+struct Layout<'a> {
+    doc: &'a Document,
+    writer: PdfWriter,
+    styles: HashMap<&'a str, &'a ParagraphStyle>,
+    section: Option<&'a Section>,
+    geometry: Geometry,
+    // 当前位置，从页面顶端向下量
+    y: f32,
+    page_empty: bool,
+    page_number: i32,
+    lower_roman: bool,
+    // 行号计数，各节连续
+    line_count: u32,
+    footnotes: Vec<(usize, Vec<Paragraph>)>,
+}
+
+impl<'a> Layout<'a> {
+    fn new(doc: &'a Document) -> Self {
+        let styles = doc
+            .options
+            .styles
+            .iter()
+            .flat_map(|styles| &styles.paragraph_styles)
+            .filter_map(|style| style.id.as_deref().map(|id| (id, style)))
+            .collect();
+        let geometry = doc
+            .options
+            .sections
+            .first()
+            .map(Geometry::of)
+            .unwrap_or_else(|| Geometry::of(&Section::new()));
+        Self {
+            doc,
+            writer: PdfWriter::new(),
+            styles,
+            section: None,
+            geometry,
+            y: 0.0,
+            page_empty: true,
+            page_number: 0,
+            lower_roman: false,
+            line_count: 0,
+            footnotes: Vec::new(),
+        }
+    }
+
+    // 每节从新的一页开始
+    fn section(&mut self, section: &'a Section) {
+        self.section = Some(section);
+        self.geometry = Geometry::of(section);
+        if let Some(page_numbers) = section
+            .properties
+            .page
+            .as_ref()
+            .and_then(|page| page.page_numbers.as_ref())
+        {
+            if let Some(start) = page_numbers.start {
+                self.page_number = start - 1;
+            }
+            self.lower_roman = page_numbers.format == PageNumberFormat::LowerRoman;
+        }
+        self.new_page();
+
+        let left = self.geometry.left;
+        let width = self.geometry.content_width();
+        let blocks: Vec<Option<Block>> = section
+            .children
+            .iter()
+            .map(|child| match child {
+                SectionChild::Paragraph(paragraph) if paragraph.frame.is_none() => {
+                    Some(self.block(paragraph, left, width, None))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let numbered = section.properties.line_numbers;
+        let mut i = 0;
+        while i < section.children.len() {
+            match &section.children[i] {
+                SectionChild::PageBreak => {
+                    if !self.page_empty {
+                        self.new_page();
+                    }
+                }
+                SectionChild::Table(table) => self.table(table),
+                SectionChild::Paragraph(paragraph) if paragraph.frame.is_some() => {
+                    let key = frame_key(paragraph);
+                    let mut group = vec![paragraph];
+                    while let Some(SectionChild::Paragraph(next)) = section.children.get(i + 1) {
+                        if next.frame.is_none() || frame_key(next) != key {
+                            break;
+                        }
+                        group.push(next);
+                        i += 1;
+                    }
+                    self.frame(&group);
+                }
+                SectionChild::Paragraph(_) => {
+                    let block = blocks[i].as_ref().unwrap();
+                    let needed = if block.keep_next {
+                        let mut needed = block.height();
+                        for next in &blocks[i + 1..] {
+                            let Some(next) = next else {
+                                break;
+                            };
+                            if !next.keep_next {
+                                needed += next.head_height();
+                                break;
+                            }
+                            needed += next.height();
+                        }
+                        needed
+                    } else if block.keep_lines {
+                        block.height()
+                    } else {
+                        0.0
+                    };
+                    if !self.page_empty
+                        && needed > self.remaining()
+                        && needed <= self.geometry.content_height()
+                    {
+                        self.new_page();
+                    }
+                    self.place(block, numbered);
+                }
+            }
+            i += 1;
+        }
+    }
+
+    fn remaining(&self) -> f32 {
+        self.geometry.height - self.geometry.bottom - self.y
+    }
+
+    // 新的一页，并画上页眉页脚
+    fn new_page(&mut self) {
+        let geometry = self.geometry;
+        self.writer.add_page(geometry.width, geometry.height);
+        self.y = geometry.top;
+        self.page_empty = true;
+        self.page_number += 1;
+
+        let Some(section) = self.section else {
+            return;
+        };
+        let label = if self.lower_roman {
+            roman(self.page_number)
+        } else {
+            self.page_number.to_string()
+        };
+        if let Some(headers) = &section.headers {
+            let mut y = geometry.header;
+            for paragraph in &headers.default.children {
+                let block = self.block(
+                    paragraph,
+                    geometry.left,
+                    geometry.content_width(),
+                    Some(&label),
+                );
+                y = self.draw_block(&block, y);
+            }
+        }
+        if let Some(footers) = &section.footers {
+            let blocks: Vec<Block> = footers
+                .default
+                .children
+                .iter()
+                .map(|paragraph| {
+                    self.block(
+                        paragraph,
+                        geometry.left,
+                        geometry.content_width(),
+                        Some(&label),
+                    )
+                })
+                .collect();
+            let height: f32 = blocks.iter().map(Block::height).sum();
+            let mut y = geometry.height - geometry.footer - height;
+            for block in &blocks {
+                y = self.draw_block(block, y);
+            }
+        }
+    }
+
+    // 在正文中放置段落，放不下的行移到下一页
+    fn place(&mut self, block: &Block, numbered: Option<u32>) {
+        if !self.page_empty {
+            self.y += block.before;
+        }
+        let bottom = self.geometry.height - self.geometry.bottom;
+        for (i, line) in block.lines.iter().enumerate() {
+            if line.page_break_before || (!self.page_empty && self.y + line.height > bottom + 0.01)
+            {
+                self.new_page();
+            }
+            self.draw_line(block, i, self.y);
+            if let Some(every) = numbered.filter(|every| *every > 0) {
+                self.line_count += 1;
+                if self.line_count.is_multiple_of(every) {
+                    let label = self.line_count.to_string();
+                    let size = DEFAULT_FONT_SIZE * 0.8;
+                    let x = self.geometry.left - 18.0 - text_width(&label, size);
+                    self.writer.text(
+                        x,
+                        self.y + line.baseline,
+                        &label,
+                        size,
+                        FontStyle::default(),
+                        None,
+                    );
+                }
+            }
+            self.y += line.height;
+            self.page_empty = false;
+        }
+        self.y += block.after;
+        if block.page_break_after {
+            self.new_page();
+        }
+    }
+
+    // 在指定位置整段画出，不分页，返回段后的位置
+    fn draw_block(&mut self, block: &Block, y: f32) -> f32 {
+        let mut y = y + block.before;
+        for (i, line) in block.lines.iter().enumerate() {
+            self.draw_line(block, i, y);
+            y += line.height;
+        }
+        y + block.after
+    }
+
+    fn draw_line(&mut self, block: &Block, index: usize, y: f32) {
+        let line = &block.lines[index];
+        let shift = match block.alignment {
+            Some(AlignmentType::Center) => (block.width - line.width) / 2.0,
+            Some(AlignmentType::Right) => block.width - line.width,
+            _ => 0.0,
+        }
+        .max(0.0);
+        let baseline = y + line.baseline;
+        for piece in &line.pieces {
+            match piece {
+                Piece::Text { x, text, style } => {
+                    let x = block.x + shift + x;
+                    let width = text_width(text, style.size);
+                    let top = baseline - style.size * 0.8;
+                    if let Some(background) = style.background {
+                        self.writer.fill_rect(x, top, width, style.size, background);
+                    }
+                    self.writer.text(
+                        x,
+                        baseline - style.rise,
+                        text,
+                        style.size,
+                        style.font,
+                        style.color,
+                    );
+                    let thickness = style.size / 20.0;
+                    if style.underline {
+                        let under = baseline + style.size * 0.12;
+                        self.writer
+                            .line(x, under, x + width, under, thickness, style.color);
+                    }
+                    if style.strike {
+                        let middle = baseline - style.size * 0.25;
+                        self.writer
+                            .line(x, middle, x + width, middle, thickness, style.color);
+                    }
+                    if let Some(url) = &style.url {
+                        self.writer.link(x, top, width, style.size, url);
+                    }
+                }
+                Piece::Image {
+                    x,
+                    id,
+                    width,
+                    height,
+                } => {
+                    let x = block.x + shift + x;
+                    let top = baseline - height;
+                    match id {
+                        Some(id) => self.writer.draw_image(*id, x, top, *width, *height),
+                        None => self
+                            .writer
+                            .stroke_rect(x, top, *width, *height, (0.5, 0.5, 0.5)),
+                    }
+                }
+            }
+        }
+
+        // 修订线画在左页边距中，边框包住整段
+        let bottom = y + line.height;
+        if block.change_bar {
+            let x = self.geometry.left - 8.0;
+            self.writer.line(x, y, x, bottom, 1.0, None);
+        }
+        if let Some(color) = block.border {
+            let (left, right) = (block.x - 4.0, block.x + block.width + 4.0);
+            self.writer.line(left, y, left, bottom, 0.5, Some(color));
+            self.writer.line(right, y, right, bottom, 0.5, Some(color));
+            if index == 0 {
+                self.writer.line(left, y, right, y, 0.5, Some(color));
+            }
+            if index + 1 == block.lines.len() {
+                self.writer
+                    .line(left, bottom, right, bottom, 0.5, Some(color));
+            }
+        }
+    }
+
+    // 表格逐行放置，一行放不下时整行移到下一页
+    fn table(&mut self, table: &'a Table) {
+        let indent = table
+            .indent
+            .as_ref()
+            .filter(|indent| matches!(indent.width_type, WidthType::DXA))
+            .map_or(0.0, |indent| pt(indent.size));
+        let columns = table
+            .rows
+            .iter()
+            .map(|row| row.cells.len())
+            .max()
+            .unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let widths: Vec<f32> = if table.columnWidths.len() >= columns {
+            table.columnWidths.iter().map(|w| pt(*w as i32)).collect()
+        } else {
+            vec![(self.geometry.content_width() - indent) / columns as f32; columns]
+        };
+
+        for row in &table.rows {
+            let mut x = self.geometry.left + indent;
+            let mut cells = Vec::new();
+            for (cell, width) in row.cells.iter().zip(&widths) {
+                let blocks: Vec<Block> = cell
+                    .children
+                    .iter()
+                    .map(|paragraph| self.block(paragraph, x, *width, None))
+                    .collect();
+                cells.push((x, *width, blocks));
+                x += width;
+            }
+            let height = cells
+                .iter()
+                .map(|(_, _, blocks)| blocks.iter().map(Block::height).sum::<f32>())
+                .fold(0.0, f32::max);
+            if !self.page_empty && height > self.remaining() {
+                self.new_page();
+            }
+            let top = self.y;
+            for (x, width, blocks) in &cells {
+                let mut y = top;
+                for block in blocks {
+                    y = self.draw_block(block, y);
+                }
+                if !table.without_borders {
+                    self.writer
+                        .stroke_rect(*x, top, *width, height, (0.0, 0.0, 0.0));
+                }
+            }
+            self.y = top + height;
+            self.page_empty = false;
+        }
+    }
+
+    // 同一位置的一组框架段落，放在页边距内框架对齐方式对应的位置，不占用正文位置
+    fn frame(&mut self, group: &[&'a Paragraph]) {
+        let frame = group[0].frame.as_ref().unwrap();
+        let geometry = self.geometry;
+        let width = frame
+            .width
+            .filter(|width| *width > 0)
+            .map_or(geometry.content_width(), pt)
+            .min(geometry.content_width());
+        let x = match frame.x_align {
+            Some(HorizontalPositionAlign::Center) => {
+                geometry.left + (geometry.content_width() - width) / 2.0
+            }
+            Some(HorizontalPositionAlign::Right) | Some(HorizontalPositionAlign::Outside) => {
+                geometry.left + geometry.content_width() - width
+            }
+            _ => geometry.left,
+        };
+        let blocks: Vec<Block> = group
+            .iter()
+            .map(|paragraph| self.block(paragraph, x, width, None))
+            .collect();
+        let height: f32 = blocks.iter().map(Block::height).sum();
+        let mut y = match frame.y_align {
+            Some(VerticalPositionAlign::Center) => (geometry.height - height) / 2.0,
+            Some(VerticalPositionAlign::Bottom) | Some(VerticalPositionAlign::Outside) => {
+                geometry.height - geometry.bottom - height
+            }
+            _ => geometry.top,
+        };
+        for block in &blocks {
+            y = self.draw_block(block, y);
+        }
+        self.page_empty = false;
+    }
+
+    // 脚注列在文末，前面加一条分隔线
+    fn footnotes(&mut self) {
+        if self.footnotes.is_empty() {
+            return;
+        }
+        let footnotes = std::mem::take(&mut self.footnotes);
+        let size = self.default_style().size;
+        self.y += size;
+        if self.remaining() < size * 3.0 {
+            self.new_page();
+        }
+        let left = self.geometry.left;
+        self.writer
+            .line(left, self.y, left + 144.0, self.y, 0.5, None);
+        self.y += size / 2.0;
+        for (id, paragraphs) in footnotes {
+            for (i, paragraph) in paragraphs.iter().enumerate() {
+                let mut paragraph = paragraph.clone();
+                if i == 0 {
+                    paragraph
+                        .runs
+                        .insert(0, RunType::Text(TextRun::new(&format!("{}. ", id))));
+                }
+                let block = self.block(&paragraph, left, self.geometry.content_width(), None);
+                self.place(&block, None);
+            }
+        }
+    }
+
+    // 段落排版：展开内容、折行，计算缩进和间距
+    fn block(&mut self, paragraph: &Paragraph, left: f32, width: f32, page: Option<&str>) -> Block {
+        let style = paragraph
+            .style
+            .as_deref()
+            .and_then(|id| self.styles.get(id).copied());
+        let indent = |f: fn(&ParagraphIndent) -> Option<i32>| {
+            paragraph
+                .indent
+                .as_ref()
+                .and_then(f)
+                .or_else(|| style.and_then(|s| s.indent.as_ref()).and_then(f))
+                .map_or(0.0, pt)
+        };
+        let (indent_left, indent_right, first_line) = (
+            indent(|i| i.left),
+            indent(|i| i.right),
+            indent(|i| i.first_line),
+        );
+        let spacing = |f: fn(&ParagraphSpacing) -> Option<i32>| {
+            paragraph
+                .spacing
+                .as_ref()
+                .and_then(f)
+                .or_else(|| style.and_then(|s| s.spacing.as_ref()).and_then(f))
+        };
+        let line_rule = paragraph
+            .spacing
+            .as_ref()
+            .and_then(|s| s.line_rule)
+            .or_else(|| {
+                style
+                    .and_then(|s| s.spacing.as_ref())
+                    .and_then(|s| s.line_rule)
+            });
+
+        let base = self.paragraph_style(paragraph, style);
+        let line_height = match (spacing(|s| s.line), line_rule) {
+            (Some(line), Some(LineRuleType::Exact)) => pt(line),
+            (Some(line), Some(LineRuleType::AtLeast)) => pt(line).max(base.size),
+            (Some(line), _) => base.size * 1.15 * line as f32 / 240.0,
+            (None, _) => base.size * 1.15,
+        };
+        let before = spacing(|s| s.before).map(pt).unwrap_or_else(|| {
+            spacing(|s| s.before_lines).map_or(0.0, |lines| line_height * lines as f32 / 100.0)
+        });
+        let after = spacing(|s| s.after).map(pt).unwrap_or_else(|| {
+            spacing(|s| s.after_lines).map_or(0.0, |lines| line_height * lines as f32 / 100.0)
+        });
+
+        // 制表位从 left（页边距或单元格左边）算起，换算到左缩进处
+        let mut tabs: Vec<(f32, TabAlignment)> = paragraph
+            .tabs
+            .iter()
+            .chain(style.iter().flat_map(|s| &s.tabs))
+            .map(|tab| (pt(tab.position) - indent_left, tab.alignment))
+            .collect();
+        tabs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut items = Vec::new();
+        let revision = paragraph.revision.as_ref().map(|r| r.kind);
+        for run in &paragraph.runs {
+            self.run_items(run, &base, revision, page, &mut items);
+        }
+        let available = (width - indent_left - indent_right).max(base.size);
+        let (lines, page_break_after) =
+            break_lines(&items, available, first_line, &tabs, line_height, base.size);
+
+        Block {
+            lines,
+            x: left + indent_left,
+            width: available,
+            alignment: paragraph.alignment,
+            before,
+            after,
+            keep_next: paragraph.keep_next,
+            keep_lines: paragraph.keep_lines,
+            change_bar: paragraph.change_bar,
+            border: paragraph.border.as_deref().and_then(parse_color),
+            page_break_after,
+        }
+    }
+
+    fn default_style(&self) -> Style {
+        let run = self
+            .doc
+            .options
+            .styles
+            .as_ref()
+            .map(|styles| &styles.default.document.run);
+        Style {
+            size: run
+                .and_then(|run| run.size)
+                .map_or(DEFAULT_FONT_SIZE, |size| size as f32),
+            font: FontStyle {
+                bold: run.and_then(|run| run.bold).unwrap_or(false),
+                italic: run.and_then(|run| run.italic).unwrap_or(false),
+            },
+            color: run
+                .and_then(|run| run.color.as_deref())
+                .and_then(parse_color),
+            background: None,
+            underline: false,
+            strike: false,
+            rise: 0.0,
+            url: None,
+        }
+    }
+
+    // 段落样式中的文字属性叠加在文档默认样式上
+    fn paragraph_style(&self, paragraph: &Paragraph, style: Option<&ParagraphStyle>) -> Style {
+        let mut base = self.default_style();
+        if let Some(run) = style.and_then(|style| style.run.as_ref()) {
+            if let Some(size) = run.size {
+                base.size = size as f32;
+            }
+            if let Some(bold) = run.bold {
+                base.font.bold = bold;
+            }
+            if let Some(italic) = run.italic {
+                base.font.italic = italic;
+            }
+            if let Some(color) = run.color.as_deref().and_then(parse_color) {
+                base.color = Some(color);
+            }
+        }
+        // 行距按段落中第一个设置了字号的文字计算
+        if let Some(size) = paragraph.runs.iter().find_map(|run| match run {
+            RunType::Text(text) => text.props.size,
+            _ => None,
+        }) {
+            base.size = size as f32;
+        }
+        base
+    }
+
+    fn run_items(
+        &mut self,
+        run: &RunType,
+        base: &Style,
+        revision: Option<RevisionKind>,
+        page: Option<&str>,
+        items: &mut Vec<Item>,
+    ) {
+        match run {
+            RunType::Text(text_run) => {
+                if text_run.comment.is_some() {
+                    return;
+                }
+                let style = run_style(&text_run.props, base, revision, text_run.url.as_deref());
+                if let Some(id) = text_run.footnote_id {
+                    let content = match &text_run.footnote_content {
+                        Some(content) if !content.is_empty() => content.as_slice(),
+                        _ => self
+                            .doc
+                            .options
+                            .footnotes
+                            .get(&id)
+                            .map(|f| f.children.as_slice())
+                            .unwrap_or_default(),
+                    };
+                    self.footnotes.push((id, content.to_vec()));
+                    let reference = Style {
+                        size: style.size * 0.6,
+                        rise: style.size * 0.35,
+                        ..style
+                    };
+                    items.push(Item::Text(id.to_string(), reference));
+                    return;
+                }
+                if text_run.break_before || text_run.props.break_before == Some(true) {
+                    items.push(Item::LineBreak);
+                }
+                push_text(&text_run.text, &style, items);
+                match text_run.props.break_type {
+                    Some(BreakType::Page) => items.push(Item::PageBreak),
+                    Some(_) => items.push(Item::LineBreak),
+                    None => {}
+                }
+                for child in &text_run.children {
+                    self.run_items(child, base, revision, page, items);
+                }
+            }
+            RunType::Break(break_run) => match break_run.break_type {
+                BreakType::Page => items.push(Item::PageBreak),
+                _ => items.push(Item::LineBreak),
+            },
+            RunType::Hyperlink(link) => {
+                let style = run_style(&link.props, base, revision, Some(&link.url));
+                push_text(&link.text, &style, items);
+            }
+            RunType::PageNumber(page_number) => {
+                let style = run_style(&page_number.props, base, revision, None);
+                let mut text = String::new();
+                for child in &page_number.children {
+                    match child {
+                        PageNumberChild::Text(part) => text.push_str(part),
+                        PageNumberChild::PageNumber => text.push_str(page.unwrap_or("")),
+                    }
+                }
+                push_text(&text, &style, items);
+            }
+            RunType::Image(image) => {
+                let id = self.writer.add_image(&image.data);
+                // 像素按 96 dpi 换算
+                let (origin_width, origin_height) = id.map_or((72.0, 72.0), |id| {
+                    let (w, h) = self.writer.image_size(id);
+                    (w as f32 * 0.75, h as f32 * 0.75)
+                });
+                let (width, height) = match (image.width, image.height) {
+                    (Some(w), Some(h)) => (w * 72.0, h * 72.0),
+                    (Some(w), None) if origin_width > 0.0 => {
+                        (w * 72.0, w * 72.0 * origin_height / origin_width)
+                    }
+                    (None, Some(h)) if origin_height > 0.0 => {
+                        (h * 72.0 * origin_width / origin_height, h * 72.0)
+                    }
+                    _ => (origin_width, origin_height),
+                };
+                items.push(Item::Image { id, width, height });
+            }
+        }
+    }
+}
+
+// 文字中的换行和制表符拆成单独的单元
+fn push_text(text: &str, style: &Style, items: &mut Vec<Item>) {
+    let mut current = String::new();
+    for c in text.chars() {
+        match c {
+            '\n' | '\t' => {
+                if !current.is_empty() {
+                    items.push(Item::Text(std::mem::take(&mut current), style.clone()));
+                }
+                items.push(if c == '\n' {
+                    Item::LineBreak
+                } else {
+                    Item::Tab
+                });
+            }
+            '\r' => {}
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        items.push(Item::Text(current, style.clone()));
+    }
+}
+
+fn run_style(
+    props: &RunProps,
+    base: &Style,
+    revision: Option<RevisionKind>,
+    url: Option<&str>,
+) -> Style {
+    let mut style = base.clone();
+    if let Some(size) = props.size {
+        style.size = size as f32;
+    }
+    if let Some(bold) = props.bold {
+        style.font.bold = bold;
+    }
+    if let Some(italic) = props.italic {
+        style.font.italic = italic;
+    }
+    if let Some(underline) = &props.underline {
+        style.underline = !matches!(underline, UnderlineType::None);
+    }
+    if let Some(strike) = props.strike {
+        style.strike = strike;
+    }
+    if let Some(color) = props.color.as_deref().and_then(parse_color) {
+        style.color = Some(color);
+    }
+    style.background = match (&props.highlight, &props.shading) {
+        (Some(highlight), _) => parse_color(crate::odt::highlight_rgb(highlight)),
+        (None, Some(shading)) => parse_color(shading),
+        (None, None) => None,
+    };
+    if props.superscript == Some(true) {
+        style.size *= 0.65;
+        style.rise = base.size * 0.33;
+    } else if props.subscript == Some(true) {
+        style.size *= 0.65;
+        style.rise = -base.size * 0.15;
+    }
+    // 与 ODT 相同，修订以删除线/下划线表示
+    match revision {
+        Some(RevisionKind::Delete) => style.strike = true,
+        Some(RevisionKind::Insert) => style.underline = true,
+        None => {}
+    }
+    style.url = url.map(str::to_string);
+    style
+}
+
+// 折行时不可拆开的片段：连续空格、一个全角字符，或连续的其它字符（单词）
+fn words(text: &str) -> Vec<&str> {
+    let kind = |c: char| {
+        if c == ' ' {
+            0
+        } else if is_wide(c) {
+            1
+        } else {
+            2
+        }
+    };
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut last = None;
+    for (i, c) in text.char_indices() {
+        let k = kind(c);
+        if i > start && (k == 1 || Some(k) != last) {
+            words.push(&text[start..i]);
+            start = i;
+        }
+        last = Some(k);
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+fn is_wide(c: char) -> bool {
+    text_width(c.encode_utf8(&mut [0; 4]), 1.0) > 0.9
+}
+
+// 行宽为 width 的贪心折行，第一行从 first_line 处开始（负数为悬挂缩进）；
+// 返回各行，以及段落末尾是否有分页符
+fn break_lines(
+    items: &[Item],
+    width: f32,
+    first_line: f32,
+    tabs: &[(f32, TabAlignment)],
+    line_height: f32,
+    size: f32,
+) -> (Vec<Line>, bool) {
+    let mut breaker = LineBreaker {
+        lines: Vec::new(),
+        line: Line::default(),
+        x: first_line,
+        start: first_line,
+        width,
+        line_height,
+        size,
+        tab: None,
+        wrapped: false,
+        page_break: false,
+    };
+    for item in items {
+        match item {
+            Item::Text(text, style) => {
+                for word in words(text) {
+                    breaker.word(word, style);
+                }
+            }
+            Item::Tab => breaker.tab(tabs),
+            Item::LineBreak => breaker.finish_line(false),
+            Item::PageBreak => {
+                if !breaker.line.pieces.is_empty() {
+                    breaker.finish_line(false);
+                }
+                breaker.page_break = true;
+            }
+            Item::Image { id, width, height } => {
+                if breaker.x + width > breaker.width && !breaker.line.pieces.is_empty() {
+                    breaker.finish_line(true);
+                }
+                breaker.line.pieces.push(Piece::Image {
+                    x: breaker.x,
+                    id: *id,
+                    width: *width,
+                    height: *height,
+                });
+                breaker.x += width;
+                breaker.line.height = breaker.line.height.max(height + size * 0.2);
+                breaker.line.baseline = breaker.line.baseline.max(*height);
+            }
+        }
+    }
+    // 段落以分页符结束时，分页符之后没有空行
+    let page_break_after = std::mem::take(&mut breaker.page_break);
+    if !page_break_after || !breaker.line.pieces.is_empty() {
+        breaker.finish_line(false);
+    }
+    (breaker.lines, page_break_after)
+}
+
+struct LineBreaker {
+    lines: Vec<Line>,
+    line: Line,
+    x: f32,
+    // 本行起点
+    start: f32,
+    width: f32,
+    line_height: f32,
+    size: f32,
+    // 右对齐或居中的制表位：从第几个片段开始、制表符处的位置、制表位位置和对齐方式
+    tab: Option<(usize, f32, f32, TabAlignment)>,
+    // 本行是自动折行产生的，行首空格不显示
+    wrapped: bool,
+    page_break: bool,
+}
+
+impl LineBreaker {
+    fn word(&mut self, word: &str, style: &Style) {
+        let width = text_width(word, style.size);
+        if word.starts_with(' ') {
+            if self.wrapped && self.line.pieces.is_empty() {
+                return;
+            }
+            if self.x + width > self.width + 0.01 {
+                self.finish_line(true);
+                return;
+            }
+            self.push(word, style, width);
+            return;
+        }
+        if self.x + width > self.width + 0.01 && self.has_content() {
+            self.finish_line(true);
+        }
+        if self.x + width > self.width + 0.01 {
+            // 一个单词比整行还长，按字符拆开
+            for c in word.chars() {
+                let text = c.to_string();
+                let width = text_width(&text, style.size);
+                if self.x + width > self.width + 0.01 && self.has_content() {
+                    self.finish_line(true);
+                }
+                self.push(&text, style, width);
+            }
+            return;
+        }
+        self.push(word, style, width);
+    }
+
+    fn has_content(&self) -> bool {
+        self.x > self.start + 0.01
+    }
+
+    fn push(&mut self, text: &str, style: &Style, width: f32) {
+        let merged = match self.line.pieces.last_mut() {
+            Some(Piece::Text {
+                x,
+                text: last,
+                style: last_style,
+            }) if last_style == style
+                && (*x + text_width(last, style.size) - self.x).abs() < 0.01 =>
+            {
+                last.push_str(text);
+                true
+            }
+            _ => false,
+        };
+        if !merged {
+            self.line.pieces.push(Piece::Text {
+                x: self.x,
+                text: text.to_string(),
+                style: style.clone(),
+            });
+        }
+        self.x += width;
+    }
+
+    // 跳到下一个制表位；右对齐和居中的制表位等后面的内容排完再移动
+    fn tab(&mut self, tabs: &[(f32, TabAlignment)]) {
+        self.align_tab();
+        match tabs.iter().find(|(position, _)| *position > self.x + 0.01) {
+            Some((position, TabAlignment::Left)) => self.x = *position,
+            Some((position, alignment)) => {
+                self.tab = Some((self.line.pieces.len(), self.x, *position, *alignment));
+            }
+            None => self.x = ((self.x / DEFAULT_TAB).floor() + 1.0) * DEFAULT_TAB,
+        }
+    }
+
+    fn align_tab(&mut self) {
+        let Some((from, tab_x, position, alignment)) = self.tab.take() else {
+            return;
+        };
+        let content = self.x - tab_x;
+        let target = match alignment {
+            TabAlignment::Center => position - content / 2.0,
+            _ => position - content,
+        };
+        let shift = target - tab_x;
+        if shift > 0.0 {
+            for piece in &mut self.line.pieces[from..] {
+                piece.shift(shift);
+            }
+            self.x += shift;
+        }
+    }
+
+    fn finish_line(&mut self, wrapped: bool) {
+        self.align_tab();
+        let mut line = std::mem::take(&mut self.line);
+        // 行尾空格不计入行宽
+        while let Some(Piece::Text { text, .. }) = line.pieces.last() {
+            if !text.trim_end_matches(' ').is_empty() {
+                break;
+            }
+            line.pieces.pop();
+        }
+        line.width = match line.pieces.last_mut() {
+            Some(Piece::Text { x, text, style }) => {
+                let trimmed = text.trim_end_matches(' ').len();
+                text.truncate(trimmed);
+                *x + text_width(text, style.size)
+            }
+            Some(Piece::Image { x, width, .. }) => *x + *width,
+            None => 0.0,
+        };
+        line.height = line.height.max(self.line_height);
+        line.baseline = line
+            .baseline
+            .max((self.line_height + self.size * 0.6) / 2.0);
+        line.page_break_before = std::mem::take(&mut self.page_break);
+        self.lines.push(line);
+        self.x = 0.0;
+        self.start = 0.0;
+        self.wrapped = wrapped;
+    }
+}
+
+// 框架的对齐方式，相同的连续框架段落放在一起
+fn frame_key(paragraph: &Paragraph) -> String {
+    paragraph
+        .frame
+        .as_ref()
+        .map(|frame| format!("{:?}/{:?}", frame.x_align, frame.y_align))
+        .unwrap_or_default()
+}
+
+// "#RRGGBB" 或 "RRGGBB" 转为颜色
+fn parse_color(text: &str) -> Option<Color> {
+    let hex = text.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some((
+        ((value >> 16) & 0xFF) as f32 / 255.0,
+        ((value >> 8) & 0xFF) as f32 / 255.0,
+        (value & 0xFF) as f32 / 255.0,
+    ))
+}
+
+fn roman(mut number: i32) -> String {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    let mut text = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            text.push_str(numeral);
+            number -= value;
+        }
+    }
+    text
+}
+
+fn pt(twips: i32) -> f32 {
+    twips as f32 / TWIPS_PER_PT
+}
@@ -21,17 +21,14 @@ impl Liner {
 
     /// 分割token为行
     pub fn split_token3(&self, token: &ScriptToken) -> Vec<Line> {
-        let tmp_text = if token.token_type == "character" && self.print_take_numbers {
-            if let Some(take_number) = token.take_number {
-                format!("{} - {}", take_number, token.text)
-            } else {
-                token.text.clone()
-            }
+        // 对白编号不写进文本，由导出时决定打印位置
+        let take_number = if token.token_type == "character" && self.print_take_numbers {
+            token.take_number
         } else {
-            token.text.clone()
+            None
         };
 
-        let lines = tmp_text.split('\n');
+        let lines = token.text.split('\n');
         let mut result = Vec::new();
         let mut st = token.start;
 
@@ -48,6 +45,7 @@ impl Liner {
                 number: token.number.clone(),
                 dual: token.dual.clone(),
                 level: token.level.clone(),
+                take_number: if i == 0 { take_number } else { None },
             });
             st += l;
         }
@@ -1 +1,3 @@
+pub mod layout;
 pub mod liner;
+pub mod writer;
@@ -0,0 +1,561 @@
+//! 最小的 PDF 写出器
+//!
+//! 只实现排版用得到的部分：标准 14 字体中的 Courier 系列（WinAnsi 编码），
+//! Courier 没有的字符（中文等）用 Acrobat 亚洲字体包中的 STSong-Light（不嵌入字体文件，由阅读器提供）；
+//! 线条和矩形、JPEG/PNG 图片以及外部链接。
+//! 内容流不压缩，也不写创建时间，同样的输入总是得到同样的文件。
+
+/// 字形：粗体、斜体
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FontStyle {
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// RGB 颜色，各分量取值 0~1
+pub type Color = (f32, f32, f32);
+
+// Courier 每个字符宽 0.6em，STSong-Light 按全角 1em 计
+const COURIER_WIDTH: f32 = 0.6;
+const CJK_WIDTH: f32 = 1.0;
+
+struct Page {
+    width: f32,
+    height: f32,
+    content: String,
+    links: Vec<([f32; 4], String)>,
+}
+
+struct Image {
+    width: u32,
+    height: u32,
+    // 图片字典中除 /Length 外的条目
+    dict: String,
+    data: Vec<u8>,
+}
+
+/// PDF 文档，坐标以磅（1/72 英寸）为单位，原点在页面左上角、y 向下
+pub struct PdfWriter {
+    pages: Vec<Page>,
+    images: Vec<Image>,
+}
+
+impl Default for PdfWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfWriter {
+    pub fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    /// 新建一页，之后的绘制都在这一页上
+    pub fn add_page(&mut self, width: f32, height: f32) {
+        self.pages.push(Page {
+            width,
+            height,
+            content: String::new(),
+            links: Vec::new(),
+        });
+    }
+
+    /// 已有的页数
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    // 当前页，还没有页面时按 Letter 纸张新建一页
+    fn page(&mut self) -> &mut Page {
+        if self.pages.is_empty() {
+            self.add_page(612.0, 792.0);
+        }
+        self.pages.last_mut().unwrap()
+    }
+
+    /// 在基线 (x, y) 处输出一行文字，返回文字宽度
+    pub fn text(
+        &mut self,
+        x: f32,
+        y: f32,
+        text: &str,
+        size: f32,
+        style: FontStyle,
+        color: Option<Color>,
+    ) -> f32 {
+        let page = self.page();
+        let baseline = page.height - y;
+        let mut ops = String::from("q ");
+        if let Some((r, g, b)) = color {
+            ops.push_str(&format!(
+                "{} {} {} rg {} {} {} RG ",
+                num(r),
+                num(g),
+                num(b),
+                num(r),
+                num(g),
+                num(b)
+            ));
+        }
+        ops.push_str("BT ");
+        let mut offset = 0.0;
+        for (cjk, segment) in segments(text) {
+            // 斜体用倾斜的文字矩阵，粗体用描边加粗，STSong-Light 只有常规字形
+            let (font, skew) = if cjk {
+                ("F5", if style.italic { 0.2 } else { 0.0 })
+            } else {
+                (courier_font(style), 0.0)
+            };
+            ops.push_str(&format!(
+                "/{} {} Tf 1 0 {} 1 {} {} Tm ",
+                font,
+                num(size),
+                num(skew),
+                num(x + offset),
+                num(baseline)
+            ));
+            if cjk && style.bold {
+                ops.push_str(&format!("2 Tr {} w ", num(size * 0.03)));
+            } else {
+                ops.push_str("0 Tr ");
+            }
+            if cjk {
+                let hex: String = segment
+                    .encode_utf16()
+                    .map(|unit| format!("{:04X}", unit))
+                    .collect();
+                ops.push_str(&format!("<{}> Tj ", hex));
+            } else {
+                let bytes: Vec<u8> = segment.chars().filter_map(win_ansi).collect();
+                ops.push_str(&format!("({}) Tj ", escape_bytes(&bytes)));
+            }
+            offset += text_width(&segment, size);
+        }
+        ops.push_str("ET Q\n");
+        page.content.push_str(&ops);
+        offset
+    }
+
+    /// 画线段
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32, color: Option<Color>) {
+        let page = self.page();
+        let (r, g, b) = color.unwrap_or((0.0, 0.0, 0.0));
+        let ops = format!(
+            "q {} {} {} RG {} w {} {} m {} {} l S Q\n",
+            num(r),
+            num(g),
+            num(b),
+            num(width),
+            num(x1),
+            num(page.height - y1),
+            num(x2),
+            num(page.height - y2)
+        );
+        page.content.push_str(&ops);
+    }
+
+    /// 填充矩形，(x, y) 为左上角
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let page = self.page();
+        let ops = format!(
+            "q {} {} {} rg {} {} {} {} re f Q\n",
+            num(color.0),
+            num(color.1),
+            num(color.2),
+            num(x),
+            num(page.height - y - height),
+            num(width),
+            num(height)
+        );
+        page.content.push_str(&ops);
+    }
+
+    /// 矩形边框，(x, y) 为左上角
+    pub fn stroke_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let page = self.page();
+        let ops = format!(
+            "q {} {} {} RG 0.5 w {} {} {} {} re S Q\n",
+            num(color.0),
+            num(color.1),
+            num(color.2),
+            num(x),
+            num(page.height - y - height),
+            num(width),
+            num(height)
+        );
+        page.content.push_str(&ops);
+    }
+
+    /// 加入图片，返回图片编号；只支持 JPEG 和不带透明通道的 PNG，其它格式返回 None
+    pub fn add_image(&mut self, data: &[u8]) -> Option<usize> {
+        let image = if data.starts_with(&[0xFF, 0xD8]) {
+            jpeg_image(data)?
+        } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            png_image(data)?
+        } else {
+            return None;
+        };
+        self.images.push(image);
+        Some(self.images.len() - 1)
+    }
+
+    /// 图片的像素尺寸
+    pub fn image_size(&self, id: usize) -> (u32, u32) {
+        let image = &self.images[id];
+        (image.width, image.height)
+    }
+
+    /// 画图片，(x, y) 为左上角
+    pub fn draw_image(&mut self, id: usize, x: f32, y: f32, width: f32, height: f32) {
+        let page = self.page();
+        let ops = format!(
+            "q {} 0 0 {} {} {} cm /Im{} Do Q\n",
+            num(width),
+            num(height),
+            num(x),
+            num(page.height - y - height),
+            id + 1
+        );
+        page.content.push_str(&ops);
+    }
+
+    /// 外部链接区域，(x, y) 为左上角
+    pub fn link(&mut self, x: f32, y: f32, width: f32, height: f32, url: &str) {
+        let page = self.page();
+        let bottom = page.height - y - height;
+        page.links
+            .push(([x, bottom, x + width, bottom + height], url.to_string()));
+    }
+
+    /// 写出整个文件
+    pub fn finish(mut self, title: &str) -> Vec<u8> {
+        self.page();
+
+        // 1 目录，2 页面树，3 文档信息，4~7 Courier，8~10 STSong-Light，11 共用的资源，
+        // 之后依次是图片和每页的页面、内容流
+        let first_image = 12;
+        let first_page = first_image + self.images.len();
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+
+        let kids: Vec<String> = (0..self.pages.len())
+            .map(|i| format!("{} 0 R", first_page + i * 2))
+            .collect();
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.pages.len()
+            )
+            .into_bytes(),
+        );
+        let title_hex: String = title
+            .encode_utf16()
+            .map(|unit| format!("{:04X}", unit))
+            .collect();
+        objects.push(
+            format!(
+                "<< /Title <FEFF{}> /Producer (betterfountain-rust) >>",
+                title_hex
+            )
+            .into_bytes(),
+        );
+        for name in [
+            "Courier",
+            "Courier-Bold",
+            "Courier-Oblique",
+            "Courier-BoldOblique",
+        ] {
+            objects.push(
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    name
+                )
+                .into_bytes(),
+            );
+        }
+        objects.push(
+            b"<< /Type /Font /Subtype /Type0 /BaseFont /STSong-Light /Encoding /UniGB-UTF16-H /DescendantFonts [9 0 R] >>"
+                .to_vec(),
+        );
+        objects.push(
+            b"<< /Type /Font /Subtype /CIDFontType0 /BaseFont /STSong-Light \
+/CIDSystemInfo << /Registry (Adobe) /Ordering (GB1) /Supplement 2 >> /FontDescriptor 10 0 R /DW 1000 >>"
+                .to_vec(),
+        );
+        objects.push(
+            b"<< /Type /FontDescriptor /FontName /STSong-Light /Flags 6 /FontBBox [-25 -254 1000 880] \
+/ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 880 /StemV 93 >>"
+                .to_vec(),
+        );
+        let xobjects: Vec<String> = (0..self.images.len())
+            .map(|i| format!("/Im{} {} 0 R", i + 1, first_image + i))
+            .collect();
+        objects.push(
+            format!(
+                "<< /Font << /F1 4 0 R /F2 5 0 R /F3 6 0 R /F4 7 0 R /F5 8 0 R >> /XObject << {} >> >>",
+                xobjects.join(" ")
+            )
+            .into_bytes(),
+        );
+        for image in &self.images {
+            objects.push(stream(&image.dict, &image.data));
+        }
+        for (i, page) in self.pages.iter().enumerate() {
+            let annots = if page.links.is_empty() {
+                String::new()
+            } else {
+                let links: Vec<String> = page
+                    .links
+                    .iter()
+                    .map(|(rect, url)| {
+                        format!(
+                            "<< /Type /Annot /Subtype /Link /Rect [{} {} {} {}] /Border [0 0 0] /A << /S /URI /URI ({}) >> >>",
+                            num(rect[0]),
+                            num(rect[1]),
+                            num(rect[2]),
+                            num(rect[3]),
+                            escape_bytes(url.as_bytes())
+                        )
+                    })
+                    .collect();
+                format!(" /Annots [{}]", links.join(" "))
+            };
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources 11 0 R /Contents {} 0 R{} >>",
+                    num(page.width),
+                    num(page.height),
+                    first_page + i * 2 + 1,
+                    annots
+                )
+                .into_bytes(),
+            );
+            objects.push(stream("", page.content.as_bytes()));
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+}
+
+/// 文字宽度（磅）
+pub fn text_width(text: &str, size: f32) -> f32 {
+    text.chars()
+        .map(|c| {
+            if win_ansi(c).is_some() {
+                COURIER_WIDTH
+            } else {
+                CJK_WIDTH
+            }
+        })
+        .sum::<f32>()
+        * size
+}
+
+// 按能否用 WinAnsi 编码把文字切分为 Courier 段和 STSong-Light 段
+fn segments(text: &str) -> Vec<(bool, String)> {
+    let mut segments: Vec<(bool, String)> = Vec::new();
+    for c in text.chars() {
+        let cjk = win_ansi(c).is_none();
+        match segments.last_mut() {
+            Some((last, segment)) if *last == cjk => segment.push(c),
+            _ => segments.push((cjk, c.to_string())),
+        }
+    }
+    segments
+}
+
+fn courier_font(style: FontStyle) -> &'static str {
+    match (style.bold, style.italic) {
+        (false, false) => "F1",
+        (true, false) => "F2",
+        (false, true) => "F3",
+        (true, true) => "F4",
+    }
+}
+
+// 字符的 WinAnsi 编码，没有时返回 None
+fn win_ansi(c: char) -> Option<u8> {
+    let code = c as u32;
+    match code {
+        0x20..=0x7E | 0xA0..=0xFF => Some(code as u8),
+        _ => Some(match c {
+            '€' => 0x80,
+            '‚' => 0x82,
+            'ƒ' => 0x83,
+            '„' => 0x84,
+            '…' => 0x85,
+            '†' => 0x86,
+            '‡' => 0x87,
+            'ˆ' => 0x88,
+            '‰' => 0x89,
+            'Š' => 0x8A,
+            '‹' => 0x8B,
+            'Œ' => 0x8C,
+            'Ž' => 0x8E,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '˜' => 0x98,
+            '™' => 0x99,
+            'š' => 0x9A,
+            '›' => 0x9B,
+            'œ' => 0x9C,
+            'ž' => 0x9E,
+            'Ÿ' => 0x9F,
+            _ => return None,
+        }),
+    }
+}
+
+// PDF 字符串转义，非 ASCII 字节写成八进制
+fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for &b in bytes {
+        match b {
+            b'(' | b')' | b'\\' => {
+                escaped.push('\\');
+                escaped.push(b as char);
+            }
+            0x20..=0x7E => escaped.push(b as char),
+            _ => escaped.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    escaped
+}
+
+// 数值最多保留两位小数，去掉多余的零
+fn num(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {}/Length {} >>\nstream\n", dict, data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+// JPEG 直接嵌入，从 SOF 段读取尺寸和颜色通道数
+fn jpeg_image(data: &[u8]) -> Option<Image> {
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_sof {
+            let segment = data.get(pos + 4..pos + 4 + 6)?;
+            let height = u16::from_be_bytes([segment[1], segment[2]]) as u32;
+            let width = u16::from_be_bytes([segment[3], segment[4]]) as u32;
+            let color_space = match segment[5] {
+                1 => "/DeviceGray",
+                3 => "/DeviceRGB",
+                4 => "/DeviceCMYK",
+                _ => return None,
+            };
+            return Some(Image {
+                width,
+                height,
+                dict: format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent 8 /Filter /DCTDecode ",
+                    width, height, color_space
+                ),
+                data: data.to_vec(),
+            });
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+// PNG 的 IDAT 数据就是 zlib 压缩流，加上 PNG 预测器参数即可直接嵌入，不用解压；
+// 带透明通道和隔行扫描的 PNG 需要解压重排，不支持
+fn png_image(data: &[u8]) -> Option<Image> {
+    let mut pos = 8;
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut idat = Vec::new();
+    while pos + 8 <= data.len() {
+        let length = u32::from_be_bytes(data[pos..pos + 4].try_into().ok()?) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let chunk = data.get(pos + 8..pos + 8 + length)?;
+        match kind {
+            b"IHDR" if length >= 13 => header = Some(chunk.to_vec()),
+            b"PLTE" => palette = chunk.to_vec(),
+            b"IDAT" => idat.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        pos += 12 + length;
+    }
+    let header = header?;
+    let width = u32::from_be_bytes(header[0..4].try_into().ok()?);
+    let height = u32::from_be_bytes(header[4..8].try_into().ok()?);
+    let bit_depth = header[8];
+    let color_type = header[9];
+    if header[12] != 0 || idat.is_empty() {
+        return None;
+    }
+    let (color_space, colors) = match color_type {
+        0 => ("/DeviceGray".to_string(), 1),
+        2 => ("/DeviceRGB".to_string(), 3),
+        3 if !palette.is_empty() => {
+            let hex: String = palette.iter().map(|b| format!("{:02X}", b)).collect();
+            (
+                format!("[/Indexed /DeviceRGB {} <{}>]", palette.len() / 3 - 1, hex),
+                1,
+            )
+        }
+        _ => return None,
+    };
+    Some(Image {
+        width,
+        height,
+        dict: format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent {} \
+/Filter /FlateDecode /DecodeParms << /Predictor 15 /Colors {} /BitsPerComponent {} /Columns {} >> ",
+            width, height, color_space, bit_depth, colors, bit_depth, width
+        ),
+        data: idat,
+    })
+}
@@ -0,0 +1,130 @@
+//! 进度与取消
+//!
+//! 解析和导出较长的剧本需要一段时间。调用方通过 `Progress` 接收各阶段处理到第几行，用于显示进度条；
+//! 持有同一个 `CancellationToken` 的其它线程可以随时取消，解析和导出在处理下一行前发现已取消，
+//! 尽快返回 `Cancelled`，不会写出不完整的文件。
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// 每处理这么多行回调一次进度，避免回调过于频繁
+const REPORT_INTERVAL: usize = 50;
+
+/// 导出阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ExportPhase {
+    /// 解析 Fountain 文本
+    Parse,
+    /// 排版（生成文档内容）
+    Layout,
+    /// 序列化并写入文件或编码为 Base64
+    Save,
+}
+
+/// 一次进度通知
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    pub phase: ExportPhase,
+    /// 已处理的行数
+    pub done: usize,
+    /// 本阶段的总行数
+    pub total: usize,
+}
+
+impl ProgressEvent {
+    /// 本阶段完成的比例，0.0 到 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.done as f64 / self.total as f64).min(1.0)
+        }
+    }
+}
+
+/// 操作已被取消
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("操作已取消")]
+pub struct Cancelled;
+
+/// 取消令牌，复制出的令牌共享同一个取消状态，可以交给其它线程
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 取消，之后所有持有该令牌的解析和导出都会返回 `Cancelled`
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// 进度回调与取消令牌，传给解析和导出；默认没有回调，也不会被取消
+#[derive(Clone, Default)]
+pub struct Progress {
+    callback: Option<Arc<dyn Fn(ProgressEvent) + Send + Sync>>,
+    token: CancellationToken,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置进度回调，回调可能在执行解析、导出的线程中调用
+    pub fn on_progress(mut self, callback: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// 使用指定的取消令牌
+    pub fn with_token(mut self, token: CancellationToken) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// 已取消时返回 `Cancelled`
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.token.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 报告某阶段处理到第 `done` 行（共 `total` 行），每隔若干行及阶段开始、结束时回调；已取消时返回 `Cancelled`
+    pub fn report(&self, phase: ExportPhase, done: usize, total: usize) -> Result<(), Cancelled> {
+        self.check()?;
+        if let Some(callback) = &self.callback {
+            if done.is_multiple_of(REPORT_INTERVAL) || done >= total {
+                callback(ProgressEvent { phase, done, total });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("callback", &self.callback.is_some())
+            .field("token", &self.token)
+            .finish()
+    }
+}
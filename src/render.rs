@@ -0,0 +1,377 @@
+//! 导出后端
+//!
+//! docx_maker 的排版结果（adapter 中的 Document/Section/Paragraph/TextRun 模型）与输出格式无关，
+//! 各种格式实现 [`RenderBackend`]，把同一份排版结果序列化为文件，而不必各自重新遍历 token，
+//! 从而保证不同格式的分页、缩进和样式一致。
+
+use crate::docx::adapter::docx::{
+    Document, PageNumberChild, Paragraph, RunType, SectionChild, TextRun,
+};
+use crate::docx::adapter::{AlignmentType, DocxAdapterError, RunProps, TabAlignment};
+use crate::odt::OdtError;
+use thiserror::Error;
+
+/// 导出后端错误
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error("DOCX导出错误: {0}")]
+    Docx(#[from] DocxAdapterError),
+
+    #[error("ODT导出错误: {0}")]
+    Odt(#[from] OdtError),
+}
+
+/// 导出后端：把排版结果序列化为某种文件格式
+pub trait RenderBackend: Send + Sync {
+    /// 输出文件的扩展名
+    fn extension(&self) -> &'static str;
+
+    /// 序列化为文件内容
+    fn render(&self, doc: &Document) -> Result<Vec<u8>, RenderError>;
+}
+
+/// Word 文档（.docx）
+pub struct DocxBackend;
+
+impl RenderBackend for DocxBackend {
+    fn extension(&self) -> &'static str {
+        "docx"
+    }
+
+    fn render(&self, doc: &Document) -> Result<Vec<u8>, RenderError> {
+        Ok(doc.to_bytes()?)
+    }
+}
+
+/// OpenDocument 文本（.odt）
+pub struct OdtBackend;
+
+impl RenderBackend for OdtBackend {
+    fn extension(&self) -> &'static str {
+        "odt"
+    }
+
+    fn render(&self, doc: &Document) -> Result<Vec<u8>, RenderError> {
+        Ok(crate::odt::to_odt_bytes(doc)?)
+    }
+}
+
+/// 按排版结果生成的 HTML，分页处以分隔线表示
+///
+/// 与解析时按 token 生成的 HTML（`ParseOutput::script_html`）不同，这里保留了打印排版的缩进和分页。
+pub struct HtmlBackend;
+
+impl RenderBackend for HtmlBackend {
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn render(&self, doc: &Document) -> Result<Vec<u8>, RenderError> {
+        let mut body = String::new();
+        let mut footnotes = Vec::new();
+        for (i, section) in doc.options.sections.iter().enumerate() {
+            if i > 0 {
+                body.push_str("<hr class=\"page-break\">\n");
+            }
+            for child in &section.children {
+                match child {
+                    SectionChild::Paragraph(paragraph) => {
+                        body.push_str(&html_paragraph(paragraph, &mut footnotes));
+                    }
+                    SectionChild::Table(table) => {
+                        body.push_str("<table class=\"dual-dialogue\">\n");
+                        for row in &table.rows {
+                            body.push_str("<tr>");
+                            for cell in &row.cells {
+                                body.push_str("<td>");
+                                for paragraph in &cell.children {
+                                    body.push_str(&html_paragraph(paragraph, &mut footnotes));
+                                }
+                                body.push_str("</td>");
+                            }
+                            body.push_str("</tr>\n");
+                        }
+                        body.push_str("</table>\n");
+                    }
+                    SectionChild::PageBreak => body.push_str("<hr class=\"page-break\">\n"),
+                }
+            }
+        }
+
+        // 脚注统一列在文末
+        if !footnotes.is_empty() {
+            body.push_str("<ol class=\"footnotes\">\n");
+            for id in footnotes {
+                let content: String = doc
+                    .options
+                    .footnotes
+                    .get(&id)
+                    .map(|f| {
+                        f.children
+                            .iter()
+                            .map(|p| html_paragraph(p, &mut Vec::new()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                body.push_str(&format!(
+                    "<li id=\"fn{}\" value=\"{}\">{}</li>\n",
+                    id, id, content
+                ));
+            }
+            body.push_str("</ol>\n");
+        }
+
+        let html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+body {{ font-family: \"Courier Prime\", monospace; }}\n\
+p {{ margin: 0; white-space: pre-wrap; }}\n\
+.page-break {{ border: none; border-top: 1px dashed #999; margin: 2em 0; }}\n\
+.dual-dialogue {{ width: 100%; border-collapse: collapse; table-layout: fixed; }}\n\
+.dual-dialogue td {{ vertical-align: top; }}\n\
+</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape_html(&doc.options.title),
+            body
+        );
+        Ok(html.into_bytes())
+    }
+}
+
+/// PDF，折行和分页在 [`crate::pdf::layout`] 中完成
+///
+/// 英文用阅读器内置的 Courier，中文用 STSong-Light，都不嵌入字体文件。
+pub struct PdfBackend;
+
+impl RenderBackend for PdfBackend {
+    fn extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn render(&self, doc: &Document) -> Result<Vec<u8>, RenderError> {
+        Ok(crate::pdf::layout::render(doc))
+    }
+}
+
+/// 按扩展名（docx、odt、html、pdf）取得导出后端
+pub fn backend_for(extension: &str) -> Option<Box<dyn RenderBackend>> {
+    match extension.to_lowercase().as_str() {
+        "docx" => Some(Box::new(DocxBackend)),
+        "odt" => Some(Box::new(OdtBackend)),
+        "html" | "htm" => Some(Box::new(HtmlBackend)),
+        "pdf" => Some(Box::new(PdfBackend)),
+        _ => None,
+    }
+}
+
+// 段落转为 HTML，缩进单位 twips 换算为 pt（1pt = 20 twips）
+fn html_paragraph(paragraph: &Paragraph, footnotes: &mut Vec<usize>) -> String {
+    let mut style = String::new();
+    if let Some(indent) = &paragraph.indent {
+        if let Some(left) = indent.left {
+            style.push_str(&format!("margin-left:{}pt;", left as f32 / 20.0));
+        }
+        if let Some(right) = indent.right {
+            style.push_str(&format!("margin-right:{}pt;", right as f32 / 20.0));
+        }
+        if let Some(first_line) = indent.first_line {
+            style.push_str(&format!("text-indent:{}pt;", first_line as f32 / 20.0));
+        }
+    }
+    if let Some(alignment) = &paragraph.alignment {
+        let align = match alignment {
+            AlignmentType::Left => "left",
+            AlignmentType::Center => "center",
+            AlignmentType::Right => "right",
+            AlignmentType::Justify => "justify",
+        };
+        style.push_str(&format!("text-align:{};", align));
+    }
+    if paragraph.keep_next {
+        style.push_str("break-after:avoid;");
+    }
+    if paragraph.keep_lines {
+        style.push_str("break-inside:avoid;");
+    }
+    if paragraph.change_bar {
+        style.push_str("border-left:2px solid #000;padding-left:4pt;");
+    }
+    if let Some(border) = &paragraph.border {
+        style.push_str(&format!(
+            "border:1px solid #{};padding:2pt 4pt;",
+            border.trim_start_matches('#')
+        ));
+    }
+
+    // HTML 没有制表位：最后一个制表符跳到右对齐制表位时，从它开始的内容改为靠右浮动，
+    // 其余制表符按空白显示（如左侧场景编号与标题之间的制表符）
+    let mut tabs: Vec<_> = paragraph.tabs.iter().collect();
+    tabs.sort_by_key(|tab| tab.position);
+    let tab_count: usize = paragraph
+        .runs
+        .iter()
+        .map(|run| match run {
+            RunType::Text(t) => t.text.matches('\t').count(),
+            _ => 0,
+        })
+        .sum();
+    let right_from = if tab_count > 0
+        && tab_count == tabs.len()
+        && tabs
+            .last()
+            .is_some_and(|tab| tab.alignment == TabAlignment::Right)
+    {
+        paragraph
+            .runs
+            .iter()
+            .rposition(|run| matches!(run, RunType::Text(t) if t.text.starts_with('\t')))
+    } else {
+        None
+    };
+    let mut content = String::new();
+    let mut right = String::new();
+    for (i, run) in paragraph.runs.iter().enumerate() {
+        if right_from.is_some_and(|from| i >= from) {
+            html_run(run, footnotes, &mut right);
+        } else {
+            html_run(run, footnotes, &mut content);
+        }
+    }
+    if !right.is_empty() {
+        content.push_str(&format!("<span style=\"float:right\">{}</span>", right));
+    }
+    if content.is_empty() {
+        content.push_str("&nbsp;");
+    }
+    let class = paragraph
+        .style
+        .as_ref()
+        .map(|s| format!(" class=\"{}\"", escape_html(s)))
+        .unwrap_or_default();
+    let style = if style.is_empty() {
+        String::new()
+    } else {
+        format!(" style=\"{}\"", style)
+    };
+    format!("<p{}{}>{}</p>\n", class, style, content)
+}
+
+fn html_run(run: &RunType, footnotes: &mut Vec<usize>, out: &mut String) {
+    match run {
+        RunType::Text(text_run) => html_text_run(text_run, footnotes, out),
+        RunType::Break(_) => out.push_str("<br>"),
+        RunType::Hyperlink(link) => out.push_str(&format!(
+            "<a href=\"{}\">{}</a>",
+            escape_html(&link.url),
+            html_span(&link.props, &escape_html(&link.text))
+        )),
+        RunType::PageNumber(page_number) => {
+            // HTML 不分页，页码只保留文字部分
+            for child in &page_number.children {
+                if let PageNumberChild::Text(text) = child {
+                    out.push_str(&escape_html(text));
+                }
+            }
+        }
+        RunType::Image(image) => {
+            let mime = if image.data.starts_with(&[0xFF, 0xD8]) {
+                "image/jpeg"
+            } else {
+                "image/png"
+            };
+            let width = image
+                .width
+                .map(|w| format!(" width=\"{}\"", (w * 96.0) as u32))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "<img src=\"data:{};base64,{}\"{}>",
+                mime,
+                base64::encode(&image.data),
+                width
+            ));
+        }
+    }
+}
+
+fn html_text_run(run: &TextRun, footnotes: &mut Vec<usize>, out: &mut String) {
+    if let Some(comment) = &run.comment {
+        let text: Vec<String> = comment
+            .children
+            .iter()
+            .flat_map(|p| &p.runs)
+            .filter_map(|r| match r {
+                RunType::Text(t) => Some(t.text.clone()),
+                _ => None,
+            })
+            .collect();
+        out.push_str(&format!(
+            "<span class=\"comment\" title=\"{}\">&#x1F4AC;</span>",
+            escape_html(&text.concat())
+        ));
+        return;
+    }
+    if let Some(id) = run.footnote_id {
+        footnotes.push(id);
+        out.push_str(&format!("<sup><a href=\"#fn{}\">{}</a></sup>", id, id));
+        return;
+    }
+    if run.break_before || run.props.break_before == Some(true) {
+        out.push_str("<br>");
+    }
+    if !run.text.is_empty() {
+        let span = html_span(&run.props, &escape_html(&run.text));
+        match &run.url {
+            Some(url) => out.push_str(&format!("<a href=\"{}\">{}</a>", escape_html(url), span)),
+            None => out.push_str(&span),
+        }
+    }
+    if run.props.break_type.is_some() {
+        out.push_str("<br>");
+    }
+    for child in &run.children {
+        html_run(child, footnotes, out);
+    }
+}
+
+fn html_span(props: &RunProps, content: &str) -> String {
+    let mut style = String::new();
+    if props.bold == Some(true) {
+        style.push_str("font-weight:bold;");
+    }
+    if props.italic == Some(true) {
+        style.push_str("font-style:italic;");
+    }
+    match (props.underline.is_some(), props.strike == Some(true)) {
+        (true, true) => style.push_str("text-decoration:underline line-through;"),
+        (true, false) => style.push_str("text-decoration:underline;"),
+        (false, true) => style.push_str("text-decoration:line-through;"),
+        (false, false) => {}
+    }
+    if let Some(highlight) = &props.highlight {
+        style.push_str(&format!("background-color:{};", highlight));
+    } else if let Some(shading) = &props.shading {
+        style.push_str(&format!(
+            "background-color:#{};",
+            shading.trim_start_matches('#')
+        ));
+    }
+    if let Some(border) = &props.border {
+        style.push_str(&format!(
+            "border:1px solid #{};",
+            border.trim_start_matches('#')
+        ));
+    }
+    if let Some(color) = &props.color {
+        style.push_str(&format!("color:#{};", color.trim_start_matches('#')));
+    }
+    if style.is_empty() {
+        content.to_string()
+    } else {
+        format!("<span style=\"{}\">{}</span>", escape_html(&style), content)
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,169 @@
+//! 场景重排
+//!
+//! 按场景编号列表调整场景的先后顺序，用于非线性剪辑式的改稿。重排后各场景保留原来的编号：
+//! `reorder_scenes` 直接调整解析结果，可继续用于生成 DOCX、HTML；
+//! `reorder_scene_text` 调整 Fountain 源文本，并把编号以 `#n#` 写入场景标题，重新解析后编号不变。
+//! 章节、概要以及第一个场景之前的内容留在原处，只有场景在各自的位置之间交换。
+
+use crate::models::{Conf, ScriptToken};
+use crate::parser::scene_numbers::set_scene_number;
+use crate::parser::{scan_scene_numbers, FountainParser, ParseOutput};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// 重排错误
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReorderError {
+    #[error("找不到编号为 {0} 的场景")]
+    UnknownScene(String),
+
+    #[error("场景 {0} 在顺序中出现了多次")]
+    DuplicateScene(String),
+}
+
+/// 按 `order` 中的场景编号重排解析结果中的场景，`order` 中未列出的场景按原顺序排在后面。
+/// 场景标题的编号保持不变；`with_order` 为 true 时在场景标题的 metadata 中记录 "order"（重排后的序号，从 1 开始）
+pub fn reorder_scenes(
+    output: &ParseOutput,
+    order: &[String],
+    with_order: bool,
+) -> Result<ParseOutput, ReorderError> {
+    let tokens = &output.tokens;
+    let starts: Vec<usize> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| token.token_type == "scene_heading")
+        .map(|(i, _)| i)
+        .collect();
+    let numbers: Vec<String> = starts
+        .iter()
+        .enumerate()
+        .map(|(ordinal, &i)| {
+            tokens[i]
+                .number
+                .as_deref()
+                .map(|n| n.replace('↑', "").trim().to_string())
+                .unwrap_or_else(|| (ordinal + 1).to_string())
+        })
+        .collect();
+    let order = resolve_order(&numbers, order)?;
+
+    // 场景到下一个场景标题或章节为止
+    let ranges: Vec<(usize, usize)> = starts
+        .iter()
+        .map(|&start| {
+            let end = tokens[start + 1..]
+                .iter()
+                .position(|token| token.is_type(&["scene_heading", "section"]))
+                .map(|offset| start + 1 + offset)
+                .unwrap_or(tokens.len());
+            (start, end)
+        })
+        .collect();
+
+    let mut reordered: Vec<ScriptToken> = Vec::with_capacity(tokens.len());
+    let mut pos = 0;
+    for (slot, &(start, end)) in ranges.iter().enumerate() {
+        reordered.extend_from_slice(&tokens[pos..start]);
+        let (from, to) = ranges[order[slot]];
+        let mut scene = tokens[from..to].to_vec();
+        if with_order {
+            scene[0]
+                .metadata
+                .get_or_insert_with(HashMap::new)
+                .insert("order".to_string(), (slot + 1).to_string());
+        }
+        reordered.extend(scene);
+        pos = end;
+    }
+    reordered.extend_from_slice(&tokens[pos..]);
+
+    let mut result = output.clone();
+    result.tokens = reordered;
+    result.script_html = None;
+    Ok(result)
+}
+
+/// 按 `order` 中的场景编号重排 Fountain 源文本中的场景，`order` 中未列出的场景按原顺序排在后面。
+/// 所有场景标题都写入显式的 `#n#` 编号（已有的编号不变），重新解析后各场景仍是原来的编号
+pub fn reorder_scene_text(
+    script: &str,
+    config: &Conf,
+    order: &[String],
+) -> Result<String, ReorderError> {
+    let script = script.replace("\r\n", "\n");
+    let entries = scan_scene_numbers(&script, config);
+    let numbers: Vec<String> = entries.iter().map(|e| e.number.clone()).collect();
+    let order = resolve_order(&numbers, order)?;
+
+    let mut lines: Vec<String> = script.split('\n').map(|l| l.to_string()).collect();
+    for entry in entries.iter().filter(|e| e.explicit.is_none()) {
+        if let Some(line) = lines.get_mut(entry.line) {
+            *line = set_scene_number(line, entry.var.as_deref(), &entry.number);
+        }
+    }
+
+    // 场景到下一个场景标题或章节为止，与 extract_scenes 的划分一致
+    let parser = FountainParser::new();
+    let parsed = parser.parse(&script, config, false, Some(false));
+    let mut boundaries: Vec<usize> = parsed
+        .tokens
+        .iter()
+        .filter(|token| token.is_type(&["scene_heading", "section"]))
+        .map(|token| token.line)
+        .collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    let ranges: Vec<(usize, usize)> = entries
+        .iter()
+        .map(|entry| {
+            let end = boundaries
+                .iter()
+                .copied()
+                .find(|&b| b > entry.line)
+                .unwrap_or(lines.len());
+            (entry.line, end)
+        })
+        .collect();
+
+    let mut result: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut pos = 0;
+    for (slot, &(start, end)) in ranges.iter().enumerate() {
+        result.extend(lines[pos..start].iter().map(|l| l.as_str()));
+        // 去掉场景末尾的空行，再统一补一个空行与下一段隔开
+        let (from, to) = ranges[order[slot]];
+        let mut scene: Vec<&str> = lines[from..to].iter().map(|l| l.as_str()).collect();
+        while scene.last().is_some_and(|l| l.trim().is_empty()) {
+            scene.pop();
+        }
+        result.extend(scene);
+        result.push("");
+        pos = end;
+    }
+    result.extend(lines[pos..].iter().map(|l| l.as_str()));
+
+    let mut text = result.join("\n").trim_end().to_string();
+    text.push('\n');
+    Ok(text)
+}
+
+// 把编号列表转换为场景下标：列出的场景在前，其余按原顺序在后
+fn resolve_order(numbers: &[String], order: &[String]) -> Result<Vec<usize>, ReorderError> {
+    let mut indices: Vec<usize> = Vec::with_capacity(numbers.len());
+    for number in order {
+        let number = number.trim();
+        let index = numbers
+            .iter()
+            .position(|n| n == number)
+            .ok_or_else(|| ReorderError::UnknownScene(number.to_string()))?;
+        if indices.contains(&index) {
+            return Err(ReorderError::DuplicateScene(number.to_string()));
+        }
+        indices.push(index);
+    }
+    let rest: Vec<usize> = (0..numbers.len())
+        .filter(|i| !indices.contains(i))
+        .collect();
+    indices.extend(rest);
+    Ok(indices)
+}
@@ -0,0 +1,58 @@
+//! 试镜台词本（sides）
+//!
+//! 只保留指定角色出场（有对白）的场景，供 DOCX 导出时突出这些角色的对白、淡化其他对白。
+
+use crate::parser::ParseOutput;
+use crate::utils::strip_style_chars;
+
+/// 角色名是否在选定的角色列表中，忽略大小写和首尾空白
+pub fn is_sides_character(name: &str, characters: &[String]) -> bool {
+    let name = strip_style_chars(name);
+    let name = name.trim();
+    characters
+        .iter()
+        .any(|c| c.trim().to_uppercase() == name.to_uppercase())
+}
+
+/// 只保留选定角色出场的场景
+///
+/// 每个场景从场景标题开始，到下一个场景标题或章节为止；场景之外的内容（第一个场景之前的动作、
+/// 章节、概要等）都会被去掉。选定的角色可以用标题页 metadata 中声明的别名。
+pub fn keep_character_scenes(output: &mut ParseOutput, characters: &[String]) {
+    let characters: Vec<String> = characters
+        .iter()
+        .map(|name| output.properties.canonical_character(name))
+        .collect();
+    let mut kept = Vec::new();
+    let mut scene = Vec::new();
+    let mut in_scene = false;
+    let mut appears = false;
+
+    for token in output.tokens.drain(..) {
+        let scene_start = token.token_type == "scene_heading";
+        if scene_start || token.token_type == "section" {
+            if appears {
+                kept.append(&mut scene);
+            }
+            scene.clear();
+            in_scene = scene_start;
+            appears = false;
+        }
+        if !in_scene {
+            continue;
+        }
+        if token.token_type == "character"
+            && token
+                .character
+                .as_deref()
+                .is_some_and(|name| is_sides_character(name, &characters))
+        {
+            appears = true;
+        }
+        scene.push(token);
+    }
+    if appears {
+        kept.append(&mut scene);
+    }
+    output.tokens = kept;
+}
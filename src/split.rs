@@ -0,0 +1,142 @@
+//! 分集/分幕导出
+//!
+//! 按顶层章节（幕、集）或每 N 个场景把剧本拆成几部分，分别导出。各部分保留全剧的标题页，
+//! 页眉页脚中的 {title}、{author} 等保持一致；场景编号沿用全剧的编号，
+//! `ScriptPart::first_page` 给出本部分在全剧正文中的起始页，导出时页码从这里接续。
+
+use crate::models::ScriptToken;
+use crate::parser::ParseOutput;
+use crate::utils::strip_style_chars;
+
+/// 拆分方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// 每个顶层章节（剧本中层级最高的章节）一部分
+    Section,
+    /// 每 N 个场景一部分
+    Scenes(usize),
+}
+
+impl SplitBy {
+    /// "section" 按顶层章节拆分，正整数 N 按每 N 个场景拆分，其它返回 None
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("section") {
+            return Some(SplitBy::Section);
+        }
+        name.parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .map(SplitBy::Scenes)
+    }
+}
+
+/// 拆分出的一部分
+#[derive(Debug, Clone)]
+pub struct ScriptPart {
+    /// 名称：按章节拆分时为章节标题，按场景拆分时为 "场景 1-10"
+    pub title: String,
+    /// 本部分第一页是全剧正文的第几页（从 1 开始）
+    pub first_page: usize,
+    /// 只含本部分内容的解析结果，标题页与全剧相同；不含 `script_html`，需要时用 `generate_html` 生成
+    pub output: ParseOutput,
+}
+
+/// 拆分剧本，第一个拆分点之前的内容（如开场的动作）归入第一部分；没有可拆分的章节或场景时整部剧本为一部分
+pub fn split_script(output: &ParseOutput, by: SplitBy) -> Vec<ScriptPart> {
+    let mut starts = split_points(&output.tokens, by);
+    if starts.is_empty() {
+        starts.push(0);
+    }
+
+    let mut parts = Vec::new();
+    let mut first_page = 1;
+    let mut scene_ordinal = 0;
+    for (i, &start) in starts.iter().enumerate() {
+        let from = if i == 0 { 0 } else { start };
+        let to = starts.get(i + 1).copied().unwrap_or(output.tokens.len());
+        let tokens = trim_page_breaks(&output.tokens[from..to]).to_vec();
+
+        let scenes: Vec<String> = tokens
+            .iter()
+            .filter(|token| token.token_type == "scene_heading")
+            .map(|token| {
+                scene_ordinal += 1;
+                token
+                    .number
+                    .clone()
+                    .unwrap_or_else(|| scene_ordinal.to_string())
+            })
+            .collect();
+        let title = match by {
+            SplitBy::Section => output
+                .tokens
+                .get(start)
+                .filter(|token| token.token_type == "section")
+                .map(|token| strip_style_chars(&token.text).trim().to_string())
+                .unwrap_or_default(),
+            SplitBy::Scenes(_) => match (scenes.first(), scenes.last()) {
+                (Some(first), Some(last)) if first != last => format!("场景 {}-{}", first, last),
+                (Some(first), _) => format!("场景 {}", first),
+                _ => String::new(),
+            },
+        };
+
+        let pages = tokens
+            .iter()
+            .filter(|token| token.token_type == "page_break")
+            .count()
+            + 1;
+        let mut part_output = output.clone();
+        part_output.tokens = tokens;
+        part_output.script_html = None;
+        parts.push(ScriptPart {
+            title,
+            first_page,
+            output: part_output,
+        });
+        first_page += pages;
+    }
+    parts
+}
+
+// 各部分开始处的 token 下标
+fn split_points(tokens: &[ScriptToken], by: SplitBy) -> Vec<usize> {
+    match by {
+        SplitBy::Section => {
+            let Some(top_level) = tokens
+                .iter()
+                .filter(|token| token.token_type == "section")
+                .map(|token| token.level.unwrap_or(1))
+                .min()
+            else {
+                return Vec::new();
+            };
+            tokens
+                .iter()
+                .enumerate()
+                .filter(|(_, token)| {
+                    token.token_type == "section" && token.level.unwrap_or(1) == top_level
+                })
+                .map(|(i, _)| i)
+                .collect()
+        }
+        SplitBy::Scenes(count) => tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| token.token_type == "scene_heading")
+            .map(|(i, _)| i)
+            .step_by(count.max(1))
+            .collect(),
+    }
+}
+
+// 去掉首尾的分页符，各部分都从新的一页开始，也不以空白页结束
+fn trim_page_breaks(tokens: &[ScriptToken]) -> &[ScriptToken] {
+    let is_content = |token: &ScriptToken| token.token_type != "page_break";
+    let Some(first) = tokens.iter().position(is_content) else {
+        return &[];
+    };
+    let last = tokens.iter().rposition(is_content).unwrap_or(first);
+    &tokens[first..=last]
+}
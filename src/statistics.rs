@@ -1,3 +1,4 @@
+use crate::duration::DurationModel;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
@@ -182,18 +183,10 @@ pub fn calculate_statistics(
     properties: &crate::models::ScreenplayProperties,
     length_dialogue: f64,
     length_action: f64,
-    dial_sec_per_char: f64,
-    dial_sec_per_punc_short: f64,
-    dial_sec_per_punc_long: f64,
+    duration_model: &dyn DurationModel,
 ) -> Statistics {
     Statistics {
-        character_stats: calculate_character_statistics(
-            tokens,
-            properties,
-            dial_sec_per_char,
-            dial_sec_per_punc_short,
-            dial_sec_per_punc_long,
-        ),
+        character_stats: calculate_character_statistics(tokens, properties, duration_model),
         location_stats: calculate_location_statistics(properties),
         scene_stats: calculate_scene_statistics(properties),
         duration_stats: calculate_duration_statistics(
@@ -209,9 +202,7 @@ pub fn calculate_statistics(
 fn calculate_character_statistics(
     tokens: &[crate::models::ScriptToken],
     properties: &crate::models::ScreenplayProperties,
-    dial_sec_per_char: f64,
-    dial_sec_per_punc_short: f64,
-    dial_sec_per_punc_long: f64,
+    duration_model: &dyn DurationModel,
 ) -> CharacterStatistics {
     let mut dialogue_per_character: HashMap<String, Vec<String>> = HashMap::new();
     // 记录每个角色的动作时长
@@ -298,12 +289,7 @@ fn calculate_character_statistics(
         let mut all_dialogue_combined = String::new();
 
         for speech in speeches {
-            let time = calculate_dialogue_duration(
-                speech,
-                dial_sec_per_char,
-                dial_sec_per_punc_short,
-                dial_sec_per_punc_long,
-            );
+            let time = duration_model.dialogue_duration(speech, Some(character_name));
             seconds_spoken += time;
             all_dialogue_combined.push_str(speech);
             all_dialogue_combined.push(' ');
@@ -701,35 +687,6 @@ fn calculate_duration_statistics(
     }
 }
 
-/// 计算对话时长
-fn calculate_dialogue_duration(
-    dialogue: &str,
-    dial_sec_per_char: f64,
-    dial_sec_per_punc_short: f64,
-    dial_sec_per_punc_long: f64,
-) -> f64 {
-    use regex::Regex;
-
-    let mut duration = 0.0;
-
-    let sanitized = Regex::new(r"\s|\p{P}|\p{S}")
-        .unwrap()
-        .replace_all(dialogue, "");
-    duration += sanitized.chars().count() as f64 * dial_sec_per_char;
-
-    let rec = Regex::new(r"(\.|\?|\!|\:|。|？|！|：)|(\,|，|;|；|、)").unwrap();
-    for cap in rec.captures_iter(dialogue) {
-        if cap.get(1).is_some() {
-            duration += dial_sec_per_punc_long;
-        }
-        if cap.get(2).is_some() {
-            duration += dial_sec_per_punc_short;
-        }
-    }
-
-    duration
-}
-
 /// 统计单词数
 fn count_words(text: &str) -> usize {
     text.split_whitespace().count()
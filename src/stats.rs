@@ -0,0 +1,899 @@
+//! 剧本概要统计
+//!
+//! 与 `statistics` 模块的图表数据不同，这里只给出一组汇总数字（总时长、对白/动作占比、
+//! 内外景场景数、平均场景时长、各地点合计），可直接序列化为 JSON 供看板使用。
+//! 另外提供逐场时长报表，镜头交切的场景合并为一个单元，可导出为 CSV；
+//! 以及地点附录（各地点的内/外景、时间、场景编号和总时长），可导出为 CSV 或附在 DOCX 之后；
+//! 多级地点（如 "HOUSE - KITCHEN"）在附录中排在主地点之后，也可按主地点、子地点组织为地点树。
+//! 对白平衡报告按标题页元数据中给角色打的标签（如性别）分组统计对白字数，并列出同一标签的
+//! 两个角色互相对话的场景（类似 Bechdel 测试），可序列化为 JSON。
+//! 时间规范化报告按对照表把场景标题中的时间（日、夜、DAY、NIGHT 等）归为 DAY/NIGHT/DUSK/DAWN，
+//! 统计各类场景数，并列出对照表之外的写法。
+
+use crate::models::StructToken;
+use crate::parser::ParseOutput;
+use crate::utils::strip_style_chars;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// 剧本概要统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptStats {
+    /// 预估总时长（秒），即对白时长与动作时长之和
+    pub runtime_sec: f64,
+    /// 对白时长（秒）
+    pub dialogue_sec: f64,
+    /// 动作时长（秒）
+    pub action_sec: f64,
+    /// 对白时长占总时长的比例（0~1）
+    pub dialogue_ratio: f64,
+    /// 场景总数
+    pub scene_count: usize,
+    /// 按内/外景分类的场景数
+    pub scenes_by_type: SceneTypeCounts,
+    /// 平均场景时长（秒）
+    pub average_scene_sec: f64,
+    /// 各地点合计，按时长从长到短排列
+    pub locations: Vec<LocationTotal>,
+}
+
+/// 按内/外景分类的场景数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SceneTypeCounts {
+    /// 内景
+    pub int: usize,
+    /// 外景
+    pub ext: usize,
+    /// 内外景
+    pub int_ext: usize,
+    /// 场景标题中没有内/外景标记
+    pub unknown: usize,
+}
+
+/// 单个地点的合计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationTotal {
+    /// 地点名称（大写、规范化后的名称）
+    pub name: String,
+    /// 在该地点发生的场景数
+    pub scene_count: usize,
+    /// 在该地点的总时长（秒）
+    pub duration_sec: f64,
+}
+
+impl ScriptStats {
+    /// 从解析结果计算统计
+    pub fn from_output(output: &ParseOutput) -> Self {
+        let dialogue_sec = output.length_dialogue;
+        let action_sec = output.length_action;
+        let runtime_sec = dialogue_sec + action_sec;
+
+        let scene_durations = scene_durations_by_line(output);
+
+        let mut scenes_by_type = SceneTypeCounts::default();
+        let mut typed_lines = 0;
+        let mut locations = Vec::new();
+        for (name, references) in &output.properties.locations {
+            for reference in references {
+                typed_lines += 1;
+                match (reference.interior, reference.exterior) {
+                    (true, true) => scenes_by_type.int_ext += 1,
+                    (true, false) => scenes_by_type.int += 1,
+                    (false, true) => scenes_by_type.ext += 1,
+                    (false, false) => scenes_by_type.unknown += 1,
+                }
+            }
+            locations.push(LocationTotal {
+                name: name.clone(),
+                scene_count: references.len(),
+                duration_sec: references
+                    .iter()
+                    .filter_map(|r| scene_durations.get(&r.line))
+                    .sum(),
+            });
+        }
+        locations.sort_by(|a, b| {
+            b.duration_sec
+                .total_cmp(&a.duration_sec)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let scene_count = output.properties.scenes.len();
+        // 无法解析出地点的场景标题也算作未标记内/外景
+        scenes_by_type.unknown += scene_count.saturating_sub(typed_lines);
+
+        ScriptStats {
+            runtime_sec,
+            dialogue_sec,
+            action_sec,
+            dialogue_ratio: if runtime_sec > 0.0 {
+                dialogue_sec / runtime_sec
+            } else {
+                0.0
+            },
+            scene_count,
+            scenes_by_type,
+            average_scene_sec: if scene_count > 0 {
+                scene_durations.values().sum::<f64>() / scene_count as f64
+            } else {
+                0.0
+            },
+            locations,
+        }
+    }
+
+    /// 输出 JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// 逐场时长报表中的一行：单个场景，或一组镜头交切的场景
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneDuration {
+    /// 场景编号，交切组按出现顺序列出组内所有场景
+    pub numbers: Vec<String>,
+    /// 场景标题
+    pub titles: Vec<String>,
+    /// 第一个场景标题的行号（从 0 开始）
+    pub line: usize,
+    /// 在影片中的开始时间（秒）
+    pub start_sec: f64,
+    /// 预估时长（秒），交切组为组内场景之和
+    pub duration_sec: f64,
+    /// 交切类型：`+`、`#` 或 `=`，单个场景为 None
+    pub shot_cut: Option<String>,
+}
+
+/// 逐场时长报表
+///
+/// 交切段落中的时长在解析时已平均分摊到参与交切的场景，这里再把同一组的场景合并成一行，
+/// 一个场景属于多个交切组时归入最先出现的一组。
+pub fn scene_durations(output: &ParseOutput) -> Vec<SceneDuration> {
+    let mut scenes = Vec::new();
+    collect_scenes(&output.properties.structure, &mut scenes);
+    scenes.sort_by_key(|s| s.range.as_ref().map(|r| r.start.line).unwrap_or(0));
+
+    let numbers: HashMap<usize, String> = output
+        .properties
+        .scenes
+        .iter()
+        .filter_map(|scene| {
+            let line = scene.get("line")?.as_u64()? as usize;
+            let number = scene.get("number")?.as_str()?.to_string();
+            Some((line, number))
+        })
+        .collect();
+
+    // 场景 id -> (交切组序号, 交切类型)
+    let mut groups: HashMap<String, (usize, String)> = HashMap::new();
+    for (idx, shot_cut) in output.properties.shot_cuts.iter().enumerate() {
+        let kind = shot_cut
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let ids = shot_cut.get("scene_ids").and_then(|v| v.as_array());
+        for id in ids.into_iter().flatten().filter_map(|v| v.as_str()) {
+            groups
+                .entry(id.to_string())
+                .or_insert_with(|| (idx, kind.clone()));
+        }
+    }
+
+    let mut rows: Vec<SceneDuration> = Vec::new();
+    let mut group_rows: HashMap<usize, usize> = HashMap::new();
+    for scene in scenes {
+        let line = scene.range.as_ref().map(|r| r.start.line).unwrap_or(0);
+        let number = numbers.get(&line).cloned().unwrap_or_default();
+        let title = strip_style_chars(&scene.text)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let group = scene.id.as_ref().and_then(|id| groups.get(id));
+
+        if let Some(&row_idx) = group.and_then(|(idx, _)| group_rows.get(idx)) {
+            let row = &mut rows[row_idx];
+            row.numbers.push(number);
+            row.titles.push(title);
+            row.duration_sec += scene.duration_sec;
+            continue;
+        }
+        if let Some((idx, _)) = group {
+            group_rows.insert(*idx, rows.len());
+        }
+        rows.push(SceneDuration {
+            numbers: vec![number],
+            titles: vec![title],
+            line,
+            start_sec: scene.play_sec,
+            duration_sec: scene.duration_sec,
+            shot_cut: group.map(|(_, kind)| kind.clone()),
+        });
+    }
+    rows
+}
+
+/// 把逐场时长报表输出为 CSV
+pub fn scene_durations_csv(rows: &[SceneDuration]) -> String {
+    let mut csv = String::from("number,title,line,start_sec,duration_sec,shot_cut\n");
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{:.1},{:.1},{}\n",
+            csv_field(&row.numbers.join("/")),
+            csv_field(&row.titles.join(" / ")),
+            row.line,
+            row.start_sec,
+            row.duration_sec,
+            row.shot_cut.as_deref().unwrap_or("")
+        ));
+    }
+    csv
+}
+
+/// 地点附录页的标题
+pub const LOCATIONS_TITLE: &str = "LOCATIONS";
+
+/// 地点附录中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationSummary {
+    /// 地点名称（大写、规范化后的名称）
+    pub name: String,
+    /// 主地点，子地点（如 "HOUSE - KITCHEN"）与其主地点（"HOUSE"）相同
+    pub master: String,
+    /// "INT"、"EXT"、"INT/EXT"，场景标题中没有内/外景标记时为空
+    pub int_ext: String,
+    /// 出现过的时间（如 "日"、"NIGHT"），按首次出现的顺序
+    pub times_of_day: Vec<String>,
+    /// 在该地点发生的场景编号
+    pub scene_numbers: Vec<String>,
+    /// 在该地点的总时长（秒）
+    pub duration_sec: f64,
+}
+
+impl LocationSummary {
+    /// 地点附录中的一行文本，如 "客厅 | INT | 日 / 夜 | SC. 1, 3 | 02:05"
+    pub fn display(&self) -> String {
+        let seconds = self.duration_sec.round() as u64;
+        [
+            self.name.clone(),
+            self.int_ext.clone(),
+            self.times_of_day.join(" / "),
+            format!("SC. {}", self.scene_numbers.join(", ")),
+            format!("{:02}:{:02}", seconds / 60, seconds % 60),
+        ]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" | ")
+    }
+}
+
+/// 地点附录：每个地点的内/外景、时间、场景编号和总时长，按首次出现的顺序排列，
+/// 子地点紧跟在其主地点之后
+pub fn location_summaries(output: &ParseOutput) -> Vec<LocationSummary> {
+    let durations = scene_durations_by_line(output);
+    let mut rows: Vec<(usize, bool, LocationSummary)> = output
+        .properties
+        .locations
+        .iter()
+        .map(|(name, references)| {
+            let mut references: Vec<_> = references.iter().collect();
+            references.sort_by_key(|r| r.line);
+            let interior = references.iter().any(|r| r.interior);
+            let exterior = references.iter().any(|r| r.exterior);
+            let mut times_of_day: Vec<String> = Vec::new();
+            for reference in &references {
+                let time = reference.time_of_day.trim().to_uppercase();
+                if !time.is_empty() && !times_of_day.contains(&time) {
+                    times_of_day.push(time);
+                }
+            }
+            let master = references
+                .first()
+                .map(|r| r.path().remove(0))
+                .unwrap_or_else(|| name.clone());
+            let summary = LocationSummary {
+                name: name.clone(),
+                master,
+                int_ext: match (interior, exterior) {
+                    (true, true) => "INT/EXT",
+                    (true, false) => "INT",
+                    (false, true) => "EXT",
+                    (false, false) => "",
+                }
+                .to_string(),
+                times_of_day,
+                scene_numbers: references
+                    .iter()
+                    .map(|r| r.scene_number.trim_start_matches('↑').to_string())
+                    .collect(),
+                duration_sec: references
+                    .iter()
+                    .filter_map(|r| durations.get(&r.line))
+                    .sum(),
+            };
+            (
+                references.first().map(|r| r.line).unwrap_or(usize::MAX),
+                references.iter().any(|r| !r.sub_locations.is_empty()),
+                summary,
+            )
+        })
+        .collect();
+    // 主地点按其下所有地点中最早出现的行排序
+    let mut master_lines: HashMap<String, usize> = HashMap::new();
+    for (line, _, summary) in &rows {
+        let first = master_lines.entry(summary.master.clone()).or_insert(*line);
+        *first = (*first).min(*line);
+    }
+    rows.sort_by(|a, b| {
+        master_lines[&a.2.master]
+            .cmp(&master_lines[&b.2.master])
+            .then_with(|| a.2.master.cmp(&b.2.master))
+            .then_with(|| a.1.cmp(&b.1))
+            .then_with(|| a.0.cmp(&b.0))
+            .then_with(|| a.2.name.cmp(&b.2.name))
+    });
+    rows.into_iter().map(|(_, _, summary)| summary).collect()
+}
+
+/// 地点树中的一个地点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationNode {
+    /// 本级地点名称，如 "KITCHEN"
+    pub name: String,
+    /// 从主地点开始的完整名称，如 "HOUSE - KITCHEN"
+    pub path: String,
+    /// 直接发生在该地点（不含子地点）的场景编号
+    pub scene_numbers: Vec<String>,
+    /// 场景数，包含子地点
+    pub scene_count: usize,
+    /// 总时长（秒），包含子地点
+    pub duration_sec: f64,
+    /// 子地点，按首次出现的顺序排列
+    pub children: Vec<LocationNode>,
+}
+
+/// 按主地点、子地点组织的地点树，主地点按首次出现的顺序排列
+pub fn location_tree(output: &ParseOutput) -> Vec<LocationNode> {
+    let durations = scene_durations_by_line(output);
+    let mut references: Vec<_> = output.properties.locations.values().flatten().collect();
+    references.sort_by_key(|r| r.line);
+
+    let mut tree: Vec<LocationNode> = Vec::new();
+    for reference in references {
+        let path = reference.path();
+        let duration = durations.get(&reference.line).copied().unwrap_or(0.0);
+        let mut nodes = &mut tree;
+        for (depth, name) in path.iter().enumerate() {
+            let index = match nodes.iter().position(|n| n.name == *name) {
+                Some(index) => index,
+                None => {
+                    nodes.push(LocationNode {
+                        name: name.clone(),
+                        path: path[..=depth].join(" - "),
+                        scene_numbers: Vec::new(),
+                        scene_count: 0,
+                        duration_sec: 0.0,
+                        children: Vec::new(),
+                    });
+                    nodes.len() - 1
+                }
+            };
+            let node = &mut nodes[index];
+            node.scene_count += 1;
+            node.duration_sec += duration;
+            if depth + 1 == path.len() {
+                node.scene_numbers
+                    .push(reference.scene_number.trim_start_matches('↑').to_string());
+            }
+            nodes = &mut node.children;
+        }
+    }
+    tree
+}
+
+/// 按时间（日/夜等）统计场景数，按场景数从多到少排列，场景标题中没有时间的不计
+pub fn scenes_by_time_of_day(output: &ParseOutput) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    let mut references: Vec<_> = output.properties.locations.values().flatten().collect();
+    references.sort_by_key(|r| r.line);
+    for reference in references {
+        let time = reference.time_of_day.trim().to_uppercase();
+        if time.is_empty() {
+            continue;
+        }
+        match counts.iter_mut().find(|(t, _)| *t == time) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((time, 1)),
+        }
+    }
+    // 稳定排序，场景数相同时保持首次出现的顺序
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts
+}
+
+/// 标准时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TimeOfDay {
+    Day,
+    Night,
+    Dusk,
+    Dawn,
+}
+
+impl TimeOfDay {
+    /// 全部标准时间，按报告中的顺序排列
+    pub const ALL: [TimeOfDay; 4] = [
+        TimeOfDay::Day,
+        TimeOfDay::Night,
+        TimeOfDay::Dusk,
+        TimeOfDay::Dawn,
+    ];
+
+    /// 标准写法
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeOfDay::Day => "DAY",
+            TimeOfDay::Night => "NIGHT",
+            TimeOfDay::Dusk => "DUSK",
+            TimeOfDay::Dawn => "DAWN",
+        }
+    }
+}
+
+/// 时间规范化对照表：场景标题中的写法（大写）及其对应的标准时间
+pub const TIME_OF_DAY_TABLE: &[(&str, TimeOfDay)] = &[
+    ("DAY", TimeOfDay::Day),
+    ("MORNING", TimeOfDay::Day),
+    ("NOON", TimeOfDay::Day),
+    ("AFTERNOON", TimeOfDay::Day),
+    ("日", TimeOfDay::Day),
+    ("白天", TimeOfDay::Day),
+    ("早上", TimeOfDay::Day),
+    ("早晨", TimeOfDay::Day),
+    ("上午", TimeOfDay::Day),
+    ("中午", TimeOfDay::Day),
+    ("正午", TimeOfDay::Day),
+    ("午后", TimeOfDay::Day),
+    ("下午", TimeOfDay::Day),
+    ("NIGHT", TimeOfDay::Night),
+    ("MIDNIGHT", TimeOfDay::Night),
+    ("夜", TimeOfDay::Night),
+    ("夜晚", TimeOfDay::Night),
+    ("晚上", TimeOfDay::Night),
+    ("深夜", TimeOfDay::Night),
+    ("午夜", TimeOfDay::Night),
+    ("凌晨", TimeOfDay::Night),
+    ("DUSK", TimeOfDay::Dusk),
+    ("SUNSET", TimeOfDay::Dusk),
+    ("EVENING", TimeOfDay::Dusk),
+    ("黄昏", TimeOfDay::Dusk),
+    ("傍晚", TimeOfDay::Dusk),
+    ("日落", TimeOfDay::Dusk),
+    ("DAWN", TimeOfDay::Dawn),
+    ("SUNRISE", TimeOfDay::Dawn),
+    ("黎明", TimeOfDay::Dawn),
+    ("拂晓", TimeOfDay::Dawn),
+    ("清晨", TimeOfDay::Dawn),
+    ("日出", TimeOfDay::Dawn),
+];
+
+/// 按对照表把场景标题中的时间规范化为标准时间，不在表中的写法返回 `None`。
+/// 比较前去掉首尾空白和末尾的句点，并合并连续的空白，不区分大小写
+pub fn normalize_time_of_day(value: &str) -> Option<TimeOfDay> {
+    let value = value
+        .trim()
+        .trim_end_matches('.')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase();
+    TIME_OF_DAY_TABLE
+        .iter()
+        .find(|(written, _)| *written == value)
+        .map(|(_, time)| *time)
+}
+
+/// 时间规范化报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeOfDayReport {
+    /// 各标准时间的场景数，按 DAY、NIGHT、DUSK、DAWN 的顺序，没有场景的也列出
+    pub counts: Vec<(TimeOfDay, usize)>,
+    /// 场景标题中没有时间的场景数
+    pub unspecified: usize,
+    /// 不在对照表中的写法，按首次出现的顺序排列
+    pub nonstandard: Vec<NonstandardTimeOfDay>,
+}
+
+/// 不在对照表中的时间写法
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NonstandardTimeOfDay {
+    /// 场景标题中的写法（大写）
+    pub value: String,
+    /// 使用该写法的场景编号
+    pub scene_numbers: Vec<String>,
+    /// 使用该写法的场景标题所在行
+    pub lines: Vec<usize>,
+}
+
+impl TimeOfDayReport {
+    /// 某个标准时间的场景数
+    pub fn count(&self, time: TimeOfDay) -> usize {
+        self.counts
+            .iter()
+            .find(|(t, _)| *t == time)
+            .map_or(0, |(_, count)| *count)
+    }
+}
+
+/// 按对照表统计各标准时间的场景数，并列出不在对照表中的写法，
+/// 制片可据此统一场景标题的写法后再统计日/夜戏
+pub fn time_of_day_report(output: &ParseOutput) -> TimeOfDayReport {
+    let mut report = TimeOfDayReport {
+        counts: TimeOfDay::ALL.iter().map(|time| (*time, 0)).collect(),
+        ..Default::default()
+    };
+    let mut references: Vec<_> = output.properties.locations.values().flatten().collect();
+    references.sort_by_key(|r| r.line);
+    for reference in &references {
+        let value = reference.time_of_day.trim().to_uppercase();
+        if value.is_empty() {
+            report.unspecified += 1;
+            continue;
+        }
+        if let Some(time) = normalize_time_of_day(&value) {
+            if let Some((_, count)) = report.counts.iter_mut().find(|(t, _)| *t == time) {
+                *count += 1;
+            }
+            continue;
+        }
+        let scene_number = reference.scene_number.trim_start_matches('↑').to_string();
+        match report.nonstandard.iter_mut().find(|n| n.value == value) {
+            Some(entry) => {
+                entry.scene_numbers.push(scene_number);
+                entry.lines.push(reference.line);
+            }
+            None => report.nonstandard.push(NonstandardTimeOfDay {
+                value,
+                scene_numbers: vec![scene_number],
+                lines: vec![reference.line],
+            }),
+        }
+    }
+    // 无法解析出地点的场景标题也没有时间
+    report.unspecified += output
+        .properties
+        .scenes
+        .len()
+        .saturating_sub(references.len());
+    report
+}
+
+/// 把地点附录输出为 CSV
+pub fn location_summaries_csv(rows: &[LocationSummary]) -> String {
+    let mut csv = String::from("name,int_ext,times_of_day,scenes,duration_sec\n");
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{:.1}\n",
+            csv_field(&row.name),
+            row.int_ext,
+            csv_field(&row.times_of_day.join("/")),
+            csv_field(&row.scene_numbers.join(" ")),
+            row.duration_sec
+        ));
+    }
+    csv
+}
+
+/// 各镜头交切组中参与交切的地点，键为交切开始标记所在行（从 0 开始），
+/// 地点按场景出现的顺序排列、不重复
+pub fn shot_cut_locations(output: &ParseOutput) -> HashMap<usize, Vec<String>> {
+    let line_locations = scene_locations_by_line(output);
+    shot_cut_scene_lines(output)
+        .into_iter()
+        .map(|(line, scene_lines)| {
+            let mut locations: Vec<String> = Vec::new();
+            for name in scene_lines.iter().filter_map(|l| line_locations.get(l)) {
+                if !locations.iter().any(|location| location == name) {
+                    locations.push(name.to_string());
+                }
+            }
+            (line, locations)
+        })
+        .collect()
+}
+
+/// 参与镜头交切的场景所在的地点，键为场景标题所在行（从 0 开始）
+pub fn intercut_scene_locations(output: &ParseOutput) -> HashMap<usize, String> {
+    let line_locations = scene_locations_by_line(output);
+    shot_cut_scene_lines(output)
+        .into_iter()
+        .flat_map(|(_, scene_lines)| scene_lines)
+        .filter_map(|line| Some((line, line_locations.get(&line)?.to_string())))
+        .collect()
+}
+
+/// 对白平衡报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DialogueBalance {
+    /// 用于分组的标签名，如 "gender"
+    pub tag: String,
+    /// 各角色的对白，按字数从多到少排列
+    pub characters: Vec<CharacterDialogue>,
+    /// 按标签值分组的合计，按字数从多到少排列；没有该标签的角色归入 value 为 None 的一组
+    pub groups: Vec<DialogueGroup>,
+    /// 同一标签值的两个角色互相对话的场景，按行号排列
+    pub conversations: Vec<TaggedConversation>,
+}
+
+/// 单个角色的对白
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterDialogue {
+    pub name: String,
+    /// 该角色的标签值
+    pub value: Option<String>,
+    /// 对白段数
+    pub speeches: usize,
+    /// 对白词数（中日文每个字算一个词）
+    pub words: usize,
+}
+
+/// 同一标签值的角色合计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueGroup {
+    pub value: Option<String>,
+    /// 按出场顺序排列
+    pub characters: Vec<String>,
+    pub speeches: usize,
+    pub words: usize,
+    /// 占全部对白词数的比例（0~1）
+    pub share: f64,
+}
+
+/// 同一标签值的角色之间的一段对话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaggedConversation {
+    pub value: String,
+    /// 场景编号，第一个场景之前的对话为空字符串
+    pub scene_number: String,
+    /// 这段对话中第一段对白的角色名所在行
+    pub line: usize,
+    /// 参与对话的角色，按说话顺序排列
+    pub characters: Vec<String>,
+}
+
+impl DialogueBalance {
+    /// 输出 JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// 按角色标签统计对白平衡
+///
+/// 标签取自标题页元数据中的 `characters`，如
+/// `Metadata: {"characters": {"阿珍": {"gender": "female"}}}`，角色名不区分大小写。
+/// 同一场戏中前后相接的两段对白出自标签值相同的两个不同角色时，记为一次该标签值内部的对话，
+/// 同一场戏中同一标签值的对话合并为一条。
+pub fn dialogue_balance(output: &ParseOutput, tag: &str) -> DialogueBalance {
+    let tags = character_tags(output, tag);
+    let value_of = |name: &str| tags.get(&name.trim().to_uppercase()).cloned();
+
+    // 每段对白：(场景编号, 角色名所在行, 角色, 词数)
+    let mut speeches: Vec<(String, usize, String, usize)> = Vec::new();
+    let mut scene_number = String::new();
+    for token in &output.tokens {
+        match token.token_type.as_str() {
+            "scene_heading" => {
+                scene_number = token
+                    .number
+                    .as_deref()
+                    .unwrap_or_default()
+                    .trim_start_matches('↑')
+                    .to_string();
+            }
+            "character" => {
+                if let Some(name) = &token.character {
+                    speeches.push((scene_number.clone(), token.line, name.clone(), 0));
+                }
+            }
+            "dialogue" => {
+                if let Some(speech) = speeches.last_mut() {
+                    speech.3 += strip_style_chars(&token.text).unicode_words().count();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut characters: Vec<CharacterDialogue> = Vec::new();
+    for (_, _, name, words) in &speeches {
+        let index = match characters.iter().position(|c| c.name == *name) {
+            Some(index) => index,
+            None => {
+                characters.push(CharacterDialogue {
+                    name: name.clone(),
+                    value: value_of(name),
+                    speeches: 0,
+                    words: 0,
+                });
+                characters.len() - 1
+            }
+        };
+        characters[index].speeches += 1;
+        characters[index].words += words;
+    }
+
+    let total_words: usize = characters.iter().map(|c| c.words).sum();
+    let mut groups: Vec<DialogueGroup> = Vec::new();
+    for character in &characters {
+        let index = match groups.iter().position(|g| g.value == character.value) {
+            Some(index) => index,
+            None => {
+                groups.push(DialogueGroup {
+                    value: character.value.clone(),
+                    characters: Vec::new(),
+                    speeches: 0,
+                    words: 0,
+                    share: 0.0,
+                });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[index];
+        group.characters.push(character.name.clone());
+        group.speeches += character.speeches;
+        group.words += character.words;
+    }
+    for group in &mut groups {
+        group.share = group.words as f64 / total_words.max(1) as f64;
+    }
+
+    let mut conversations: Vec<TaggedConversation> = Vec::new();
+    for pair in speeches.windows(2) {
+        let (scene, line, a, _) = &pair[0];
+        let (next_scene, _, b, _) = &pair[1];
+        if scene != next_scene || a == b {
+            continue;
+        }
+        let (Some(value), Some(next_value)) = (value_of(a), value_of(b)) else {
+            continue;
+        };
+        if value != next_value {
+            continue;
+        }
+        let existing = conversations
+            .iter_mut()
+            .find(|c| c.scene_number == *scene && c.value == value);
+        let conversation = match existing {
+            Some(conversation) => conversation,
+            None => {
+                conversations.push(TaggedConversation {
+                    value,
+                    scene_number: scene.clone(),
+                    line: *line,
+                    characters: Vec::new(),
+                });
+                conversations.last_mut().unwrap()
+            }
+        };
+        for name in [a, b] {
+            if !conversation.characters.contains(name) {
+                conversation.characters.push(name.clone());
+            }
+        }
+    }
+
+    // 稳定排序，字数相同时保持出场顺序
+    characters.sort_by_key(|c| std::cmp::Reverse(c.words));
+    groups.sort_by_key(|g| std::cmp::Reverse(g.words));
+    DialogueBalance {
+        tag: tag.to_string(),
+        characters,
+        groups,
+        conversations,
+    }
+}
+
+// 标题页元数据中各角色的标签值：大写角色名 -> 标签值
+fn character_tags(output: &ParseOutput, tag: &str) -> HashMap<String, String> {
+    let mut tags = HashMap::new();
+    let hidden = output.title_page.get("hidden").into_iter().flatten();
+    for token in hidden.filter(|t| t.token_type == "metadata") {
+        let json_text = token.text.strip_prefix("Metadata: ").unwrap_or(&token.text);
+        let Ok(metadata) = serde_json::from_str::<serde_json::Value>(json_text) else {
+            continue;
+        };
+        let Some(characters) = metadata.get("characters").and_then(|v| v.as_object()) else {
+            continue;
+        };
+        for (name, fields) in characters {
+            let value = match fields.get(tag) {
+                Some(serde_json::Value::String(value)) => value.trim().to_string(),
+                Some(serde_json::Value::Null) | None => continue,
+                Some(value) => value.to_string(),
+            };
+            if !value.is_empty() {
+                tags.insert(name.trim().to_uppercase(), value);
+            }
+        }
+    }
+    tags
+}
+
+// 场景标题行号 -> 场景时长
+fn scene_durations_by_line(output: &ParseOutput) -> HashMap<usize, f64> {
+    let mut durations = HashMap::new();
+    for scene in &output.properties.scenes {
+        let Some(line) = scene.get("line").and_then(|v| v.as_u64()) else {
+            continue;
+        };
+        let length = |key: &str| scene.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        durations.insert(
+            line as usize,
+            length("actionLength") + length("dialogueLength"),
+        );
+    }
+    durations
+}
+
+// 交切开始标记所在行 -> 参与交切的场景标题行号（从小到大）
+fn shot_cut_scene_lines(output: &ParseOutput) -> Vec<(usize, Vec<usize>)> {
+    let mut scenes = Vec::new();
+    collect_scenes(&output.properties.structure, &mut scenes);
+    // 场景 id -> 场景标题行号
+    let scene_lines: HashMap<&str, usize> = scenes
+        .iter()
+        .filter_map(|scene| Some((scene.id.as_deref()?, scene.range.as_ref()?.start.line)))
+        .collect();
+
+    output
+        .properties
+        .shot_cuts
+        .iter()
+        .filter_map(|shot_cut| {
+            let line = shot_cut.get("line")?.as_u64()? as usize;
+            let mut lines: Vec<usize> = shot_cut
+                .get("scene_ids")?
+                .as_array()?
+                .iter()
+                .filter_map(|id| scene_lines.get(id.as_str()?).copied())
+                .collect();
+            lines.sort_unstable();
+            lines.dedup();
+            Some((line, lines))
+        })
+        .collect()
+}
+
+// 场景标题行号 -> 地点
+fn scene_locations_by_line(output: &ParseOutput) -> HashMap<usize, &str> {
+    let mut locations = HashMap::new();
+    for (name, references) in &output.properties.locations {
+        for reference in references {
+            locations.insert(reference.line, name.as_str());
+        }
+    }
+    locations
+}
+
+fn collect_scenes<'a>(structure: &'a [StructToken], scenes: &mut Vec<&'a StructToken>) {
+    for token in structure {
+        if token.isscene {
+            scenes.push(token);
+        }
+        collect_scenes(&token.children, scenes);
+    }
+}
+
+pub(crate) fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,142 @@
+//! 标题页模板
+//!
+//! 预设的标题页版式，规定各标题页字段放在哪个区域（tl/tc/tr/cc/bl/br）、区域内的先后顺序和字体。
+//! 由 `Conf::title_page_template` 选择，没有写出的字段沿用默认的位置。
+
+use serde::{Deserialize, Serialize};
+
+/// 标题页字段的位置和字体
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TitleField {
+    /// 标题页的 key，如 "title"、"draft_date"
+    pub key: &'static str,
+    /// 所在区域：tl、tc、tr、cc、bl、br
+    pub position: &'static str,
+    /// 区域内的先后顺序
+    pub index: i32,
+    /// 字号 //磅，为空时与正文相同
+    pub size: Option<f32>,
+    /// 是否加粗
+    pub bold: bool,
+    /// 是否加下划线
+    pub underline: bool,
+    /// 是否转为大写
+    pub uppercase: bool,
+}
+
+impl TitleField {
+    const fn new(key: &'static str, position: &'static str, index: i32) -> Self {
+        TitleField {
+            key,
+            position,
+            index,
+            size: None,
+            bold: false,
+            underline: false,
+            uppercase: false,
+        }
+    }
+
+    const fn size(mut self, size: f32) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    const fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    const fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    const fn uppercase(mut self) -> Self {
+        self.uppercase = true;
+        self
+    }
+}
+
+/// 标题页模板
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TitlePageTemplate {
+    /// 美国规范：剧名大写加下划线居中，联系方式在左下，稿本日期在右下
+    Us,
+    /// BBC 版式：剧名加粗居中，稿本信息紧随作者之下，联系方式在左下
+    Bbc,
+    /// 中文封面：剧名大字号加粗居中，编剧、版本和日期依次居中排在其下，联系方式在右下
+    China,
+}
+
+const US_FIELDS: &[TitleField] = &[
+    TitleField::new("title", "cc", 0).uppercase().underline(),
+    TitleField::new("credit", "cc", 1),
+    TitleField::new("author", "cc", 2),
+    TitleField::new("authors", "cc", 3),
+    TitleField::new("source", "cc", 4),
+    TitleField::new("contact", "bl", 0),
+    TitleField::new("contact_info", "bl", 1),
+    TitleField::new("notes", "bl", 2),
+    TitleField::new("copyright", "bl", 3),
+    TitleField::new("revision", "br", 0),
+    TitleField::new("draft_date", "br", 1),
+    TitleField::new("date", "br", 2),
+];
+
+const BBC_FIELDS: &[TitleField] = &[
+    TitleField::new("title", "cc", 0).bold().uppercase(),
+    TitleField::new("credit", "cc", 1),
+    TitleField::new("author", "cc", 2),
+    TitleField::new("authors", "cc", 3),
+    TitleField::new("source", "cc", 4),
+    TitleField::new("revision", "cc", 5),
+    TitleField::new("draft_date", "cc", 6),
+    TitleField::new("date", "cc", 7),
+    TitleField::new("contact", "bl", 0),
+    TitleField::new("contact_info", "bl", 1),
+    TitleField::new("copyright", "bl", 2),
+    TitleField::new("notes", "br", 0),
+];
+
+const CHINA_FIELDS: &[TitleField] = &[
+    TitleField::new("title", "cc", 0).size(26.0).bold(),
+    TitleField::new("source", "cc", 1),
+    TitleField::new("credit", "cc", 2).size(16.0),
+    TitleField::new("author", "cc", 3).size(16.0),
+    TitleField::new("authors", "cc", 4).size(16.0),
+    TitleField::new("revision", "cc", 5),
+    TitleField::new("draft_date", "cc", 6),
+    TitleField::new("date", "cc", 7),
+    TitleField::new("notes", "bl", 0),
+    TitleField::new("copyright", "bl", 1),
+    TitleField::new("contact", "br", 0),
+    TitleField::new("contact_info", "br", 1),
+];
+
+impl TitlePageTemplate {
+    /// 按名称（"us"、"bbc"、"china"，不区分大小写）选择模板，空名称或不认识的名称返回 None
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "us" => Some(TitlePageTemplate::Us),
+            "bbc" => Some(TitlePageTemplate::Bbc),
+            "china" | "zh" => Some(TitlePageTemplate::China),
+            _ => None,
+        }
+    }
+
+    /// 模板规定的所有字段
+    pub fn fields(&self) -> &'static [TitleField] {
+        match self {
+            TitlePageTemplate::Us => US_FIELDS,
+            TitlePageTemplate::Bbc => BBC_FIELDS,
+            TitlePageTemplate::China => CHINA_FIELDS,
+        }
+    }
+
+    /// 某个标题页 key 的位置和字体
+    pub fn field(&self, key: &str) -> Option<&'static TitleField> {
+        self.fields().iter().find(|field| field.key == key)
+    }
+}
@@ -0,0 +1,185 @@
+//! 打字机格式纯文本导出
+//!
+//! 按 10 cpi（每英寸 10 个字符）的等宽排版输出 .txt 剧本：各元素的起始列由 `PrintProfile` 中的 feed 决定
+//! （例如 feed 为 4.2 英寸的角色名从第 43 列开始），行宽以纸张右边距为界，对话等元素左右缩进对称。
+//! 全角字符按两列计算，折行遵循中日韩文字的避头尾规则。分页符输出为换页符 `\f`，双对话按先左后右的顺序依次输出。
+
+use crate::docx::line_processor::{display_width, wrap};
+use crate::docx::PrintProfile;
+use crate::models::ScriptToken;
+use crate::parser::ParseOutput;
+use crate::utils::strip_style_chars;
+
+/// 每英寸字符数
+const CHARS_PER_INCH: f32 = 10.0;
+
+/// 生成打字机格式的纯文本剧本
+pub fn generate_typewriter(output: &ParseOutput, profile: &PrintProfile) -> String {
+    let layout = Layout::new(profile);
+    let mut lines: Vec<String> = Vec::new();
+
+    let title_page = title_page_lines(output, &layout);
+    if !title_page.is_empty() {
+        lines.extend(title_page);
+        lines.push("\u{c}".to_string());
+    }
+
+    for token in &output.tokens {
+        if token.ignore {
+            continue;
+        }
+        let text = strip_style_chars(&token.text);
+        match token.token_type.as_str() {
+            // 连续的空行只保留一行，换页后不留空行
+            "separator" if lines.last().is_some_and(|l| !l.is_empty() && l != "\u{c}") => {
+                lines.push(String::new());
+            }
+            "page_break" => {
+                while lines.last().is_some_and(|l| l.is_empty()) {
+                    lines.pop();
+                }
+                lines.push("\u{c}".to_string());
+            }
+            "scene_heading" => {
+                let start = lines.len();
+                let wrapped = wrap(&text.to_uppercase(), layout.action.1 - layout.action.0);
+                push_block(&mut lines, &wrapped, layout.action.0);
+                // 场景编号写在左边距里，与标题最多隔开 4 列
+                if let (Some(number), Some(first)) = (scene_number(token), lines.get_mut(start)) {
+                    let width = display_width(&number);
+                    let pad = layout.action.0.saturating_sub(width + 4);
+                    if pad + width < layout.action.0 {
+                        *first = format!("{}{}{}", " ".repeat(pad), number, &first[pad + width..]);
+                    }
+                }
+            }
+            "action" => push_block(
+                &mut lines,
+                &wrap(&text, layout.action.1 - layout.action.0),
+                layout.action.0,
+            ),
+            "lyric" => push_block(
+                &mut lines,
+                &wrap(&format!("♪ {}", text), layout.action.1 - layout.action.0),
+                layout.action.0,
+            ),
+            "character" => push_block(
+                &mut lines,
+                &wrap(&text, layout.character.1 - layout.character.0),
+                layout.character.0,
+            ),
+            "dialogue" => push_block(
+                &mut lines,
+                &wrap(&text, layout.dialogue.1 - layout.dialogue.0),
+                layout.dialogue.0,
+            ),
+            "parenthetical" => push_block(
+                &mut lines,
+                &wrap(&text, layout.parenthetical.1 - layout.parenthetical.0),
+                layout.parenthetical.0,
+            ),
+            "transition" => {
+                // 转场右对齐到右边界
+                for line in wrap(&text.to_uppercase(), layout.action.1 - layout.action.0) {
+                    let pad = layout.action.1.saturating_sub(display_width(&line));
+                    lines.push(format!("{}{}", " ".repeat(pad), line));
+                }
+            }
+            "centered" => {
+                for line in wrap(&text, layout.action.1 - layout.action.0) {
+                    let pad = layout.action.0
+                        + (layout.action.1 - layout.action.0).saturating_sub(display_width(&line))
+                            / 2;
+                    lines.push(format!("{}{}", " ".repeat(pad), line));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    // 换页符独占一行时去掉其前后的换行，与打印机习惯一致
+    text.replace("\n\u{c}\n", "\n\u{c}")
+}
+
+/// 各元素的 (起始列, 结束列)，列号从 0 开始
+struct Layout {
+    action: (usize, usize),
+    character: (usize, usize),
+    dialogue: (usize, usize),
+    parenthetical: (usize, usize),
+}
+
+impl Layout {
+    fn new(profile: &PrintProfile) -> Self {
+        let column = |inches: f32| (inches * CHARS_PER_INCH).round().max(0.0) as usize;
+        let right = column(profile.page_width - profile.right_margin);
+        let action_start = column(profile.action.feed);
+        // 以 action 为基准，左侧缩进多少，右侧也缩进多少
+        let span = |feed: f32| {
+            let start = column(feed);
+            let end = right.saturating_sub(start.saturating_sub(action_start));
+            (start, end.max(start + 1))
+        };
+        Self {
+            action: (action_start, right.max(action_start + 1)),
+            // 角色名只需要起始列，宽度放到右边界
+            character: (
+                column(profile.character.feed),
+                right.max(column(profile.character.feed) + 1),
+            ),
+            dialogue: span(profile.dialogue.feed),
+            parenthetical: span(profile.parenthetical.feed),
+        }
+    }
+}
+
+fn title_page_lines(output: &ParseOutput, layout: &Layout) -> Vec<String> {
+    let width = layout.action.1 - layout.action.0;
+    let mut lines = Vec::new();
+    for position in ["tl", "tc", "tr", "cc", "bl", "br"] {
+        let Some(tokens) = output.title_page.get(position) else {
+            continue;
+        };
+        let mut tokens: Vec<&ScriptToken> = tokens.iter().collect();
+        tokens.sort_by_key(|t| t.index);
+        for token in tokens {
+            for line in strip_style_chars(&token.text).split('\n') {
+                let line = line.trim();
+                let pad = match position {
+                    "tc" | "cc" => layout.action.0 + width.saturating_sub(display_width(line)) / 2,
+                    "tr" | "br" => layout.action.1.saturating_sub(display_width(line)),
+                    _ => layout.action.0,
+                };
+                lines.push(format!("{}{}", " ".repeat(pad), line));
+            }
+            lines.push(String::new());
+        }
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn scene_number(token: &ScriptToken) -> Option<String> {
+    token
+        .number
+        .as_ref()
+        .map(|n| n.trim_start_matches('↑').to_string())
+        .filter(|n| !n.is_empty())
+}
+
+fn push_block(lines: &mut Vec<String>, block: &[String], indent: usize) {
+    for line in block {
+        if line.is_empty() {
+            lines.push(String::new());
+        } else {
+            lines.push(format!("{}{}", " ".repeat(indent), line));
+        }
+    }
+}
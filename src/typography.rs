@@ -0,0 +1,140 @@
+//! 排版美化
+//!
+//! 把直引号换成弯引号（或该语言习惯的引号）、双连字符换成破折号、连续句点换成省略号。
+//! 文本中的样式标记字符原样保留，判断引号开闭时跳过它们；注解（`[[...]]`）中的内容不做处理。
+//! 通过 `Conf::typography` 按语言启用，解析时作用于动作、对白等正文 token 的显示文本。
+
+use crate::utils::FountainConstants;
+
+/// 某种语言的排版习惯
+struct Typography {
+    /// 双引号（开, 闭）
+    double: (&'static str, &'static str),
+    /// 单引号（开, 闭）
+    single: (&'static str, &'static str),
+    /// 破折号
+    dash: &'static str,
+    /// 省略号
+    ellipsis: &'static str,
+}
+
+impl Typography {
+    /// 按语言代码取得排版习惯，不支持的语言（包括空字符串）返回 None
+    fn for_language(language: &str) -> Option<Self> {
+        let language = language.trim().to_lowercase();
+        let typography = match language.split(['-', '_']).next().unwrap_or("") {
+            "en" => Typography {
+                double: ("“", "”"),
+                single: ("‘", "’"),
+                dash: "—",
+                ellipsis: "…",
+            },
+            "de" => Typography {
+                double: ("„", "“"),
+                single: ("‚", "‘"),
+                dash: "—",
+                ellipsis: "…",
+            },
+            "fr" => Typography {
+                double: ("«\u{a0}", "\u{a0}»"),
+                single: ("‹\u{a0}", "\u{a0}›"),
+                dash: "—",
+                ellipsis: "…",
+            },
+            "zh" => Typography {
+                double: ("“", "”"),
+                single: ("‘", "’"),
+                dash: "——",
+                ellipsis: "……",
+            },
+            "ja" => Typography {
+                double: ("「", "」"),
+                single: ("『", "』"),
+                dash: "——",
+                ellipsis: "……",
+            },
+            _ => return None,
+        };
+        Some(typography)
+    }
+}
+
+/// 按 `language`（"en"、"de"、"fr"、"zh"、"ja"，可带地区如 "en-US"）美化文本，不支持的语言原样返回
+pub fn typeset(text: &str, language: &str) -> String {
+    let Some(typography) = Typography::for_language(language) else {
+        return text.to_string();
+    };
+    let style_chars = FountainConstants::style_chars();
+    let markers = style_chars["all"];
+    let note_begin = format!(
+        "{}{}",
+        style_chars["note_begin"], style_chars["note_begin_ext"]
+    );
+    let note_end = style_chars["note_end"];
+
+    let chars: Vec<char> = text.chars().collect();
+    let visible = |c: char| !markers.contains(c);
+    // 前一个可见字符
+    let mut prev: Option<char> = None;
+    let mut in_note = 0;
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if note_begin.contains(c) {
+            in_note += 1;
+        } else if note_end.contains(c) {
+            in_note = (in_note - 1).max(0);
+        }
+        if in_note > 0 || !visible(c) {
+            out.push(c);
+            i += 1;
+            continue;
+        }
+
+        let run = chars[i..].iter().take_while(|&&x| x == c).count();
+        let replaced = match c {
+            '-' if run >= 2 => Some(typography.dash),
+            '.' if run >= 3 => Some(typography.ellipsis),
+            '…' => Some(typography.ellipsis),
+            _ => None,
+        };
+        if let Some(replacement) = replaced {
+            out.push_str(replacement);
+            prev = replacement.chars().last();
+            i += run;
+            continue;
+        }
+
+        let next = chars[i + 1..].iter().copied().find(|x| visible(*x));
+        // 空白、左括号之后为左引号，字母、文字之后为右引号；标点之后看后面是否紧跟文字
+        let opening = match prev {
+            None => true,
+            Some(p) if p.is_whitespace() || "([{<“‘„‚«‹「『（".contains(p) => true,
+            Some(p) if p.is_alphanumeric() => false,
+            Some(_) => next.is_some_and(char::is_alphanumeric),
+        };
+        let quote = match c {
+            '"' if opening => typography.double.0,
+            '"' => typography.double.1,
+            // 词中的撇号（如 don't）
+            '\'' if prev.is_some_and(char::is_alphanumeric)
+                && next.is_some_and(char::is_alphanumeric) =>
+            {
+                "’"
+            }
+            '\'' if opening => typography.single.0,
+            '\'' => typography.single.1,
+            _ => {
+                out.push(c);
+                prev = Some(c);
+                i += 1;
+                continue;
+            }
+        };
+        out.push_str(quote);
+        prev = quote.chars().last();
+        i += 1;
+    }
+    out
+}
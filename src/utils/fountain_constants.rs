@@ -18,6 +18,12 @@ impl FountainConstants {
         map.insert("italic_underline", "⇀");
         map.insert("bold_underline", "☍");
         map.insert("bold_italic_underline", "☋");
+        map.insert("strikethrough", "↮");
+        map.insert("highlight", "⇋");
+        // 彩色文字：color_begin 颜色值 color_text 文字 color_end
+        map.insert("color_begin", "⇉");
+        map.insert("color_text", "⇊");
+        map.insert("color_end", "⇇");
         map.insert("link", "𓆡");
         map.insert("style_left_stash", "↷");
         map.insert("style_left_pop", "↶");
@@ -28,7 +34,7 @@ impl FountainConstants {
         map.insert("style_global_clean", "⇜");
         map.insert("italic_global_begin", "↾");
         map.insert("italic_global_end", "↿");
-        map.insert("all", "☄☈↭↯↺↻↬↫☍☋↷↶↾↿↝↜⇀𓆡⇜இ");
+        map.insert("all", "☄☈↭↯↺↻↬↫☍☋↷↶↾↿↝↜⇀𓆡⇜இ↮⇋⇉⇊⇇");
         map
     }
 }
@@ -37,7 +43,7 @@ lazy_static! {
     // 块级元素正则
     pub static ref BLOCK_REGEX: HashMap<&'static str, Regex> = {
         let mut map = HashMap::new();
-        map.insert("block_dialogue_begin", Regex::new(r"^[ \t]*((\p{Lu}[^\p{Ll}\r\n@]*)|(@[^\r\n\(（\^]*))(\(.*\)|（.*）)?(\s*\^)?\s*$").unwrap());
+        map.insert("block_dialogue_begin", Regex::new(r"^[ \t]*((\p{Lu}[^\p{Ll}\r\n@]*)|(@[^\r\n\(（\^]*))(\(.*\)|（.*）)?(\s*\^\^?)?\s*$").unwrap());
         map.insert("block_except_dialogue_begin", Regex::new(r"^\s*[^\s]+.*$").unwrap());
         map.insert("block_end", Regex::new(r"^\s*$").unwrap());
         map.insert("line_break", Regex::new(r"^\s{2,}$").unwrap());
@@ -14,3 +14,182 @@ pub fn is_blank_line_after_style(text: &str) -> bool {
     let t = re.replace_all(text, "");
     t.trim().is_empty()
 }
+
+/// 可以用反斜杠转义的字符，转义后按原样输出，不切换样式
+pub const ESCAPABLE_CHARS: &str = "\\*_~=[]{}";
+
+/// 把 `\*`、`\[` 等转义字符换成私用区字符（U+F0000 加上原字符的码位），使其不被当作样式标记处理
+pub fn escape_style_chars(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&next) if c == '\\' && ESCAPABLE_CHARS.contains(next) => {
+                out.push(char::from_u32(0xF0000 + next as u32).unwrap());
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 把 `escape_style_chars` 换出的私用区字符换回原字符
+pub fn unescape_style_chars(text: &str) -> String {
+    text.chars()
+        .map(|c| match (c as u32).checked_sub(0xF0000) {
+            Some(code) if code < 0x80 => char::from_u32(code).unwrap(),
+            _ => c,
+        })
+        .collect()
+}
+
+/// 去掉文本中的注解内容、彩色文字的颜色值和所有样式标记字符，得到纯文本
+pub fn strip_style_chars(text: &str) -> String {
+    let chars = FountainConstants::style_chars();
+    let note_begin = format!("{}{}", chars["note_begin"], chars["note_begin_ext"]);
+    let mut out = String::with_capacity(text.len());
+    let mut in_note = 0;
+    let mut in_color = false;
+    for c in text.chars() {
+        if note_begin.contains(c) {
+            in_note += 1;
+        } else if chars["note_end"].contains(c) {
+            in_note = (in_note - 1).max(0);
+        } else if chars["color_begin"].contains(c) {
+            in_color = true;
+        } else if chars["color_text"].contains(c) {
+            in_color = false;
+        } else if in_note == 0 && !in_color && !chars["all"].contains(c) {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// 按语言规则转为大写：`lang` 为 "tr"、"az"（土耳其语、阿塞拜疆语）时 i 转为 İ、ı 转为 I，
+/// 其他语言与 `str::to_uppercase` 相同
+pub fn to_uppercase_locale(text: &str, lang: &str) -> String {
+    let lang = lang.trim().to_lowercase();
+    if !matches!(lang.split(['-', '_']).next(), Some("tr" | "az")) {
+        return text.to_uppercase();
+    }
+    text.chars()
+        .map(|c| match c {
+            'i' => "İ".to_string(),
+            'ı' => "I".to_string(),
+            c => c.to_uppercase().to_string(),
+        })
+        .collect()
+}
+
+/// 把文本中的粗体、斜体、下划线、删除线、高亮标记字符换成对应的 HTML 标签，
+/// 彩色文字换成带颜色的 `<span>`，其余字符原样保留
+///
+/// 标记字符成对出现，第一个开始、第二个结束；文本结束时仍未关闭的标签依次补上结束标签。
+pub fn style_chars_to_html(text: &str) -> String {
+    let chars = FountainConstants::style_chars();
+    let tags = [
+        (chars["bold_italic"], "<strong><em>", "</em></strong>"),
+        (chars["bold"], "<strong>", "</strong>"),
+        (chars["italic"], "<em>", "</em>"),
+        (chars["underline"], "<u>", "</u>"),
+        (chars["strikethrough"], "<del>", "</del>"),
+        (chars["highlight"], "<mark>", "</mark>"),
+    ];
+    let mut open = [false; 6];
+    let mut out = String::with_capacity(text.len());
+    // 正在读取的颜色值，读完后输出 <span>
+    let mut color: Option<String> = None;
+    let mut color_spans = 0;
+    for c in text.chars() {
+        if chars["color_begin"].contains(c) {
+            color = Some(String::new());
+        } else if chars["color_text"].contains(c) {
+            let value = color.take().unwrap_or_default();
+            out.push_str(&format!("<span style=\"color:{}\">", value));
+            color_spans += 1;
+        } else if chars["color_end"].contains(c) {
+            if color_spans > 0 {
+                out.push_str("</span>");
+                color_spans -= 1;
+            }
+        } else if let Some(value) = color.as_mut() {
+            value.push(c);
+        } else {
+            match tags.iter().position(|(marker, _, _)| marker.contains(c)) {
+                Some(i) => {
+                    out.push_str(if open[i] { tags[i].2 } else { tags[i].1 });
+                    open[i] = !open[i];
+                }
+                None => out.push(c),
+            }
+        }
+    }
+    for i in (0..tags.len()).rev().filter(|i| open[*i]) {
+        out.push_str(tags[i].2);
+    }
+    out.push_str(&"</span>".repeat(color_spans));
+    out
+}
+
+lazy_static::lazy_static! {
+    // 网址（http(s):// 或 www. 开头）和邮箱地址；剧本文本中的 _、* 已被换成下划线、斜体标记字符，一并匹配
+    static ref LINK_REGEX: regex::Regex = regex::Regex::new(
+        r"(?i)(?:https?://|www\.)[a-z0-9](?:[a-z0-9-]*[a-z0-9])?(?:\.[a-z0-9](?:[a-z0-9-]*[a-z0-9])?)+(?::\d+)?(?:[/?#][!#-;=?-~☄☈]*)?|(?:mailto:)?[a-z0-9_.%+☄-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)+"
+    )
+    .unwrap();
+}
+
+/// 文本中识别出的超链接
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// 在文本中的起始位置（字节）
+    pub start: usize,
+    /// 在文本中的长度（字节）
+    pub length: usize,
+    /// 显示的文本，样式标记字符已换回 _、*
+    pub text: String,
+    /// 链接地址：www. 开头的补上 http://，邮箱地址补上 mailto:
+    pub url: String,
+}
+
+/// 识别文本中的网址和邮箱地址
+///
+/// 末尾的标点（句号、逗号、右括号等）不算在链接内。
+pub fn find_links(text: &str) -> Vec<Link> {
+    let chars = FountainConstants::style_chars();
+    LINK_REGEX
+        .find_iter(text)
+        .filter_map(|m| {
+            let matched = m
+                .as_str()
+                .trim_end_matches(|c: char| ".,;:!?)]}'".contains(c) || chars["all"].contains(c));
+            // 前面紧挨着字母、数字时是更长的单词的一部分，如 awww.example.com
+            let prev = text[..m.start()].chars().next_back();
+            if matched.is_empty() || matches!(prev, Some(c) if c.is_ascii_alphanumeric()) {
+                return None;
+            }
+            let display = matched
+                .replace(chars["underline"], "_")
+                .replace(chars["italic"], "*");
+            let lower = display.to_lowercase();
+            let url = if lower.starts_with("http://")
+                || lower.starts_with("https://")
+                || lower.starts_with("mailto:")
+            {
+                display.clone()
+            } else if lower.starts_with("www.") {
+                format!("http://{}", display)
+            } else {
+                format!("mailto:{}", display)
+            };
+            Some(Link {
+                start: m.start(),
+                length: matched.len(),
+                text: display,
+                url,
+            })
+        })
+        .collect()
+}
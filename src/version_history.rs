@@ -0,0 +1,116 @@
+//! 版本记录（审批页）
+//!
+//! 由标题页的 `Revision:`、`Draft date:`（或 `Date:`）、`Author:` 生成当前版本，
+//! 此前的版本取自标题页 Metadata 中的 `history` 列表，如
+//! `{"history": [{"version": "1", "date": "2024-03-01", "author": "张三", "notes": "初稿", "approved_by": "李四"}]}`。
+//! 开启 `Conf::print_version_history` 后，DOCX 导出会在标题页之后插入一页版本记录表。
+
+use crate::parser::ParseOutput;
+use crate::utils::strip_style_chars;
+use serde::{Deserialize, Serialize};
+
+/// 版本记录页的标题
+pub const VERSION_HISTORY_TITLE: &str = "VERSION HISTORY";
+
+/// 版本记录表的表头
+pub const VERSION_HISTORY_COLUMNS: [&str; 5] = ["版本", "日期", "作者", "说明", "审批"];
+
+/// 版本记录中的一行
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VersionEntry {
+    pub version: String,
+    pub date: String,
+    pub author: String,
+    /// 修改说明
+    pub notes: String,
+    /// 审批人
+    pub approved_by: String,
+}
+
+impl VersionEntry {
+    /// 表格中的一行，顺序与 `VERSION_HISTORY_COLUMNS` 相同
+    pub fn cells(&self) -> [&str; 5] {
+        [
+            &self.version,
+            &self.date,
+            &self.author,
+            &self.notes,
+            &self.approved_by,
+        ]
+    }
+}
+
+/// 按时间顺序列出所有版本，当前版本在最后；Metadata 中已记录了同一版本号时不重复列出
+pub fn version_history(output: &ParseOutput) -> Vec<VersionEntry> {
+    let title_page_text = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| {
+                output
+                    .title_page
+                    .values()
+                    .flatten()
+                    .find(|token| token.token_type == *key)
+            })
+            .map(|token| {
+                strip_style_chars(&token.text)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default()
+    };
+
+    let mut entries = metadata_history(output);
+    let current = VersionEntry {
+        version: title_page_text(&["revision"]),
+        date: title_page_text(&["draft_date", "date"]),
+        author: title_page_text(&["author", "authors"]),
+        ..VersionEntry::default()
+    };
+    let recorded =
+        !current.version.is_empty() && entries.iter().any(|entry| entry.version == current.version);
+    if !recorded && (!current.version.is_empty() || !current.date.is_empty()) {
+        entries.push(current);
+    }
+    entries
+}
+
+// 标题页 Metadata 中的 history 列表，字段可以是字符串或数字
+fn metadata_history(output: &ParseOutput) -> Vec<VersionEntry> {
+    let metadata = output
+        .title_page
+        .get("hidden")
+        .into_iter()
+        .flatten()
+        .find(|token| token.token_type == "metadata")
+        .and_then(|token| serde_json::from_str::<serde_json::Value>(&token.text).ok());
+    let Some(history) = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("history"))
+        .and_then(|history| history.as_array())
+    else {
+        return Vec::new();
+    };
+
+    history
+        .iter()
+        .filter_map(|item| {
+            let field = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|key| match item.get(*key)? {
+                        serde_json::Value::String(s) => Some(s.trim().to_string()),
+                        serde_json::Value::Number(n) => Some(n.to_string()),
+                        _ => None,
+                    })
+                    .unwrap_or_default()
+            };
+            item.is_object().then(|| VersionEntry {
+                version: field(&["version", "revision"]),
+                date: field(&["date"]),
+                author: field(&["author"]),
+                notes: field(&["notes", "changes"]),
+                approved_by: field(&["approved_by", "approver"]),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,30 @@
+use betterfountain_rust::adr::{adr_cues, adr_cues_csv, timecode};
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_adr_cue_sheet() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来，看了看四周。\n\n@阿强\n(小声)\n你好，\n今天天气**不错**。\n\n@阿珍\n你好。\n";
+    let output = parse(script, &Conf::default(), false, None);
+    let cues = adr_cues(&output);
+
+    assert_eq!(cues.len(), 2);
+    assert_eq!(cues[0].take, 1);
+    assert_eq!(cues[0].character, "阿强");
+    assert_eq!(cues[0].scene_number.as_deref(), Some("1"));
+    // 括号注释不算台词，多行以空格连接
+    assert_eq!(cues[0].text, "你好， 今天天气不错。");
+    assert!(cues[0].start_sec > 0.0);
+    assert!(cues[0].end_sec > cues[0].start_sec);
+    assert_eq!(cues[1].take, 2);
+    assert_eq!(cues[1].start_sec, cues[0].end_sec);
+
+    let csv = adr_cues_csv(&cues, 24);
+    let mut rows = csv.lines();
+    assert_eq!(
+        rows.next(),
+        Some("take,character,scene,line,timecode_in,timecode_out,text")
+    );
+    assert!(rows.next().unwrap().starts_with("1,阿强,1,4,"));
+
+    assert_eq!(timecode(3725.5, 24), "01:02:05:12");
+}
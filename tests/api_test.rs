@@ -0,0 +1,241 @@
+use betterfountain_rust::api::{extract_scenes, SceneSelector};
+
+const SCRIPT: &str = "Title: 测试\n\nINT. 客厅 - 日\n\n阿强走进来。\n\n# 第二幕\n\nEXT. 花园 - 夜\n\n月光洒在草地上。\n\nINT. 客厅 - 夜\n\n@阿珍\n该睡觉了。\n";
+
+#[test]
+fn test_extract_scenes() {
+    use betterfountain_rust::models::Conf;
+
+    let config = Conf::default();
+    let scenes = extract_scenes(SCRIPT, &SceneSelector::Range { from: 1, to: 2 }, &config);
+    assert_eq!(scenes.len(), 2);
+    // 章节不属于前一个场景
+    assert_eq!(scenes[0].text, "INT. 客厅 - 日\n\n阿强走进来。\n");
+    assert_eq!(scenes[1].text, "EXT. 花园 - 夜\n\n月光洒在草地上。\n");
+
+    let scenes = extract_scenes(
+        SCRIPT,
+        &SceneSelector::Location("客厅".to_string()),
+        &config,
+    );
+    assert_eq!(scenes.len(), 2);
+    assert!(scenes[1].text.ends_with("@阿珍\n该睡觉了。\n"));
+
+    let number = scenes[1].number.clone();
+    let scenes = extract_scenes(SCRIPT, &SceneSelector::Numbers(vec![number]), &config);
+    assert_eq!(scenes.len(), 1);
+    assert!(scenes[0].text.starts_with("INT. 客厅 - 夜"));
+
+    // CRLF 换行的行号与 LF 一致
+    let crlf = SCRIPT.replace('\n', "\r\n");
+    assert_eq!(
+        extract_scenes(&crlf, &SceneSelector::Range { from: 3, to: 3 }, &config)[0].line,
+        scenes[0].line
+    );
+
+    // 自定义的场景标题前缀按配置识别
+    let script = "场景 客厅 - 日\n\n阿强走进来。\n\n场景 花园 - 夜\n\n月光洒在草地上。\n";
+    let all = SceneSelector::Range { from: 1, to: 10 };
+    assert!(extract_scenes(script, &all, &config).is_empty());
+    let config = Conf {
+        scene_heading_prefixes: vec!["场景".to_string()],
+        ..Conf::default()
+    };
+    let scenes = extract_scenes(script, &all, &config);
+    assert_eq!(scenes.len(), 2);
+    assert_eq!(scenes[1].text, "场景 花园 - 夜\n\n月光洒在草地上。\n");
+}
+
+#[test]
+fn test_search_with_filters() {
+    use betterfountain_rust::api::{search, SearchFilters};
+    use betterfountain_rust::models::Conf;
+    use betterfountain_rust::parser::FountainParser;
+
+    let parser = FountainParser::new();
+    let output = parser.parse(SCRIPT, &Conf::default(), false, None);
+
+    let filters = SearchFilters::parse("type:dialogue character:阿珍 location:客厅").unwrap();
+    let hits = search(&output, "睡觉", &filters);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].text, "该睡觉了。");
+    assert_eq!(hits[0].scene_number.as_deref(), Some("3"));
+    assert_eq!(output.tokens[hits[0].token_index].line, hits[0].line);
+
+    let hits = search(
+        &output,
+        "",
+        &SearchFilters::parse("scene:1-2 type:action").unwrap(),
+    );
+    assert_eq!(hits.len(), 2);
+
+    assert!(search(
+        &output,
+        "月光",
+        &SearchFilters::parse("location:客厅").unwrap()
+    )
+    .is_empty());
+    assert!(SearchFilters::parse("scene:a-b").is_err());
+    assert!(SearchFilters::parse("foo:bar").is_err());
+}
+
+#[test]
+fn test_completion_context() {
+    use betterfountain_rust::api::{completion_context, CompletionKind};
+    use betterfountain_rust::models::Conf;
+
+    let offset = |text: &str| text.chars().count();
+
+    // 标题页：已经使用的键不再提示
+    let context = completion_context(SCRIPT, offset("Title: 测试\n"), &Conf::default());
+    assert_eq!(context.kind, CompletionKind::TitlePage);
+    assert!(!context.title_page_keys.contains(&"Title".to_string()));
+    assert!(context.title_page_keys.contains(&"Author".to_string()));
+
+    // 场景标题：提示前缀和已有地点
+    let script = format!("{}\nINT. 客\n", SCRIPT);
+    let context = completion_context(&script, offset(&script) - 1, &Conf::default());
+    assert_eq!(context.kind, CompletionKind::SceneHeading);
+    assert_eq!(context.prefix, "INT. 客");
+    assert_eq!(context.locations, vec!["客厅", "花园"]);
+    assert!(context.scene_heading_prefixes.contains(&"INT.".to_string()));
+    assert!(context.characters.is_empty());
+
+    // 空行之后：提示角色名和场景标题前缀，光标所在行的角色不算
+    let script = format!("{}\n@阿\n", SCRIPT);
+    let context = completion_context(&script, offset(&script) - 1, &Conf::default());
+    assert_eq!(context.kind, CompletionKind::Character);
+    assert_eq!(context.characters, vec!["阿珍"]);
+    assert!(!context.scene_heading_prefixes.is_empty());
+
+    let context = completion_context(SCRIPT, offset(SCRIPT) - 2, &Conf::default());
+    assert_eq!(context.kind, CompletionKind::Text);
+    assert!(context.scene_heading_prefixes.is_empty());
+}
+
+#[test]
+fn test_completion_context_crlf() {
+    use betterfountain_rust::api::{completion_context, CompletionKind};
+    use betterfountain_rust::models::Conf;
+
+    // 光标位置按原文计算，\r\n 算两个字符
+    let offset = |text: &str| text.chars().count();
+    let script = format!("{}\nINT. 客\n", SCRIPT).replace('\n', "\r\n");
+    let context = completion_context(&script, offset("Title: 测试\r\n"), &Conf::default());
+    assert_eq!(context.kind, CompletionKind::TitlePage);
+    assert_eq!(context.prefix, "");
+
+    let context = completion_context(&script, offset(&script) - 2, &Conf::default());
+    assert_eq!(context.kind, CompletionKind::SceneHeading);
+    assert_eq!(context.prefix, "INT. 客");
+    assert_eq!(context.locations, vec!["客厅", "花园"]);
+}
+
+#[test]
+fn test_completion_context_custom_prefixes() {
+    use betterfountain_rust::api::{completion_context, CompletionKind};
+    use betterfountain_rust::models::Conf;
+
+    let script = format!("{}\n场景 客\n", SCRIPT);
+    let cursor = script.chars().count() - 1;
+    let context = completion_context(&script, cursor, &Conf::default());
+    assert_eq!(context.kind, CompletionKind::Character);
+    assert!(!context.scene_heading_prefixes.contains(&"场景".to_string()));
+
+    // 配置的场景标题前缀参与识别，也作为候选项返回
+    let config = Conf {
+        scene_heading_prefixes: vec!["场景".to_string()],
+        ..Conf::default()
+    };
+    let context = completion_context(&script, cursor, &config);
+    assert_eq!(context.kind, CompletionKind::SceneHeading);
+    assert_eq!(context.prefix, "场景 客");
+    assert!(context.scene_heading_prefixes.contains(&"场景".to_string()));
+    assert!(context.scene_heading_prefixes.contains(&"INT.".to_string()));
+}
+
+#[test]
+fn test_document_symbols_and_folding_ranges() {
+    use betterfountain_rust::api::{document_symbols, folding_ranges, FoldingRange, SymbolKind};
+    use betterfountain_rust::models::Conf;
+    use betterfountain_rust::parser::FountainParser;
+
+    let script = "# 第一幕\n\nINT. 客厅 - 日\n\n阿强走进来。[[待定]]\n\n@阿珍\n你回来了。\n\n## 插曲\n\nEXT. 花园 - 夜\n\n月光洒在草地上。\n\n# 第二幕\n\nINT. 客厅 - 夜\n\n灯灭了。\n";
+    let conf = Conf {
+        dialogue_foldable: true,
+        ..Conf::default()
+    };
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, false, None);
+
+    let symbols = document_symbols(&output);
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(symbols[0].kind, SymbolKind::Section);
+    assert_eq!((symbols[0].start_line, symbols[0].end_line), (0, 13));
+    assert_eq!((symbols[1].start_line, symbols[1].end_line), (15, 19));
+
+    let act_one = &symbols[0].children;
+    assert_eq!(act_one.len(), 2);
+    assert_eq!(act_one[0].kind, SymbolKind::Scene);
+    assert_eq!((act_one[0].start_line, act_one[0].end_line), (2, 7));
+    let kinds: Vec<SymbolKind> = act_one[0].children.iter().map(|s| s.kind).collect();
+    assert_eq!(kinds, vec![SymbolKind::Note, SymbolKind::Dialogue]);
+    assert_eq!(act_one[0].children[1].end_line, 7);
+    assert_eq!(act_one[1].kind, SymbolKind::Section);
+    assert_eq!(act_one[1].level, 2);
+    assert!(act_one[1].children[0].name.contains("花园"));
+
+    let ranges = folding_ranges(&output);
+    assert_eq!(
+        ranges[..3],
+        [
+            FoldingRange {
+                start_line: 0,
+                end_line: 13,
+                kind: SymbolKind::Section
+            },
+            FoldingRange {
+                start_line: 2,
+                end_line: 7,
+                kind: SymbolKind::Scene
+            },
+            FoldingRange {
+                start_line: 6,
+                end_line: 7,
+                kind: SymbolKind::Dialogue
+            },
+        ]
+    );
+    assert!(ranges
+        .windows(2)
+        .all(|w| w[0].start_line <= w[1].start_line));
+}
+
+#[test]
+fn test_playback_map() {
+    use betterfountain_rust::api::PlaybackMap;
+    use betterfountain_rust::models::Conf;
+    use betterfountain_rust::parser::FountainParser;
+
+    let parser = FountainParser::new();
+    let output = parser.parse(SCRIPT, &Conf::default(), false, None);
+    let map = PlaybackMap::new(&output);
+
+    // 行号与 SCRIPT 对应：2 为第一场标题，4 为动作，8、10 为第二场标题和动作，14、15 为角色和对白
+    let action_end = map.line_to_time(6);
+    assert_eq!(map.line_to_time(2), 0.0);
+    assert_eq!(map.line_to_time(4), 0.0);
+    assert!(action_end > 0.0);
+    assert_eq!(map.line_to_time(8), action_end);
+
+    assert_eq!(map.time_to_line(0.0), Some(2));
+    assert_eq!(map.time_to_line(action_end / 2.0), Some(4));
+    assert_eq!(map.time_to_line(action_end), Some(8));
+    assert_eq!(map.time_to_line(map.line_to_time(15) + 0.1), Some(15));
+    assert_eq!(map.time_to_line(map.total_sec() + 10.0), Some(15));
+
+    // 场景中第一个占时长的行的开始时间对应场景标题
+    assert_eq!(map.time_to_line(map.line_to_time(10)), Some(8));
+    assert_eq!(map.time_to_line(map.line_to_time(15)), Some(12));
+    assert_eq!(PlaybackMap::default().time_to_line(1.0), None);
+}
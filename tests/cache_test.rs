@@ -0,0 +1,155 @@
+use betterfountain_rust::api::{CacheKey, CachedEngine};
+use betterfountain_rust::models::Conf;
+use std::fs;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_cached_engine() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n";
+    let config = Conf::default();
+    let dir = std::env::temp_dir().join(format!("betterfountain-cache-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    // 同一剧本和配置只解析、导出一次
+    let engine = CachedEngine::with_dir(&dir);
+    let first = engine.parse(script, &config);
+    let second = engine.parse(script, &config);
+    assert!(Arc::ptr_eq(&first, &second));
+    let docx = engine.export(script, &config, "docx").await.unwrap();
+    let again = engine.export(script, &config, "DOCX").await.unwrap();
+    assert!(Arc::ptr_eq(&docx, &again));
+    assert!(engine.export(script, &config, "rtf").await.is_err());
+
+    // 配置变化后重新解析
+    let changed = Conf {
+        print_notes: !config.print_notes,
+        ..config.clone()
+    };
+    assert_ne!(
+        CacheKey::new(script, &config),
+        CacheKey::new(script, &changed)
+    );
+    assert!(!Arc::ptr_eq(&first, &engine.parse(script, &changed)));
+
+    // 新的引擎从磁盘读到上次的结果
+    let key = CacheKey::new(script, &config);
+    assert!(dir.join(format!("{}.json", key)).exists());
+    let restarted = CachedEngine::with_dir(&dir);
+    let cached = restarted.export(script, &config, "docx").await.unwrap();
+    assert_eq!(cached, docx);
+    assert_eq!(
+        restarted.parse(script, &config).tokens.len(),
+        first.tokens.len()
+    );
+    assert_eq!(restarted.stats().misses, 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cache_key_parser_fingerprint() {
+    use betterfountain_rust::duration::CharRateModel;
+    use betterfountain_rust::parser::FountainParser;
+
+    let script = "INT. 客厅 - 日\n\n@阿强\n今天怎么这么安静？\n";
+    let config = Conf::default();
+    let slow = |sec_per_char: f64| {
+        let mut parser = FountainParser::new();
+        parser.set_duration_model(Arc::new(CharRateModel {
+            sec_per_char,
+            ..CharRateModel::default()
+        }));
+        parser
+    };
+
+    // 默认解析器与 CacheKey::new 相同；自定义时长模型及其参数不同时键也不同
+    let key = CacheKey::new(script, &config);
+    assert_eq!(
+        key,
+        CacheKey::with_parser(script, &config, &FountainParser::new())
+    );
+    let custom = CacheKey::with_parser(script, &config, &slow(1.0));
+    assert_ne!(key, custom);
+    assert_eq!(custom, CacheKey::with_parser(script, &config, &slow(1.0)));
+    assert_ne!(custom, CacheKey::with_parser(script, &config, &slow(2.0)));
+    assert_eq!((key.script, key.config), (custom.script, custom.config));
+
+    // 共用缓存目录时，换了时长模型的引擎不会读到默认模型的结果
+    let dir = std::env::temp_dir().join(format!(
+        "betterfountain-cache-parser-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    let default_time = CachedEngine::with_dir(&dir)
+        .parse(script, &config)
+        .length_dialogue;
+    let engine = CachedEngine::with_dir(&dir).with_parser(slow(1.0));
+    let custom_time = engine.parse(script, &config).length_dialogue;
+    assert_eq!(engine.stats().misses, 1);
+    assert!(custom_time > default_time);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_cache_external_files() {
+    let dir =
+        std::env::temp_dir().join(format!("betterfountain-cache-files-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("acts")).unwrap();
+    let main = dir.join("main.fountain");
+    fs::write(&main, "Title: 缓存\n\n{include: acts/act1.fountain}\n").unwrap();
+    fs::write(
+        dir.join("acts/act1.fountain"),
+        "INT. 客厅 - 日\n\n阿强走进来。\n",
+    )
+    .unwrap();
+    let config = Conf::default();
+    let engine = CachedEngine::with_dir(dir.join("cache"));
+
+    // 被引用的文件没有变化时命中
+    let first = engine.parse_file(&main, &config).unwrap();
+    assert!(Arc::ptr_eq(
+        &first,
+        &engine.parse_file(&main, &config).unwrap()
+    ));
+    let html = engine.export_file(&main, &config, "html").await.unwrap();
+    assert!(Arc::ptr_eq(
+        &html,
+        &engine.export_file(&main, &config, "html").await.unwrap()
+    ));
+    let misses = engine.stats().misses;
+
+    // 修改被引用的文件后，解析和导出都不再命中
+    fs::write(
+        dir.join("acts/act1.fountain"),
+        "INT. 客厅 - 日\n\n阿珍坐着。\n",
+    )
+    .unwrap();
+    let changed = engine.parse_file(&main, &config).unwrap();
+    assert!(changed.tokens.iter().any(|t| t.text == "阿珍坐着。"));
+    assert_eq!(engine.stats().misses, misses + 1);
+    let changed_html = engine.export_file(&main, &config, "html").await.unwrap();
+    assert!(!Arc::ptr_eq(&html, &changed_html));
+    assert_eq!(engine.stats().misses, misses + 2);
+    assert!(engine
+        .parse_file(&dir.join("缺少.fountain"), &config)
+        .is_err());
+
+    // 插图修改后导出不再命中，解析结果不受影响
+    let image = dir.join("board.png");
+    fs::write(&image, b"first").unwrap();
+    let script = format!("INT. 客厅 - 日\n\n[[img: {}]]\n", image.display());
+    let exported = engine.export(&script, &config, "html").await.unwrap();
+    assert!(Arc::ptr_eq(
+        &exported,
+        &engine.export(&script, &config, "html").await.unwrap()
+    ));
+    let misses = engine.stats().misses;
+    fs::write(&image, b"second").unwrap();
+    let exported_again = engine.export(&script, &config, "html").await.unwrap();
+    assert!(!Arc::ptr_eq(&exported, &exported_again));
+    assert_eq!(engine.stats().misses, misses + 1);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,21 @@
+use betterfountain_rust::cast::{cast_list, cast_page_text};
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_cast_list_in_order_of_appearance() {
+    let script = "INT. 客厅 - 日\n\n@阿珍\n你回来了。\n\n@阿强\n嗯。\n\nEXT. 花园 - 夜\n\n@阿强\n好冷。\n\n@老王\n进屋吧。\n";
+    let output = parse(script, &Conf::default(), false, None);
+    let cast = cast_list(&output);
+
+    let names: Vec<&str> = cast.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["阿珍", "阿强", "老王"]);
+    assert_eq!(cast[1].first_scene.as_deref(), Some("1"));
+    assert_eq!(cast[1].scene_count, 2);
+    assert_eq!(cast[2].first_scene.as_deref(), Some("2"));
+    assert_eq!(cast[2].first_line, 13);
+
+    assert_eq!(
+        cast_page_text(&cast),
+        "CAST OF CHARACTERS\n\n阿珍 (SC. 1)\n阿强 (SC. 1)\n老王 (SC. 2)\n"
+    );
+}
@@ -0,0 +1,54 @@
+use betterfountain_rust::characters::CharacterIndex;
+use betterfountain_rust::utils::strip_style_chars;
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_character_index() {
+    let script = r#"Title: 角色测试
+Metadata: {"aliases": {"阿强哥": "阿强"}}
+
+INT. 客厅 - 日
+
+@阿珍
+你回来了。
+
+@阿强 (V.O.)
+(小声)
+嗯。
+
+@阿珍
+吃饭了吗？
+
+@阿强哥 ^
+还没。
+"#;
+    let output = parse(script, &Conf::default(), false, None);
+    let index = CharacterIndex::new(&output);
+
+    let names: Vec<&str> = index.characters().iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["阿珍", "阿强"]);
+
+    let aqiang = index.get("阿强哥").unwrap();
+    assert_eq!(aqiang.name, "阿强");
+    assert_eq!(aqiang.extensions, vec!["V.O."]);
+    assert_eq!(aqiang.cue_count, 2);
+    assert_eq!(aqiang.dual_count, 1);
+    assert_eq!((aqiang.first_line, aqiang.last_line), (Some(8), Some(15)));
+    assert_eq!(aqiang.aliases, vec!["阿强哥"]);
+
+    let lines: Vec<(String, String)> = index
+        .dialogue("阿强")
+        .iter()
+        .map(|t| (t.token_type.clone(), strip_style_chars(&t.text)))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            ("parenthetical", "(小声)"),
+            ("dialogue", "嗯。"),
+            ("dialogue", "还没。")
+        ]
+        .map(|(t, text)| (t.to_string(), text.to_string()))
+    );
+    assert!(index.dialogue("老王").is_empty());
+}
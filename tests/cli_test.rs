@@ -0,0 +1,244 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const SCRIPT: &str = "INT. 客厅 - 日\n\n阿强走进来。\n";
+
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "betterfountain-cli-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn bf(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_bf"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_export_txt_keeps_input() {
+    let dir = work_dir("txt");
+    let input = dir.join("script.txt");
+    fs::write(&input, SCRIPT).unwrap();
+    let input = input.to_string_lossy().to_string();
+
+    // 默认输出路径就是输入文件，拒绝导出
+    let output = bf(&["export", &input, "--format", "txt"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("输出文件与输入文件相同"));
+    assert_eq!(fs::read_to_string(&input).unwrap(), SCRIPT);
+
+    // 用 --out 指向输入文件也一样
+    let output = bf(&["export", &input, "--format", "txt", "--out", &input]);
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(&input).unwrap(), SCRIPT);
+
+    // 指定其他文件时正常导出
+    let out = dir.join("script.typewriter.txt");
+    let output = bf(&[
+        "export",
+        &input,
+        "--format",
+        "txt",
+        "--out",
+        &out.to_string_lossy(),
+    ]);
+    assert!(output.status.success());
+    assert!(fs::read_to_string(&out).unwrap().contains("阿强走进来。"));
+    assert_eq!(fs::read_to_string(&input).unwrap(), SCRIPT);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_export_fadein_keeps_input() {
+    let dir = work_dir("fadein");
+    let script = dir.join("script.txt");
+    fs::write(&script, SCRIPT).unwrap();
+    let input = dir.join("script.fadein");
+    let input = input.to_string_lossy().to_string();
+    let output = bf(&[
+        "export",
+        &script.to_string_lossy(),
+        "--format",
+        "fadein",
+        "--out",
+        &input,
+    ]);
+    assert!(output.status.success());
+    let original = fs::read(&input).unwrap();
+
+    // 从 fadein 导出 fadein 时默认输出路径就是输入文件，拒绝导出
+    let output = bf(&["export", &input, "--format", "fadein"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("输出文件与输入文件相同"));
+    assert_eq!(fs::read(&input).unwrap(), original);
+
+    let out = dir.join("copy.fadein");
+    let output = bf(&[
+        "export",
+        &input,
+        "--format",
+        "fadein",
+        "--out",
+        &out.to_string_lossy(),
+    ]);
+    assert!(output.status.success());
+    assert!(out.exists());
+    assert_eq!(fs::read(&input).unwrap(), original);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_export_formats() {
+    let output = bf(&["export", "--help"]);
+    assert!(output.status.success());
+    let help = String::from_utf8_lossy(&output.stdout);
+    assert!(help.contains("docx"));
+    assert!(help.contains("pdf"));
+
+    // 导出 PDF，默认输出路径替换扩展名
+    let dir = work_dir("pdf");
+    let input = dir.join("script.fountain");
+    fs::write(&input, SCRIPT).unwrap();
+    let output = bf(&["export", &input.to_string_lossy(), "--format", "pdf"]);
+    assert!(output.status.success());
+    let pdf = fs::read(dir.join("script.pdf")).unwrap();
+    assert!(pdf.starts_with(b"%PDF-"));
+    assert!(pdf.ends_with(b"%%EOF\n"));
+
+    // 从标准输入导出时必须指定输出文件
+    let output = bf(&["export", "-", "--format", "pdf"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--out"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_export_batch() {
+    let dir = work_dir("batch");
+    let a = dir.join("a.fountain");
+    let b = dir.join("b.fountain");
+    fs::write(&a, SCRIPT).unwrap();
+    fs::write(&b, SCRIPT).unwrap();
+    fs::write(dir.join("notes.txt"), SCRIPT).unwrap();
+    let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+
+    // 多个输入各自输出到所在目录
+    let output = bf(&["export", &a, &b, "--format", "pdf"]);
+    assert!(output.status.success());
+    assert!(dir.join("a.pdf").exists());
+    assert!(dir.join("b.pdf").exists());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("共 2 个文件，成功 2，失败 0"));
+
+    // 通配符只匹配 .fountain 文件，--out-dir 不存在时自动创建
+    let glob = dir.join("*.fountain").to_string_lossy().to_string();
+    let out_dir = dir.join("out");
+    let out = out_dir.to_string_lossy();
+    let output = bf(&["export", &glob, "--format", "pdf", "--out-dir", &out]);
+    assert!(output.status.success());
+    assert!(out_dir.join("a.pdf").exists());
+    assert!(out_dir.join("b.pdf").exists());
+    assert!(!out_dir.join("notes.pdf").exists());
+
+    // 有一个文件无法读取时其余照常导出，汇总表列出结果和页数，退出码为 1
+    let _ = fs::remove_dir_all(&out_dir);
+    fs::write(dir.join("c.fountain"), b"\xff\xfe\xff").unwrap();
+    let output = bf(&["export", &glob, "--format", "pdf", "--out-dir", &out]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(out_dir.join("a.pdf").exists());
+    assert!(out_dir.join("b.pdf").exists());
+    assert!(!out_dir.join("c.pdf").exists());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let row = |name: &str| {
+        stderr
+            .lines()
+            .find(|l| l.contains(name))
+            .unwrap()
+            .to_string()
+    };
+    assert!(row("a.fountain").contains("成功"));
+    assert!(row("a.fountain").trim_end().ends_with(" 1"));
+    assert!(row("c.fountain").contains("失败"));
+    assert!(stderr.contains("共 3 个文件，成功 2，失败 1"));
+
+    // 没有匹配的文件时报错
+    let missing = dir.join("*.spmd").to_string_lossy().to_string();
+    let output = bf(&["export", &missing, "--format", "pdf"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("没有匹配"));
+
+    // 目录部分不支持通配符
+    let nested = dir
+        .join("*")
+        .join("a.fountain")
+        .to_string_lossy()
+        .to_string();
+    let output = bf(&["export", &nested, "--format", "pdf"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("通配符只能用于文件名部分"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// 等待 `path` 的内容满足 `done`，最多等十秒
+fn wait_for(path: &std::path::Path, done: impl Fn(&str) -> bool) -> bool {
+    for _ in 0..100 {
+        if fs::read_to_string(path).is_ok_and(|content| done(&content)) {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    false
+}
+
+#[test]
+fn test_watch_reexports() {
+    let dir = work_dir("watch");
+    let input = dir.join("script.fountain");
+    fs::write(&input, SCRIPT).unwrap();
+    let out_dir = dir.join("out");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bf"))
+        .args(["watch", &input.to_string_lossy()])
+        .args(["--format", "html", "--format", "txt"])
+        .args(["--out-dir", &out_dir.to_string_lossy()])
+        .args(["--debounce-ms", "50"])
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // 启动时先导出一次所有格式，修改后重新导出
+    let html = out_dir.join("script.html");
+    let txt = out_dir.join("script.txt");
+    let started = wait_for(&html, |c| c.contains("阿强走进来。"))
+        && wait_for(&txt, |c| c.contains("阿强走进来。"));
+    if started {
+        fs::write(&input, SCRIPT.replace("阿强走进来。", "阿珍坐着。")).unwrap();
+    }
+    let updated = started
+        && wait_for(&html, |c| c.contains("阿珍坐着。"))
+        && wait_for(&txt, |c| c.contains("阿珍坐着。"));
+
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = fs::remove_dir_all(&dir);
+    assert!(started);
+    assert!(updated);
+}
+
+#[test]
+fn test_watch_missing_file() {
+    let output = bf(&["watch", "不存在.fountain"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("找不到文件"));
+}
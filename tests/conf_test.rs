@@ -0,0 +1,132 @@
+use betterfountain_rust::models::{Conf, ConfError};
+
+#[test]
+fn test_conf_merge_toml_and_overrides() {
+    let toml = r#"
+print_notes = false
+scenes_numbers = "left"
+dial_sec_per_char = 0.25
+
+[print_profile]
+font_size = 14.0
+"#;
+    let conf = Conf::default().merge_toml_str(toml).unwrap();
+    assert!(!conf.print_notes);
+    assert_eq!(conf.scenes_numbers, "left");
+    assert_eq!(conf.dial_sec_per_char, 0.25);
+    assert_eq!(conf.print_profile.font_size, 14.0);
+    // 未写出的字段保持默认值
+    assert_eq!(conf.text_contd, Conf::default().text_contd);
+    assert_eq!(
+        conf.print_profile.note_font_size,
+        Conf::default().print_profile.note_font_size
+    );
+
+    let conf = conf
+        .merge_overrides(&[
+            "print_notes=true".to_string(),
+            "print_profile.font_size=12".to_string(),
+            "print_header=第一稿".to_string(),
+        ])
+        .unwrap();
+    assert!(conf.print_notes);
+    assert_eq!(conf.print_profile.font_size, 12.0);
+    assert_eq!(conf.print_header, "第一稿");
+
+    assert!(Conf::default().merge_overrides(&["print_notes".to_string()]).is_err());
+}
+
+#[test]
+fn test_conf_rejects_unknown_keys() {
+    // 拼错的字段名报错，而不是悄悄忽略
+    let err = Conf::default()
+        .merge_overrides(&["print_profile.font_sise=12".to_string()])
+        .unwrap_err();
+    assert!(matches!(err, ConfError::InvalidOverride(ref key) if key == "print_profile.font_sise"));
+    assert!(Conf::default()
+        .merge_overrides(&["print_note=false".to_string()])
+        .is_err());
+    assert!(matches!(
+        Conf::default().merge_toml_str("scene_numbers = \"left\"\n"),
+        Err(ConfError::InvalidOverride(_))
+    ));
+    assert!(matches!(
+        Conf::default().merge_json_str(r#"{"print_profile": {"font_size": 12, "colour": "red"}}"#),
+        Err(ConfError::InvalidOverride(ref key)) if key == "print_profile.colour"
+    ));
+
+    // 映射字段中的键任意
+    let conf = Conf::default()
+        .merge_overrides(&["variables.hero=阿强".to_string()])
+        .unwrap();
+    assert_eq!(conf.variables["hero"], "阿强");
+    let conf = conf
+        .merge_json_str(r#"{"speaking_rates": {"BOB": 1.2}}"#)
+        .unwrap();
+    assert_eq!(conf.speaking_rates["BOB"], 1.2);
+
+    // 直接反序列化时缺少的字段取默认值
+    let conf: Conf = serde_json::from_str(r#"{"print_notes": false}"#).unwrap();
+    assert!(!conf.print_notes);
+    assert_eq!(conf.text_contd, Conf::default().text_contd);
+}
+
+#[test]
+fn test_conf_note_style() {
+    let toml = r##"
+[print_profile.note]
+font = "KaiTi"
+boxed = true
+shading = "#F2F2F2"
+"##;
+    let conf = Conf::default().merge_toml_str(toml).unwrap();
+    let note = &conf.print_profile.note;
+    assert_eq!(note.font.as_deref(), Some("KaiTi"));
+    assert!(note.boxed);
+    assert_eq!(note.shading.as_deref(), Some("#F2F2F2"));
+    // 未写出的字段保持默认值
+    assert!(!note.brackets);
+    assert!(note.italic);
+    assert_eq!(note.color, Conf::default().print_profile.note.color);
+    assert!(note.footnote.separator);
+    assert_eq!(note.footnote.format, "1");
+}
+
+#[test]
+fn test_conf_footnote() {
+    let toml = r#"
+[print_profile.note.footnote]
+format = "i"
+restart_each_page = true
+"#;
+    let conf = Conf::default().merge_toml_str(toml).unwrap();
+    let footnote = &conf.print_profile.note.footnote;
+    assert_eq!(footnote.format, "i");
+    assert!(footnote.restart_each_page);
+    assert!(footnote.separator);
+    assert_eq!(footnote.line_spacing, None);
+}
+
+#[test]
+fn test_conf_section_levels() {
+    let toml = r#"
+[[print_profile.section.levels]]
+size = 16.0
+bold = true
+page_break_before = true
+
+[[print_profile.section.levels]]
+spacing_before = 12.0
+"#;
+    let conf = Conf::default().merge_toml_str(toml).unwrap();
+    let section = &conf.print_profile.section;
+    let act = section.level(1).unwrap();
+    assert_eq!(act.size, Some(16.0));
+    assert!(act.bold && act.page_break_before);
+    let sequence = section.level(2).unwrap();
+    assert_eq!(sequence.spacing_before, Some(12.0));
+    assert!(!sequence.page_break_before);
+    // 没有对应项的层级使用章节的默认样式
+    assert!(section.level(3).is_none());
+    assert!(section.level(0).is_none());
+}
@@ -0,0 +1,80 @@
+use betterfountain_rust::diff::{compare, revised_draft, ChangeKind};
+use betterfountain_rust::utils::strip_style_chars;
+use betterfountain_rust::{parse, Conf};
+
+const OLD: &str = "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿珍\n你回来了。\n\nEXT. 花园 - 夜\n\n月光洒在草地上。\n\nINT. 厨房 - 夜\n\n水壶响了。\n";
+const NEW: &str = "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿珍\n你终于回来了。\n\nINT. 厨房 - 夜\n\n水壶响了。\n\nEXT. 天台 - 夜\n\n风很大。\n";
+
+#[test]
+fn test_compare_drafts() {
+    let config = Conf::default();
+    let diff = compare(
+        &parse(OLD, &config, false, None),
+        &parse(NEW, &config, false, None),
+    );
+
+    let kinds: Vec<_> = diff
+        .scenes
+        .iter()
+        .map(|s| (s.kind, s.heading.as_str()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (ChangeKind::Modified, "INT. 客厅 - 日"),
+            (ChangeKind::Removed, "EXT. 花园 - 夜"),
+            (ChangeKind::Added, "EXT. 天台 - 夜"),
+        ]
+    );
+
+    let lines = &diff.scenes[0].lines;
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].kind, ChangeKind::Removed);
+    assert_eq!(lines[1].text, "你终于回来了。");
+    assert_eq!(lines[1].character.as_deref(), Some("阿珍"));
+
+    // 新稿中改动的对白和新增场景的各行
+    assert_eq!(diff.changed_lines(), vec![5, 11, 13]);
+    assert!(diff.to_json().contains("\"kind\": \"removed\""));
+}
+
+#[test]
+fn test_revised_draft() {
+    let config = Conf::default();
+    let old = parse(OLD, &config, false, None);
+    let new = parse(NEW, &config, false, None);
+    let revised = revised_draft(&old, &new);
+
+    let texts: Vec<String> = revised
+        .output
+        .tokens
+        .iter()
+        .map(|t| strip_style_chars(&t.text).trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    // 删除的对白插在修改后的对白之前，删除的场景插在下一个保留的场景之前
+    let expected = [
+        "INT. 客厅 - 日",
+        "阿强走进来。",
+        "阿珍",
+        "你回来了。",
+        "你终于回来了。",
+        "EXT. 花园 - 夜",
+        "月光洒在草地上。",
+        "INT. 厨房 - 夜",
+    ];
+    assert_eq!(&texts[..expected.len()], &expected);
+
+    let removed: Vec<String> = revised
+        .output
+        .tokens
+        .iter()
+        .filter(|t| revised.revisions.get(&t.line) == Some(&ChangeKind::Removed))
+        .map(|t| strip_style_chars(&t.text))
+        .collect();
+    assert_eq!(
+        removed,
+        vec!["你回来了。", "EXT. 花园 - 夜", "月光洒在草地上。"]
+    );
+    assert_eq!(revised.revisions.get(&5), Some(&ChangeKind::Added));
+}
@@ -2,14 +2,64 @@ use betterfountain_rust::models::Conf;
 use betterfountain_rust::parser::fountain_parser::FountainParser;
 use betterfountain_rust::docx::docx::generate_docx_document;
 use std::fs;
+use std::io::Read;
 use std::path::Path;
 
+// 解析剧本并导出为 DOCX 文件内容
+async fn export_docx(script: &str, config: &Conf) -> Vec<u8> {
+    use betterfountain_rust::docx::generate_docx_bytes;
+
+    let result = FountainParser::new().parse(script, config, false, None);
+    generate_docx_bytes(config, &result).await.unwrap()
+}
+
+// DOCX 包中名为 `name` 的部件
+fn docx_part(docx: &[u8], name: &str) -> String {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(docx)).unwrap();
+    let mut xml = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut xml)
+        .unwrap();
+    xml
+}
+
+// DOCX 包中名称以 `prefix` 开头的部件，按名称排序
+fn docx_parts(docx: &[u8], prefix: &str) -> Vec<String> {
+    let archive = zip::ZipArchive::new(std::io::Cursor::new(docx)).unwrap();
+    let mut names: Vec<&str> = archive
+        .file_names()
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort();
+    names.iter().map(|name| docx_part(docx, name)).collect()
+}
+
+// 各节 w:pgNumType 的起始页码，接续上一节（没有 w:start）的不计
+fn page_number_starts(document: &str) -> Vec<String> {
+    document
+        .split("<w:pgNumType")
+        .skip(1)
+        .filter_map(|rest| {
+            let element = &rest[..rest.find('>').unwrap()];
+            let start = element.split("w:start=\"").nth(1)?;
+            Some(start[..start.find('"').unwrap()].to_string())
+        })
+        .collect()
+}
+
+// 导出 DOCX 后的正文 word/document.xml
+async fn document_xml(script: &str, config: &Conf) -> String {
+    docx_part(&export_docx(script, config).await, "word/document.xml")
+}
+
 #[tokio::test]
 async fn test_docx_export() {
     eprintln!("测试开始");
 
     // 创建解析器
-    let mut parser = FountainParser::new();
+    let parser = FountainParser::new();
     eprintln!("解析器创建成功");
 
     // 读取中文测试文件
@@ -61,3 +111,1030 @@ async fn test_docx_export() {
 
     eprintln!("DOCX 导出成功: {}", output_path);
 }
+
+#[tokio::test]
+async fn test_export_timings() {
+    use betterfountain_rust::api::export_to_docx_base64;
+
+    let script = "Title: 计时\n\nINT. 客厅 - 日\n\n阿强走进来。\n";
+    let result = export_to_docx_base64(script.to_string(), None).await;
+    assert!(result.success, "{}", result.message);
+    // 排版和保存都有耗时，总耗时是各阶段之和
+    let timings = result.timings;
+    assert!(timings.layout > std::time::Duration::ZERO);
+    assert!(timings.save > std::time::Duration::ZERO);
+    assert_eq!(
+        timings.total(),
+        timings.parse + timings.lines + timings.layout + timings.save
+    );
+}
+
+#[tokio::test]
+async fn test_cancelled_export() {
+    use betterfountain_rust::docx::{generate_docx_document_with_progress, DocxGenerateError};
+    use betterfountain_rust::progress::{CancellationToken, Progress};
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n";
+    let result = FountainParser::new().parse(script, &Conf::default(), false, None);
+    let output_path = "tests/test_data_out/取消导出.docx";
+    let _ = fs::remove_file(output_path);
+
+    // 已取消的导出返回 Cancelled，不写出文件
+    let token = CancellationToken::new();
+    token.cancel();
+    let progress = Progress::new().with_token(token);
+    let docx_result =
+        generate_docx_document_with_progress(output_path, &Conf::default(), &result, progress)
+            .await;
+    assert!(matches!(docx_result, Err(DocxGenerateError::Cancelled(_))));
+    assert!(!Path::new(output_path).exists());
+}
+
+#[tokio::test]
+async fn test_write_docx_to_writer() {
+    use betterfountain_rust::docx::{build_docx, write_docx, DocxOptions};
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿强\n你好。\n";
+    let result = FountainParser::new().parse(script, &Conf::default(), false, None);
+    let options = DocxOptions {
+        parsed: Some(result),
+        ..Default::default()
+    };
+
+    // 写入内存缓冲区，不产生文件
+    let mut buffer: Vec<u8> = Vec::new();
+    let stats = write_docx(options.clone(), &mut buffer).await.unwrap();
+    assert!(buffer.starts_with(b"PK"));
+    assert!(stats.page_count >= 1);
+
+    // 同步接口得到同样的统计信息
+    let built = build_docx(&options).unwrap();
+    assert!(built.data.starts_with(b"PK"));
+    assert_eq!(built.stats.page_count, stats.page_count);
+}
+
+#[tokio::test]
+async fn test_export_to_bytes() {
+    use betterfountain_rust::docx::{
+        generate_docx_bytes, generate_html_bytes, generate_odt_bytes, validate_docx,
+    };
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿强\n你好。\n";
+    let config = Conf::default();
+    let result = FountainParser::new().parse(script, &config, false, None);
+
+    let docx = generate_docx_bytes(&config, &result).await.unwrap();
+    assert!(docx.starts_with(b"PK"));
+    assert_eq!(validate_docx(&docx), vec![]);
+
+    let odt = generate_odt_bytes(&config, &result).await.unwrap();
+    assert!(odt.starts_with(b"PK"));
+
+    let html = String::from_utf8(generate_html_bytes(&config, &result).await.unwrap()).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("你好。"));
+}
+
+#[tokio::test]
+async fn test_backends_match_export_paths() {
+    use betterfountain_rust::docx::{
+        generate_bytes_with_backend, generate_document_with_backend, generate_docx_bytes,
+        generate_html_bytes, generate_odt_bytes, generate_odt_document,
+    };
+    use betterfountain_rust::render::{backend_for, DocxBackend, HtmlBackend, OdtBackend};
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。[[灯没开]]\n\n@阿强\n你好。\n\n===\n\nEXT. 花园 - 夜\n\n阿珍坐着。\n";
+    let config = Conf {
+        deterministic: true,
+        ..Conf::default()
+    };
+    let result = FountainParser::new().parse(script, &config, false, None);
+    let dir = std::env::temp_dir().join(format!("betterfountain-backend-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    // DOCX：写文件的 generate_docx_document、内存中的 generate_docx_bytes 和显式的 DocxBackend 字节相同
+    let docx = generate_docx_bytes(&config, &result).await.unwrap();
+    let path = dir.join("script.docx");
+    let path = path.to_string_lossy();
+    generate_docx_document(&path, &config, &result)
+        .await
+        .unwrap();
+    assert_eq!(fs::read(path.as_ref()).unwrap(), docx);
+    let rendered = generate_bytes_with_backend(&config, &result, Box::new(DocxBackend))
+        .await
+        .unwrap();
+    assert_eq!(rendered, docx);
+    let rendered = generate_bytes_with_backend(&config, &result, backend_for("docx").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(rendered, docx);
+
+    // ODT：压缩包条目带有打包时间，比较各部件的内容
+    let odt = generate_odt_bytes(&config, &result).await.unwrap();
+    let path = dir.join("script.odt");
+    let path = path.to_string_lossy();
+    generate_odt_document(&path, &config, &result)
+        .await
+        .unwrap();
+    let written = fs::read(path.as_ref()).unwrap();
+    let rendered = generate_bytes_with_backend(&config, &result, Box::new(OdtBackend))
+        .await
+        .unwrap();
+    for part in [
+        "mimetype",
+        "content.xml",
+        "styles.xml",
+        "META-INF/manifest.xml",
+    ] {
+        let expected = docx_part(&odt, part);
+        assert_eq!(docx_part(&written, part), expected, "{}", part);
+        assert_eq!(docx_part(&rendered, part), expected, "{}", part);
+    }
+    assert!(docx_part(&odt, "content.xml").contains("阿珍坐着。"));
+
+    // HTML
+    let html = generate_html_bytes(&config, &result).await.unwrap();
+    let path = dir.join("script.html");
+    let path = path.to_string_lossy();
+    generate_document_with_backend(&path, &config, &result, Box::new(HtmlBackend))
+        .await
+        .unwrap();
+    assert_eq!(fs::read(path.as_ref()).unwrap(), html);
+    let rendered = generate_bytes_with_backend(&config, &result, backend_for("html").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(rendered, html);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_deterministic_export() {
+    use betterfountain_rust::docx::generate_docx_bytes;
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。[[灯没开]]\n\n@阿强\n你好。\n";
+    let config = Conf {
+        deterministic: true,
+        notes_as_word_comments: true,
+        ..Conf::default()
+    };
+    let result = FountainParser::new().parse(script, &config, false, None);
+
+    let first = generate_docx_bytes(&config, &result).await.unwrap();
+    let second = generate_docx_bytes(&config, &result).await.unwrap();
+    assert_eq!(first, second);
+    assert!(docx_part(&first, "docProps/core.xml").contains("1980-01-01T00:00:00Z"));
+    assert!(docx_part(&first, "word/comments.xml").contains("1980-01-01T00:00:00Z"));
+
+    // 指定时间后日期都使用这个时间，不需要另外打开 deterministic
+    let config = Conf {
+        source_date_epoch: Some(1_700_000_000),
+        notes_as_word_comments: true,
+        ..Conf::default()
+    };
+    let epoch = generate_docx_bytes(&config, &result).await.unwrap();
+    assert_eq!(epoch, generate_docx_bytes(&config, &result).await.unwrap());
+    assert_ne!(epoch, first);
+    assert!(docx_part(&epoch, "docProps/core.xml").contains("2023-11-14T22:13:20Z"));
+    assert!(docx_part(&epoch, "word/comments.xml").contains("2023-11-14T22:13:20Z"));
+}
+
+#[tokio::test]
+async fn test_word_comments() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。[[灯没开]]他坐下。\n";
+    let config = Conf {
+        notes_as_word_comments: true,
+        ..Conf::default()
+    };
+    let docx = export_docx(script, &config).await;
+
+    // 注释内容只在 word/comments.xml 中，正文不留标记
+    let document = docx_part(&docx, "word/document.xml");
+    assert!(!document.contains("灯没开"));
+    let start = document.find("<w:commentRangeStart").unwrap();
+    let id = document[start..].split("w:id=\"").nth(1).unwrap();
+    let id = &id[..id.find('"').unwrap()];
+    let comments = docx_part(&docx, "word/comments.xml");
+    assert!(comments.contains(&format!("<w:comment w:id=\"{}\"", id)));
+    assert!(comments.contains("灯没开"));
+
+    // 批注范围只包住注释所在的位置：前后的文字在范围之外，引用紧跟在范围结束之后
+    let end = document
+        .find(&format!("<w:commentRangeEnd w:id=\"{}\"", id))
+        .unwrap();
+    let reference = document
+        .find(&format!("<w:commentReference w:id=\"{}\"", id))
+        .unwrap();
+    assert!(start < end && end < reference);
+    assert!(!document[start..end].contains("<w:t"));
+    assert!(!document[end..reference].contains("<w:t"));
+    assert!(document[..start].contains("阿强走进来。"));
+    assert!(document[reference..].contains("他坐下。"));
+
+    // 批注部件在关系和内容类型中都有登记
+    let rels = docx_part(&docx, "word/_rels/document.xml.rels");
+    assert!(rels.contains(
+        "Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/comments\""
+    ));
+    assert!(rels.contains("Target=\"comments.xml\""));
+    assert!(docx_part(&docx, "[Content_Types].xml").contains("PartName=\"/word/comments.xml\""));
+}
+
+#[tokio::test]
+async fn test_tracked_changes() {
+    use betterfountain_rust::docx::generate_docx_revisions;
+
+    let config = Conf {
+        source_date_epoch: Some(1_700_000_000),
+        ..Conf::default()
+    };
+    let parser = FountainParser::new();
+    let old = parser.parse(
+        "INT. 客厅 - 日\n\n阿强走进来。\n\n阿珍坐着。\n",
+        &config,
+        false,
+        None,
+    );
+    let new = parser.parse(
+        "INT. 客厅 - 日\n\n阿强走进来。\n\n阿明跑出去。\n",
+        &config,
+        false,
+        None,
+    );
+    let path = std::env::temp_dir().join(format!("revisions-{}.docx", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    generate_docx_revisions(&path, &config, &old, &new, "审阅者")
+        .await
+        .unwrap();
+    let docx = fs::read(&path).unwrap();
+    let _ = fs::remove_file(&path);
+
+    // 新增的段落在 w:ins 中，删除的段落在 w:del 中并以 w:delText 保留原文，都带有修订者和时间
+    let document = docx_part(&docx, "word/document.xml");
+    let element = |tag: &str, text: &str| {
+        document
+            .split(&format!("<w:{} ", tag))
+            .skip(1)
+            .filter_map(|rest| Some(&rest[..rest.find(&format!("</w:{}>", tag))?]))
+            .find(|element| element.contains(text))
+            .map(str::to_string)
+    };
+    let inserted = element("ins", "阿明跑出去。").unwrap();
+    let deleted = element("del", "阿珍坐着。").unwrap();
+    for revision in [&inserted, &deleted] {
+        assert!(revision.contains("w:author=\"审阅者\""));
+        assert!(revision.contains("w:date=\"2023-11-14T22:13:20Z\""));
+    }
+    assert!(deleted.contains("<w:delText"));
+    assert!(!inserted.contains("<w:delText"));
+
+    // 没有改动的段落不标记为修订
+    assert!(element("ins", "阿强走进来。").is_none());
+    assert!(element("del", "阿强走进来。").is_none());
+}
+
+#[tokio::test]
+async fn test_footnote_layout() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。[[灯没开]]\n";
+    let mut config = Conf::default();
+    config.print_profile.note.footnote.format = "※".to_string();
+    config.print_profile.note.footnote.restart_each_page = true;
+    config.print_profile.note.footnote.separator = false;
+    let docx = export_docx(script, &config).await;
+    let document = docx_part(&docx, "word/document.xml");
+    assert!(document.contains("w:customMarkFollows=\"1\""));
+    assert!(document.contains("<w:numRestart w:val=\"eachPage\" />"));
+    assert!(docx_part(&docx, "word/footnotes.xml").contains("w:type=\"separator\""));
+}
+
+#[tokio::test]
+async fn test_section_header_page_numbers() {
+    let script = "# 第一幕\n\nINT. 客厅 - 日\n\n阿强走进来。\n\n===\n\n# 第二幕\n\nEXT. 花园 - 夜\n\n阿珍坐着。\n";
+    let config = Conf {
+        print_sections: true,
+        print_section_header: true,
+        ..Conf::default()
+    };
+    let docx = export_docx(script, &config).await;
+    let document = docx_part(&docx, "word/document.xml");
+    // 每一幕的页眉各成一节，页码只在第一节从 1 开始，第二幕接续
+    assert_eq!(document.matches("<w:sectPr").count(), 2);
+    assert_eq!(page_number_starts(&document), ["1"]);
+    let headers = docx_parts(&docx, "word/header");
+    assert_eq!(headers.iter().filter(|h| h.contains("第一幕")).count(), 1);
+    assert_eq!(headers.iter().filter(|h| h.contains("第二幕")).count(), 1);
+}
+
+#[tokio::test]
+async fn test_header_fields_split_sections() {
+    // 第二页沿用第一页的场景，页眉相同，不分节；第三页换了场景，另起一节
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\n===\n\n他坐下。\n\n===\n\nEXT. 花园 - 夜\n\n阿珍坐着。\n";
+    let config = Conf {
+        print_header: "{scene}".to_string(),
+        ..Conf::default()
+    };
+    let docx = export_docx(script, &config).await;
+    let document = docx_part(&docx, "word/document.xml");
+    assert_eq!(document.matches("<w:sectPr").count(), 2);
+    assert_eq!(document.matches("w:type=\"page\"").count(), 1);
+    assert_eq!(page_number_starts(&document), ["1"]);
+    let headers = docx_parts(&docx, "word/header");
+    assert_eq!(headers.iter().filter(|h| h.contains("客厅")).count(), 1);
+    assert_eq!(headers.iter().filter(|h| h.contains("花园")).count(), 1);
+
+    // 页眉不含随页变化的字段时不拆分
+    let config = Conf {
+        print_header: "{title}".to_string(),
+        ..Conf::default()
+    };
+    let document = document_xml(script, &config).await;
+    assert_eq!(document.matches("<w:sectPr").count(), 1);
+    assert_eq!(document.matches("w:type=\"page\"").count(), 2);
+}
+
+#[tokio::test]
+async fn test_continued_page_numbers() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\n===\n\n他坐下。\n\n===\n\n阿珍进来。\n";
+    let config = Conf {
+        page_number_start: 5,
+        hide_first_page_number: true,
+        ..Conf::default()
+    };
+    let docx = export_docx(script, &config).await;
+    let document = docx_part(&docx, "word/document.xml");
+    // 第一页单独成节，页脚不显示页码但从 5 开始计数，之后的节接续，不再重新编号
+    assert_eq!(document.matches("<w:sectPr").count(), 2);
+    assert_eq!(page_number_starts(&document), ["5"]);
+    let footers = docx_parts(&docx, "word/footer");
+    assert_eq!(footers.iter().filter(|f| f.contains("PAGE")).count(), 1);
+}
+
+#[tokio::test]
+async fn test_note_links() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。[[灯没开]]\n";
+    let config = Conf {
+        note_position_end: true,
+        ..Conf::default()
+    };
+    let document = document_xml(script, &config).await;
+    // 正文中的编号链接到尾注，尾注的编号链接回正文
+    assert!(document.contains("w:anchor=\"_Note1\""));
+    assert!(document.contains("w:name=\"_Note1\""));
+    assert!(document.contains("w:anchor=\"_NoteRef1\""));
+    assert!(document.contains("w:name=\"_NoteRef1\""));
+}
+
+#[tokio::test]
+async fn test_toc_links() {
+    let script = "# 第一幕\n\nINT. 客厅 - 日\n\n阿强走进来。\n\nEXT. 街道 - 夜\n\n下雨。\n";
+    let config = Conf {
+        print_toc: true,
+        print_sections: true,
+        ..Conf::default()
+    };
+    let document = document_xml(script, &config).await;
+    // 章节和场景各有书签，目录条目链接到它们
+    assert!(document.contains("目录"));
+    for name in ["_Section1", "_Scene1", "_Scene2"] {
+        assert!(document.contains(&format!("w:name=\"{}\"", name)));
+        assert!(document.contains(&format!("w:anchor=\"{}\"", name)));
+    }
+}
+
+#[tokio::test]
+async fn test_hyperlinks() {
+    use betterfountain_rust::utils::find_links;
+
+    let links = find_links("详见www.example.com，或写信到 a_b@example.cn。");
+    let urls: Vec<&str> = links.iter().map(|l| l.url.as_str()).collect();
+    assert_eq!(urls, ["http://www.example.com", "mailto:a_b@example.cn"]);
+
+    let script = "INT. 客厅 - 日\n\n详见 www.example.com/a_b，或写信到 team@example.cn。\n";
+    let config = Conf::default();
+    let docx = export_docx(script, &config).await;
+    let document = docx_part(&docx, "word/document.xml");
+    let rels = docx_part(&docx, "word/_rels/document.xml.rels");
+    assert_eq!(document.matches("<w:hyperlink").count(), 2);
+    // 链接中的下划线按原样显示，不作为样式标记
+    assert!(document.contains("www.example.com/a_b"));
+    assert!(rels.contains("Target=\"http://www.example.com/a_b\""));
+    assert!(rels.contains("Target=\"mailto:team@example.cn\""));
+}
+
+#[tokio::test]
+async fn test_shot_cut_banner() {
+    let script = "INT. 客厅 - 日\n\n阿强在打电话。\n\n> {+ 交切 +} ↓\n\nEXT. 街道 - 日\n\n阿珍边走边接电话。\n\n> {- 交切结束 -} ↑\n";
+    let mut config = Conf::default();
+    config.print_profile.shot_cut.style = "banner".to_string();
+    config.print_profile.shot_cut.auto_label = true;
+    let document = document_xml(script, &config).await;
+    // 开始标记按参与交切的地点生成文字，结束标记保留原文；横幅四周加框
+    assert!(document.contains("INTERCUT – 客厅 / 街道 ↓"));
+    assert!(document.contains("交切结束 ↑"));
+    assert!(!document.contains("(交切"));
+    assert!(document.contains("<w:pBdr>"));
+}
+
+#[tokio::test]
+async fn test_intercut_character_locations() {
+    let script = "INT. 客厅 - 日\n\n@阿强\n喂？\n\n> {+ 交切 +} ↓\n\nEXT. 街道 - 日\n\n@阿珍\n是我。\n\n> {- 交切结束 -} ↑\n\nEXT. 花园 - 夜\n\n@阿强\n晚安。\n";
+    let config = Conf {
+        intercut_character_locations: true,
+        ..Conf::default()
+    };
+    let document = document_xml(script, &config).await;
+    assert!(document.contains("阿强 (客厅)"));
+    assert!(document.contains("阿珍 (街道)"));
+    assert!(!document.contains("(花园)"));
+}
+
+#[tokio::test]
+async fn test_right_scene_numbers() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n";
+    let twip = |inches: f32| (inches * 1440.0) as i32;
+    let tab = |val: &str, pos: i32| format!("<w:tab w:val=\"{}\" w:pos=\"{}\" />", val, pos);
+
+    // 场景标题在左边距之内：左侧编号右对齐到标题前 2 个字符处，标题从左制表位开始；
+    // 右侧编号右对齐到正文右边缘
+    let mut config = Conf::default();
+    config.print_profile.scene_heading.feed = 2.0;
+    let print = config.print_profile.clone();
+    let scene_indent = twip(print.scene_heading.feed - print.left_margin);
+    let gap = twip(2.0 * print.font_width);
+    let edge = twip(print.page_width - print.left_margin - print.right_margin);
+    for mode in ["both", "right", "left"] {
+        config.scenes_numbers = mode.to_string();
+        let document = document_xml(script, &config).await;
+        let (left, right) = (mode != "right", mode != "left");
+        assert_eq!(
+            document.contains(&tab("right", scene_indent - gap)),
+            left,
+            "{}",
+            mode
+        );
+        assert_eq!(
+            document.contains(&tab("left", scene_indent)),
+            left,
+            "{}",
+            mode
+        );
+        assert_eq!(document.contains(&tab("right", edge)), right, "{}", mode);
+        assert_eq!(
+            document.matches("<w:tab />").count(),
+            2 * left as usize + right as usize,
+            "{}",
+            mode
+        );
+        assert!(!document.contains("  INT."), "{}", mode);
+    }
+
+    // 默认配置中场景标题伸出左边距，制表位不能放在页边距之外，左侧编号用空格补齐
+    config.print_profile.scene_heading.feed = print.left_margin - 0.3;
+    config.scenes_numbers = "both".to_string();
+    let document = document_xml(script, &config).await;
+    assert!(document.contains("  1  INT."));
+    assert_eq!(document.matches("<w:tab />").count(), 1);
+    assert!(document.contains(&tab("right", edge)));
+}
+
+#[tokio::test]
+async fn test_transition_tab_stop() {
+    let script = "阿强走进来。\n\nCUT TO:\n\n阿珍坐着。\n";
+    let config = Conf::default();
+    let document = document_xml(script, &config).await;
+
+    // 转场的段落左对齐，文字从制表符跳到转场右边缘（正文右边缘减去动作样式的右缩进）的右对齐制表位
+    let print = &config.print_profile;
+    let twip = |inches: f32| (inches * 1440.0) as i32;
+    let edge = twip(print.page_width - print.left_margin - print.right_margin)
+        - twip(print.action.feed - print.left_margin);
+    assert_eq!(document.matches("<w:tabs>").count(), 1);
+    assert!(document.contains(&format!(
+        "<w:tabs><w:tab w:val=\"right\" w:pos=\"{}\" /></w:tabs>",
+        edge
+    )));
+    assert_eq!(document.matches("<w:tab />").count(), 1);
+    assert!(!document.contains("<w:jc w:val=\"right\" />"));
+}
+
+#[tokio::test]
+async fn test_keep_with_next() {
+    use betterfountain_rust::docx::generate_html_bytes;
+
+    let script = "INT. 客厅 - 日\n\n@阿强\n(低声)\n你好。\n";
+    let config = Conf::default();
+    let document = document_xml(script, &config).await;
+    // 与下一段在同一页：场景标题、标题后的空行、角色名、括号注释；整段不拆开：场景标题、括号注释
+    assert_eq!(document.matches("<w:keepNext").count(), 4);
+    assert_eq!(document.matches("<w:keepLines").count(), 2);
+
+    let result = FountainParser::new().parse(script, &config, false, None);
+    let html = String::from_utf8(generate_html_bytes(&config, &result).await.unwrap()).unwrap();
+    assert_eq!(html.matches("break-after:avoid;").count(), 4);
+    assert_eq!(html.matches("break-inside:avoid;").count(), 2);
+}
+
+#[tokio::test]
+async fn test_pagination_rules() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n他坐下。\n\n@阿强\n你好。\n";
+    let mut config = Conf::default();
+    let rules = &mut config.print_profile.pagination;
+    rules.min_action_lines_after_scene_heading = 2;
+    rules.min_dialogue_lines_after_character = 0;
+    let document = document_xml(script, &config).await;
+    // 场景标题、空行和第一行动作与下一段在同一页，角色名不再约束
+    assert_eq!(document.matches("<w:keepNext").count(), 3);
+    assert_eq!(document.matches("<w:keepLines").count(), 1);
+}
+
+#[test]
+fn test_dual_dialogue_rows() {
+    use betterfountain_rust::docx::docx_maker::dual_dialogue_rows;
+
+    // (类型, 文本)
+    type Item = (&'static str, &'static str);
+    let kind = |item: &Item| item.0;
+    let rows = |left: &[Item], right: &[Item]| -> Vec<Vec<Vec<&'static str>>> {
+        dual_dialogue_rows(&[left, right], true, kind)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|cell| cell.into_iter().map(|item| item.1).collect())
+                    .collect()
+            })
+            .collect()
+    };
+
+    // 两边结构相同时逐段配对
+    let left = [("C", "阿强"), ("P", "(低声)"), ("D", "你好。")];
+    let right = [("C", "阿珍"), ("P", "(笑)"), ("D", "你好。")];
+    assert_eq!(
+        rows(&left, &right),
+        vec![
+            vec![vec!["阿强"], vec!["阿珍"]],
+            vec![vec!["(低声)"], vec!["(笑)"]],
+            vec![vec!["你好。"], vec!["你好。"]],
+        ]
+    );
+
+    // 只有一边有括号注释时，另一边留空，对白仍在同一行
+    let left = [("C", "阿强"), ("D", "你好。")];
+    assert_eq!(
+        rows(&left, &right),
+        vec![
+            vec![vec!["阿强"], vec!["阿珍"]],
+            vec![vec![], vec!["(笑)"]],
+            vec![vec!["你好。"], vec!["你好。"]],
+        ]
+    );
+    let right = [("C", "阿珍"), ("D", "嗯。"), ("D", "回来了？")];
+    assert_eq!(
+        rows(&left, &right),
+        vec![
+            vec![vec!["阿强"], vec!["阿珍"]],
+            vec![vec!["你好。"], vec!["嗯。"]],
+            vec![vec![], vec!["回来了？"]],
+        ]
+    );
+
+    // 不平衡时每栏一个单元格
+    assert_eq!(
+        dual_dialogue_rows(&[&left[..], &right[..]], false, kind),
+        vec![vec![left.to_vec(), right.to_vec()]]
+    );
+}
+
+#[tokio::test]
+async fn test_balanced_dual_dialogue() {
+    let script = "INT. 客厅 - 日\n\n@阿强\n你好。\n\n@阿珍 ^\n(笑)\n你也好。\n";
+    let config = Conf {
+        balance_dual_dialogue: true,
+        ..Conf::default()
+    };
+    let document = document_xml(script, &config).await;
+    let rows: Vec<&str> = document.split("<w:tr>").skip(1).collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].contains("阿强") && rows[0].contains("阿珍"));
+    assert!(rows[1].contains("(笑)") && !rows[1].contains("你好。"));
+    assert!(rows[2].contains("你好。") && rows[2].contains("你也好。"));
+}
+
+#[tokio::test]
+async fn test_print_metadata_flags() {
+    use betterfountain_rust::cast::CAST_TITLE;
+    use betterfountain_rust::docx::metadata_extractor::parse_metadata_bool;
+
+    assert_eq!(parse_metadata_bool("true"), Some(true));
+    assert_eq!(parse_metadata_bool("false"), Some(false));
+    assert_eq!(parse_metadata_bool("1"), Some(true));
+    assert_eq!(parse_metadata_bool("0"), Some(false));
+    assert_eq!(parse_metadata_bool("是"), None);
+
+    // metadata 中的布尔值和 0/1 都能关闭或打开角色表，无法识别的值沿用配置
+    let body = "\n\nINT. 客厅 - 日\n\n@阿强\n你好。\n";
+    let with_cast = |value: &str, print_cast_list: bool| {
+        let script = format!(
+            "Title: 测试\nMetadata: {{\"print\": {{\"print_cast_list\": {}}}}}{}",
+            value, body
+        );
+        let config = Conf {
+            print_cast_list,
+            ..Conf::default()
+        };
+        async move { document_xml(&script, &config).await.contains(CAST_TITLE) }
+    };
+    assert!(!with_cast("false", true).await);
+    assert!(!with_cast("0", true).await);
+    assert!(with_cast("true", false).await);
+    assert!(with_cast("1", false).await);
+    assert!(with_cast("\"是\"", true).await);
+    assert!(!with_cast("\"是\"", false).await);
+}
+
+#[tokio::test]
+async fn test_av_script() {
+    use betterfountain_rust::docx::generate_docx_av;
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿强\n你好。\n\nSFX: 雷声\n\n他坐下。\n";
+    let config = Conf::default();
+    let result = FountainParser::new().parse(script, &config, false, None);
+    let path = std::env::temp_dir().join(format!("av-{}.docx", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    generate_docx_av(&path, &config, &result).await.unwrap();
+    let docx = fs::read(&path).unwrap();
+    let _ = fs::remove_file(&path);
+    let document = docx_part(&docx, "word/document.xml");
+
+    // 场景标题在表格之外，场景内容是一张两栏表格，首行为栏目标题
+    assert_eq!(document.matches("<w:tbl>").count(), 1);
+    assert!(document.find("客厅").unwrap() < document.find("<w:tbl>").unwrap());
+    let rows: Vec<Vec<&str>> = document
+        .split("<w:tr>")
+        .skip(1)
+        .map(|row| row.split("<w:tc>").skip(1).collect())
+        .collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows.iter().all(|cells| cells.len() == 2));
+    assert!(rows[0][0].contains("画面") && rows[0][1].contains("声音"));
+
+    // 画面之后的对白和音效与画面同行，声音之后的画面另起一行
+    assert!(rows[1][0].contains("阿强走进来。"));
+    for text in ["阿强", "你好。", "SFX: 雷声"] {
+        assert!(rows[1][1].contains(text), "{}", text);
+    }
+    assert!(rows[2][0].contains("他坐下。"));
+    assert!(!rows[2][1].contains("<w:t"));
+}
+
+#[tokio::test]
+async fn test_vertical_text() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n";
+    // 正文各节 w:pgSz 的宽和高
+    let page_sizes = |document: &str| -> Vec<(u32, u32)> {
+        let attr = |element: &str, name: &str| -> u32 {
+            let value = element.split(&format!("{}=\"", name)).nth(1).unwrap();
+            value[..value.find('"').unwrap()].parse().unwrap()
+        };
+        document
+            .split("<w:pgSz ")
+            .skip(1)
+            .map(|rest| {
+                let element = &rest[..rest.find("/>").unwrap()];
+                (attr(element, "w:w"), attr(element, "w:h"))
+            })
+            .collect()
+    };
+
+    let document = document_xml(script, &Conf::default()).await;
+    assert!(!document.contains("<w:textDirection"));
+    assert!(page_sizes(&document).iter().all(|(w, h)| w < h));
+
+    // 竖排时正文的节为 tbRl，纸张横放
+    let mut config = Conf::default();
+    config.print_profile.vertical_text = true;
+    let document = document_xml(script, &config).await;
+    assert!(document.contains("<w:textDirection w:val=\"tbRl\" />"));
+    assert!(page_sizes(&document).iter().any(|(w, h)| w > h));
+
+    // metadata 中的 print.vertical_text 同样生效
+    let script = format!(
+        "Title: 测试\nMetadata: {{\"print\": {{\"vertical_text\": true}}}}\n\n{}",
+        script
+    );
+    let document = document_xml(&script, &Conf::default()).await;
+    assert!(document.contains("<w:textDirection w:val=\"tbRl\" />"));
+}
+
+#[tokio::test]
+async fn test_line_numbers() {
+    use betterfountain_rust::docx::validate_docx;
+
+    let script = "Title: 测试\n\nINT. 客厅 - 日\n\n阿强走进来。\n\n@阿强\n你好。\n";
+    assert!(!document_xml(script, &Conf::default())
+        .await
+        .contains("<w:lnNumType"));
+
+    let config = Conf {
+        print_line_numbers: 5,
+        ..Conf::default()
+    };
+    let docx = export_docx(script, &config).await;
+    assert_eq!(validate_docx(&docx), vec![]);
+    let document = docx_part(&docx, "word/document.xml");
+    let sections: Vec<&str> = document
+        .split("<w:sectPr")
+        .skip(1)
+        .map(|rest| &rest[..rest.find("</w:sectPr>").unwrap()])
+        .collect();
+    // 标题页不标行号，正文每五行标一次、各节连续编号，lnNumType 排在 pgNumType 之前
+    assert!(!sections[0].contains("<w:lnNumType"));
+    let numbered: Vec<&&str> = sections
+        .iter()
+        .filter(|section| section.contains("<w:lnNumType"))
+        .collect();
+    assert!(!numbered.is_empty());
+    for section in numbered {
+        assert!(section.contains(
+            "<w:lnNumType w:countBy=\"5\" w:distance=\"360\" w:restart=\"continuous\" />"
+        ));
+        if let Some(page_number) = section.find("<w:pgNumType") {
+            assert!(section.find("<w:lnNumType").unwrap() < page_number);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_front_matter_page_numbers() {
+    let script = "Title: 测试\n\nINT. 客厅 - 日\n\n@阿强\n你好。\n\n@阿珍\n你也好。\n";
+    let roman = |document: &str| -> Vec<bool> {
+        document
+            .split("<w:sectPr")
+            .skip(1)
+            .map(|rest| rest[..rest.find("</w:sectPr>").unwrap()].contains("w:fmt=\"lowerRoman\""))
+            .collect()
+    };
+    let config = Conf {
+        print_cast_list: true,
+        ..Conf::default()
+    };
+    assert!(!roman(&document_xml(script, &config).await).contains(&true));
+
+    // 标题页和角色表各成一节，以 i、ii 连续编页码；正文另起一节，页码重新从 1 开始
+    let config = Conf {
+        print_cast_list: true,
+        front_matter_page_numbers: true,
+        ..Conf::default()
+    };
+    let document = document_xml(script, &config).await;
+    let roman = roman(&document);
+    assert!(roman.len() >= 3);
+    assert_eq!(roman[..3], [true, true, false]);
+    assert!(!roman[3..].contains(&true));
+    assert_eq!(page_number_starts(&document), ["1", "1"]);
+}
+
+#[tokio::test]
+async fn test_docx_protection() {
+    use betterfountain_rust::docx::validate_docx;
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n";
+    let settings = |protection: &str| {
+        let config = Conf {
+            docx_protection: protection.to_string(),
+            deterministic: true,
+            ..Conf::default()
+        };
+        async move {
+            let docx = export_docx(script, &config).await;
+            assert_eq!(validate_docx(&docx), vec![]);
+            docx_part(&docx, "word/settings.xml")
+        }
+    };
+
+    let unprotected = settings("").await;
+    assert!(!unprotected.contains("<w:documentProtection"));
+    assert!(!unprotected.contains("<w:writeProtection"));
+    assert_eq!(settings("不认识").await, unprotected);
+
+    // 建议只读：writeProtection 是 settings 的第一个子元素
+    let xml = settings("read_only_recommended").await;
+    let body = &xml[xml.find("<w:settings").unwrap()..];
+    let body = &body[body.find('>').unwrap() + 1..];
+    assert!(body.starts_with("<w:writeProtection w:recommended=\"1\" />"));
+    assert!(!xml.contains("<w:documentProtection"));
+
+    // 编辑限制：documentProtection 排在 defaultTabStop 等子元素之前，不设密码
+    for (name, edit) in [
+        ("read_only", "readOnly"),
+        ("comments", "comments"),
+        ("tracked_changes", "trackedChanges"),
+    ] {
+        let xml = settings(name).await;
+        let element = format!(
+            "<w:documentProtection w:edit=\"{}\" w:enforcement=\"1\" />",
+            edit
+        );
+        let at = xml.find(&element).unwrap();
+        if let Some(tab_stop) = xml.find("<w:defaultTabStop") {
+            assert!(at < tab_stop);
+        }
+        assert!(!xml.contains("w:hashValue"));
+    }
+}
+
+#[tokio::test]
+async fn test_outline_docx() {
+    use betterfountain_rust::docx::generate_docx_outline;
+
+    let script = "# 第一幕\n\nINT. 客厅 - 日\n\n= 阿强回家\n\n阿强走进来。\n\n@阿珍\n你回来了。\n\nEXT. 花园 - 夜\n\n阿珍坐着。\n";
+    let outline = |config: Conf| async move {
+        let result = FountainParser::new().parse(script, &config, false, None);
+        let path = std::env::temp_dir().join(format!("outline-{}.docx", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        generate_docx_outline(&path, &config, &result)
+            .await
+            .unwrap();
+        let docx = fs::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        docx_part(&docx, "word/document.xml")
+    };
+
+    // 只有章节、场景标题和概要；配置中关闭了章节打印时大纲中仍有章节
+    let document = outline(Conf {
+        print_sections: false,
+        ..Conf::default()
+    })
+    .await;
+    for text in ["第一幕", "客厅", "花园", "阿强回家"] {
+        assert!(document.contains(text), "{}", text);
+    }
+    for text in ["阿强走进来。", "阿珍", "你回来了。"] {
+        assert!(!document.contains(text), "{}", text);
+    }
+
+    let document = outline(Conf {
+        print_synopsis_outline: false,
+        ..Conf::default()
+    })
+    .await;
+    assert!(document.contains("客厅"));
+    assert!(!document.contains("阿强回家"));
+}
+
+#[tokio::test]
+async fn test_reference_docx() {
+    use betterfountain_rust::docx::{build_docx, DocxOptions};
+
+    // 用另一份导出的文档作为模板
+    let template = export_docx("公司抬头说明。\n", &Conf::default()).await;
+    let path = std::env::temp_dir().join(format!("reference-{}.docx", std::process::id()));
+    fs::write(&path, &template).unwrap();
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n";
+    let result = FountainParser::new().parse(script, &Conf::default(), false, None);
+    let options = DocxOptions {
+        parsed: Some(result),
+        reference_docx: Some(path.clone()),
+        ..Default::default()
+    };
+    let built = build_docx(&options);
+    let _ = fs::remove_file(&path);
+
+    // 模板中的正文保留在前，剧本内容追加在后
+    let document = docx_part(&built.unwrap().data, "word/document.xml");
+    let header = document.find("公司抬头说明。").unwrap();
+    assert!(header < document.find("阿强走进来。").unwrap());
+
+    // 模板不存在时导出失败
+    let options = DocxOptions {
+        reference_docx: Some(path),
+        ..options
+    };
+    assert!(build_docx(&options).is_err());
+}
+
+#[tokio::test]
+async fn test_endnotes() {
+    let script = "阿强在门外。[[开场前]]\n\nINT. 客厅 - 日\n\n阿强走进来。[[灯没开]]\n\nEXT. 花园 - 夜\n\n阿珍坐着。[[要下雨]]\n";
+    let config = Conf {
+        note_position_end: true,
+        ..Conf::default()
+    };
+    let document = document_xml(script, &config).await;
+
+    // 正文只留编号，注释内容在文末的“尾注”之后另起一页
+    let heading = document.find("尾注").unwrap();
+    let (body, endnotes) = document.split_at(heading);
+    for no in ["[1]", "[2]", "[3]"] {
+        assert!(body.contains(no), "{}", no);
+    }
+    for note in ["开场前", "灯没开", "要下雨"] {
+        assert!(!body.contains(note), "{}", note);
+    }
+    assert!(body.contains("阿珍坐着。"));
+    assert!(body[body.rfind("阿珍坐着。").unwrap()..].contains("w:type=\"page\""));
+
+    // 按编号排列，注明注释所在的场景，场景之前的注释不加说明
+    let first = endnotes.find("开场前").unwrap();
+    let second = endnotes.find("灯没开").unwrap();
+    let third = endnotes.find("要下雨").unwrap();
+    assert!(first < second && second < third);
+    assert!(!endnotes[first..second].contains("场景："));
+    let scene = &endnotes[second..third];
+    assert!(scene.contains("场景：") && scene.contains("客厅"));
+    let scene = &endnotes[third..];
+    assert!(scene.contains("场景：") && scene.contains("花园"));
+}
+
+#[tokio::test]
+async fn test_element_colors() {
+    let script = "INT. 客厅 - 日\n\n@阿强\n你好。\n\nCUT TO:\n\nEXT. 花园 - 夜\n";
+    // 包含 `text` 的第一个运行的颜色
+    let run_color = |document: &str, text: &str| -> Option<String> {
+        let run = document
+            .split("<w:r>")
+            .skip(1)
+            .map(|run| &run[..run.find("</w:r>").unwrap_or(run.len())])
+            .find(|run| run.contains(text))
+            .unwrap();
+        let color = run.split("<w:color w:val=\"").nth(1)?;
+        Some(color[..color.find('"').unwrap()].to_string())
+    };
+
+    let config = Conf {
+        scene_heading_color: "#c00".to_string(),
+        character_color: "blue".to_string(),
+        transition_color: "#008000".to_string(),
+        ..Conf::default()
+    };
+    let document = document_xml(script, &config).await;
+    assert_eq!(run_color(&document, "客厅").as_deref(), Some("CC0000"));
+    assert_eq!(run_color(&document, "花园").as_deref(), Some("CC0000"));
+    assert_eq!(run_color(&document, "阿强").as_deref(), Some("0000FF"));
+    assert_eq!(run_color(&document, "CUT TO:").as_deref(), Some("008000"));
+    // 对白不受角色名颜色影响
+    assert_ne!(run_color(&document, "你好。").as_deref(), Some("0000FF"));
+
+    // metadata 覆盖配置，不认识的颜色忽略
+    let script = format!(
+        "Title: 测试\nMetadata: {{\"print\": {{\"character_color\": \"red\", \"scene_heading_color\": \"不是颜色\"}}}}\n\n{}",
+        script
+    );
+    let document = document_xml(&script, &config).await;
+    assert_eq!(run_color(&document, "阿强").as_deref(), Some("FF0000"));
+    assert_ne!(run_color(&document, "客厅").as_deref(), Some("CC0000"));
+    assert_eq!(run_color(&document, "CUT TO:").as_deref(), Some("008000"));
+}
+
+#[tokio::test]
+async fn test_images_relative_to_script() {
+    use betterfountain_rust::docx::generate_docx_bytes;
+
+    // 1x1 的 PNG
+    let png = base64::decode(
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP4z8AAAAMBAQDJ/pLvAAAAAElFTkSuQmCC",
+    )
+    .unwrap();
+    let dir = std::env::temp_dir().join(format!("bf_docx_images_{}", std::process::id()));
+    fs::create_dir_all(dir.join("acts/boards")).unwrap();
+    fs::write(dir.join("logo.png"), &png).unwrap();
+    fs::write(dir.join("acts/boards/01.png"), &png).unwrap();
+    fs::write(
+        dir.join("main.fountain"),
+        "Title: 插图\nImage: logo.png\n\n{include: acts/act1.fountain}\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("acts/act1.fountain"),
+        "INT. 客厅 - 日\n\n[[img: boards/01.png | 3x2]]\n\n阿强走进来。\n",
+    )
+    .unwrap();
+
+    // 在内存中导出（没有输出路径），图片按各自所在文件的目录找到，而不是当前目录
+    let config = Conf::default();
+    let result = FountainParser::new()
+        .parse_file(&dir.join("main.fountain"), &config, false, None)
+        .unwrap();
+    let docx = generate_docx_bytes(&config, &result).await.unwrap();
+    let archive = zip::ZipArchive::new(std::io::Cursor::new(docx.as_slice())).unwrap();
+    let media = archive
+        .file_names()
+        .filter(|name| name.starts_with("word/media/"))
+        .count();
+    assert_eq!(media, 2);
+    assert!(!docx_part(&docx, "word/document.xml").contains("[图片:"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_profile_export() {
+    use betterfountain_rust::api::profile_export;
+
+    let script = fs::read_to_string("tests/test_data/黑色爱情诗.fountain").unwrap();
+    let timings = profile_export(&script, &Conf::default()).await.unwrap();
+    assert!(timings.parse > std::time::Duration::ZERO);
+    assert!(timings.lines > std::time::Duration::ZERO);
+    assert!(timings.layout > std::time::Duration::ZERO);
+    assert!(timings.save > std::time::Duration::ZERO);
+}
@@ -6,7 +6,7 @@ use std::path::Path;
 #[test]
 fn test_chinese_script_parsing() {
     // 创建解析器
-    let mut parser = FountainParser::new();
+    let parser = FountainParser::new();
 
     // 读取中文测试文件
     let script_path = Path::new("tests/test_data/黑色爱情诗.fountain");
@@ -159,7 +159,7 @@ fn test_time_calculation() {
 
 #[test]
 fn test_duration_parameters() {
-    let mut parser = FountainParser::new();
+    let parser = FountainParser::new();
 
     // 测试自定义配置参数
     let mut custom_conf = Conf::default();
@@ -205,3 +205,630 @@ JOHN
     println!("- 对话长标点时长: {}", result.dial_sec_per_punc_long);
     println!("- 动作每字符时长: {}", result.action_sec_per_char);
 }
+
+#[test]
+fn test_tagged_notes() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。[[todo: 补充动作]]\n\n阿珍关上门。[[prod: 需要摇臂]]\n\n窗外下起雨。[[普通注解]]\n";
+    let mut conf = Conf {
+        hide_note_tags: vec!["TODO".to_string()],
+        ..Conf::default()
+    };
+    let parser = FountainParser::new();
+    let result = parser.parse(script, &conf, false, None);
+
+    let todo = &result.properties.notes_by_tag["todo"];
+    assert_eq!(todo.len(), 1);
+    assert_eq!(todo[0].note, "补充动作");
+    assert_eq!(todo[0].line, 2);
+    assert_eq!(result.properties.notes_by_tag["prod"][0].note, "需要摇臂");
+    assert_eq!(result.properties.notes_by_tag.len(), 2);
+
+    let text: String = result.tokens.iter().map(|t| t.text.as_str()).collect();
+    assert!(!text.contains("补充动作"));
+    assert!(text.contains("需要摇臂"));
+    assert!(text.contains("普通注解"));
+
+    assert!(conf.should_print_note(None));
+    assert!(!conf.should_print_note(Some("todo")));
+    conf.print_note_tags = vec!["prod".to_string()];
+    assert!(conf.should_print_note(Some("prod")));
+    assert!(!conf.should_print_note(Some("cast")));
+}
+
+#[test]
+fn test_keep_character_scenes() {
+    use betterfountain_rust::sides::keep_character_scenes;
+
+    let script = "开场动作。\n\nINT. 客厅 - 日\n\n@阿强\n你好。\n\n@阿珍\n你也好。\n\nEXT. 花园 - 夜\n\n@阿珍\n月亮真圆。\n\nINT. 厨房 - 日\n\n@阿强 (V.O.)\n饭好了。\n";
+    let parser = FountainParser::new();
+    let mut result = parser.parse(script, &Conf::default(), false, None);
+    keep_character_scenes(&mut result, &["阿强".to_string()]);
+
+    let headings: Vec<&str> = result
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "scene_heading")
+        .map(|t| t.text.trim())
+        .collect();
+    assert_eq!(headings.len(), 2);
+    assert!(headings[0].contains("客厅"));
+    assert!(headings[1].contains("厨房"));
+    assert!(!result.tokens.iter().any(|t| t.text.contains("开场动作")));
+    assert!(result.tokens.iter().any(|t| t.text.contains("你也好")));
+}
+
+#[test]
+fn test_parse_file_with_includes() {
+    use betterfountain_rust::parser::IncludeError;
+
+    let dir = std::env::temp_dir().join(format!("bf_include_{}", std::process::id()));
+    fs::create_dir_all(dir.join("acts")).unwrap();
+    fs::write(
+        dir.join("main.fountain"),
+        "Title: 引用测试\n\n{include: acts/act1.fountain}\n\nEXT. 花园 - 夜\n\n月光洒在草地上。\n",
+    )
+    .unwrap();
+    fs::write(dir.join("acts/act1.fountain"), "INT. 客厅 - 日\n\n阿强走进来。\n").unwrap();
+
+    let parser = FountainParser::new();
+    let result = parser
+        .parse_file(&dir.join("main.fountain"), &Conf::default(), false, None)
+        .unwrap();
+    let headings: Vec<_> = result
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "scene_heading")
+        .collect();
+    assert_eq!(headings.len(), 2);
+    let origin = headings[0].origin.as_ref().unwrap();
+    assert!(origin.file.ends_with("act1.fountain"));
+    assert_eq!(origin.line, 0);
+    let origin = headings[1].origin.as_ref().unwrap();
+    assert!(origin.file.ends_with("main.fountain"));
+    assert_eq!(origin.line, 4);
+
+    // 循环引用
+    fs::write(dir.join("acts/act1.fountain"), "{include: ../main.fountain}\n").unwrap();
+    let err = parser
+        .parse_file(&dir.join("main.fountain"), &Conf::default(), false, None)
+        .unwrap_err();
+    assert!(matches!(err, IncludeError::Cycle(_)));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_parse_file_source_ranges_with_crlf_include() {
+    let dir = std::env::temp_dir().join(format!("bf_include_crlf_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let main = "Title: 引用\n\n{include: act1.fountain}\n\nEXT. 花园 - 夜\n\n月光😀洒在草地上。\n";
+    let act1 = "INT. 客厅 - 日\r\n\r\n  阿强走进来。\r\n\r\n@阿珍\r\n你好。\r\n";
+    fs::write(dir.join("main.fountain"), main).unwrap();
+    fs::write(dir.join("act1.fountain"), act1).unwrap();
+
+    let result = FountainParser::new()
+        .parse_file(&dir.join("main.fountain"), &Conf::default(), false, None)
+        .unwrap();
+    // 范围是在 origin 所指文件原文中的范围，被引用文件的 \r\n 按两个字节计
+    let slice = |text: &str| {
+        let token = result
+            .tokens
+            .iter()
+            .find(|t| t.text.contains(text))
+            .unwrap();
+        let file = token.origin.as_ref().unwrap().file.clone();
+        let source = fs::read_to_string(&file).unwrap();
+        let r = token.source_range.unwrap();
+        let utf16: Vec<u16> = source.encode_utf16().collect();
+        assert_eq!(
+            String::from_utf16(&utf16[r.utf16_start..r.utf16_end]).unwrap(),
+            &source[r.byte_start..r.byte_end]
+        );
+        (
+            file,
+            source[r.byte_start..r.byte_end].to_string(),
+            r.column_start,
+        )
+    };
+    let (file, text, column) = slice("阿强走进来。");
+    assert!(file.ends_with("act1.fountain"));
+    assert_eq!(text, "阿强走进来。");
+    assert_eq!(column, 2);
+    assert_eq!(slice("你好。").1, "你好。");
+    let (file, text, _) = slice("月光😀洒在草地上。");
+    assert!(file.ends_with("main.fountain"));
+    assert_eq!(text, "月光😀洒在草地上。");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_parse_file_image_paths() {
+    let dir = std::env::temp_dir().join(format!("bf_include_image_{}", std::process::id()));
+    fs::create_dir_all(dir.join("acts")).unwrap();
+    fs::write(
+        dir.join("main.fountain"),
+        "Title: 插图\nImage: logo.png\n\n{include: acts/act1.fountain}\n\n[[img: /abs/board.png]]\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("acts/act1.fountain"),
+        "INT. 客厅 - 日\n\n[[img: boards/01.png | 3x2]]\n",
+    )
+    .unwrap();
+
+    let result = FountainParser::new()
+        .parse_file(&dir.join("main.fountain"), &Conf::default(), false, None)
+        .unwrap();
+    let src = |token: &betterfountain_rust::models::ScriptToken| {
+        token.metadata.as_ref().unwrap()["src"].clone()
+    };
+
+    // 相对路径按所在文件的目录展开，绝对路径不变
+    let images: Vec<_> = result
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "image")
+        .collect();
+    assert_eq!(images.len(), 2);
+    assert_eq!(
+        Path::new(&src(images[0])),
+        dir.join("acts").join("boards/01.png")
+    );
+    assert_eq!(images[0].text, "boards/01.png");
+    assert_eq!(src(images[1]), "/abs/board.png");
+
+    let logo = result
+        .title_page
+        .values()
+        .flatten()
+        .find(|t| t.token_type == "image")
+        .unwrap();
+    assert_eq!(Path::new(&src(logo)), dir.join("logo.png"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_script_variables() {
+    let script = "Title: {{PROTAGONIST}}的故事\nMetadata: {\"variables\": {\"PROTAGONIST\": \"阿强\",\n    \"HOME\": \"老宅\"}}\n\nINT. {{HOME}} - 日\n\n{{PROTAGONIST}}走进来，看见{{SIDEKICK}}。\n\n@{{PROTAGONIST}}\n我回来了。\n";
+    let mut conf = Conf::default();
+    conf.variables.insert("PROTAGONIST".to_string(), "配置里的名字".to_string());
+    conf.variables.insert("SIDEKICK".to_string(), "阿珍".to_string());
+    let parser = FountainParser::new();
+    let result = parser.parse(script, &conf, false, None);
+
+    let heading = result.tokens.iter().find(|t| t.token_type == "scene_heading").unwrap();
+    assert!(heading.text.contains("老宅"));
+    let action = result.tokens.iter().find(|t| t.token_type == "action").unwrap();
+    assert!(action.text.contains("阿强走进来，看见阿珍。"));
+    assert!(result.properties.characters.contains_key("阿强"));
+    let title = result.title_page.values().flatten().find(|t| t.token_type == "title").unwrap();
+    assert!(title.text.contains("阿强的故事"));
+
+    conf.variables.clear();
+    let result = parser.parse(script, &conf, false, None);
+    assert!(result.diagnostics.iter().any(|d| d.code == "undefined-variable" && d.range.start.line == 6));
+}
+
+#[test]
+fn test_token_source_ranges() {
+    let script = "Title: 长夜\n    第二行\n\r\n.客厅 - 日 #3#\n\n  @阿强\n你好😀。\n";
+    let config = Conf::default();
+    let parser = FountainParser::new();
+    let result = parser.parse(script, &config, false, None);
+
+    let slice = |t: &betterfountain_rust::ScriptToken| {
+        let r = t.source_range.unwrap();
+        let utf16: Vec<u16> = script.encode_utf16().collect();
+        assert_eq!(
+            String::from_utf16(&utf16[r.utf16_start..r.utf16_end]).unwrap(),
+            &script[r.byte_start..r.byte_end]
+        );
+        &script[r.byte_start..r.byte_end]
+    };
+    let find = |ty: &str| result.tokens.iter().find(|t| t.token_type == ty).unwrap();
+    assert_eq!(slice(find("scene_heading")), ".客厅 - 日 #3#");
+    assert_eq!(slice(find("character")), "@阿强");
+    assert_eq!(slice(find("dialogue")), "你好😀。");
+    let title = &result.title_page.values().flatten().next().unwrap();
+    assert_eq!(slice(title), "Title: 长夜\n    第二行");
+    assert!(result
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "dialogue_end")
+        .all(|t| t.source_range.is_none()));
+}
+
+#[test]
+fn test_custom_scene_heading_prefixes() {
+    let script =
+        "INNEN. KÜCHE - TAG\n\nAnna kocht.\n\nExtérieur. JARDIN - NUIT\n\nИНТ. КУХНЯ - ДЕНЬ\n";
+    let heading_count = |conf: &Conf| {
+        let parser = FountainParser::new();
+        parser
+            .parse(script, conf, false, None)
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == "scene_heading")
+            .count()
+    };
+    assert_eq!(heading_count(&Conf::default()), 0);
+
+    let conf = Conf {
+        scene_heading_prefixes: vec![
+            "INNEN".to_string(),
+            "EXTÉRIEUR".to_string(),
+            "ИНТ.".to_string(),
+        ],
+        ..Conf::default()
+    };
+    assert_eq!(heading_count(&conf), 3);
+
+    // 正则按配置缓存，交替使用不同配置时各自生效
+    assert_eq!(heading_count(&Conf::default()), 0);
+    assert_eq!(heading_count(&conf), 3);
+}
+
+#[test]
+fn test_custom_transition_keywords() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\nCUT TO:\n\n阿珍关上门。\n\n切至：\n\n窗外下起雨。\n\nSmash Cut To:\n";
+    let conf = Conf {
+        transition_keywords: vec!["切至：".to_string(), "SMASH CUT TO:".to_string()],
+        ..Conf::default()
+    };
+    let parser = FountainParser::new();
+    let result = parser.parse(script, &conf, false, None);
+
+    let transitions: Vec<Option<&str>> = result
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "transition")
+        .map(|t| t.transition.as_deref())
+        .collect();
+    assert_eq!(
+        transitions,
+        vec![Some("CUT TO:"), Some("切至："), Some("SMASH CUT TO:")]
+    );
+
+    let result = FountainParser::new().parse(script, &Conf::default(), false, None);
+    assert_eq!(
+        result
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == "transition")
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn test_page_break_before_sections() {
+    let script = "# 第一幕\n\nINT. 客厅 - 日\n\n阿强走进来。\n\n## 序列一\n\n阿珍关上门。\n\n===\n\n# 第二幕\n\n# 第三幕\n\nEXT. 花园 - 夜\n";
+    let types = |page_break_before_sections: Option<usize>| {
+        let conf = Conf {
+            page_break_before_sections,
+            ..Conf::default()
+        };
+        let parser = FountainParser::new();
+        parser
+            .parse(script, &conf, false, None)
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == "section" || t.token_type == "page_break")
+            .map(|t| t.token_type.clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        types(None),
+        vec!["section", "section", "page_break", "section", "section"]
+    );
+    // 剧本开头和已有分页符之后不重复分页
+    assert_eq!(
+        types(Some(1)),
+        vec![
+            "section",
+            "section",
+            "page_break",
+            "section",
+            "page_break",
+            "section"
+        ]
+    );
+    assert_eq!(
+        types(Some(2)),
+        vec![
+            "section",
+            "page_break",
+            "section",
+            "page_break",
+            "section",
+            "page_break",
+            "section"
+        ]
+    );
+}
+
+#[test]
+fn test_omitted_scenes() {
+    let script = r#"Title: 删场测试
+Metadata: {"omitted": [3]}
+
+INT. 客厅 - 日
+
+阿强走进来。
+
+OMITTED #2#
+
+INT. 厨房 - 夜
+
+阿珍在做饭。
+
+INT. 阳台 - 夜
+
+阿强抽烟。
+"#;
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &Conf::default(), false, None);
+    let tokens: Vec<(&str, &str)> = output
+        .tokens
+        .iter()
+        .filter(|t| t.token_type != "separator")
+        .map(|t| (t.token_type.as_str(), t.text.as_str()))
+        .collect();
+
+    // 删去的场景保留编号，内容不输出，后面的场景编号不变
+    assert_eq!(
+        tokens,
+        vec![
+            ("scene_heading", "INT. 客厅 - 日"),
+            ("action", "阿强走进来。"),
+            ("scene_heading", "SCENE 2 – OMITTED"),
+            ("scene_heading", "SCENE 3 – OMITTED"),
+            ("scene_heading", "INT. 阳台 - 夜"),
+            ("action", "阿强抽烟。"),
+        ]
+    );
+    assert_eq!(output.properties.omitted_scenes, vec!["2", "3"]);
+    let last = output
+        .tokens
+        .iter()
+        .rfind(|t| t.token_type == "scene_heading");
+    assert_eq!(last.and_then(|t| t.number.as_deref()), Some("4"));
+    assert!(!output
+        .properties
+        .locations
+        .keys()
+        .any(|l| l.contains("厨房")));
+}
+
+#[test]
+fn test_character_aliases() {
+    let script = r#"Title: 别名测试
+Metadata: {"aliases": {"BOB": "ROBERT"}}
+
+INT. 客厅 - 日
+
+ROBERT
+你好。
+
+EXT. 街道 - 夜
+
+路灯下站着BOB。
+
+BOB (V.O.)
+我回来了。
+"#;
+    let parser = FountainParser::new();
+    let mut output = parser.parse(script, &Conf::default(), false, None);
+
+    // 别名对白记在规范角色名下，角色列表中只有一个角色
+    let speakers: Vec<Option<&str>> = output
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "character")
+        .map(|t| t.character.as_deref())
+        .collect();
+    assert_eq!(speakers, vec![Some("ROBERT"), Some("ROBERT")]);
+    assert_eq!(output.properties.characters.len(), 1);
+    assert_eq!(output.properties.characters["ROBERT"], vec![0, 1]);
+    let action = output.tokens.iter().find(|t| t.text.contains("路灯"));
+    assert_eq!(
+        action.and_then(|t| t.characters_action.clone()),
+        Some(vec!["ROBERT".to_string()])
+    );
+
+    // 试镜台词本可以用别名选定角色
+    betterfountain_rust::sides::keep_character_scenes(&mut output, &["bob".to_string()]);
+    assert_eq!(
+        output
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == "scene_heading")
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn test_parser_shared_across_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<FountainParser>();
+
+    let script = "INT. 客厅 - 日\n\n@阿强\n（低声）\n你来了。\n\n场景 二 车站\n\n阿珍走进来。\n";
+    let mut spec = Conf::default();
+    spec.spec_mode = true;
+    let mut prefixes = Conf::default();
+    prefixes.scene_heading_prefixes = vec!["场景".to_string()];
+    let configs = [Conf::default(), spec, prefixes];
+
+    // 同一个解析器在多个线程中同时解析，各次解析的配置互不影响
+    let parser = FountainParser::new();
+    let summary = |conf: &Conf| -> Vec<(String, String)> {
+        parser
+            .parse(script, conf, false, None)
+            .tokens
+            .into_iter()
+            .map(|t| (t.token_type, t.text))
+            .collect()
+    };
+    let expected: Vec<_> = configs.iter().map(summary).collect();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .flat_map(|_| configs.iter())
+            .map(|conf| (conf, scope.spawn(|| summary(conf))))
+            .collect();
+        for (conf, handle) in handles {
+            let index = configs.iter().position(|c| std::ptr::eq(c, conf)).unwrap();
+            assert_eq!(handle.join().unwrap(), expected[index]);
+        }
+    });
+    // 全角括号只在扩展语法开启时是括号注释，自定义前缀只在配置了时是场景标题
+    assert!(expected[0].iter().any(|(t, _)| t == "parenthetical"));
+    assert!(!expected[1].iter().any(|(t, _)| t == "parenthetical"));
+    let scenes = |i: usize| {
+        expected[i]
+            .iter()
+            .filter(|(t, _)| t == "scene_heading")
+            .count()
+    };
+    assert_eq!((scenes(0), scenes(2)), (1, 2));
+}
+
+#[test]
+fn test_parse_borrowed() {
+    let script =
+        "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿强\n今天怎么这么安静？\n\n**重音**的动作。\n";
+    let config = Conf::default();
+    let parser = FountainParser::new();
+    let owned = parser.parse(script, &config, false, Some(false));
+    let borrowed = parser.parse_borrowed(script, &config);
+
+    assert_eq!(borrowed.tokens.len(), owned.tokens.len());
+    for (b, o) in borrowed.tokens.iter().zip(&owned.tokens) {
+        assert_eq!(b.token_type, o.token_type);
+        assert_eq!(b.text, o.text);
+        assert_eq!(b.line, o.line);
+    }
+
+    // 未经改写的动作和角色名直接借用原文，指向原文中的位置
+    let range = script.as_bytes().as_ptr_range();
+    let action = &borrowed.tokens[2];
+    assert_eq!(action.text, "阿强走进来。");
+    assert!(action.is_borrowed());
+    assert!(range.contains(&action.text.as_ptr()));
+    let character = borrowed
+        .tokens
+        .iter()
+        .find(|t| t.is_type(&["character"]))
+        .unwrap();
+    assert!(character.is_borrowed());
+    assert!(range.contains(&character.text.as_ptr()));
+    // 加了样式标记的文本与原文不同，单独分配
+    let styled = borrowed.tokens.iter().find(|t| t.line == 7).unwrap();
+    assert!(!styled.is_borrowed());
+}
+
+#[test]
+fn test_parse_borrowed_matches_parse() {
+    // 多个场景、删去的场景、action 中提到的角色、双对白和排版美化，逐场景转换的结果与 parse 一致
+    let script = r#"Title: 借用测试
+Metadata: {"omitted": [2]}
+
+INT. 客厅 - 日
+
+阿强走进来。
+
+@阿强
+今天怎么这么安静？
+
+@阿珍 ^
+"大家都出去了..."
+
+INT. 厨房 - 夜
+
+阿珍在做饭，阿强在一旁看着。
+
+INT. 阳台 - 夜
+
+阿珍抽烟 -- 阿强不知道。
+
+@阿珍
+"你怎么来了？"
+"#;
+    let config = Conf {
+        typography: "en".to_string(),
+        ..Conf::default()
+    };
+    let parser = FountainParser::new();
+    let owned = parser.parse(script, &config, false, Some(false));
+    let borrowed = parser.parse_borrowed(script, &config);
+
+    assert_eq!(borrowed.tokens.len(), owned.tokens.len());
+    for (b, o) in borrowed.tokens.iter().zip(&owned.tokens) {
+        assert_eq!(b.token_type, o.token_type);
+        assert_eq!(b.text, o.text);
+        assert_eq!(b.line, o.line);
+        assert_eq!(b.source_range, o.source_range);
+        assert_eq!(b.dual.as_deref(), o.dual.as_deref());
+        assert_eq!(b.omitted, o.is_omitted());
+    }
+    assert!(borrowed.tokens.iter().any(|t| t.omitted));
+    assert!(borrowed.tokens.iter().any(|t| t.dual.is_some()));
+    assert_eq!(borrowed.properties.characters, owned.properties.characters);
+    assert_eq!(
+        borrowed.properties.character_scene_number,
+        owned.properties.character_scene_number
+    );
+    assert_eq!(borrowed.title_page.len(), owned.title_page.len());
+}
+
+#[test]
+fn test_scene_heading_normalization() {
+    let script = "int. kitchen-day\n\nJane cooks.\n";
+    let heading = |mode: &str| {
+        let config = Conf {
+            scene_heading_normalization: mode.to_string(),
+            ..Conf::default()
+        };
+        let output = FountainParser::new().parse(script, &config, false, None);
+        output.tokens[0].text.clone()
+    };
+    assert_eq!(heading("upper"), "INT. KITCHEN - DAY");
+    assert_eq!(heading("preserve"), "int. kitchen-day");
+
+    let turkish = "ext. istasyon - gün ışığı\n\nBekliyor.\n";
+    let config = Conf {
+        scene_heading_normalization: "locale:tr".to_string(),
+        ..Conf::default()
+    };
+    let output = FountainParser::new().parse(turkish, &config, false, None);
+    assert_eq!(output.tokens[0].text, "EXT. İSTASYON - GÜN IŞIĞI");
+}
+
+#[test]
+fn test_scene_number_format() {
+    let script = "INT. 客厅 - 日\n\n序幕。\n\n# 第一幕\n\nINT. 厨房 - 夜\n\n水壶响了。\n\nEXT. 花园 - 夜 #7A#\n\n月光。\n\nEXT. 街道 - 日\n\n车来了。\n\n# 第二幕\n\nINT. 卧室 - 夜\n\n灯灭了。\n";
+    let numbers = |format: &str| {
+        let config = Conf {
+            scene_number_format: format.to_string(),
+            ..Conf::default()
+        };
+        let output = FountainParser::new().parse(script, &config, false, None);
+        output
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == "scene_heading")
+            .map(|t| t.number.clone().unwrap_or_default())
+            .collect::<Vec<_>>()
+    };
+    // 手动编号的场景同样占用一个序号
+    assert_eq!(numbers("{n}"), vec!["1", "2", "7A", "4", "5"]);
+    assert_eq!(numbers("{n:3}"), vec!["001", "002", "7A", "004", "005"]);
+    assert_eq!(numbers("{act}-{n}"), vec!["0-1", "1-1", "7A", "1-3", "2-1"]);
+    assert_eq!(
+        numbers("{act_roman}.{n:2}"),
+        vec![".01", "I.01", "7A", "I.03", "II.01"]
+    );
+}
@@ -0,0 +1,233 @@
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::{line_anchor_id, FountainParser, HtmlLineMap};
+
+#[test]
+fn test_dual_dialogue_html_columns() {
+    let script = "INT. 客厅 - 日\n\n@阿强\n你好。\n\n@阿珍 ^\n(笑)\n你也好。\n\n两人握手。\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+    let html = output.script_html.unwrap();
+
+    let left = html.find("<div class=\"dual-dialogue-left\"").unwrap();
+    let right = html.find("<div class=\"dual-dialogue-right\"").unwrap();
+    let qiang = html.find("阿强").unwrap();
+    let zhen = html.find("阿珍").unwrap();
+    assert!(left < qiang && qiang < right && right < zhen);
+    assert!(html[right..].find("parenthetical").is_some());
+    assert!(!html.contains("fountain-dual_dialogue"));
+}
+
+#[test]
+fn test_triple_dialogue_html_columns() {
+    let script = "INT. 客厅 - 日\n\n@阿强\n你好。\n\n@阿珍 ^\n你也好。\n\n@阿美 ^^\n大家好。\n\n三人握手。\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+
+    let duals: Vec<(&str, Option<&str>)> = output
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "character")
+        .map(|t| (t.character.as_deref().unwrap(), t.dual.as_deref()))
+        .collect();
+    assert_eq!(
+        duals,
+        vec![
+            ("阿强", Some("left")),
+            ("阿珍", Some("middle")),
+            ("阿美", Some("right"))
+        ]
+    );
+    let groups = output
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "dual_dialogue_end")
+        .count();
+    assert_eq!(groups, 1);
+
+    let html = output.script_html.unwrap();
+    let middle = html.find("<div class=\"dual-dialogue-middle\"").unwrap();
+    let right = html.find("<div class=\"dual-dialogue-right\"").unwrap();
+    let zhen = html.find("阿珍").unwrap();
+    let mei = html.find("阿美").unwrap();
+    assert!(middle < zhen && zhen < right && right < mei);
+    assert!(!html.contains("^"));
+}
+
+#[test]
+fn test_inline_emphasis_html() {
+    let script = "INT. 客厅 - 日\n\n他把**旧名字**划掉：~~阿强~~，改成==阿珍==。a == b\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+    let html = output.script_html.unwrap();
+
+    assert!(html.contains("<strong>旧名字</strong>"));
+    assert!(html.contains("<del>阿强</del>"));
+    assert!(html.contains("<mark>阿珍</mark>"));
+    // 落单的标记不处理
+    assert!(html.contains("a == b"));
+}
+
+#[test]
+fn test_escaped_style_chars_html() {
+    let script =
+        "INT. 客厅 \\*后门\\* - 日\n\n2 \\* 3 = 6，\\_x\\_ 不是*斜体*，\\[[不是注解]]，\\~~a~~\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+    let html = output.script_html.unwrap();
+
+    assert!(html.contains("INT. 客厅 *后门* - 日"));
+    assert!(html.contains("2 * 3 = 6，_x_ 不是<em>斜体</em>，[[不是注解]]，~~a~~"));
+    assert!(output.diagnostics.is_empty());
+}
+
+#[test]
+fn test_color_span_html() {
+    let script = "INT. 客厅 - 日\n\n他说{color:red}停下{/color}，然后{color:#0a0}**走**{/color}。{color:nope}x{/color}\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+    let html = output.script_html.unwrap();
+
+    assert!(html.contains("<span style=\"color:#FF0000\">停下</span>"));
+    assert!(html.contains("<span style=\"color:#00AA00\"><strong>走</strong></span>"));
+    // 不认识的颜色原样保留
+    assert!(html.contains("{color:nope}x{/color}"));
+}
+
+#[test]
+fn test_title_page_html_positions() {
+    let script = "Title: 我的剧本\nAuthor: 张三\nDraft date: 2024\nNotes: 备注 & 说明\n\nINT. 客厅 - 日\n\n阿强走进来。\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+    let html = output.title_html.unwrap();
+
+    let cc = html.find("<div class=\"title-cc\">").unwrap();
+    let bl = html.find("<div class=\"title-bl\">").unwrap();
+    let br = html.find("<div class=\"title-br\">").unwrap();
+    assert!(cc < bl && bl < br);
+    assert!(html[cc..bl].contains("<div class=\"title-title\">我的剧本</div>"));
+    assert!(html[cc..bl].contains("张三"));
+    assert!(html[bl..br].contains("备注 &amp; 说明"));
+    assert!(html[br..].contains("2024"));
+}
+
+#[test]
+fn test_title_page_template() {
+    let script = "Title: 我的剧本\nAuthor: 张三\nDraft date: 2024\nContact: 13800000000\n\nINT. 客厅 - 日\n\n阿强走进来。\n";
+    let keys = |template: &str, position: &str| {
+        let conf = Conf {
+            title_page_template: template.to_string(),
+            ..Conf::default()
+        };
+        let parser = FountainParser::new();
+        let output = parser.parse(script, &conf, false, None);
+        let mut tokens = output.title_page.get(position).cloned().unwrap_or_default();
+        tokens.sort_by_key(|t| t.index);
+        tokens.into_iter().map(|t| t.token_type).collect::<Vec<_>>()
+    };
+
+    assert_eq!(keys("", "br"), vec!["draft_date", "contact"]);
+    assert_eq!(keys("us", "bl"), vec!["contact"]);
+    assert_eq!(keys("us", "br"), vec!["draft_date"]);
+    // 中文封面的日期排在剧名和编剧之下
+    assert_eq!(keys("china", "cc"), vec!["title", "author", "draft_date"]);
+    assert_eq!(keys("china", "br"), vec!["contact"]);
+    // 同一个解析器换回默认版式时恢复默认位置
+    let parser = FountainParser::new();
+    let china = Conf {
+        title_page_template: "china".to_string(),
+        ..Conf::default()
+    };
+    parser.parse(script, &china, false, None);
+    let output = parser.parse(script, &Conf::default(), false, None);
+    assert_eq!(output.title_page["br"].len(), 2);
+}
+
+#[test]
+fn test_image_tokens() {
+    let script = "Title: 分镜\nImage: logo_small.png | 2\n\nINT. 客厅 - 日\n\n[[img: board_01.png | 3x2]]\n\n阿强走进来。\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+
+    let image = output.tokens.iter().find(|t| t.token_type == "image").unwrap();
+    assert_eq!(image.text, "board_01.png");
+    let metadata = image.metadata.as_ref().unwrap();
+    assert_eq!(metadata["width"], "3");
+    assert_eq!(metadata["height"], "2");
+    assert!(output.tokens.iter().any(|t| t.token_type == "action"));
+
+    let logo = output.title_page["tc"].iter().find(|t| t.token_type == "image").unwrap();
+    assert_eq!(logo.text, "logo_small.png");
+    assert_eq!(logo.metadata.as_ref().unwrap()["width"], "2");
+
+    assert!(output.script_html.unwrap().contains("<img src=\"board_01.png\""));
+    assert!(output.title_html.unwrap().contains("<img src=\"logo_small.png\""));
+}
+
+#[test]
+fn test_lyric_html() {
+    let script = "INT. 客厅 - 日\n\n~月亮代表我的心\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+    let html = output.script_html.unwrap();
+
+    let lyric = html
+        .find("<div id=\"line-2\" data-line=\"2\" class=\"lyric\"><em>♪ ")
+        .unwrap();
+    assert!(html[lyric..].contains("月亮代表我的心"));
+    assert!(!html.contains("fountain-lyric"));
+}
+
+#[test]
+fn test_synopsis_html_visibility() {
+    let script = "# 第一幕\n\n= 阿强回家\n\nINT. 客厅 - 日\n\n阿强走进来。\n";
+    let html = |print_synopsis_html: bool| {
+        let conf = Conf {
+            print_synopsis: false,
+            print_synopsis_html,
+            ..Conf::default()
+        };
+        let parser = FountainParser::new();
+        parser.parse(script, &conf, true, None).script_html.unwrap()
+    };
+
+    // HTML 预览中的概要不受 print_synopsis（DOCX 正文）影响
+    assert!(html(true)
+        .contains("<div id=\"line-2\" data-line=\"2\" class=\"fountain-synopsis\">阿强回家</div>"));
+    let hidden = html(false);
+    assert!(!hidden.contains("阿强回家"));
+    assert!(hidden.contains("阿强走进来。"));
+}
+
+#[test]
+fn test_html_line_anchors() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿强\n你好。\n\n@阿珍 ^\n你也好。\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, true, None);
+    let html = output.script_html.unwrap();
+
+    // 每行第一个元素带锚点 id，双对白的外框和各栏也带 data-line
+    assert!(html.contains("<div id=\"line-0\" data-line=\"0\" class=\"scene-heading\">"));
+    assert!(html.contains("<div id=\"line-2\" data-line=\"2\" class=\"action\">"));
+    assert!(html.contains("<div class=\"dual-dialogue-right\" data-line=\"7\">"));
+    assert_eq!(html.matches("id=\"line-2\"").count(), 1);
+
+    let map = HtmlLineMap::new(&output.tokens);
+    assert_eq!(map.anchor_for_line(2).as_deref(), Some("line-2"));
+    // 超出最后一个元素的行取前面最近的锚点
+    let last = map.lines().last().copied().unwrap();
+    assert_eq!(map.anchor_for_line(100), Some(line_anchor_id(last)));
+    assert_eq!(map.line_for_anchor("line-5"), Some(5));
+    assert_eq!(map.line_for_anchor("line-100"), None);
+    for line in map.lines() {
+        assert!(html.contains(&format!("id=\"{}\"", line_anchor_id(*line))));
+    }
+}
@@ -0,0 +1,32 @@
+use betterfountain_rust::interactions::InteractionGraph;
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_interaction_graph() {
+    let script = "INT. 客厅 - 日\n\n@阿珍\n你回来了。\n\n@阿强\n嗯。\n\n@阿珍\n吃饭了吗？\n\nEXT. 花园 - 夜\n\n@阿强\n好冷。\n\n@老王\n进屋吧。\n\nINT. 厨房 - 夜\n\n@阿珍\n谁在外面？\n";
+    let output = parse(script, &Conf::default(), false, None);
+    let graph = InteractionGraph::from_output(&output);
+
+    let names: Vec<&str> = graph.nodes.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(names, vec!["阿珍", "阿强", "老王"]);
+    assert_eq!(graph.nodes[0].speeches, 3);
+    assert_eq!(graph.nodes[0].dialogue_chars, 15);
+
+    // 阿珍和阿强在第一场有两次往来，阿强和老王在第二场一次，阿珍和老王没有同场
+    assert_eq!(graph.edges.len(), 2);
+    let first = &graph.edges[0];
+    assert_eq!(
+        (first.source.as_str(), first.target.as_str()),
+        ("阿珍", "阿强")
+    );
+    assert_eq!((first.exchanges, first.shared_scenes), (2, 1));
+    assert_eq!(first.dialogue_chars, 12);
+    assert_eq!(graph.edges[1].source, "阿强");
+    assert_eq!(graph.edges[1].exchanges, 1);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("graph characters {"));
+    assert!(dot.contains("\"阿珍\" -- \"阿强\""));
+    let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+    assert_eq!(json["edges"][0]["exchanges"], 2);
+}
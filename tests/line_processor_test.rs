@@ -0,0 +1,183 @@
+use betterfountain_rust::docx::line_processor::{
+    estimate_page_count, pagination_keeps, process_document_lines, scene_lengths, wrap, LineKeep,
+    SceneLength,
+};
+use betterfountain_rust::docx::PrintProfile;
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_wrap_line_breaking_rules() {
+    // 拉丁文字在空格处断开
+    assert_eq!(
+        wrap("Hello there, friend", 12),
+        vec!["Hello there,", "friend"]
+    );
+    // 句号不放在行首，与前一个字一起换到下一行
+    assert_eq!(wrap("今日は晴れです。", 14), vec!["今日は晴れで", "す。"]);
+    // 左括号不放在行尾
+    assert_eq!(
+        wrap("彼は「はい」と言った", 6),
+        vec!["彼は", "「は", "い」と", "言った"]
+    );
+    // 中英混排时英文单词不拆开
+    assert_eq!(
+        wrap("我们去Starbucks喝咖啡", 12),
+        vec!["我们去", "Starbucks喝", "咖啡"]
+    );
+    // 连续的破折号不拆开
+    assert_eq!(wrap("他说——", 5), vec!["他说", "——"]);
+    // 谚文字与字之间可以断开
+    assert_eq!(wrap("안녕하세요", 6), vec!["안녕하", "세요"]);
+}
+
+#[test]
+fn test_estimate_page_count() {
+    let config = Conf::default();
+    let profile = PrintProfile {
+        lines_per_page: 10,
+        ..PrintProfile::default()
+    };
+    let long_action = "这是一段很长的动作描写。".repeat(20);
+    let script = format!(
+        "INT. HOUSE - DAY\n\n{}\n\n===\n\nJohn walks in.\n",
+        long_action
+    );
+    let mut output = parse(&script, &config, false, None);
+    process_document_lines(&mut output, &config);
+
+    // 长段落折成多行后超过一页，分页符之后另起一页
+    assert_eq!(estimate_page_count(&output.lines, &profile), 3);
+}
+
+#[test]
+fn test_scene_lengths_in_eighths() {
+    let config = Conf::default();
+    let profile = PrintProfile {
+        lines_per_page: 16,
+        ..PrintProfile::default()
+    };
+    let long_action = "这是一段很长的动作描写。".repeat(30);
+    let script = format!(
+        "Title: 测试\n\nINT. 客厅 - 日\n\n阿强走进来。\n\nEXT. 花园 - 夜\n\n{}\n",
+        long_action
+    );
+    let mut output = parse(&script, &config, false, None);
+    process_document_lines(&mut output, &config);
+
+    let scenes = scene_lengths(&output.lines, &profile);
+    assert_eq!(scenes.len(), 2);
+    assert_eq!(scenes[0].number, "1");
+    assert_eq!(scenes[1].number, "2");
+    // 每页 16 行，每两行算八分之一页，不足的按八分之一页计
+    for scene in &scenes {
+        assert_eq!(scene.eighths, scene.print_lines.div_ceil(2).max(1));
+    }
+    assert!(scenes[0].eighths < 8);
+    assert!(scenes[1].eighths > 8);
+
+    let length = |eighths| SceneLength {
+        number: "1".to_string(),
+        line: 0,
+        print_lines: 0,
+        eighths,
+    };
+    assert_eq!(length(3).display(), "3/8");
+    assert_eq!(length(10).display(), "1 2/8");
+    assert_eq!(length(16).display(), "2");
+}
+
+#[test]
+fn test_dialogue_numbers() {
+    let script = "INT. 客厅 - 日\n\n@阿强\n你好。\n\n@阿珍\n你好。\n\n@阿强\n再见。\n";
+    let numbers = |numbering: &str| {
+        let config = Conf {
+            print_dialogue_numbers: true,
+            dialogue_numbering: numbering.to_string(),
+            ..Conf::default()
+        };
+        let mut output = parse(script, &config, false, None);
+        process_document_lines(&mut output, &config);
+        output
+            .lines
+            .iter()
+            .filter(|line| line.token_type == "character")
+            .map(|line| (line.text.clone(), line.take_number))
+            .collect::<Vec<_>>()
+    };
+
+    // 编号不写进角色名
+    assert_eq!(
+        numbers("global"),
+        vec![
+            ("阿强".to_string(), Some(1)),
+            ("阿珍".to_string(), Some(2)),
+            ("阿强".to_string(), Some(3)),
+        ]
+    );
+    assert_eq!(
+        numbers("character"),
+        vec![
+            ("阿强".to_string(), Some(1)),
+            ("阿珍".to_string(), Some(1)),
+            ("阿强".to_string(), Some(2)),
+        ]
+    );
+}
+
+#[test]
+fn test_pagination_keeps() {
+    let config = Conf::default();
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿强\n(低声)\n你好。\n再见。\n";
+    let mut output = parse(script, &config, false, None);
+    process_document_lines(&mut output, &config);
+    let lines = &output.lines;
+    let keep = |profile: &PrintProfile, types: &[&str]| -> Vec<LineKeep> {
+        let keeps = pagination_keeps(lines, profile);
+        lines
+            .iter()
+            .zip(keeps)
+            .filter(|(line, _)| types.contains(&line.token_type.as_str()))
+            .map(|(_, keep)| keep)
+            .collect()
+    };
+    let (next, both, none) = (
+        LineKeep {
+            keep_next: true,
+            keep_lines: false,
+        },
+        LineKeep {
+            keep_next: true,
+            keep_lines: true,
+        },
+        LineKeep::default(),
+    );
+
+    // 默认：场景标题连同其后的空行与第一行动作在同一页，角色名、括号注释与第一行对白在同一页
+    let profile = PrintProfile::default();
+    assert_eq!(
+        keep(&profile, &["scene_heading", "action"]),
+        vec![both, none]
+    );
+    assert_eq!(
+        keep(&profile, &["character", "parenthetical", "dialogue"]),
+        vec![next, both, none, none]
+    );
+
+    // 角色名之后至少两行对白；括号注释不再单独约束，只是连接角色名和对白
+    let mut profile = PrintProfile::default();
+    profile.pagination.min_dialogue_lines_after_character = 2;
+    profile.pagination.keep_parentheticals_together = false;
+    profile.pagination.min_action_lines_after_scene_heading = 0;
+    assert_eq!(
+        keep(&profile, &["character", "parenthetical", "dialogue"]),
+        vec![next, next, next, none]
+    );
+    // 不限制场景标题之后的动作行数时，标题只是本身不跨页
+    assert_eq!(
+        keep(&profile, &["scene_heading"]),
+        vec![LineKeep {
+            keep_next: false,
+            keep_lines: true,
+        }]
+    );
+}
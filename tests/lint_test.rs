@@ -0,0 +1,62 @@
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::{lint, parse_strict, FountainParser, ParseError};
+
+#[test]
+fn test_lint_diagnostics() {
+    let script = "Title: 测试\nTitel: 拼错的字段\n\nINT. 客厅 - 日 #1#\n\n(叹气)\n\nINT. 厨房 - 日 #1#\n\n@阿强\n你好。\n\n阿珍 [[没有闭合的注解\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, false, None);
+    let diagnostics = lint(&output, &conf);
+
+    let codes: Vec<&str> = diagnostics.iter().map(|d| d.code.as_str()).collect();
+    assert!(codes.contains(&"unknown-title-page-key"));
+    assert!(codes.contains(&"orphan-parenthetical"));
+    assert!(codes.contains(&"duplicate-scene-number"));
+    assert!(codes.contains(&"single-line-character"));
+    assert!(codes.contains(&"unclosed-note"));
+
+    let note = diagnostics.iter().find(|d| d.code == "unclosed-note").unwrap();
+    assert_eq!(note.range.start.line, 12);
+    assert_eq!(note.range.start.character, 3);
+
+    let dup = diagnostics
+        .iter()
+        .find(|d| d.code == "duplicate-scene-number")
+        .unwrap();
+    assert_eq!(dup.range.start.line, 7);
+}
+
+#[test]
+fn test_lint_columns_from_source() {
+    // 场景编号、强制符号 @ 不在 token 文本中，列仍按原文计算
+    let script = "INT. 客厅 - 日 #1#\n\n.花园\n\n园子里没有人。\n\n  @阿强\n你好。\n";
+    let conf = Conf::default();
+    let output = FountainParser::new().parse(script, &conf, false, None);
+    let diagnostics = lint(&output, &conf);
+    let range = |code: &str| {
+        let d = diagnostics.iter().find(|d| d.code == code).unwrap();
+        (
+            d.range.start.line,
+            d.range.start.character,
+            d.range.end.line,
+            d.range.end.character,
+        )
+    };
+    assert_eq!(range("empty-scene"), (0, 0, 0, 15));
+    assert_eq!(range("single-line-character"), (6, 2, 6, 5));
+}
+
+#[test]
+fn test_parse_strict() {
+    let conf = Conf::default();
+    assert!(parse_strict("INT. 客厅 - 日\n\n阿强走进来。\n", &conf, false, None).is_ok());
+
+    let script = "Title: 测试\nTitel: 拼错的字段\n\nINT. 客厅 - 日 #1#\n\nINT. 厨房 - 日 #1#\n\n阿珍 [[没有闭合的注解\n";
+    let errors = parse_strict(script, &conf, false, None).unwrap_err();
+    assert_eq!(errors.len(), 3);
+    assert!(matches!(&errors[0], ParseError::InvalidTitleKey { key, .. } if key == "Titel"));
+    assert!(matches!(&errors[1], ParseError::DuplicateSceneNumber { number, .. } if number == "1"));
+    assert!(matches!(&errors[2], ParseError::UnclosedNote { range } if range.start.line == 7));
+    assert_eq!(errors[2].to_string(), "第 8 行: 注解 [[ 没有对应的 ]]");
+}
@@ -0,0 +1,105 @@
+use betterfountain_rust::docx::generate_odt_bytes;
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::fountain_parser::FountainParser;
+use std::io::Read;
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.text";
+
+// 解析剧本并导出为 ODT 文件内容
+async fn export_odt(script: &str) -> Vec<u8> {
+    let config = Conf::default();
+    let result = FountainParser::new().parse(script, &config, false, None);
+    generate_odt_bytes(&config, &result).await.unwrap()
+}
+
+// ODT 包中名为 `name` 的部件
+fn odt_part(odt: &[u8], name: &str) -> String {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(odt)).unwrap();
+    let mut xml = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut xml)
+        .unwrap();
+    xml
+}
+
+#[tokio::test]
+async fn test_odt_content_and_styles() {
+    let script =
+        "INT. 客厅 - 日\n\n阿强走进来。\n\n@阿强\n你好。\n\n===\n\nEXT. 花园 - 夜\n\n阿珍坐着。\n";
+    let odt = export_odt(script).await;
+
+    // styles.xml 定义场景标题、角色名和对白的段落样式，以及页面布局
+    let styles = odt_part(&odt, "styles.xml");
+    for (id, name) in [
+        ("scene", "Scene"),
+        ("character", "Character"),
+        ("dial", "Dialogue"),
+    ] {
+        assert!(styles.contains(&format!(
+            "style:name=\"{}\" style:family=\"paragraph\" style:display-name=\"{}\"",
+            id, name
+        )));
+    }
+    assert!(styles.contains("<style:page-layout style:name=\"pm1\">"));
+    assert!(styles.contains("<style:master-page style:name=\"Standard\""));
+
+    // content.xml 中的段落通过自动样式继承上面的样式
+    let content = odt_part(&odt, "content.xml");
+    assert!(content.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+    for id in ["scene", "character", "dial"] {
+        assert!(content.contains(&format!("style:parent-style-name=\"{}\"", id)));
+    }
+    for text in ["客厅", "阿强", "你好。", "花园", "阿珍坐着。"] {
+        assert!(content.contains(text), "{}", text);
+    }
+
+    // 强制分页：分页后的第一个段落使用带 fo:break-before 的自动样式，正文开头不分页
+    let break_styles: Vec<&str> = content
+        .split("<style:style style:name=\"")
+        .skip(1)
+        .filter(|style| style.contains("fo:break-before=\"page\""))
+        .map(|style| &style[..style.find('"').unwrap()])
+        .collect();
+    assert!(!break_styles.is_empty());
+    let body = &content[content.find("<office:body>").unwrap()..];
+    let uses_break = |xml: &str| {
+        break_styles
+            .iter()
+            .any(|style| xml.contains(&format!("text:style-name=\"{}\"", style)))
+    };
+    let before = &body[..body.find("你好。").unwrap()];
+    let after = &body[body.find("你好。").unwrap()..body.find("花园").unwrap()];
+    assert!(!uses_break(before));
+    assert!(uses_break(after));
+}
+
+#[tokio::test]
+async fn test_odt_mimetype() {
+    let odt = export_odt("INT. 客厅 - 日\n\n阿强走进来。\n").await;
+
+    // mimetype 是第一个条目、不压缩且没有扩展字段，内容从第 38 字节开始（30 字节的本地文件头加文件名），
+    // 这样不解压也能从文件开头识别格式
+    assert_eq!(&odt[..4], b"PK\x03\x04");
+    assert_eq!(&odt[30..38], b"mimetype");
+    assert_eq!(&odt[38..38 + MIMETYPE.len()], MIMETYPE.as_bytes());
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(odt.as_slice())).unwrap();
+    let mimetype = archive.by_index(0).unwrap();
+    assert_eq!(mimetype.name(), "mimetype");
+    assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+    drop(mimetype);
+    assert_eq!(odt_part(&odt, "mimetype"), MIMETYPE);
+
+    // manifest 中声明了相同的类型和各个部件
+    let manifest = odt_part(&odt, "META-INF/manifest.xml");
+    assert!(manifest.contains(&format!(
+        "manifest:full-path=\"/\" manifest:media-type=\"{}\"",
+        MIMETYPE
+    )));
+    for part in ["content.xml", "styles.xml", "meta.xml"] {
+        assert!(manifest.contains(&format!("manifest:full-path=\"{}\"", part)));
+        assert!(archive.by_name(part).is_ok());
+    }
+}
@@ -0,0 +1,42 @@
+use betterfountain_rust::osf::{generate_osf, import_osf};
+use betterfountain_rust::utils::strip_style_chars;
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_osf_round_trip() {
+    let config = Conf::default();
+    let script = "Title: 夜航\n\nINT. 客厅 - 日 #3#\n\n阿强走进来，**很累**。\n\n@阿珍\n(小声)\n你回来了。\n\nJOHN ^\nHi.\n\n> 切到：\n\n===\n\n> 完 <\n";
+    let xml = generate_osf(&parse(script, &config, false, None));
+    assert!(xml.contains("<para number=\"3\"><style basestylename=\"Scene Heading\"/>"));
+    assert!(xml.contains("<text bold=\"1\">很累</text>"));
+    assert!(xml.contains("page_break=\"1\""));
+
+    let imported = import_osf(&xml, &config).unwrap();
+    let tokens: Vec<(String, String)> = imported
+        .tokens
+        .iter()
+        .filter(|t| t.token_type != "separator")
+        .map(|t| (t.token_type.clone(), strip_style_chars(&t.text)))
+        .collect();
+    let expected = [
+        ("scene_heading", "INT. 客厅 - 日"),
+        ("action", "阿强走进来，很累。"),
+        ("dual_dialogue_begin", ""),
+        ("character", "阿珍"),
+        ("parenthetical", "(小声)"),
+        ("dialogue", "你回来了。"),
+        ("character", "JOHN"),
+        ("dialogue", "Hi."),
+        ("dual_dialogue_end", ""),
+        ("transition", "切到："),
+        ("page_break", ""),
+        ("centered", "完"),
+    ];
+    let expected: Vec<(String, String)> = expected
+        .iter()
+        .map(|(t, s)| (t.to_string(), s.to_string()))
+        .collect();
+    assert_eq!(tokens, expected);
+    assert_eq!(imported.tokens[1].number.as_deref(), Some("3"));
+    assert!(!imported.title_page.values().all(|t| t.is_empty()));
+}
@@ -0,0 +1,67 @@
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::outline::{build_outline, export_outline, OutlineFormat, OutlineNode};
+use betterfountain_rust::parser::fountain_parser::FountainParser;
+
+const SCRIPT: &str = "Title: 测试 & 大纲\n\n# 第一幕\n\n= 开场\n\nINT. 客厅 - 日\n\n阿强走进来。[[灯没开]]\n\n@阿强\n你好，今天过得怎么样？\n\nEXT. 花园 - 夜\n\n阿珍坐着。\n";
+
+#[test]
+fn test_outline_json() {
+    let result = FountainParser::new().parse(SCRIPT, &Conf::default(), false, None);
+    let json = export_outline(&result, OutlineFormat::Json);
+    let nodes: Vec<OutlineNode> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        serde_json::to_string(&nodes).unwrap(),
+        serde_json::to_string(&build_outline(&result.properties.structure)).unwrap()
+    );
+
+    // 章节下是两个场景，注解按行号排在顶层
+    assert_eq!(nodes.len(), 2);
+    let act = &nodes[0];
+    assert_eq!(
+        (act.kind.as_str(), act.title.as_str()),
+        ("section", "第一幕")
+    );
+    assert_eq!(act.line, Some(2));
+    assert_eq!(act.synopses, ["开场"]);
+    let scenes: Vec<&str> = act.children.iter().map(|c| c.kind.as_str()).collect();
+    assert_eq!(scenes, ["scene", "scene"]);
+    assert!(act.children[0].title.contains("INT. 客厅 - 日"));
+    assert!(act.children[1].title.contains("EXT. 花园 - 夜"));
+    assert_eq!(act.children[0].line, Some(6));
+    assert_eq!(nodes[1].kind, "note");
+    assert_eq!(nodes[1].title, "灯没开");
+    assert_eq!(nodes[1].line, Some(8));
+
+    // 章节的时长是其下场景之和，后一场从前一场结束时开始
+    let first = &act.children[0];
+    let second = &act.children[1];
+    assert!(first.duration_sec > second.duration_sec && second.duration_sec > 0.0);
+    assert!((act.duration_sec - first.duration_sec - second.duration_sec).abs() < 1e-9);
+    assert!((second.play_sec - first.play_sec - first.duration_sec).abs() < 1e-9);
+}
+
+#[test]
+fn test_outline_opml() {
+    let result = FountainParser::new().parse(SCRIPT, &Conf::default(), false, None);
+    let opml = export_outline(&result, OutlineFormat::Opml);
+    assert!(opml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">"));
+    assert!(opml.contains("<title>测试 &amp; 大纲</title>"));
+
+    // 有子节点的章节成对闭合，场景和注解是空元素；概要放在 _note 中
+    let act = opml
+        .lines()
+        .find(|line| line.contains("_type=\"section\""))
+        .unwrap();
+    assert!(act.trim_start().starts_with("<outline text=\"第一幕\""));
+    assert!(act.ends_with(" _line=\"2\" _note=\"开场\">"));
+    assert_eq!(opml.matches("<outline ").count(), 4);
+    assert_eq!(opml.matches("</outline>").count(), 1);
+    assert_eq!(opml.matches("/>\n").count(), 3);
+    assert!(opml.contains("_type=\"note\""));
+    let section = opml.find("_type=\"section\"").unwrap();
+    let close = opml.find("</outline>").unwrap();
+    for scene in opml.match_indices("_type=\"scene\"").map(|(i, _)| i) {
+        assert!(section < scene && scene < close);
+    }
+    assert!(opml.find("_type=\"note\"").unwrap() > close);
+}
@@ -0,0 +1,151 @@
+use betterfountain_rust::docx::generate_pdf_bytes;
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::fountain_parser::FountainParser;
+use betterfountain_rust::pdf::writer::{text_width, FontStyle, PdfWriter};
+
+// 1x1 的 RGB PNG
+const PNG: &str =
+    "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP4z8AAAAMBAQDJ/pLvAAAAAElFTkSuQmCC";
+
+// 解析剧本并导出为 PDF 文件内容
+async fn export_pdf(script: &str) -> Vec<u8> {
+    let config = Conf::default();
+    let result = FountainParser::new().parse(script, &config, false, None);
+    generate_pdf_bytes(&config, &result).await.unwrap()
+}
+
+fn contains(pdf: &[u8], text: &str) -> bool {
+    pdf.windows(text.len()).any(|w| w == text.as_bytes())
+}
+
+// 交叉引用表中的每个偏移都指向对应编号的对象，返回对象个数
+fn check_xref(pdf: &[u8]) -> usize {
+    // 文件头第二行是二进制注释，按字节取各部分
+    let tail = |offset: usize| {
+        String::from_utf8_lossy(&pdf[offset..pdf.len().min(offset + 64)]).to_string()
+    };
+    let startxref = pdf.windows(10).rposition(|w| w == b"startxref\n").unwrap();
+    let start: usize = tail(startxref + 10)
+        .lines()
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let xref = String::from_utf8(pdf[start..].to_vec()).unwrap();
+    assert!(xref.starts_with("xref\n"));
+    let mut lines = xref.lines().skip(1);
+    let count: usize = lines
+        .next()
+        .unwrap()
+        .split(' ')
+        .nth(1)
+        .unwrap()
+        .parse()
+        .unwrap();
+    for (i, line) in lines.take(count).enumerate().skip(1) {
+        let offset: usize = line[..10].parse().unwrap();
+        assert!(
+            tail(offset).starts_with(&format!("{} 0 obj\n", i)),
+            "object {}",
+            i
+        );
+    }
+    count - 1
+}
+
+#[tokio::test]
+async fn test_pdf_pages_and_text() {
+    let script = "INT. LIVING ROOM - DAY\n\nBob walks in.\n\nBOB\nHello.\n\n===\n\nEXT. 花园 - 夜\n\n阿珍坐着。\n";
+    let pdf = export_pdf(script).await;
+    assert!(pdf.starts_with(b"%PDF-1.4\n"));
+    assert!(pdf.ends_with(b"%%EOF\n"));
+    check_xref(&pdf);
+
+    // 强制分页后是第二页
+    assert!(contains(&pdf, "/Count 2 "));
+    assert!(contains(&pdf, "LIVING ROOM"));
+    assert!(contains(&pdf, "(BOB) Tj"));
+    assert!(contains(&pdf, "(Hello.) Tj"));
+
+    // 中文用 STSong-Light，以 UTF-16BE 十六进制写出
+    assert!(contains(
+        &pdf,
+        "/BaseFont /STSong-Light /Encoding /UniGB-UTF16-H"
+    ));
+    assert!(contains(&pdf, "<82B156ED>"));
+
+    // 不写时间，同样的输入得到同样的文件
+    assert_eq!(pdf, export_pdf(script).await);
+}
+
+#[tokio::test]
+async fn test_pdf_long_action_wraps() {
+    // 一段很长的动作描述折成多行，超过一页时接着排到下一页
+    let line = "Bob walks across the room and looks out of the window. ".repeat(4);
+    let script = format!(
+        "INT. ROOM - DAY\n\n{}\n",
+        vec![line.trim(); 40].join("\n\n")
+    );
+    let pdf = export_pdf(&script).await;
+    check_xref(&pdf);
+    let text = String::from_utf8_lossy(&pdf);
+    let pages: usize = text
+        .split("/Type /Pages /Kids [")
+        .nth(1)
+        .unwrap()
+        .split(" /Count ")
+        .nth(1)
+        .unwrap()
+        .split(' ')
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(pages > 1);
+
+    // 每一行都不超过动作的宽度（一行 Courier 12pt 约 61 个字符）
+    for shown in text.split(") Tj").filter_map(|s| s.rsplit_once('(')) {
+        assert!(shown.1.len() <= 61, "{}", shown.1);
+    }
+}
+
+#[test]
+fn test_pdf_writer() {
+    assert_eq!(text_width("AB", 10.0), 12.0);
+    assert_eq!(text_width("中文", 10.0), 20.0);
+    assert_eq!(text_width("“…”", 10.0), 18.0);
+
+    let mut writer = PdfWriter::new();
+    writer.add_page(612.0, 792.0);
+    writer.text(72.0, 72.0, "(a) 中", 12.0, FontStyle::default(), None);
+    let bold = FontStyle {
+        bold: true,
+        italic: false,
+    };
+    writer.text(72.0, 90.0, "“Hi”", 12.0, bold, Some((1.0, 0.0, 0.0)));
+
+    // PNG 的压缩数据直接嵌入，不支持的格式返回 None
+    let image = writer.add_image(&base64::decode(PNG).unwrap()).unwrap();
+    assert_eq!(writer.image_size(image), (1, 1));
+    assert!(writer.add_image(b"GIF89a").is_none());
+    writer.draw_image(image, 72.0, 100.0, 36.0, 36.0);
+    writer.link(72.0, 72.0, 50.0, 12.0, "https://example.com");
+    writer.add_page(612.0, 792.0);
+    assert_eq!(writer.page_count(), 2);
+
+    let pdf = writer.finish("标题");
+    // 目录、页面树、文档信息，4 个 Courier，3 个 STSong-Light 相关对象，资源，1 张图片，2 页各两个对象
+    assert_eq!(check_xref(&pdf), 16);
+    assert!(contains(&pdf, "/F1 12 Tf"));
+    assert!(contains(&pdf, "(\\(a\\) ) Tj"));
+    assert!(contains(&pdf, "<4E2D> Tj"));
+    assert!(contains(&pdf, "/F2 12 Tf"));
+    assert!(contains(&pdf, "(\\223Hi\\224) Tj"));
+    assert!(contains(
+        &pdf,
+        "/Filter /FlateDecode /DecodeParms << /Predictor 15 /Colors 3"
+    ));
+    assert!(contains(&pdf, "/Im1 Do"));
+    assert!(contains(&pdf, "/URI (https://example.com)"));
+    assert!(contains(&pdf, "/Title <FEFF68079898>"));
+}
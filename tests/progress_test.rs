@@ -0,0 +1,46 @@
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::FountainParser;
+use betterfountain_rust::progress::{CancellationToken, Cancelled, ExportPhase, Progress};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_parse_progress_and_cancel() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\n".repeat(40);
+    let lines = script.split('\n').count();
+    let parser = FountainParser::new();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    let progress = Progress::new().on_progress(move |event| recorded.lock().unwrap().push(event));
+    let output = parser
+        .parse_with_progress(&script, &Conf::default(), false, None, &progress)
+        .unwrap();
+    assert_eq!(
+        output.tokens.len(),
+        parser
+            .parse(&script, &Conf::default(), false, None)
+            .tokens
+            .len()
+    );
+
+    // 每隔若干行通知一次，最后一次为全部完成
+    let events = events.lock().unwrap();
+    assert!(events.len() > 2 && events.len() < lines);
+    assert!(events
+        .iter()
+        .all(|e| e.phase == ExportPhase::Parse && e.total == lines));
+    assert!(events.windows(2).all(|w| w[0].done < w[1].done));
+    assert_eq!(events.last().unwrap().fraction(), 1.0);
+
+    // 在回调中取消，解析返回 Cancelled
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    let progress = Progress::new().with_token(token).on_progress(move |event| {
+        if event.done > 0 {
+            canceller.cancel();
+        }
+    });
+    let result = parser.parse_with_progress(&script, &Conf::default(), false, None, &progress);
+    assert_eq!(result.err(), Some(Cancelled));
+    assert!(progress.token().is_cancelled());
+}
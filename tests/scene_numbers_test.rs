@@ -0,0 +1,116 @@
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::{
+    find_duplicate_scene_numbers, lock_scene_numbers, merge_scripts,
+    repair_duplicate_scene_numbers, LockedNumberStyle, MergeSource,
+};
+
+#[test]
+fn test_lock_scene_numbers() {
+    let conf = Conf::default();
+
+    // 全新剧本：顺序编号
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\nEXT. 街道 - 夜\n\n下雨了。\n";
+    let locked = lock_scene_numbers(script, &conf, LockedNumberStyle::Suffix);
+    assert!(locked.contains("INT. 客厅 - 日 #1#"));
+    assert!(locked.contains("EXT. 街道 - 夜 #2#"));
+
+    // 已锁定的 1、2 之间新增场景
+    let script = "INT. 客厅 - 日 #1#\n\n阿强走进来。\n\nINT. 厨房 - 日\n\n水开了。\n\nEXT. 街道 - 夜 #2#\n\n下雨了。\n";
+    let suffix = lock_scene_numbers(script, &conf, LockedNumberStyle::Suffix);
+    assert!(suffix.contains("INT. 厨房 - 日 #1A#"));
+    assert!(suffix.contains("EXT. 街道 - 夜 #2#"));
+    let prefix = lock_scene_numbers(script, &conf, LockedNumberStyle::Prefix);
+    assert!(prefix.contains("INT. 厨房 - 日 #A1#"));
+}
+
+#[test]
+fn test_repair_duplicate_scene_numbers() {
+    let conf = Conf::default();
+    let script = "INT. 客厅 - 日 #5#\n\n阿强走进来。\n\nINT. 厨房 - 日 #5#\n\n水开了。\n\nEXT. 街道 - 夜 #3#\n\n下雨了。\n";
+
+    let dups = find_duplicate_scene_numbers(script, &conf);
+    assert_eq!(dups.len(), 1);
+    assert_eq!(dups[0].number, "5");
+    assert_eq!(dups[0].lines, vec![0, 4]);
+
+    let repaired = repair_duplicate_scene_numbers(script, &conf, LockedNumberStyle::Suffix);
+    assert!(repaired.contains("INT. 厨房 - 日 #5A#"));
+    assert!(find_duplicate_scene_numbers(&repaired, &conf).is_empty());
+}
+
+#[test]
+fn test_merge_scripts() {
+    let sources = vec![
+        MergeSource {
+            name: "第一集.fountain".to_string(),
+            script: "Title: 合集\nAuthor: 阿强\n\nINT. 客厅 - 日 #5#\n\n阿强走进来。\n".to_string(),
+        },
+        MergeSource {
+            name: "第二集.fountain".to_string(),
+            script: "Title: 第二集\nDraft date: 2024\n\nEXT. 花园 - 夜 #1#\n\n月光洒在草地上。\n".to_string(),
+        },
+    ];
+    let merged = merge_scripts(&sources, &Conf::default());
+
+    assert!(merged.script.starts_with("Title: 合集\nAuthor: 阿强\nDraft date: 2024\n\n"));
+    assert!(!merged.script.contains("第二集\n"));
+
+    let numbers: Vec<String> = merged
+        .output
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "scene_heading")
+        .map(|t| t.number.clone().unwrap_or_default())
+        .collect();
+    assert_eq!(numbers, vec!["1", "2"]);
+
+    let garden = merged
+        .output
+        .tokens
+        .iter()
+        .find(|t| t.token_type == "scene_heading" && t.text.contains("花园"))
+        .unwrap();
+    assert_eq!(merged.locate(garden.line), Some(("第二集.fountain", 3)));
+    assert_eq!(merged.locate(0), None);
+}
+
+#[test]
+fn test_reorder_scenes() {
+    use betterfountain_rust::parse;
+    use betterfountain_rust::reorder::{reorder_scene_text, reorder_scenes, ReorderError};
+
+    let conf = Conf::default();
+    let script = "# 第一幕\n\nINT. 客厅 - 日\n\n阿强走进来。\n\nINT. 厨房 - 日 #5#\n\n水开了。\n\nEXT. 街道 - 夜\n\n下雨了。\n";
+    let order = vec!["3".to_string(), "1".to_string()];
+
+    // Fountain 源文本：章节留在原处，场景写入原来的编号
+    let text = reorder_scene_text(script, &conf, &order).unwrap();
+    assert_eq!(
+        text,
+        "# 第一幕\n\nEXT. 街道 - 夜 #3#\n\n下雨了。\n\nINT. 客厅 - 日 #1#\n\n阿强走进来。\n\nINT. 厨房 - 日 #5#\n\n水开了。\n"
+    );
+
+    // 解析结果：编号不变，记录重排后的序号
+    let output = parse(script, &conf, false, None);
+    let reordered = reorder_scenes(&output, &order, true).unwrap();
+    let scenes: Vec<(String, String)> = reordered
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "scene_heading")
+        .map(|t| {
+            let number = t.number.clone().unwrap_or_default();
+            let order = t.metadata.as_ref().and_then(|m| m.get("order"));
+            (number, order.cloned().unwrap_or_default())
+        })
+        .collect();
+    assert_eq!(
+        scenes,
+        [("3", "1"), ("1", "2"), ("5", "3")].map(|(n, o)| (n.to_string(), o.to_string()))
+    );
+    assert_eq!(reordered.tokens.len(), output.tokens.len());
+
+    assert_eq!(
+        reorder_scenes(&output, &["7".to_string()], false).unwrap_err(),
+        ReorderError::UnknownScene("7".to_string())
+    );
+}
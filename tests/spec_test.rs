@@ -0,0 +1,131 @@
+use betterfountain_rust::parser::{detect_extensions, used_extensions, Extension};
+use betterfountain_rust::utils::strip_style_chars;
+use betterfountain_rust::{parse, Conf};
+use std::fs;
+use std::path::Path;
+
+// 每个 tests/test_data/spec/*.fountain 旁有同名的 .tokens，每行一个 token："类型: 文本"，不含空行分隔
+// 期望结果是按 fountain.io 语法说明中的规则和示例手工整理的；官方示例剧本（Brick & Steel、
+// The Last Birthday Card）没有收进仓库，需要时把剧本和按同样格式整理的 .tokens 放进该目录即可
+#[test]
+fn test_spec_fixtures() {
+    let conf = Conf {
+        spec_mode: true,
+        ..Conf::default()
+    };
+    let dir = Path::new("tests/test_data/spec");
+    let mut fixtures: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "fountain"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+
+    for path in fixtures {
+        let script = fs::read_to_string(&path).unwrap();
+        let expected = fs::read_to_string(path.with_extension("tokens")).unwrap();
+        let actual: Vec<String> = parse(&script, &conf, false, None)
+            .tokens
+            .iter()
+            .filter(|t| t.token_type != "separator")
+            .map(|t| {
+                let text = strip_style_chars(&t.text).trim().to_string();
+                if text.is_empty() {
+                    t.token_type.clone()
+                } else {
+                    format!("{}: {}", t.token_type, text)
+                }
+            })
+            .collect();
+        assert_eq!(
+            actual,
+            expected.lines().collect::<Vec<_>>(),
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn test_detect_extensions() {
+    let conf = Conf::default();
+    let standard = fs::read_to_string("tests/test_data/spec/dialogue.fountain").unwrap();
+    assert!(used_extensions(&standard, &conf).is_empty());
+
+    let script = fs::read_to_string("tests/test_data/spec/extensions.fountain").unwrap();
+    assert_eq!(
+        used_extensions(&script, &conf),
+        vec![
+            Extension::ShotCut,
+            Extension::InlineNote,
+            Extension::ChineseMarkers,
+            Extension::Image,
+            Extension::Variable
+        ]
+    );
+    let uses = detect_extensions(&script, &conf);
+    assert_eq!(uses[0].extension, Extension::ChineseMarkers);
+    assert_eq!(uses[0].line, 3);
+    assert_eq!(Extension::from_name("SHOT_CUT"), Some(Extension::ShotCut));
+
+    // 默认解析时全角括号是括号注释，规范模式下只是对白
+    let parenthetical = |conf: &Conf| {
+        parse(&script, conf, false, None)
+            .tokens
+            .iter()
+            .any(|t| t.token_type == "parenthetical")
+    };
+    let spec = Conf {
+        spec_mode: true,
+        ..Conf::default()
+    };
+    assert!(parenthetical(&conf));
+    assert!(!parenthetical(&spec));
+}
+
+#[test]
+fn test_disabled_extensions() {
+    let script = "Title: 测试\nMetadata: {\"print\": {\"chinaFormat\": 1}}\n\nINT. 客厅 - 日\n\n@阿强\n（小声）\n你好。\n\n> {+ 交切 +} ↓\n\n阿强走进来。\n\n> {- 交切 -} ↑\n";
+    let conf = Conf::default();
+    assert!(used_extensions(script, &conf).contains(&Extension::ChinaFormat));
+
+    // 只关闭镜头交切，全角括号照常是括号注释
+    let conf = Conf {
+        disabled_extensions: vec!["shot_cut".to_string()],
+        ..Conf::default()
+    };
+    assert!(!conf.extension_enabled(Extension::ShotCut));
+    assert!(conf.extension_enabled(Extension::ChineseMarkers));
+    let output = parse(script, &conf, false, None);
+    assert!(output.properties.shot_cuts.is_empty());
+    assert!(output
+        .tokens
+        .iter()
+        .any(|t| t.token_type == "parenthetical"));
+    assert!(!parse(script, &Conf::default(), false, None)
+        .properties
+        .shot_cuts
+        .is_empty());
+}
+
+#[test]
+fn test_ellipsis_line_in_spec_mode() {
+    // 标准 Fountain 中以省略号开头的行是动作，默认模式仍按 . 强制为场景标题
+    let script = "INT. 客厅 - 日\n\n...and then nothing.\n";
+    let first_type = |conf: &Conf| {
+        parse(script, conf, false, None)
+            .tokens
+            .iter()
+            .filter(|t| t.text.to_lowercase().contains("and then"))
+            .map(|t| t.token_type.clone())
+            .next()
+            .unwrap()
+    };
+    let spec = Conf {
+        spec_mode: true,
+        ..Conf::default()
+    };
+    assert_eq!(first_type(&spec), "action");
+    assert_eq!(first_type(&Conf::default()), "scene_heading");
+}
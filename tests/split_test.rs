@@ -0,0 +1,63 @@
+use betterfountain_rust::split::{split_script, SplitBy};
+use betterfountain_rust::{parse, Conf};
+
+const SCRIPT: &str = r#"Title: 三幕剧
+
+开场字幕。
+
+# 第一幕
+
+INT. 客厅 - 日
+
+阿强走进来。
+
+EXT. 街道 - 夜
+
+阿珍在等车。
+
+# 第二幕
+
+INT. 车站 - 夜
+
+阿强追上来。
+"#;
+
+#[test]
+fn test_split_by_section() {
+    let output = parse(SCRIPT, &Conf::default(), false, None);
+    let parts = split_script(&output, SplitBy::Section);
+
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].title, "第一幕");
+    assert_eq!(parts[1].title, "第二幕");
+    assert_eq!(parts[0].first_page, 1);
+    // 第一个章节之前的内容归入第一部分，各部分保留标题页
+    assert!(parts[0]
+        .output
+        .tokens
+        .iter()
+        .any(|t| t.text.contains("开场字幕")));
+    assert!(!parts[1].output.title_page.is_empty());
+    let scenes = |i: usize| {
+        parts[i]
+            .output
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == "scene_heading")
+            .count()
+    };
+    assert_eq!((scenes(0), scenes(1)), (2, 1));
+}
+
+#[test]
+fn test_split_by_scenes() {
+    assert_eq!(SplitBy::from_name("section"), Some(SplitBy::Section));
+    assert_eq!(SplitBy::from_name("2"), Some(SplitBy::Scenes(2)));
+    assert_eq!(SplitBy::from_name("0"), None);
+
+    let output = parse(SCRIPT, &Conf::default(), false, None);
+    let parts = split_script(&output, SplitBy::Scenes(2));
+    let titles: Vec<&str> = parts.iter().map(|p| p.title.as_str()).collect();
+    // 场景编号沿用全剧的编号
+    assert_eq!(titles, ["场景 1-2", "场景 3"]);
+}
@@ -5,7 +5,7 @@ use std::path::Path;
 
 #[test]
 fn test_aqiang_aizhen_dialogue() {
-    let mut parser = FountainParser::new();
+    let parser = FountainParser::new();
     let script_path = "/Users/arming/Documents/write/无限失恋，珍强！/无限失恋，珍强！-[03].fountain";
     let script = fs::read_to_string(script_path).expect("无法读取");
 
@@ -41,7 +41,7 @@ fn test_aqiang_aizhen_dialogue() {
 #[test]
 fn test_statistics_debug() {
     // 创建解析器
-    let mut parser = FountainParser::new();
+    let parser = FountainParser::new();
 
     // 读取中文测试文件
     let script_path = Path::new("tests/test_data/黑色爱情诗.fountain");
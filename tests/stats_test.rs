@@ -0,0 +1,286 @@
+use betterfountain_rust::models::Conf;
+use betterfountain_rust::parser::FountainParser;
+use betterfountain_rust::stats::ScriptStats;
+
+#[test]
+fn test_script_stats() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来，坐下。\n\n@阿强\n你好，今天天气不错。\n\nEXT. 花园 - 夜\n\n月光洒在草地上。\n\nINT. 客厅 - 夜\n\n@阿珍\n该睡觉了。\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, false, None);
+    let stats = ScriptStats::from_output(&output);
+
+    assert_eq!(stats.scene_count, 3);
+    assert_eq!(stats.scenes_by_type.int, 2);
+    assert_eq!(stats.scenes_by_type.ext, 1);
+    assert!(stats.dialogue_sec > 0.0 && stats.action_sec > 0.0);
+    assert!((stats.runtime_sec - stats.dialogue_sec - stats.action_sec).abs() < 1e-9);
+    assert!(stats.dialogue_ratio > 0.0 && stats.dialogue_ratio < 1.0);
+    assert!((stats.average_scene_sec * 3.0 - stats.runtime_sec).abs() < 1e-6);
+
+    let living_room = stats.locations.iter().find(|l| l.name == "客厅").unwrap();
+    assert_eq!(living_room.scene_count, 2);
+
+    let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+    assert_eq!(json["scenes_by_type"]["ext"], 1);
+}
+
+#[test]
+fn test_scene_durations_with_shot_cut() {
+    let script = "INT. 客厅 - 日\n\n阿强在打电话。\n\n> {+ 交切 +} ↓\n\nEXT. 街道 - 日\n\n阿珍边走边接电话。\n\nINT. 办公室 - 日\n\n阿强挂断电话。\n\n> {- 交切结束 -} ↑\n\nEXT. 花园 - 夜\n\n月光洒在草地上。\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, false, None);
+    let rows = betterfountain_rust::stats::scene_durations(&output);
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].shot_cut.as_deref(), Some("+"));
+    assert_eq!(rows[0].numbers, vec!["1", "2", "3"]);
+    assert_eq!(rows[1].shot_cut, None);
+    let total: f64 = rows.iter().map(|r| r.duration_sec).sum();
+    assert!((total - output.length_action - output.length_dialogue).abs() < 1e-6);
+
+    let csv = betterfountain_rust::stats::scene_durations_csv(&rows);
+    assert!(csv.starts_with("number,title,line,start_sec,duration_sec,shot_cut\n"));
+    assert!(csv.lines().nth(1).unwrap().starts_with("1/2/3,"));
+
+    let locations = betterfountain_rust::stats::shot_cut_locations(&output);
+    assert_eq!(locations[&4], vec!["客厅", "街道", "办公室"]);
+    let scenes = betterfountain_rust::stats::intercut_scene_locations(&output);
+    assert_eq!(scenes.len(), 3);
+    assert_eq!(scenes[&6], "街道");
+}
+
+#[test]
+fn test_dialogue_duration_models() {
+    use betterfountain_rust::duration::{
+        DurationModel, SpeakingRateModel, SyllableModel, WordsPerMinuteModel,
+    };
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    let wpm = WordsPerMinuteModel {
+        words_per_minute: 120.0,
+        sec_per_punc_short: 0.0,
+        sec_per_punc_long: 0.0,
+    };
+    assert!((wpm.dialogue_duration("one two three four", None) - 2.0).abs() < 1e-9);
+
+    let syllables = SyllableModel {
+        syllables_per_sec: 2.0,
+        sec_per_punc_short: 0.0,
+        sec_per_punc_long: 0.5,
+    };
+    // "hello" 两个音节，"你好" 两个音节，句号停顿 0.5 秒
+    assert!((syllables.dialogue_duration("hello 你好。", None) - 2.5).abs() < 1e-9);
+
+    let fast = SpeakingRateModel {
+        base: Arc::new(syllables),
+        rates: HashMap::from([("阿强".to_string(), 2.0)]),
+    };
+    assert!((fast.dialogue_duration("你好你好", Some("阿强")) - 1.0).abs() < 1e-9);
+    assert!((fast.dialogue_duration("你好你好", Some("阿珍")) - 2.0).abs() < 1e-9);
+
+    // 通过配置选择模型并按角色调整语速
+    let script = "INT. 客厅 - 日\n\n@阿强\n你好你好\n\n@阿珍\n你好你好\n";
+    let conf = Conf {
+        dialogue_duration_model: "syllables".to_string(),
+        dialogue_syllables_per_sec: 4.0,
+        speaking_rates: HashMap::from([("阿强".to_string(), 2.0)]),
+        ..Default::default()
+    };
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, false, None);
+    assert!((output.length_dialogue - 1.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_metadata_character_rates() {
+    let body = "INT. 客厅 - 日\n\n@GRANDPA\n你好你好\n\n@阿珍\n你好你好\n";
+    let conf = Conf::default();
+    let parser = FountainParser::new();
+    let plain = parser.parse(body, &conf, false, None);
+    assert!((plain.length_dialogue - 2.4).abs() < 1e-9);
+
+    // GRANDPA 每字 0.45 秒，比默认的 0.3 秒慢
+    let script = format!(
+        "Title: 测试\nMetadata: {{\"rates\": {{\"GRANDPA\": 0.45}}}}\n\n{}",
+        body
+    );
+    let parser = FountainParser::new();
+    let output = parser.parse(&script, &conf, false, Some(true));
+    assert!((output.length_dialogue - 3.0).abs() < 1e-9);
+    let dialogue: Vec<f64> = output
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "dialogue")
+        .filter_map(|t| t.time)
+        .collect();
+    assert_eq!(dialogue.len(), 2);
+    assert!((dialogue[0] - 1.8).abs() < 1e-9);
+    assert!((dialogue[1] - 1.2).abs() < 1e-9);
+}
+
+#[test]
+fn test_metadata_rates_override_config() {
+    use std::collections::HashMap;
+
+    // 配置和 metadata 都为 GRANDPA 设置了语速时以 metadata 的 rates 为准；
+    // metadata 中没有 speaking_rates 键，写了也不生效
+    let script = concat!(
+        "Title: 测试\n",
+        "Metadata: {\"rates\": {\"GRANDPA\": 0.45}, \"speaking_rates\": {\"阿珍\": 0.5}}\n\n",
+        "INT. 客厅 - 日\n\n@GRANDPA\n你好你好\n\n@阿珍\n你好你好\n"
+    );
+    let conf = Conf {
+        speaking_rates: HashMap::from([("GRANDPA".to_string(), 3.0), ("阿珍".to_string(), 2.0)]),
+        ..Default::default()
+    };
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &conf, false, None);
+    let dialogue: Vec<f64> = output
+        .tokens
+        .iter()
+        .filter(|t| t.token_type == "dialogue")
+        .filter_map(|t| t.time)
+        .collect();
+    assert_eq!(dialogue.len(), 2);
+    assert!((dialogue[0] - 1.8).abs() < 1e-9);
+    assert!((dialogue[1] - 0.6).abs() < 1e-9);
+}
+
+#[test]
+fn test_location_summaries() {
+    use betterfountain_rust::stats::{
+        location_summaries, location_summaries_csv, scenes_by_time_of_day,
+    };
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来，坐下。\n\nEXT. 花园 - 夜\n\n月光洒在草地上。\n\nINT. 客厅 - 夜\n\n灯灭了。\n";
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &Conf::default(), false, None);
+
+    let rows = location_summaries(&output);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].name, "客厅");
+    assert_eq!(rows[0].int_ext, "INT");
+    assert_eq!(rows[0].times_of_day, vec!["日", "夜"]);
+    assert_eq!(rows[0].scene_numbers, vec!["1", "3"]);
+    assert!(rows[0].duration_sec > 0.0);
+    assert!(rows[0]
+        .display()
+        .starts_with("客厅 | INT | 日 / 夜 | SC. 1, 3 | 00:"));
+    assert_eq!(rows[1].int_ext, "EXT");
+
+    assert_eq!(
+        scenes_by_time_of_day(&output),
+        vec![("夜".to_string(), 2), ("日".to_string(), 1)]
+    );
+
+    let csv = location_summaries_csv(&rows);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "name,int_ext,times_of_day,scenes,duration_sec");
+    assert!(lines[1].starts_with("客厅,INT,日/夜,1 3,"));
+}
+
+#[test]
+fn test_dialogue_balance() {
+    use betterfountain_rust::stats::dialogue_balance;
+
+    let script = "Title: 测试\nMetadata: {\"characters\": {\"阿珍\": {\"gender\": \"female\"}, \"阿美\": {\"gender\": \"female\"}, \"阿强\": {\"gender\": \"male\"}}}\n\nINT. 客厅 - 日\n\n@阿珍\n你看见他了吗？\n\n@阿美\n没有。\n\n@阿强\n我在这儿。\n\nEXT. 花园 - 夜\n\n@阿强\n走吧。\n\n@路人\n等等我。\n";
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &Conf::default(), false, None);
+    let balance = dialogue_balance(&output, "gender");
+
+    assert_eq!(balance.tag, "gender");
+    assert_eq!(balance.characters[0].name, "阿珍");
+    assert_eq!(balance.characters[0].value.as_deref(), Some("female"));
+    assert_eq!(balance.characters[0].words, 6);
+
+    assert_eq!(balance.groups[0].value.as_deref(), Some("female"));
+    assert_eq!(balance.groups[0].characters, vec!["阿珍", "阿美"]);
+    assert_eq!(balance.groups[0].words, 8);
+    assert_eq!(balance.groups[1].value.as_deref(), Some("male"));
+    assert_eq!(balance.groups[1].speeches, 2);
+    // 没有标签的角色单独一组
+    assert_eq!(balance.groups[2].value, None);
+    assert_eq!(balance.groups[2].characters, vec!["路人"]);
+    let total: f64 = balance.groups.iter().map(|g| g.share).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+
+    // 只有第一场中阿珍和阿美之间的对话算同一标签内部的对话
+    assert_eq!(balance.conversations.len(), 1);
+    assert_eq!(balance.conversations[0].value, "female");
+    assert_eq!(balance.conversations[0].scene_number, "1");
+    assert_eq!(balance.conversations[0].characters, vec!["阿珍", "阿美"]);
+
+    assert!(balance.to_json().contains("\"conversations\""));
+}
+
+#[test]
+fn test_time_of_day_report() {
+    use betterfountain_rust::stats::{normalize_time_of_day, time_of_day_report, TimeOfDay};
+
+    assert_eq!(normalize_time_of_day(" night. "), Some(TimeOfDay::Night));
+    assert_eq!(normalize_time_of_day("黄昏"), Some(TimeOfDay::Dusk));
+    assert_eq!(normalize_time_of_day("LATER"), None);
+
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n\nEXT. 花园 - NIGHT\n\n月光。\n\nINT. 客厅 - 夜\n\n灯灭了。\n\nEXT. 街道 - 稍后\n\n车开走了。\n\nINT. 卧室\n\n一片漆黑。\n";
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &Conf::default(), false, None);
+
+    let report = time_of_day_report(&output);
+    assert_eq!(report.count(TimeOfDay::Day), 1);
+    assert_eq!(report.count(TimeOfDay::Night), 2);
+    assert_eq!(report.count(TimeOfDay::Dawn), 0);
+    assert_eq!(report.unspecified, 1);
+    assert_eq!(report.nonstandard.len(), 1);
+    assert_eq!(report.nonstandard[0].value, "稍后");
+    assert_eq!(report.nonstandard[0].scene_numbers, vec!["4"]);
+}
+
+#[test]
+fn test_location_hierarchy() {
+    use betterfountain_rust::stats::{location_summaries, location_tree};
+
+    let script = "INT. HOUSE - KITCHEN - NIGHT\n\n水壶响了。\n\nEXT. 花园 - 夜\n\n月光。\n\nINT. HOUSE - NIGHT\n\n灯灭了。\n\nINT. HOUSE - UPSTAIRS - BEDROOM - DAY\n\n阳光。\n\nINT. 客厅 - 日 - 雨\n\n雨声。\n";
+    let parser = FountainParser::new();
+    let output = parser.parse(script, &Conf::default(), false, None);
+
+    let kitchen = &output.properties.locations["HOUSE-KITCHEN"][0];
+    assert_eq!(kitchen.name, "HOUSE - KITCHEN");
+    assert_eq!(kitchen.master, "HOUSE");
+    assert_eq!(kitchen.sub_locations, vec!["KITCHEN"]);
+    assert_eq!(kitchen.time_of_day, "NIGHT");
+    // 第一段之后的标准时间开始都算作时间
+    let living_room = &output.properties.locations["客厅"][0];
+    assert_eq!(living_room.time_of_day, "日 - 雨");
+
+    let names: Vec<String> = location_summaries(&output)
+        .into_iter()
+        .map(|row| row.name)
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "HOUSE",
+            "HOUSE-KITCHEN",
+            "HOUSE-UPSTAIRS-BEDROOM",
+            "花园",
+            "客厅"
+        ]
+    );
+
+    let tree = location_tree(&output);
+    assert_eq!(tree.len(), 3);
+    let house = &tree[0];
+    assert_eq!(house.name, "HOUSE");
+    assert_eq!(house.scene_count, 3);
+    assert_eq!(house.scene_numbers, vec!["3"]);
+    assert_eq!(house.children.len(), 2);
+    assert_eq!(house.children[0].path, "HOUSE - KITCHEN");
+    assert_eq!(
+        house.children[1].children[0].path,
+        "HOUSE - UPSTAIRS - BEDROOM"
+    );
+    assert_eq!(house.children[1].children[0].scene_numbers, vec!["4"]);
+}
@@ -0,0 +1,34 @@
+use betterfountain_rust::docx::PrintProfile;
+use betterfountain_rust::typewriter::generate_typewriter;
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_typewriter_columns() {
+    let config = Conf::default();
+    let mut profile = PrintProfile {
+        page_width: 8.5,
+        right_margin: 1.0,
+        ..PrintProfile::default()
+    };
+    profile.action.feed = 1.5;
+    profile.character.feed = 4.2;
+    profile.dialogue.feed = 2.8;
+    profile.parenthetical.feed = 3.4;
+
+    let script = "INT. HOUSE - DAY #12#\n\nJohn walks in.\n\nJOHN\n(quietly)\nHello there, this line of dialogue is long enough that it has to wrap.\n\nCUT TO:\n\n===\n\n> THE END <\n";
+    let text = generate_typewriter(&parse(script, &config, false, None), &profile);
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert_eq!(lines[0], format!("{}12    INT. HOUSE - DAY", " ".repeat(9)));
+    assert_eq!(lines[2], format!("{}John walks in.", " ".repeat(15)));
+    // 角色名从第 43 列开始，对话从第 29 列开始
+    assert_eq!(lines[4], format!("{}JOHN", " ".repeat(42)));
+    assert_eq!(lines[5], format!("{}(quietly)", " ".repeat(34)));
+    assert!(lines[6].starts_with(&format!("{}Hello", " ".repeat(28))));
+    assert!(lines[6..9].iter().all(|l| l.chars().count() <= 62));
+    assert_eq!(lines[7].trim(), "is long enough that it has to");
+    assert_eq!(lines[8].trim(), "wrap.");
+    assert_eq!(lines[10], format!("{}CUT TO:", " ".repeat(68)));
+    assert!(lines[11].starts_with('\u{c}'));
+    assert!(lines[11].contains("THE END"));
+}
@@ -0,0 +1,36 @@
+use betterfountain_rust::typography::typeset;
+use betterfountain_rust::utils::strip_style_chars;
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_typeset_languages() {
+    assert_eq!(
+        typeset("\"Don't go--wait...\" she said 'quietly'.", "en"),
+        "“Don’t go—wait…” she said ‘quietly’."
+    );
+    assert_eq!(typeset("Er sagte \"Hallo\".", "de-DE"), "Er sagte „Hallo“.");
+    assert_eq!(typeset("\"Oui\"", "fr"), "«\u{a0}Oui\u{a0}»");
+    assert_eq!(typeset("他说:\"等等--......\"", "zh"), "他说:“等等——……”");
+    assert_eq!(typeset("\"Hi\"", ""), "\"Hi\"");
+}
+
+#[test]
+fn test_typeset_keeps_style_chars_and_notes() {
+    // 样式标记不影响引号开闭的判断，注解中的内容保持原样
+    assert_eq!(
+        typeset("↭\"Run\"↭ now↺\"raw\" -- ...↻", "en"),
+        "↭“Run”↭ now↺\"raw\" -- ...↻"
+    );
+
+    let conf = Conf {
+        typography: "en".to_string(),
+        ..Conf::default()
+    };
+    let output = parse("INT. HOUSE - DAY\n\nJOHN\n\"Wait--\"\n", &conf, false, None);
+    let dialogue = output
+        .tokens
+        .iter()
+        .find(|t| t.token_type == "dialogue")
+        .unwrap();
+    assert_eq!(strip_style_chars(&dialogue.text), "“Wait—”");
+}
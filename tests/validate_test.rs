@@ -0,0 +1,90 @@
+use betterfountain_rust::docx::{validate_docx, ValidationError};
+use std::io::Write;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+<Default Extension="xml" ContentType="application/xml"/>
+</Types>"#;
+
+const PACKAGE_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+</Relationships>"#;
+
+const DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header1.xml"/>
+<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/footnotes" Target="footnotes.xml"/>
+<Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com" TargetMode="External"/>
+</Relationships>"#;
+
+const FOOTNOTES: &str = r#"<w:footnotes><w:footnote w:id="1"><w:p/></w:footnote></w:footnotes>"#;
+
+fn package(document: &str, extra: &[(&str, &str)]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let mut parts = vec![
+        ("[Content_Types].xml", CONTENT_TYPES),
+        ("_rels/.rels", PACKAGE_RELS),
+        ("word/document.xml", document),
+        ("word/_rels/document.xml.rels", DOCUMENT_RELS),
+        ("word/header1.xml", "<w:hdr/>"),
+        ("word/footnotes.xml", FOOTNOTES),
+    ];
+    parts.extend_from_slice(extra);
+    for (name, content) in parts {
+        writer
+            .start_file(name, zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(content.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_validate_docx() {
+    let valid = r#"<w:document><w:body>
+<w:p><w:r><w:footnoteReference w:id="1"/></w:r><w:hyperlink r:id="rId3"/></w:p>
+<w:sectPr><w:headerReference w:type="default" r:id="rId1"/><w:pgSz w:w="11906" w:h="16838"/><w:pgMar w:top="1440" w:right="1800" w:bottom="1440" w:left="1800"/><w:cols w:space="425"/></w:sectPr>
+</w:body></w:document>"#;
+    assert_eq!(validate_docx(&package(valid, &[])), vec![]);
+
+    let broken = r#"<w:document><w:body>
+<w:p><w:r><w:footnoteReference w:id="2"/><w:commentReference w:id="0"/></w:r></w:p>
+<w:p><w:pPr><w:sectPr><w:headerReference w:type="default" r:id="rId9"/><w:pgMar w:top="1440" w:right="1800" w:bottom="1440" w:left="1800"/><w:pgSz w:w="11906" w:h="16838"/></w:sectPr></w:pPr></w:p>
+<w:sectPr><w:headerReference w:type="default" r:id="rId1"/><w:headerReference w:type="default" r:id="rId1"/><w:pgSz w:w="2000" w:h="16838"/><w:pgMar w:top="0" w:right="1000" w:bottom="0" w:left="1000"/></w:sectPr>
+<w:p/>
+</w:body></w:document>"#;
+    let errors = validate_docx(&package(broken, &[("word/media/image1.png", "")]));
+    let expected = [
+        ValidationError::MissingContentType("word/media/image1.png".to_string()),
+        ValidationError::UnknownRelationship {
+            part: "word/document.xml".to_string(),
+            id: "rId9".to_string(),
+        },
+        ValidationError::UnknownFootnote("2".to_string()),
+        ValidationError::UnknownComment("0".to_string()),
+        ValidationError::InvalidSection {
+            index: 1,
+            reason: "子元素 pgSz 应排在 pgMar 之前".to_string(),
+        },
+        ValidationError::InvalidSection {
+            index: 2,
+            reason: "左右页边距之和超出页面宽度".to_string(),
+        },
+        ValidationError::InvalidSection {
+            index: 2,
+            reason: "headerReference 的类型 default 重复".to_string(),
+        },
+        ValidationError::InvalidSection {
+            index: 2,
+            reason: "最后一个节的属性必须是 body 的最后一个元素".to_string(),
+        },
+    ];
+    assert_eq!(errors, expected);
+
+    assert!(matches!(
+        validate_docx(b"not a zip").as_slice(),
+        [ValidationError::InvalidPackage(_)]
+    ));
+}
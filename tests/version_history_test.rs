@@ -0,0 +1,48 @@
+use betterfountain_rust::version_history::{version_history, VersionEntry};
+use betterfountain_rust::{parse, Conf};
+
+#[test]
+fn test_version_history_from_title_page() {
+    let script = r#"Title: 我的剧本
+Author: 张三
+Revision: 3
+Draft date: 2024-06-01
+Metadata: {"history": [{"version": 1, "date": "2024-03-01", "author": "张三", "notes": "初稿", "approved_by": "李四"}, {"version": "2", "date": "2024-05-01", "changes": "改结局"}]}
+
+INT. 客厅 - 日
+
+阿强走进来。
+"#;
+    let output = parse(script, &Conf::default(), false, None);
+    let history = version_history(&output);
+
+    assert_eq!(history.len(), 3);
+    assert_eq!(
+        history[0].cells(),
+        ["1", "2024-03-01", "张三", "初稿", "李四"]
+    );
+    assert_eq!(history[1].notes, "改结局");
+    // 当前版本取自标题页，排在最后
+    assert_eq!(
+        history[2],
+        VersionEntry {
+            version: "3".to_string(),
+            date: "2024-06-01".to_string(),
+            author: "张三".to_string(),
+            ..VersionEntry::default()
+        }
+    );
+}
+
+#[test]
+fn test_version_history_without_revision() {
+    let script = "INT. 客厅 - 日\n\n阿强走进来。\n";
+    let output = parse(script, &Conf::default(), false, None);
+    assert!(version_history(&output).is_empty());
+
+    // Metadata 中已有当前版本时不重复列出
+    let script = "Title: 剧本\nRevision: 2\nMetadata: {\"history\": [{\"version\": 2, \"notes\": \"定稿\"}]}\n\nINT. 客厅 - 日\n";
+    let history = version_history(&parse(script, &Conf::default(), false, None));
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].notes, "定稿");
+}